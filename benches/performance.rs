@@ -50,11 +50,14 @@ fn bench_window_operator(c: &mut Criterion) {
                 },
             },
         ],
+        ..Default::default()
     };
     let budget = MemoryBudgetImpl::new(4 * 1024 * 1024);
     c.bench_function("window_op", |b| {
         b.iter(|| {
-            let _ = window.eval_block(&[batch.clone()], &budget).unwrap();
+            let _ = window
+                .eval_block(std::slice::from_ref(&batch), &budget)
+                .unwrap();
         })
     });
 }
@@ -0,0 +1,396 @@
+//! `emsqrt backfill`: run a pipeline once per date of a range.
+//!
+//! Each date is passed as `--param <name>=YYYY-MM-DD`, so source templates,
+//! `${name}` variables and sink destinations (`out/{date}.csv`) pick it up.
+//! Runs go through an [`EnginePool`] capped at the backfill's memory cap,
+//! each planned for an equal share of it, so `--parallel` runs fit side by
+//! side. The state file is rewritten after every run; it keeps each date's
+//! manifest (or error) and lets `--resume` skip the dates already done.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_exec::resources;
+use emsqrt_exec::{EnginePool, ExecError, JobHandle, PoolConfig, PoolJob};
+use serde::{Deserialize, Serialize};
+
+use crate::logging::civil_from_days;
+use crate::{apply_pipeline_config, compile_pipeline, stats_store_dir};
+
+/// What `emsqrt backfill` was asked to do.
+pub struct BackfillOptions {
+    pub pipeline: PathBuf,
+    /// Parameter that receives each date.
+    pub param: String,
+    pub from: String,
+    pub to: String,
+    pub parallel: usize,
+    /// Cap shared by all runs; the configured cap when unset.
+    pub memory_cap: Option<usize>,
+    pub spill_dir: Option<String>,
+    /// State file; `<pipeline>.backfill.json` when unset.
+    pub state: Option<PathBuf>,
+    pub resume: bool,
+}
+
+/// Progress of a backfill, as kept in its state file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BackfillState {
+    pub pipeline: String,
+    pub param: String,
+    /// Outcome per date, by date.
+    pub runs: BTreeMap<String, DateRun>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DateRun {
+    pub status: RunStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<RunManifest>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Done,
+    Failed,
+}
+
+impl BackfillState {
+    fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .map_err(|e| format!("cannot read backfill state '{}': {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("cannot read '{}': {}", path.display(), e)),
+        }
+    }
+
+    /// Write through a temporary file, so an interrupted backfill never
+    /// leaves a truncated state behind.
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("cannot write '{}': {}", path.display(), e))
+    }
+
+    fn record(&mut self, date: &str, outcome: Result<RunManifest, String>) {
+        let run = match outcome {
+            Ok(manifest) => {
+                println!(
+                    "✓ {} ({}ms)",
+                    date,
+                    manifest.finished_ms - manifest.started_ms
+                );
+                DateRun {
+                    status: RunStatus::Done,
+                    error: None,
+                    manifest: Some(manifest),
+                }
+            }
+            Err(error) => {
+                println!("✗ {}: {}", date, error);
+                DateRun {
+                    status: RunStatus::Failed,
+                    error: Some(error),
+                    manifest: None,
+                }
+            }
+        };
+        self.runs.insert(date.to_string(), run);
+    }
+}
+
+/// Days since 1970-01-01 of a `YYYY-MM-DD` date.
+fn parse_date(date: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid date '{}' (expected YYYY-MM-DD)", date);
+    let parts: Vec<&str> = date.split('-').collect();
+    let [y, m, d] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    if y.len() != 4 || m.len() != 2 || d.len() != 2 {
+        return Err(invalid());
+    }
+    let (year, month, day): (i64, u32, u32) = (
+        y.parse().map_err(|_| invalid())?,
+        m.parse().map_err(|_| invalid())?,
+        d.parse().map_err(|_| invalid())?,
+    );
+    if !(1..=12).contains(&month) || day == 0 {
+        return Err(invalid());
+    }
+    // Inverse of `civil_from_days`.
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    // Day 31 of a 30-day month lands in the next month.
+    if civil_from_days(days) != (year, month, day) {
+        return Err(invalid());
+    }
+    Ok(days)
+}
+
+/// Every date from `from` to `to`, both included.
+pub fn date_range(from: &str, to: &str) -> Result<Vec<String>, String> {
+    let (start, end) = (parse_date(from)?, parse_date(to)?);
+    if end < start {
+        return Err(format!("--from {} is after --to {}", from, to));
+    }
+    Ok((start..=end)
+        .map(|days| {
+            let (y, m, d) = civil_from_days(days);
+            format!("{:04}-{:02}-{:02}", y, m, d)
+        })
+        .collect())
+}
+
+/// Run the backfill; `Ok(false)` when some date failed.
+pub fn run_backfill(opts: &BackfillOptions) -> Result<bool, Box<dyn std::error::Error>> {
+    let dates = date_range(&opts.from, &opts.to)?;
+    let state_path = opts.state.clone().unwrap_or_else(|| {
+        let mut path = opts.pipeline.clone().into_os_string();
+        path.push(".backfill.json");
+        PathBuf::from(path)
+    });
+    let pipeline = opts.pipeline.display().to_string();
+    let mut state = match BackfillState::load(&state_path)? {
+        Some(_) if !opts.resume => {
+            return Err(format!(
+                "'{}' holds an earlier backfill; pass --resume to continue it, or remove it",
+                state_path.display()
+            )
+            .into())
+        }
+        Some(state) if state.pipeline != pipeline || state.param != opts.param => {
+            return Err(format!(
+                "'{}' is a backfill of {} over --param {}",
+                state_path.display(),
+                state.pipeline,
+                state.param
+            )
+            .into())
+        }
+        Some(state) => state,
+        None => BackfillState {
+            pipeline,
+            param: opts.param.clone(),
+            runs: BTreeMap::new(),
+        },
+    };
+    let pending: Vec<&String> = dates
+        .iter()
+        .filter(|date| {
+            state
+                .runs
+                .get(*date)
+                .is_none_or(|run| run.status != RunStatus::Done)
+        })
+        .collect();
+    let skipped = dates.len() - pending.len();
+
+    let parallel = opts.parallel.max(1);
+    let mut base = resources::config_from_env();
+    let total_cap = opts.memory_cap.unwrap_or(base.mem_cap_bytes);
+    let job_cap = total_cap / parallel;
+    if let Some(dir) = &opts.spill_dir {
+        base.spill_dir = dir.clone();
+    }
+    base.stats_dir = stats_store_dir().map(|d| d.display().to_string());
+
+    // Plan every date first, so a sink shared by two dates stops the
+    // backfill before anything is written.
+    let mut jobs: Vec<(&String, PoolJob)> = Vec::new();
+    let mut writers: BTreeMap<String, &String> = BTreeMap::new();
+    for date in pending {
+        let params = [format!("{}={}", opts.param, date)];
+        let compiled = match compile_pipeline(&opts.pipeline, Some(job_cap), &params, None) {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                state.record(date, Err(e.to_string()));
+                continue;
+            }
+        };
+        for binding in compiled.program.bindings.values() {
+            let Some(dest) = binding.config.get("destination").and_then(|d| d.as_str()) else {
+                continue;
+            };
+            let other = *writers.entry(dest.to_string()).or_insert(date);
+            if other != date {
+                return Err(format!(
+                    "{} and {} both write '{}'; put {{{}}} in the sink destination",
+                    other, date, dest, opts.param
+                )
+                .into());
+            }
+        }
+        let mut config = base.clone();
+        apply_pipeline_config(&mut config, &compiled.config);
+        if opts.spill_dir.is_some() {
+            config.spill_dir = base.spill_dir.clone();
+        }
+        config.spill_dir = format!("{}/backfill-{}", config.spill_dir, date);
+        config.mem_cap_bytes = job_cap;
+        jobs.push((date, PoolJob::new(config, compiled.program, compiled.te)));
+    }
+    state.save(&state_path)?;
+
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: total_cap,
+        max_concurrent: parallel,
+    });
+    let finish = |state: &mut BackfillState, date: &str, handle: JobHandle| {
+        state.record(date, handle.join().map_err(|e: ExecError| e.to_string()));
+        state.save(&state_path)
+    };
+    let mut running: VecDeque<(&String, JobHandle)> = VecDeque::new();
+    for (date, job) in jobs {
+        if running.len() == parallel {
+            let (done, handle) = running.pop_front().unwrap();
+            finish(&mut state, done, handle)?;
+        }
+        match pool.submit(job) {
+            Ok(handle) => running.push_back((date, handle)),
+            Err(e) => {
+                state.record(date, Err(e.to_string()));
+                state.save(&state_path)?;
+            }
+        }
+    }
+    while let Some((date, handle)) = running.pop_front() {
+        finish(&mut state, date, handle)?;
+    }
+
+    let done: Vec<&DateRun> = dates
+        .iter()
+        .filter_map(|date| state.runs.get(date))
+        .filter(|run| run.status == RunStatus::Done)
+        .collect();
+    let failed = dates.len() - done.len();
+    let millis: u64 = done
+        .iter()
+        .filter_map(|run| run.manifest.as_ref())
+        .map(|m| m.finished_ms - m.started_ms)
+        .sum();
+    let cost: f64 = done
+        .iter()
+        .filter_map(|run| run.manifest.as_ref()?.cost.as_ref())
+        .map(|c| c.cost)
+        .sum();
+    println!(
+        "Backfill of {} dates: {} done ({} from an earlier run), {} failed",
+        dates.len(),
+        done.len(),
+        skipped,
+        failed
+    );
+    println!("  Run time: {}ms, cost: {:.4}", millis, cost);
+    println!("  State: {}", state_path.display());
+    Ok(failed == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_range_crosses_months_and_leap_days() {
+        let dates = date_range("2024-02-27", "2024-03-01").unwrap();
+        assert_eq!(
+            dates,
+            ["2024-02-27", "2024-02-28", "2024-02-29", "2024-03-01"]
+        );
+        assert_eq!(date_range("2023-12-31", "2024-01-01").unwrap().len(), 2);
+        assert_eq!(date_range("2024-01-01", "2024-03-31").unwrap().len(), 91);
+
+        assert!(date_range("2023-02-29", "2023-03-01").is_err());
+        assert!(date_range("2024-04-31", "2024-05-01").is_err());
+        assert!(date_range("2024-1-01", "2024-01-02").is_err());
+        let err = date_range("2024-01-02", "2024-01-01").unwrap_err();
+        assert!(err.contains("after"), "{}", err);
+    }
+
+    #[test]
+    fn backfill_records_each_date_and_resumes_the_failed_ones() {
+        // Keep the runs' statistics out of the user's stats store.
+        std::env::set_var("EMSQRT_STATS_DIR", "");
+        let dir = std::env::temp_dir().join(format!("emsqrt-backfill-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("out")).unwrap();
+        for day in ["01", "03"] {
+            let input = format!("id\n{}\n", day);
+            fs::write(dir.join(format!("2024-01-{}.csv", day)), input).unwrap();
+        }
+        let pipeline = dir.join("pipeline.yaml");
+        let write_pipeline = |destination: &str| {
+            let yaml = format!(
+                "steps:\n  - op: scan\n    source_template: \"{0}/{{day}}.csv\"\n    schema: [ {{ name: id, type: Int64 }} ]\n  - op: sink\n    destination: \"{0}/out/{1}\"\n    format: csv\n",
+                dir.display(),
+                destination
+            );
+            fs::write(&pipeline, yaml).unwrap();
+        };
+        write_pipeline("{day}.csv");
+        let mut opts = BackfillOptions {
+            pipeline: pipeline.clone(),
+            param: "day".into(),
+            from: "2024-01-01".into(),
+            to: "2024-01-03".into(),
+            parallel: 2,
+            memory_cap: Some(64 << 20),
+            spill_dir: Some(dir.join("spill").display().to_string()),
+            state: None,
+            resume: false,
+        };
+        let state_path = dir.join("pipeline.yaml.backfill.json");
+        let state = || -> BackfillState {
+            serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap()
+        };
+
+        // The 2nd has no input file.
+        assert!(!run_backfill(&opts).unwrap());
+        let runs = state().runs;
+        let status: Vec<RunStatus> = runs.values().map(|run| run.status).collect();
+        assert_eq!(
+            status,
+            [RunStatus::Done, RunStatus::Failed, RunStatus::Done]
+        );
+        assert!(runs["2024-01-01"].manifest.is_some());
+        let out = fs::read_to_string(dir.join("out/2024-01-03.csv")).unwrap();
+        assert_eq!(out, "id\n3\n");
+
+        // Starting over needs --resume, which runs only the failed date.
+        let err = run_backfill(&opts).unwrap_err();
+        assert!(err.to_string().contains("--resume"), "{}", err);
+        fs::write(dir.join("2024-01-02.csv"), "id\n2\n").unwrap();
+        fs::remove_file(dir.join("out/2024-01-01.csv")).unwrap();
+        opts.resume = true;
+        assert!(run_backfill(&opts).unwrap());
+        assert!(state()
+            .runs
+            .values()
+            .all(|run| run.status == RunStatus::Done));
+        assert!(dir.join("out/2024-01-02.csv").is_file());
+        assert!(!dir.join("out/2024-01-01.csv").exists());
+
+        // A sink every date would overwrite is refused before anything runs.
+        write_pipeline("all.csv");
+        opts.state = Some(dir.join("other.json"));
+        opts.resume = false;
+        let err = run_backfill(&opts).unwrap_err();
+        assert!(err.to_string().contains("{day}"), "{}", err);
+        assert!(!dir.join("out/all.csv").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+//! Log output for the CLI: a small `tracing` subscriber.
+//!
+//! The engine crates emit `tracing` events when built with their `tracing`
+//! feature; this module filters and writes them. A filter is a list of
+//! directives such as `warn,exec=debug,io=trace`: a bare level sets the
+//! default, and `target=level` sets the level for a module and everything
+//! below it. The crate short names `exec`, `io`, `mem`, ... stand for
+//! `emsqrt_exec`, `emsqrt_io`, `emsqrt_mem`; any other target is matched as
+//! written.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Filter used when neither `--log-level` nor `EMSQRT_LOG` is set.
+pub const DEFAULT_FILTER: &str = "warn";
+
+/// Engine crates that can be named without their `emsqrt_` prefix.
+const CRATES: &[&str] = &[
+    "core",
+    "te",
+    "mem",
+    "io",
+    "operators",
+    "planner",
+    "exec",
+    "cli",
+];
+
+/// How log lines are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// One human-readable line per event.
+    #[default]
+    Pretty,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Per-target level filter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// `(target prefix, level)`, most specific prefix first.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parse comma-separated directives, e.g. `info,exec=debug,mem=off`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = Self {
+            default: LevelFilter::ERROR,
+            targets: Vec::new(),
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                None => filter.default = parse_level(directive)?,
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if target.is_empty() {
+                        return Err(format!("log filter '{}' has no target", directive));
+                    }
+                    let target = if CRATES.contains(&target) {
+                        format!("emsqrt_{}", target)
+                    } else {
+                        target.replace('-', "_")
+                    };
+                    let level = parse_level(level.trim())?;
+                    filter.targets.retain(|(t, _)| *t != target);
+                    filter.targets.push((target, level));
+                }
+            }
+        }
+        filter
+            .targets
+            .sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Ok(filter)
+    }
+
+    /// The level in effect for `target`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn enabled(&self, target: &str, level: &Level) -> bool {
+        self.level_for(target) >= *level
+    }
+
+    /// The most verbose level any target gets.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, LevelFilter::max)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.parse().map_err(|_| {
+        format!(
+            "unknown log level '{}' (expected off, error, warn, info, debug or trace)",
+            level
+        )
+    })
+}
+
+/// Install the global subscriber. `level` falls back to `EMSQRT_LOG`, then
+/// to [`DEFAULT_FILTER`]; logs go to `file` (appended to) or stderr.
+pub fn init(level: Option<&str>, format: LogFormat, file: Option<&Path>) -> Result<(), String> {
+    let spec = match level {
+        Some(level) => level.to_string(),
+        None => std::env::var("EMSQRT_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string()),
+    };
+    let filter = LogFilter::parse(&spec)?;
+    let out: Box<dyn Write + Send> = match file {
+        Some(path) => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("cannot open log file '{}': {}", path.display(), e))?,
+        ),
+        None => Box::new(std::io::stderr()),
+    };
+    tracing::subscriber::set_global_default(Logger::new(filter, format, out))
+        .map_err(|e| e.to_string())
+}
+
+struct SpanData {
+    name: &'static str,
+    fields: Map<String, Value>,
+    refs: usize,
+}
+
+thread_local! {
+    /// Spans entered on this thread, innermost last.
+    static CURRENT: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Writes each enabled event as one line, prefixed by the spans it is in.
+pub struct Logger {
+    filter: LogFilter,
+    format: LogFormat,
+    out: Mutex<Box<dyn Write + Send>>,
+    next_id: AtomicU64,
+    spans: Mutex<HashMap<u64, SpanData>>,
+}
+
+impl Logger {
+    pub fn new(filter: LogFilter, format: LogFormat, out: Box<dyn Write + Send>) -> Self {
+        Self {
+            filter,
+            format,
+            out: Mutex::new(out),
+            next_id: AtomicU64::new(1),
+            spans: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn render(&self, meta: &Metadata<'_>, fields: FieldSet) -> String {
+        let spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let current: Vec<&SpanData> = CURRENT.with(|stack| {
+            stack
+                .borrow()
+                .iter()
+                .filter_map(|id| spans.get(id))
+                .collect()
+        });
+        let timestamp = format_timestamp(SystemTime::now());
+        match self.format {
+            LogFormat::Pretty => {
+                let mut line = format!("{} {:>5} ", timestamp, meta.level());
+                for span in &current {
+                    line.push_str(span.name);
+                    if !span.fields.is_empty() {
+                        line.push('{');
+                        line.push_str(&pretty_fields(&span.fields));
+                        line.push('}');
+                    }
+                    line.push(':');
+                }
+                line.push_str(meta.target());
+                line.push_str(": ");
+                line.push_str(&fields.message);
+                if !fields.values.is_empty() {
+                    line.push(' ');
+                    line.push_str(&pretty_fields(&fields.values));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut obj = Map::new();
+                obj.insert("timestamp".into(), timestamp.into());
+                obj.insert("level".into(), meta.level().as_str().into());
+                obj.insert("target".into(), meta.target().into());
+                obj.insert("message".into(), fields.message.into());
+                if !fields.values.is_empty() {
+                    obj.insert("fields".into(), fields.values.into());
+                }
+                if !current.is_empty() {
+                    let spans = current
+                        .iter()
+                        .map(|span| {
+                            let mut entry = span.fields.clone();
+                            entry.insert("name".into(), span.name.into());
+                            Value::Object(entry)
+                        })
+                        .collect();
+                    obj.insert("spans".into(), Value::Array(spans));
+                }
+                Value::Object(obj).to_string()
+            }
+        }
+    }
+}
+
+impl Subscriber for Logger {
+    fn register_callsite(&self, _meta: &'static Metadata<'static>) -> Interest {
+        // Ask `enabled` every time: tests install loggers with other filters.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, meta: &Metadata<'_>) -> bool {
+        self.filter.enabled(meta.target(), meta.level())
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.filter.max_level())
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = FieldSet::default();
+        attrs.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        spans.insert(
+            id,
+            SpanData {
+                name: attrs.metadata().name(),
+                fields: fields.values,
+                refs: 1,
+            },
+        );
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut fields = FieldSet::default();
+        values.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(data) = spans.get_mut(&span.into_u64()) {
+            data.fields.extend(fields.values);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = FieldSet::default();
+        event.record(&mut fields);
+        let line = self.render(event.metadata(), fields);
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Logging must never fail the run it describes.
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+
+    fn enter(&self, span: &Id) {
+        CURRENT.with(|stack| stack.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        CURRENT.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(pos) = stack.iter().rposition(|id| *id == span.into_u64()) {
+                stack.remove(pos);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(data) = spans.get_mut(&span.into_u64()) {
+            data.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().unwrap_or_else(|e| e.into_inner());
+        let id = span.into_u64();
+        let closed = spans.get_mut(&id).is_some_and(|data| {
+            data.refs -= 1;
+            data.refs == 0
+        });
+        if closed {
+            spans.remove(&id);
+        }
+        closed
+    }
+}
+
+/// An event's or span's fields; `message` is kept apart from the rest.
+#[derive(Default)]
+struct FieldSet {
+    message: String,
+    values: Map<String, Value>,
+}
+
+impl FieldSet {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(s) => s,
+                other => other.to_string(),
+            };
+        } else {
+            self.values.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldSet {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+fn pretty_fields(fields: &Map<String, Value>) -> String {
+    fields
+        .iter()
+        .map(|(k, v)| match v {
+            Value::String(s) => format!("{}={}", k, s),
+            other => format!("{}={}", k, other),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// RFC 3339 UTC time with milliseconds, e.g. `2024-03-01T12:00:00.250Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Proleptic Gregorian date of the day `days` after 1970-01-01.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn filter_picks_the_most_specific_target() {
+        let filter =
+            LogFilter::parse("info, exec=debug, emsqrt_exec::runtime=trace, mem=off").unwrap();
+        assert_eq!(filter.level_for("emsqrt_planner"), LevelFilter::INFO);
+        assert_eq!(filter.level_for("emsqrt_exec::metrics"), LevelFilter::DEBUG);
+        assert_eq!(filter.level_for("emsqrt_exec::runtime"), LevelFilter::TRACE);
+        // A prefix only matches whole path segments.
+        assert_eq!(filter.level_for("emsqrt_executor"), LevelFilter::INFO);
+        assert!(!filter.enabled("emsqrt_mem::tracking", &Level::ERROR));
+        assert_eq!(filter.max_level(), LevelFilter::TRACE);
+
+        assert!(LogFilter::parse("verbose").is_err());
+        assert!(LogFilter::parse("=debug").is_err());
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(spec: &str, format: LogFormat) -> Vec<String> {
+        let buffer = Buffer::default();
+        let logger = Logger::new(
+            LogFilter::parse(spec).unwrap(),
+            format,
+            Box::new(buffer.clone()),
+        );
+        tracing::subscriber::with_default(logger, || {
+            let span = tracing::info_span!(target: "emsqrt_exec::runtime", "block", id = 7);
+            let _entered = span.enter();
+            tracing::debug!(target: "emsqrt_exec::runtime", rows = 3u64, op = %"sort", "executed");
+            tracing::debug!(target: "emsqrt_io::buf", "hidden");
+            tracing::warn!(target: "emsqrt_io::buf", attempt = 1u64, "retrying read");
+        });
+        let bytes = buffer.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn pretty_lines_carry_spans_and_fields() {
+        let lines = capture("warn,exec=debug", LogFormat::Pretty);
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert!(
+            lines[0].ends_with("DEBUG block{id=7}:emsqrt_exec::runtime: executed op=sort rows=3"),
+            "{}",
+            lines[0]
+        );
+        assert!(
+            lines[1].ends_with(" WARN block{id=7}:emsqrt_io::buf: retrying read attempt=1"),
+            "{}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn json_lines_parse_as_objects() {
+        let lines = capture("exec=debug,io=warn", LogFormat::Json);
+        let events: Vec<Value> = lines
+            .iter()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["level"], "DEBUG");
+        assert_eq!(events[0]["target"], "emsqrt_exec::runtime");
+        assert_eq!(events[0]["message"], "executed");
+        assert_eq!(events[0]["fields"]["rows"], 3);
+        assert_eq!(events[0]["spans"][0]["name"], "block");
+        assert_eq!(events[0]["spans"][0]["id"], 7);
+        assert_eq!(events[1]["fields"]["attempt"], 1);
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_utc() {
+        let time = UNIX_EPOCH + Duration::from_millis(951_782_400_250);
+        assert_eq!(format_timestamp(time), "2000-02-29T00:00:00.250Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    }
+}
@@ -1,12 +1,30 @@
 //! EM-√ CLI: Command-line interface for running pipelines.
 
-use clap::{Parser, Subcommand};
-use emsqrt_core::config::EngineConfig;
-use emsqrt_exec::Engine;
-use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline, rules};
-use emsqrt_te::plan_te;
+mod backfill;
+mod logging;
+mod templates;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use emsqrt_core::config::{EngineConfig, SpillCodec};
+use emsqrt_core::manifest::OpTotals;
+use emsqrt_core::stats::SourceStats;
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::audit::{first_divergence, read_audit, AuditRun};
+use emsqrt_exec::resources;
+use emsqrt_exec::{
+    compare_outputs, profile_source, read_stats_file, resolve_lets, with_source_files,
+    CompareOptions, DiffKind, Engine, FollowOptions, Follower, LiveMetrics, MetricsServer,
+    OutputDiff, ProfileOptions, StatsStore,
+};
+use emsqrt_planner::{
+    attach_source_stats, column_lineage, estimate_work, let_stages, load_pipeline,
+    lower_to_physical, migrate_v1, parse_schema_spec, parse_template_params,
+    parse_yaml_pipeline_with_params, render_lineage, rules, CompiledPlan, ExplainGraph,
+    TemplateParams,
+};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "emsqrt")]
@@ -14,15 +32,42 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Log filter: a level, optionally with per-module levels, e.g.
+    /// `info,exec=debug,io=trace` (default: `EMSQRT_LOG`, else `warn`)
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    /// Log line format
+    #[arg(long, global = true, value_enum, default_value_t = logging::LogFormat::Pretty)]
+    log_format: logging::LogFormat,
+
+    /// Append logs to this file instead of stderr
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+}
+
+/// Output of `emsqrt explain`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExplainFormat {
+    Text,
+    Json,
+    Dot,
+    Html,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
-    /// Execute a pipeline from a YAML file
+    /// Execute a pipeline from a YAML file or a compiled plan
     Run {
         /// Path to the pipeline YAML file
-        #[arg(short, long)]
-        pipeline: PathBuf,
+        #[arg(short, long, required_unless_present = "plan", conflicts_with = "plan")]
+        pipeline: Option<PathBuf>,
+
+        /// Path to a plan written by `emsqrt compile`
+        #[arg(long)]
+        plan: Option<PathBuf>,
 
         /// Memory cap in bytes (overrides config)
         #[arg(long)]
@@ -64,6 +109,14 @@ enum Commands {
         #[arg(long)]
         spill_retry_max: Option<usize>,
 
+        /// Spill compression: none, lz4, zstd or zstd:LEVEL (overrides config)
+        #[arg(long, value_name = "CODEC", value_parser = parse_spill_codec)]
+        spill_codec: Option<SpillCodec>,
+
+        /// Most spill bytes held on disk at once (overrides config)
+        #[arg(long, value_name = "BYTES")]
+        spill_quota: Option<u64>,
+
         /// Override spill retry initial backoff (ms)
         #[arg(long)]
         spill_retry_initial_ms: Option<u64>,
@@ -75,6 +128,72 @@ enum Commands {
         /// Maximum parallel tasks (overrides config)
         #[arg(long)]
         max_parallel: Option<usize>,
+
+        /// Check declared scan constraints (keys, sort order) while reading
+        #[arg(long)]
+        verify: bool,
+
+        /// Keep running: process data appended to the sources in micro-batches
+        #[arg(long)]
+        follow: bool,
+
+        /// Milliseconds between polls for new data
+        #[arg(long, default_value = "1000", requires = "follow")]
+        poll_interval_ms: u64,
+
+        /// Stop after this many micro-batches
+        #[arg(long, requires = "follow")]
+        max_batches: Option<u64>,
+
+        /// Stop after this many milliseconds without new data
+        #[arg(long, requires = "follow")]
+        idle_timeout_ms: Option<u64>,
+
+        /// Template parameter for `source_template` scans (repeatable)
+        #[arg(long = "param", value_name = "KEY=VALUE", conflicts_with = "plan")]
+        params: Vec<String>,
+
+        /// Column statistics file: used for planning if present, then
+        /// updated with the statistics of the sources this run reads
+        #[arg(long, value_name = "PATH", conflicts_with = "follow")]
+        stats: Option<PathBuf>,
+
+        /// Append the row counts and hashes of every block's inputs and
+        /// output to this file (compare runs with `emsqrt compare-audits`)
+        #[arg(long, value_name = "PATH")]
+        audit: Option<PathBuf>,
+
+        /// Serve Prometheus metrics at http://ADDR/metrics while running
+        /// (needs the metrics-server feature)
+        #[arg(long, value_name = "ADDR")]
+        metrics_addr: Option<String>,
+
+        /// Write per-operator and per-block metrics to PATH (FORMAT: json)
+        #[arg(long, num_args = 2, value_names = ["FORMAT", "PATH"], conflicts_with = "follow")]
+        metrics: Option<Vec<String>>,
+    },
+
+    /// Compile a pipeline into a serialized physical + TE plan
+    Compile {
+        /// Path to the pipeline YAML file
+        #[arg(short, long)]
+        pipeline: PathBuf,
+
+        /// Where to write the compiled plan
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Memory cap in bytes to size TE blocks for (overrides config)
+        #[arg(long)]
+        memory_cap: Option<usize>,
+
+        /// Template parameter for `source_template` scans (repeatable)
+        #[arg(long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+
+        /// Column statistics file written by `emsqrt run --stats`
+        #[arg(long, value_name = "PATH")]
+        stats: Option<PathBuf>,
     },
 
     /// Validate a pipeline YAML file (syntax check)
@@ -82,6 +201,24 @@ enum Commands {
         /// Path to the pipeline YAML file
         #[arg(short, long)]
         pipeline: PathBuf,
+
+        /// Template parameter for `source_template` scans (repeatable)
+        #[arg(long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+
+        /// Also open each source and sample its rows against the declared
+        /// schema, and check that each sink destination is writable
+        #[arg(long)]
+        check_io: bool,
+
+        /// With --check-io, rows sampled from each source file
+        #[arg(
+            long,
+            value_name = "ROWS",
+            default_value_t = 100,
+            requires = "check_io"
+        )]
+        sample: usize,
     },
 
     /// Show execution plan for a pipeline (EXPLAIN)
@@ -93,15 +230,179 @@ enum Commands {
         /// Memory cap in bytes (for planning)
         #[arg(long, default_value = "536870912")] // 512MB default
         memory_cap: usize,
+
+        /// Run the pipeline (sinks included) and show actual rows, time, and spill
+        #[arg(long)]
+        analyze: bool,
+
+        /// With --analyze, read at most this many rows from each source
+        #[arg(long, value_name = "ROWS", requires = "analyze")]
+        sample: Option<usize>,
+
+        /// Template parameter for `source_template` scans (repeatable)
+        #[arg(long = "param", value_name = "KEY=VALUE")]
+        params: Vec<String>,
+
+        /// Print the plan as text, JSON, Graphviz DOT, or a standalone HTML page
+        #[arg(long, value_enum, default_value = "text", conflicts_with = "analyze")]
+        format: ExplainFormat,
+
+        /// Also show which source columns feed each sink column (text or JSON)
+        #[arg(long)]
+        lineage: bool,
+    },
+
+    /// Diff two sink outputs (CSV/JSONL/Parquet), ignoring row order
+    CompareOutputs {
+        /// Baseline output file
+        left: PathBuf,
+
+        /// Output file to compare against the baseline
+        right: PathBuf,
+
+        /// Key columns; rows with the same key and different values count as changed
+        #[arg(long, value_delimiter = ',')]
+        key: Vec<String>,
+
+        /// Memory cap in bytes
+        #[arg(long, default_value = "536870912")] // 512MB default
+        memory_cap: usize,
+
+        /// Number of differing rows to print
+        #[arg(long, default_value = "10")]
+        max_examples: usize,
+
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Profile a source's columns (types, nulls, distinct counts, min/max,
+    /// top values, histograms) in one bounded-memory pass
+    Profile {
+        /// File to profile (CSV, JSONL or Parquet)
+        #[arg(long)]
+        source: String,
+
+        /// Columns as `name:Type` pairs, e.g. `id:Int64,name:Utf8,score:Float64`
+        #[arg(long)]
+        schema: String,
+
+        /// Memory cap in bytes
+        #[arg(long, default_value = "536870912")] // 512MB default
+        memory_cap: usize,
+
+        /// Most frequent values to report per column
+        #[arg(long, default_value = "5")]
+        top: usize,
+
+        /// Histogram bins for numeric columns
+        #[arg(long, default_value = "10")]
+        bins: usize,
+
+        /// Values sampled per numeric column for its histogram
+        #[arg(long, default_value = "1024")]
+        sample: usize,
+
+        /// Write the JSON report here instead of stdout
+        #[arg(long, value_name = "FILE")]
+        json: Option<PathBuf>,
+
+        /// Also write an HTML report
+        #[arg(long, value_name = "FILE")]
+        html: Option<PathBuf>,
+    },
+
+    /// Find the first block where two `run --audit` logs differ (last run of each)
+    CompareAudits {
+        /// Audit file of the baseline run
+        left: PathBuf,
+
+        /// Audit file of the run to check against the baseline
+        right: PathBuf,
+    },
+
+    /// Write a starter pipeline, a schema file and a small fixture
+    Init {
+        /// Which starter pipeline to write
+        #[arg(long, value_enum)]
+        template: templates::Template,
+
+        /// Directory to write into (created if missing)
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Run a pipeline once per date of a range, resumably
+    Backfill {
+        /// Path to the pipeline YAML file
+        #[arg(short, long)]
+        pipeline: PathBuf,
+
+        /// Parameter set to each date (used as `{name}`/`${name}` in the pipeline)
+        #[arg(long = "param", value_name = "NAME")]
+        param: String,
+
+        /// First date, YYYY-MM-DD
+        #[arg(long)]
+        from: String,
+
+        /// Last date (included), YYYY-MM-DD
+        #[arg(long)]
+        to: String,
+
+        /// Dates running at once, sharing the memory cap
+        #[arg(long, default_value = "1")]
+        parallel: usize,
+
+        /// Memory cap in bytes for all runs together (overrides config)
+        #[arg(long)]
+        memory_cap: Option<usize>,
+
+        /// Spill directory (overrides config); each date spills in a subdirectory
+        #[arg(long)]
+        spill_dir: Option<String>,
+
+        /// Progress and manifests of the backfill [default: <pipeline>.backfill.json]
+        #[arg(long, value_name = "PATH")]
+        state: Option<PathBuf>,
+
+        /// Continue the backfill in the state file, skipping dates already done
+        #[arg(long)]
+        resume: bool,
+    },
+
+    /// Rewrite a version 1 (`steps:`) pipeline as version 2 (`stages:`)
+    Migrate {
+        /// Path to the pipeline YAML file
+        #[arg(short, long)]
+        pipeline: PathBuf,
+
+        /// Write the migrated pipeline here instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
 }
 
 fn main() {
     let cli = Cli::parse();
+    if let Err(e) = logging::init(
+        cli.log_level.as_deref(),
+        cli.log_format,
+        cli.log_file.as_deref(),
+    ) {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    }
 
     match cli.command {
         Commands::Run {
             pipeline,
+            plan,
             memory_cap,
             spill_dir,
             spill_uri,
@@ -112,12 +413,38 @@ fn main() {
             spill_gcs_service_account,
             spill_azure_access_key,
             spill_retry_max,
+            spill_codec,
+            spill_quota,
             spill_retry_initial_ms,
             spill_retry_max_ms,
             max_parallel,
+            verify,
+            follow,
+            poll_interval_ms,
+            max_batches,
+            idle_timeout_ms,
+            params,
+            stats,
+            audit,
+            metrics_addr,
+            metrics,
         } => {
+            let metrics_path = match metrics.as_deref() {
+                Some([format, path]) if format == "json" => Some(PathBuf::from(path)),
+                Some([format, _]) => {
+                    eprintln!("Error: unknown metrics format '{}' (expected json)", format);
+                    std::process::exit(2);
+                }
+                _ => None,
+            };
+            let follow = follow.then(|| FollowOptions {
+                poll_interval: Duration::from_millis(poll_interval_ms),
+                max_batches,
+                idle_timeout: idle_timeout_ms.map(Duration::from_millis),
+            });
             if let Err(e) = run_pipeline(
-                &pipeline,
+                pipeline.as_ref(),
+                plan.as_ref(),
                 memory_cap,
                 spill_dir,
                 spill_uri,
@@ -128,16 +455,42 @@ fn main() {
                 spill_gcs_service_account,
                 spill_azure_access_key,
                 spill_retry_max,
+                spill_codec,
+                spill_quota,
                 spill_retry_initial_ms,
                 spill_retry_max_ms,
                 max_parallel,
+                verify,
+                follow,
+                &params,
+                stats.as_ref(),
+                audit.as_ref(),
+                metrics_addr,
+                metrics_path.as_ref(),
             ) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
-        Commands::Validate { pipeline } => {
-            if let Err(e) = validate_pipeline(&pipeline) {
+        Commands::Compile {
+            pipeline,
+            out,
+            memory_cap,
+            params,
+            stats,
+        } => {
+            if let Err(e) = compile_to_file(&pipeline, &out, memory_cap, &params, stats.as_ref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Validate {
+            pipeline,
+            params,
+            check_io,
+            sample,
+        } => {
+            if let Err(e) = validate_pipeline(&pipeline, &params, check_io.then_some(sample)) {
                 eprintln!("Validation failed: {}", e);
                 std::process::exit(1);
             }
@@ -146,8 +499,127 @@ fn main() {
         Commands::Explain {
             pipeline,
             memory_cap,
+            analyze,
+            sample,
+            params,
+            format,
+            lineage,
         } => {
-            if let Err(e) = explain_pipeline(&pipeline, memory_cap) {
+            let analyze = analyze.then_some(sample);
+            if let Err(e) =
+                explain_pipeline(&pipeline, memory_cap, analyze, &params, format, lineage)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::CompareOutputs {
+            left,
+            right,
+            key,
+            memory_cap,
+            max_examples,
+            json,
+        } => {
+            let opts = CompareOptions {
+                key,
+                max_examples,
+                ..Default::default()
+            };
+            match compare_files(&left, &right, &opts, memory_cap, json) {
+                Ok(true) => {}
+                // Like diff(1): exit status 1 when the outputs differ.
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Commands::Profile {
+            source,
+            schema,
+            memory_cap,
+            top,
+            bins,
+            sample,
+            json,
+            html,
+        } => {
+            let opts = ProfileOptions { top, bins, sample };
+            if let Err(e) = profile(
+                &source,
+                &schema,
+                &opts,
+                memory_cap,
+                json.as_ref(),
+                html.as_ref(),
+            ) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::CompareAudits { left, right } => match compare_audits(&left, &right) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        Commands::Init {
+            template,
+            dir,
+            force,
+        } => match templates::write_template(template, &dir, force) {
+            Ok(paths) => {
+                for path in &paths {
+                    println!("✓ {}", path.display());
+                }
+                println!(
+                    "Paths in the pipeline are relative to {}; run it from there:",
+                    dir.display()
+                );
+                println!("  emsqrt run --pipeline pipeline.yaml");
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Commands::Backfill {
+            pipeline,
+            param,
+            from,
+            to,
+            parallel,
+            memory_cap,
+            spill_dir,
+            state,
+            resume,
+        } => {
+            let opts = backfill::BackfillOptions {
+                pipeline,
+                param,
+                from,
+                to,
+                parallel,
+                memory_cap,
+                spill_dir,
+                state,
+                resume,
+            };
+            match backfill::run_backfill(&opts) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Commands::Migrate { pipeline, output } => {
+            if let Err(e) = migrate_pipeline(&pipeline, output.as_ref()) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
@@ -155,8 +627,30 @@ fn main() {
     }
 }
 
+/// Print `pipeline` as a version 2 document, or write it to `output`.
+/// Comments are not carried over.
+fn migrate_pipeline(
+    pipeline: &PathBuf,
+    output: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let yaml = fs::read_to_string(pipeline)?;
+    let migrated = migrate_v1(&yaml)?;
+    // The result must still plan the same way; a failure here is a bug.
+    load_pipeline(&migrated)?;
+    match output {
+        Some(path) => {
+            fs::write(path, &migrated)?;
+            println!("✓ Wrote version 2 pipeline to {}", path.display());
+        }
+        None => print!("{}", migrated),
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_pipeline(
-    pipeline_path: &PathBuf,
+    pipeline_path: Option<&PathBuf>,
+    plan_path: Option<&PathBuf>,
     memory_cap: Option<usize>,
     spill_dir: Option<String>,
     spill_uri: Option<String>,
@@ -167,32 +661,45 @@ fn run_pipeline(
     spill_gcs_service_account: Option<String>,
     spill_azure_access_key: Option<String>,
     spill_retry_max: Option<usize>,
+    spill_codec: Option<SpillCodec>,
+    spill_quota: Option<u64>,
     spill_retry_initial_ms: Option<u64>,
     spill_retry_max_ms: Option<u64>,
     max_parallel: Option<usize>,
+    verify: bool,
+    follow: Option<FollowOptions>,
+    params: &[String],
+    stats_path: Option<&PathBuf>,
+    audit_path: Option<&PathBuf>,
+    metrics_addr: Option<String>,
+    metrics_path: Option<&PathBuf>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Read YAML file
-    let yaml_content = fs::read_to_string(pipeline_path)?;
-
-    // Parse pipeline
-    let parsed = parse_yaml_pipeline(&yaml_content)?;
-    let logical_plan = parsed.plan.clone();
-
-    // Optimize
-    let optimized = rules::optimize(logical_plan);
-
-    // Lower to physical plan
-    let phys_prog = lower_to_physical(&optimized);
-
-    // Estimate work
-    let work = estimate_work(&optimized, None);
+    // Load a precompiled plan, or parse + plan the YAML now
+    let compiled = match (plan_path, pipeline_path) {
+        (Some(plan_path), _) => {
+            let compiled = CompiledPlan::from_bytes(&fs::read(plan_path)?)?;
+            if let Some(cap) = memory_cap {
+                if cap < compiled.mem_cap_bytes {
+                    return Err(format!(
+                        "plan was compiled for a {} byte memory cap; recompile with --memory-cap {}",
+                        compiled.mem_cap_bytes, cap
+                    )
+                    .into());
+                }
+            }
+            compiled
+        }
+        (None, Some(pipeline_path)) => {
+            let stats = stats_path.map(|p| load_stats(p)).transpose()?;
+            compile_pipeline(pipeline_path, memory_cap, params, stats.as_ref())?
+        }
+        (None, None) => return Err("either --pipeline or --plan is required".into()),
+    };
 
     // Create config
-    let mut config = EngineConfig::from_env();
-    apply_pipeline_config(&mut config, &parsed.config);
-    if let Some(cap) = memory_cap {
-        config.mem_cap_bytes = cap;
-    }
+    let mut config = resources::config_from_env();
+    apply_pipeline_config(&mut config, &compiled.config);
+    config.mem_cap_bytes = memory_cap.unwrap_or(compiled.mem_cap_bytes);
     if let Some(dir) = spill_dir {
         config.spill_dir = dir;
     }
@@ -220,6 +727,12 @@ fn run_pipeline(
     if let Some(max) = spill_retry_max {
         config.spill_retry_max_retries = max;
     }
+    if let Some(codec) = spill_codec {
+        config.spill_codec = codec;
+    }
+    if let Some(quota) = spill_quota {
+        config.spill_quota_bytes = Some(quota);
+    }
     if let Some(initial) = spill_retry_initial_ms {
         config.spill_retry_initial_backoff_ms = initial;
     }
@@ -229,14 +742,48 @@ fn run_pipeline(
     if let Some(parallel) = max_parallel {
         config.max_parallel_tasks = parallel;
     }
-    // Plan TE execution
-    let te = plan_te(&phys_prog.plan, &work, config.mem_cap_bytes)
-        .map_err(|e| format!("TE planning failed: {}", e))?;
+    if verify {
+        config.verify_constraints = true;
+    }
+    if let Some(path) = stats_path {
+        config.stats_file = Some(path.display().to_string());
+    }
+    if let Some(path) = audit_path {
+        config.audit_path = Some(path.display().to_string());
+    }
+    if let Some(addr) = metrics_addr {
+        config.metrics_addr = Some(addr);
+    }
+    if follow.is_none() {
+        config.stats_dir = stats_store_dir().map(|d| d.display().to_string());
+    }
+    let metrics_addr = config.metrics_addr.clone();
+
+    if let Some(opts) = follow {
+        // One manifest per micro-batch, as JSON lines on stdout.
+        let mut follower = Follower::new(compiled.program, compiled.te, config)?;
+        let _server = serve_metrics(metrics_addr.as_deref(), follower.live_metrics())?;
+        let batches = follower.run(&opts, |batch| {
+            match serde_json::to_string(&batch.manifest) {
+                Ok(line) => println!("{}", line),
+                Err(e) => eprintln!("warning: failed to encode manifest: {}", e),
+            }
+        })?;
+        eprintln!("✓ Follow stopped after {} micro-batches", batches);
+        return Ok(());
+    }
 
     // Execute
     let mut engine =
         Engine::new(config).map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
-    let manifest = engine.run(&phys_prog, &te)?;
+    let _server = serve_metrics(metrics_addr.as_deref(), engine.live_metrics())?;
+    let manifest = engine.run(&compiled.program, &compiled.te)?;
+    if let (Some(path), None) = (stats_path, &manifest.stats_file) {
+        // The engine leaves a warning naming the failed write.
+        let path = path.display().to_string();
+        let warning = manifest.warnings.iter().find(|w| w.contains(&path));
+        return Err(warning.cloned().unwrap_or(path).into());
+    }
 
     println!("✓ Pipeline executed successfully");
     println!(
@@ -244,29 +791,298 @@ fn run_pipeline(
         manifest.finished_ms - manifest.started_ms
     );
     println!("  Plan hash: {}", manifest.plan_hash);
+    if let Some(cost) = &manifest.cost {
+        println!(
+            "  Cost: {:.4} (cpu {:.3}s, spilled {:.4}GB, scanned {:.4}GB)",
+            cost.cost, cost.cpu_seconds, cost.gb_spilled, cost.gb_scanned
+        );
+    }
+    if let Some(spill) = manifest.spill.filter(|s| s.bytes_compressed > 0) {
+        println!(
+            "  Spilled: {} ({} before {} compression)",
+            format_bytes(spill.bytes_compressed),
+            format_bytes(spill.bytes_uncompressed),
+            spill.codec
+        );
+        println!("  Spill peak: {}", format_bytes(spill.peak_bytes));
+    }
+    println!();
+    for line in metrics_table(&manifest.operators) {
+        println!("  {}", line);
+    }
+    if let Some(path) = metrics_path {
+        fs::write(path, serde_json::to_string_pretty(&manifest.metrics())?)?;
+        println!("  Metrics: {}", path.display());
+    }
 
     Ok(())
 }
 
-fn validate_pipeline(pipeline_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+fn parse_spill_codec(s: &str) -> Result<SpillCodec, String> {
+    SpillCodec::parse(s).ok_or_else(|| {
+        format!(
+            "unknown spill codec '{}' (expected none, lz4, zstd or zstd:LEVEL with LEVEL 1-22)",
+            s
+        )
+    })
+}
+
+/// Start the Prometheus endpoint at `addr`, if one is configured; it
+/// serves until the returned handle is dropped.
+fn serve_metrics(
+    addr: Option<&str>,
+    metrics: LiveMetrics,
+) -> Result<Option<MetricsServer>, Box<dyn std::error::Error>> {
+    let Some(addr) = addr else {
+        return Ok(None);
+    };
+    let server = emsqrt_exec::metrics::serve(addr, metrics)?;
+    eprintln!("Serving metrics at http://{}/metrics", addr);
+    Ok(Some(server))
+}
+
+/// Per-operator metrics as an aligned table, one row per operator.
+fn metrics_table(operators: &[OpTotals]) -> Vec<String> {
+    let header = [
+        "op", "blocks", "rows in", "rows out", "bytes in", "peak mem", "spill w", "spill r",
+        "time ms",
+    ];
+    let mut rows: Vec<Vec<String>> = vec![header.iter().map(|h| h.to_string()).collect()];
+    for t in operators {
+        rows.push(vec![
+            format!("{} {}", t.op_id, t.op),
+            t.blocks.to_string(),
+            t.rows_in.to_string(),
+            t.rows_out.to_string(),
+            format_bytes(t.bytes_in),
+            format_bytes(t.peak_memory_bytes),
+            format_bytes(t.bytes_spilled),
+            format_bytes(t.bytes_spill_read),
+            format!("{:.1}", t.wall_nanos as f64 / 1e6),
+        ]);
+    }
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|r| r[i].len()).max().unwrap_or(0))
+        .collect();
+    rows.iter()
+        .map(|row| {
+            // The operator column is left-aligned, numbers right-aligned.
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (cell, &w))| match i {
+                    0 => format!("{:<w$}", cell),
+                    _ => format!("{:>w$}", cell),
+                })
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+/// `bytes` in B, KiB, MiB or GiB.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Parse, optimize, lower, and TE-plan a pipeline YAML file.
+///
+/// The TE plan is sized for `memory_cap`, falling back to the env/pipeline config.
+fn compile_pipeline(
+    pipeline_path: &PathBuf,
+    memory_cap: Option<usize>,
+    params: &[String],
+    stats: Option<&SourceStats>,
+) -> Result<CompiledPlan, Box<dyn std::error::Error>> {
     let yaml_content = fs::read_to_string(pipeline_path)?;
-    let _ = parse_yaml_pipeline(&yaml_content)?;
+
+    // Parse pipeline (expanding source templates and let variables)
+    let params = pipeline_params(&yaml_content, params, memory_cap)?;
+    let parsed = parse_yaml_pipeline_with_params(&yaml_content, &params)?;
+    let mut logical_plan = parsed.plan.clone();
+
+    // Stats from earlier runs inform row counts and selectivities
+    if let Some(stats) = stats {
+        attach_source_stats(&mut logical_plan, stats);
+    }
+    // ...as do stats saved by earlier runs over the same file contents
+    if let Some(dir) = stats_store_dir() {
+        StatsStore::open(dir).warm_start(&mut logical_plan);
+    }
+
+    // Optimize
+    let optimized = rules::optimize(logical_plan);
+
+    // Lower to physical plan, then apply join strategy/parallelism hints
+    let mut phys_prog = lower_to_physical(&optimized);
+    parsed.hints.apply(&mut phys_prog);
+
+    // Estimate work (source size hints, and the files of glob and directory
+    // sources, correct the defaults)
+    let hint = with_source_files(&optimized, parsed.hints.work_hint());
+    let work = estimate_work(&optimized, hint.as_ref());
+
+    let mut config = resources::config_from_env();
+    apply_pipeline_config(&mut config, &parsed.config);
+    let mem_cap = memory_cap.unwrap_or(config.mem_cap_bytes);
+
+    // Plan TE execution
+    let te = parsed
+        .hints
+        .plan_te(&phys_prog, &work, mem_cap)
+        .map_err(|e| format!("TE planning failed: {}", e))?;
+
+    Ok(CompiledPlan::new(phys_prog, te, parsed.config, mem_cap))
+}
+
+/// Warm-start stats store: `EMSQRT_STATS_DIR`, else the default config
+/// directory. Setting `EMSQRT_STATS_DIR` to an empty string turns it off.
+fn stats_store_dir() -> Option<PathBuf> {
+    match std::env::var("EMSQRT_STATS_DIR") {
+        Ok(dir) if dir.is_empty() => None,
+        Ok(dir) => Some(PathBuf::from(dir)),
+        Err(_) => StatsStore::default_dir(),
+    }
+}
+
+/// Read a `--stats` file; a missing file is empty (the first run creates it).
+fn load_stats(path: &Path) -> Result<SourceStats, Box<dyn std::error::Error>> {
+    Ok(read_stats_file(path)?)
+}
+
+fn compile_to_file(
+    pipeline_path: &PathBuf,
+    out: &PathBuf,
+    memory_cap: Option<usize>,
+    params: &[String],
+    stats_path: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stats = stats_path.map(|p| load_stats(p)).transpose()?;
+    let compiled = compile_pipeline(pipeline_path, memory_cap, params, stats.as_ref())?;
+    fs::write(out, compiled.to_bytes()?)?;
+
+    println!("✓ Compiled plan written to {}", out.display());
+    println!("  Blocks: {}", compiled.te.order.len());
+    println!("  Memory cap: {} bytes", compiled.mem_cap_bytes);
     Ok(())
 }
 
+/// Check the pipeline parses; with `check_io = Some(sample)`, also check its
+/// sources and sinks, printing every issue found.
+fn validate_pipeline(
+    pipeline_path: &PathBuf,
+    params: &[String],
+    check_io: Option<usize>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let yaml_content = fs::read_to_string(pipeline_path)?;
+    let mut params = parse_template_params(params)?;
+    // Validation reads no data: check each let's sub-pipeline, then let a
+    // placeholder stand in for its value.
+    for stage in let_stages(&yaml_content)? {
+        stage.plan(&params)?;
+        params.entry(stage.name).or_insert_with(|| "null".into());
+    }
+    let parsed = parse_yaml_pipeline_with_params(&yaml_content, &params)?;
+    let Some(sample) = check_io else {
+        return Ok(());
+    };
+
+    let optimized = rules::optimize(parsed.plan.clone());
+    let mut phys_prog = lower_to_physical(&optimized);
+    parsed.hints.apply(&mut phys_prog);
+    let mut config = resources::config_from_env();
+    apply_pipeline_config(&mut config, &parsed.config);
+    let issues = emsqrt_exec::check_io(&phys_prog, &config, sample);
+    if issues.is_empty() {
+        println!("✓ Sources and sinks are usable");
+        return Ok(());
+    }
+    for issue in &issues {
+        eprintln!("  ✗ {}", issue);
+    }
+    Err(format!("{} I/O issue(s)", issues.len()).into())
+}
+
+/// `--param` values plus the values of the pipeline's `let` steps, which run
+/// under the pipeline's engine config.
+fn pipeline_params(
+    yaml_content: &str,
+    params: &[String],
+    memory_cap: Option<usize>,
+) -> Result<TemplateParams, Box<dyn std::error::Error>> {
+    let params = parse_template_params(params)?;
+    if let_stages(yaml_content)?.is_empty() {
+        return Ok(params);
+    }
+    let doc = load_pipeline(yaml_content)?;
+    let mut config = resources::config_from_env();
+    apply_pipeline_config(&mut config, &doc.config.unwrap_or_default());
+    if let Some(cap) = memory_cap {
+        config.mem_cap_bytes = cap;
+    }
+    Ok(resolve_lets(yaml_content, &params, &config)?)
+}
+
+/// Print the plan; with `analyze = Some(sample)`, also run it and show actuals.
 fn explain_pipeline(
     pipeline_path: &PathBuf,
     memory_cap: usize,
+    analyze: Option<Option<usize>>,
+    params: &[String],
+    format: ExplainFormat,
+    lineage: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if lineage && matches!(format, ExplainFormat::Dot | ExplainFormat::Html) {
+        return Err("--lineage is shown with --format text or json".into());
+    }
     let yaml_content = fs::read_to_string(pipeline_path)?;
-    let parsed = parse_yaml_pipeline(&yaml_content)?;
+    let params = pipeline_params(&yaml_content, params, Some(memory_cap))?;
+    let parsed = parse_yaml_pipeline_with_params(&yaml_content, &params)?;
     let logical_plan = parsed.plan.clone();
     let optimized = rules::optimize(logical_plan);
-    let phys_prog = lower_to_physical(&optimized);
-    let work = estimate_work(&optimized, None);
-    let te = plan_te(&phys_prog.plan, &work, memory_cap)
+    let mut phys_prog = lower_to_physical(&optimized);
+    parsed.hints.apply(&mut phys_prog);
+    let hint = with_source_files(&optimized, parsed.hints.work_hint());
+    let work = estimate_work(&optimized, hint.as_ref());
+    let te = parsed
+        .hints
+        .plan_te(&phys_prog, &work, memory_cap)
         .map_err(|e| format!("TE planning failed: {}", e))?;
 
+    let graph = || ExplainGraph::new(&phys_prog, &work, &te, memory_cap);
+    match format {
+        ExplainFormat::Text => {}
+        ExplainFormat::Json => {
+            let graph = graph().with_logical_plan(&optimized);
+            let graph = if lineage {
+                graph.with_lineage(&optimized)
+            } else {
+                graph
+            };
+            println!("{}", graph.to_json());
+            return Ok(());
+        }
+        ExplainFormat::Dot => {
+            print!("{}", graph().to_dot());
+            return Ok(());
+        }
+        ExplainFormat::Html => {
+            print!("{}", graph().to_html());
+            return Ok(());
+        }
+    }
+
     println!("Pipeline Execution Plan");
     println!("======================");
     println!();
@@ -305,10 +1121,210 @@ fn explain_pipeline(
             block.deps.len()
         );
     }
+    println!();
+    println!("Stage Schemas:");
+    for line in graph().render_schemas().lines() {
+        println!("  {}", line);
+    }
+    if lineage {
+        println!();
+        println!("Column Lineage:");
+        for line in render_lineage(&column_lineage(&optimized)).lines() {
+            println!("  {}", line);
+        }
+    }
+
+    let Some(sample) = analyze else {
+        return Ok(());
+    };
+    if let Some(rows) = sample {
+        for binding in phys_prog.bindings.values_mut() {
+            if binding.key == "source" {
+                if let Some(obj) = binding.config.as_object_mut() {
+                    obj.insert("limit_rows".to_string(), rows.into());
+                }
+            }
+        }
+    }
+
+    let mut config = resources::config_from_env();
+    apply_pipeline_config(&mut config, &parsed.config);
+    config.mem_cap_bytes = memory_cap;
+    let mut engine =
+        Engine::new(config).map_err(|e| -> Box<dyn std::error::Error> { Box::new(e) })?;
+    let manifest = engine.run(&phys_prog, &te)?;
+
+    println!();
+    match sample {
+        Some(rows) => println!("Analyze (sampled, at most {} rows per source):", rows),
+        None => println!("Analyze:"),
+    }
+    for line in analyze_lines(&te, &manifest) {
+        println!("  {}", line);
+    }
+    println!("  Total: {}ms", manifest.finished_ms - manifest.started_ms);
 
     Ok(())
 }
 
+/// Diff two outputs and print the report; returns whether they are identical.
+fn compare_files(
+    left: &Path,
+    right: &Path,
+    opts: &CompareOptions,
+    memory_cap: usize,
+    json: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut config = resources::config_from_env();
+    config.mem_cap_bytes = memory_cap;
+    let diff = compare_outputs(
+        &left.to_string_lossy(),
+        &right.to_string_lossy(),
+        opts,
+        &config,
+    )?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        for line in diff_lines(&diff) {
+            println!("{}", line);
+        }
+    }
+    Ok(diff.is_identical())
+}
+
+/// Profile `source` and write the JSON report (to stdout without `--json`)
+/// and, with `--html`, the HTML page.
+fn profile(
+    source: &str,
+    schema: &str,
+    opts: &ProfileOptions,
+    memory_cap: usize,
+    json: Option<&PathBuf>,
+    html: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let schema = parse_schema_spec(schema).map_err(|e| format!("--schema: {}", e))?;
+    let mut config = resources::config_from_env();
+    config.mem_cap_bytes = memory_cap;
+    let profile = profile_source(source, &schema, opts, &config)?;
+    match json {
+        Some(path) => {
+            fs::write(path, profile.to_json())?;
+            eprintln!("✓ JSON report written to {}", path.display());
+        }
+        None => println!("{}", profile.to_json()),
+    }
+    if let Some(path) = html {
+        fs::write(path, profile.to_html())?;
+        eprintln!("✓ HTML report written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Compare the last run of two audit files; returns whether every block matches.
+fn compare_audits(left: &Path, right: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let last_run = |path: &Path| -> Result<AuditRun, Box<dyn std::error::Error>> {
+        read_audit(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .pop()
+            .ok_or_else(|| format!("{}: no runs recorded", path.display()).into())
+    };
+    let (left, right) = (last_run(left)?, last_run(right)?);
+    if left.plan_hash != right.plan_hash {
+        println!("Note: the runs used different plans");
+    } else if left.te_hash != right.te_hash {
+        println!("Note: the runs used different TE plans");
+    }
+    println!(
+        "Blocks: {} left, {} right",
+        left.blocks.len(),
+        right.blocks.len()
+    );
+    match first_divergence(&left, &right) {
+        Some(d) => {
+            println!("First divergence: {}", d);
+            Ok(false)
+        }
+        None => {
+            println!("✓ Audits match");
+            Ok(true)
+        }
+    }
+}
+
+fn diff_lines(diff: &OutputDiff) -> Vec<String> {
+    let mut lines = vec![
+        format!("Rows: {} left, {} right", diff.left_rows, diff.right_rows),
+        format!(
+            "Added: {}  Removed: {}  Changed: {}",
+            diff.added, diff.removed, diff.changed
+        ),
+    ];
+    for change in &diff.schema_changes {
+        lines.push(format!("Schema: {}", change));
+    }
+    if !diff.examples.is_empty() {
+        lines.push(format!("Columns: {}", diff.columns.join(", ")));
+    }
+    let render = |row: &Option<Vec<Scalar>>| {
+        row.iter()
+            .flatten()
+            .map(|v| match v {
+                Scalar::Null => "null".to_string(),
+                Scalar::Bool(b) => b.to_string(),
+                Scalar::I32(i) => i.to_string(),
+                Scalar::I64(i) => i.to_string(),
+                Scalar::F32(f) => f.to_string(),
+                Scalar::F64(f) => f.to_string(),
+                Scalar::Str(s) => format!("{:?}", s),
+                Scalar::Bin(b) => format!("[binary {} bytes]", b.len()),
+                Scalar::Date64(_) | Scalar::Timestamp(_) | Scalar::Decimal128(..) => v.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    for ex in &diff.examples {
+        lines.push(match ex.kind {
+            DiffKind::Added => format!("  + {}", render(&ex.right)),
+            DiffKind::Removed => format!("  - {}", render(&ex.left)),
+            DiffKind::Changed => format!("  ~ {} => {}", render(&ex.left), render(&ex.right)),
+        });
+    }
+    if diff.is_identical() {
+        lines.push("✓ Outputs match".to_string());
+    }
+    lines
+}
+
+/// One line per operator: estimated rows (from TE block ranges) next to actuals.
+fn analyze_lines(
+    te: &emsqrt_te::TePlan,
+    manifest: &emsqrt_core::manifest::RunManifest,
+) -> Vec<String> {
+    manifest
+        .op_totals()
+        .iter()
+        .map(|t| {
+            let est_rows: u64 = te
+                .order
+                .iter()
+                .filter(|b| b.op.get() == t.op_id)
+                .filter_map(|b| b.range_rows.map(|(start, end)| end.saturating_sub(start)))
+                .sum();
+            format!(
+                "Op {} ({}): est rows {}, actual rows {}, blocks {}, time {:.3}ms, spilled {} bytes",
+                t.op_id,
+                t.op,
+                est_rows,
+                t.rows_out,
+                t.blocks,
+                t.wall_nanos as f64 / 1e6,
+                t.bytes_spilled
+            )
+        })
+        .collect()
+}
+
 fn apply_pipeline_config(cfg: &mut EngineConfig, doc: &emsqrt_planner::PipelineConfig) {
     if let Some(dir) = &doc.spill_dir {
         cfg.spill_dir = dir.clone();
@@ -334,13 +1350,95 @@ fn apply_pipeline_config(cfg: &mut EngineConfig, doc: &emsqrt_planner::PipelineC
     if let Some(azure_key) = &doc.spill_azure_access_key {
         cfg.spill_azure_access_key = Some(azure_key.clone());
     }
+    for (key, chain) in &doc.fallbacks {
+        cfg.fallbacks.insert(key.clone(), chain.clone());
+    }
+    if let Some(source_batch) = &doc.source_batch {
+        cfg.source_batch = source_batch.clone();
+    }
+    if let Some(guard) = &doc.join_guard {
+        cfg.join_guard = Some(guard.clone());
+    }
+    if let Some(binary) = doc.binary_encoding {
+        cfg.binary_encoding = binary;
+    }
+    if let Some(parquet) = &doc.parquet {
+        cfg.parquet = parquet.clone();
+    }
+    if let Some(csv) = &doc.csv {
+        cfg.csv = csv.clone();
+    }
+    if let Some(policy) = doc.schedule_policy {
+        cfg.schedule_policy = policy;
+    }
+    if let Some(policy) = doc.arithmetic_errors {
+        cfg.arithmetic_errors = policy;
+    }
+    if let Some(read_retry) = &doc.read_retry {
+        cfg.read_retry = read_retry.clone();
+    }
+    if let Some(codec) = doc.spill_codec {
+        cfg.spill_codec = codec;
+    }
+    if let Some(quota) = doc.spill_quota_bytes {
+        cfg.spill_quota_bytes = Some(quota);
+    }
+    if let Some(cleanup) = doc.spill_cleanup {
+        cfg.spill_cleanup = cleanup;
+    }
+    if let Some(check) = doc.spill_space_check {
+        cfg.spill_space_check = check;
+    }
+    if let Some(deterministic) = doc.deterministic_output {
+        cfg.deterministic_output = deterministic;
+    }
+    if let Some(addr) = &doc.metrics_addr {
+        cfg.metrics_addr = Some(addr.clone());
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{apply_pipeline_config, EngineConfig};
+    use super::{apply_pipeline_config, format_bytes, metrics_table, EngineConfig, OpTotals};
     use emsqrt_planner::PipelineConfig;
 
+    #[test]
+    fn metrics_table_aligns_operator_rows() {
+        let operators = [
+            OpTotals {
+                op_id: 0,
+                op: "source".into(),
+                blocks: 2,
+                rows_out: 1500,
+                bytes_in: 0,
+                wall_nanos: 2_500_000,
+                ..Default::default()
+            },
+            OpTotals {
+                op_id: 1,
+                op: "sort".into(),
+                blocks: 1,
+                rows_in: 1500,
+                rows_out: 1500,
+                bytes_in: 3 << 20,
+                peak_memory_bytes: 1536,
+                bytes_spilled: 10,
+                ..Default::default()
+            },
+        ];
+        let lines = metrics_table(&operators);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("op        blocks"), "{}", lines[0]);
+        assert!(lines[1].starts_with("0 source"));
+        assert!(lines[2].contains("3.0 MiB"), "{}", lines[2]);
+        assert!(lines[2].contains("1.5 KiB"), "{}", lines[2]);
+        let widths: Vec<usize> = lines.iter().map(|l| l.len()).collect();
+        assert!(widths.iter().all(|w| *w == widths[0]), "{:?}", lines);
+
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(5 << 30), "5.0 GiB");
+    }
+
     #[test]
     fn pipeline_config_overrides_env_defaults() {
         let mut config = EngineConfig::default();
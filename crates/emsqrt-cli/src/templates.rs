@@ -0,0 +1,156 @@
+//! Starter projects written by `emsqrt init`.
+//!
+//! Each template is a commented `pipeline.yaml`, each scan's columns as a
+//! `name:Type` schema file (the form `emsqrt profile --schema` takes) and a
+//! small fixture per scan, all with paths relative to the project directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+/// Template for `emsqrt init --template`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Template {
+    /// Scan a CSV file, filter and project it, write Parquet
+    CsvToParquet,
+    /// Keep one row per key with a row_number window
+    Dedup,
+    /// Join two tables in a version 2 pipeline and add computed columns
+    JoinEnrich,
+}
+
+impl Template {
+    /// The template's files as `(relative path, contents)`.
+    pub fn files(self) -> &'static [(&'static str, &'static str)] {
+        macro_rules! files {
+            ($dir:literal: $($path:literal),+) => {
+                &[$(($path, include_str!(concat!("../templates/", $dir, "/", $path)))),+]
+            };
+        }
+        match self {
+            Template::CsvToParquet => files!("csv-to-parquet":
+                "pipeline.yaml", "schemas/events.schema", "data/events.csv"),
+            Template::Dedup => files!("dedup":
+                "pipeline.yaml", "schemas/customers.schema", "data/customers.csv"),
+            Template::JoinEnrich => files!("join-enrich":
+                "pipeline.yaml", "schemas/orders.schema", "data/orders.csv",
+                "schemas/customers.schema", "data/customers.csv"),
+        }
+    }
+}
+
+/// Write `template` into `dir`, creating directories as needed, plus the
+/// empty `output/` its sink writes to (sinks do not create directories).
+/// Existing files are left alone (and reported as an error) unless `force`
+/// is set.
+pub fn write_template(template: Template, dir: &Path, force: bool) -> Result<Vec<PathBuf>, String> {
+    let paths: Vec<PathBuf> = template
+        .files()
+        .iter()
+        .map(|(path, _)| dir.join(path))
+        .collect();
+    if !force {
+        let existing: Vec<String> = paths
+            .iter()
+            .filter(|p| p.exists())
+            .map(|p| p.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(format!(
+                "{} already exist(s); pass --force to overwrite",
+                existing.join(", ")
+            ));
+        }
+    }
+    for (path, (_, contents)) in paths.iter().zip(template.files()) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("cannot create '{}': {}", parent.display(), e))?;
+        }
+        fs::write(path, contents)
+            .map_err(|e| format!("cannot write '{}': {}", path.display(), e))?;
+    }
+    let output = dir.join("output");
+    fs::create_dir_all(&output)
+        .map_err(|e| format!("cannot create '{}': {}", output.display(), e))?;
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use emsqrt_planner::dsl::yaml::Step;
+    use emsqrt_planner::{load_pipeline, parse_schema_spec, parse_yaml_pipeline, StageOp};
+
+    fn file(template: Template, name: &str) -> &'static str {
+        template
+            .files()
+            .iter()
+            .find(|(path, _)| *path == name)
+            .map(|(_, contents)| *contents)
+            .unwrap()
+    }
+
+    #[test]
+    fn templates_parse_and_fixtures_match_their_schemas() {
+        for template in Template::value_variants() {
+            let yaml = file(*template, "pipeline.yaml");
+            parse_yaml_pipeline(yaml).unwrap_or_else(|e| panic!("{:?}: {}", template, e));
+
+            let mut scanned: Vec<Vec<&str>> = Vec::new();
+            let doc = load_pipeline(yaml).unwrap();
+            for stage in &doc.stages {
+                let StageOp::Step(Step::Scan {
+                    source: Some(source),
+                    schema,
+                    ..
+                }) = &stage.op
+                else {
+                    continue;
+                };
+                let declared: Vec<&str> = schema.iter().map(|f| f.name.as_str()).collect();
+                let header = file(*template, source).lines().next().unwrap();
+                assert_eq!(header.split(',').collect::<Vec<_>>(), declared);
+                scanned.push(declared);
+            }
+            assert!(!scanned.is_empty(), "{:?} has no scan", template);
+
+            // Each schema file lists the columns of one of the scans.
+            for (path, contents) in template.files() {
+                if !path.ends_with(".schema") {
+                    continue;
+                }
+                let spec = parse_schema_spec(contents.trim()).unwrap();
+                let names: Vec<&str> = spec.fields.iter().map(|f| f.name.as_str()).collect();
+                assert!(scanned.contains(&names), "{:?}: {}", template, path);
+            }
+        }
+    }
+
+    #[test]
+    fn init_refuses_to_overwrite_without_force() {
+        let dir = std::env::temp_dir().join(format!("emsqrt-init-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let written = write_template(Template::Dedup, &dir, false).unwrap();
+        assert_eq!(written.len(), 3);
+        assert!(dir.join("data/customers.csv").is_file());
+        assert!(dir.join("output").is_dir());
+
+        fs::write(dir.join("pipeline.yaml"), "edited").unwrap();
+        let err = write_template(Template::Dedup, &dir, false).unwrap_err();
+        assert!(err.contains("pipeline.yaml"), "{}", err);
+        assert_eq!(
+            fs::read_to_string(dir.join("pipeline.yaml")).unwrap(),
+            "edited"
+        );
+
+        write_template(Template::Dedup, &dir, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("pipeline.yaml")).unwrap(),
+            file(Template::Dedup, "pipeline.yaml")
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
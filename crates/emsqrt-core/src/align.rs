@@ -0,0 +1,120 @@
+//! Schema alignment for inputs with different column sets ("ragged" unions).
+//!
+//! Unions and multi-file scans combine inputs that may not share every
+//! column. `unify_schemas` builds the combined schema and `align_batch`
+//! reshapes one input's rows to it, under an `AlignPolicy` chosen per scan.
+
+use serde::{Deserialize, Serialize};
+
+use crate::schema::{Field, Schema};
+use crate::types::{Column, RowBatch, Scalar};
+
+/// How an input whose columns differ from the target schema is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignPolicy {
+    /// Inputs must have every target column; a missing one is an error.
+    #[default]
+    Exact,
+    /// Missing columns are filled with nulls; extra columns are an error.
+    FillMissing,
+    /// Missing columns are filled with nulls; extra columns are dropped.
+    FillAndDrop,
+}
+
+impl AlignPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == AlignPolicy::Exact
+    }
+
+    /// Whether missing columns become nulls rather than errors.
+    pub fn fills_missing(&self) -> bool {
+        !self.is_default()
+    }
+
+    /// Check an input's columns against `target` without reshaping anything.
+    pub fn check<'a>(
+        &self,
+        columns: impl IntoIterator<Item = &'a str>,
+        target: &[String],
+    ) -> Result<(), String> {
+        let columns: Vec<&str> = columns.into_iter().collect();
+        if !self.fills_missing() {
+            if let Some(missing) = target.iter().find(|t| !columns.contains(&t.as_str())) {
+                return Err(format!(
+                    "missing column '{}' (have {:?}); use align: fill_missing to fill it with nulls",
+                    missing, columns
+                ));
+            }
+        }
+        if *self == AlignPolicy::FillMissing {
+            if let Some(extra) = columns.iter().find(|c| !target.iter().any(|t| t == *c)) {
+                return Err(format!(
+                    "unexpected column '{}' (expected {:?}); use align: fill_and_drop to drop it",
+                    extra, target
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The schema covering every input: fields in first-seen order, nullable
+/// wherever some input lacks them or declares them nullable. A column whose
+/// type differs between inputs is an error.
+pub fn unify_schemas(schemas: &[Schema]) -> Result<Schema, String> {
+    let mut fields: Vec<Field> = Vec::new();
+    for schema in schemas {
+        for field in &schema.fields {
+            match fields.iter_mut().find(|f| f.name == field.name) {
+                Some(seen) if seen.data_type != field.data_type => {
+                    return Err(format!(
+                        "column '{}' is {:?} in one input and {:?} in another",
+                        field.name, seen.data_type, field.data_type
+                    ))
+                }
+                Some(seen) => seen.nullable |= field.nullable,
+                None => fields.push(field.clone()),
+            }
+        }
+    }
+    for field in &mut fields {
+        if schemas.iter().any(|s| s.index_of(&field.name).is_none()) {
+            field.nullable = true;
+        }
+    }
+    Ok(Schema::new(fields))
+}
+
+/// Reshape `batch` to the columns `target`, in that order, under `policy`.
+pub fn align_columns(
+    batch: &RowBatch,
+    target: &[String],
+    policy: AlignPolicy,
+) -> Result<RowBatch, String> {
+    policy.check(batch.columns.iter().map(|c| c.name.as_str()), target)?;
+    let rows = batch.num_rows();
+    let columns = target
+        .iter()
+        .map(
+            |name| match batch.columns.iter().find(|c| c.name == *name) {
+                Some(col) => col.clone(),
+                None => Column {
+                    name: name.clone(),
+                    values: vec![Scalar::Null; rows],
+                },
+            },
+        )
+        .collect();
+    Ok(RowBatch { columns })
+}
+
+/// [`align_columns`] to the fields of `target`.
+pub fn align_batch(
+    batch: &RowBatch,
+    target: &Schema,
+    policy: AlignPolicy,
+) -> Result<RowBatch, String> {
+    let names: Vec<String> = target.fields.iter().map(|f| f.name.clone()).collect();
+    align_columns(batch, &names, policy)
+}
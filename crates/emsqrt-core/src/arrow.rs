@@ -7,15 +7,18 @@
 compile_error!("arrow module requires 'arrow' feature to be enabled");
 
 use arrow_array::builder::{
-    BinaryBuilder, BooleanBuilder, Float32Builder, Float64Builder, Int32Builder, Int64Builder,
-    StringBuilder,
+    BinaryBuilder, BooleanBuilder, Date64Builder, Decimal128Builder, Float32Builder,
+    Float64Builder, Int32Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder,
 };
 use arrow_array::{
-    Array, ArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array,
-    RecordBatch, StringArray,
+    Array, ArrayRef, BinaryArray, BooleanArray, Date64Array, Decimal128Array, Float32Array,
+    Float64Array, Int32Array, Int64Array, RecordBatch, StringArray, TimestampMicrosecondArray,
+};
+use arrow_schema::{
+    DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema, TimeUnit,
 };
-use arrow_schema::{DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema};
 
+use crate::decimal;
 use crate::schema::{DataType, Field, Schema};
 use crate::types::{Column, RowBatch, Scalar};
 
@@ -40,6 +43,7 @@ pub fn data_type_to_arrow(dt: &DataType) -> ArrowDataType {
         DataType::Utf8 => ArrowDataType::Utf8,
         DataType::Binary => ArrowDataType::Binary,
         DataType::Date64 => ArrowDataType::Date64,
+        DataType::Timestamp => ArrowDataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
         DataType::Decimal128 => ArrowDataType::Decimal128(38, 10), // Default precision/scale
     }
 }
@@ -56,8 +60,8 @@ pub fn row_batch_to_arrow(batch: &RowBatch) -> Result<RecordBatch, String> {
     let num_rows = batch.num_rows();
     if num_rows == 0 {
         // Return empty batch with schema
-        let schema = Schema {
-            fields: batch
+        let schema = Schema::new(
+            batch
                 .columns
                 .iter()
                 .map(|c| Field {
@@ -66,7 +70,7 @@ pub fn row_batch_to_arrow(batch: &RowBatch) -> Result<RecordBatch, String> {
                     nullable: true,
                 })
                 .collect(),
-        };
+        );
         let arrow_schema = schema_to_arrow(&schema);
         return Ok(RecordBatch::new_empty(Arc::new(arrow_schema)));
     }
@@ -123,7 +127,7 @@ fn batch_to_schema(batch: &RowBatch) -> Schema {
             }
         })
         .collect();
-    Schema { fields }
+    Schema::new(fields)
 }
 
 /// Convert a `Column` to an Arrow `ArrayRef`.
@@ -134,7 +138,19 @@ fn column_to_arrow_array(column: &Column, num_rows: usize) -> Result<ArrayRef, S
 
     // Determine type from first non-null value
     let data_type = column.values[0].data_type();
-    let arrow_dt = data_type_to_arrow(&data_type);
+    let mut arrow_dt = data_type_to_arrow(&data_type);
+    // Decimals keep the widest scale in the column.
+    if let ArrowDataType::Decimal128(_, scale) = &mut arrow_dt {
+        *scale = column
+            .values
+            .iter()
+            .filter_map(|v| match v {
+                Scalar::Decimal128(_, s) => Some(*s as i8),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+    }
 
     match arrow_dt {
         ArrowDataType::Boolean => {
@@ -214,6 +230,56 @@ fn column_to_arrow_array(column: &Column, num_rows: usize) -> Result<ArrayRef, S
             }
             Ok(Arc::new(builder.finish()))
         }
+        ArrowDataType::Date64 => {
+            let mut builder = Date64Builder::with_capacity(num_rows);
+            for scalar in &column.values {
+                match scalar {
+                    Scalar::Null => builder.append_null(),
+                    Scalar::Date64(v) => builder.append_value(*v),
+                    _ => return Err(format!("Type mismatch: expected Date64, got {:?}", scalar)),
+                }
+            }
+            Ok(Arc::new(builder.finish()))
+        }
+        ArrowDataType::Timestamp(_, tz) => {
+            let mut builder = TimestampMicrosecondBuilder::with_capacity(num_rows);
+            for scalar in &column.values {
+                match scalar {
+                    Scalar::Null => builder.append_null(),
+                    Scalar::Timestamp(v) => builder.append_value(*v),
+                    _ => {
+                        return Err(format!(
+                            "Type mismatch: expected Timestamp, got {:?}",
+                            scalar
+                        ))
+                    }
+                }
+            }
+            Ok(Arc::new(builder.finish().with_timezone_opt(tz)))
+        }
+        ArrowDataType::Decimal128(precision, scale) => {
+            let mut builder = Decimal128Builder::with_capacity(num_rows);
+            for scalar in &column.values {
+                match scalar {
+                    Scalar::Null => builder.append_null(),
+                    Scalar::Decimal128(v, s) => builder.append_value(
+                        decimal::rescale(*v, *s, scale as u8)
+                            .ok_or_else(|| format!("decimal {} out of range", scalar))?,
+                    ),
+                    _ => {
+                        return Err(format!(
+                            "Type mismatch: expected Decimal128, got {:?}",
+                            scalar
+                        ))
+                    }
+                }
+            }
+            builder
+                .finish()
+                .with_precision_and_scale(precision, scale)
+                .map(|a| Arc::new(a) as ArrayRef)
+                .map_err(|e| e.to_string())
+        }
         _ => Err(format!("Unsupported Arrow data type: {:?}", arrow_dt)),
     }
 }
@@ -293,6 +359,39 @@ fn arrow_array_to_column(array: &ArrayRef, name: &str, num_rows: usize) -> Resul
                 }
             }
         }
+        ArrowDataType::Date64 => {
+            let arr = array.as_any().downcast_ref::<Date64Array>().unwrap();
+            for i in 0..num_rows {
+                if arr.is_null(i) {
+                    values.push(Scalar::Null);
+                } else {
+                    values.push(Scalar::Date64(arr.value(i)));
+                }
+            }
+        }
+        ArrowDataType::Timestamp(TimeUnit::Microsecond, _) => {
+            let arr = array
+                .as_any()
+                .downcast_ref::<TimestampMicrosecondArray>()
+                .unwrap();
+            for i in 0..num_rows {
+                if arr.is_null(i) {
+                    values.push(Scalar::Null);
+                } else {
+                    values.push(Scalar::Timestamp(arr.value(i)));
+                }
+            }
+        }
+        ArrowDataType::Decimal128(_, scale) if *scale >= 0 => {
+            let arr = array.as_any().downcast_ref::<Decimal128Array>().unwrap();
+            for i in 0..num_rows {
+                if arr.is_null(i) {
+                    values.push(Scalar::Null);
+                } else {
+                    values.push(Scalar::Decimal128(arr.value(i), *scale as u8));
+                }
+            }
+        }
         _ => {
             return Err(format!(
                 "Unsupported Arrow data type: {:?}",
@@ -0,0 +1,59 @@
+//! Text encodings of binary values for text formats (CSV, JSONL).
+//!
+//! Binary columns have no native form in text files, so readers and sinks
+//! encode them with the engine's `binary_encoding`. Parquet, Arrow, Avro and
+//! spill files store the bytes as they are.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+/// How binary values are written to and read from text formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryEncoding {
+    /// Standard base64 with padding: compact.
+    #[default]
+    Base64,
+    /// Lowercase hex: twice the size, but encoded values sort like their bytes.
+    Hex,
+}
+
+impl BinaryEncoding {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "base64" => Some(Self::Base64),
+            "hex" => Some(Self::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base64 => STANDARD.encode(bytes),
+            Self::Hex => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+
+    /// Decode text written by `encode`; hex also accepts uppercase digits.
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Base64 => STANDARD
+                .decode(text)
+                .map_err(|e| format!("invalid base64: {}", e)),
+            Self::Hex => {
+                if !text.len().is_multiple_of(2) {
+                    return Err("invalid hex: odd number of digits".to_string());
+                }
+                (0..text.len())
+                    .step_by(2)
+                    .map(|i| {
+                        text.get(i..i + 2)
+                            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                            .ok_or_else(|| format!("invalid hex digits at offset {}", i))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
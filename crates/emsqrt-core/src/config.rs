@@ -1,7 +1,13 @@
 //! Engine configuration that downstream crates can serialize/deserialize.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::binary::BinaryEncoding;
+use crate::expr::ArithErrorPolicy;
+use crate::hash::{PartitionHashKind, PartitionHasher};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EngineConfig {
     /// Hard memory cap (in bytes). The engine and operators must *never* exceed this.
@@ -16,10 +22,15 @@ pub struct EngineConfig {
     /// Optional seed for deterministic shuffles/partitioning.
     pub seed: Option<u64>,
 
+    /// Hash function used for join/aggregate partitioning (seeded by `seed`).
+    #[serde(default)]
+    pub partition_hash: PartitionHashKind,
+
     /// Execution parallelism. The scheduler must respect this when launching tasks.
     pub max_parallel_tasks: usize,
 
-    /// Directory for spill files (legacy local-path configuration).
+    /// Directory for spill files. A URI such as `s3://bucket/prefix` selects
+    /// that storage backend when `spill_uri` is not set.
     pub spill_dir: String,
 
     /// Optional fully-qualified spill URI (e.g., `s3://bucket/prefix`).
@@ -37,6 +48,608 @@ pub struct EngineConfig {
     pub spill_retry_max_retries: usize,
     pub spill_retry_initial_backoff_ms: u64,
     pub spill_retry_max_backoff_ms: u64,
+
+    /// Restrictions for running untrusted pipelines (None = unrestricted).
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+
+    /// Fallback chains keyed by operator key (e.g. `join_hash`), tried in order
+    /// when a block keeps failing with a recoverable error after retries.
+    #[serde(default)]
+    pub fallbacks: BTreeMap<String, Vec<FallbackAction>>,
+
+    /// How many rows sources read per block, adapted to budget pressure.
+    #[serde(default)]
+    pub source_batch: SourceBatchConfig,
+
+    /// Check declared schema constraints (keys, sort order) on source data
+    /// and fail the run on a violation.
+    #[serde(default)]
+    pub verify_constraints: bool,
+
+    /// Compute column statistics (min/max/null counts) of every source's
+    /// rows and record them in the run manifest (`source_stats`).
+    #[serde(default)]
+    pub collect_stats: bool,
+
+    /// Directory of the warm-start stats store. When set, source stats are
+    /// collected and saved there after each run (see `emsqrt_exec::StatsStore`).
+    #[serde(default)]
+    pub stats_dir: Option<String>,
+
+    /// Stats file (JSON `SourceStats`) that each run updates with the stats
+    /// of the sources it read, keeping other sources' entries. It is named
+    /// in the run manifest (`stats_file`) and read by `--stats` compiles.
+    #[serde(default)]
+    pub stats_file: Option<String>,
+
+    /// Append the row counts and content hashes of every block's inputs
+    /// and output to this file (see `emsqrt_exec::audit`).
+    #[serde(default)]
+    pub audit_path: Option<String>,
+
+    /// Address (`host:port`) to serve Prometheus metrics on at `/metrics`
+    /// while pipelines run; needs the `metrics-server` feature of
+    /// `emsqrt-exec`.
+    #[serde(default)]
+    pub metrics_addr: Option<String>,
+
+    /// Check the estimated output of each hash join block before running
+    /// it (None = no check).
+    #[serde(default)]
+    pub join_guard: Option<JoinGuardConfig>,
+
+    /// How long a memory reservation that does not fit waits for other
+    /// blocks to release bytes before failing (None = fail at once).
+    #[serde(default)]
+    pub memory_wait_ms: Option<u64>,
+
+    /// Text encoding of binary values in CSV and JSONL sources and sinks.
+    #[serde(default)]
+    pub binary_encoding: BinaryEncoding,
+
+    /// Compression, encodings and statistics of Parquet sink files.
+    #[serde(default)]
+    pub parquet: ParquetSinkConfig,
+
+    /// Delimiter, quoting and compression of CSV sink files.
+    #[serde(default)]
+    pub csv: CsvSinkConfig,
+
+    /// Which ready block runs next.
+    #[serde(default)]
+    pub schedule_policy: SchedulePolicy,
+
+    /// What a division by zero in an expression yields: an error that fails
+    /// the run, null, or a default value.
+    #[serde(default)]
+    pub arithmetic_errors: ArithErrorPolicy,
+
+    /// Retries of failed reads of source files (network filesystems).
+    #[serde(default)]
+    pub read_retry: ReadRetryConfig,
+
+    /// Compression of spill segments. `zstd` and `lz4` need the matching
+    /// feature of `emsqrt-mem`.
+    #[serde(default)]
+    pub spill_codec: SpillCodec,
+
+    /// Most bytes of spill segments held in storage at once (None =
+    /// unlimited). A write that would go over fails the run.
+    ///
+    /// The sandbox's `max_spill_bytes` also caps spill writes, but counts
+    /// every byte a run writes, deleted segments included. A write must pass
+    /// both; `max_spill_bytes` is checked first. It starts over with each
+    /// run while segments an earlier run left behind still count toward the
+    /// quota, so a quota at or above it can still trip.
+    #[serde(default)]
+    pub spill_quota_bytes: Option<u64>,
+
+    /// Which leftover spill segments the end-of-run sweep deletes.
+    #[serde(default)]
+    pub spill_cleanup: SpillCleanup,
+
+    /// Before a run, compare its worst-case spill volume with the space
+    /// free in `spill_dir` and fail at once if it may not fit (see
+    /// `emsqrt_exec::spill_space`). Not checked for a `spill_uri`.
+    #[serde(default = "default_true")]
+    pub spill_space_check: bool,
+
+    /// Emit aggregate groups sorted by group key and Grace hash join rows
+    /// in probe order, so identical runs write identical files. Off, both
+    /// come out in hash partition order, saving the final sort.
+    #[serde(default = "default_true")]
+    pub deterministic_output: bool,
+
+    /// Settings defaulted from the container's resource limits rather than
+    /// configured (see `emsqrt_exec::resources`).
+    #[serde(default)]
+    pub detected_resources: Vec<DetectedLimit>,
+}
+
+/// A setting defaulted from a detected resource limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DetectedLimit {
+    /// `mem_cap_bytes` or `max_parallel_tasks`.
+    pub setting: String,
+    /// Value the setting was given.
+    pub value: u64,
+    /// The limit it was derived from (bytes or CPUs).
+    pub limit: u64,
+    /// File the limit was read from, e.g. `/sys/fs/cgroup/memory.max`.
+    pub source: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Order in which the engine runs blocks whose inputs are ready. Every
+/// policy keeps each operator's own blocks in TE order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulePolicy {
+    /// TE order.
+    #[default]
+    Fifo,
+    /// The block with the longest chain of blocks after it first, so the
+    /// chain that bounds the run's length starts as early as possible.
+    CriticalPath,
+    /// The block whose run releases the most bytes of held intermediate
+    /// results first, keeping the frontier small.
+    MemoryAware,
+}
+
+impl SchedulePolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "fifo" => Some(Self::Fifo),
+            "critical_path" => Some(Self::CriticalPath),
+            "memory_aware" => Some(Self::MemoryAware),
+            _ => None,
+        }
+    }
+}
+
+/// What the sweep at the end of every run deletes, besides the segments
+/// the run wrote (those are always deleted).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpillCleanup {
+    /// Segments of this engine whose delete failed earlier.
+    #[default]
+    Run,
+    /// Also every other segment file under the spill root, e.g. left by a
+    /// crashed process. Only safe when no other engine shares the root.
+    Sweep,
+}
+
+impl SpillCleanup {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "run" => Some(Self::Run),
+            "sweep" => Some(Self::Sweep),
+            _ => None,
+        }
+    }
+}
+
+/// Compression of spill segments, written `none`, `lz4`, `zstd` or
+/// `zstd:level`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpillCodec {
+    #[default]
+    None,
+    /// Zstandard at `level` (1-22).
+    Zstd {
+        level: i32,
+    },
+    Lz4,
+}
+
+impl SpillCodec {
+    /// Zstandard level when none is given.
+    pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => (name, Some(level.trim().parse::<i32>().ok()?)),
+            None => (s.as_str(), None),
+        };
+        match (name.trim(), level) {
+            ("none", None) => Some(Self::None),
+            ("lz4", None) => Some(Self::Lz4),
+            ("zstd", None) => Some(Self::Zstd {
+                level: Self::DEFAULT_ZSTD_LEVEL,
+            }),
+            ("zstd", Some(level)) if (1..=22).contains(&level) => Some(Self::Zstd { level }),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SpillCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Zstd { level } => write!(f, "zstd:{}", level),
+            Self::Lz4 => f.write_str("lz4"),
+        }
+    }
+}
+
+impl Serialize for SpillCodec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpillCodec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        SpillCodec::parse(&s).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(&s),
+                &"\"none\", \"lz4\", \"zstd\" or \"zstd:level\" (1-22)",
+            )
+        })
+    }
+}
+
+/// Adaptive source read sizing.
+///
+/// Before each read a source checks budget utilization (`used / cap`): above
+/// `shrink_above` it halves its read size, below `grow_below` it doubles it,
+/// and in between it keeps the current size. The gap between the two
+/// watermarks is the hysteresis band that keeps sizes from flapping. Only the
+/// size of each read changes: a source still returns every row of its input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceBatchConfig {
+    pub initial_rows: usize,
+    pub min_rows: usize,
+    pub max_rows: usize,
+    pub shrink_above: f64,
+    pub grow_below: f64,
+}
+
+impl Default for SourceBatchConfig {
+    fn default() -> Self {
+        Self {
+            initial_rows: 10_000,
+            min_rows: 1_000,
+            max_rows: 100_000,
+            shrink_above: 0.8,
+            grow_below: 0.5,
+        }
+    }
+}
+
+/// Retries of source file reads that fail mid-stream, as reads on NFS or
+/// FUSE mounts do. A failed read reopens the file, seeks back to the byte
+/// offset reached so far and tries again, waiting `initial_backoff_ms`
+/// before the first retry and doubling the wait up to `max_backoff_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadRetryConfig {
+    /// Retries of one read before it fails (0 = fail at once).
+    pub max_retries: usize,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for ReadRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 2_000,
+        }
+    }
+}
+
+/// Writer settings of CSV sinks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvSinkConfig {
+    /// Field separator, a single ASCII character.
+    pub delimiter: char,
+    pub quoting: CsvQuoting,
+    /// Compression of the file; unset gzips destinations ending in `.gz`.
+    pub compression: Option<CsvCompression>,
+    /// gzip level (0-9); 6 if unset.
+    pub compression_level: Option<u32>,
+}
+
+impl Default for CsvSinkConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quoting: CsvQuoting::default(),
+            compression: None,
+            compression_level: None,
+        }
+    }
+}
+
+impl CsvSinkConfig {
+    /// Whether a file written to `path` is gzipped.
+    pub fn gzip(&self, path: &str) -> bool {
+        match self.compression {
+            Some(c) => c == CsvCompression::Gzip,
+            None => path.ends_with(".gz"),
+        }
+    }
+}
+
+/// Which CSV fields are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvQuoting {
+    /// Fields holding the delimiter, a quote or a line break.
+    #[default]
+    Necessary,
+    Always,
+    /// Every field that is not a number.
+    NonNumeric,
+    /// None; fields are written as they are, even if that breaks the row.
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvCompression {
+    None,
+    Gzip,
+}
+
+/// Writer settings of Parquet sinks. File-wide settings apply to every
+/// column without an entry in `columns`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetSinkConfig {
+    pub compression: ParquetCodec,
+    /// Codec level for `gzip` (0-9) and `zstd` (1-22); codec default if unset.
+    pub compression_level: Option<u32>,
+    /// Dictionary-encode columns (on unless disabled).
+    pub dictionary: Option<bool>,
+    pub statistics: ParquetStatistics,
+    /// Rows per row group. Sinks default it to their block size, but at
+    /// least 64Ki rows.
+    pub row_group_rows: Option<usize>,
+    /// Bytes a row group may buffer before it is written. Sinks default it
+    /// to an eighth of the memory cap, at most 128 MiB.
+    pub row_group_bytes: Option<usize>,
+    /// Per-column overrides, keyed by output column name.
+    pub columns: BTreeMap<String, ParquetColumnConfig>,
+}
+
+impl ParquetSinkConfig {
+    /// Check that every `columns` entry names one of `columns`.
+    pub fn check_columns<'a>(
+        &self,
+        columns: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let columns: Vec<&str> = columns.into_iter().collect();
+        match self.columns.keys().find(|c| !columns.contains(&c.as_str())) {
+            Some(unknown) => Err(format!(
+                "parquet settings for column '{}', which is not in the output {:?}",
+                unknown, columns
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Settings of one Parquet column; unset fields follow the file-wide ones.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetColumnConfig {
+    pub compression: Option<ParquetCodec>,
+    pub compression_level: Option<u32>,
+    /// Encoding of values not dictionary-encoded (all of them with
+    /// `dictionary: false`).
+    pub encoding: Option<ParquetEncoding>,
+    pub dictionary: Option<bool>,
+    pub statistics: Option<ParquetStatistics>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetCodec {
+    Uncompressed,
+    #[default]
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+}
+
+/// Non-dictionary value encodings. Delta encodings apply to integer
+/// (`delta_binary_packed`) or string and binary columns (the other two);
+/// `byte_stream_split` to floats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParquetEncoding {
+    Plain,
+    DeltaBinaryPacked,
+    DeltaLengthByteArray,
+    DeltaByteArray,
+    ByteStreamSplit,
+}
+
+/// Min/max/null-count statistics written for readers to prune with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetStatistics {
+    None,
+    /// Per column chunk (row group).
+    Chunk,
+    /// Per page and per column chunk.
+    #[default]
+    Page,
+}
+
+/// Bounds on the output of a hash join block, checked on a sample of both
+/// sides' keys before the join runs, so a many-to-many join is caught before
+/// it fills memory and spill storage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JoinGuardConfig {
+    /// Largest estimated output rows allowed per block.
+    pub max_output_rows: Option<u64>,
+    /// Largest estimated output bytes allowed per block.
+    pub max_output_bytes: Option<u64>,
+    /// Rows sampled from each side, evenly spaced through the block.
+    pub sample_rows: usize,
+    /// What to do when an estimate is over a bound.
+    pub on_exceed: JoinGuardAction,
+}
+
+impl Default for JoinGuardConfig {
+    fn default() -> Self {
+        Self {
+            max_output_rows: None,
+            max_output_bytes: None,
+            sample_rows: 10_000,
+            on_exceed: JoinGuardAction::Fail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinGuardAction {
+    /// Record a warning in the run manifest and run the join anyway.
+    Warn,
+    /// Fail the run before the join starts.
+    #[default]
+    Fail,
+}
+
+/// One step of an operator fallback chain.
+///
+/// In YAML: `- partitions: 64`, `- strategy: join_merge`, or `- skip`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "FallbackRepr", into = "FallbackRepr")]
+pub enum FallbackAction {
+    /// Rebuild the operator with an explicit partition count (smaller partitions).
+    Partitions(usize),
+    /// Run the block with a different operator key and the same config.
+    Strategy(String),
+    /// Emit an empty batch for the block and record a warning in the manifest.
+    Skip,
+}
+
+/// Wire form of [`FallbackAction`]: a bare `skip`, or a single-key map.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FallbackRepr {
+    Name(String),
+    Partitions { partitions: usize },
+    Strategy { strategy: String },
+}
+
+impl TryFrom<FallbackRepr> for FallbackAction {
+    type Error = String;
+
+    fn try_from(repr: FallbackRepr) -> Result<Self, Self::Error> {
+        match repr {
+            FallbackRepr::Name(name) if name == "skip" => Ok(FallbackAction::Skip),
+            FallbackRepr::Name(name) => Err(format!(
+                "unknown fallback '{}' (expected skip, partitions, or strategy)",
+                name
+            )),
+            FallbackRepr::Partitions { partitions } => Ok(FallbackAction::Partitions(partitions)),
+            FallbackRepr::Strategy { strategy } => Ok(FallbackAction::Strategy(strategy)),
+        }
+    }
+}
+
+impl From<FallbackAction> for FallbackRepr {
+    fn from(action: FallbackAction) -> Self {
+        match action {
+            FallbackAction::Partitions(partitions) => FallbackRepr::Partitions { partitions },
+            FallbackAction::Strategy(strategy) => FallbackRepr::Strategy { strategy },
+            FallbackAction::Skip => FallbackRepr::Name("skip".to_string()),
+        }
+    }
+}
+
+/// Sandbox for untrusted pipelines: which URIs may be read/written, and hard
+/// limits on spill volume and wall time.
+///
+/// Prefixes match whole path segments (`data/` allows `data/x.csv`, not
+/// `database.csv`), and URIs containing `..` segments are always rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// URI prefixes sources may read from. Empty = no reads allowed.
+    pub read_allow: Vec<String>,
+    /// URI prefixes sinks may write to. Empty = no writes allowed (read-only).
+    pub sink_allow: Vec<String>,
+    /// URI prefixes sinks may never write to, even if allowed above.
+    pub sink_deny: Vec<String>,
+    /// Maximum total bytes written to spill storage during one run, deleted
+    /// segments included. Checked before `spill_quota_bytes`, which caps only
+    /// the bytes held at once.
+    pub max_spill_bytes: Option<u64>,
+    /// Maximum wall-clock time of one run, in milliseconds.
+    pub max_wall_time_ms: Option<u64>,
+}
+
+impl SandboxConfig {
+    /// Check that a source URI may be read.
+    pub fn check_read(&self, uri: &str) -> Result<(), String> {
+        if has_parent_segment(uri) {
+            return Err(format!("source '{}' contains '..'", uri));
+        }
+        if self.read_allow.iter().any(|p| prefix_matches(p, uri)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "source '{}' is not in the sandbox read allowlist",
+                uri
+            ))
+        }
+    }
+
+    /// Check that a sink URI may be written.
+    pub fn check_write(&self, uri: &str) -> Result<(), String> {
+        if has_parent_segment(uri) {
+            return Err(format!("sink '{}' contains '..'", uri));
+        }
+        if self.sink_deny.iter().any(|p| prefix_matches(p, uri)) {
+            return Err(format!("sink '{}' is in the sandbox denylist", uri));
+        }
+        if self.sink_allow.iter().any(|p| prefix_matches(p, uri)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "sink '{}' is not in the sandbox sink allowlist",
+                uri
+            ))
+        }
+    }
+}
+
+/// Strip `file://` so `file:///data/x` and `/data/x` compare equal.
+fn normalize_uri(uri: &str) -> &str {
+    uri.strip_prefix("file://").unwrap_or(uri)
+}
+
+fn prefix_matches(prefix: &str, uri: &str) -> bool {
+    let prefix = normalize_uri(prefix);
+    let uri = normalize_uri(uri);
+    match uri.strip_prefix(prefix) {
+        Some(rest) => prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+fn has_parent_segment(uri: &str) -> bool {
+    normalize_uri(uri).split(['/', '\\']).any(|seg| seg == "..")
 }
 
 impl Default for EngineConfig {
@@ -46,6 +659,7 @@ impl Default for EngineConfig {
             block_size_hint: None,
             max_spill_concurrency: 4,
             seed: None,
+            partition_hash: PartitionHashKind::default(),
             max_parallel_tasks: 4,
             spill_dir: "/tmp/emsqrt-spill".to_string(),
             spill_uri: None,
@@ -58,6 +672,29 @@ impl Default for EngineConfig {
             spill_retry_max_retries: 3,
             spill_retry_initial_backoff_ms: 200,
             spill_retry_max_backoff_ms: 5_000,
+            sandbox: None,
+            fallbacks: BTreeMap::new(),
+            source_batch: SourceBatchConfig::default(),
+            verify_constraints: false,
+            collect_stats: false,
+            stats_dir: None,
+            stats_file: None,
+            audit_path: None,
+            metrics_addr: None,
+            join_guard: None,
+            memory_wait_ms: None,
+            binary_encoding: BinaryEncoding::default(),
+            parquet: ParquetSinkConfig::default(),
+            csv: CsvSinkConfig::default(),
+            schedule_policy: SchedulePolicy::default(),
+            arithmetic_errors: ArithErrorPolicy::default(),
+            read_retry: ReadRetryConfig::default(),
+            spill_codec: SpillCodec::default(),
+            spill_quota_bytes: None,
+            spill_cleanup: SpillCleanup::default(),
+            spill_space_check: true,
+            deterministic_output: true,
+            detected_resources: Vec::new(),
         }
     }
 }
@@ -95,7 +732,20 @@ impl EngineConfig {
     /// - `EMSQRT_BLOCK_SIZE_HINT`: block size hint
     /// - `EMSQRT_MAX_SPILL_CONCURRENCY`: max spill concurrency
     /// - `EMSQRT_SEED`: random seed
+    /// - `EMSQRT_PARTITION_HASH`: partition hash (`blake3`, `xxhash64`, `ahash`)
     /// - `EMSQRT_MAX_PARALLEL_TASKS`: max parallel tasks
+    /// - `EMSQRT_MEMORY_WAIT_MS`: how long memory reservations wait
+    /// - `EMSQRT_STATS_DIR`: warm-start stats store directory
+    /// - `EMSQRT_BINARY_ENCODING`: binary values in CSV/JSONL (`base64`, `hex`)
+    /// - `EMSQRT_SCHEDULE_POLICY`: block order (`fifo`, `critical_path`, `memory_aware`)
+    /// - `EMSQRT_ARITHMETIC_ERRORS`: division by zero (`error`, `null`, `default(x)`)
+    /// - `EMSQRT_READ_RETRY_MAX_RETRIES`, `EMSQRT_READ_RETRY_INITIAL_MS`,
+    ///   `EMSQRT_READ_RETRY_MAX_MS`: retries of failed source file reads
+    /// - `EMSQRT_SPILL_CODEC`: spill compression (`none`, `lz4`, `zstd`, `zstd:level`)
+    /// - `EMSQRT_SPILL_QUOTA_BYTES`: most spill bytes held at once
+    /// - `EMSQRT_SPILL_CLEANUP`: end-of-run sweep (`run`, `sweep`)
+    /// - `EMSQRT_SPILL_SPACE_CHECK`: check free spill space before a run (`true`, `false`)
+    /// - `EMSQRT_DETERMINISTIC_OUTPUT`: stable aggregate/join output order (`true`, `false`)
     pub fn from_env() -> Self {
         let mut cfg = Self::default();
 
@@ -123,12 +773,114 @@ impl EngineConfig {
             }
         }
 
+        if let Ok(s) = std::env::var("EMSQRT_PARTITION_HASH") {
+            if let Some(v) = PartitionHashKind::parse(&s) {
+                cfg.partition_hash = v;
+            }
+        }
+
         if let Ok(s) = std::env::var("EMSQRT_MAX_PARALLEL_TASKS") {
             if let Ok(v) = s.parse::<usize>() {
                 cfg.max_parallel_tasks = v;
             }
         }
 
+        if let Ok(s) = std::env::var("EMSQRT_MEMORY_WAIT_MS") {
+            if let Ok(v) = s.parse::<u64>() {
+                cfg.memory_wait_ms = Some(v);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_STATS_DIR") {
+            if !s.is_empty() {
+                cfg.stats_dir = Some(s);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_STATS_FILE") {
+            if !s.is_empty() {
+                cfg.stats_file = Some(s);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_AUDIT_PATH") {
+            if !s.is_empty() {
+                cfg.audit_path = Some(s);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_METRICS_ADDR") {
+            if !s.is_empty() {
+                cfg.metrics_addr = Some(s);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_BINARY_ENCODING") {
+            if let Some(v) = BinaryEncoding::parse(&s) {
+                cfg.binary_encoding = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_SCHEDULE_POLICY") {
+            if let Some(v) = SchedulePolicy::parse(&s) {
+                cfg.schedule_policy = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_ARITHMETIC_ERRORS") {
+            if let Some(v) = ArithErrorPolicy::parse(&s) {
+                cfg.arithmetic_errors = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_READ_RETRY_MAX_RETRIES") {
+            if let Ok(v) = s.parse::<usize>() {
+                cfg.read_retry.max_retries = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_READ_RETRY_INITIAL_MS") {
+            if let Ok(v) = s.parse::<u64>() {
+                cfg.read_retry.initial_backoff_ms = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_READ_RETRY_MAX_MS") {
+            if let Ok(v) = s.parse::<u64>() {
+                cfg.read_retry.max_backoff_ms = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_SPILL_CODEC") {
+            if let Some(v) = SpillCodec::parse(&s) {
+                cfg.spill_codec = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_SPILL_QUOTA_BYTES") {
+            if let Ok(v) = s.parse::<u64>() {
+                cfg.spill_quota_bytes = Some(v);
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_SPILL_CLEANUP") {
+            if let Some(v) = SpillCleanup::parse(&s) {
+                cfg.spill_cleanup = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_SPILL_SPACE_CHECK") {
+            if let Ok(v) = s.parse::<bool>() {
+                cfg.spill_space_check = v;
+            }
+        }
+
+        if let Ok(s) = std::env::var("EMSQRT_DETERMINISTIC_OUTPUT") {
+            if let Ok(v) = s.parse::<bool>() {
+                cfg.deterministic_output = v;
+            }
+        }
+
         if let Ok(s) = std::env::var("EMSQRT_SPILL_DIR") {
             cfg.spill_dir = s;
         }
@@ -182,17 +934,40 @@ impl EngineConfig {
         cfg
     }
 
+    /// The seeded partition hasher shared by all partitioning operators in a run.
+    pub fn partition_hasher(&self) -> PartitionHasher {
+        PartitionHasher::new(self.partition_hash, self.seed.unwrap_or(0))
+    }
+
+    /// The `detected_resources` whose settings still hold the detected value,
+    /// i.e. were not overridden after detection.
+    pub fn resources_in_effect(&self) -> Vec<DetectedLimit> {
+        self.detected_resources
+            .iter()
+            .filter(|d| match d.setting.as_str() {
+                "mem_cap_bytes" => self.mem_cap_bytes as u64 == d.value,
+                "max_parallel_tasks" => self.max_parallel_tasks as u64 == d.value,
+                _ => false,
+            })
+            .cloned()
+            .collect()
+    }
+
     /// Produce a storage configuration snapshot used by the IO layer.
     pub fn storage_config(&self) -> StorageConfig {
-        let scheme = self
-            .spill_uri
+        let uri = self.spill_uri.clone().or_else(|| {
+            self.spill_dir
+                .contains("://")
+                .then(|| self.spill_dir.clone())
+        });
+        let scheme = uri
             .as_deref()
             .and_then(|uri| uri.split("://").next())
             .map(|s| s.trim())
             .filter(|s| !s.is_empty())
             .map(|s| s.to_string());
 
-        let root = match (scheme.as_deref(), self.spill_uri.as_ref()) {
+        let root = match (scheme.as_deref(), uri.as_ref()) {
             (Some("file"), Some(uri)) => {
                 file_uri_to_path(uri).unwrap_or_else(|| self.spill_dir.clone())
             }
@@ -201,7 +976,7 @@ impl EngineConfig {
         };
 
         StorageConfig {
-            uri: self.spill_uri.clone(),
+            uri,
             root,
             aws_region: self.spill_aws_region.clone(),
             aws_access_key_id: self.spill_aws_access_key_id.clone(),
@@ -3,10 +3,12 @@
 //! The planner produces a `LogicalPlan` (what to do), then a `PhysicalPlan`
 //! that binds concrete operator implementations and TE block boundaries.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 use crate::id::OpId;
-use crate::schema::Schema;
+use crate::schema::{DataType, Field, Schema};
 
 /// Simple join types (expand as needed).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,10 +19,40 @@ pub enum JoinType {
     Full,
 }
 
+/// Suffix of a join's right column whose name an earlier column has.
+pub const RIGHT_SUFFIX: &str = "_right";
+
+impl JoinType {
+    /// Output schema of joining `left` with `right`: the left columns, then
+    /// the right ones, suffixed `_right` where an earlier column has the name
+    /// (see `Schema::new_deduplicated`). Columns of a side an outer join pads
+    /// with nulls become nullable.
+    pub fn output_schema(self, left: &Schema, right: &Schema) -> Schema {
+        let pad_left = matches!(self, JoinType::Right | JoinType::Full);
+        let pad_right = matches!(self, JoinType::Left | JoinType::Full);
+        let mut fields: Vec<Field> = left
+            .fields
+            .iter()
+            .map(|f| Field::new(f.name.clone(), f.data_type.clone(), f.nullable || pad_left))
+            .collect();
+        for f in &right.fields {
+            fields.push(Field::new(
+                f.name.clone(),
+                f.data_type.clone(),
+                f.nullable || pad_right,
+            ));
+        }
+        Schema::new_deduplicated(fields, RIGHT_SUFFIX)
+    }
+}
+
 /// Simplified aggregations.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Aggregation {
+    /// `count(*)`: rows per group.
     Count,
+    /// `count(col)`: non-null values of `col` per group.
+    CountColumn(String),
     Sum(String),
     Avg(String),
     Min(String),
@@ -28,6 +60,73 @@ pub enum Aggregation {
     // TODO: distinct, multi-agg per group, etc.
 }
 
+impl Aggregation {
+    /// Parse the spec an aggregate binding carries: `count`, or
+    /// `count:col`, `sum:col`, `avg:col`, `min:col`, `max:col`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if spec == "count" {
+            return Ok(Aggregation::Count);
+        }
+        let (func, col) = spec
+            .split_once(':')
+            .filter(|(_, col)| !col.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "invalid aggregate '{}' (expected count or func:column)",
+                    spec
+                )
+            })?;
+        let col = col.to_string();
+        match func {
+            "count" => Ok(Aggregation::CountColumn(col)),
+            "sum" => Ok(Aggregation::Sum(col)),
+            "avg" => Ok(Aggregation::Avg(col)),
+            "min" => Ok(Aggregation::Min(col)),
+            "max" => Ok(Aggregation::Max(col)),
+            _ => Err(format!("unknown aggregate function '{}'", func)),
+        }
+    }
+
+    /// Result column over `input`: counts are non-null `Int64`, the rest
+    /// nullable (null for groups without values). `sum` and `avg` of a
+    /// `Decimal128` column are `Decimal128`, `min` and `max` of a
+    /// `Decimal128`, `Date64` or `Timestamp` column keep its type; the rest
+    /// are `Float64`.
+    pub fn output_field(&self, input: &Schema) -> Field {
+        let input_type = |col: &str| {
+            input
+                .index_of(col)
+                .map(|i| input.fields[i].data_type.clone())
+        };
+        let data_type = match self {
+            Aggregation::Count | Aggregation::CountColumn(_) => DataType::Int64,
+            Aggregation::Sum(col) | Aggregation::Avg(col) => match input_type(col) {
+                Some(DataType::Decimal128) => DataType::Decimal128,
+                _ => DataType::Float64,
+            },
+            Aggregation::Min(col) | Aggregation::Max(col) => match input_type(col) {
+                Some(t @ (DataType::Decimal128 | DataType::Date64 | DataType::Timestamp)) => t,
+                _ => DataType::Float64,
+            },
+        };
+        let nullable = data_type != DataType::Int64;
+        Field::new(self.output_name(), data_type, nullable)
+    }
+
+    /// Name of the result column: `count`, or the function and column
+    /// (`sum_price`).
+    pub fn output_name(&self) -> String {
+        match self {
+            Aggregation::Count => "count".to_string(),
+            Aggregation::CountColumn(col) => format!("count_{}", col),
+            Aggregation::Sum(col) => format!("sum_{}", col),
+            Aggregation::Avg(col) => format!("avg_{}", col),
+            Aggregation::Min(col) => format!("min_{}", col),
+            Aggregation::Max(col) => format!("max_{}", col),
+        }
+    }
+}
+
 /// High-level logical nodes (source → transforms → sink).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LogicalPlan {
@@ -39,9 +138,17 @@ pub enum LogicalPlan {
         input: Box<LogicalPlan>,
         expr: String, // TODO: real expr AST
     },
+    /// Keep rows whose `columns` appear in a key file (semi-join without a join).
+    FilterIn {
+        input: Box<LogicalPlan>,
+        columns: Vec<String>,
+        keys: String, // key file, one key per line
+        delimiter: Option<String>,
+    },
     Map {
         input: Box<LogicalPlan>,
-        expr: String, // TODO: real projection list
+        /// `expr [AS alias], ...`; see `Expr::parse_list`.
+        expr: String,
     },
     Project {
         input: Box<LogicalPlan>,
@@ -53,6 +160,15 @@ pub enum LogicalPlan {
         on: Vec<(String, String)>,
         join_type: JoinType,
     },
+    /// Join on an arbitrary `condition` over the output columns (right
+    /// columns named as in `JoinType::output_schema`); `None` pairs every
+    /// row with every row, a cross join. Inner and left joins only.
+    ThetaJoin {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        condition: Option<String>,
+        join_type: JoinType,
+    },
     Aggregate {
         input: Box<LogicalPlan>,
         group_by: Vec<String>,
@@ -70,11 +186,73 @@ pub enum LogicalPlan {
         alias: String,
         delimiter: Option<String>,
     },
+    /// The `k` most frequent values of `column`, with approximate counts,
+    /// from a sketch of `capacity` counters (`None` = sized from `k`).
+    TopK {
+        input: Box<LogicalPlan>,
+        column: String,
+        k: usize,
+        capacity: Option<usize>,
+    },
+    /// The first `limit` rows after skipping `offset`: in input order, or
+    /// by `order_by` when it is set (a top-k kept in a bounded heap rather
+    /// than a full sort).
+    Limit {
+        input: Box<LogicalPlan>,
+        limit: usize,
+        offset: usize,
+        order_by: Vec<SortKey>,
+    },
     Sink {
         input: Box<LogicalPlan>,
         destination: String, // e.g., "s3://bucket/out/"
         format: String,      // "parquet", "csv", ...
     },
+    /// Concatenate inputs that share a schema (e.g., a templated list of files).
+    Union { inputs: Vec<LogicalPlan> },
+}
+
+/// A sort column and direction, written `col` or `col desc` (`col asc` is
+/// the default). Nulls sort first ascending, so last descending.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortKey {
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut words = s.split_whitespace();
+        let column = words
+            .next()
+            .ok_or_else(|| "empty sort key".to_string())?
+            .to_string();
+        let descending = match words.next().map(str::to_lowercase).as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => {
+                return Err(format!(
+                    "sort key '{}': expected 'asc' or 'desc', found '{}'",
+                    s, other
+                ))
+            }
+        };
+        if words.next().is_some() {
+            return Err(format!("sort key '{}': expected 'col [asc|desc]'", s));
+        }
+        Ok(Self { column, descending })
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.descending {
+            write!(f, "{} desc", self.column)
+        } else {
+            f.write_str(&self.column)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +291,10 @@ pub enum WindowFrameBound {
 }
 
 /// Physical nodes bind to operator IDs (resolved in `emsqrt-operators`).
+///
+/// A subplan read by several operators appears under each of them with the
+/// same `OpId`s. It is one set of operators and runs once; [`PlanGraph`] is
+/// the plan with each operator once and its edges.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PhysicalPlan {
     Source {
@@ -134,6 +316,12 @@ pub enum PhysicalPlan {
         op: OpId,
         input: Box<PhysicalPlan>,
     },
+    /// N-ary node (e.g., union); each input block feeds one output block.
+    Nary {
+        op: OpId,
+        inputs: Vec<PhysicalPlan>,
+        schema: Schema,
+    },
 }
 
 impl LogicalPlan {
@@ -143,13 +331,17 @@ impl LogicalPlan {
         match self {
             Scan { .. } => 0,
             Filter { .. }
+            | FilterIn { .. }
             | Map { .. }
             | Project { .. }
             | Aggregate { .. }
             | Window { .. }
             | Lateral { .. }
+            | TopK { .. }
+            | Limit { .. }
             | Sink { .. } => 1,
-            Join { .. } => 2,
+            Join { .. } | ThetaJoin { .. } => 2,
+            Union { inputs } => inputs.len(),
         }
     }
 
@@ -164,7 +356,78 @@ impl LogicalPlan {
     }
 }
 
+/// A physical plan as a DAG of operators (see [`PhysicalPlan::graph`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanGraph {
+    /// Inputs of each operator, in operator order.
+    pub inputs: BTreeMap<OpId, Vec<OpId>>,
+    /// Operators reading each operator's output, one entry per edge.
+    pub consumers: BTreeMap<OpId, Vec<OpId>>,
+}
+
+impl PlanGraph {
+    /// Operators whose output more than one edge reads.
+    pub fn shared(&self) -> Vec<OpId> {
+        self.consumers
+            .iter()
+            .filter(|(_, readers)| readers.len() > 1)
+            .map(|(op, _)| *op)
+            .collect()
+    }
+}
+
 impl PhysicalPlan {
+    /// The node's operator.
+    pub fn op(&self) -> OpId {
+        use PhysicalPlan::*;
+        match self {
+            Source { op, .. }
+            | Unary { op, .. }
+            | Binary { op, .. }
+            | Sink { op, .. }
+            | Nary { op, .. } => *op,
+        }
+    }
+
+    /// The node's inputs, in operator order.
+    pub fn children(&self) -> Vec<&PhysicalPlan> {
+        use PhysicalPlan::*;
+        match self {
+            Source { .. } => vec![],
+            Unary { input, .. } | Sink { input, .. } => vec![input],
+            Binary { left, right, .. } => vec![left, right],
+            Nary { inputs, .. } => inputs.iter().collect(),
+        }
+    }
+
+    /// Each operator once, with its input and consumer edges.
+    pub fn graph(&self) -> PlanGraph {
+        fn walk(node: &PhysicalPlan, graph: &mut PlanGraph) {
+            // A shared subplan's edges are recorded on its first visit.
+            if graph.inputs.contains_key(&node.op()) {
+                return;
+            }
+            let children = node.children();
+            for child in &children {
+                graph
+                    .consumers
+                    .entry(child.op())
+                    .or_default()
+                    .push(node.op());
+            }
+            graph
+                .inputs
+                .insert(node.op(), children.iter().map(|c| c.op()).collect());
+            graph.consumers.entry(node.op()).or_default();
+            for child in children {
+                walk(child, graph);
+            }
+        }
+        let mut graph = PlanGraph::default();
+        walk(self, &mut graph);
+        graph
+    }
+
     /// Returns the number of inputs for this node.
     pub fn inputs(&self) -> usize {
         use PhysicalPlan::*;
@@ -172,6 +435,7 @@ impl PhysicalPlan {
             Source { .. } => 0,
             Unary { .. } | Sink { .. } => 1,
             Binary { .. } => 2,
+            Nary { inputs, .. } => inputs.len(),
         }
     }
 
@@ -0,0 +1,128 @@
+//! Fixed-point decimals: `Scalar::Decimal128(value, scale)` is
+//! `value / 10^scale`.
+//!
+//! Arithmetic is exact within 38 digits: `+`/`-` take the larger scale, `*`
+//! adds the scales, and `/` keeps at least [`DIV_SCALE`] digits, rounding
+//! half away from zero. Results that overflow are errors, not wraps.
+
+use std::cmp::Ordering;
+
+/// Digits a `Decimal128` holds.
+pub const MAX_PRECISION: u8 = 38;
+/// Least scale of a quotient.
+pub const DIV_SCALE: u8 = 6;
+
+fn pow10(exp: u8) -> Option<i128> {
+    10i128.checked_pow(exp as u32)
+}
+
+/// `[+-]digits[.digits]` as `(value, scale)`; the scale is the number of
+/// fraction digits given.
+pub fn parse_decimal(s: &str) -> Option<(i128, u8)> {
+    let s = s.trim();
+    let (negative, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int, frac) = body.split_once('.').unwrap_or((body, ""));
+    let all_digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+    if (int.is_empty() && frac.is_empty()) || !all_digits(int) || !all_digits(frac) {
+        return None;
+    }
+    let scale = u8::try_from(frac.len())
+        .ok()
+        .filter(|&s| s <= MAX_PRECISION)?;
+    let digits = format!("{}{}", int, frac);
+    let value: i128 = if digits.is_empty() {
+        0
+    } else {
+        digits.parse().ok()?
+    };
+    Some((if negative { -value } else { value }, scale))
+}
+
+/// `value` at `scale` as text, with exactly `scale` fraction digits.
+pub fn format_decimal(value: i128, scale: u8) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let sign = if value < 0 { "-" } else { "" };
+    if scale == 0 {
+        return format!("{}{}", sign, digits);
+    }
+    let digits = format!("{:0>width$}", digits, width = scale as usize + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale as usize);
+    format!("{}{}.{}", sign, int, frac)
+}
+
+/// `value` moved from scale `from` to scale `to`, rounding half away from
+/// zero when digits are dropped; `None` on overflow.
+pub fn rescale(value: i128, from: u8, to: u8) -> Option<i128> {
+    match to.cmp(&from) {
+        Ordering::Equal => Some(value),
+        Ordering::Greater => value.checked_mul(pow10(to - from)?),
+        Ordering::Less => Some(div_round(value, pow10(from - to)?)),
+    }
+}
+
+/// `a / b` rounded half away from zero.
+pub fn div_round(a: i128, b: i128) -> i128 {
+    let (q, r) = (a / b, a % b);
+    if r.unsigned_abs() >= b.unsigned_abs() - r.unsigned_abs() {
+        q + if (a < 0) == (b < 0) { 1 } else { -1 }
+    } else {
+        q
+    }
+}
+
+/// The same number with trailing fraction zeros dropped, so equal values
+/// have one representation.
+pub fn normalize(mut value: i128, mut scale: u8) -> (i128, u8) {
+    while scale > 0 && value % 10 == 0 {
+        value /= 10;
+        scale -= 1;
+    }
+    (value, scale)
+}
+
+pub fn to_f64(value: i128, scale: u8) -> f64 {
+    value as f64 / 10f64.powi(scale as i32)
+}
+
+pub fn cmp(a: i128, a_scale: u8, b: i128, b_scale: u8) -> Ordering {
+    let scale = a_scale.max(b_scale);
+    match (rescale(a, a_scale, scale), rescale(b, b_scale, scale)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        // Too wide to line up exactly; the magnitudes differ a lot anyway.
+        _ => to_f64(a, a_scale).total_cmp(&to_f64(b, b_scale)),
+    }
+}
+
+pub fn add(a: i128, a_scale: u8, b: i128, b_scale: u8) -> Option<(i128, u8)> {
+    let scale = a_scale.max(b_scale);
+    let sum = rescale(a, a_scale, scale)?.checked_add(rescale(b, b_scale, scale)?)?;
+    Some((sum, scale))
+}
+
+pub fn sub(a: i128, a_scale: u8, b: i128, b_scale: u8) -> Option<(i128, u8)> {
+    add(a, a_scale, b.checked_neg()?, b_scale)
+}
+
+pub fn mul(a: i128, a_scale: u8, b: i128, b_scale: u8) -> Option<(i128, u8)> {
+    let product = a.checked_mul(b)?;
+    let scale = a_scale + b_scale;
+    if scale > MAX_PRECISION {
+        return Some((rescale(product, scale, MAX_PRECISION)?, MAX_PRECISION));
+    }
+    Some((product, scale))
+}
+
+/// `a / b` at scale `max(a_scale, DIV_SCALE)`; `None` when `b` is zero or
+/// on overflow.
+pub fn div(a: i128, a_scale: u8, b: i128, b_scale: u8) -> Option<(i128, u8)> {
+    if b == 0 {
+        return None;
+    }
+    let scale = a_scale.max(DIV_SCALE);
+    // a·10^(scale − a_scale + b_scale) / b has `scale` fraction digits.
+    let numerator = a.checked_mul(pow10(scale - a_scale + b_scale)?)?;
+    Some((div_round(numerator, b), scale))
+}
@@ -1,11 +1,19 @@
 //! Expression engine for SQL-like expressions.
 //!
-//! Supports arithmetic operations, comparisons, logical operations, and column references.
-//! Used by Filter and Project operators for complex expressions.
+//! Supports arithmetic operations, comparisons, logical operations, column references,
+//! and the seeded functions `rand()`, `hash(col)` and `sample_hash(col, fraction)`.
+//! Used by the Filter and Map operators and the join filters.
+//!
+//! Expressions evaluate one row at a time ([`Expr::evaluate_with`]) or over a
+//! whole batch column at a time ([`Expr::evaluate_batch_with`], and
+//! [`Expr::evaluate_selection`] for predicates), which is what operators use.
 
 use serde::{Deserialize, Serialize};
 
-use crate::types::{RowBatch, Scalar};
+use crate::hash::{PartitionHashKind, PartitionHasher};
+use crate::schema::{DataType, Field, Schema};
+use crate::types::{typed_cmp, Column, RowBatch, Scalar};
+use crate::{decimal, functions, temporal};
 
 /// Binary operators for expressions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -68,6 +76,183 @@ impl UnaryOp {
     }
 }
 
+/// Built-in functions. All are deterministic given the run seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Func {
+    /// `rand()`: uniform Float64 in `[0, 1)`, keyed on the row's position in the input.
+    Rand,
+    /// `hash(col)`: seeded 64-bit hash of a value, as Int64 (null stays null).
+    Hash,
+    /// `sample_hash(col, fraction)`: true for a stable `fraction` of distinct values.
+    SampleHash,
+    /// `safe_div(a, b)`: `a / b`, or null when `b` is zero or either is null.
+    SafeDiv,
+    /// `nullif(a, b)`: null when `a` equals `b`, otherwise `a`.
+    NullIf,
+    /// `upper(s)`: `s` in upper case.
+    Upper,
+    /// `lower(s)`: `s` in lower case.
+    Lower,
+    /// `trim(s)`: `s` without leading and trailing whitespace.
+    Trim,
+    /// `substr(s, start[, len])`: characters of `s` from the 1-based `start`.
+    Substr,
+    /// `concat(a, ...)`: the arguments' text, joined; nulls are skipped.
+    Concat,
+    /// `abs(x)`: magnitude of a number, in its type.
+    Abs,
+    /// `round(x[, digits])`: `x` rounded half away from zero.
+    Round,
+    /// `floor(x)`: largest whole number not above `x`.
+    Floor,
+    /// `ceil(x)`: smallest whole number not below `x`.
+    Ceil,
+    /// `coalesce(a, ...)`: the first argument that is not null.
+    Coalesce,
+    /// `cast(x, 'Type')`: `x` converted to a schema type (`Int64`, `date`, ...).
+    Cast,
+    /// `date_trunc(unit, t)`: start of the year, quarter, month, week, day,
+    /// hour, minute or second holding `t`.
+    DateTrunc,
+    /// `date_diff(unit, start, end)`: unit boundaries between two times, as Int64.
+    DateDiff,
+}
+
+impl Func {
+    /// Parse a function name.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "rand" => Ok(Func::Rand),
+            "hash" => Ok(Func::Hash),
+            "sample_hash" => Ok(Func::SampleHash),
+            "safe_div" => Ok(Func::SafeDiv),
+            "nullif" => Ok(Func::NullIf),
+            "upper" => Ok(Func::Upper),
+            "lower" => Ok(Func::Lower),
+            "trim" => Ok(Func::Trim),
+            "substr" => Ok(Func::Substr),
+            "concat" => Ok(Func::Concat),
+            "abs" => Ok(Func::Abs),
+            "round" => Ok(Func::Round),
+            "floor" => Ok(Func::Floor),
+            "ceil" => Ok(Func::Ceil),
+            "coalesce" => Ok(Func::Coalesce),
+            "cast" => Ok(Func::Cast),
+            "date_trunc" => Ok(Func::DateTrunc),
+            "date_diff" => Ok(Func::DateDiff),
+            _ => Err(format!("unknown function: {}", name)),
+        }
+    }
+
+    /// Fewest and most arguments the function takes.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Func::Rand => (0, 0),
+            Func::Hash
+            | Func::Upper
+            | Func::Lower
+            | Func::Trim
+            | Func::Abs
+            | Func::Floor
+            | Func::Ceil => (1, 1),
+            Func::SampleHash | Func::SafeDiv | Func::NullIf | Func::Cast | Func::DateTrunc => {
+                (2, 2)
+            }
+            Func::Round => (1, 2),
+            Func::Substr => (2, 3),
+            Func::DateDiff => (3, 3),
+            Func::Concat | Func::Coalesce => (1, usize::MAX),
+        }
+    }
+}
+
+/// What a division by zero evaluates to.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ArithErrorPolicy {
+    /// Fail the expression, and with it the run.
+    #[default]
+    Error,
+    /// Null.
+    Null,
+    /// This value, in the type the quotient would have had.
+    Default(f64),
+}
+
+impl ArithErrorPolicy {
+    /// Parse `error`, `null` or `default(x)`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "error" => Some(Self::Error),
+            "null" => Some(Self::Null),
+            _ => s
+                .strip_prefix("default(")?
+                .strip_suffix(')')?
+                .trim()
+                .parse()
+                .ok()
+                .map(Self::Default),
+        }
+    }
+}
+
+impl std::fmt::Display for ArithErrorPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => f.write_str("error"),
+            Self::Null => f.write_str("null"),
+            Self::Default(x) => write!(f, "default({})", x),
+        }
+    }
+}
+
+impl Serialize for ArithErrorPolicy {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ArithErrorPolicy {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = ArithErrorPolicy;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("\"error\", \"null\" or \"default(x)\"")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                ArithErrorPolicy::parse(v)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+
+            // An unquoted YAML `null`.
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(ArithErrorPolicy::Null)
+            }
+
+            fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+                Ok(ArithErrorPolicy::Null)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Run-level inputs to expression evaluation.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EvalContext {
+    /// Run seed (recorded in the manifest) for `rand`, `hash` and `sample_hash`.
+    pub seed: u64,
+    /// Position of the batch's first row in the operator's input stream.
+    pub row_base: u64,
+    /// What `/` yields for a zero divisor (`safe_div` always yields null).
+    pub arith_errors: ArithErrorPolicy,
+}
+
 /// Expression AST for SQL-like expressions.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expr {
@@ -83,116 +268,98 @@ pub enum Expr {
     },
     /// Unary operation: OP arg
     UnaryOp { op: UnaryOp, arg: Box<Expr> },
+    /// Function call: name(args...)
+    Call { func: Func, args: Vec<Expr> },
+    /// `expr [NOT] BETWEEN low AND high`, inclusive at both ends.
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+        negated: bool,
+    },
+    /// `expr [NOT] IN (v1, v2, ...)`
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
 }
 
 impl Expr {
-    /// Parse a simple expression string into an Expr AST.
+    /// Parse an expression string into an Expr AST.
     ///
-    /// Supports expressions with proper operator precedence:
-    /// - Logical operators (AND/OR) have lowest precedence
-    /// - Comparison operators (==, !=, <, <=, >, >=)
-    /// - Arithmetic operators (+, -, *, /) have highest precedence
+    /// Precedence, loosest first; binary operators are left-associative:
+    /// 1. `OR` / `||`
+    /// 2. `AND` / `&&`
+    /// 3. `NOT` / `!` (prefix)
+    /// 4. Comparisons (`==`, `=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`),
+    ///    `IS [NOT] NULL`, `[NOT] BETWEEN low AND high`, `[NOT] IN (...)`
+    /// 5. `+`, `-`
+    /// 6. `*`, `/`
+    /// 7. Unary `-` / `+`
+    ///
+    /// Parentheses group, and keywords match in any case. Operands are
+    /// function calls, literals (see [`parse_literal`]) and column names.
     pub fn parse(expr_str: &str) -> Result<Self, String> {
-        let expr_str = expr_str.trim();
-
-        // Parse with operator precedence: logical operators last (lowest precedence)
-        // This allows expressions like "age > 20 AND price < 15" to be parsed correctly
-
-        // First, try to find logical operators (AND/OR) - these have lowest precedence
-        // We need to find the rightmost AND/OR to handle left-associativity
-        // Check for " AND " first (with spaces), then " OR " (with spaces)
-        // This avoids matching "AND" inside column names
-        let logical_ops = [(" AND ", BinOp::And), (" OR ", BinOp::Or)];
-
-        // Find the rightmost logical operator
-        let mut best_pos: Option<usize> = None;
-        let mut best_op: Option<BinOp> = None;
-        let mut best_op_str: Option<&str> = None;
-
-        for (op_str, op) in &logical_ops {
-            if let Some(pos) = expr_str.rfind(op_str) {
-                if best_pos.map_or(true, |best| pos > best) {
-                    best_pos = Some(pos);
-                    best_op = Some(*op);
-                    best_op_str = Some(op_str);
-                }
-            }
-        }
-
-        if let (Some(pos), Some(op), Some(op_str)) = (best_pos, best_op, best_op_str) {
-            let left_str = expr_str[..pos].trim();
-            let right_str = expr_str[pos + op_str.len()..].trim();
-
-            if !left_str.is_empty() && !right_str.is_empty() {
-                let left = Self::parse(left_str)?; // Recursive parse
-                let right = Self::parse(right_str)?; // Recursive parse
-                return Ok(Expr::BinaryOp {
-                    op,
-                    left: Box::new(left),
-                    right: Box::new(right),
-                });
-            }
+        let mut parser = Parser {
+            source: expr_str,
+            tokens: tokenize(expr_str)?,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected '{}' in '{}'", token, expr_str)),
         }
+    }
 
-        // Then, try comparison operators
-        for op_str in &["==", "!=", "<=", ">=", "<", ">"] {
-            if let Some(pos) = expr_str.find(op_str) {
-                let left_str = expr_str[..pos].trim();
-                let right_str = expr_str[pos + op_str.len()..].trim();
-
-                if !left_str.is_empty() && !right_str.is_empty() {
-                    let op = BinOp::parse(op_str)?;
-                    let left = Self::parse(left_str)?; // Recursive parse for nested expressions
-                    let right = Self::parse(right_str)?; // Recursive parse for nested expressions
-                    return Ok(Expr::BinaryOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    });
-                }
+    /// Parse a `map` list: comma-separated `expr [AS alias]` items.
+    ///
+    /// A bare column may go without an alias (it is kept as is); any other
+    /// expression needs one to name its column. See [`map_schema`].
+    pub fn parse_list(list: &str) -> Result<Vec<(Expr, Option<String>)>, String> {
+        let mut parser = Parser {
+            source: list,
+            tokens: tokenize(list)?,
+            pos: 0,
+        };
+        let mut items = Vec::new();
+        loop {
+            let start = parser.pos;
+            let expr = parser.expr(0)?;
+            let alias = parser.alias()?;
+            if alias.is_none() && !matches!(expr, Expr::Column(_)) {
+                let item: Vec<String> = parser.tokens[start..parser.pos]
+                    .iter()
+                    .map(Token::to_string)
+                    .collect();
+                let item = item.join(" ");
+                return Err(format!("'{}' needs an alias ('{} AS name')", item, item));
             }
-        }
-
-        // Finally, try arithmetic operators (highest precedence)
-        for op_str in &["+", "-", "*", "/"] {
-            if let Some(pos) = expr_str.find(op_str) {
-                let left_str = expr_str[..pos].trim();
-                let right_str = expr_str[pos + op_str.len()..].trim();
-
-                if !left_str.is_empty() && !right_str.is_empty() {
-                    let op = BinOp::parse(op_str)?;
-                    let left = Self::parse(left_str)?; // Recursive parse for nested expressions
-                    let right = Self::parse(right_str)?; // Recursive parse for nested expressions
-                    return Ok(Expr::BinaryOp {
-                        op,
-                        left: Box::new(left),
-                        right: Box::new(right),
-                    });
-                }
+            items.push((expr, alias));
+            if parser.pos == parser.tokens.len() {
+                return Ok(items);
             }
+            let found = parser.eat_symbol(",");
+            parser.expect("','", found)?;
         }
-
-        // Try to parse as a single atom (column or literal)
-        Self::parse_atom(expr_str)
-    }
-
-    /// Parse an atomic expression (column or literal).
-    fn parse_atom(atom_str: &str) -> Result<Self, String> {
-        let atom_str = atom_str.trim();
-
-        // Try to parse as literal first
-        if let Ok(scalar) = parse_literal(atom_str) {
-            return Ok(Expr::Literal(scalar));
-        }
-
-        // Otherwise, treat as column reference
-        Ok(Expr::Column(atom_str.to_string()))
     }
 
     /// Evaluate an expression against a row in a RowBatch.
     ///
-    /// Returns the resulting Scalar value.
+    /// Returns the resulting Scalar value. Functions use seed 0 and treat the
+    /// batch as the start of the input; see [`Expr::evaluate_with`].
     pub fn evaluate(&self, batch: &RowBatch, row_idx: usize) -> Result<Scalar, String> {
+        self.evaluate_with(batch, row_idx, &EvalContext::default())
+    }
+
+    /// Evaluate against a row with the run seed and stream position in `ctx`.
+    pub fn evaluate_with(
+        &self,
+        batch: &RowBatch,
+        row_idx: usize,
+        ctx: &EvalContext,
+    ) -> Result<Scalar, String> {
         match self {
             Expr::Column(name) => {
                 // Find column and get value at row_idx
@@ -216,15 +383,56 @@ impl Expr {
                 Ok(col.values[row_idx].clone())
             }
             Expr::Literal(scalar) => Ok(scalar.clone()),
+            Expr::BinaryOp {
+                op: BinOp::And | BinOp::Or,
+                ..
+            } => self
+                .evaluate_bool_with(batch, row_idx, ctx)
+                .map(Scalar::Bool),
             Expr::BinaryOp { op, left, right } => {
-                let left_val = left.evaluate(batch, row_idx)?;
-                let right_val = right.evaluate(batch, row_idx)?;
-                evaluate_binary_op(*op, &left_val, &right_val)
+                let left_val = left.evaluate_with(batch, row_idx, ctx)?;
+                let right_val = right.evaluate_with(batch, row_idx, ctx)?;
+                evaluate_binary_op_with(*op, &left_val, &right_val, ctx)
             }
             Expr::UnaryOp { op, arg } => {
-                let arg_val = arg.evaluate(batch, row_idx)?;
+                let arg_val = arg.evaluate_with(batch, row_idx, ctx)?;
                 evaluate_unary_op(*op, &arg_val)
             }
+            Expr::Call { func, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| a.evaluate_with(batch, row_idx, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                evaluate_call(*func, &args.iter().collect::<Vec<_>>(), row_idx, ctx)
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                // Same result as `expr >= low AND expr <= high`.
+                let value = expr.evaluate_with(batch, row_idx, ctx)?;
+                let low = low.evaluate_with(batch, row_idx, ctx)?;
+                let high = high.evaluate_with(batch, row_idx, ctx)?;
+                let inside = scalar_cmp(&value, &low).is_ge() && scalar_cmp(&value, &high).is_le();
+                Ok(Scalar::Bool(inside != *negated))
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = expr.evaluate_with(batch, row_idx, ctx)?;
+                let mut found = false;
+                for item in list {
+                    if scalar_eq(&value, &item.evaluate_with(batch, row_idx, ctx)?) {
+                        found = true;
+                        break;
+                    }
+                }
+                Ok(Scalar::Bool(found != *negated))
+            }
         }
     }
 
@@ -232,13 +440,874 @@ impl Expr {
     ///
     /// Returns true if the expression evaluates to a truthy value.
     pub fn evaluate_bool(&self, batch: &RowBatch, row_idx: usize) -> Result<bool, String> {
-        let scalar = self.evaluate(batch, row_idx)?;
-        scalar_to_bool(&scalar)
+        self.evaluate_bool_with(batch, row_idx, &EvalContext::default())
+    }
+
+    /// Like [`Expr::evaluate_bool`], with the run seed and stream position in `ctx`.
+    ///
+    /// `AND` and `OR` short-circuit: the right side is only evaluated when
+    /// the left does not decide the result.
+    pub fn evaluate_bool_with(
+        &self,
+        batch: &RowBatch,
+        row_idx: usize,
+        ctx: &EvalContext,
+    ) -> Result<bool, String> {
+        match self {
+            Expr::BinaryOp {
+                op: BinOp::And,
+                left,
+                right,
+            } => Ok(left.evaluate_bool_with(batch, row_idx, ctx)?
+                && right.evaluate_bool_with(batch, row_idx, ctx)?),
+            Expr::BinaryOp {
+                op: BinOp::Or,
+                left,
+                right,
+            } => Ok(left.evaluate_bool_with(batch, row_idx, ctx)?
+                || right.evaluate_bool_with(batch, row_idx, ctx)?),
+            _ => scalar_to_bool(&self.evaluate_with(batch, row_idx, ctx)?),
+        }
+    }
+
+    /// Evaluate over every row of `batch` at once, column at a time.
+    ///
+    /// Same values as [`Expr::evaluate`] row by row, but columns are looked
+    /// up once and referenced rather than cloned per row. The column is
+    /// named after the referenced column for a plain column reference and
+    /// unnamed otherwise. Errors name the failing row (`at row N: ...`).
+    pub fn evaluate_batch(&self, batch: &RowBatch) -> Result<Column, String> {
+        self.evaluate_batch_with(batch, &EvalContext::default())
+    }
+
+    /// Like [`Expr::evaluate_batch`], with the run seed and stream position in `ctx`.
+    pub fn evaluate_batch_with(
+        &self,
+        batch: &RowBatch,
+        ctx: &EvalContext,
+    ) -> Result<Column, String> {
+        let rows = Rows::All(batch.num_rows());
+        let values = self.eval_rows(batch, rows, ctx).map_err(row_error)?;
+        Ok(Column {
+            name: match self {
+                Expr::Column(name) => name.clone(),
+                _ => String::new(),
+            },
+            values: values.into_vec(rows),
+        })
+    }
+
+    /// Selection vector of a predicate over `batch`: whether each row passes,
+    /// as [`Expr::evaluate_bool_with`] decides it.
+    ///
+    /// `AND` and `OR` evaluate their right side only on the rows the left
+    /// side leaves undecided, so a guard such as `b != 0 AND a / b > 1`
+    /// protects the rows it rejects. Errors name the failing row.
+    pub fn evaluate_selection(
+        &self,
+        batch: &RowBatch,
+        ctx: &EvalContext,
+    ) -> Result<Vec<bool>, String> {
+        self.select_rows(batch, Rows::All(batch.num_rows()), ctx)
+            .map_err(row_error)
+    }
+
+    /// Truth of the predicate at each of `rows`.
+    fn select_rows(
+        &self,
+        batch: &RowBatch,
+        rows: Rows<'_>,
+        ctx: &EvalContext,
+    ) -> Result<Vec<bool>, RowError> {
+        match self {
+            Expr::BinaryOp {
+                op: op @ (BinOp::And | BinOp::Or),
+                left,
+                right,
+            } => {
+                let mut out = left.select_rows(batch, rows, ctx)?;
+                // AND decides on false, OR on true; the rest go right.
+                let undecided: Vec<usize> = (0..rows.len())
+                    .filter(|&i| out[i] == (*op == BinOp::And))
+                    .collect();
+                if undecided.is_empty() {
+                    return Ok(out);
+                }
+                let batch_rows: Vec<usize> = undecided.iter().map(|&i| rows.row(i)).collect();
+                let decided = right.select_rows(batch, Rows::Some(&batch_rows), ctx)?;
+                for (i, pass) in undecided.into_iter().zip(decided) {
+                    out[i] = pass;
+                }
+                Ok(out)
+            }
+            _ => {
+                let values = self.eval_rows(batch, rows, ctx)?;
+                (0..rows.len())
+                    .map(|i| scalar_to_bool(values.get(i)).map_err(|e| (rows.row(i), e)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Values of the expression at each of `rows`.
+    fn eval_rows<'a>(
+        &'a self,
+        batch: &'a RowBatch,
+        rows: Rows<'a>,
+        ctx: &EvalContext,
+    ) -> Result<Values<'a>, RowError> {
+        let n = rows.len();
+        if n == 0 {
+            return Ok(Values::Owned(Vec::new()));
+        }
+        // Applies `f` at each row, naming the row of a failure.
+        let map = |f: &mut dyn FnMut(usize) -> Result<Scalar, String>| {
+            (0..n)
+                .map(|i| f(i).map_err(|e| (rows.row(i), e)))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Values::Owned)
+        };
+        match self {
+            Expr::Column(name) => {
+                let col = batch
+                    .columns
+                    .iter()
+                    .find(|c| &c.name == name)
+                    .ok_or_else(|| {
+                        let available: Vec<String> =
+                            batch.columns.iter().map(|c| c.name.clone()).collect();
+                        (
+                            rows.row(0),
+                            format!(
+                                "column '{}' not found. Available columns: {:?}",
+                                name, available
+                            ),
+                        )
+                    })?;
+                // Rows ascend, so the last is the largest.
+                let last = rows.row(n - 1);
+                if last >= col.values.len() {
+                    return Err((last, format!("row index {} out of bounds", last)));
+                }
+                Ok(Values::Column(&col.values, rows))
+            }
+            Expr::Literal(scalar) => Ok(Values::Const(scalar.clone())),
+            Expr::BinaryOp {
+                op: BinOp::And | BinOp::Or,
+                ..
+            } => Ok(Values::Owned(
+                self.select_rows(batch, rows, ctx)?
+                    .into_iter()
+                    .map(Scalar::Bool)
+                    .collect(),
+            )),
+            Expr::BinaryOp { op, left, right } => {
+                let left = left.eval_rows(batch, rows, ctx)?;
+                let right = right.eval_rows(batch, rows, ctx)?;
+                map(&mut |i| evaluate_binary_op_with(*op, left.get(i), right.get(i), ctx))
+            }
+            Expr::UnaryOp { op, arg } => {
+                let arg = arg.eval_rows(batch, rows, ctx)?;
+                map(&mut |i| evaluate_unary_op(*op, arg.get(i)))
+            }
+            Expr::Call { func, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval_rows(batch, rows, ctx))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut row_args = Vec::with_capacity(args.len());
+                map(&mut |i| {
+                    row_args.clear();
+                    row_args.extend(args.iter().map(|a| a.get(i)));
+                    evaluate_call(*func, &row_args, rows.row(i), ctx)
+                })
+            }
+            Expr::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let value = expr.eval_rows(batch, rows, ctx)?;
+                let low = low.eval_rows(batch, rows, ctx)?;
+                let high = high.eval_rows(batch, rows, ctx)?;
+                map(&mut |i| {
+                    let v = value.get(i);
+                    let inside =
+                        scalar_cmp(v, low.get(i)).is_ge() && scalar_cmp(v, high.get(i)).is_le();
+                    Ok(Scalar::Bool(inside != *negated))
+                })
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let value = expr.eval_rows(batch, rows, ctx)?;
+                let mut found = vec![false; n];
+                // Each item is only evaluated on the rows no earlier item matched.
+                let mut pending: Vec<usize> = (0..n).collect();
+                for item in list {
+                    if pending.is_empty() {
+                        break;
+                    }
+                    let batch_rows: Vec<usize> = pending.iter().map(|&i| rows.row(i)).collect();
+                    let item = item.eval_rows(batch, Rows::Some(&batch_rows), ctx)?;
+                    let mut k = 0;
+                    pending.retain(|&i| {
+                        let matched = scalar_eq(value.get(i), item.get(k));
+                        k += 1;
+                        found[i] |= matched;
+                        !matched
+                    });
+                }
+                Ok(Values::Owned(
+                    found
+                        .into_iter()
+                        .map(|f| Scalar::Bool(f != *negated))
+                        .collect(),
+                ))
+            }
+        }
+    }
+
+    /// Whether the value depends on the row's position in the input stream
+    /// (`rand()`), so it changes if rows are evaluated in other batches.
+    pub fn uses_row_position(&self) -> bool {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => false,
+            Expr::BinaryOp { left, right, .. } => {
+                left.uses_row_position() || right.uses_row_position()
+            }
+            Expr::UnaryOp { arg, .. } => arg.uses_row_position(),
+            Expr::Call { func, args } => {
+                *func == Func::Rand || args.iter().any(Expr::uses_row_position)
+            }
+            Expr::Between {
+                expr, low, high, ..
+            } => expr.uses_row_position() || low.uses_row_position() || high.uses_row_position(),
+            Expr::InList { expr, list, .. } => {
+                expr.uses_row_position() || list.iter().any(Expr::uses_row_position)
+            }
+        }
+    }
+
+    /// Type of the values the expression yields over rows of `schema`.
+    ///
+    /// Follows evaluation: comparisons, logic and predicates are `Boolean`;
+    /// arithmetic takes the wider numeric operand (`Int32` < `Int64` <
+    /// `Float32` < `Float64`) and `+` on two strings is `Utf8`. Dates and
+    /// timestamps shift by integers and subtract to `Int64`; decimals stay
+    /// `Decimal128` with integers and become `Float64` with floats. An
+    /// always-null expression is `Utf8`, as in [`Scalar::data_type`].
+    pub fn data_type(&self, schema: &Schema) -> Result<DataType, String> {
+        if let Some(name) = self
+            .referenced_columns()
+            .into_iter()
+            .find(|name| schema.index_of(name).is_none())
+        {
+            return Err(format!("column '{}' not found", name));
+        }
+        Ok(self.value_type(schema)?.unwrap_or(DataType::Utf8))
+    }
+
+    /// `None` for an expression that is always null.
+    fn value_type(&self, schema: &Schema) -> Result<Option<DataType>, String> {
+        Ok(match self {
+            Expr::Column(name) => schema
+                .index_of(name)
+                .map(|i| schema.fields[i].data_type.clone()),
+            Expr::Literal(Scalar::Null) => None,
+            Expr::Literal(value) => Some(value.data_type()),
+            Expr::BinaryOp {
+                op: op @ (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div),
+                left,
+                right,
+            } => arithmetic_type(*op, left.value_type(schema)?, right.value_type(schema)?)?,
+            Expr::Call { func, args } => match func {
+                Func::Rand => Some(DataType::Float64),
+                Func::Hash => Some(DataType::Int64),
+                Func::SampleHash => Some(DataType::Boolean),
+                Func::SafeDiv => arithmetic_type(
+                    BinOp::Div,
+                    args[0].value_type(schema)?,
+                    args[1].value_type(schema)?,
+                )?,
+                Func::NullIf | Func::Abs | Func::Round | Func::Floor | Func::Ceil => {
+                    args[0].value_type(schema)?
+                }
+                Func::Upper | Func::Lower | Func::Trim | Func::Substr | Func::Concat => {
+                    Some(DataType::Utf8)
+                }
+                Func::Coalesce => {
+                    let mut first = None;
+                    for arg in args {
+                        if let Some(t) = arg.value_type(schema)? {
+                            first = Some(t);
+                            break;
+                        }
+                    }
+                    first
+                }
+                Func::Cast => match &args[1] {
+                    Expr::Literal(Scalar::Str(ty)) => DataType::parse(ty),
+                    _ => None,
+                },
+                Func::DateTrunc => match args[1].value_type(schema)? {
+                    Some(DataType::Date64) => Some(DataType::Date64),
+                    Some(_) => Some(DataType::Timestamp),
+                    None => None,
+                },
+                Func::DateDiff => Some(DataType::Int64),
+            },
+            Expr::BinaryOp { .. }
+            | Expr::UnaryOp { .. }
+            | Expr::Between { .. }
+            | Expr::InList { .. } => Some(DataType::Boolean),
+        })
+    }
+
+    /// Names of the columns the expression reads, in order of first use.
+    pub fn referenced_columns(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.collect_columns(&mut out);
+        out
+    }
+
+    fn collect_columns(&self, out: &mut Vec<String>) {
+        match self {
+            Expr::Column(name) => {
+                if !out.contains(name) {
+                    out.push(name.clone());
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::BinaryOp { left, right, .. } => {
+                left.collect_columns(out);
+                right.collect_columns(out);
+            }
+            Expr::UnaryOp { arg, .. } => arg.collect_columns(out),
+            Expr::Call { args, .. } => args.iter().for_each(|a| a.collect_columns(out)),
+            Expr::Between {
+                expr, low, high, ..
+            } => {
+                expr.collect_columns(out);
+                low.collect_columns(out);
+                high.collect_columns(out);
+            }
+            Expr::InList { expr, list, .. } => {
+                expr.collect_columns(out);
+                list.iter().for_each(|e| e.collect_columns(out));
+            }
+        }
+    }
+}
+
+/// Output schema of a `map` list (see [`Expr::parse_list`]) over `input`.
+///
+/// Items apply in order, each seeing the columns earlier ones produced:
+/// `col AS name` renames in place, and `expr AS name` appends a nullable
+/// computed column. Either replaces a column already called `name`.
+/// Constraints are dropped, since renames may touch constrained columns.
+pub fn map_schema(items: &[(Expr, Option<String>)], input: &Schema) -> Result<Schema, String> {
+    let mut fields = input.fields.clone();
+    for (expr, alias) in items {
+        match (expr, alias) {
+            (Expr::Column(name), alias) => {
+                let idx = fields
+                    .iter()
+                    .position(|f| &f.name == name)
+                    .ok_or_else(|| format!("column '{}' not found", name))?;
+                if let Some(alias) = alias {
+                    fields[idx].name = alias.clone();
+                    let mut i = 0;
+                    fields.retain(|f| {
+                        i += 1;
+                        i - 1 == idx || &f.name != alias
+                    });
+                }
+            }
+            (expr, Some(alias)) => {
+                let data_type = expr.data_type(&Schema::new(fields.clone()))?;
+                fields.retain(|f| &f.name != alias);
+                fields.push(Field::new(alias.clone(), data_type, true));
+            }
+            (_, None) => return Err("computed map columns need an alias".into()),
+        }
+    }
+    Ok(Schema::new_with_stats(fields, input.stats.clone()))
+}
+
+/// Result type of arithmetic on operands of these types (`None`: always null).
+fn arithmetic_type(
+    op: BinOp,
+    left: Option<DataType>,
+    right: Option<DataType>,
+) -> Result<Option<DataType>, String> {
+    use DataType::*;
+    let rank = |t: &DataType| [Int32, Int64, Float32, Float64].iter().position(|n| n == t);
+    Ok(match (left, right) {
+        // Arithmetic on a null is null.
+        (None, t) | (t, None) => t,
+        (Some(Utf8), Some(Utf8)) if op == BinOp::Add => Some(Utf8),
+        (Some(t @ (Date64 | Timestamp)), Some(Int32 | Int64))
+            if matches!(op, BinOp::Add | BinOp::Sub) =>
+        {
+            Some(t)
+        }
+        (Some(Int32 | Int64), Some(t @ (Date64 | Timestamp))) if op == BinOp::Add => Some(t),
+        (Some(Date64 | Timestamp), Some(Date64 | Timestamp)) if op == BinOp::Sub => Some(Int64),
+        (Some(Decimal128), Some(Int32 | Int64 | Decimal128))
+        | (Some(Int32 | Int64), Some(Decimal128)) => Some(Decimal128),
+        (Some(Decimal128), Some(Float32 | Float64))
+        | (Some(Float32 | Float64), Some(Decimal128)) => Some(Float64),
+        (Some(l), Some(r)) => match (rank(&l), rank(&r)) {
+            (Some(a), Some(b)) => Some(if a >= b { l } else { r }),
+            _ => return Err(format!("unsupported arithmetic: {:?} {:?} {:?}", l, op, r)),
+        },
+    })
+}
+
+/// Cheap single-field predicate a reader can apply while parsing, before it
+/// builds rows. Semantics match the corresponding `Expr` comparison.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FieldPredicate {
+    /// `field == value`
+    Eq { field: String, value: Scalar },
+    /// The field is a string containing `needle`.
+    Contains { field: String, needle: String },
+}
+
+impl FieldPredicate {
+    pub fn field(&self) -> &str {
+        match self {
+            FieldPredicate::Eq { field, .. } | FieldPredicate::Contains { field, .. } => field,
+        }
+    }
+
+    /// Test a field value (missing fields should be passed as `Scalar::Null`).
+    pub fn matches(&self, value: &Scalar) -> bool {
+        match self {
+            FieldPredicate::Eq {
+                value: expected, ..
+            } => scalar_eq(value, expected),
+            FieldPredicate::Contains { needle, .. } => {
+                matches!(value, Scalar::Str(s) if s.contains(needle.as_str()))
+            }
+        }
     }
 }
 
+impl Expr {
+    /// Top-level `column == literal` conjuncts that a source may apply early.
+    ///
+    /// Rows failing any returned predicate would also fail `self`, so a reader
+    /// may drop them; the filter itself must still run on the rest.
+    pub fn pushdown_predicates(&self) -> Vec<FieldPredicate> {
+        let mut out = Vec::new();
+        self.collect_pushdown(&mut out);
+        out
+    }
+
+    fn collect_pushdown(&self, out: &mut Vec<FieldPredicate>) {
+        if let Expr::BinaryOp { op, left, right } = self {
+            match (op, left.as_ref(), right.as_ref()) {
+                (BinOp::And, l, r) => {
+                    l.collect_pushdown(out);
+                    r.collect_pushdown(out);
+                }
+                (BinOp::Eq, Expr::Column(field), Expr::Literal(value))
+                | (BinOp::Eq, Expr::Literal(value), Expr::Column(field)) => {
+                    out.push(FieldPredicate::Eq {
+                        field: field.clone(),
+                        value: value.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lexical token of an expression string.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Column, function or keyword name.
+    Ident(String),
+    /// Numeric literal as written, sign excluded and suffix included.
+    Number(String),
+    /// Quoted string, without its quotes.
+    Str(String),
+    /// Operator or punctuation.
+    Symbol(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(s) | Token::Number(s) => f.write_str(s),
+            Token::Str(s) => write!(f, "'{}'", s),
+            Token::Symbol(s) => f.write_str(s),
+        }
+    }
+}
+
+/// Two-character symbols come first so they win over their prefixes.
+const SYMBOLS: [&str; 18] = [
+    "==", "!=", "<>", "<=", ">=", "&&", "||", "=", "<", ">", "!", "+", "-", "*", "/", "(", ")", ",",
+];
+
+/// Binding powers: an operator binds its operands only against operators
+/// with a lower power. Comparisons share theirs with IS/BETWEEN/IN.
+const OR_BP: u8 = 1;
+const AND_BP: u8 = 2;
+const NOT_BP: u8 = 3;
+const COMPARE_BP: u8 = 4;
+const ADD_BP: u8 = 5;
+const MUL_BP: u8 = 6;
+const NEG_BP: u8 = 7;
+
+/// Part of a name or number: ASCII alphanumerics, `_`, `.`, and any
+/// non-ASCII byte (so names may be UTF-8).
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || !b.is_ascii()
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let bytes = s.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b.is_ascii_whitespace() {
+            i += 1;
+        } else if b == b'\'' || b == b'"' {
+            let len = s[i + 1..]
+                .find(b as char)
+                .ok_or_else(|| format!("unterminated string in '{}'", s))?;
+            tokens.push(Token::Str(s[i + 1..i + 1 + len].to_string()));
+            i += len + 2;
+        } else if is_word_byte(b) {
+            let start = i;
+            let number = b.is_ascii_digit() || b == b'.';
+            while i < bytes.len()
+                && (is_word_byte(bytes[i])
+                    || (number && is_exponent_sign(&bytes[start..], i - start)))
+            {
+                i += 1;
+            }
+            let word = s[start..i].to_string();
+            tokens.push(if number {
+                Token::Number(word)
+            } else {
+                Token::Ident(word)
+            });
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|sym| s[i..].starts_with(**sym))
+                .ok_or_else(|| {
+                    format!(
+                        "unexpected character '{}' in '{}'",
+                        s[i..].chars().next().unwrap_or_default(),
+                        s
+                    )
+                })?;
+            tokens.push(Token::Symbol(symbol));
+            i += symbol.len();
+        }
+    }
+    Ok(tokens)
+}
+
+/// Precedence-climbing parser over the tokens of `source`.
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn keyword_at(&self, offset: usize, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos + offset),
+            Some(Token::Ident(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.keyword_at(0, keyword);
+        self.pos += found as usize;
+        found
+    }
+
+    fn eat_symbol(&mut self, symbol: &'static str) -> bool {
+        let found = self.tokens.get(self.pos) == Some(&Token::Symbol(symbol));
+        self.pos += found as usize;
+        found
+    }
+
+    fn expect(&mut self, what: &str, found: bool) -> Result<(), String> {
+        if found {
+            return Ok(());
+        }
+        Err(match self.tokens.get(self.pos) {
+            Some(token) => format!("expected {}, found '{}' in '{}'", what, token, self.source),
+            None => format!("expected {} at end of '{}'", what, self.source),
+        })
+    }
+
+    /// The binary operator at the cursor and its binding power.
+    fn binary_op(&self) -> Option<(BinOp, u8)> {
+        let op = match self.tokens.get(self.pos)? {
+            Token::Ident(word) if word.eq_ignore_ascii_case("OR") => BinOp::Or,
+            Token::Ident(word) if word.eq_ignore_ascii_case("AND") => BinOp::And,
+            Token::Symbol(symbol) => BinOp::parse(symbol).ok()?,
+            _ => return None,
+        };
+        let bp = match op {
+            BinOp::Or => OR_BP,
+            BinOp::And => AND_BP,
+            BinOp::Add | BinOp::Sub => ADD_BP,
+            BinOp::Mul | BinOp::Div => MUL_BP,
+            _ => COMPARE_BP,
+        };
+        Some((op, bp))
+    }
+
+    /// An expression whose operators all bind at least as tightly as `min_bp`.
+    fn expr(&mut self, min_bp: u8) -> Result<Expr, String> {
+        let mut left = self.prefix()?;
+        loop {
+            if let Some((op, bp)) = self.binary_op() {
+                if bp < min_bp {
+                    break;
+                }
+                self.pos += 1;
+                let right = self.expr(bp + 1)?;
+                left = Expr::BinaryOp {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                };
+            } else if min_bp <= COMPARE_BP {
+                match self.predicate(left)? {
+                    Ok(expr) => left = expr,
+                    Err(expr) => return Ok(expr),
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    /// `IS [NOT] NULL`, `[NOT] BETWEEN` or `[NOT] IN` applied to `left`;
+    /// `Err(left)` when none follows.
+    fn predicate(&mut self, left: Expr) -> Result<Result<Expr, Expr>, String> {
+        if self.eat_keyword("IS") {
+            let op = if self.eat_keyword("NOT") {
+                UnaryOp::IsNotNull
+            } else {
+                UnaryOp::IsNull
+            };
+            let found = self.eat_keyword("NULL");
+            self.expect("NULL after IS", found)?;
+            return Ok(Ok(Expr::UnaryOp {
+                op,
+                arg: Box::new(left),
+            }));
+        }
+        let negated = self.keyword_at(0, "NOT")
+            && (self.keyword_at(1, "BETWEEN") || self.keyword_at(1, "IN"));
+        let offset = negated as usize;
+        if self.keyword_at(offset, "BETWEEN") {
+            self.pos += offset + 1;
+            // The bounds stop short of comparisons, so the AND is not logical.
+            let low = self.expr(COMPARE_BP + 1)?;
+            let found = self.eat_keyword("AND");
+            self.expect("AND in BETWEEN", found)?;
+            let high = self.expr(COMPARE_BP + 1)?;
+            return Ok(Ok(Expr::Between {
+                expr: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+                negated,
+            }));
+        }
+        if self.keyword_at(offset, "IN") {
+            self.pos += offset + 1;
+            let found = self.eat_symbol("(");
+            self.expect("a parenthesized list after IN", found)?;
+            if self.eat_symbol(")") {
+                return Err(format!("empty IN list in '{}'", self.source));
+            }
+            let list = self.list()?;
+            return Ok(Ok(Expr::InList {
+                expr: Box::new(left),
+                list,
+                negated,
+            }));
+        }
+        Ok(Err(left))
+    }
+
+    /// Comma-separated expressions up to and including the closing `)`.
+    fn list(&mut self) -> Result<Vec<Expr>, String> {
+        let mut items = vec![self.expr(0)?];
+        while self.eat_symbol(",") {
+            items.push(self.expr(0)?);
+        }
+        let found = self.eat_symbol(")");
+        self.expect("')'", found)?;
+        Ok(items)
+    }
+
+    /// A prefix operator and its operand, a parenthesized group, or an atom.
+    fn prefix(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(format!("unexpected end of '{}'", self.source));
+        };
+        self.pos += 1;
+        match token {
+            Token::Symbol("(") => {
+                let expr = self.expr(0)?;
+                let found = self.eat_symbol(")");
+                self.expect("')'", found)?;
+                Ok(expr)
+            }
+            Token::Symbol("!") => self.not(),
+            Token::Ident(word) if word.eq_ignore_ascii_case("NOT") => self.not(),
+            Token::Symbol(sign @ ("-" | "+")) => {
+                // A signed number is one literal, so `-2147483648` is an I32.
+                if let Some(Token::Number(digits)) = self.tokens.get(self.pos) {
+                    let literal = parse_number(&format!("{}{}", sign, digits))?;
+                    self.pos += 1;
+                    return Ok(Expr::Literal(literal));
+                }
+                let arg = self.expr(NEG_BP)?;
+                Ok(match sign {
+                    "-" => Expr::BinaryOp {
+                        op: BinOp::Sub,
+                        left: Box::new(Expr::Literal(Scalar::I32(0))),
+                        right: Box::new(arg),
+                    },
+                    _ => arg,
+                })
+            }
+            Token::Number(digits) => parse_number(&digits).map(Expr::Literal),
+            Token::Str(s) => Ok(Expr::Literal(Scalar::Str(s))),
+            Token::Ident(name) => self.name(name),
+            Token::Symbol(symbol) => Err(format!("unexpected '{}' in '{}'", symbol, self.source)),
+        }
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        Ok(Expr::UnaryOp {
+            op: UnaryOp::Not,
+            arg: Box::new(self.expr(NOT_BP)?),
+        })
+    }
+
+    /// `AS name` after a list item, if present.
+    fn alias(&mut self) -> Result<Option<String>, String> {
+        if !self.eat_keyword("AS") {
+            return Ok(None);
+        }
+        if let Some(Token::Ident(name) | Token::Str(name)) = self.tokens.get(self.pos) {
+            let name = name.clone();
+            self.pos += 1;
+            return Ok(Some(name));
+        }
+        self.expect("a name after AS", false).map(|()| None)
+    }
+
+    /// A function call, `date '...'`, `true`/`false`, or a column.
+    fn name(&mut self, name: String) -> Result<Expr, String> {
+        if self.eat_symbol("(") {
+            let func = Func::parse(&name)?;
+            let mut args = if self.eat_symbol(")") {
+                Vec::new()
+            } else {
+                self.list()?
+            };
+            let (min, max) = func.arity();
+            if args.len() < min || args.len() > max {
+                let takes = match (min, max) {
+                    (min, max) if min == max => min.to_string(),
+                    (min, usize::MAX) => format!("at least {}", min),
+                    (min, max) => format!("{} to {}", min, max),
+                };
+                return Err(format!(
+                    "{}() takes {} argument(s), got {}",
+                    name,
+                    takes,
+                    args.len()
+                ));
+            }
+            if func == Func::Cast {
+                // The type may be written bare: `cast(x, Int64)`.
+                let target = match &args[1] {
+                    Expr::Column(ty) | Expr::Literal(Scalar::Str(ty)) => DataType::parse(ty),
+                    _ => None,
+                };
+                let Some(target) = target else {
+                    return Err(format!(
+                        "cast() takes a type name as its second argument in '{}'",
+                        self.source
+                    ));
+                };
+                args[1] = Expr::Literal(Scalar::Str(format!("{:?}", target)));
+            }
+            return Ok(Expr::Call { func, args });
+        }
+        if name.eq_ignore_ascii_case("date") {
+            if let Some(Token::Str(date)) = self.tokens.get(self.pos) {
+                let date = parse_date(date)?;
+                self.pos += 1;
+                return Ok(Expr::Literal(Scalar::Str(date)));
+            }
+        }
+        if let Ok(b) = name.parse::<bool>() {
+            return Ok(Expr::Literal(Scalar::Bool(b)));
+        }
+        if ["AND", "OR", "IS", "BETWEEN", "IN", "AS"]
+            .iter()
+            .any(|k| name.eq_ignore_ascii_case(k))
+        {
+            return Err(format!("unexpected '{}' in '{}'", name, self.source));
+        }
+        Ok(Expr::Column(name))
+    }
+}
+
+fn is_exponent_sign(bytes: &[u8], i: usize) -> bool {
+    if !matches!(bytes[i], b'+' | b'-') || i == 0 || !matches!(bytes[i - 1], b'e' | b'E') {
+        return false;
+    }
+    let start = bytes[..i]
+        .iter()
+        .rposition(|b| !(b.is_ascii_alphanumeric() || *b == b'.' || *b == b'_'))
+        .map_or(0, |p| p + 1);
+    let token = &bytes[start..i];
+    let mantissa = &token[..token.len() - 1];
+    !mantissa.is_empty()
+        && (mantissa[0].is_ascii_digit() || mantissa[0] == b'.')
+        && mantissa.iter().all(|b| b.is_ascii_digit() || *b == b'.')
+}
+
 /// Parse a literal string into a Scalar value.
-fn parse_literal(literal: &str) -> Result<Scalar, String> {
+///
+/// Type selection is fixed by the literal's spelling, never by context:
+/// - `true`/`false` are `Bool`; `'...'` or `"..."` are `Str`.
+/// - `date 'YYYY-MM-DD'` is a calendar-checked `Str` in canonical form, so it
+///   compares correctly with ISO date columns.
+/// - A suffix (`100i64`, `2.5f32`, `0xffi64`, `1e9i64`) picks the type; the
+///   value must fit it exactly.
+/// - Unsuffixed integers (decimal or `0x` hex) are `I32` if they fit, else
+///   `I64`; larger values are an error.
+/// - Unsuffixed decimals and scientific notation (`1e9`, `2.5E-3`) are `F32`
+///   if the value is exactly representable as `f32`, else `F64`.
+pub fn parse_literal(literal: &str) -> Result<Scalar, String> {
     let trimmed = literal.trim();
 
     // Try boolean
@@ -246,37 +1315,164 @@ fn parse_literal(literal: &str) -> Result<Scalar, String> {
         return Ok(Scalar::Bool(b));
     }
 
-    // Try integer (i32 first, then i64)
-    if let Ok(i) = trimmed.parse::<i32>() {
-        return Ok(Scalar::I32(i));
+    if let Some(date) = date_literal(trimmed) {
+        return parse_date(date).map(Scalar::Str);
+    }
+
+    // Try string (remove quotes if present)
+    if let Some(unquoted) = unquote(trimmed) {
+        return Ok(Scalar::Str(unquoted.to_string()));
+    }
+
+    if looks_numeric(trimmed) {
+        return parse_number(trimmed);
+    }
+
+    Err(format!("cannot parse '{}' as literal", literal))
+}
+
+fn unquote(s: &str) -> Option<&str> {
+    let quoted = s.len() >= 2
+        && ((s.starts_with('"') && s.ends_with('"')) || (s.starts_with('\'') && s.ends_with('\'')));
+    quoted.then(|| &s[1..s.len() - 1])
+}
+
+/// The quoted part of `date '...'`, if `s` has that shape.
+fn date_literal(s: &str) -> Option<&str> {
+    let keyword = s.get(..4)?;
+    if !keyword.eq_ignore_ascii_case("date") {
+        return None;
     }
-    if let Ok(i) = trimmed.parse::<i64>() {
-        return Ok(Scalar::I64(i));
+    unquote(s[4..].trim_start()).filter(|_| s[4..].starts_with(char::is_whitespace))
+}
+
+fn parse_date(date: &str) -> Result<String, String> {
+    temporal::parse_date(date)
+        .map(temporal::format_date)
+        .ok_or_else(|| format!("invalid date literal '{}' (expected YYYY-MM-DD)", date))
+}
+
+/// Starts like a number (optional sign, then a digit or `.digit`). Such atoms
+/// are literals or errors, never column names.
+fn looks_numeric(s: &str) -> bool {
+    let body = s.strip_prefix(['-', '+']).unwrap_or(s);
+    let body = body.strip_prefix('.').unwrap_or(body);
+    body.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn parse_number(s: &str) -> Result<Scalar, String> {
+    let out_of_range = |ty: &str| format!("literal '{}' out of range for {}", s, ty);
+    let (negative, body) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    // Hex digits include 'f', so hex literals only take integer suffixes.
+    if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        let (digits, suffix) = split_suffix(hex, &["i32", "i64"]);
+        let magnitude =
+            u64::from_str_radix(digits, 16).map_err(|_| format!("invalid hex literal '{}'", s))?;
+        let value = if negative {
+            0i128 - magnitude as i128
+        } else {
+            magnitude as i128
+        };
+        return integer_scalar(value, suffix).ok_or_else(|| out_of_range(suffix.unwrap_or("i64")));
     }
 
-    // Try float (f32 first, then f64)
-    if let Ok(f) = trimmed.parse::<f32>() {
-        return Ok(Scalar::F32(f));
+    let (digits, suffix) = split_suffix(body, &["i32", "i64", "f32", "f64"]);
+    let text = if negative {
+        format!("-{}", digits)
+    } else {
+        digits.to_string()
+    };
+    let is_integer = digits.bytes().all(|b| b.is_ascii_digit());
+    match suffix {
+        None if is_integer => {
+            let value = text.parse::<i128>().map_err(|_| out_of_range("i64"))?;
+            integer_scalar(value, None).ok_or_else(|| out_of_range("i64"))
+        }
+        Some(ty @ ("i32" | "i64")) => {
+            let value = if is_integer {
+                text.parse::<i128>().map_err(|_| out_of_range(ty))?
+            } else {
+                // `1e9i64`: scientific notation is fine if the value is integral.
+                let f = parse_float(&text, s)?;
+                if f.fract() != 0.0 || f.abs() > i64::MAX as f64 {
+                    return Err(format!("literal '{}' is not an integral {}", s, ty));
+                }
+                f as i128
+            };
+            integer_scalar(value, Some(ty)).ok_or_else(|| out_of_range(ty))
+        }
+        Some("f32") => {
+            let f = parse_float(&text, s)?;
+            let v = f as f32;
+            if v.is_infinite() {
+                return Err(out_of_range("f32"));
+            }
+            Ok(Scalar::F32(v))
+        }
+        _ => {
+            let f = parse_float(&text, s)?;
+            if f.is_infinite() {
+                return Err(out_of_range("f64"));
+            }
+            if suffix.is_none() && (f as f32) as f64 == f {
+                Ok(Scalar::F32(f as f32))
+            } else {
+                Ok(Scalar::F64(f))
+            }
+        }
     }
-    if let Ok(f) = trimmed.parse::<f64>() {
-        return Ok(Scalar::F64(f));
+}
+
+fn split_suffix<'a>(s: &'a str, suffixes: &[&'static str]) -> (&'a str, Option<&'static str>) {
+    for &suffix in suffixes {
+        if let Some(digits) = s.strip_suffix(suffix) {
+            return (digits, Some(suffix));
+        }
     }
+    (s, None)
+}
 
-    // Try string (remove quotes if present)
-    if (trimmed.starts_with('"') && trimmed.ends_with('"'))
-        || (trimmed.starts_with('\'') && trimmed.ends_with('\''))
-    {
-        let unquoted = &trimmed[1..trimmed.len() - 1];
-        return Ok(Scalar::Str(unquoted.to_string()));
+/// `I32`/`I64` for `value` as the suffix (or, without one, the smallest fit) asks.
+fn integer_scalar(value: i128, suffix: Option<&str>) -> Option<Scalar> {
+    match suffix {
+        Some("i32") => i32::try_from(value).ok().map(Scalar::I32),
+        Some(_) => i64::try_from(value).ok().map(Scalar::I64),
+        None => i32::try_from(value)
+            .map(Scalar::I32)
+            .ok()
+            .or_else(|| i64::try_from(value).ok().map(Scalar::I64)),
     }
+}
 
-    Err(format!("cannot parse '{}' as literal", literal))
+fn parse_float(text: &str, literal: &str) -> Result<f64, String> {
+    // Rust also accepts "inf"/"nan"; literals are plain digits only.
+    let plain = text
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+'));
+    match text.parse::<f64>() {
+        Ok(f) if plain => Ok(f),
+        _ => Err(format!("invalid numeric literal '{}'", literal)),
+    }
 }
 
 /// Evaluate a binary operation.
 fn evaluate_binary_op(op: BinOp, left: &Scalar, right: &Scalar) -> Result<Scalar, String> {
     use Scalar::*;
 
+    // Arithmetic on a null is null, so `a / nullif(b, 0)` skips zero divisors.
+    if matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div)
+        && (matches!(left, Null) || matches!(right, Null))
+    {
+        return Ok(Null);
+    }
+    if let Some(result) = evaluate_typed_arithmetic(op, left, right) {
+        return result;
+    }
+
     match op {
         BinOp::Eq => Ok(Scalar::Bool(scalar_eq(left, right))),
         BinOp::Ne => Ok(Scalar::Bool(!scalar_eq(left, right))),
@@ -477,6 +1673,284 @@ fn evaluate_binary_op(op: BinOp, left: &Scalar, right: &Scalar) -> Result<Scalar
     }
 }
 
+/// Arithmetic on dates, timestamps and decimals; `None` when neither
+/// operand is one, or for a pair only the numeric rules cover.
+///
+/// - date ± integer days is a date; date − date is `I64` days.
+/// - timestamp ± integer microseconds is a timestamp; timestamp − timestamp
+///   (or date) is `I64` microseconds.
+/// - decimal with decimal or integer is exact (see [`crate::decimal`]);
+///   with a float it is `F64`.
+fn evaluate_typed_arithmetic(
+    op: BinOp,
+    left: &Scalar,
+    right: &Scalar,
+) -> Option<Result<Scalar, String>> {
+    use Scalar::*;
+    let int = |s: &Scalar| match s {
+        I32(i) => Some(*i as i64),
+        I64(i) => Some(*i),
+        _ => None,
+    };
+    let overflow = || {
+        Err(format!(
+            "arithmetic overflow: {:?} {:?} {:?}",
+            left, op, right
+        ))
+    };
+    let micros = |s: &Scalar| match s {
+        Date64(ms) => Some(temporal::date_to_timestamp(*ms)),
+        Timestamp(us) => Some(*us),
+        _ => None,
+    };
+
+    let result = match (op, left, right) {
+        (BinOp::Add | BinOp::Sub, Date64(ms), n) | (BinOp::Add, n, Date64(ms))
+            if int(n).is_some() =>
+        {
+            let delta = int(n)?.checked_mul(temporal::MS_PER_DAY);
+            let sum = match op {
+                BinOp::Add => delta.and_then(|d| ms.checked_add(d)),
+                _ => delta.and_then(|d| ms.checked_sub(d)),
+            };
+            sum.map_or_else(overflow, |ms| Ok(Date64(ms)))
+        }
+        (BinOp::Sub, Date64(a), Date64(b)) => Ok(I64(
+            a.div_euclid(temporal::MS_PER_DAY) - b.div_euclid(temporal::MS_PER_DAY)
+        )),
+        (BinOp::Add | BinOp::Sub, Timestamp(us), n) | (BinOp::Add, n, Timestamp(us))
+            if int(n).is_some() =>
+        {
+            let sum = match op {
+                BinOp::Add => us.checked_add(int(n)?),
+                _ => us.checked_sub(int(n)?),
+            };
+            sum.map_or_else(overflow, |us| Ok(Timestamp(us)))
+        }
+        (BinOp::Sub, Timestamp(_), Date64(_) | Timestamp(_))
+        | (BinOp::Sub, Date64(_), Timestamp(_)) => micros(left)?
+            .checked_sub(micros(right)?)
+            .map_or_else(overflow, |us| Ok(I64(us))),
+        (_, Decimal128(..), I32(_) | I64(_) | Decimal128(..))
+        | (_, I32(_) | I64(_), Decimal128(..)) => {
+            let as_decimal = |s: &Scalar| match s {
+                Decimal128(v, scale) => Some((*v, *scale)),
+                other => int(other).map(|i| (i as i128, 0)),
+            };
+            let ((a, a_scale), (b, b_scale)) = (as_decimal(left)?, as_decimal(right)?);
+            let result = match op {
+                BinOp::Add => decimal::add(a, a_scale, b, b_scale),
+                BinOp::Sub => decimal::sub(a, a_scale, b, b_scale),
+                BinOp::Mul => decimal::mul(a, a_scale, b, b_scale),
+                BinOp::Div if b == 0 => return Some(Err("division by zero".to_string())),
+                BinOp::Div => decimal::div(a, a_scale, b, b_scale),
+                _ => return None,
+            };
+            result.map_or_else(overflow, |(v, scale)| Ok(Decimal128(v, scale)))
+        }
+        (_, Decimal128(v, scale), F32(_) | F64(_)) => {
+            evaluate_binary_op(op, &F64(decimal::to_f64(*v, *scale)), right)
+        }
+        (_, F32(_) | F64(_), Decimal128(v, scale)) => {
+            evaluate_binary_op(op, left, &F64(decimal::to_f64(*v, *scale)))
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// A row that failed batch evaluation and why.
+type RowError = (usize, String);
+
+fn row_error((row, e): RowError) -> String {
+    format!("at row {}: {}", row, e)
+}
+
+/// The batch rows a batch evaluation covers: all of them, or the ascending
+/// subset an `AND`, `OR` or `IN` left undecided.
+#[derive(Debug, Clone, Copy)]
+enum Rows<'a> {
+    All(usize),
+    Some(&'a [usize]),
+}
+
+impl Rows<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Rows::All(n) => *n,
+            Rows::Some(rows) => rows.len(),
+        }
+    }
+
+    /// Batch row of the `i`th evaluated row.
+    fn row(&self, i: usize) -> usize {
+        match self {
+            Rows::All(_) => i,
+            Rows::Some(rows) => rows[i],
+        }
+    }
+}
+
+/// An expression's values at the evaluated rows.
+enum Values<'a> {
+    /// One value for every row (literals).
+    Const(Scalar),
+    /// A batch column, read in place at the evaluated rows.
+    Column(&'a [Scalar], Rows<'a>),
+    Owned(Vec<Scalar>),
+}
+
+impl Values<'_> {
+    /// Value at the `i`th evaluated row.
+    fn get(&self, i: usize) -> &Scalar {
+        match self {
+            Values::Const(v) => v,
+            Values::Column(values, rows) => &values[rows.row(i)],
+            Values::Owned(values) => &values[i],
+        }
+    }
+
+    fn into_vec(self, rows: Rows<'_>) -> Vec<Scalar> {
+        match self {
+            Values::Const(v) => vec![v; rows.len()],
+            Values::Column(values, Rows::All(n)) => values[..n].to_vec(),
+            Values::Column(values, Rows::Some(rows)) => {
+                rows.iter().map(|&r| values[r].clone()).collect()
+            }
+            Values::Owned(values) => values,
+        }
+    }
+}
+
+/// Salt mixed into the seed for `rand()`, so it is independent of `hash(col)`.
+const RAND_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Evaluate a function call on already-evaluated arguments.
+fn evaluate_call(
+    func: Func,
+    args: &[&Scalar],
+    row_idx: usize,
+    ctx: &EvalContext,
+) -> Result<Scalar, String> {
+    // xxHash64 over the key encoding: stable across versions and platforms.
+    let hash = |seed: u64, v: &Scalar| {
+        PartitionHasher::new(PartitionHashKind::Xxhash64, seed).hash_values(&[v])
+    };
+    match func {
+        Func::Rand => {
+            let position = Scalar::I64((ctx.row_base + row_idx as u64) as i64);
+            Ok(Scalar::F64(unit_interval(hash(
+                ctx.seed ^ RAND_SALT,
+                &position,
+            ))))
+        }
+        Func::Hash => match args[0] {
+            Scalar::Null => Ok(Scalar::Null),
+            v => Ok(Scalar::I64(hash(ctx.seed, v) as i64)),
+        },
+        Func::SampleHash => {
+            let fraction = match args[1] {
+                Scalar::F32(f) => *f as f64,
+                Scalar::F64(f) => *f,
+                Scalar::I32(i) => *i as f64,
+                Scalar::I64(i) => *i as f64,
+                other => {
+                    return Err(format!(
+                        "sample_hash fraction must be numeric, got {:?}",
+                        other
+                    ))
+                }
+            };
+            Ok(Scalar::Bool(match args[0] {
+                Scalar::Null => false,
+                v => unit_interval(hash(ctx.seed, v)) < fraction,
+            }))
+        }
+        Func::SafeDiv => match (args[0], args[1]) {
+            (a, b) if divides_by_zero(a, b) => Ok(Scalar::Null),
+            (a, b) => evaluate_binary_op(BinOp::Div, a, b),
+        },
+        Func::NullIf => Ok(if scalar_eq(args[0], args[1]) {
+            Scalar::Null
+        } else {
+            args[0].clone()
+        }),
+        Func::Upper => functions::map_text("upper", args[0], str::to_uppercase),
+        Func::Lower => functions::map_text("lower", args[0], str::to_lowercase),
+        Func::Trim => functions::map_text("trim", args[0], |s| s.trim().to_string()),
+        Func::Substr => functions::substr(args),
+        Func::Concat => Ok(functions::concat(args)),
+        Func::Abs => functions::abs(args[0]),
+        Func::Round => functions::round(args),
+        Func::Floor => functions::floor_ceil(args[0], false),
+        Func::Ceil => functions::floor_ceil(args[0], true),
+        Func::Coalesce => Ok(functions::coalesce(args)),
+        Func::Cast => match args[1] {
+            Scalar::Str(ty) => match DataType::parse(ty) {
+                Some(target) => functions::cast(args[0], &target),
+                None => Err(format!("cast() to unknown type '{}'", ty)),
+            },
+            other => Err(format!("cast() to {:?}, not a type name", other)),
+        },
+        Func::DateTrunc => functions::date_trunc(args),
+        Func::DateDiff => functions::date_diff(args),
+    }
+}
+
+/// A binary operation, with a division by zero resolved by the policy.
+fn evaluate_binary_op_with(
+    op: BinOp,
+    left: &Scalar,
+    right: &Scalar,
+    ctx: &EvalContext,
+) -> Result<Scalar, String> {
+    if op == BinOp::Div && divides_by_zero(left, right) {
+        match ctx.arith_errors {
+            ArithErrorPolicy::Error => {}
+            ArithErrorPolicy::Null => return Ok(Scalar::Null),
+            ArithErrorPolicy::Default(x) => return Ok(quotient_of_type(x, left, right)),
+        }
+    }
+    evaluate_binary_op(op, left, right)
+}
+
+/// Whether `left / right` is a numeric division by zero.
+fn divides_by_zero(left: &Scalar, right: &Scalar) -> bool {
+    use Scalar::*;
+    matches!(left, I32(_) | I64(_) | F32(_) | F64(_) | Decimal128(..))
+        && match right {
+            I32(b) => *b == 0,
+            I64(b) => *b == 0,
+            F32(b) => *b == 0.0,
+            F64(b) => *b == 0.0,
+            Decimal128(b, _) => *b == 0,
+            _ => false,
+        }
+}
+
+/// `x` in the type `left / right` yields.
+fn quotient_of_type(x: f64, left: &Scalar, right: &Scalar) -> Scalar {
+    use Scalar::*;
+    match (left, right) {
+        (Decimal128(..), I32(_) | I64(_) | Decimal128(..)) | (I32(_) | I64(_), Decimal128(..)) => {
+            let scale = match left {
+                Decimal128(_, scale) => (*scale).max(decimal::DIV_SCALE),
+                _ => decimal::DIV_SCALE,
+            };
+            Decimal128((x * 10f64.powi(scale as i32)).round() as i128, scale)
+        }
+        (I32(_), I32(_)) => I32(x as i32),
+        (I32(_) | I64(_), I32(_) | I64(_)) => I64(x as i64),
+        (I32(_) | I64(_) | F32(_), I32(_) | I64(_) | F32(_)) => F32(x as f32),
+        _ => F64(x),
+    }
+}
+
+/// Map a 64-bit hash to a float in `[0, 1)` using its top 53 bits.
+fn unit_interval(h: u64) -> f64 {
+    (h >> 11) as f64 / (1u64 << 53) as f64
+}
+
 /// Evaluate a unary operation.
 fn evaluate_unary_op(op: UnaryOp, arg: &Scalar) -> Result<Scalar, String> {
     match op {
@@ -517,7 +1991,7 @@ fn scalar_eq(a: &Scalar, b: &Scalar) -> bool {
         (F64(x), I64(y)) => (x - (*y as f64)).abs() < f64::EPSILON,
         (Str(x), Str(y)) => x == y,
         (Bin(x), Bin(y)) => x == y,
-        _ => false,
+        _ => typed_cmp(a, b).is_some_and(|o| o.is_eq()),
     }
 }
 
@@ -552,12 +2026,12 @@ fn scalar_cmp(a: &Scalar, b: &Scalar) -> std::cmp::Ordering {
         (F64(x), I64(y)) => x.partial_cmp(&(*y as f64)).unwrap_or(Ordering::Equal),
         (Str(x), Str(y)) => x.cmp(y),
         (Bin(x), Bin(y)) => x.cmp(y),
-        _ => {
+        _ => typed_cmp(a, b).unwrap_or_else(|| {
             // Mixed types: compare by type order
             let a_order = scalar_type_order(a);
             let b_order = scalar_type_order(b);
             a_order.cmp(&b_order)
-        }
+        }),
     }
 }
 
@@ -573,6 +2047,9 @@ fn scalar_type_order(s: &Scalar) -> u8 {
         F64(_) => 5,
         Str(_) => 6,
         Bin(_) => 7,
+        Date64(_) => 8,
+        Timestamp(_) => 9,
+        Decimal128(..) => 10,
     }
 }
 
@@ -588,5 +2065,7 @@ fn scalar_to_bool(scalar: &Scalar) -> Result<bool, String> {
         F64(f) => Ok(*f != 0.0),
         Str(s) => Ok(!s.is_empty()),
         Bin(b) => Ok(!b.is_empty()),
+        Decimal128(v, _) => Ok(*v != 0),
+        Date64(_) | Timestamp(_) => Err(format!("expected a boolean, got {}", scalar)),
     }
 }
@@ -0,0 +1,362 @@
+//! The string, math, conversion and date functions of [`crate::expr::Func`].
+//!
+//! Each is null when an argument is null, except `concat`, which skips
+//! nulls, and `coalesce`, which picks the first value that is not null.
+//! Arguments of the wrong type are errors naming the function.
+
+use crate::schema::DataType;
+use crate::types::Scalar;
+use crate::{decimal, temporal};
+
+fn text<'a>(func: &str, value: &'a Scalar) -> Result<&'a str, String> {
+    match value {
+        Scalar::Str(s) => Ok(s),
+        other => Err(format!("{}() expects a string, got {:?}", func, other)),
+    }
+}
+
+fn integer(func: &str, value: &Scalar) -> Result<i64, String> {
+    match value {
+        Scalar::I32(i) => Ok(*i as i64),
+        Scalar::I64(i) => Ok(*i),
+        other => Err(format!("{}() expects an integer, got {:?}", func, other)),
+    }
+}
+
+fn is_null(args: &[&Scalar]) -> bool {
+    args.iter().any(|a| matches!(a, Scalar::Null))
+}
+
+/// `upper(s)`, `lower(s)` and `trim(s)`.
+pub(crate) fn map_text(
+    func: &str,
+    value: &Scalar,
+    f: fn(&str) -> String,
+) -> Result<Scalar, String> {
+    if is_null(&[value]) {
+        return Ok(Scalar::Null);
+    }
+    Ok(Scalar::Str(f(text(func, value)?)))
+}
+
+/// `substr(s, start[, len])`: `len` characters (or the rest) from the 1-based
+/// `start`. Positions before the first character count toward `len`, as in
+/// SQL: `substr('abc', 0, 2)` is `'a'`.
+pub(crate) fn substr(args: &[&Scalar]) -> Result<Scalar, String> {
+    if is_null(args) {
+        return Ok(Scalar::Null);
+    }
+    let s = text("substr", args[0])?;
+    let start = integer("substr", args[1])?.saturating_sub(1);
+    let end = match args.get(2) {
+        Some(len) => match integer("substr", len)? {
+            len if len < 0 => return Err(format!("substr() length {} is negative", len)),
+            len => start.saturating_add(len),
+        },
+        None => i64::MAX,
+    };
+    let from = start.max(0);
+    let count = end.saturating_sub(from).max(0);
+    Ok(Scalar::Str(
+        s.chars().skip(from as usize).take(count as usize).collect(),
+    ))
+}
+
+/// `concat(a, ...)`: the text of each argument that is not null.
+pub(crate) fn concat(args: &[&Scalar]) -> Scalar {
+    Scalar::Str(
+        args.iter()
+            .filter(|a| !matches!(a, Scalar::Null))
+            .map(|a| a.to_string())
+            .collect(),
+    )
+}
+
+/// `coalesce(a, ...)`: the first argument that is not null.
+pub(crate) fn coalesce(args: &[&Scalar]) -> Scalar {
+    args.iter()
+        .find(|a| !matches!(a, Scalar::Null))
+        .map_or(Scalar::Null, |a| (*a).clone())
+}
+
+/// `abs(x)`, in the type of `x`.
+pub(crate) fn abs(value: &Scalar) -> Result<Scalar, String> {
+    let overflow = || format!("abs() overflow: {:?}", value);
+    Ok(match value {
+        Scalar::Null => Scalar::Null,
+        Scalar::I32(i) => Scalar::I32(i.checked_abs().ok_or_else(overflow)?),
+        Scalar::I64(i) => Scalar::I64(i.checked_abs().ok_or_else(overflow)?),
+        Scalar::F32(f) => Scalar::F32(f.abs()),
+        Scalar::F64(f) => Scalar::F64(f.abs()),
+        Scalar::Decimal128(v, scale) => {
+            Scalar::Decimal128(v.checked_abs().ok_or_else(overflow)?, *scale)
+        }
+        other => return Err(format!("abs() expects a number, got {:?}", other)),
+    })
+}
+
+/// `round(x[, digits])`: half away from zero to `digits` fraction digits
+/// (default 0; negative rounds to tens, hundreds, ...), in the type of `x`.
+/// A decimal keeps at most `digits` fraction digits.
+pub(crate) fn round(args: &[&Scalar]) -> Result<Scalar, String> {
+    if is_null(args) {
+        return Ok(Scalar::Null);
+    }
+    let digits = match args.get(1) {
+        Some(d) => integer("round", d)?.clamp(-38, 38) as i32,
+        None => 0,
+    };
+    // An integer rounded to a multiple of 10^-digits.
+    let round_int = |v: i128| -> Option<i128> {
+        if digits >= 0 {
+            return Some(v);
+        }
+        match 10i128.checked_pow(digits.unsigned_abs()) {
+            Some(m) => decimal::div_round(v, m).checked_mul(m),
+            None => Some(0),
+        }
+    };
+    let round_float = |f: f64| {
+        let m = 10f64.powi(digits);
+        let rounded = (f * m).round() / m;
+        if rounded.is_finite() {
+            rounded
+        } else {
+            f
+        }
+    };
+    let overflow = || format!("round() overflow: {:?}", args[0]);
+    Ok(match args[0] {
+        Scalar::I32(i) => Scalar::I32(
+            round_int(*i as i128)
+                .and_then(|v| i32::try_from(v).ok())
+                .ok_or_else(overflow)?,
+        ),
+        Scalar::I64(i) => Scalar::I64(
+            round_int(*i as i128)
+                .and_then(|v| i64::try_from(v).ok())
+                .ok_or_else(overflow)?,
+        ),
+        Scalar::F32(f) => Scalar::F32(round_float(*f as f64) as f32),
+        Scalar::F64(f) => Scalar::F64(round_float(*f)),
+        Scalar::Decimal128(v, scale) if digits >= *scale as i32 => Scalar::Decimal128(*v, *scale),
+        Scalar::Decimal128(v, scale) => {
+            let to = digits.max(0) as u8;
+            let v = decimal::rescale(*v, *scale, to).ok_or_else(overflow)?;
+            Scalar::Decimal128(round_int(v).ok_or_else(overflow)?, to)
+        }
+        other => return Err(format!("round() expects a number, got {:?}", other)),
+    })
+}
+
+/// `floor(x)` (`ceil` when `up`): integers are unchanged, floats keep their
+/// type, decimals become whole (scale 0).
+pub(crate) fn floor_ceil(value: &Scalar, up: bool) -> Result<Scalar, String> {
+    let func = if up { "ceil" } else { "floor" };
+    Ok(match value {
+        Scalar::Null => Scalar::Null,
+        Scalar::I32(_) | Scalar::I64(_) => value.clone(),
+        Scalar::F32(f) => Scalar::F32(if up { f.ceil() } else { f.floor() }),
+        Scalar::F64(f) => Scalar::F64(if up { f.ceil() } else { f.floor() }),
+        Scalar::Decimal128(v, scale) => {
+            let m = 10i128.pow(*scale as u32);
+            let whole = if up {
+                -(-v).div_euclid(m)
+            } else {
+                v.div_euclid(m)
+            };
+            Scalar::Decimal128(whole, 0)
+        }
+        other => return Err(format!("{}() expects a number, got {:?}", func, other)),
+    })
+}
+
+/// `cast(x, 'Type')`: `x` converted to `target`. Text parses as the type
+/// does in a CSV scan; floats truncate toward zero into integers; a
+/// timestamp cast to a date keeps its day. Values that do not convert are
+/// errors.
+pub(crate) fn cast(value: &Scalar, target: &DataType) -> Result<Scalar, String> {
+    use Scalar::*;
+    let fail = || format!("cannot cast {:?} to {:?}", value, target);
+    let to_i64 = || -> Option<i64> {
+        match value {
+            I32(i) => Some(*i as i64),
+            I64(i) => Some(*i),
+            Bool(b) => Some(*b as i64),
+            F32(_) | F64(_) => {
+                let f = float(value)?.trunc();
+                (f.is_finite() && f >= i64::MIN as f64 && f < i64::MAX as f64).then_some(f as i64)
+            }
+            Decimal128(v, scale) => i64::try_from(v / 10i128.checked_pow(*scale as u32)?).ok(),
+            Str(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    };
+    let result = match (target, value) {
+        (_, Null) => Some(Null),
+        (DataType::Utf8, Bin(b)) => Some(Str(String::from_utf8_lossy(b).into_owned())),
+        (DataType::Utf8, v) => Some(Str(v.to_string())),
+        (DataType::Int64, _) => to_i64().map(I64),
+        (DataType::Int32, _) => to_i64().and_then(|i| i32::try_from(i).ok()).map(I32),
+        (DataType::Float64, Str(s)) => s.trim().parse().ok().map(F64),
+        (DataType::Float32, Str(s)) => s.trim().parse().ok().map(F32),
+        (DataType::Float64, v) => float(v).map(F64),
+        (DataType::Float32, v) => float(v).map(|f| F32(f as f32)),
+        (DataType::Boolean, Bool(b)) => Some(Bool(*b)),
+        (DataType::Boolean, Str(s)) => s.trim().to_lowercase().parse().ok().map(Bool),
+        (DataType::Boolean, v) => float(v).map(|f| Bool(f != 0.0)),
+        (DataType::Binary, Bin(b)) => Some(Bin(b.clone())),
+        (DataType::Binary, Str(s)) => Some(Bin(s.as_bytes().to_vec())),
+        (DataType::Date64, Date64(ms)) => Some(Date64(*ms)),
+        (DataType::Date64, Timestamp(us)) => Some(Date64(
+            us.div_euclid(temporal::US_PER_DAY) * temporal::MS_PER_DAY,
+        )),
+        (DataType::Date64, Str(s)) => temporal::parse_date(s).map(Date64),
+        (DataType::Timestamp, Timestamp(us)) => Some(Timestamp(*us)),
+        (DataType::Timestamp, Date64(ms)) => Some(Timestamp(temporal::date_to_timestamp(*ms))),
+        (DataType::Timestamp, Str(s)) => temporal::parse_timestamp(s).map(Timestamp),
+        (DataType::Decimal128, Decimal128(v, scale)) => Some(Decimal128(*v, *scale)),
+        (DataType::Decimal128, I32(_) | I64(_)) => to_i64().map(|i| Decimal128(i as i128, 0)),
+        (DataType::Decimal128, F32(_) | F64(_)) => float(value)
+            .filter(|f| f.is_finite())
+            .and_then(|f| decimal::parse_decimal(&f.to_string()))
+            .map(|(v, scale)| Decimal128(v, scale)),
+        (DataType::Decimal128, Str(s)) => {
+            decimal::parse_decimal(s).map(|(v, scale)| Decimal128(v, scale))
+        }
+        _ => None,
+    };
+    result.ok_or_else(fail)
+}
+
+fn float(value: &Scalar) -> Option<f64> {
+    match value {
+        Scalar::I32(i) => Some(*i as f64),
+        Scalar::I64(i) => Some(*i as f64),
+        Scalar::F32(f) => Some(*f as f64),
+        Scalar::F64(f) => Some(*f),
+        Scalar::Bool(b) => Some(*b as i64 as f64),
+        Scalar::Decimal128(v, scale) => Some(decimal::to_f64(*v, *scale)),
+        _ => None,
+    }
+}
+
+/// A calendar or clock unit of `date_trunc` and `date_diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateUnit {
+    Year,
+    Quarter,
+    Month,
+    Week,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl DateUnit {
+    fn parse(func: &str, value: &Scalar) -> Result<Self, String> {
+        Ok(match text(func, value)?.to_lowercase().as_str() {
+            "year" => DateUnit::Year,
+            "quarter" => DateUnit::Quarter,
+            "month" => DateUnit::Month,
+            "week" => DateUnit::Week,
+            "day" => DateUnit::Day,
+            "hour" => DateUnit::Hour,
+            "minute" => DateUnit::Minute,
+            "second" => DateUnit::Second,
+            other => {
+                return Err(format!(
+                    "{}() unit '{}' is not one of year, quarter, month, week, day, hour, minute, second",
+                    func, other
+                ))
+            }
+        })
+    }
+
+    /// Length in microseconds, for the units of fixed length.
+    fn micros(self) -> Option<i64> {
+        Some(match self {
+            DateUnit::Week => 7 * temporal::US_PER_DAY,
+            DateUnit::Day => temporal::US_PER_DAY,
+            DateUnit::Hour => 3_600_000_000,
+            DateUnit::Minute => 60_000_000,
+            DateUnit::Second => 1_000_000,
+            DateUnit::Year | DateUnit::Quarter | DateUnit::Month => return None,
+        })
+    }
+
+    /// Start of the unit holding `us`; weeks start on Monday.
+    fn truncate(self, us: i64) -> i64 {
+        let days = us.div_euclid(temporal::US_PER_DAY);
+        let (year, month, _) = temporal::civil_from_days(days);
+        let start_day = match self {
+            DateUnit::Year => temporal::days_from_civil(year, 1, 1),
+            DateUnit::Quarter => temporal::days_from_civil(year, (month - 1) / 3 * 3 + 1, 1),
+            DateUnit::Month => temporal::days_from_civil(year, month, 1),
+            // 1970-01-01 was a Thursday.
+            DateUnit::Week => days - (days + 3).rem_euclid(7),
+            _ => {
+                let len = self.micros().unwrap_or(1);
+                return us - us.rem_euclid(len);
+            }
+        };
+        start_day * temporal::US_PER_DAY
+    }
+}
+
+/// A date, timestamp or ISO text as microseconds since the epoch.
+fn micros(func: &str, value: &Scalar) -> Result<i64, String> {
+    match value {
+        Scalar::Date64(ms) => Ok(temporal::date_to_timestamp(*ms)),
+        Scalar::Timestamp(us) => Ok(*us),
+        Scalar::Str(s) => temporal::parse_timestamp(s)
+            .ok_or_else(|| format!("{}() cannot read '{}' as a date or timestamp", func, s)),
+        other => Err(format!(
+            "{}() expects a date or timestamp, got {:?}",
+            func, other
+        )),
+    }
+}
+
+/// `date_trunc(unit, t)`: the start of the unit holding `t`. A date stays a
+/// date; a timestamp or ISO text becomes a timestamp.
+pub(crate) fn date_trunc(args: &[&Scalar]) -> Result<Scalar, String> {
+    if is_null(args) {
+        return Ok(Scalar::Null);
+    }
+    let unit = DateUnit::parse("date_trunc", args[0])?;
+    let start = unit.truncate(micros("date_trunc", args[1])?);
+    Ok(match args[1] {
+        Scalar::Date64(_) => Scalar::Date64(start.div_euclid(1000)),
+        _ => Scalar::Timestamp(start),
+    })
+}
+
+/// `date_diff(unit, start, end)`: unit boundaries crossed from `start` to
+/// `end`, negative when `end` is earlier (`Int64`). Days from 23:00 to 01:00
+/// the next day count 1; months from 2024-01-31 to 2024-02-01 count 1.
+pub(crate) fn date_diff(args: &[&Scalar]) -> Result<Scalar, String> {
+    if is_null(args) {
+        return Ok(Scalar::Null);
+    }
+    let unit = DateUnit::parse("date_diff", args[0])?;
+    let (start, end) = (micros("date_diff", args[1])?, micros("date_diff", args[2])?);
+    let diff = match unit.micros() {
+        Some(len) => (unit.truncate(end) - unit.truncate(start)) / len,
+        None => {
+            let month_index = |us: i64| {
+                let (year, month, _) =
+                    temporal::civil_from_days(us.div_euclid(temporal::US_PER_DAY));
+                year * 12 + month as i64 - 1
+            };
+            let months = |us| match unit {
+                DateUnit::Year => month_index(us).div_euclid(12),
+                DateUnit::Quarter => month_index(us).div_euclid(3),
+                _ => month_index(us),
+            };
+            months(end) - months(start)
+        }
+    };
+    Ok(Scalar::I64(diff))
+}
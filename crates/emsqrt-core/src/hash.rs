@@ -1,7 +1,10 @@
 //! Stable hashing helpers for plans, manifests, and content-addressable pieces.
 
 use blake3::Hasher;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::key::write_key_scalar;
+use crate::types::Scalar;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Hash256(pub [u8; 32]);
@@ -41,3 +44,173 @@ pub fn hash_serde<T: Serialize>(v: &T) -> Result<Hash256, crate::error::Error> {
     let bytes = serde_json::to_vec(v).map_err(|e| crate::error::Error::Hash(e.to_string()))?;
     Ok(hash_bytes(&bytes))
 }
+
+/// Version of the [`hash_canonical`] encoding. Bump on any change to it, so
+/// hashes from different encodings can never compare equal.
+pub const CANONICAL_HASH_VERSION: u32 = 1;
+
+/// Hash a serde value through a canonical encoding, for hashes that manifests
+/// and caches compare across runs and platforms.
+///
+/// Unlike [`hash_serde`], the result does not depend on map iteration order
+/// (object keys are sorted) or on how a number was typed: integral floats hash
+/// like integers (`2.0` == `2`) and `-0.0` hashes like `0`.
+pub fn hash_canonical<T: Serialize>(v: &T) -> Result<Hash256, crate::error::Error> {
+    let value = serde_json::to_value(v).map_err(|e| crate::error::Error::Hash(e.to_string()))?;
+    let mut h = Hasher::new();
+    h.update(b"emsqrt-canonical");
+    h.update(&CANONICAL_HASH_VERSION.to_le_bytes());
+    write_canonical(&value, &mut h);
+    Ok(Hash256(h.finalize().into()))
+}
+
+fn write_canonical(value: &serde_json::Value, h: &mut Hasher) {
+    use serde_json::Value;
+    match value {
+        Value::Null => {
+            h.update(b"n");
+        }
+        Value::Bool(b) => {
+            h.update(if *b { b"t" } else { b"f" });
+        }
+        Value::Number(n) => {
+            h.update(b"d");
+            write_len_prefixed(canonical_number(n).as_bytes(), h);
+        }
+        Value::String(s) => {
+            h.update(b"s");
+            write_len_prefixed(s.as_bytes(), h);
+        }
+        Value::Array(items) => {
+            h.update(b"a");
+            h.update(&(items.len() as u64).to_le_bytes());
+            for item in items {
+                write_canonical(item, h);
+            }
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            h.update(b"o");
+            h.update(&(entries.len() as u64).to_le_bytes());
+            for (k, v) in entries {
+                write_len_prefixed(k.as_bytes(), h);
+                write_canonical(v, h);
+            }
+        }
+    }
+}
+
+fn write_len_prefixed(bytes: &[u8], h: &mut Hasher) {
+    h.update(&(bytes.len() as u64).to_le_bytes());
+    h.update(bytes);
+}
+
+/// Decimal text for a JSON number: integers as-is; floats with an exact
+/// integer value as that integer; other floats in shortest round-trip form.
+fn canonical_number(n: &serde_json::Number) -> String {
+    if let Some(i) = n.as_i64() {
+        return i.to_string();
+    }
+    if let Some(u) = n.as_u64() {
+        return u.to_string();
+    }
+    let f = n.as_f64().unwrap_or(0.0);
+    if f.fract() == 0.0 && f.abs() < 9.007_199_254_740_992e15 {
+        // Within 2^53 every integral float is exact; `-0.0` becomes `0`.
+        return (f as i64).to_string();
+    }
+    format!("{:e}", f)
+}
+
+/// Hash function used to route rows to partitions (join/aggregate partitioning).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionHashKind {
+    /// BLAKE3 (keyed by the seed when non-zero). Slowest, but stable across versions.
+    #[default]
+    Blake3,
+    /// xxHash64 with the seed.
+    Xxhash64,
+    /// aHash with the seed. Fast; output is not guaranteed stable across aHash versions.
+    Ahash,
+}
+
+impl PartitionHashKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "blake3" => Some(Self::Blake3),
+            "xxhash64" | "xxhash" | "xxh64" => Some(Self::Xxhash64),
+            "ahash" => Some(Self::Ahash),
+            _ => None,
+        }
+    }
+}
+
+/// Seeded partition hash shared by every operator that splits rows by key.
+///
+/// Both sides of a join must use the same hasher or partitions won't line up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionHasher {
+    pub kind: PartitionHashKind,
+    pub seed: u64,
+}
+
+impl PartitionHasher {
+    pub fn new(kind: PartitionHashKind, seed: u64) -> Self {
+        Self { kind, seed }
+    }
+
+    /// Hash a row's key values into a u64.
+    ///
+    /// Hashes the [`crate::key`] encoding, so rows that compare equal as join or
+    /// group keys always land in the same partition.
+    pub fn hash_values(&self, values: &[&Scalar]) -> u64 {
+        match self.kind {
+            PartitionHashKind::Blake3 => {
+                let mut h = if self.seed == 0 {
+                    Hasher::new()
+                } else {
+                    let mut key = [0u8; 32];
+                    for chunk in key.chunks_mut(8) {
+                        chunk.copy_from_slice(&self.seed.to_le_bytes());
+                    }
+                    Hasher::new_keyed(&key)
+                };
+                for v in values {
+                    write_key_scalar(v, &mut |b| {
+                        h.update(b);
+                    });
+                }
+                let out = h.finalize();
+                u64::from_le_bytes(out.as_bytes()[0..8].try_into().unwrap())
+            }
+            PartitionHashKind::Xxhash64 => {
+                let mut h = twox_hash::XxHash64::with_seed(self.seed);
+                for v in values {
+                    write_key_scalar(v, &mut |b| std::hash::Hasher::write(&mut h, b));
+                }
+                std::hash::Hasher::finish(&h)
+            }
+            PartitionHashKind::Ahash => {
+                use std::hash::BuildHasher;
+                let state = ahash::RandomState::with_seeds(
+                    self.seed,
+                    self.seed.rotate_left(16),
+                    self.seed.rotate_left(32),
+                    self.seed.rotate_left(48),
+                );
+                let mut h = state.build_hasher();
+                for v in values {
+                    write_key_scalar(v, &mut |b| std::hash::Hasher::write(&mut h, b));
+                }
+                std::hash::Hasher::finish(&h)
+            }
+        }
+    }
+
+    /// Map a row's key values to a partition in `0..num_partitions`.
+    pub fn partition(&self, values: &[&Scalar], num_partitions: usize) -> usize {
+        (self.hash_values(values) as usize) % num_partitions
+    }
+}
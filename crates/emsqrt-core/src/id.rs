@@ -3,7 +3,12 @@
 //! Downstream crates (exec, operators, mem, etc.) should *not* use raw integers for IDs.
 
 use serde::{Deserialize, Serialize};
+#[cfg(debug_assertions)]
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(debug_assertions)]
+use std::sync::Mutex;
 
 macro_rules! new_id {
     ($name:ident) => {
@@ -34,5 +39,177 @@ new_id!(BlockId);
 new_id!(OpId);
 new_id!(SpillId);
 
-// TODO: If you need globally unique IDs (beyond per-run counters),
-// introduce UUID-based wrappers here (but prefer local counters for perf/replay).
+/// Id spaces handed out by an [`IdAllocator`]. Each namespace has its own counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IdNamespace {
+    Block,
+    Op,
+    Spill,
+}
+
+/// Raw ids below this value are reserved for fixed/well-known ids (tests,
+/// hand-built plans). The allocator never hands them out.
+pub const RESERVED_ID_LIMIT: u64 = 1 << 16;
+
+/// Number of low bits used for the per-namespace counter; the high bits carry
+/// a tag derived from the run seed, so allocators with different seeds issue
+/// different ids. Allocators with the same seed (or none) issue the same ids;
+/// spill segments of concurrent runs are kept apart by their file names (see
+/// `SpillManager`), not by these ids.
+const COUNTER_BITS: u32 = 48;
+const COUNTER_MASK: u64 = (1 << COUNTER_BITS) - 1;
+
+/// A contiguous block of raw ids reserved in one call (e.g. one per partition).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdRange {
+    pub namespace: IdNamespace,
+    pub start: u64,
+    pub len: u64,
+}
+
+impl IdRange {
+    pub fn get(&self, i: u64) -> Option<u64> {
+        (i < self.len).then(|| self.start + i)
+    }
+
+    pub fn contains(&self, raw: u64) -> bool {
+        raw >= self.start && raw < self.start + self.len
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> {
+        self.start..self.start + self.len
+    }
+}
+
+/// Run-scoped id allocator.
+///
+/// - Namespaced: block/op/spill ids come from independent counters.
+/// - Deterministic: the same seed yields the same sequence of ids.
+/// - Collision-free within a run; in debug builds the issued ranges are
+///   tracked and reuse panics. Adjacent ranges are merged, so the tracking
+///   stays a handful of intervals per namespace however many ids are issued.
+#[derive(Debug)]
+pub struct IdAllocator {
+    seed: u64,
+    tag: u64,
+    next_block: AtomicU64,
+    next_op: AtomicU64,
+    next_spill: AtomicU64,
+    /// Disjoint `[start, end)` intervals of issued ids, sorted, per namespace.
+    #[cfg(debug_assertions)]
+    issued: Mutex<HashMap<IdNamespace, Vec<(u64, u64)>>>,
+}
+
+impl IdAllocator {
+    /// Create an allocator for one run. The seed determines the high-bit tag.
+    pub fn new(seed: u64) -> Self {
+        // Fold the seed into the 16 high bits.
+        let folded = (seed ^ (seed >> 16) ^ (seed >> 32) ^ (seed >> 48)) & 0xFFFF;
+        Self {
+            seed,
+            tag: folded << COUNTER_BITS,
+            next_block: AtomicU64::new(RESERVED_ID_LIMIT),
+            next_op: AtomicU64::new(RESERVED_ID_LIMIT),
+            next_spill: AtomicU64::new(RESERVED_ID_LIMIT),
+            #[cfg(debug_assertions)]
+            issued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn next_block_id(&self) -> BlockId {
+        BlockId::new(self.next_raw(IdNamespace::Block))
+    }
+
+    pub fn next_op_id(&self) -> OpId {
+        OpId::new(self.next_raw(IdNamespace::Op))
+    }
+
+    pub fn next_spill_id(&self) -> SpillId {
+        SpillId::new(self.next_raw(IdNamespace::Spill))
+    }
+
+    /// Allocate the next raw id in `ns`.
+    pub fn next_raw(&self, ns: IdNamespace) -> u64 {
+        self.reserve(ns, 1).start
+    }
+
+    /// Reserve `len` consecutive ids in `ns`.
+    pub fn reserve(&self, ns: IdNamespace, len: u64) -> IdRange {
+        let counter = self.counter(ns).fetch_add(len, Ordering::Relaxed);
+        assert!(
+            counter + len <= COUNTER_MASK,
+            "id space exhausted for {:?}",
+            ns
+        );
+        let range = IdRange {
+            namespace: ns,
+            start: self.tag | counter,
+            len,
+        };
+        #[cfg(debug_assertions)]
+        self.track(range);
+        range
+    }
+
+    /// Intervals of issued ids tracked for `ns` (debug builds only).
+    #[cfg(debug_assertions)]
+    pub fn tracked_intervals(&self, ns: IdNamespace) -> usize {
+        self.issued.lock().unwrap().get(&ns).map_or(0, Vec::len)
+    }
+
+    /// Record `range` as issued, panicking if it overlaps an issued id.
+    #[cfg(debug_assertions)]
+    fn track(&self, range: IdRange) {
+        if range.len == 0 {
+            return;
+        }
+        let (start, end) = (range.start, range.start + range.len);
+        let mut issued = self.issued.lock().unwrap();
+        let intervals = issued.entry(range.namespace).or_default();
+        // First interval ending after `start`; it and its predecessor are the
+        // only ones that can overlap or touch the new range.
+        let i = intervals.partition_point(|&(_, e)| e <= start);
+        if let Some(&(s, _)) = intervals.get(i) {
+            assert!(
+                s >= end,
+                "id {} reused in {:?}",
+                start.max(s),
+                range.namespace
+            );
+        }
+        let merge_prev = i > 0 && intervals[i - 1].1 == start;
+        let merge_next = intervals.get(i).is_some_and(|&(s, _)| s == end);
+        match (merge_prev, merge_next) {
+            (true, true) => {
+                intervals[i - 1].1 = intervals[i].1;
+                intervals.remove(i);
+            }
+            (true, false) => intervals[i - 1].1 = end,
+            (false, true) => intervals[i].0 = start,
+            (false, false) => intervals.insert(i, (start, end)),
+        }
+    }
+
+    /// True if `raw` falls in the reserved (never allocated) range.
+    pub fn is_reserved(raw: u64) -> bool {
+        raw & COUNTER_MASK < RESERVED_ID_LIMIT
+    }
+
+    fn counter(&self, ns: IdNamespace) -> &AtomicU64 {
+        match ns {
+            IdNamespace::Block => &self.next_block,
+            IdNamespace::Op => &self.next_op,
+            IdNamespace::Spill => &self.next_spill,
+        }
+    }
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -0,0 +1,119 @@
+//! Compact, order-preserving binary encoding of row keys.
+//!
+//! Hash joins, aggregates, and partitioning key rows by one or more columns.
+//! Encoding those values into a flat byte string (instead of formatting each
+//! into a `String`) lets a single reusable buffer serve every row, and makes
+//! multi-column keys a plain concatenation.
+//!
+//! Layout per value: a type tag, then a payload whose byte order matches the
+//! value order, so encoded keys compare like the scalars they came from:
+//! - `I32`/`I64` share one tag and encode as sign-flipped big-endian `i64`,
+//!   so `I32(1)` and `I64(1)` produce the same key.
+//! - `F32`/`F64` share one tag and encode as order-preserving `f64` bits
+//!   (`-0.0` folds into `0.0`, all NaNs fold into one NaN that sorts last).
+//! - `Str`/`Bin` escape `0x00` as `0x00 0xFF` and end with `0x00 0x00`, so a
+//!   value is never a prefix of the next column's bytes.
+//!
+//! - `Date64` and `Timestamp` each have a tag and encode like integers.
+//! - `Decimal128` encodes as its `f64` order bits, then the exact value with
+//!   trailing fraction zeros dropped, so `1.50` and `1.5` share a key. Order
+//!   is exact unless two values differ only past `f64` precision.
+//!
+//! Integers and floats are distinct key types: `I64(1)` does not match `F64(1.0)`.
+//! Decimals, dates and timestamps are distinct from both.
+
+use crate::decimal;
+use crate::types::{Column, Scalar};
+
+const TAG_NULL: u8 = 0;
+const TAG_BOOL: u8 = 1;
+const TAG_INT: u8 = 2;
+const TAG_FLOAT: u8 = 3;
+const TAG_STR: u8 = 4;
+const TAG_BIN: u8 = 5;
+const TAG_DATE: u8 = 6;
+const TAG_TIMESTAMP: u8 = 7;
+const TAG_DECIMAL: u8 = 8;
+
+/// Feed a scalar's key bytes to `out`, in one or more chunks.
+pub fn write_key_scalar(scalar: &Scalar, out: &mut dyn FnMut(&[u8])) {
+    match scalar {
+        Scalar::Null => out(&[TAG_NULL]),
+        Scalar::Bool(b) => out(&[TAG_BOOL, *b as u8]),
+        Scalar::I32(i) => write_int(TAG_INT, *i as i64, out),
+        Scalar::I64(i) => write_int(TAG_INT, *i, out),
+        Scalar::F32(f) => write_float(TAG_FLOAT, *f as f64, out),
+        Scalar::F64(f) => write_float(TAG_FLOAT, *f, out),
+        Scalar::Str(s) => write_escaped(TAG_STR, s.as_bytes(), out),
+        Scalar::Bin(b) => write_escaped(TAG_BIN, b, out),
+        Scalar::Date64(ms) => write_int(TAG_DATE, *ms, out),
+        Scalar::Timestamp(us) => write_int(TAG_TIMESTAMP, *us, out),
+        Scalar::Decimal128(v, scale) => {
+            write_float(TAG_DECIMAL, decimal::to_f64(*v, *scale), out);
+            let (v, scale) = decimal::normalize(*v, *scale);
+            out(&((v as u128) ^ (1 << 127)).to_be_bytes());
+            out(&[scale]);
+        }
+    }
+}
+
+/// Append a scalar's key bytes to `buf`.
+pub fn encode_scalar(scalar: &Scalar, buf: &mut Vec<u8>) {
+    write_key_scalar(scalar, &mut |b| buf.extend_from_slice(b));
+}
+
+/// Clear `buf` and encode a multi-column key into it.
+pub fn encode_key(values: &[&Scalar], buf: &mut Vec<u8>) {
+    buf.clear();
+    for v in values {
+        encode_scalar(v, buf);
+    }
+}
+
+/// Clear `buf` and encode row `row` of `columns` into it.
+pub fn encode_row_key(columns: &[&Column], row: usize, buf: &mut Vec<u8>) {
+    buf.clear();
+    for col in columns {
+        encode_scalar(&col.values[row], buf);
+    }
+}
+
+fn write_int(tag: u8, i: i64, out: &mut dyn FnMut(&[u8])) {
+    let bits = (i as u64) ^ (1 << 63);
+    let mut bytes = [0u8; 9];
+    bytes[0] = tag;
+    bytes[1..].copy_from_slice(&bits.to_be_bytes());
+    out(&bytes);
+}
+
+fn write_float(tag: u8, f: f64, out: &mut dyn FnMut(&[u8])) {
+    let f = if f == 0.0 {
+        0.0
+    } else if f.is_nan() {
+        f64::NAN
+    } else {
+        f
+    };
+    let bits = f.to_bits();
+    let ordered = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits ^ (1 << 63)
+    };
+    let mut bytes = [0u8; 9];
+    bytes[0] = tag;
+    bytes[1..].copy_from_slice(&ordered.to_be_bytes());
+    out(&bytes);
+}
+
+fn write_escaped(tag: u8, data: &[u8], out: &mut dyn FnMut(&[u8])) {
+    out(&[tag]);
+    let mut rest = data;
+    while let Some(pos) = rest.iter().position(|&b| b == 0) {
+        out(&rest[..pos]);
+        out(&[0x00, 0xFF]);
+        rest = &rest[pos + 1..];
+    }
+    out(rest);
+    out(&[0x00, 0x00]);
+}
@@ -13,18 +13,25 @@
 //! - emsqrt-planner: produces LogicalPlan/PhysicalPlan using these types.
 //! - emsqrt-exec: orchestrates everything and emits RunManifest.
 
+pub mod align;
+pub mod binary;
 pub mod block;
 pub mod budget;
 pub mod config;
 pub mod dag;
+pub mod decimal;
 pub mod error;
 pub mod expr;
+mod functions;
 pub mod hash;
 pub mod id;
+pub mod key;
 pub mod manifest;
 pub mod prelude;
+pub mod pretty;
 pub mod schema;
 pub mod stats;
+pub mod temporal;
 pub mod types;
 
 #[cfg(feature = "arrow")]
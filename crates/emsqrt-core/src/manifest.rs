@@ -3,10 +3,14 @@
 //! The engine emits a manifest after successful execution; replay can rehydrate
 //! the exact same outputs given identical inputs, config, and seeds.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::hash::Hash256;
+use crate::config::{DetectedLimit, SpillCodec};
+use crate::hash::{Hash256, PartitionHasher};
+use crate::stats::SourceStats;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -31,6 +35,61 @@ pub struct RunManifest {
     /// Optional outputs digest (format-specific; may be a list in future).
     pub outputs_digest: Option<Hash256>,
 
+    /// Partition hash function and seed used, so partition layouts can be reproduced.
+    #[serde(default)]
+    pub partition_hash: Option<PartitionHasher>,
+
+    /// Seed behind `rand()`, `hash()` and `sample_hash()`; rerun with
+    /// `EMSQRT_SEED` set to it to reproduce their results.
+    #[serde(default)]
+    pub seed: u64,
+
+    /// Per-block CPU/IO accounting, in execution order.
+    #[serde(default)]
+    pub block_costs: Vec<BlockCost>,
+
+    /// Run-level totals derived from `block_costs`.
+    #[serde(default)]
+    pub cost: Option<CostSummary>,
+
+    /// Codec of the run's spill segments and the bytes it spilled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill: Option<SpillTotals>,
+
+    /// Per-operator totals of `block_costs` (see [`RunManifest::op_totals`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub operators: Vec<OpTotals>,
+
+    /// Cells that failed to parse as their field's type: source → column → count.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parse_errors: BTreeMap<String, BTreeMap<String, u64>>,
+
+    /// Column statistics of each source's rows (with `EngineConfig::collect_stats`,
+    /// `stats_dir` or `stats_file`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub source_stats: SourceStats,
+
+    /// Stats file this run updated with `source_stats` (`EngineConfig::stats_file`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats_file: Option<String>,
+
+    /// Sequence number of this run within `emsqrt run --follow` (0-based).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub micro_batch: Option<u64>,
+
+    /// The scheduler's choice of each block, in execution order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub schedule: Vec<ScheduleDecision>,
+
+    /// Settings in effect for the run that were defaulted from the
+    /// container's resource limits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detected_resources: Vec<DetectedLimit>,
+
+    /// Non-fatal events worth surfacing (e.g., operator fallbacks, skipped blocks).
+    #[serde(default)]
+    pub warnings: Vec<String>,
+
     /// Milliseconds since Unix epoch (UTC).
     pub started_ms: u64,
     pub finished_ms: u64,
@@ -45,6 +104,19 @@ impl RunManifest {
             engine_version: crate::VERSION.to_string(),
             inputs_digest: None,
             outputs_digest: None,
+            partition_hash: None,
+            seed: 0,
+            block_costs: Vec::new(),
+            cost: None,
+            spill: None,
+            operators: Vec::new(),
+            parse_errors: BTreeMap::new(),
+            source_stats: BTreeMap::new(),
+            stats_file: None,
+            micro_batch: None,
+            schedule: Vec::new(),
+            detected_resources: Vec::new(),
+            warnings: Vec::new(),
             started_ms,
             finished_ms: started_ms,
         }
@@ -55,4 +127,181 @@ impl RunManifest {
         self.outputs_digest = outputs_digest;
         self
     }
+
+    /// Per-operator totals of `block_costs`, in order of each operator's first
+    /// block. A join's exchange blocks are totalled apart from its join blocks.
+    pub fn op_totals(&self) -> Vec<OpTotals> {
+        let mut totals: Vec<OpTotals> = Vec::new();
+        for b in &self.block_costs {
+            let idx = match totals
+                .iter()
+                .position(|t| t.op_id == b.op_id && t.op == b.op)
+            {
+                Some(idx) => idx,
+                None => {
+                    totals.push(OpTotals {
+                        op_id: b.op_id,
+                        op: b.op.clone(),
+                        ..Default::default()
+                    });
+                    totals.len() - 1
+                }
+            };
+            let t = &mut totals[idx];
+            t.blocks += 1;
+            t.rows_in += b.rows_in;
+            t.rows_out += b.rows_out;
+            t.bytes_in += b.bytes_in;
+            t.bytes_out += b.bytes_out;
+            t.cpu_nanos += b.cpu_nanos;
+            t.wall_nanos += b.wall_nanos;
+            t.peak_memory_bytes = t.peak_memory_bytes.max(b.peak_memory_bytes);
+            t.bytes_spilled += b.bytes_spilled;
+            t.bytes_spill_read += b.bytes_spill_read;
+        }
+        totals
+    }
+
+    /// The run's per-operator and per-block metrics (`emsqrt run --metrics`).
+    pub fn metrics(&self) -> RunMetrics {
+        RunMetrics {
+            duration_ms: self.finished_ms.saturating_sub(self.started_ms),
+            peak_memory_bytes: self
+                .block_costs
+                .iter()
+                .map(|b| b.peak_memory_bytes)
+                .max()
+                .unwrap_or(0),
+            operators: self.op_totals(),
+            blocks: self.block_costs.clone(),
+        }
+    }
+}
+
+/// Resources consumed by a single TE block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockCost {
+    pub block_id: u64,
+    pub op_id: u64,
+    /// Operator name (e.g., "source", "sort").
+    pub op: String,
+    /// CPU time (user + system) of the thread that evaluated the block.
+    pub cpu_nanos: u64,
+    pub wall_nanos: u64,
+    /// Bytes written to spill storage while the block ran.
+    pub bytes_spilled: u64,
+    /// Spill bytes read back for the block, its spilled inputs included.
+    #[serde(default)]
+    pub bytes_spill_read: u64,
+    /// Bytes source readers read from storage for the block, buffered
+    /// read-ahead included (only non-zero for source blocks).
+    pub bytes_scanned: u64,
+    /// Rows in the block's input batches.
+    #[serde(default)]
+    pub rows_in: u64,
+    /// Rows in the block's output batch.
+    #[serde(default)]
+    pub rows_out: u64,
+    /// Estimated in-memory size of the input batches.
+    #[serde(default)]
+    pub bytes_in: u64,
+    /// Estimated in-memory size of the output batch.
+    #[serde(default)]
+    pub bytes_out: u64,
+    /// Most memory reserved from the block's budget while it ran.
+    #[serde(default)]
+    pub peak_memory_bytes: u64,
+    /// Operator-defined counters recorded through `OpContext::metrics`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, u64>,
+}
+
+/// One choice of the block scheduler (see `EngineConfig::schedule_policy`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleDecision {
+    pub block_id: u64,
+    pub op_id: u64,
+    /// Blocks that were ready to run, this one included.
+    pub ready: usize,
+    /// The policy's score of the block: blocks on its longest downstream
+    /// chain (`critical_path`), bytes it releases (`memory_aware`), or 0.
+    pub priority: u64,
+}
+
+/// `BlockCost`s summed per operator (see [`RunManifest::op_totals`]).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpTotals {
+    pub op_id: u64,
+    pub op: String,
+    pub blocks: usize,
+    #[serde(default)]
+    pub rows_in: u64,
+    pub rows_out: u64,
+    #[serde(default)]
+    pub bytes_in: u64,
+    #[serde(default)]
+    pub bytes_out: u64,
+    pub cpu_nanos: u64,
+    pub wall_nanos: u64,
+    /// Highest `peak_memory_bytes` of the operator's blocks.
+    #[serde(default)]
+    pub peak_memory_bytes: u64,
+    pub bytes_spilled: u64,
+    #[serde(default)]
+    pub bytes_spill_read: u64,
+}
+
+/// Metrics of one run: what `emsqrt run --metrics json` writes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunMetrics {
+    pub duration_ms: u64,
+    /// Highest peak reservation of any block.
+    pub peak_memory_bytes: u64,
+    pub operators: Vec<OpTotals>,
+    pub blocks: Vec<BlockCost>,
+}
+
+/// Spill volume of a run, before and after compression.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpillTotals {
+    pub codec: SpillCodec,
+    /// Serialized batches, before compression.
+    pub bytes_uncompressed: u64,
+    /// Segments as written, headers included (the `bytes_spilled` total).
+    pub bytes_compressed: u64,
+    /// Most segment bytes held in spill storage at once during the run.
+    #[serde(default)]
+    pub peak_bytes: u64,
+}
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Run totals plus a single comparable cost number.
+///
+/// `cost = cpu_seconds + gb_spilled + gb_scanned`. The units are deliberately
+/// naive; the number is meant for comparing runs and pipeline revisions, and
+/// for chargeback with an externally chosen price per unit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostSummary {
+    pub cpu_seconds: f64,
+    pub gb_spilled: f64,
+    pub gb_scanned: f64,
+    pub cost: f64,
+}
+
+impl CostSummary {
+    pub fn from_blocks(blocks: &[BlockCost]) -> Self {
+        let cpu_nanos: u64 = blocks.iter().map(|b| b.cpu_nanos).sum();
+        let spilled: u64 = blocks.iter().map(|b| b.bytes_spilled).sum();
+        let scanned: u64 = blocks.iter().map(|b| b.bytes_scanned).sum();
+        let cpu_seconds = cpu_nanos as f64 / 1e9;
+        let gb_spilled = spilled as f64 / GB;
+        let gb_scanned = scanned as f64 / GB;
+        Self {
+            cpu_seconds,
+            gb_spilled,
+            gb_scanned,
+            cost: cpu_seconds + gb_spilled + gb_scanned,
+        }
+    }
 }
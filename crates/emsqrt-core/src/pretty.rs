@@ -0,0 +1,181 @@
+//! Human-readable rendering of values and batches: `Display` for `Scalar`
+//! and `RowBatch` (a bordered text table), and the per-column summary
+//! returned by `RowBatch::describe`.
+
+use std::fmt;
+
+use crate::schema::DataType;
+use crate::stats::ColumnStats;
+use crate::types::{RowBatch, Scalar};
+use crate::{decimal, temporal};
+
+/// Cells longer than this many characters are cut and end in `…`.
+pub const MAX_CELL_WIDTH: usize = 40;
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Null => f.write_str("null"),
+            Scalar::Bool(v) => write!(f, "{}", v),
+            Scalar::I32(v) => write!(f, "{}", v),
+            Scalar::I64(v) => write!(f, "{}", v),
+            Scalar::F32(v) => write!(f, "{}", v),
+            Scalar::F64(v) => write!(f, "{}", v),
+            Scalar::Str(v) => f.write_str(v),
+            Scalar::Bin(v) => {
+                f.write_str("0x")?;
+                v.iter().try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Scalar::Date64(v) => f.write_str(&temporal::format_date(*v)),
+            Scalar::Timestamp(v) => f.write_str(&temporal::format_timestamp(*v)),
+            Scalar::Decimal128(v, scale) => f.write_str(&decimal::format_decimal(*v, *scale)),
+        }
+    }
+}
+
+/// Summary of one column of a batch (`RowBatch::describe`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnSummary {
+    pub name: String,
+    /// Type of the first non-null value; `None` if every value is null.
+    pub data_type: Option<DataType>,
+    pub stats: ColumnStats,
+}
+
+impl ColumnSummary {
+    /// Percentage of null values (0 for an empty column).
+    pub fn null_percent(&self) -> f64 {
+        if self.stats.total_count == 0 {
+            0.0
+        } else {
+            100.0 * self.stats.null_count as f64 / self.stats.total_count as f64
+        }
+    }
+}
+
+/// Per-column summaries of a batch, printable as a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSummary {
+    pub rows: usize,
+    pub columns: Vec<ColumnSummary>,
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = ["column", "type", "nulls", "null %", "min", "max"].map(String::from);
+        let opt = |v: &Option<Scalar>| v.as_ref().map_or(String::new(), Scalar::to_string);
+        let rows: Vec<Vec<String>> = self
+            .columns
+            .iter()
+            .map(|c| {
+                vec![
+                    c.name.clone(),
+                    c.data_type
+                        .as_ref()
+                        .map_or("null".to_string(), |t| format!("{:?}", t)),
+                    c.stats.null_count.to_string(),
+                    format!("{:.1}", c.null_percent()),
+                    opt(&c.stats.min),
+                    opt(&c.stats.max),
+                ]
+            })
+            .collect();
+        writeln!(f, "{} rows", self.rows)?;
+        write_table(f, &header, &rows, &[false, false, true, true, false, false])
+    }
+}
+
+impl fmt::Display for RowBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header: Vec<String> = self.columns.iter().map(|c| c.name.clone()).collect();
+        let rows: Vec<Vec<String>> = (0..self.num_rows())
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .map(|c| c.values.get(row).map_or(String::new(), Scalar::to_string))
+                    .collect()
+            })
+            .collect();
+        // Numeric columns are right-aligned, by their first non-null value.
+        let numeric: Vec<bool> = self
+            .columns
+            .iter()
+            .map(|c| {
+                c.values
+                    .iter()
+                    .find(|v| !matches!(v, Scalar::Null))
+                    .is_some_and(|v| {
+                        matches!(
+                            v,
+                            Scalar::I32(_) | Scalar::I64(_) | Scalar::F32(_) | Scalar::F64(_)
+                        )
+                    })
+            })
+            .collect();
+        write_table(f, &header, &rows, &numeric)
+    }
+}
+
+/// Cut `s` to `MAX_CELL_WIDTH` characters; newlines and tabs become spaces.
+fn cell(s: &str) -> String {
+    let s: String = s
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    if s.chars().count() <= MAX_CELL_WIDTH {
+        return s;
+    }
+    let mut cut: String = s.chars().take(MAX_CELL_WIDTH - 1).collect();
+    cut.push('…');
+    cut
+}
+
+fn write_table(
+    f: &mut fmt::Formatter<'_>,
+    header: &[String],
+    rows: &[Vec<String>],
+    right_align: &[bool],
+) -> fmt::Result {
+    let header: Vec<String> = header.iter().map(|h| cell(h)).collect();
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|r| r.iter().map(|c| cell(c)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain([header[i].chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let rule = |f: &mut fmt::Formatter<'_>| {
+        f.write_str("+")?;
+        for w in &widths {
+            write!(f, "{}+", "-".repeat(w + 2))?;
+        }
+        writeln!(f)
+    };
+    let line = |f: &mut fmt::Formatter<'_>, cells: &[String], align: &[bool]| {
+        f.write_str("|")?;
+        for ((c, w), right) in cells.iter().zip(&widths).zip(align) {
+            let pad = " ".repeat(w - c.chars().count());
+            if *right {
+                write!(f, " {}{} |", pad, c)?;
+            } else {
+                write!(f, " {}{} |", c, pad)?;
+            }
+        }
+        writeln!(f)
+    };
+
+    rule(f)?;
+    line(f, &header, &vec![false; header.len()])?;
+    rule(f)?;
+    for row in &rows {
+        line(f, row, right_align)?;
+    }
+    rule(f)
+}
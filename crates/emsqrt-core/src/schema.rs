@@ -3,8 +3,12 @@
 //! The `types.rs` module contains lightweight `Scalar`/`Column` placeholders.
 //! In `emsqrt-operators`, you'll likely convert to Arrow arrays for execution.
 
+use std::collections::{BTreeMap, HashSet};
+
 use serde::{Deserialize, Serialize};
 
+use crate::align::AlignPolicy;
+use crate::error::{Error, Result};
 use crate::stats::SchemaStats;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -17,10 +21,32 @@ pub enum DataType {
     Utf8,
     Binary,
     Date64,
+    /// Microseconds since the Unix epoch, UTC.
+    Timestamp,
     Decimal128,
     // TODO: Add Time/Struct/List as needed.
 }
 
+impl DataType {
+    /// A type by its name in a schema or `cast`: the variant name or a short
+    /// alias (`Int64`/`i64`, `Utf8`/`string`, `Date64`/`date`, ...).
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "Boolean" | "bool" => DataType::Boolean,
+            "Int32" | "i32" => DataType::Int32,
+            "Int64" | "i64" => DataType::Int64,
+            "Float32" | "f32" => DataType::Float32,
+            "Float64" | "f64" => DataType::Float64,
+            "Utf8" | "utf8" | "String" | "string" => DataType::Utf8,
+            "Binary" | "bytes" => DataType::Binary,
+            "Date64" | "date" => DataType::Date64,
+            "Timestamp" | "timestamp" => DataType::Timestamp,
+            "Decimal128" | "decimal" => DataType::Decimal128,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
@@ -38,17 +64,294 @@ impl Field {
     }
 }
 
+/// Source options that decide which text cells read as null.
+///
+/// Applied to every string cell a source reads (CSV cells before they are
+/// parsed into the field's type, JSONL and Parquet string values after), in
+/// order: trim, then match `null_tokens`, then `empty_as_null`. With the
+/// defaults, cells are kept as written and only unparsable numbers are null.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NullOptions {
+    /// Read empty cells as null instead of `""`.
+    #[serde(default)]
+    pub empty_as_null: bool,
+    /// Cell values that mean null, e.g. `["NA", "\N"]` (exact match).
+    #[serde(default)]
+    pub null_tokens: Vec<String>,
+    /// Strip leading and trailing whitespace from cells.
+    #[serde(default)]
+    pub trim_whitespace: bool,
+}
+
+impl NullOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The cell as the source should see it, or `None` for null.
+    pub fn normalize<'a>(&self, raw: &'a str) -> Option<&'a str> {
+        let value = if self.trim_whitespace {
+            raw.trim()
+        } else {
+            raw
+        };
+        if (self.empty_as_null && value.is_empty()) || self.null_tokens.iter().any(|t| t == value) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+}
+
+/// What to do with a non-empty cell that does not parse as its field's
+/// numeric or boolean type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseErrorPolicy {
+    /// Keep the row with a null in that column.
+    #[default]
+    Null,
+    /// Fail the run.
+    Fail,
+    /// Drop the row from the output and write it to the dead-letter file.
+    DeadLetter,
+}
+
+/// Per-source parse error handling, with per-column overrides.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseErrorOptions {
+    #[serde(default)]
+    pub on_parse_error: ParseErrorPolicy,
+    /// Column name → policy, overriding `on_parse_error`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub columns: BTreeMap<String, ParseErrorPolicy>,
+    /// JSONL file receiving rows dropped by `dead_letter`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<String>,
+}
+
+impl ParseErrorOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn policy_for(&self, column: &str) -> ParseErrorPolicy {
+        self.columns
+            .get(column)
+            .copied()
+            .unwrap_or(self.on_parse_error)
+    }
+
+    /// Whether any column sends rows to the dead-letter file.
+    pub fn uses_dead_letter(&self) -> bool {
+        self.on_parse_error == ParseErrorPolicy::DeadLetter
+            || self
+                .columns
+                .values()
+                .any(|p| *p == ParseErrorPolicy::DeadLetter)
+    }
+}
+
+/// File format of a source whose extension does not say (e.g. `events.log`
+/// holding JSON lines).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceFormat {
+    Csv,
+    Jsonl,
+    Parquet,
+    Avro,
+}
+
+impl SourceFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(Self::Csv),
+            "jsonl" | "ndjson" => Some(Self::Jsonl),
+            "parquet" | "parq" => Some(Self::Parquet),
+            "avro" => Some(Self::Avro),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+            Self::Parquet => "parquet",
+            Self::Avro => "avro",
+        }
+    }
+}
+
+/// Record and field boundaries of a text source that is neither CSV nor JSONL,
+/// such as a mainframe export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextLayout {
+    /// Ends each record; any non-empty string, e.g. `"\n"`, `"\r\n"` or `"~"`.
+    pub record_delimiter: String,
+    /// Leading records to drop (banners, headers), before any header record.
+    #[serde(default)]
+    pub skip_records: usize,
+    pub fields: FieldLayout,
+}
+
+/// How a `TextLayout` record splits into fields.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldLayout {
+    /// Fields separated by `delimiter` (any non-empty string; no quoting).
+    /// With `header`, the first record names the columns; otherwise fields
+    /// are read in schema order.
+    Delimited { delimiter: String, header: bool },
+    /// Each column is a character span of the record, in schema field order.
+    FixedWidth { spans: Vec<FixedWidthSpan> },
+}
+
+/// Characters `offset..offset + length` of a fixed-width record. A record that
+/// ends early yields the part of the span it has (possibly empty).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedWidthSpan {
+    pub offset: usize,
+    pub length: usize,
+}
+
+/// Guarantees about the rows of a dataset.
+///
+/// Sources declare them; each operator either preserves them or drops the
+/// ones it may break, and the planner relies on what survives (e.g. merge
+/// join on inputs already sorted by the join keys). They are trusted, not
+/// checked, unless the engine runs with `verify_constraints`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Constraints {
+    /// Columns whose values identify a row (unique and, by convention, non-null).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub primary_key: Vec<String>,
+    /// Other column sets with no duplicate value combinations.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unique: Vec<Vec<String>>,
+    /// Rows are in ascending order of these columns (lexicographically).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sorted_by: Vec<String>,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.primary_key.is_empty() && self.unique.is_empty() && self.sorted_by.is_empty()
+    }
+
+    /// All unique column sets, the primary key first.
+    pub fn unique_keys(&self) -> impl Iterator<Item = &[String]> {
+        std::iter::once(self.primary_key.as_slice())
+            .chain(self.unique.iter().map(|u| u.as_slice()))
+            .filter(|k| !k.is_empty())
+    }
+
+    /// True if no two rows share values on `columns` (some unique key is a subset).
+    pub fn is_unique_on(&self, columns: &[String]) -> bool {
+        self.unique_keys()
+            .any(|key| key.iter().all(|c| columns.contains(c)))
+    }
+
+    /// True if rows are sorted by `keys`, i.e. `keys` is a prefix of `sorted_by`.
+    pub fn is_sorted_by(&self, keys: &[String]) -> bool {
+        !keys.is_empty() && self.sorted_by.starts_with(keys)
+    }
+
+    /// Constraints that survive keeping only `columns`.
+    pub fn project(&self, columns: &[String]) -> Self {
+        let kept = |key: &[String]| key.iter().all(|c| columns.contains(c));
+        Self {
+            primary_key: if kept(&self.primary_key) {
+                self.primary_key.clone()
+            } else {
+                Vec::new()
+            },
+            unique: self.unique.iter().filter(|u| kept(u)).cloned().collect(),
+            sorted_by: self
+                .sorted_by
+                .iter()
+                .take_while(|c| columns.contains(c))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Constraints that survive a row-duplicating operator (order is kept).
+    pub fn sorted_only(&self) -> Self {
+        Self {
+            sorted_by: self.sorted_by.clone(),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
     pub fields: Vec<Field>,
     /// Optional column statistics for cost estimation
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stats: Option<SchemaStats>,
+    /// Declared row constraints (primary key, uniqueness, sort order).
+    #[serde(default, skip_serializing_if = "Constraints::is_empty")]
+    pub constraints: Constraints,
+    /// How a source maps raw text cells to nulls.
+    #[serde(default, skip_serializing_if = "NullOptions::is_default")]
+    pub null_options: NullOptions,
+    /// What a source does with cells that do not parse as their field's type.
+    #[serde(default, skip_serializing_if = "ParseErrorOptions::is_default")]
+    pub parse_errors: ParseErrorOptions,
+    /// Record/field layout of a delimited or fixed-width text source; `None`
+    /// reads the source by its extension (CSV, JSONL, Parquet).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Box<TextLayout>>,
+    /// Reader of a source without a layout; `None` follows the extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<SourceFormat>,
+    /// JSONL sources: nested objects become `parent.child` columns instead
+    /// of JSON text.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flatten: bool,
+    /// How inputs missing some of these columns, or carrying others, are
+    /// aligned to them (multi-file scans and unions).
+    #[serde(default, skip_serializing_if = "AlignPolicy::is_default")]
+    pub align: AlignPolicy,
+}
+
+/// `names` with each name an earlier one already has made unique: `suffix`
+/// is appended, then a counter if that is taken too (`id_right`,
+/// `id_right2`, ...). Case is kept and names compare exactly.
+pub fn dedup_names(names: impl IntoIterator<Item = String>, suffix: &str) -> Vec<String> {
+    let names: Vec<String> = names.into_iter().collect();
+    let mut taken: HashSet<String> = HashSet::new();
+    let mut out = Vec::with_capacity(names.len());
+    for (i, name) in names.iter().enumerate() {
+        let mut unique = name.clone();
+        if taken.contains(&unique) {
+            unique = format!("{}{}", name, suffix);
+            let mut n = 2;
+            // Later names keep theirs, so skip those too.
+            while taken.contains(&unique) || names[i + 1..].contains(&unique) {
+                unique = format!("{}{}{}", name, suffix, n);
+                n += 1;
+            }
+        }
+        taken.insert(unique.clone());
+        out.push(unique);
+    }
+    out
 }
 
 impl PartialEq for Schema {
     fn eq(&self, other: &Self) -> bool {
         self.fields == other.fields
+            && self.constraints == other.constraints
+            && self.null_options == other.null_options
+            && self.parse_errors == other.parse_errors
+            && self.layout == other.layout
+            && self.format == other.format
+            && self.flatten == other.flatten
+            && self.align == other.align
         // Note: stats are not compared for equality (HashMap + floats make this complex)
     }
 }
@@ -58,11 +361,85 @@ impl Schema {
         Self {
             fields,
             stats: None,
+            constraints: Constraints::default(),
+            null_options: NullOptions::default(),
+            parse_errors: ParseErrorOptions::default(),
+            layout: None,
+            format: None,
+            flatten: false,
+            align: AlignPolicy::default(),
         }
     }
 
+    /// `new`, failing if two fields share a name. Names compare exactly, so
+    /// `id` and `ID` are distinct columns.
+    pub fn try_new(fields: Vec<Field>) -> Result<Self> {
+        let mut seen = HashSet::new();
+        for f in &fields {
+            if !seen.insert(f.name.as_str()) {
+                return Err(Error::Schema(format!("duplicate field name '{}'", f.name)));
+            }
+        }
+        Ok(Self::new(fields))
+    }
+
+    /// `new`, renaming each field whose name an earlier field has; see
+    /// `dedup_names`.
+    pub fn new_deduplicated(mut fields: Vec<Field>, suffix: &str) -> Self {
+        let names = dedup_names(fields.iter().map(|f| f.name.clone()), suffix);
+        for (f, name) in fields.iter_mut().zip(names) {
+            f.name = name;
+        }
+        Self::new(fields)
+    }
+
     pub fn new_with_stats(fields: Vec<Field>, stats: Option<SchemaStats>) -> Self {
-        Self { fields, stats }
+        Self {
+            fields,
+            stats,
+            constraints: Constraints::default(),
+            null_options: NullOptions::default(),
+            parse_errors: ParseErrorOptions::default(),
+            layout: None,
+            format: None,
+            flatten: false,
+            align: AlignPolicy::default(),
+        }
+    }
+
+    pub fn with_constraints(mut self, constraints: Constraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn with_null_options(mut self, null_options: NullOptions) -> Self {
+        self.null_options = null_options;
+        self
+    }
+
+    pub fn with_parse_errors(mut self, parse_errors: ParseErrorOptions) -> Self {
+        self.parse_errors = parse_errors;
+        self
+    }
+
+    pub fn with_layout(mut self, layout: Option<TextLayout>) -> Self {
+        self.layout = layout.map(Box::new);
+        self
+    }
+
+    pub fn with_format(mut self, format: Option<SourceFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+
+    pub fn with_align(mut self, align: AlignPolicy) -> Self {
+        self.align = align;
+        self
     }
 
     pub fn field(&self, idx: usize) -> Option<&Field> {
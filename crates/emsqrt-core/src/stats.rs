@@ -6,7 +6,63 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::types::Scalar;
+use crate::hash::{PartitionHashKind, PartitionHasher};
+use crate::types::{typed_cmp, RowBatch, Scalar};
+
+/// Register index bits of `HyperLogLog`: 4096 one-byte registers, about
+/// 1.6% standard error.
+const HLL_BITS: u32 = 12;
+
+/// HyperLogLog sketch of the distinct values of a column. Sketches of the
+/// same column merge exactly, so per-block sketches give the source's
+/// distinct count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << HLL_BITS],
+        }
+    }
+
+    pub fn insert(&mut self, value: &Scalar) {
+        // xxHash64 is stable across versions, so estimates are reproducible.
+        let hash = PartitionHasher::new(PartitionHashKind::Xxhash64, 0).hash_values(&[value]);
+        let index = (hash >> (64 - HLL_BITS)) as usize;
+        // Leading zeros of the remaining bits, plus one.
+        let rank = ((hash << HLL_BITS) | (1 << (HLL_BITS - 1))).leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (a, b) in self.registers.iter_mut().zip(&other.registers) {
+            *a = (*a).max(*b);
+        }
+    }
+
+    /// Estimated distinct values, with linear counting for small counts.
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Statistics for a single column.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -21,6 +77,10 @@ pub struct ColumnStats {
     pub distinct_count: Option<u64>,
     /// Total number of values (including nulls)
     pub total_count: u64,
+    /// Sketch behind `distinct_count` while stats are being collected; not
+    /// persisted.
+    #[serde(skip)]
+    pub distinct_sketch: Option<HyperLogLog>,
 }
 
 impl ColumnStats {
@@ -32,6 +92,7 @@ impl ColumnStats {
             null_count: 0,
             distinct_count: None,
             total_count: 0,
+            distinct_sketch: None,
         }
     }
 
@@ -44,6 +105,10 @@ impl ColumnStats {
                 self.null_count += 1;
             }
             val => {
+                if let Some(sketch) = &mut self.distinct_sketch {
+                    sketch.insert(val);
+                }
+
                 // Update min
                 match &mut self.min {
                     Some(min) => {
@@ -75,7 +140,16 @@ impl ColumnStats {
     ///
     /// Used when combining stats from multiple partitions or batches.
     pub fn merge(&self, other: &ColumnStats) -> ColumnStats {
-        let merged = ColumnStats {
+        // Distinct counts only add up through their sketches.
+        let distinct_sketch = match (&self.distinct_sketch, &other.distinct_sketch) {
+            (Some(a), Some(b)) => {
+                let mut merged = a.clone();
+                merged.merge(b);
+                Some(merged)
+            }
+            _ => None,
+        };
+        ColumnStats {
             min: match (&self.min, &other.min) {
                 (Some(a), Some(b)) => {
                     if scalar_cmp(a, b).is_le() {
@@ -101,11 +175,10 @@ impl ColumnStats {
                 (None, None) => None,
             },
             null_count: self.null_count + other.null_count,
-            distinct_count: None, // Merging distinct counts is complex, set to None
+            distinct_count: distinct_sketch.as_ref().map(HyperLogLog::estimate),
             total_count: self.total_count + other.total_count,
-        };
-
-        merged
+            distinct_sketch,
+        }
     }
 
     /// Get the number of non-null values.
@@ -136,8 +209,8 @@ impl ColumnStats {
         match (min_val, max_val) {
             (Some(min), Some(max)) => {
                 // Range predicate: estimate based on overlap
-                if scalar_cmp(min, &self.max.as_ref().unwrap()).is_gt()
-                    || scalar_cmp(max, &self.min.as_ref().unwrap()).is_lt()
+                if scalar_cmp(min, self.max.as_ref().unwrap()).is_gt()
+                    || scalar_cmp(max, self.min.as_ref().unwrap()).is_lt()
                 {
                     return 0.0; // No overlap
                 }
@@ -165,7 +238,7 @@ impl ColumnStats {
                     if let Some(threshold) = scalar_to_f64(min) {
                         if max_val > min_val {
                             let selectivity = (max_val - threshold) / (max_val - min_val);
-                            return selectivity.max(0.0).min(1.0);
+                            return selectivity.clamp(0.0, 1.0);
                         }
                     }
                 }
@@ -192,7 +265,7 @@ impl ColumnStats {
                     if let Some(threshold) = scalar_to_f64(max) {
                         if max_val > min_val {
                             let selectivity = (threshold - min_val) / (max_val - min_val);
-                            return selectivity.max(0.0).min(1.0);
+                            return selectivity.clamp(0.0, 1.0);
                         }
                     }
                 }
@@ -228,6 +301,9 @@ impl Default for ColumnStats {
     }
 }
 
+/// Statistics per source URI, as collected by a run (`RunManifest::source_stats`).
+pub type SourceStats = std::collections::BTreeMap<String, SchemaStats>;
+
 /// Statistics for all columns in a schema.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SchemaStats {
@@ -250,9 +326,33 @@ impl SchemaStats {
 
     /// Get or create statistics for a column.
     pub fn get_or_create(&mut self, column_name: String) -> &mut ColumnStats {
-        self.column_stats
-            .entry(column_name)
-            .or_insert_with(ColumnStats::new)
+        self.column_stats.entry(column_name).or_default()
+    }
+
+    /// Add one batch's values, column by column, keeping a distinct-value
+    /// sketch per column so `distinct_count` is set.
+    pub fn update_batch(&mut self, batch: &RowBatch) {
+        for col in &batch.columns {
+            let stats = self.get_or_create(col.name.clone());
+            if stats.total_count == 0 {
+                stats.distinct_sketch.get_or_insert_with(HyperLogLog::new);
+            }
+            col.values.iter().for_each(|v| stats.update(v));
+            stats.distinct_count = stats.distinct_sketch.as_ref().map(HyperLogLog::estimate);
+        }
+    }
+
+    /// Drop the distinct-value sketches once collection is done, keeping
+    /// their `distinct_count`s.
+    pub fn drop_sketches(&mut self) {
+        for stats in self.column_stats.values_mut() {
+            stats.distinct_sketch = None;
+        }
+    }
+
+    /// Rows seen: the largest `total_count` over columns.
+    pub fn row_count(&self) -> Option<u64> {
+        self.column_stats.values().map(|c| c.total_count).max()
     }
 
     /// Merge statistics from another SchemaStats into this one.
@@ -301,6 +401,7 @@ fn scalar_to_f64(s: &Scalar) -> Option<f64> {
         I64(x) => Some(*x as f64),
         F32(x) => Some(*x as f64),
         F64(x) => Some(*x),
+        Decimal128(v, scale) => Some(crate::decimal::to_f64(*v, *scale)),
         _ => None,
     }
 }
@@ -321,6 +422,11 @@ fn scalar_cmp(a: &Scalar, b: &Scalar) -> std::cmp::Ordering {
         (F64(x), F64(y)) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
         (Str(x), Str(y)) => x.cmp(y),
         (Bin(x), Bin(y)) => x.cmp(y),
+        _ if typed_cmp(a, b).is_some() => typed_cmp(a, b).unwrap(),
+        // Mixed numeric widths (an `I32` literal against `I64` stats) compare by value.
+        _ if scalar_to_f64(a).is_some() && scalar_to_f64(b).is_some() => scalar_to_f64(a)
+            .partial_cmp(&scalar_to_f64(b))
+            .unwrap_or(Ordering::Equal),
         _ => {
             // Mixed types: compare by type discriminant
             let a_order = scalar_type_order(a);
@@ -342,5 +448,8 @@ fn scalar_type_order(s: &Scalar) -> u8 {
         F64(_) => 5,
         Str(_) => 6,
         Bin(_) => 7,
+        Date64(_) => 8,
+        Timestamp(_) => 9,
+        Decimal128(..) => 10,
     }
 }
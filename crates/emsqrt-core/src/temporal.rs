@@ -0,0 +1,154 @@
+//! Dates and timestamps: `Scalar::Date64` and `Scalar::Timestamp`.
+//!
+//! A date is a day since the Unix epoch, held as milliseconds (Arrow's
+//! `Date64`); a timestamp is microseconds since the epoch, UTC. Both read
+//! and print as ISO 8601 text (`2024-03-01`, `2024-03-01T12:30:00.250`) on
+//! the proleptic Gregorian calendar.
+
+pub const MS_PER_DAY: i64 = 86_400_000;
+pub const US_PER_DAY: i64 = 86_400_000_000;
+const US_PER_SECOND: i64 = 1_000_000;
+
+/// Days since 1970-01-01 of a calendar date (Hinnant's `days_from_civil`).
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Calendar date of a day since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+fn digits(s: &str, min: usize, max: usize) -> Option<u32> {
+    (s.len() >= min && s.len() <= max && s.bytes().all(|b| b.is_ascii_digit()))
+        .then(|| s.parse().ok())
+        .flatten()
+}
+
+/// Days since the epoch of `YYYY-MM-DD` (month and day may take one digit).
+fn parse_days(s: &str) -> Option<i64> {
+    let mut parts = s.split('-');
+    let (y, m, d) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let (year, month, day) = (digits(y, 4, 4)?, digits(m, 1, 2)?, digits(d, 1, 2)?);
+    let year = year as i64;
+    (day >= 1 && day <= days_in_month(year, month)).then(|| days_from_civil(year, month, day))
+}
+
+/// `YYYY-MM-DD` as a `Date64` value.
+pub fn parse_date(s: &str) -> Option<i64> {
+    parse_days(s.trim()).map(|days| days * MS_PER_DAY)
+}
+
+/// `YYYY-MM-DD` of a `Date64` value (a time of day, if any, is dropped).
+pub fn format_date(ms: i64) -> String {
+    let (year, month, day) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// A `Timestamp` value from `YYYY-MM-DD`, optionally followed by `T` or a
+/// space, `HH:MM[:SS[.fraction]]` and a `Z` or `±HH:MM` offset. Digits of
+/// the fraction past microseconds are dropped.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (date, time) = match s.find(['T', ' ']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let days = parse_days(date)?;
+    let Some(time) = time else {
+        return days.checked_mul(US_PER_DAY);
+    };
+
+    // The offset: `Z`, or a sign after the time of day.
+    let (time, offset_us) = if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else if let Some(i) = time.rfind(['+', '-']) {
+        let (hh, mm) = time[i + 1..].split_once(':')?;
+        let minutes = digits(hh, 2, 2)? as i64 * 60 + digits(mm, 2, 2)? as i64;
+        let sign = if time.as_bytes()[i] == b'-' { -1 } else { 1 };
+        (&time[..i], sign * minutes * 60 * US_PER_SECOND)
+    } else {
+        (time, 0)
+    };
+
+    let (clock, fraction) = match time.split_once('.') {
+        Some((clock, fraction)) => (clock, Some(fraction)),
+        None => (time, None),
+    };
+    let mut fields = clock.split(':');
+    let hour = digits(fields.next()?, 2, 2)?;
+    let minute = digits(fields.next()?, 2, 2)?;
+    let second = fields.next().map_or(Some(0), |s| digits(s, 2, 2))?;
+    if fields.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let micros = match fraction {
+        Some(f) if f.is_empty() || !f.bytes().all(|b| b.is_ascii_digit()) => return None,
+        Some(f) => format!("{:0<6}", &f[..f.len().min(6)])
+            .parse::<i64>()
+            .ok()?,
+        None => 0,
+    };
+    let seconds = hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+    days.checked_mul(US_PER_DAY)?
+        .checked_add(seconds * US_PER_SECOND + micros)?
+        .checked_sub(offset_us)
+}
+
+/// `YYYY-MM-DDTHH:MM:SS` of a `Timestamp` value, with milliseconds or
+/// microseconds when it has them.
+pub fn format_timestamp(us: i64) -> String {
+    let (year, month, day) = civil_from_days(us.div_euclid(US_PER_DAY));
+    let of_day = us.rem_euclid(US_PER_DAY);
+    let seconds = of_day / US_PER_SECOND;
+    let micros = of_day % US_PER_SECOND;
+    let fraction = match micros {
+        0 => String::new(),
+        m if m % 1000 == 0 => format!(".{:03}", m / 1000),
+        m => format!(".{:06}", m),
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        fraction
+    )
+}
+
+/// The `Timestamp` at midnight of a `Date64` value.
+pub fn date_to_timestamp(ms: i64) -> i64 {
+    ms.saturating_mul(1000)
+}
@@ -5,7 +5,10 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::hash::PartitionHasher;
+use crate::pretty::{BatchSummary, ColumnSummary};
 use crate::schema::DataType;
+use crate::stats::ColumnStats;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Scalar {
@@ -17,7 +20,12 @@ pub enum Scalar {
     F64(f64),
     Str(String),
     Bin(Vec<u8>),
-    // TODO: Add Date/Time/Decimal, etc.
+    /// Days as milliseconds since the Unix epoch (a date at midnight UTC).
+    Date64(i64),
+    /// Microseconds since the Unix epoch, UTC.
+    Timestamp(i64),
+    /// Unscaled value and scale: `Decimal128(12345, 2)` is `123.45`.
+    Decimal128(i128, u8),
 }
 
 impl Scalar {
@@ -31,6 +39,9 @@ impl Scalar {
             Scalar::F64(_) => DataType::Float64,
             Scalar::Str(_) => DataType::Utf8,
             Scalar::Bin(_) => DataType::Binary,
+            Scalar::Date64(_) => DataType::Date64,
+            Scalar::Timestamp(_) => DataType::Timestamp,
+            Scalar::Decimal128(..) => DataType::Decimal128,
         }
     }
 }
@@ -63,6 +74,45 @@ impl RowBatch {
         self.columns.first().map(|c| c.len()).unwrap_or(0)
     }
 
+    /// The first `n` rows (all rows if there are fewer).
+    pub fn head(&self, n: usize) -> RowBatch {
+        RowBatch {
+            columns: self
+                .columns
+                .iter()
+                .map(|c| Column {
+                    name: c.name.clone(),
+                    values: c.values.iter().take(n).cloned().collect(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Per-column type, null count and min/max. Prints as a table.
+    pub fn describe(&self) -> BatchSummary {
+        let columns = self
+            .columns
+            .iter()
+            .map(|c| {
+                let mut stats = ColumnStats::new();
+                c.values.iter().for_each(|v| stats.update(v));
+                ColumnSummary {
+                    name: c.name.clone(),
+                    data_type: c
+                        .values
+                        .iter()
+                        .find(|v| !matches!(v, Scalar::Null))
+                        .map(Scalar::data_type),
+                    stats,
+                }
+            })
+            .collect();
+        BatchSummary {
+            rows: self.num_rows(),
+            columns,
+        }
+    }
+
     /// Sort rows by the specified columns (in order).
     ///
     /// Creates a vector of (sort_key_tuple, original_index), sorts it,
@@ -118,6 +168,16 @@ impl RowBatch {
         &self,
         hash_keys: &[String],
         num_partitions: usize,
+    ) -> Result<Vec<usize>, String> {
+        self.hash_columns_with(hash_keys, num_partitions, &PartitionHasher::default())
+    }
+
+    /// Like [`RowBatch::hash_columns`], but with an explicit (seeded) hash function.
+    pub fn hash_columns_with(
+        &self,
+        hash_keys: &[String],
+        num_partitions: usize,
+        hasher: &PartitionHasher,
     ) -> Result<Vec<usize>, String> {
         let num_rows = self.num_rows();
         if num_rows == 0 {
@@ -137,14 +197,15 @@ impl RowBatch {
 
         // Compute hash for each row
         let mut result = Vec::with_capacity(num_rows);
+        let mut key_values: Vec<&Scalar> = Vec::with_capacity(key_indices.len());
         for row_idx in 0..num_rows {
-            let mut hasher = blake3::Hasher::new();
-            for &col_idx in &key_indices {
-                hash_scalar(&self.columns[col_idx].values[row_idx], &mut hasher);
-            }
-            let hash = hasher.finalize();
-            let hash_u64 = u64::from_le_bytes(hash.as_bytes()[0..8].try_into().unwrap());
-            result.push((hash_u64 as usize) % num_partitions);
+            key_values.clear();
+            key_values.extend(
+                key_indices
+                    .iter()
+                    .map(|&col_idx| &self.columns[col_idx].values[row_idx]),
+            );
+            result.push(hasher.partition(&key_values, num_partitions));
         }
 
         Ok(result)
@@ -199,7 +260,7 @@ fn scalar_tuple_cmp(a: &[Scalar], b: &[Scalar]) -> std::cmp::Ordering {
 /// Compare two scalars for sorting.
 ///
 /// Nulls are sorted first, then values are compared by type.
-fn scalar_cmp(a: &Scalar, b: &Scalar) -> std::cmp::Ordering {
+pub fn scalar_cmp(a: &Scalar, b: &Scalar) -> std::cmp::Ordering {
     use std::cmp::Ordering;
     use Scalar::*;
 
@@ -234,8 +295,11 @@ fn scalar_cmp(a: &Scalar, b: &Scalar) -> std::cmp::Ordering {
         }
         (Str(x), Str(y)) => x.cmp(y),
         (Bin(x), Bin(y)) => x.cmp(y),
-        // Mixed types: order by variant order
-        _ => scalar_type_order(a).cmp(&scalar_type_order(b)),
+        _ => match typed_cmp(a, b) {
+            Some(ordering) => ordering,
+            // Mixed types: order by variant order
+            None => scalar_type_order(a).cmp(&scalar_type_order(b)),
+        },
     }
 }
 
@@ -251,38 +315,54 @@ fn scalar_type_order(s: &Scalar) -> u8 {
         F64(_) => 5,
         Str(_) => 6,
         Bin(_) => 7,
+        Date64(_) => 8,
+        Timestamp(_) => 9,
+        Decimal128(..) => 10,
     }
 }
 
-/// Hash a scalar value into a hasher.
-fn hash_scalar(scalar: &Scalar, hasher: &mut blake3::Hasher) {
+/// Order of a date, timestamp or decimal against a value it compares with
+/// by meaning rather than by type: a date against a timestamp (as its
+/// midnight), either against ISO text, and a decimal against a decimal of
+/// any scale or against an integer or float. `None` for any other pair.
+pub(crate) fn typed_cmp(a: &Scalar, b: &Scalar) -> Option<std::cmp::Ordering> {
+    use crate::{decimal, temporal};
     use Scalar::*;
 
-    // Write type discriminant first
-    hasher.update(&[scalar_type_order(scalar)]);
-
-    match scalar {
-        Null => {}
-        Bool(b) => {
-            hasher.update(&[*b as u8]);
-        }
-        I32(i) => {
-            hasher.update(&i.to_le_bytes());
-        }
-        I64(i) => {
-            hasher.update(&i.to_le_bytes());
-        }
-        F32(f) => {
-            hasher.update(&f.to_bits().to_le_bytes());
-        }
-        F64(f) => {
-            hasher.update(&f.to_bits().to_le_bytes());
-        }
-        Str(s) => {
-            hasher.update(s.as_bytes());
+    // Dates and timestamps as microseconds.
+    let micros = |s: &Scalar| match s {
+        Date64(ms) => Some(temporal::date_to_timestamp(*ms)),
+        Timestamp(us) => Some(*us),
+        Str(text) => temporal::parse_timestamp(text),
+        _ => None,
+    };
+    match (a, b) {
+        (Date64(_) | Timestamp(_), Date64(_) | Timestamp(_) | Str(_))
+        | (Str(_), Date64(_) | Timestamp(_)) => Some(micros(a)?.cmp(&micros(b)?)),
+        (Decimal128(x, xs), Decimal128(y, ys)) => Some(decimal::cmp(*x, *xs, *y, *ys)),
+        (Decimal128(x, xs), I32(_) | I64(_)) => Some(decimal::cmp(*x, *xs, int_value(b)?, 0)),
+        (Decimal128(x, xs), F32(_) | F64(_)) => {
+            decimal::to_f64(*x, *xs).partial_cmp(&float_value(b)?)
         }
-        Bin(b) => {
-            hasher.update(b);
+        (I32(_) | I64(_) | F32(_) | F64(_), Decimal128(..)) => {
+            typed_cmp(b, a).map(std::cmp::Ordering::reverse)
         }
+        _ => None,
+    }
+}
+
+fn int_value(s: &Scalar) -> Option<i128> {
+    match s {
+        Scalar::I32(i) => Some(*i as i128),
+        Scalar::I64(i) => Some(*i as i128),
+        _ => None,
+    }
+}
+
+fn float_value(s: &Scalar) -> Option<f64> {
+    match s {
+        Scalar::F32(f) => Some(*f as f64),
+        Scalar::F64(f) => Some(*f),
+        _ => None,
     }
 }
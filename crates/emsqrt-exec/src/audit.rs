@@ -0,0 +1,235 @@
+//! Block execution audit log (`EngineConfig::audit_path`).
+//!
+//! With an audit path set, every run appends a header line and then one
+//! JSON line per executed block, holding the row count and content hash of
+//! each input batch and of the output batch. Two audits of the same plan can
+//! then be compared offline: `first_divergence` finds the first block whose
+//! recorded batches differ, which is where a replay stopped reproducing the
+//! original run.
+//!
+//! Batch hashes cover column names and values in order. Values hash through
+//! their row key encoding (see `emsqrt_core::key`), so `I32(1)` and `I64(1)`
+//! hash alike.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use emsqrt_core::key::write_key_scalar;
+use emsqrt_core::types::RowBatch;
+use serde::{Deserialize, Serialize};
+
+/// Row count and content hash of one batch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchDigest {
+    pub rows: usize,
+    /// Hex blake3 hash of the batch's column names and values.
+    pub hash: String,
+}
+
+impl BatchDigest {
+    pub fn of(batch: &RowBatch) -> Self {
+        let mut h = blake3::Hasher::new();
+        h.update(&(batch.columns.len() as u64).to_le_bytes());
+        for col in &batch.columns {
+            h.update(&(col.name.len() as u64).to_le_bytes());
+            h.update(col.name.as_bytes());
+            h.update(&(col.values.len() as u64).to_le_bytes());
+            for v in &col.values {
+                write_key_scalar(v, &mut |b| {
+                    h.update(b);
+                });
+            }
+        }
+        Self {
+            rows: batch.num_rows(),
+            hash: h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// The record of one executed block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockAudit {
+    pub block_id: u64,
+    pub op_id: u64,
+    /// Operator name, e.g. `filter`.
+    pub op: String,
+    /// Input batches in the order the operator received them.
+    pub inputs: Vec<BatchDigest>,
+    pub output: BatchDigest,
+}
+
+/// One line of an audit file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEntry {
+    /// Starts the records of one run.
+    Run {
+        run_id: String,
+        plan_hash: String,
+        te_hash: String,
+        started_ms: u64,
+    },
+    Block(BlockAudit),
+}
+
+/// The records of one run read back from an audit file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRun {
+    pub run_id: String,
+    pub plan_hash: String,
+    pub te_hash: String,
+    pub started_ms: u64,
+    pub blocks: Vec<BlockAudit>,
+}
+
+/// Appends audit entries to a file, one JSON object per line.
+pub struct AuditLog {
+    file: File,
+}
+
+impl AuditLog {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    /// Write one entry. Each entry is written with a single `write_all`, so
+    /// a run that dies mid-way leaves every completed block on disk.
+    pub fn append(&mut self, entry: &AuditEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+/// Read every run recorded in the audit file at `path`, oldest first.
+pub fn read_audit(path: impl AsRef<Path>) -> io::Result<Vec<AuditRun>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut runs: Vec<AuditRun> = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: AuditEntry = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+        })?;
+        match entry {
+            AuditEntry::Run {
+                run_id,
+                plan_hash,
+                te_hash,
+                started_ms,
+            } => runs.push(AuditRun {
+                run_id,
+                plan_hash,
+                te_hash,
+                started_ms,
+                blocks: Vec::new(),
+            }),
+            AuditEntry::Block(block) => match runs.last_mut() {
+                Some(run) => run.blocks.push(block),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: block record before any run header", i + 1),
+                    ))
+                }
+            },
+        }
+    }
+    Ok(runs)
+}
+
+/// Where two audited runs first differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the block in TE order.
+    pub index: usize,
+    pub block_id: u64,
+    pub op: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "block {} (#{}, {}): {}",
+            self.block_id, self.index, self.op, self.reason
+        )
+    }
+}
+
+/// The first block, in execution order, whose records differ between `a`
+/// and `b`, or `None` if every block matches.
+///
+/// A block whose inputs match but whose output differs points at that
+/// operator; differing inputs mean the divergence came from upstream data
+/// that was not audited (e.g. a source file that changed).
+pub fn first_divergence(a: &AuditRun, b: &AuditRun) -> Option<Divergence> {
+    let diverged = |index: usize, block: &BlockAudit, reason: String| Divergence {
+        index,
+        block_id: block.block_id,
+        op: block.op.clone(),
+        reason,
+    };
+    for (index, (x, y)) in a.blocks.iter().zip(&b.blocks).enumerate() {
+        if (x.block_id, x.op_id) != (y.block_id, y.op_id) {
+            return Some(diverged(
+                index,
+                x,
+                format!(
+                    "runs executed different blocks (block {} of op {} vs block {} of op {})",
+                    x.block_id, x.op_id, y.block_id, y.op_id
+                ),
+            ));
+        }
+        if x.inputs.len() != y.inputs.len() {
+            return Some(diverged(
+                index,
+                x,
+                format!("{} vs {} inputs", x.inputs.len(), y.inputs.len()),
+            ));
+        }
+        if let Some((i, (l, r))) = x
+            .inputs
+            .iter()
+            .zip(&y.inputs)
+            .enumerate()
+            .find(|(_, (l, r))| l != r)
+        {
+            return Some(diverged(index, x, describe(&format!("input {}", i), l, r)));
+        }
+        if x.output != y.output {
+            return Some(diverged(index, x, describe("output", &x.output, &y.output)));
+        }
+    }
+    let (shorter, longer) = if a.blocks.len() < b.blocks.len() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    longer.blocks.get(shorter.blocks.len()).map(|block| {
+        diverged(
+            shorter.blocks.len(),
+            block,
+            format!(
+                "one run stops after {} blocks, the other has {}",
+                shorter.blocks.len(),
+                longer.blocks.len()
+            ),
+        )
+    })
+}
+
+fn describe(what: &str, a: &BatchDigest, b: &BatchDigest) -> String {
+    if a.rows != b.rows {
+        format!("{} has {} vs {} rows", what, a.rows, b.rows)
+    } else {
+        format!("{} hashes differ ({} rows)", what, a.rows)
+    }
+}
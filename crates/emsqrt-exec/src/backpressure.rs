@@ -0,0 +1,58 @@
+//! Budget-driven read sizing for sources.
+//!
+//! Sources ask a `ReadSizer` how many rows to read before each unplanned read:
+//! a block without a planned range, or a chunk of the last block past its
+//! range. The sizer looks at live budget utilization and halves or doubles its
+//! size with a hysteresis band (see `SourceBatchConfig`), staying within
+//! `[min, max]`. It sets the size of one read, never how many rows a source
+//! returns in all.
+
+use emsqrt_core::config::SourceBatchConfig;
+
+#[derive(Debug, Clone)]
+pub struct ReadSizer {
+    cfg: SourceBatchConfig,
+    current: usize,
+    started: bool,
+}
+
+impl ReadSizer {
+    pub fn new(cfg: SourceBatchConfig) -> Self {
+        let min = cfg.min_rows.max(1);
+        let max = cfg.max_rows.max(min);
+        let current = cfg.initial_rows.clamp(min, max);
+        Self {
+            cfg: SourceBatchConfig {
+                min_rows: min,
+                max_rows: max,
+                ..cfg
+            },
+            current,
+            started: false,
+        }
+    }
+
+    /// Rows the next read would use, without adjusting.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Adjust to the budget's current utilization and return the next read size.
+    /// The first read always uses `initial_rows`.
+    pub fn next_rows(&mut self, used_bytes: usize, capacity_bytes: usize) -> usize {
+        if !std::mem::replace(&mut self.started, true) {
+            return self.current;
+        }
+        let utilization = if capacity_bytes == 0 {
+            1.0
+        } else {
+            used_bytes as f64 / capacity_bytes as f64
+        };
+        if utilization > self.cfg.shrink_above {
+            self.current = (self.current / 2).max(self.cfg.min_rows);
+        } else if utilization < self.cfg.grow_below {
+            self.current = self.current.saturating_mul(2).min(self.cfg.max_rows);
+        }
+        self.current
+    }
+}
@@ -0,0 +1,498 @@
+//! Order-insensitive diff of two sink outputs (`emsqrt compare-outputs`).
+//!
+//! Each side is read in chunks and its rows are sorted into runs of at most
+//! `run_rows` rows, which are spilled. A k-way merge then streams each side
+//! in (key, row) order and a merge-diff classifies rows as added, removed or
+//! changed. Memory is bounded by one run being sorted plus one spilled chunk
+//! per run during the merge.
+//!
+//! Values are normalized before comparison so the same data written as CSV
+//! (all text), JSONL or Parquet compares equal: numeric and boolean text is
+//! parsed, empty text is null, and integral floats equal integers. Columns
+//! are matched by name; a column present on one side only is reported as a
+//! schema change and left out of the row comparison.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+use serde::Serialize;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::key::encode_scalar;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::buf::ResumableFile;
+use emsqrt_io::readers::csv::CsvReader;
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_io::storage::build_storage_from_config;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::spill::SegmentMeta;
+use emsqrt_mem::{Codec, SpillManager};
+
+use crate::runtime::{detect_file_format, ExecError};
+
+/// Rows per spilled chunk; the merge holds one chunk per run.
+const CHUNK_ROWS: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    /// Columns identifying a row. Rows with equal keys but different values
+    /// are reported as changed; with no key, whole rows are compared and
+    /// differences show up as added/removed only.
+    pub key: Vec<String>,
+    /// Rows sorted in memory per run before spilling.
+    pub run_rows: usize,
+    /// Differing rows kept as examples in the report.
+    pub max_examples: usize,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            key: Vec::new(),
+            run_rows: 100_000,
+            max_examples: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One differing row; `left`/`right` are values in `OutputDiff::columns` order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffRow {
+    pub kind: DiffKind,
+    pub left: Option<Vec<Scalar>>,
+    pub right: Option<Vec<Scalar>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OutputDiff {
+    /// Columns compared (present on both sides, in left-hand order).
+    pub columns: Vec<String>,
+    /// Columns present on one side only.
+    pub schema_changes: Vec<String>,
+    pub left_rows: u64,
+    pub right_rows: u64,
+    /// Rows only in the right-hand output.
+    pub added: u64,
+    /// Rows only in the left-hand output.
+    pub removed: u64,
+    /// Keys present on both sides with different values.
+    pub changed: u64,
+    /// The first `max_examples` differences, in key order.
+    pub examples: Vec<DiffRow>,
+}
+
+impl OutputDiff {
+    pub fn is_identical(&self) -> bool {
+        self.schema_changes.is_empty() && self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+/// Diff two output files (format detected from the extension).
+pub fn compare_outputs(
+    left: &str,
+    right: &str,
+    opts: &CompareOptions,
+    cfg: &EngineConfig,
+) -> Result<OutputDiff, ExecError> {
+    let left_cols = OutputReader::columns(left)?;
+    let right_cols = OutputReader::columns(right)?;
+
+    let mut diff = OutputDiff::default();
+    for c in &left_cols {
+        if right_cols.contains(c) {
+            diff.columns.push(c.clone());
+        } else {
+            diff.schema_changes
+                .push(format!("column '{}' only in {}", c, left));
+        }
+    }
+    for c in right_cols.iter().filter(|c| !left_cols.contains(c)) {
+        diff.schema_changes
+            .push(format!("column '{}' only in {}", c, right));
+    }
+    let key_idx: Vec<usize> = opts
+        .key
+        .iter()
+        .map(|k| {
+            diff.columns.iter().position(|c| c == k).ok_or_else(|| {
+                ExecError::Compare(format!("key column '{}' is not in both outputs", k))
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let storage_cfg = cfg.storage_config();
+    let storage =
+        build_storage_from_config(&storage_cfg).map_err(|e| ExecError::Storage(e.to_string()))?;
+    let mut spill = SpillManager::new(storage, Codec::None, storage_cfg.root.clone());
+    let budget = MemoryBudgetImpl::new(cfg.mem_cap_bytes);
+    let run_rows = opts.run_rows.max(1);
+
+    let (left_runs, left_rows) = sort_runs(left, &diff.columns, &key_idx, run_rows, &mut spill)?;
+    let (right_runs, right_rows) = sort_runs(right, &diff.columns, &key_idx, run_rows, &mut spill)?;
+    diff.left_rows = left_rows;
+    diff.right_rows = right_rows;
+
+    let segments: Vec<SegmentMeta> = left_runs
+        .iter()
+        .chain(&right_runs)
+        .flatten()
+        .cloned()
+        .collect();
+    let result = {
+        let mut l = SortedStream::new(left_runs, &key_idx, &spill, &budget)?;
+        let mut r = SortedStream::new(right_runs, &key_idx, &spill, &budget)?;
+        merge_diff(&mut l, &mut r, opts.max_examples, &mut diff)
+    };
+    for seg in segments {
+        let _ = spill.delete_segment(&seg.name);
+    }
+    result.map(|_| diff)
+}
+
+/// Chunked reader over a CSV, JSONL or Parquet output.
+enum OutputReader {
+    Csv(CsvReader<ResumableFile>),
+    Jsonl(JsonlReader<ResumableFile>),
+    #[cfg(feature = "parquet")]
+    Parquet(emsqrt_io::readers::parquet::ParquetReader),
+}
+
+impl OutputReader {
+    fn open(path: &str) -> Result<Self, ExecError> {
+        let err = |e: emsqrt_io::error::Error| {
+            ExecError::Compare(format!("failed to open '{}': {}", path, e))
+        };
+        Ok(match detect_file_format(path, None) {
+            #[cfg(feature = "parquet")]
+            "parquet" => Self::Parquet(
+                emsqrt_io::readers::parquet::ParquetReader::from_path(path, None, CHUNK_ROWS)
+                    .map_err(err)?,
+            ),
+            #[cfg(not(feature = "parquet"))]
+            "parquet" => {
+                return Err(ExecError::Compare(format!(
+                    "'{}' is Parquet; rebuild with --features parquet",
+                    path
+                )))
+            }
+            "jsonl" => Self::Jsonl(JsonlReader::from_path(path).map_err(err)?),
+            _ => Self::Csv(CsvReader::from_path(path, true).map_err(err)?),
+        })
+    }
+
+    /// Column names of an output. JSONL has no header, so its columns are the
+    /// union of keys over the whole file.
+    fn columns(path: &str) -> Result<Vec<String>, ExecError> {
+        let mut reader = Self::open(path)?;
+        if matches!(reader, Self::Jsonl(_)) {
+            while reader.next_chunk(path)?.is_some() {}
+        }
+        Ok(match &reader {
+            Self::Csv(r) => r.schema().fields.iter().map(|f| f.name.clone()).collect(),
+            Self::Jsonl(r) => r.schema().fields.iter().map(|f| f.name.clone()).collect(),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(r) => r
+                .schema()
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect(),
+        })
+    }
+
+    fn next_chunk(&mut self, path: &str) -> Result<Option<RowBatch>, ExecError> {
+        let out = match self {
+            Self::Csv(r) => r.next_batch(CHUNK_ROWS),
+            Self::Jsonl(r) => r.next_batch(CHUNK_ROWS),
+            #[cfg(feature = "parquet")]
+            Self::Parquet(r) => r.next_batch(),
+        };
+        out.map_err(|e| ExecError::Compare(format!("failed to read '{}': {}", path, e)))
+    }
+}
+
+/// Normalize a value so equal data compares equal across output formats.
+fn normalize(value: &Scalar) -> Scalar {
+    match value {
+        Scalar::Str(s) => {
+            let t = s.trim();
+            if t.is_empty() {
+                Scalar::Null
+            } else if let Ok(i) = t.parse::<i64>() {
+                Scalar::I64(i)
+            } else if let Ok(b) = t.parse::<bool>() {
+                Scalar::Bool(b)
+            } else if let Ok(f) = t.parse::<f64>() {
+                normalize(&Scalar::F64(f))
+            } else {
+                value.clone()
+            }
+        }
+        Scalar::I32(i) => Scalar::I64(*i as i64),
+        Scalar::F32(f) => normalize(&Scalar::F64(*f as f64)),
+        Scalar::F64(f) if f.fract() == 0.0 && f.abs() < 9.0e15 => Scalar::I64(*f as i64),
+        other => other.clone(),
+    }
+}
+
+/// A row in comparison order: key bytes, then whole-row bytes.
+struct SortRow {
+    key: Vec<u8>,
+    row: Vec<u8>,
+    values: Vec<Scalar>,
+}
+
+impl SortRow {
+    fn new(values: Vec<Scalar>, key_idx: &[usize]) -> Self {
+        let mut row = Vec::new();
+        for v in &values {
+            encode_scalar(v, &mut row);
+        }
+        // Without key columns the whole row is the key.
+        let key = if key_idx.is_empty() {
+            row.clone()
+        } else {
+            let mut key = Vec::new();
+            for &i in key_idx {
+                encode_scalar(&values[i], &mut key);
+            }
+            key
+        };
+        Self { key, row, values }
+    }
+
+    fn cmp_order(&self, other: &Self) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| self.row.cmp(&other.row))
+    }
+}
+
+/// Read `path`, align it to `columns`, and write sorted runs of chunk segments.
+fn sort_runs(
+    path: &str,
+    columns: &[String],
+    key_idx: &[usize],
+    run_rows: usize,
+    spill: &mut SpillManager,
+) -> Result<(Vec<Vec<SegmentMeta>>, u64), ExecError> {
+    let mut reader = OutputReader::open(path)?;
+    let spill_id = spill.next_spill_id();
+    let mut runs = Vec::new();
+    let mut pending: Vec<SortRow> = Vec::new();
+    let mut total = 0u64;
+
+    let flush = |pending: &mut Vec<SortRow>, spill: &mut SpillManager| {
+        pending.sort_by(|a, b| a.cmp_order(b));
+        let mut run = Vec::new();
+        for chunk in pending.chunks(CHUNK_ROWS) {
+            let batch = RowBatch {
+                columns: columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| Column {
+                        name: name.clone(),
+                        values: chunk.iter().map(|r| r.values[i].clone()).collect(),
+                    })
+                    .collect(),
+            };
+            let seg = spill
+                .write_batch(&batch, spill_id, spill.next_run_index())
+                .map_err(|e| ExecError::Compare(format!("spilling sorted run: {}", e)))?;
+            run.push(seg);
+        }
+        pending.clear();
+        Ok::<_, ExecError>(run)
+    };
+
+    while let Some(batch) = reader.next_chunk(path)? {
+        let src: Vec<Option<&Column>> = columns
+            .iter()
+            .map(|name| batch.columns.iter().find(|c| &c.name == name))
+            .collect();
+        for row in 0..batch.num_rows() {
+            let values = src
+                .iter()
+                .map(|col| col.map_or(Scalar::Null, |c| normalize(&c.values[row])))
+                .collect();
+            pending.push(SortRow::new(values, key_idx));
+            total += 1;
+            if pending.len() >= run_rows {
+                runs.push(flush(&mut pending, spill)?);
+            }
+        }
+    }
+    if !pending.is_empty() {
+        runs.push(flush(&mut pending, spill)?);
+    }
+    Ok((runs, total))
+}
+
+/// Cursor over one spilled run, holding a single chunk in memory.
+struct RunCursor {
+    chunks: VecDeque<SegmentMeta>,
+    batch: RowBatch,
+    pos: usize,
+}
+
+impl RunCursor {
+    fn next_row(
+        &mut self,
+        key_idx: &[usize],
+        spill: &SpillManager,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<Option<SortRow>, ExecError> {
+        while self.pos >= self.batch.num_rows() {
+            let Some(seg) = self.chunks.pop_front() else {
+                return Ok(None);
+            };
+            self.batch = spill
+                .read_batch(&seg, budget)
+                .map_err(|e| ExecError::Compare(format!("reading sorted run: {}", e)))?;
+            self.pos = 0;
+        }
+        let values = self
+            .batch
+            .columns
+            .iter()
+            .map(|c| c.values[self.pos].clone())
+            .collect();
+        self.pos += 1;
+        Ok(Some(SortRow::new(values, key_idx)))
+    }
+}
+
+struct HeapEntry {
+    row: SortRow,
+    run: usize,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reverse for a min-heap; tie-break on run for a deterministic order.
+        other
+            .row
+            .cmp_order(&self.row)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+/// K-way merge of one side's runs, yielding rows in comparison order.
+struct SortedStream<'a> {
+    cursors: Vec<RunCursor>,
+    heap: BinaryHeap<HeapEntry>,
+    key_idx: &'a [usize],
+    spill: &'a SpillManager,
+    budget: &'a MemoryBudgetImpl,
+}
+
+impl<'a> SortedStream<'a> {
+    fn new(
+        runs: Vec<Vec<SegmentMeta>>,
+        key_idx: &'a [usize],
+        spill: &'a SpillManager,
+        budget: &'a MemoryBudgetImpl,
+    ) -> Result<Self, ExecError> {
+        let mut stream = Self {
+            cursors: runs
+                .into_iter()
+                .map(|chunks| RunCursor {
+                    chunks: chunks.into(),
+                    batch: RowBatch { columns: vec![] },
+                    pos: 0,
+                })
+                .collect(),
+            heap: BinaryHeap::new(),
+            key_idx,
+            spill,
+            budget,
+        };
+        for run in 0..stream.cursors.len() {
+            stream.advance(run)?;
+        }
+        Ok(stream)
+    }
+
+    fn advance(&mut self, run: usize) -> Result<(), ExecError> {
+        if let Some(row) = self.cursors[run].next_row(self.key_idx, self.spill, self.budget)? {
+            self.heap.push(HeapEntry { row, run });
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&SortRow> {
+        self.heap.peek().map(|e| &e.row)
+    }
+
+    fn next(&mut self) -> Result<Option<SortRow>, ExecError> {
+        let Some(entry) = self.heap.pop() else {
+            return Ok(None);
+        };
+        self.advance(entry.run)?;
+        Ok(Some(entry.row))
+    }
+}
+
+fn merge_diff(
+    left: &mut SortedStream,
+    right: &mut SortedStream,
+    max_examples: usize,
+    diff: &mut OutputDiff,
+) -> Result<(), ExecError> {
+    let record = |diff: &mut OutputDiff, kind, l: Option<SortRow>, r: Option<SortRow>| {
+        match kind {
+            DiffKind::Added => diff.added += 1,
+            DiffKind::Removed => diff.removed += 1,
+            DiffKind::Changed => diff.changed += 1,
+        }
+        if diff.examples.len() < max_examples {
+            diff.examples.push(DiffRow {
+                kind,
+                left: l.map(|r| r.values),
+                right: r.map(|r| r.values),
+            });
+        }
+    };
+
+    loop {
+        let order = match (left.peek(), right.peek()) {
+            (None, None) => return Ok(()),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(l), Some(r)) => l.key.cmp(&r.key),
+        };
+        match order {
+            Ordering::Less => record(diff, DiffKind::Removed, left.next()?, None),
+            Ordering::Greater => record(diff, DiffKind::Added, None, right.next()?),
+            Ordering::Equal => {
+                let (l, r) = (left.next()?, right.next()?);
+                let same = matches!((&l, &r), (Some(l), Some(r)) if l.row == r.row);
+                if !same {
+                    record(diff, DiffKind::Changed, l, r);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Runtime checks of declared schema constraints (`verify_constraints`).
+//!
+//! A verifier follows one source's blocks in TE order. Sort order is checked
+//! row to row, across block boundaries. Uniqueness keeps the encoded keys
+//! seen so far under a budget guard; if they stop fitting, that check is
+//! abandoned (reported as a warning) rather than failing the run.
+
+use std::collections::HashSet;
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::key::encode_row_key;
+use emsqrt_core::schema::Constraints;
+use emsqrt_core::types::{Column, RowBatch};
+use emsqrt_mem::guard::{BudgetGuardImpl, MemoryBudgetImpl};
+
+/// Approximate per-key hash-set overhead on top of the encoded key bytes.
+const KEY_OVERHEAD_BYTES: usize = 48;
+
+struct UniqueCheck {
+    columns: Vec<String>,
+    seen: HashSet<Vec<u8>>,
+    bytes: usize,
+}
+
+pub struct ConstraintVerifier {
+    source: String,
+    sorted_by: Vec<String>,
+    last_sort_key: Option<Vec<u8>>,
+    unique: Vec<UniqueCheck>,
+    guard: Option<BudgetGuardImpl>,
+}
+
+impl ConstraintVerifier {
+    /// `None` when the source declares nothing to check.
+    pub fn new(source: &str, constraints: &Constraints) -> Option<Self> {
+        if constraints.is_empty() {
+            return None;
+        }
+        Some(Self {
+            source: source.to_string(),
+            sorted_by: constraints.sorted_by.clone(),
+            last_sort_key: None,
+            unique: constraints
+                .unique_keys()
+                .map(|k| UniqueCheck {
+                    columns: k.to_vec(),
+                    seen: HashSet::new(),
+                    bytes: 0,
+                })
+                .collect(),
+            guard: None,
+        })
+    }
+
+    /// Check the next block. `Err` is a violation; `Ok(Some(_))` is a warning
+    /// that a uniqueness check was dropped for lack of memory.
+    pub fn check(
+        &mut self,
+        batch: &RowBatch,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<Option<String>, String> {
+        let mut key = Vec::new();
+
+        if !self.sorted_by.is_empty() {
+            let cols = columns(batch, &self.sorted_by, &self.source)?;
+            for row in 0..batch.num_rows() {
+                encode_row_key(&cols, row, &mut key);
+                if self.last_sort_key.as_ref().is_some_and(|last| key < *last) {
+                    return Err(format!(
+                        "source '{}' is not sorted by ({}) at row {} of a block",
+                        self.source,
+                        self.sorted_by.join(", "),
+                        row
+                    ));
+                }
+                self.last_sort_key = Some(key.clone());
+            }
+        }
+
+        let mut warning = None;
+        let mut i = 0;
+        while i < self.unique.len() {
+            let others: usize =
+                self.unique.iter().map(|u| u.bytes).sum::<usize>() - self.unique[i].bytes;
+            let check = &mut self.unique[i];
+            let cols = columns(batch, &check.columns, &self.source)?;
+            let mut fits = true;
+            for row in 0..batch.num_rows() {
+                encode_row_key(&cols, row, &mut key);
+                if check.seen.contains(&key) {
+                    return Err(format!(
+                        "source '{}' has duplicate values for unique key ({})",
+                        self.source,
+                        check.columns.join(", ")
+                    ));
+                }
+                check.bytes += key.len() + KEY_OVERHEAD_BYTES;
+                check.seen.insert(key.clone());
+                if !grow(&mut self.guard, budget, others + check.bytes) {
+                    fits = false;
+                    break;
+                }
+            }
+            if fits {
+                i += 1;
+            } else {
+                let dropped = self.unique.remove(i);
+                warning = Some(format!(
+                    "uniqueness of ({}) on source '{}' not verified: keys exceed the memory budget",
+                    dropped.columns.join(", "),
+                    self.source
+                ));
+                let total = self.unique.iter().map(|u| u.bytes).sum();
+                grow(&mut self.guard, budget, total);
+            }
+        }
+        Ok(warning)
+    }
+}
+
+/// Resize the guard to `total` key bytes; false if the budget refuses.
+fn grow(guard: &mut Option<BudgetGuardImpl>, budget: &MemoryBudgetImpl, total: usize) -> bool {
+    match guard {
+        Some(guard) => guard.try_resize(total),
+        None => {
+            *guard = budget.try_acquire(total, "verify_constraints");
+            guard.is_some()
+        }
+    }
+}
+
+fn columns<'a>(
+    batch: &'a RowBatch,
+    names: &[String],
+    source: &str,
+) -> Result<Vec<&'a Column>, String> {
+    names
+        .iter()
+        .map(|name| {
+            batch
+                .columns
+                .iter()
+                .find(|c| &c.name == name)
+                .ok_or_else(|| {
+                    format!(
+                        "constraint column '{}' not found in source '{}'",
+                        name, source
+                    )
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,416 @@
+//! Continuous micro-batch mode (`emsqrt run --follow`).
+//!
+//! A `Follower` tails the pipeline's file sources: data appended to a source
+//! file, or new files matching a `*`/`?` pattern in the source's file name,
+//! is cut into a delta file (complete lines only) under the spill directory.
+//! Each poll that finds new data re-runs the program against the deltas with
+//! the same memory cap, appending to the sinks, and yields that run's manifest.
+//!
+//! Only row-wise pipelines qualify (scan, filter, filter_in, map, project,
+//! lateral explode, union, sink into CSV or JSONL): their output for the new rows does
+//! not depend on rows already processed. Records must not span lines.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::id::OpId;
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_io::files::wildcard_match;
+use emsqrt_planner::physical::PhysicalProgram;
+use emsqrt_te::tree_eval::TePlan;
+
+use crate::metrics::LiveMetrics;
+use crate::runtime::{check_sandbox, detect_file_format, Engine, ExecError};
+
+/// Operators whose output for new rows is independent of earlier rows.
+const ROW_WISE_OPS: &[&str] = &[
+    "source",
+    "filter",
+    "filter_in",
+    "map",
+    "project",
+    "lateral_explode",
+    "union",
+    "sink",
+];
+
+/// When to stop following.
+#[derive(Debug, Clone)]
+pub struct FollowOptions {
+    /// Wait between polls that found no new data.
+    pub poll_interval: Duration,
+    /// Stop after this many micro-batches.
+    pub max_batches: Option<u64>,
+    /// Stop once no new data has arrived for this long.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for FollowOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            max_batches: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+/// One executed micro-batch.
+#[derive(Debug, Clone)]
+pub struct MicroBatch {
+    pub index: u64,
+    /// New source rows processed by this micro-batch.
+    pub rows_in: u64,
+    pub manifest: RunManifest,
+}
+
+/// Read position within one source file.
+#[derive(Debug, Default)]
+struct FileCursor {
+    offset: u64,
+    /// CSV header line, once read.
+    header: Option<Vec<u8>>,
+}
+
+struct FollowedSource {
+    op: OpId,
+    /// File path, possibly with wildcards in the file name.
+    pattern: String,
+    format: &'static str,
+    /// Rows the TE plan's blocks for this source are guaranteed to read.
+    capacity_rows: u64,
+    files: BTreeMap<PathBuf, FileCursor>,
+}
+
+pub struct Follower {
+    engine: Engine,
+    program: PhysicalProgram,
+    te: TePlan,
+    sources: Vec<FollowedSource>,
+    delta_dir: PathBuf,
+    batches: u64,
+}
+
+impl Follower {
+    pub fn new(
+        program: PhysicalProgram,
+        te: TePlan,
+        mut cfg: EngineConfig,
+    ) -> Result<Self, ExecError> {
+        for binding in program.bindings.values() {
+            if !ROW_WISE_OPS.contains(&binding.key.as_str()) {
+                return Err(ExecError::Invalid(format!(
+                    "follow mode needs a row-wise pipeline; '{}' depends on rows already processed",
+                    binding.key
+                )));
+            }
+            if binding.key == "sink" {
+                let format = binding.config.get("format").and_then(|v| v.as_str());
+                if !matches!(format.unwrap_or("csv"), "csv" | "jsonl") {
+                    return Err(ExecError::Invalid(
+                        "follow mode can only append to CSV and JSONL sinks".into(),
+                    ));
+                }
+            }
+        }
+
+        // Check the real sources once; micro-batches read deltas from the spill dir.
+        let delta_dir = Path::new(&cfg.spill_dir).join("follow");
+        if let Some(sandbox) = &mut cfg.sandbox {
+            check_sandbox(sandbox, &program)?;
+            sandbox
+                .read_allow
+                .push(delta_dir.to_string_lossy().into_owned());
+        }
+
+        // Source blocks read their planned ranges; the open-ended last one also
+        // takes a first `ReadSizer` read (`initial_rows`) past its range.
+        let batch = &cfg.source_batch;
+        let min_rows = batch.min_rows.max(1);
+        let tail_rows = batch
+            .initial_rows
+            .clamp(min_rows, batch.max_rows.max(min_rows)) as u64;
+        let mut sources = Vec::new();
+        for (op, binding) in &program.bindings {
+            if binding.key != "source" {
+                continue;
+            }
+            let uri = binding
+                .config
+                .get("source")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let pattern = uri.strip_prefix("file://").unwrap_or(uri);
+            if pattern.contains("://") {
+                return Err(ExecError::Invalid(format!(
+                    "follow mode needs local file sources, got '{}'",
+                    uri
+                )));
+            }
+            let declared = binding.config["schema"]["format"].as_str();
+            let format = detect_file_format(pattern, declared);
+            if format == "parquet" || format == "avro" {
+                return Err(ExecError::Invalid(format!(
+                    "follow mode cannot tail {} source '{}'",
+                    if format == "avro" { "Avro" } else { "Parquet" },
+                    uri
+                )));
+            }
+            let ranges: Vec<(u64, u64)> = te
+                .order
+                .iter()
+                .filter(|b| b.op == *op)
+                .filter_map(|b| b.range_rows)
+                .collect();
+            let Some(&(last_start, last_end)) = ranges.iter().max_by_key(|(_, end)| *end) else {
+                return Err(ExecError::Invalid(format!(
+                    "TE plan has no ranged blocks for source '{}'",
+                    uri
+                )));
+            };
+            let last = last_end - last_start;
+            let planned: u64 = ranges.iter().map(|(start, end)| end - start).sum();
+            let capacity_rows = planned - last + last.max(tail_rows);
+            sources.push(FollowedSource {
+                op: *op,
+                pattern: pattern.to_string(),
+                format,
+                capacity_rows,
+                files: BTreeMap::new(),
+            });
+        }
+        if sources.is_empty() {
+            return Err(ExecError::Invalid(
+                "pipeline has no sources to follow".into(),
+            ));
+        }
+
+        fs::create_dir_all(&delta_dir)
+            .map_err(|e| ExecError::Storage(format!("create {}: {}", delta_dir.display(), e)))?;
+        Ok(Self {
+            engine: Engine::new(cfg)?,
+            program,
+            te,
+            sources,
+            delta_dir,
+            batches: 0,
+        })
+    }
+
+    /// Counters of the micro-batch runs (see [`Engine::live_metrics`]).
+    pub fn live_metrics(&self) -> LiveMetrics {
+        self.engine.live_metrics()
+    }
+
+    /// Micro-batches executed so far.
+    pub fn batches(&self) -> u64 {
+        self.batches
+    }
+
+    /// Run one micro-batch over data that arrived since the last one.
+    /// `None` if no source has new complete lines.
+    pub fn poll(&mut self) -> Result<Option<MicroBatch>, ExecError> {
+        let index = self.batches;
+        let mut program = self.program.clone();
+        let mut warnings = Vec::new();
+        let mut deltas = Vec::new();
+        let mut rows_in = 0;
+
+        for source in &mut self.sources {
+            let delta = self
+                .delta_dir
+                .join(format!("batch-{}-op{}.{}", index, source.op, source.format));
+            rows_in += source.cut_delta(&delta, &mut warnings)?;
+            if let Some(binding) = program.bindings.get_mut(&source.op) {
+                binding.config["source"] = delta.to_string_lossy().into_owned().into();
+            }
+            deltas.push(delta);
+        }
+
+        let result = if rows_in == 0 {
+            Ok(None)
+        } else {
+            // The first micro-batch starts the sinks (and dead-letter files)
+            // fresh, like a normal run.
+            if index > 0 {
+                for binding in program.bindings.values_mut() {
+                    if binding.key == "sink" || binding.key == "source" {
+                        binding.config["append"] = true.into();
+                    }
+                }
+            }
+            self.engine.run(&program, &self.te).map(|mut manifest| {
+                manifest.micro_batch = Some(index);
+                manifest.warnings.extend(warnings);
+                Some(MicroBatch {
+                    index,
+                    rows_in,
+                    manifest,
+                })
+            })
+        };
+        for delta in deltas {
+            let _ = fs::remove_file(delta);
+        }
+        if let Ok(Some(_)) = &result {
+            self.batches += 1;
+        }
+        result
+    }
+
+    /// Poll until `opts` says to stop, handing each micro-batch to `on_batch`.
+    /// Returns the number of micro-batches run.
+    pub fn run(
+        &mut self,
+        opts: &FollowOptions,
+        mut on_batch: impl FnMut(&MicroBatch),
+    ) -> Result<u64, ExecError> {
+        let mut last_data = Instant::now();
+        let started = self.batches;
+        loop {
+            if opts
+                .max_batches
+                .is_some_and(|max| self.batches - started >= max)
+            {
+                break;
+            }
+            match self.poll()? {
+                Some(batch) => {
+                    on_batch(&batch);
+                    last_data = Instant::now();
+                }
+                None => {
+                    if opts
+                        .idle_timeout
+                        .is_some_and(|idle| last_data.elapsed() >= idle)
+                    {
+                        break;
+                    }
+                    std::thread::sleep(opts.poll_interval);
+                }
+            }
+        }
+        Ok(self.batches - started)
+    }
+}
+
+impl FollowedSource {
+    /// Copy up to `capacity_rows` new complete lines into `delta`; returns the row count.
+    fn cut_delta(&mut self, delta: &Path, warnings: &mut Vec<String>) -> Result<u64, ExecError> {
+        let io_err = |path: &Path, e: std::io::Error| {
+            ExecError::Storage(format!("follow {}: {}", path.display(), e))
+        };
+        for path in
+            expand_pattern(&self.pattern).map_err(|e| io_err(Path::new(&self.pattern), e))?
+        {
+            self.files.entry(path).or_default();
+        }
+
+        let mut out = BufWriter::new(File::create(delta).map_err(|e| io_err(delta, e))?);
+        let mut header: Option<Vec<u8>> = None;
+        let mut rows = 0;
+        let mut line = Vec::new();
+        for (path, cursor) in &mut self.files {
+            if rows >= self.capacity_rows {
+                break;
+            }
+            let len = match fs::metadata(path) {
+                Ok(meta) => meta.len(),
+                Err(_) => continue, // removed since it was listed
+            };
+            if len < cursor.offset {
+                warnings.push(format!(
+                    "{} shrank below the followed offset; reading it again from the start",
+                    path.display()
+                ));
+                *cursor = FileCursor::default();
+            }
+            if len == cursor.offset {
+                continue;
+            }
+
+            let mut file = File::open(path).map_err(|e| io_err(path, e))?;
+            file.seek(SeekFrom::Start(cursor.offset))
+                .map_err(|e| io_err(path, e))?;
+            let mut reader = BufReader::new(file);
+            while rows < self.capacity_rows {
+                line.clear();
+                let n = reader
+                    .read_until(b'\n', &mut line)
+                    .map_err(|e| io_err(path, e))?;
+                // A line without its newline is still being written.
+                if n == 0 || line.last() != Some(&b'\n') {
+                    break;
+                }
+                cursor.offset += n as u64;
+                if self.format == "csv" && cursor.header.is_none() {
+                    cursor.header = Some(line.clone());
+                    continue;
+                }
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    continue;
+                }
+                if self.format == "csv" {
+                    let file_header = cursor.header.as_ref().unwrap();
+                    match &header {
+                        None => {
+                            out.write_all(file_header).map_err(|e| io_err(delta, e))?;
+                            header = Some(file_header.clone());
+                        }
+                        Some(h) if h != file_header => {
+                            return Err(ExecError::Invalid(format!(
+                                "{} has a different CSV header than other files matching '{}'",
+                                path.display(),
+                                self.pattern
+                            )));
+                        }
+                        Some(_) => {}
+                    }
+                }
+                out.write_all(&line).map_err(|e| io_err(delta, e))?;
+                rows += 1;
+            }
+        }
+        out.flush().map_err(|e| io_err(delta, e))?;
+        Ok(rows)
+    }
+}
+
+/// Files matching `pattern`, sorted; wildcards are only expanded in the file name.
+fn expand_pattern(pattern: &str) -> std::io::Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        // A plain file is followed once it exists.
+        return Ok(if path.exists() {
+            vec![path.to_path_buf()]
+        } else {
+            Vec::new()
+        });
+    }
+    let dir = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let mut matches = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file()
+            && wildcard_match(
+                name.as_bytes(),
+                entry.file_name().to_string_lossy().as_bytes(),
+            )
+        {
+            matches.push(entry.path());
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
@@ -0,0 +1,377 @@
+//! I/O preflight of a pipeline (`emsqrt validate --check-io`).
+//!
+//! Every file a source would read is opened and its first rows sampled: a
+//! CSV header (or the keys of JSONL records) must name the declared columns,
+//! as the source's `align` policy requires, and the sampled values must parse
+//! as their declared types. Sink destinations (and dead-letter files) must be
+//! files that can be created in an existing directory. Nothing past the
+//! sample is read and nothing is left behind, and every problem is reported
+//! rather than the first.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::Serialize;
+
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::{DataType, Schema};
+use emsqrt_core::types::Scalar;
+use emsqrt_io::files::{is_multi_file, list_source_files};
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_planner::physical::PhysicalProgram;
+
+use crate::memtable::mem_table_name;
+use crate::runtime::{
+    detect_file_format, has_type, normalize_nulls, parse_cell, raw_json, source_path,
+};
+
+/// One problem with a source or sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IoIssue {
+    /// File (or URI) the problem is with.
+    pub endpoint: String,
+    pub message: String,
+}
+
+impl fmt::Display for IoIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.endpoint, self.message)
+    }
+}
+
+/// Check the sources and sinks of `program`, sampling up to `sample_rows`
+/// rows of each source file. Empty if every endpoint looks usable.
+pub fn check_io(
+    program: &PhysicalProgram,
+    config: &EngineConfig,
+    sample_rows: usize,
+) -> Vec<IoIssue> {
+    let mut issues = Issues::default();
+    for binding in program.bindings.values() {
+        let str_of = |pointer: &str| binding.config.pointer(pointer).and_then(|v| v.as_str());
+        match binding.key.as_str() {
+            "source" => {
+                let Some(uri) = str_of("/source") else {
+                    continue;
+                };
+                // A schema that does not deserialize reads as no columns, as in the engine.
+                let schema: Schema = binding
+                    .config
+                    .get("schema")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .unwrap_or_else(|| Schema::new(vec![]));
+                check_source(
+                    uri,
+                    &schema,
+                    config.binary_encoding,
+                    sample_rows,
+                    &mut issues,
+                );
+                if let Some(path) = str_of("/schema/parse_errors/dead_letter") {
+                    check_writable(path, &mut issues);
+                }
+            }
+            "sink" => {
+                let Some(uri) = str_of("/destination") else {
+                    continue;
+                };
+                if mem_table_name(uri).is_some() {
+                    continue;
+                }
+                if let Some(message) = str_of("/format").and_then(sink_format_issue) {
+                    issues.add(uri, message);
+                }
+                check_writable(uri, &mut issues);
+            }
+            _ => {}
+        }
+    }
+    issues.0
+}
+
+#[derive(Default)]
+struct Issues(Vec<IoIssue>);
+
+impl Issues {
+    fn add(&mut self, endpoint: &str, message: impl Into<String>) {
+        self.0.push(IoIssue {
+            endpoint: endpoint.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Values of one column that did not parse: how many, and the first.
+struct BadValues {
+    count: usize,
+    first_line: Option<u64>,
+    first_value: String,
+}
+
+fn check_source(
+    uri: &str,
+    schema: &Schema,
+    binary: BinaryEncoding,
+    sample_rows: usize,
+    issues: &mut Issues,
+) {
+    if mem_table_name(uri).is_some() {
+        return;
+    }
+    let path = source_path(uri);
+    let files = if is_multi_file(path) {
+        match list_source_files(path) {
+            Ok(files) if files.is_empty() => {
+                issues.add(uri, "matches no files");
+                return;
+            }
+            Ok(files) => files,
+            Err(e) => {
+                issues.add(uri, format!("cannot list files: {}", e));
+                return;
+            }
+        }
+    } else {
+        vec![path.to_string()]
+    };
+
+    for file_path in &files {
+        let file = match File::open(file_path) {
+            Ok(file) => file,
+            Err(e) => {
+                issues.add(file_path, format!("cannot open source: {}", e));
+                continue;
+            }
+        };
+        // Declared layouts and binary formats are only checked to open.
+        if schema.layout.is_some() {
+            continue;
+        }
+        match detect_file_format(file_path, schema.format.map(|f| f.as_str())) {
+            "csv" => check_csv(file_path, file, schema, binary, sample_rows, issues),
+            "jsonl" => check_jsonl(file_path, file, schema, binary, sample_rows, issues),
+            _ => {}
+        }
+    }
+}
+
+fn check_csv(
+    path: &str,
+    file: File,
+    schema: &Schema,
+    binary: BinaryEncoding,
+    sample_rows: usize,
+    issues: &mut Issues,
+) {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(BufReader::new(file));
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            issues.add(path, format!("cannot read CSV header: {}", e));
+            return;
+        }
+    };
+    if schema.fields.is_empty() {
+        return;
+    }
+    let col_indices: Vec<Option<usize>> = schema
+        .fields
+        .iter()
+        .map(|field| headers.iter().position(|h| h.trim() == field.name.trim()))
+        .collect();
+    check_columns(
+        path,
+        schema,
+        headers.iter().map(str::trim).collect(),
+        issues,
+    );
+
+    let mut bad: BTreeMap<usize, BadValues> = BTreeMap::new();
+    let mut sampled = 0;
+    for record in reader.records().take(sample_rows) {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                issues.add(path, format!("cannot read CSV record: {}", e));
+                break;
+            }
+        };
+        sampled += 1;
+        let line = record.position().map(|p| p.line());
+        for (i, (field, col)) in schema.fields.iter().zip(&col_indices).enumerate() {
+            let Some(raw) = col.and_then(|c| record.get(c)) else {
+                continue;
+            };
+            let Some(value) = schema.null_options.normalize(raw) else {
+                continue;
+            };
+            if value.is_empty() || parse_cell(&field.data_type, value, binary).is_some() {
+                continue;
+            }
+            note_bad(&mut bad, i, line, value.to_string());
+        }
+    }
+    report_bad(path, schema, &bad, sampled, issues);
+}
+
+fn check_jsonl(
+    path: &str,
+    file: File,
+    schema: &Schema,
+    binary: BinaryEncoding,
+    sample_rows: usize,
+    issues: &mut Issues,
+) {
+    let reader = JsonlReader::from_reader(file)
+        .map(|r| r.with_flatten(schema.flatten).with_types(&schema.fields));
+    let batch = match reader.and_then(|mut r| r.next_batch(sample_rows)) {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return,
+        Err(e) => {
+            issues.add(path, format!("cannot read JSONL records: {}", e));
+            return;
+        }
+    };
+    if schema.fields.is_empty() || batch.num_rows() == 0 {
+        return;
+    }
+    check_columns(
+        path,
+        schema,
+        batch.columns.iter().map(|c| c.name.as_str()).collect(),
+        issues,
+    );
+
+    let mut batch = batch;
+    normalize_nulls(&mut batch, &schema.null_options);
+    let mut bad: BTreeMap<usize, BadValues> = BTreeMap::new();
+    for (i, field) in schema.fields.iter().enumerate() {
+        let Some(column) = batch.columns.iter().find(|c| c.name == field.name) else {
+            continue;
+        };
+        for (row, value) in column.values.iter().enumerate() {
+            let ok = match (&field.data_type, value) {
+                (_, Scalar::Null) => true,
+                (DataType::Binary, Scalar::Str(s)) => binary.decode(s).is_ok(),
+                (data_type, value) => has_type(value, data_type),
+            };
+            if !ok {
+                // Blank lines are skipped, so the record number is not a line.
+                let value = raw_json(value, binary).to_string();
+                note_bad(&mut bad, i, None, format!("{} (record {})", value, row + 1));
+            }
+        }
+    }
+    report_bad(path, schema, &bad, batch.num_rows(), issues);
+}
+
+/// Report declared columns the file lacks, and extra columns, as the
+/// schema's `align` policy would reject them.
+fn check_columns(path: &str, schema: &Schema, columns: Vec<&str>, issues: &mut Issues) {
+    if schema.align.fills_missing() {
+        let names: Vec<String> = schema.fields.iter().map(|f| f.name.clone()).collect();
+        if let Err(e) = schema.align.check(columns.iter().copied(), &names) {
+            issues.add(path, e);
+        }
+        return;
+    }
+    let missing: Vec<String> = schema
+        .fields
+        .iter()
+        .filter(|f| !columns.contains(&f.name.trim()))
+        .map(|f| format!("'{}'", f.name))
+        .collect();
+    if !missing.is_empty() {
+        issues.add(
+            path,
+            format!(
+                "missing column(s) {} (have {:?})",
+                missing.join(", "),
+                columns
+            ),
+        );
+    }
+}
+
+fn note_bad(bad: &mut BTreeMap<usize, BadValues>, field: usize, line: Option<u64>, value: String) {
+    bad.entry(field)
+        .or_insert(BadValues {
+            count: 0,
+            first_line: line,
+            first_value: value,
+        })
+        .count += 1;
+}
+
+fn report_bad(
+    path: &str,
+    schema: &Schema,
+    bad: &BTreeMap<usize, BadValues>,
+    sampled: usize,
+    issues: &mut Issues,
+) {
+    for (&i, values) in bad {
+        let field = &schema.fields[i];
+        let at = values
+            .first_line
+            .map_or(String::new(), |l| format!("line {}: ", l));
+        issues.add(
+            path,
+            format!(
+                "column '{}': {} of {} sampled values are not a valid {:?} (first: {}'{}')",
+                field.name, values.count, sampled, field.data_type, at, values.first_value
+            ),
+        );
+    }
+}
+
+/// Whether the engine can write `format`, given the features it was built with.
+fn sink_format_issue(format: &str) -> Option<String> {
+    let feature = match format {
+        "csv" | "jsonl" | "mem" => return None,
+        "parquet" => cfg!(feature = "parquet").then_some("parquet"),
+        "arrow" => cfg!(any(feature = "arrow", feature = "parquet")).then_some("arrow"),
+        "avro" => cfg!(feature = "avro").then_some("avro"),
+        other => return Some(format!("unsupported sink format '{}'", other)),
+    };
+    match feature {
+        Some(_) => None,
+        None => Some(format!(
+            "the {} sink format requires the `{}` feature",
+            format, format
+        )),
+    }
+}
+
+/// Check that `uri` can be written: an existing file opens for writing
+/// (without truncating it), and a new one can be created (and is removed).
+fn check_writable(uri: &str, issues: &mut Issues) {
+    let path = Path::new(uri.strip_prefix("file://").unwrap_or(uri));
+    if path.is_dir() {
+        issues.add(uri, "destination is a directory");
+        return;
+    }
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if !dir.is_dir() {
+        issues.add(uri, format!("directory '{}' does not exist", dir.display()));
+        return;
+    }
+    let result = if path.exists() {
+        OpenOptions::new().append(true).open(path).map(drop)
+    } else {
+        File::create(path).and_then(|_| fs::remove_file(path))
+    };
+    if let Err(e) = result {
+        issues.add(uri, format!("not writable: {}", e));
+    }
+}
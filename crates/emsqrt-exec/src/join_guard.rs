@@ -0,0 +1,185 @@
+//! Pre-checks of hash join output size (`EngineConfig::join_guard`).
+//!
+//! Before a `join_hash` block runs, up to `sample_rows` evenly spaced rows
+//! of each side are sampled and their keys counted. The pairs that match
+//! within the samples, scaled by both sampling rates, estimate the block's
+//! output rows; the sampled row widths turn that into bytes. When every row
+//! fits in the sample the estimate is exact. Filters fused into the join's
+//! inputs are applied first, so dropped rows do not count.
+
+use std::collections::HashMap;
+
+use emsqrt_core::config::{JoinGuardAction, JoinGuardConfig};
+use emsqrt_core::key::encode_row_key;
+use emsqrt_core::types::{Column, RowBatch};
+use emsqrt_operators::join::filter::InputFilters;
+use emsqrt_operators::join::hash::JoinType;
+
+use crate::runtime::scalar_bytes;
+
+/// Estimated output of one join block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JoinEstimate {
+    pub rows: u64,
+    pub bytes: u64,
+    /// Rows sampled from the left and right inputs.
+    pub sampled: (usize, usize),
+}
+
+/// Estimate the output of joining `left` and `right` on `on` from samples of
+/// at most `sample_rows` rows per side.
+pub fn estimate_join_output(
+    left: &RowBatch,
+    right: &RowBatch,
+    on: &[(String, String)],
+    join_type: JoinType,
+    sample_rows: usize,
+) -> Result<JoinEstimate, String> {
+    let left_keys = key_columns(left, on.iter().map(|(l, _)| l), "left")?;
+    let right_keys = key_columns(right, on.iter().map(|(_, r)| r), "right")?;
+    let left_sample = sample(left.num_rows(), sample_rows);
+    let right_sample = sample(right.num_rows(), sample_rows);
+
+    let mut counts: HashMap<Vec<u8>, (u64, u64)> = HashMap::new();
+    let mut buf = Vec::new();
+    for &row in &left_sample {
+        encode_row_key(&left_keys, row, &mut buf);
+        counts.entry(buf.clone()).or_default().0 += 1;
+    }
+    for &row in &right_sample {
+        encode_row_key(&right_keys, row, &mut buf);
+        if let Some(c) = counts.get_mut(buf.as_slice()) {
+            c.1 += 1;
+        }
+    }
+    let sampled_pairs: u64 = counts.values().map(|(l, r)| l * r).sum();
+
+    let (left_rows, right_rows) = (left.num_rows() as f64, right.num_rows() as f64);
+    let scale = |rows: f64, sampled: usize| {
+        if sampled == 0 {
+            0.0
+        } else {
+            rows / sampled as f64
+        }
+    };
+    let mut rows = sampled_pairs as f64
+        * scale(left_rows, left_sample.len())
+        * scale(right_rows, right_sample.len());
+    // Outer joins keep at least every row of their preserved side.
+    rows = match join_type {
+        JoinType::Inner => rows,
+        JoinType::Left => rows.max(left_rows),
+        JoinType::Right => rows.max(right_rows),
+        JoinType::Full => rows.max(left_rows).max(right_rows),
+    };
+
+    let width = |batch: &RowBatch, rows: &[usize]| {
+        if rows.is_empty() {
+            return 0.0;
+        }
+        let bytes: usize = rows
+            .iter()
+            .flat_map(|&row| {
+                batch
+                    .columns
+                    .iter()
+                    .map(move |c| scalar_bytes(&c.values[row]))
+            })
+            .sum();
+        bytes as f64 / rows.len() as f64
+    };
+    let row_bytes = width(left, &left_sample) + width(right, &right_sample);
+
+    Ok(JoinEstimate {
+        rows: rows.round() as u64,
+        bytes: (rows * row_bytes).round() as u64,
+        sampled: (left_sample.len(), right_sample.len()),
+    })
+}
+
+/// The guard for one `join_hash` operator.
+pub struct JoinGuard {
+    cfg: JoinGuardConfig,
+    on: Vec<(String, String)>,
+    join_type: JoinType,
+    filters: InputFilters,
+    warned: bool,
+}
+
+impl JoinGuard {
+    pub fn new(cfg: &JoinGuardConfig, on: Vec<(String, String)>, join_type: JoinType) -> Self {
+        Self {
+            cfg: cfg.clone(),
+            on,
+            join_type,
+            filters: InputFilters::default(),
+            warned: false,
+        }
+    }
+
+    /// Apply the join's fused input filters before estimating.
+    pub fn with_filters(mut self, filters: InputFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Check a block's `[left, right]` inputs. `Err` means the estimate is
+    /// over a bound and the action is `fail`; `Ok(Some(_))` is a warning,
+    /// given once per operator.
+    pub fn check(&mut self, inputs: &[RowBatch]) -> Result<Option<String>, String> {
+        let inputs = self.filters.apply(inputs).map_err(|e| e.to_string())?;
+        let [left, right] = inputs.as_ref() else {
+            return Ok(None);
+        };
+        let est =
+            estimate_join_output(left, right, &self.on, self.join_type, self.cfg.sample_rows)?;
+        let over = match (self.cfg.max_output_rows, self.cfg.max_output_bytes) {
+            (Some(max), _) if est.rows > max => {
+                format!("{} rows (max_output_rows {})", est.rows, max)
+            }
+            (_, Some(max)) if est.bytes > max => {
+                format!("{} bytes (max_output_bytes {})", est.bytes, max)
+            }
+            _ => return Ok(None),
+        };
+        let msg = format!(
+            "estimated output of {} from {} left and {} right rows (sampled {} and {}); \
+             check the join keys for a many-to-many match",
+            over,
+            left.num_rows(),
+            right.num_rows(),
+            est.sampled.0,
+            est.sampled.1
+        );
+        match self.cfg.on_exceed {
+            JoinGuardAction::Fail => Err(msg),
+            JoinGuardAction::Warn if self.warned => Ok(None),
+            JoinGuardAction::Warn => {
+                self.warned = true;
+                Ok(Some(msg))
+            }
+        }
+    }
+}
+
+/// Up to `max` row indices spread evenly over `rows` rows.
+fn sample(rows: usize, max: usize) -> Vec<usize> {
+    let n = rows.min(max);
+    (0..n).map(|i| i * rows / n).collect()
+}
+
+fn key_columns<'a, 'b>(
+    batch: &'a RowBatch,
+    names: impl Iterator<Item = &'b String>,
+    side: &str,
+) -> Result<Vec<&'a Column>, String> {
+    names
+        .map(|name| {
+            batch
+                .columns
+                .iter()
+                .find(|c| c.name == *name)
+                .ok_or_else(|| format!("{} join key '{}' not found", side, name))
+        })
+        .collect()
+}
@@ -0,0 +1,139 @@
+//! Evaluation of pipeline `let` steps (see `emsqrt_planner::dsl::lets`).
+//!
+//! Each `let` sub-pipeline runs as its own plan under the caller's engine
+//! config, with a `mem://` sink collecting its rows; the rows are reduced to
+//! one scalar, which becomes a parameter for the rest of the pipeline. The
+//! sink holds every output row, so a `let` over large data should filter
+//! down early (only the `column` being reduced is kept).
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan;
+use emsqrt_core::types::{scalar_cmp, RowBatch, Scalar};
+use emsqrt_planner::{
+    estimate_work, let_literal, let_stages, lower_to_physical, rules, LetReduce, LetStage,
+    TemplateParams,
+};
+use emsqrt_te::plan_te;
+
+use crate::memtable::{MemTables, MEM_SCHEME};
+use crate::runtime::{Engine, ExecError};
+
+/// `params` plus the value of every `let` step in `yaml_src`, in order, as
+/// expression literals. A `let` whose name is already in `params` is not run.
+pub fn resolve_lets(
+    yaml_src: &str,
+    params: &TemplateParams,
+    config: &EngineConfig,
+) -> Result<TemplateParams, ExecError> {
+    let stages = let_stages(yaml_src).map_err(|e| ExecError::Let(e.to_string()))?;
+    let mut params = params.clone();
+    for stage in stages {
+        if params.contains_key(&stage.name) {
+            continue;
+        }
+        let value = eval_let(&stage, &params, config)?;
+        let literal =
+            let_literal(&value).map_err(|e| ExecError::Let(format!("'{}': {}", stage.name, e)))?;
+        params.insert(stage.name, literal);
+    }
+    Ok(params)
+}
+
+/// Run one `let` sub-pipeline and reduce its rows.
+pub fn eval_let(
+    stage: &LetStage,
+    params: &TemplateParams,
+    config: &EngineConfig,
+) -> Result<Scalar, ExecError> {
+    let err = |e: String| ExecError::Let(format!("'{}': {}", stage.name, e));
+    let mut plan = stage.plan(params).map_err(|e| err(e.to_string()))?;
+    if let Some(column) = &stage.column {
+        plan = LogicalPlan::Project {
+            input: Box::new(plan),
+            columns: vec![column.clone()],
+        };
+    }
+    let table = format!("let.{}", stage.name);
+    let plan = rules::optimize(LogicalPlan::Sink {
+        input: Box::new(plan),
+        destination: format!("{}{}", MEM_SCHEME, table),
+        format: "mem".into(),
+    });
+    let program = lower_to_physical(&plan);
+    let te = plan_te(
+        &program.plan,
+        &estimate_work(&plan, None),
+        config.mem_cap_bytes,
+    )
+    .map_err(|e| err(format!("TE planning failed: {}", e)))?;
+
+    // The main run's audit log should hold only the main run.
+    let config = EngineConfig {
+        audit_path: None,
+        ..config.clone()
+    };
+    let tables = MemTables::new();
+    Engine::new(config)?
+        .with_mem_tables(tables.clone())
+        .run(&program, &te)?;
+    let batches = tables.take(&table).unwrap_or_default();
+    match reduce(stage, &batches).map_err(err)? {
+        Some(value) => Ok(value),
+        None => stage.default.clone().ok_or_else(|| {
+            err("the sub-pipeline yields no value; give the let a 'default'".into())
+        }),
+    }
+}
+
+/// `stage.reduce` over the value column of `batches`; `None` when there is
+/// no non-null value to reduce.
+fn reduce(stage: &LetStage, batches: &[RowBatch]) -> Result<Option<Scalar>, String> {
+    let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+    if stage.reduce == LetReduce::Count && stage.column.is_none() {
+        return Ok(Some(Scalar::I64(rows as i64)));
+    }
+    if stage.reduce == LetReduce::Single && rows > 1 {
+        return Err(format!(
+            "the sub-pipeline yields {} rows; use reduce: max, min, sum or count",
+            rows
+        ));
+    }
+    let mut values = Vec::new();
+    for batch in batches {
+        let [column] = batch.columns.as_slice() else {
+            let names: Vec<&str> = batch.columns.iter().map(|c| c.name.as_str()).collect();
+            return Err(format!(
+                "the sub-pipeline yields columns {:?}; name one with 'column'",
+                names
+            ));
+        };
+        values.extend(column.values.iter().filter(|v| !matches!(v, Scalar::Null)));
+    }
+    Ok(match stage.reduce {
+        LetReduce::Single => values.pop().cloned(),
+        LetReduce::Max => values.into_iter().max_by(|a, b| scalar_cmp(a, b)).cloned(),
+        LetReduce::Min => values.into_iter().min_by(|a, b| scalar_cmp(a, b)).cloned(),
+        LetReduce::Count => Some(Scalar::I64(values.len() as i64)),
+        LetReduce::Sum if values.is_empty() => None,
+        LetReduce::Sum => Some(sum(&values)?),
+    })
+}
+
+/// Integers sum to `I64`; any float makes the sum `F64`.
+fn sum(values: &[&Scalar]) -> Result<Scalar, String> {
+    let mut int: i64 = 0;
+    let mut float: Option<f64> = None;
+    for value in values {
+        match value {
+            Scalar::I32(v) => int = int.checked_add(*v as i64).ok_or("sum overflows Int64")?,
+            Scalar::I64(v) => int = int.checked_add(*v).ok_or("sum overflows Int64")?,
+            Scalar::F32(v) => *float.get_or_insert(0.0) += *v as f64,
+            Scalar::F64(v) => *float.get_or_insert(0.0) += v,
+            other => return Err(format!("cannot sum non-numeric value {:?}", other)),
+        }
+    }
+    Ok(match float {
+        Some(f) => Scalar::F64(f + int as f64),
+        None => Scalar::I64(int),
+    })
+}
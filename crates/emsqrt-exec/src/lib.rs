@@ -5,10 +5,40 @@
 //! Next steps: parallel block scheduling with bounded channels, real sources/sinks,
 //! and spill-aware operators.
 
+pub mod audit;
+pub mod backpressure;
+pub mod compare;
+pub mod constraints;
 pub mod failpoints;
+pub mod follow;
+pub mod io_check;
+pub mod join_guard;
+pub mod lets;
+pub mod memtable;
 pub mod metrics;
+pub mod pool;
+pub mod profile;
+pub mod reorder;
 pub mod replay;
+pub mod resources;
+pub mod results;
 pub mod runtime;
 pub mod scheduler;
+pub mod source_files;
+pub mod spill_space;
+pub mod stats_store;
+#[cfg(feature = "testkit")]
+pub mod testkit;
 
+pub use compare::{compare_outputs, CompareOptions, DiffKind, DiffRow, OutputDiff};
+pub use follow::{FollowOptions, Follower, MicroBatch};
+pub use io_check::{check_io, IoIssue};
+pub use lets::resolve_lets;
+pub use memtable::MemTables;
+pub use metrics::{LiveMetrics, MetricsServer};
+pub use pool::{EnginePool, JobHandle, PoolConfig, PoolJob};
+pub use profile::{profile_source, ColumnProfile, DataProfile, ProfileOptions};
 pub use runtime::{Engine, ExecError};
+pub use source_files::with_source_files;
+pub use spill_space::{estimate_spill, SpillEstimate};
+pub use stats_store::{read_stats_file, update_stats_file, StatsStore};
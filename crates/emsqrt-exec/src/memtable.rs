@@ -0,0 +1,253 @@
+//! In-memory tables behind `mem://` sources and sinks.
+//!
+//! An embedding program hands batches to a pipeline and takes its results
+//! back without touching the filesystem: a scan of `mem://orders` reads the
+//! table `orders`, and a sink to `mem://totals` collects into `totals`.
+//! Tables belong to a `MemTables` handle shared by clones, usually the one
+//! returned by `Engine::mem_tables`.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{Column, RowBatch};
+use emsqrt_operators::context::OpContext;
+use emsqrt_operators::plan::{Footprint, OpPlan};
+use emsqrt_operators::traits::{OpError, Operator};
+
+/// URI scheme of in-memory tables.
+pub const MEM_SCHEME: &str = "mem://";
+
+/// The table a `mem://name` URI refers to, or `None` for other URIs.
+pub fn mem_table_name(uri: &str) -> Option<&str> {
+    uri.strip_prefix(MEM_SCHEME)
+}
+
+/// Named tables of batches; clones share the same tables.
+#[derive(Debug, Clone, Default)]
+pub struct MemTables {
+    tables: Arc<Mutex<BTreeMap<String, Vec<RowBatch>>>>,
+}
+
+impl MemTables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set table `name` to `batches`, replacing what it held.
+    pub fn insert(&self, name: impl Into<String>, batches: Vec<RowBatch>) {
+        self.tables.lock().unwrap().insert(name.into(), batches);
+    }
+
+    /// Copy of table `name`'s batches.
+    pub fn get(&self, name: &str) -> Option<Vec<RowBatch>> {
+        self.tables.lock().unwrap().get(name).cloned()
+    }
+
+    /// Remove table `name` and return its batches.
+    pub fn take(&self, name: &str) -> Option<Vec<RowBatch>> {
+        self.tables.lock().unwrap().remove(name)
+    }
+
+    /// Names of all tables, in order.
+    pub fn names(&self) -> Vec<String> {
+        self.tables.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Rows `start..start + rows` of table `name`, across its batches.
+    fn slice(&self, name: &str, start: usize, rows: usize) -> Option<RowBatch> {
+        let tables = self.tables.lock().unwrap();
+        let batches = tables.get(name)?;
+        let mut out: Option<RowBatch> = None;
+        let mut skip = start;
+        let mut want = rows;
+        for batch in batches {
+            let n = batch.num_rows();
+            if skip >= n {
+                skip -= n;
+                continue;
+            }
+            let take = (n - skip).min(want);
+            let out = out.get_or_insert_with(|| RowBatch {
+                columns: batch
+                    .columns
+                    .iter()
+                    .map(|c| Column {
+                        name: c.name.clone(),
+                        values: Vec::new(),
+                    })
+                    .collect(),
+            });
+            for (dst, src) in out.columns.iter_mut().zip(&batch.columns) {
+                dst.values.extend_from_slice(&src.values[skip..skip + take]);
+            }
+            skip = 0;
+            want -= take;
+            if want == 0 {
+                break;
+            }
+        }
+        // Past the end: no rows, but the table's columns.
+        Some(out.unwrap_or_else(|| {
+            RowBatch {
+                columns: batches
+                    .first()
+                    .map(|b| {
+                        b.columns
+                            .iter()
+                            .map(|c| Column {
+                                name: c.name.clone(),
+                                values: Vec::new(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }
+        }))
+    }
+
+    /// Append `batch` to table `name`, creating it if needed.
+    fn append(&self, name: &str, batch: RowBatch) {
+        self.tables
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .push(batch);
+    }
+}
+
+/// Source reading rows of a `mem://` table.
+pub(crate) struct MemSourceOp {
+    pub(crate) table: String,
+    pub(crate) schema: Schema,
+    pub(crate) tables: MemTables,
+    // Rows handed out so far; where an unranged read continues
+    pub(crate) position: Mutex<usize>,
+}
+
+impl MemSourceOp {
+    /// Keep the declared schema's columns, in its order.
+    fn project(&self, batch: RowBatch) -> Result<RowBatch, OpError> {
+        if self.schema.fields.is_empty() {
+            return Ok(batch);
+        }
+        let mut columns = batch.columns;
+        let mut out = Vec::with_capacity(self.schema.fields.len());
+        for field in &self.schema.fields {
+            let pos = columns
+                .iter()
+                .position(|c| c.name == field.name)
+                .ok_or_else(|| {
+                    OpError::Schema(format!(
+                        "mem table '{}' has no column '{}'",
+                        self.table, field.name
+                    ))
+                })?;
+            out.push(columns.swap_remove(pos));
+        }
+        Ok(RowBatch { columns: out })
+    }
+}
+
+impl Operator for MemSourceOp {
+    fn name(&self) -> &'static str {
+        "source"
+    }
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        Footprint {
+            bytes_per_row: 1,
+            overhead_bytes: 0,
+        }
+    }
+    fn plan(&self, _input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        Err(OpError::Plan(
+            "source.plan should not be called at exec time".into(),
+        ))
+    }
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+    fn eval_block_with(
+        &self,
+        _inputs: &[RowBatch],
+        ctx: &OpContext,
+        _budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        // Unranged reads and the last block take everything that is left.
+        let mut position = self.position.lock().unwrap();
+        let (start, rows) = match ctx.range {
+            Some(r) if !r.open_end => (r.start as usize, r.len() as usize),
+            Some(r) => (r.start as usize, usize::MAX),
+            None => (*position, usize::MAX),
+        };
+        let batch = self.tables.slice(&self.table, start, rows).ok_or_else(|| {
+            OpError::Exec(format!(
+                "mem table '{}' does not exist; add it with MemTables::insert",
+                self.table
+            ))
+        })?;
+        *position = start + batch.num_rows();
+        self.project(batch)
+    }
+}
+
+/// Sink collecting batches into a `mem://` table.
+pub(crate) struct MemSinkOp {
+    pub(crate) table: String,
+    pub(crate) tables: MemTables,
+    // Keep the table's earlier batches (follow mode) instead of replacing them
+    pub(crate) append: bool,
+    // Whether the table was cleared for this run yet
+    pub(crate) started: Mutex<bool>,
+}
+
+impl MemSinkOp {
+    fn start(&self) {
+        let mut started = self.started.lock().unwrap();
+        if !std::mem::replace(&mut *started, true) && !self.append {
+            self.tables.insert(self.table.clone(), Vec::new());
+        }
+    }
+}
+
+impl Operator for MemSinkOp {
+    fn name(&self) -> &'static str {
+        "sink"
+    }
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        Footprint {
+            bytes_per_row: 0,
+            overhead_bytes: 0,
+        }
+    }
+    fn plan(&self, _input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        Err(OpError::Plan(
+            "sink.plan should not be called at exec time".into(),
+        ))
+    }
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        _budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("sink requires one input".into()))?;
+        self.start();
+        if !input.columns.is_empty() && input.num_rows() > 0 {
+            self.tables.append(&self.table, input.clone());
+        }
+        Ok(RowBatch { columns: vec![] })
+    }
+
+    fn finish(&self) -> Result<(), OpError> {
+        // A run that wrote nothing still leaves an (empty) table behind.
+        self.start();
+        Ok(())
+    }
+}
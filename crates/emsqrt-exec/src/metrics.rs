@@ -1,11 +1,21 @@
-//! Metrics/tracing hooks (starter).
+//! Metrics/tracing hooks and live run metrics.
 //!
 //! This module purposefully avoids pulling heavy telemetry stacks.
-//! Wire these up to OpenTelemetry/Prometheus in the binary layer.
+//! [`LiveMetrics`] renders the Prometheus text format itself, and with the
+//! `metrics-server` feature [`serve`] answers `GET /metrics` from a plain
+//! `std::net` listener.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use emsqrt_core::manifest::{BlockCost, OpTotals};
+use emsqrt_mem::MemoryBudgetImpl;
+
+use crate::runtime::ExecError;
 
 #[cfg(feature = "tracing")]
 pub fn emit_span(event: &str, key_values: &[(&str, String)]) {
-    let mut span = tracing::span!(tracing::Level::TRACE, "emsqrt", event);
+    let span = tracing::span!(tracing::Level::TRACE, "emsqrt", event);
     for (k, v) in key_values {
         tracing::trace!(%event, %k, %v, "metric");
     }
@@ -15,3 +25,293 @@ pub fn emit_span(event: &str, key_values: &[(&str, String)]) {
 #[cfg(not(feature = "tracing"))]
 pub fn emit_span(_event: &str, _key_values: &[(&str, String)]) { /* no-op */
 }
+
+/// Counters and gauges of the runs an [`Engine`](crate::Engine) executes,
+/// updated after every block and rendered in the Prometheus text format
+/// (see [`serve`]). Clones share the same counters, so one `LiveMetrics`
+/// can be given to several engines.
+#[derive(Clone, Default)]
+pub struct LiveMetrics {
+    inner: Arc<Mutex<LiveState>>,
+}
+
+#[derive(Default)]
+struct LiveState {
+    runs_started: u64,
+    runs_completed: u64,
+    runs_failed: u64,
+    /// Blocks of the current (or last) run: planned and completed.
+    run_blocks: u64,
+    run_blocks_completed: u64,
+    blocks_completed: u64,
+    spill_bytes_written: u64,
+    spill_bytes_read: u64,
+    /// Budget of the current (or last) run, read at scrape time.
+    budget: Option<MemoryBudgetImpl>,
+    operators: BTreeMap<(u64, String), OpTotals>,
+}
+
+impl LiveMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, LiveState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A run of `blocks` blocks under `budget` starts.
+    pub fn start_run(&self, blocks: usize, budget: &MemoryBudgetImpl) {
+        let mut state = self.state();
+        state.runs_started += 1;
+        state.run_blocks = blocks as u64;
+        state.run_blocks_completed = 0;
+        state.budget = Some(budget.clone());
+    }
+
+    /// A block finished.
+    pub fn record_block(&self, cost: &BlockCost) {
+        let mut state = self.state();
+        state.blocks_completed += 1;
+        state.run_blocks_completed += 1;
+        state.spill_bytes_written += cost.bytes_spilled;
+        state.spill_bytes_read += cost.bytes_spill_read;
+        let totals = state
+            .operators
+            .entry((cost.op_id, cost.op.clone()))
+            .or_default();
+        totals.blocks += 1;
+        totals.rows_in += cost.rows_in;
+        totals.rows_out += cost.rows_out;
+        totals.bytes_in += cost.bytes_in;
+        totals.bytes_out += cost.bytes_out;
+        totals.cpu_nanos += cost.cpu_nanos;
+        totals.wall_nanos += cost.wall_nanos;
+        totals.bytes_spilled += cost.bytes_spilled;
+        totals.bytes_spill_read += cost.bytes_spill_read;
+    }
+
+    /// The run that last started ended, successfully or not.
+    pub fn finish_run(&self, ok: bool) {
+        let mut state = self.state();
+        match ok {
+            true => state.runs_completed += 1,
+            false => state.runs_failed += 1,
+        }
+    }
+
+    /// All metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let state = self.state();
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, f64)>| {
+            out.push_str(&format!("# HELP emsqrt_{} {}\n", name, help));
+            out.push_str(&format!("# TYPE emsqrt_{} {}\n", name, kind));
+            for (labels, value) in samples {
+                out.push_str(&format!("emsqrt_{}{} {}\n", name, labels, value));
+            }
+        };
+        let one = |value: u64| vec![(String::new(), value as f64)];
+
+        metric(
+            "runs_started_total",
+            "counter",
+            "Runs started.",
+            one(state.runs_started),
+        );
+        metric(
+            "runs_completed_total",
+            "counter",
+            "Runs that finished successfully.",
+            one(state.runs_completed),
+        );
+        metric(
+            "runs_failed_total",
+            "counter",
+            "Runs that failed or were cancelled.",
+            one(state.runs_failed),
+        );
+        metric(
+            "blocks_completed_total",
+            "counter",
+            "TE blocks executed.",
+            one(state.blocks_completed),
+        );
+        metric(
+            "run_blocks",
+            "gauge",
+            "TE blocks in the current run.",
+            one(state.run_blocks),
+        );
+        metric(
+            "run_blocks_completed",
+            "gauge",
+            "TE blocks of the current run executed so far.",
+            one(state.run_blocks_completed),
+        );
+        let (reserved, cap) = state
+            .budget
+            .as_ref()
+            .map_or((0, 0), |b| (b.used_bytes(), b.capacity_bytes()));
+        metric(
+            "memory_reserved_bytes",
+            "gauge",
+            "Bytes reserved from the memory budget.",
+            one(reserved as u64),
+        );
+        metric(
+            "memory_cap_bytes",
+            "gauge",
+            "Memory budget capacity.",
+            one(cap as u64),
+        );
+        metric(
+            "spill_written_bytes_total",
+            "counter",
+            "Bytes written to spill storage.",
+            one(state.spill_bytes_written),
+        );
+        metric(
+            "spill_read_bytes_total",
+            "counter",
+            "Bytes read back from spill storage.",
+            one(state.spill_bytes_read),
+        );
+
+        // Per-operator throughput: rate() of these gives rows and bytes per second.
+        let per_op = |value: fn(&OpTotals) -> f64| {
+            state
+                .operators
+                .iter()
+                .map(|((op_id, op), t)| {
+                    (format!("{{op_id=\"{}\",op=\"{}\"}}", op_id, op), value(t))
+                })
+                .collect::<Vec<_>>()
+        };
+        metric(
+            "operator_blocks_total",
+            "counter",
+            "Blocks executed per operator.",
+            per_op(|t| t.blocks as f64),
+        );
+        metric(
+            "operator_rows_in_total",
+            "counter",
+            "Rows read per operator.",
+            per_op(|t| t.rows_in as f64),
+        );
+        metric(
+            "operator_rows_out_total",
+            "counter",
+            "Rows produced per operator.",
+            per_op(|t| t.rows_out as f64),
+        );
+        metric(
+            "operator_bytes_in_total",
+            "counter",
+            "Estimated bytes read per operator.",
+            per_op(|t| t.bytes_in as f64),
+        );
+        metric(
+            "operator_seconds_total",
+            "counter",
+            "Wall time spent in each operator's blocks.",
+            per_op(|t| t.wall_nanos as f64 / 1e9),
+        );
+        metric(
+            "operator_spill_written_bytes_total",
+            "counter",
+            "Bytes spilled per operator.",
+            per_op(|t| t.bytes_spilled as f64),
+        );
+        out
+    }
+}
+
+/// Handle of a running metrics endpoint; it stops serving when dropped.
+pub struct MetricsServer {
+    #[cfg(feature = "metrics-server")]
+    addr: std::net::SocketAddr,
+    #[cfg(feature = "metrics-server")]
+    stop: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl MetricsServer {
+    /// The address the endpoint listens on (useful with port 0).
+    #[cfg(feature = "metrics-server")]
+    pub fn local_addr(&self) -> std::net::SocketAddr {
+        self.addr
+    }
+}
+
+#[cfg(feature = "metrics-server")]
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Serve `metrics` at `http://<addr>/metrics` from a background thread.
+#[cfg(feature = "metrics-server")]
+pub fn serve(addr: &str, metrics: LiveMetrics) -> Result<MetricsServer, ExecError> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    let bind_err = |e: std::io::Error| ExecError::Metrics(format!("'{}': {}", addr, e));
+    let listener = TcpListener::bind(addr).map_err(bind_err)?;
+    // Non-blocking accepts let the thread notice a dropped handle.
+    listener.set_nonblocking(true).map_err(bind_err)?;
+    let local = listener.local_addr().map_err(bind_err)?;
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let stopped = Arc::clone(&stop);
+    std::thread::spawn(move || {
+        while !stopped.load(Ordering::Relaxed) {
+            let mut stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(20));
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let mut request_line = String::new();
+            if BufReader::new(&stream)
+                .read_line(&mut request_line)
+                .is_err()
+            {
+                continue;
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or("");
+            let (status, body) = match (request_line.starts_with("GET "), path) {
+                (true, "/metrics") => ("200 OK", metrics.render()),
+                (true, _) => ("404 Not Found", "not found\n".to_string()),
+                (false, _) => (
+                    "405 Method Not Allowed",
+                    "only GET is supported\n".to_string(),
+                ),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    Ok(MetricsServer { addr: local, stop })
+}
+
+/// Without the `metrics-server` feature there is no endpoint to start.
+#[cfg(not(feature = "metrics-server"))]
+pub fn serve(addr: &str, _metrics: LiveMetrics) -> Result<MetricsServer, ExecError> {
+    Err(ExecError::Metrics(format!(
+        "cannot serve on '{}': built without the metrics-server feature",
+        addr
+    )))
+}
@@ -0,0 +1,261 @@
+//! EnginePool: run several pipelines concurrently under one global memory budget.
+//!
+//! Each submitted job reserves its `mem_cap_bytes` from the pool's shared
+//! `MemoryBudgetImpl` before its engine starts and holds the reservation until
+//! the run ends, so engines running side by side never over-commit the cap.
+//! Anything else the embedding service acquires from [`EnginePool::budget`]
+//! counts against the same cap.
+//!
+//! Admission is weighted-fair: a job's tag is `vclock + mem_cap / weight`, and
+//! the waiting job with the smallest tag goes next. Only that job may be
+//! admitted (no skipping ahead), so large reservations cannot be starved.
+
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_mem::guard::{BudgetGuardImpl, MemoryBudgetImpl};
+use emsqrt_operators::CancellationToken;
+use emsqrt_planner::physical::PhysicalProgram;
+use emsqrt_te::tree_eval::TePlan;
+
+use crate::runtime::{Engine, ExecError};
+
+/// How often a waiting job re-checks the budget for memory released outside the pool.
+const ADMISSION_POLL: Duration = Duration::from_millis(10);
+
+/// Pool-wide limits.
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// Global memory cap shared by all running pipelines.
+    pub mem_cap_bytes: usize,
+    /// Maximum number of pipelines running at once.
+    pub max_concurrent: usize,
+}
+
+/// A pipeline submitted to the pool.
+pub struct PoolJob {
+    pub config: EngineConfig,
+    pub program: PhysicalProgram,
+    pub te: TePlan,
+    /// Relative share; a job with weight 2 is admitted ahead of an equal-sized
+    /// weight-1 job submitted at the same time.
+    pub weight: u32,
+}
+
+impl PoolJob {
+    pub fn new(config: EngineConfig, program: PhysicalProgram, te: TePlan) -> Self {
+        Self {
+            config,
+            program,
+            te,
+            weight: 1,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// Handle to a submitted pipeline. Dropping it without `join` cancels the
+/// pipeline and waits for its thread, so the engine has deleted its spill
+/// segments and closed its sinks by the time the drop returns.
+pub struct JobHandle {
+    handle: Option<JoinHandle<Result<RunManifest, ExecError>>>,
+    cancel: CancellationToken,
+}
+
+impl JobHandle {
+    /// Block until the pipeline finishes and return its manifest.
+    pub fn join(mut self) -> Result<RunManifest, ExecError> {
+        self.handle
+            .take()
+            .expect("joined once")
+            .join()
+            .unwrap_or_else(|_| Err(ExecError::Operator("pipeline thread panicked".into())))
+    }
+
+    /// Stop the pipeline: before admission if it is still waiting, else
+    /// before its next block. `join` then returns `ExecError::Cancelled`.
+    pub fn cancel(&self) {
+        // A job waiting for admission sees this on its next poll.
+        self.cancel.cancel();
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.cancel.cancel();
+            let _ = handle.join();
+        }
+    }
+}
+
+struct Waiting {
+    id: u64,
+    tag: f64,
+}
+
+#[derive(Default)]
+struct SchedState {
+    vclock: f64,
+    next_id: u64,
+    running: usize,
+    waiting: Vec<Waiting>,
+}
+
+impl SchedState {
+    fn head(&self) -> Option<&Waiting> {
+        self.waiting
+            .iter()
+            .min_by(|a, b| a.tag.total_cmp(&b.tag).then(a.id.cmp(&b.id)))
+    }
+}
+
+struct Scheduler {
+    budget: MemoryBudgetImpl,
+    max_concurrent: usize,
+    state: Mutex<SchedState>,
+    wake: Condvar,
+}
+
+impl Scheduler {
+    fn lock(&self) -> MutexGuard<'_, SchedState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn enqueue(&self, bytes: usize, weight: u32) -> u64 {
+        let mut st = self.lock();
+        let id = st.next_id;
+        st.next_id += 1;
+        let tag = st.vclock + bytes as f64 / weight as f64;
+        st.waiting.push(Waiting { id, tag });
+        id
+    }
+
+    /// Block until job `id` is at the head of the queue and its reservation
+    /// fits; `None` if the job is cancelled first.
+    fn admit(
+        self: &Arc<Self>,
+        id: u64,
+        bytes: usize,
+        cancel: &CancellationToken,
+    ) -> Option<Admission> {
+        let mut st = self.lock();
+        loop {
+            if cancel.is_cancelled() {
+                st.waiting.retain(|w| w.id != id);
+                self.wake.notify_all();
+                return None;
+            }
+            let head = st.head().map(|w| (w.id, w.tag));
+            if let Some((head_id, tag)) = head {
+                if head_id == id && st.running < self.max_concurrent {
+                    if let Some(guard) = self.budget.try_acquire(bytes, "pipeline") {
+                        st.waiting.retain(|w| w.id != id);
+                        st.running += 1;
+                        st.vclock = st.vclock.max(tag);
+                        self.wake.notify_all();
+                        return Some(Admission {
+                            scheduler: Arc::clone(self),
+                            guard: Some(guard),
+                        });
+                    }
+                }
+            }
+            st = self
+                .wake
+                .wait_timeout(st, ADMISSION_POLL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+}
+
+/// A running job's reservation; releases it and wakes waiters on drop (panic-safe).
+struct Admission {
+    scheduler: Arc<Scheduler>,
+    guard: Option<BudgetGuardImpl>,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.guard.take();
+        let mut st = self.scheduler.lock();
+        st.running -= 1;
+        self.scheduler.wake.notify_all();
+    }
+}
+
+/// Runs submitted pipelines on their own threads, admitting them against one
+/// shared memory budget.
+pub struct EnginePool {
+    scheduler: Arc<Scheduler>,
+}
+
+impl EnginePool {
+    pub fn new(cfg: PoolConfig) -> Self {
+        Self {
+            scheduler: Arc::new(Scheduler {
+                budget: MemoryBudgetImpl::new(cfg.mem_cap_bytes),
+                max_concurrent: cfg.max_concurrent.max(1),
+                state: Mutex::new(SchedState::default()),
+                wake: Condvar::new(),
+            }),
+        }
+    }
+
+    /// The global budget every job reserves from.
+    pub fn budget(&self) -> &MemoryBudgetImpl {
+        &self.scheduler.budget
+    }
+
+    /// Number of pipelines currently running.
+    pub fn running(&self) -> usize {
+        self.scheduler.lock().running
+    }
+
+    /// Number of pipelines waiting for admission.
+    pub fn waiting(&self) -> usize {
+        self.scheduler.lock().waiting.len()
+    }
+
+    /// Submit a pipeline. Jobs that could never fit are rejected up front.
+    /// Each job runs on an engine of its own, so jobs may share a `spill_dir`.
+    pub fn submit(&self, job: PoolJob) -> Result<JobHandle, ExecError> {
+        let bytes = job.config.mem_cap_bytes;
+        if job.weight == 0 {
+            return Err(ExecError::Invalid("pool job weight must be > 0".into()));
+        }
+        if bytes > self.scheduler.budget.capacity_bytes() {
+            return Err(ExecError::Invalid(format!(
+                "pipeline memory cap {} exceeds pool cap {}",
+                bytes,
+                self.scheduler.budget.capacity_bytes()
+            )));
+        }
+
+        let id = self.scheduler.enqueue(bytes, job.weight);
+        let scheduler = Arc::clone(&self.scheduler);
+        let cancel = CancellationToken::new();
+        let token = cancel.clone();
+        let handle = std::thread::spawn(move || {
+            let first_block = job.te.order.first().map_or(0, |b| b.id.get());
+            let Some(_admission) = scheduler.admit(id, bytes, &token) else {
+                return Err(ExecError::Cancelled(first_block));
+            };
+            let mut engine = Engine::new(job.config)?.with_cancel_token(token);
+            engine.run(&job.program, &job.te)
+        });
+        Ok(JobHandle {
+            handle: Some(handle),
+            cancel,
+        })
+    }
+}
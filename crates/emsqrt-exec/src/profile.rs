@@ -0,0 +1,346 @@
+//! Data profile of one source (`emsqrt profile`).
+//!
+//! The source is read by an ordinary plan, `source -> profile -> sink`, with
+//! the `profile` operator (see `emsqrt_operators::profile`) summarizing each
+//! column in fixed-size sketches and a `mem://` sink taking its one row per
+//! column. The pass therefore runs in TE blocks under the engine's memory cap
+//! however large the source is. The report serializes to JSON and renders as
+//! a standalone HTML page.
+
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{LogicalPlan, PhysicalPlan};
+use emsqrt_core::id::OpId;
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{RowBatch, Scalar};
+use emsqrt_operators::profile::{self, HistogramBin, TopValue, DISTINCT_SKETCH};
+use emsqrt_planner::{estimate_work, lower_to_physical, OperatorBinding, WorkHint};
+use emsqrt_te::plan_te;
+
+use crate::memtable::{MemTables, MEM_SCHEME};
+use crate::runtime::{Engine, ExecError};
+use crate::source_files::with_source_files;
+
+#[derive(Debug, Clone)]
+pub struct ProfileOptions {
+    /// Most frequent values reported per column.
+    pub top: usize,
+    /// Histogram bins of numeric columns.
+    pub bins: usize,
+    /// Numeric values sampled per column for its histogram.
+    pub sample: usize,
+}
+
+impl Default for ProfileOptions {
+    fn default() -> Self {
+        Self {
+            top: 5,
+            bins: 10,
+            sample: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    /// Declared type, from the schema the source was read with.
+    pub data_type: String,
+    pub nulls: u64,
+    pub null_percent: f64,
+    /// Distinct non-null values; an estimate unless `distinct_exact`.
+    pub distinct: u64,
+    pub distinct_exact: bool,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub top_values: Vec<TopValue>,
+    /// Empty for non-numeric columns.
+    pub histogram: Vec<HistogramBin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataProfile {
+    pub source: String,
+    pub rows: u64,
+    pub columns: Vec<ColumnProfile>,
+}
+
+/// Profile every column of `source`, read with `schema`.
+pub fn profile_source(
+    source: &str,
+    schema: &Schema,
+    opts: &ProfileOptions,
+    config: &EngineConfig,
+) -> Result<DataProfile, ExecError> {
+    let table = "profile";
+    let plan = LogicalPlan::Sink {
+        input: Box::new(LogicalPlan::Scan {
+            source: source.to_string(),
+            schema: schema.clone(),
+        }),
+        destination: format!("{}{}", MEM_SCHEME, table),
+        format: "mem".into(),
+    };
+    let mut program = lower_to_physical(&plan);
+
+    // Put the profile operator between the scan and the sink.
+    let op = OpId::new(
+        program
+            .bindings
+            .keys()
+            .map(|id| id.get())
+            .max()
+            .unwrap_or(0)
+            + 1,
+    );
+    let PhysicalPlan::Sink { input, .. } = &mut program.plan else {
+        return Err(ExecError::Invalid("profile plan has no sink".into()));
+    };
+    let scan = std::mem::replace(
+        input.as_mut(),
+        PhysicalPlan::Source {
+            op,
+            schema: Schema::new(vec![]),
+        },
+    );
+    **input = PhysicalPlan::Unary {
+        op,
+        input: Box::new(scan),
+        schema: profile::output_schema(),
+    };
+    program.bindings.insert(
+        op,
+        OperatorBinding {
+            key: "profile".into(),
+            config: serde_json::json!({
+                "top": opts.top,
+                "bins": opts.bins,
+                "sample": opts.sample,
+            }),
+        },
+    );
+
+    // The file size (or the files' total) sizes the TE blocks.
+    let hint = std::fs::metadata(source)
+        .ok()
+        .filter(|m| m.is_file())
+        .map(|m| WorkHint {
+            source_rows: vec![],
+            source_bytes: vec![(source.to_string(), m.len())],
+            ..Default::default()
+        });
+    let te = plan_te(
+        &program.plan,
+        &estimate_work(&plan, with_source_files(&plan, hint).as_ref()),
+        config.mem_cap_bytes,
+    )
+    .map_err(|e| ExecError::Invalid(format!("TE planning failed: {}", e)))?;
+
+    let config = EngineConfig {
+        audit_path: None,
+        ..config.clone()
+    };
+    let tables = MemTables::new();
+    Engine::new(config)?
+        .with_mem_tables(tables.clone())
+        .run(&program, &te)?;
+    let batches = tables.take(table).unwrap_or_default();
+    report(source, schema, &batches)
+}
+
+/// The profile rows (one per column) as a report.
+fn report(source: &str, schema: &Schema, batches: &[RowBatch]) -> Result<DataProfile, ExecError> {
+    let bad = |what: &str| ExecError::Invalid(format!("profile output: bad {}", what));
+    let mut columns = Vec::new();
+    let mut rows = 0;
+    for batch in batches {
+        let column = |name: &str| {
+            batch
+                .columns
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| &c.values)
+                .ok_or_else(|| bad(name))
+        };
+        let int = |name: &str, row: usize| match column(name)?[row] {
+            Scalar::I64(v) => Ok(v as u64),
+            _ => Err(bad(name)),
+        };
+        let text = |name: &str, row: usize| match &column(name)?[row] {
+            Scalar::Str(s) => Ok(Some(s.clone())),
+            Scalar::Null => Ok(None),
+            _ => Err(bad(name)),
+        };
+        let json = |name: &str, row: usize| text(name, row)?.ok_or_else(|| bad(name));
+        for row in 0..batch.num_rows() {
+            let name = text("column", row)?.ok_or_else(|| bad("column"))?;
+            let (total, nulls, distinct) =
+                (int("rows", row)?, int("nulls", row)?, int("distinct", row)?);
+            rows = total;
+            let data_type = schema
+                .fields
+                .iter()
+                .find(|f| f.name == name)
+                .map_or_else(String::new, |f| format!("{:?}", f.data_type));
+            columns.push(ColumnProfile {
+                name,
+                data_type,
+                nulls,
+                null_percent: if total == 0 {
+                    0.0
+                } else {
+                    100.0 * nulls as f64 / total as f64
+                },
+                distinct,
+                distinct_exact: (distinct as usize) < DISTINCT_SKETCH,
+                min: text("min", row)?,
+                max: text("max", row)?,
+                top_values: serde_json::from_str(&json("top_values", row)?)
+                    .map_err(|_| bad("top_values"))?,
+                histogram: serde_json::from_str(&json("histogram", row)?)
+                    .map_err(|_| bad("histogram"))?,
+            });
+        }
+    }
+    Ok(DataProfile {
+        source: source.to_string(),
+        rows,
+        columns,
+    })
+}
+
+impl DataProfile {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".into())
+    }
+
+    /// Standalone HTML page: a summary table, then per column its
+    /// statistics, most frequent values, and histogram as inline SVG bars.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>EM-√ profile</title>\n<style>\n\
+             body { font-family: Helvetica, Arial, sans-serif; margin: 24px; color: #222; }\n\
+             table { border-collapse: collapse; margin-bottom: 16px; }\n\
+             td, th { border: 1px solid #ccc; padding: 4px 8px; text-align: left; font-size: 13px; }\n\
+             td.num { text-align: right; }\n\
+             svg rect { fill: #1f78b4; }\n\
+             svg text { font-size: 11px; }\n\
+             </style>\n</head>\n<body>\n",
+        );
+        let _ = writeln!(
+            out,
+            "<h1>Profile of {}</h1>\n<p>{} rows, {} columns</p>",
+            html_escape(&self.source),
+            self.rows,
+            self.columns.len()
+        );
+
+        out.push_str(
+            "<table>\n<tr><th>Column</th><th>Type</th><th>Nulls</th><th>Null %</th>\
+             <th>Distinct</th><th>Min</th><th>Max</th></tr>\n",
+        );
+        let or_dash = |v: &Option<String>| v.as_deref().map_or("-".into(), html_escape);
+        for c in &self.columns {
+            let _ = writeln!(
+                out,
+                "<tr><td><a href=\"#col-{}\">{}</a></td><td>{}</td><td class=\"num\">{}</td>\
+                 <td class=\"num\">{:.1}</td><td class=\"num\">{}</td><td>{}</td><td>{}</td></tr>",
+                html_escape(&c.name),
+                html_escape(&c.name),
+                html_escape(&c.data_type),
+                c.nulls,
+                c.null_percent,
+                distinct_label(c),
+                or_dash(&c.min),
+                or_dash(&c.max),
+            );
+        }
+        out.push_str("</table>\n");
+
+        for c in &self.columns {
+            let _ = writeln!(
+                out,
+                "<h2 id=\"col-{}\">{}</h2>",
+                html_escape(&c.name),
+                html_escape(&c.name)
+            );
+            if !c.top_values.is_empty() {
+                out.push_str("<table>\n<tr><th>Value</th><th>Count</th><th>± error</th></tr>\n");
+                for v in &c.top_values {
+                    let _ = writeln!(
+                        out,
+                        "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                        html_escape(&v.value),
+                        v.count,
+                        v.error
+                    );
+                }
+                out.push_str("</table>\n");
+            }
+            if !c.histogram.is_empty() {
+                out.push_str(&histogram_svg(&c.histogram));
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Exact counts as-is; sketch estimates marked `≈`.
+fn distinct_label(c: &ColumnProfile) -> String {
+    if c.distinct_exact {
+        c.distinct.to_string()
+    } else {
+        format!("≈{}", c.distinct)
+    }
+}
+
+/// Vertical bars, one per bin, scaled to the fullest bin (hover for range).
+fn histogram_svg(bins: &[HistogramBin]) -> String {
+    const BAR_W: usize = 32;
+    const HEIGHT: usize = 120;
+    const LABEL_H: usize = 16;
+    let max = bins.iter().map(|b| b.count).max().unwrap_or(0).max(1);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        bins.len() * BAR_W,
+        HEIGHT + LABEL_H
+    );
+    for (i, b) in bins.iter().enumerate() {
+        let h = (b.count as usize * HEIGHT).div_ceil(max as usize);
+        let _ = writeln!(
+            out,
+            "<g><title>{} to {}: {}</title><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/></g>",
+            b.lower,
+            b.upper,
+            b.count,
+            i * BAR_W + 1,
+            HEIGHT - h,
+            BAR_W - 2,
+            h
+        );
+    }
+    let _ = writeln!(
+        out,
+        "<text x=\"0\" y=\"{}\">{}</text>\n<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        HEIGHT + LABEL_H - 3,
+        bins[0].lower,
+        bins.len() * BAR_W,
+        HEIGHT + LABEL_H - 3,
+        bins[bins.len() - 1].upper
+    );
+    out.push_str("</svg>\n");
+    out
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
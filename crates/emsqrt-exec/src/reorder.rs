@@ -0,0 +1,143 @@
+//! Reorder buffer for sink writes.
+//!
+//! Each sink block gets a sequence number from its position in TE order. Blocks
+//! may finish in any order, but the sink only sees them by sequence number: a
+//! block that arrives early waits in the buffer until every block before it
+//! has been written. Waiting batches are held under the memory budget; one that
+//! does not fit is spilled and read back when its turn comes. Output files are
+//! therefore byte-for-byte the same however block completion interleaves.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::types::RowBatch;
+use emsqrt_mem::guard::{BudgetGuardImpl, MemoryBudgetImpl};
+use emsqrt_mem::spill::SegmentMeta;
+use emsqrt_mem::SpillManager;
+use emsqrt_operators::traits::OpError;
+
+use crate::runtime::batch_bytes;
+
+enum Pending {
+    Memory(RowBatch, BudgetGuardImpl),
+    Spilled(SegmentMeta),
+}
+
+pub struct ReorderBuffer {
+    next: u64,
+    pending: BTreeMap<u64, Pending>,
+    spill: Arc<Mutex<SpillManager>>,
+    spilled: u64,
+}
+
+impl ReorderBuffer {
+    pub fn new(spill: Arc<Mutex<SpillManager>>) -> Self {
+        Self {
+            next: 0,
+            pending: BTreeMap::new(),
+            spill,
+            spilled: 0,
+        }
+    }
+
+    /// Sequence number of the next batch the sink will receive.
+    pub fn next_seq(&self) -> u64 {
+        self.next
+    }
+
+    /// Batches waiting for an earlier one.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Batches that had to be spilled while waiting.
+    pub fn spilled(&self) -> u64 {
+        self.spilled
+    }
+
+    /// Accept batch `seq`. If it is next in line, `write` it and every waiting
+    /// batch that follows; otherwise hold it (in memory or spilled) until then.
+    pub fn push(
+        &mut self,
+        seq: u64,
+        batch: RowBatch,
+        budget: &MemoryBudgetImpl,
+        mut write: impl FnMut(RowBatch) -> Result<(), OpError>,
+    ) -> Result<(), OpError> {
+        if seq < self.next || self.pending.contains_key(&seq) {
+            return Err(OpError::Exec(format!("sink block {} delivered twice", seq)));
+        }
+        if seq > self.next {
+            let entry = match budget.try_acquire(batch_bytes(&batch), "sink_reorder") {
+                Some(guard) => Pending::Memory(batch, guard),
+                None => Pending::Spilled(self.spill_batch(&batch)?),
+            };
+            self.pending.insert(seq, entry);
+            return Ok(());
+        }
+
+        // A failed write still uses up its turn; the caller decides whether
+        // the run goes on (e.g. a `skip` fallback) or stops.
+        self.next += 1;
+        write(batch)?;
+        while let Some(entry) = self.pending.remove(&self.next) {
+            self.next += 1;
+            let batch = match entry {
+                Pending::Memory(batch, _guard) => batch,
+                Pending::Spilled(meta) => self.restore(meta, budget)?,
+            };
+            write(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Check that no batch is still waiting for a predecessor.
+    pub fn finish(&mut self) -> Result<(), OpError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        Err(OpError::Exec(format!(
+            "sink block {} never arrived; {} later blocks unwritten",
+            self.next,
+            self.pending.len()
+        )))
+    }
+
+    fn spill_batch(&mut self, batch: &RowBatch) -> Result<SegmentMeta, OpError> {
+        let mut spill = self.spill.lock().unwrap();
+        let id = spill.next_spill_id();
+        let run = spill.next_run_index();
+        let meta = spill
+            .write_batch(batch, id, run)
+            .map_err(|e| OpError::Exec(format!("spilling sink reorder buffer: {}", e)))?;
+        self.spilled += 1;
+        Ok(meta)
+    }
+
+    fn restore(
+        &mut self,
+        meta: SegmentMeta,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<RowBatch, OpError> {
+        let mut spill = self.spill.lock().unwrap();
+        let batch = spill
+            .read_batch(&meta, budget)
+            .map_err(|e| OpError::Exec(format!("reading sink reorder buffer: {}", e)))?;
+        let _ = spill.delete_segment(&meta.name);
+        Ok(batch)
+    }
+}
+
+impl Drop for ReorderBuffer {
+    fn drop(&mut self) {
+        // Remove spilled batches left behind by a failed run.
+        if let Ok(mut spill) = self.spill.lock() {
+            for entry in self.pending.values() {
+                if let Pending::Spilled(meta) = entry {
+                    let _ = spill.delete_segment(&meta.name);
+                }
+            }
+        }
+    }
+}
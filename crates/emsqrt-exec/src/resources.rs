@@ -0,0 +1,120 @@
+//! Container resource limits as engine defaults.
+//!
+//! Inside a container the host's memory and CPU count say little about what
+//! the engine may use. `config_from_env` reads the cgroup limits (v2
+//! `memory.max` and `cpu.max`, or v1 `memory.limit_in_bytes` and
+//! `cpu.cfs_quota_us`) and derives `mem_cap_bytes` and `max_parallel_tasks`
+//! from them unless `EMSQRT_MEM_CAP_BYTES` or `EMSQRT_MAX_PARALLEL_TASKS` set
+//! them. The memory cap is three quarters of the limit, leaving the rest to
+//! the process itself and the allocator. What was applied is kept in
+//! `EngineConfig::detected_resources` and recorded in the run manifest.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use emsqrt_core::config::{DetectedLimit, EngineConfig};
+
+/// Where the container's cgroup is mounted.
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// cgroup v1 reports "no limit" as a huge page-aligned number.
+const UNLIMITED: u64 = 1 << 62;
+
+/// Limits of the cgroup mounted at some root, with the file each came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerLimits {
+    /// Memory limit in bytes.
+    pub memory: Option<(u64, PathBuf)>,
+    /// CPUs allowed: the CPU quota over its period, rounded up.
+    pub cpus: Option<(u64, PathBuf)>,
+}
+
+impl ContainerLimits {
+    /// Default `mem_cap_bytes` and `max_parallel_tasks` from these limits,
+    /// except settings `configured` reports as set, and record each in
+    /// `cfg.detected_resources`.
+    pub fn apply(&self, cfg: &mut EngineConfig, configured: impl Fn(&str) -> bool) {
+        if let Some((limit, path)) = &self.memory {
+            if !configured("mem_cap_bytes") {
+                let value = limit / 4 * 3;
+                cfg.mem_cap_bytes = value as usize;
+                cfg.detected_resources
+                    .push(detected("mem_cap_bytes", value, *limit, path));
+            }
+        }
+        if let Some((cpus, path)) = &self.cpus {
+            if !configured("max_parallel_tasks") {
+                cfg.max_parallel_tasks = *cpus as usize;
+                cfg.detected_resources
+                    .push(detected("max_parallel_tasks", *cpus, *cpus, path));
+            }
+        }
+    }
+}
+
+fn detected(setting: &str, value: u64, limit: u64, path: &Path) -> DetectedLimit {
+    DetectedLimit {
+        setting: setting.to_string(),
+        value,
+        limit,
+        source: path.display().to_string(),
+    }
+}
+
+/// `EngineConfig::from_env`, with the settings no environment variable sets
+/// defaulted from the container's limits. `EMSQRT_DETECT_RESOURCES=false`
+/// turns detection off.
+pub fn config_from_env() -> EngineConfig {
+    let mut cfg = EngineConfig::from_env();
+    if std::env::var("EMSQRT_DETECT_RESOURCES").is_ok_and(|s| s == "false") {
+        return cfg;
+    }
+    detect_limits(Path::new(CGROUP_ROOT)).apply(&mut cfg, |setting| {
+        let var = match setting {
+            "mem_cap_bytes" => "EMSQRT_MEM_CAP_BYTES",
+            _ => "EMSQRT_MAX_PARALLEL_TASKS",
+        };
+        std::env::var_os(var).is_some()
+    });
+    cfg
+}
+
+/// Limits of the cgroup mounted at `root`; none outside a limited cgroup.
+pub fn detect_limits(root: &Path) -> ContainerLimits {
+    ContainerLimits {
+        memory: memory_limit(root),
+        cpus: cpu_limit(root),
+    }
+}
+
+fn memory_limit(root: &Path) -> Option<(u64, PathBuf)> {
+    let v2 = root.join("memory.max");
+    if let Some(text) = read(&v2) {
+        return text.parse().ok().map(|limit| (limit, v2));
+    }
+    let v1 = root.join("memory/memory.limit_in_bytes");
+    let limit: u64 = read(&v1)?.parse().ok()?;
+    (limit < UNLIMITED).then_some((limit, v1))
+}
+
+fn cpu_limit(root: &Path) -> Option<(u64, PathBuf)> {
+    let v2 = root.join("cpu.max");
+    if let Some(text) = read(&v2) {
+        // "<quota> <period>", quota "max" when unlimited.
+        let (quota, period) = text.split_once(' ')?;
+        return cpus(quota.parse().ok()?, period.parse().ok()?).map(|n| (n, v2));
+    }
+    let v1 = root.join("cpu/cpu.cfs_quota_us");
+    // -1 when unlimited.
+    let quota: i64 = read(&v1)?.parse().ok()?;
+    let period = read(&root.join("cpu/cpu.cfs_period_us"))?.parse().ok()?;
+    cpus(u64::try_from(quota).ok()?, period).map(|n| (n, v1))
+}
+
+fn cpus(quota: u64, period: u64) -> Option<u64> {
+    (quota > 0 && period > 0).then(|| quota.div_ceil(period))
+}
+
+fn read(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
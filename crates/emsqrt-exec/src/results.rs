@@ -0,0 +1,240 @@
+//! Block results waiting for the blocks that read them.
+//!
+//! A TE block's output is held until every block depending on it has run.
+//! Held batches are charged to the memory budget, and may take at most half
+//! of it so the blocks still to run keep room to work; a result that does not
+//! fit is spilled through the engine's `SpillManager` and read back when a
+//! dependent block runs. Wide plans therefore stay within the memory cap
+//! however many results are waiting.
+//!
+//! An exchange block's result is one batch per hash partition, and a
+//! co-partitioned join block takes only its own part of each.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::types::RowBatch;
+use emsqrt_mem::guard::{BudgetGuardImpl, MemoryBudgetImpl};
+use emsqrt_mem::spill::SegmentMeta;
+use emsqrt_mem::SpillManager;
+use emsqrt_te::tree_eval::TeBlock;
+
+use crate::runtime::{batch_bytes, ExecError};
+
+enum Held {
+    Memory {
+        batch: RowBatch,
+        bytes: usize,
+        _guard: BudgetGuardImpl,
+    },
+    Spilled(SegmentMeta),
+}
+
+/// A block's result, or one part of an exchange block's: (block id, part).
+type Part = (u64, usize);
+
+pub struct ResultStore {
+    held: HashMap<Part, Held>,
+    /// Dependent blocks of each result that have not taken it yet.
+    remaining_uses: HashMap<Part, usize>,
+    /// Exchange blocks, whose dependents read the part of their partition.
+    exchanges: HashSet<u64>,
+    spill: Arc<Mutex<SpillManager>>,
+    held_bytes: usize,
+    spilled: u64,
+}
+
+impl ResultStore {
+    /// A store for the results of the blocks in `order`.
+    pub fn new(spill: Arc<Mutex<SpillManager>>, order: &[TeBlock]) -> Self {
+        let exchanges = order
+            .iter()
+            .filter(|b| b.exchange.is_some())
+            .map(|b| b.id.get())
+            .collect();
+        let mut store = Self {
+            held: HashMap::new(),
+            remaining_uses: HashMap::new(),
+            exchanges,
+            spill,
+            held_bytes: 0,
+            spilled: 0,
+        };
+        // Keyless joins share upstream results, so count every consumer.
+        for b in order {
+            for part in store.parts_read_by(b) {
+                *store.remaining_uses.entry(part).or_insert(0) += 1;
+            }
+        }
+        store
+    }
+
+    /// The results `block` reads, one per dep in order: part 0 of a plain
+    /// block, and part `index` of an exchange block feeding partition `index`.
+    pub fn parts_read_by(&self, block: &TeBlock) -> Vec<(u64, usize)> {
+        let index = block.partition.as_ref().map_or(0, |p| p.index);
+        block
+            .deps
+            .iter()
+            .map(|dep| {
+                let part = if self.exchanges.contains(&dep.get()) {
+                    index
+                } else {
+                    0
+                };
+                (dep.get(), part)
+            })
+            .collect()
+    }
+
+    /// Hold `batch`, the output of block `id`, for its dependents. Returns
+    /// whether it had to be spilled; results no block reads are dropped.
+    pub fn insert(
+        &mut self,
+        id: u64,
+        batch: RowBatch,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<bool, ExecError> {
+        self.insert_part((id, 0), batch, budget)
+    }
+
+    /// Hold `parts`, the per-partition output of exchange block `id`. Returns
+    /// whether any part had to be spilled.
+    pub fn insert_parts(
+        &mut self,
+        id: u64,
+        parts: Vec<RowBatch>,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<bool, ExecError> {
+        let mut spilled = false;
+        for (part, batch) in parts.into_iter().enumerate() {
+            spilled |= self.insert_part((id, part), batch, budget)?;
+        }
+        Ok(spilled)
+    }
+
+    fn insert_part(
+        &mut self,
+        key: Part,
+        batch: RowBatch,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<bool, ExecError> {
+        let id = key.0;
+        if self.remaining_uses.get(&key).copied().unwrap_or(0) == 0 {
+            return Ok(false);
+        }
+        let bytes = batch_bytes(&batch);
+        let guard = (self.held_bytes + bytes <= budget.capacity_bytes() / 2)
+            .then(|| budget.try_acquire(bytes, "block_result"))
+            .flatten();
+        let (entry, spilled) = match guard {
+            Some(guard) => {
+                self.held_bytes += bytes;
+                let entry = Held::Memory {
+                    batch,
+                    bytes,
+                    _guard: guard,
+                };
+                (entry, false)
+            }
+            None => {
+                let mut spill = self.spill.lock().unwrap();
+                let (spill_id, run) = (spill.next_spill_id(), spill.next_run_index());
+                let meta = spill.write_batch(&batch, spill_id, run).map_err(|e| {
+                    ExecError::Storage(format!("spilling result of block {}: {}", id, e))
+                })?;
+                self.spilled += 1;
+                (Held::Spilled(meta), true)
+            }
+        };
+        self.held.insert(key, entry);
+        Ok(spilled)
+    }
+
+    /// Block `id`'s result for one of its dependents: cloned (or read back)
+    /// while other dependents still need it, released with the last.
+    pub fn take(&mut self, id: u64, budget: &MemoryBudgetImpl) -> Result<RowBatch, ExecError> {
+        self.take_part((id, 0), budget)
+    }
+
+    /// Like [`ResultStore::take`], for one part of an exchange block's result.
+    pub fn take_part(
+        &mut self,
+        (id, part): (u64, usize),
+        budget: &MemoryBudgetImpl,
+    ) -> Result<RowBatch, ExecError> {
+        let missing = || ExecError::Invalid(format!("missing dependency block result for {}", id));
+        let uses = self.remaining_uses.entry((id, part)).or_insert(1);
+        *uses = uses.saturating_sub(1);
+        if *uses > 0 {
+            return match self.held.get(&(id, part)).ok_or_else(missing)? {
+                Held::Memory { batch, .. } => Ok(batch.clone()),
+                Held::Spilled(meta) => self.read(id, meta, budget),
+            };
+        }
+        match self.held.remove(&(id, part)).ok_or_else(missing)? {
+            Held::Memory { batch, bytes, .. } => {
+                self.held_bytes -= bytes;
+                Ok(batch)
+            }
+            Held::Spilled(meta) => {
+                let batch = self.read(id, &meta, budget)?;
+                let _ = self.spill.lock().unwrap().delete_segment(&meta.name);
+                Ok(batch)
+            }
+        }
+    }
+
+    /// Bytes of held results that running `block` releases from memory:
+    /// those in memory whose last dependent it is.
+    pub fn freed_bytes(&self, block: &TeBlock) -> u64 {
+        let mut uses: HashMap<Part, usize> = HashMap::new();
+        for part in self.parts_read_by(block) {
+            *uses.entry(part).or_insert(0) += 1;
+        }
+        uses.into_iter()
+            .filter(|(dep, n)| self.remaining_uses.get(dep) == Some(n))
+            .filter_map(|(dep, _)| match self.held.get(&dep) {
+                Some(Held::Memory { bytes, .. }) => Some(*bytes as u64),
+                _ => None,
+            })
+            .sum()
+    }
+
+    /// Bytes of results held in memory.
+    pub fn held_bytes(&self) -> usize {
+        self.held_bytes
+    }
+
+    /// Results spilled so far.
+    pub fn spilled(&self) -> u64 {
+        self.spilled
+    }
+
+    fn read(
+        &self,
+        id: u64,
+        meta: &SegmentMeta,
+        budget: &MemoryBudgetImpl,
+    ) -> Result<RowBatch, ExecError> {
+        self.spill
+            .lock()
+            .unwrap()
+            .read_batch(meta, budget)
+            .map_err(|e| ExecError::Storage(format!("reading result of block {}: {}", id, e)))
+    }
+}
+
+impl Drop for ResultStore {
+    fn drop(&mut self) {
+        // Remove spilled results a failed run left behind.
+        if let Ok(mut spill) = self.spill.lock() {
+            for entry in self.held.values() {
+                if let Held::Spilled(meta) = entry {
+                    let _ = spill.delete_segment(&meta.name);
+                }
+            }
+        }
+    }
+}
@@ -7,31 +7,75 @@
 //! - Enforces a hard memory ceiling via `emsqrt-mem::MemoryBudgetImpl`.
 //! - Emits a `RunManifest` with stable plan/TE hashes.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use cpu_time::ThreadTime;
 
 use thiserror::Error;
 
-use emsqrt_core::config::EngineConfig;
-use emsqrt_core::hash::{hash_serde, Hash256};
-use emsqrt_core::manifest::RunManifest;
+use emsqrt_core::align::align_batch;
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::{
+    EngineConfig, FallbackAction, ParquetSinkConfig, ReadRetryConfig, SandboxConfig, SpillCleanup,
+    SpillCodec,
+};
+use emsqrt_core::dag::SortKey;
+use emsqrt_core::expr::FieldPredicate;
+use emsqrt_core::hash::PartitionHasher;
+use emsqrt_core::id::{IdAllocator, OpId};
+use emsqrt_core::manifest::{BlockCost, CostSummary, RunManifest, SpillTotals};
 use emsqrt_core::prelude::Schema;
-use emsqrt_core::types::RowBatch;
+use emsqrt_core::schema::{DataType, NullOptions, ParseErrorPolicy};
+use emsqrt_core::stats::SchemaStats;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_core::{decimal, temporal};
 
 use emsqrt_mem::guard::MemoryBudgetImpl;
-use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_mem::spill::SegmentName;
+use emsqrt_mem::{Codec, ReservationMode, SpillManager};
 
+use emsqrt_io::buf::ResumableFile;
+use emsqrt_io::files::{is_multi_file, list_source_files};
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_io::readers::text::TextReader;
 use emsqrt_io::storage::build_storage_from_config;
 
+use emsqrt_operators::context::{CancellationToken, OpContext, SpillScope};
+use emsqrt_operators::join::filter::InputFilters;
+use emsqrt_operators::join::hash::JoinType;
 use emsqrt_operators::registry::Registry;
-use emsqrt_operators::traits::{OpError, Operator}; // placeholder alias (Vec<RowBatch>)
+use emsqrt_operators::traits::{BlockRange, BlockSource, OpError, Operator}; // placeholder alias (Vec<RowBatch>)
 use emsqrt_operators::window::{LateralExplodeOp, WindowFnKind, WindowFnSpec, WindowOp};
 
-use emsqrt_planner::physical::PhysicalProgram;
+use emsqrt_planner::physical::{OperatorBinding, PhysicalProgram};
+
+use crate::audit::{AuditEntry, AuditLog, BatchDigest, BlockAudit};
+use crate::backpressure::ReadSizer;
+use crate::constraints::ConstraintVerifier;
+use crate::join_guard::JoinGuard;
+use crate::memtable::{mem_table_name, MemSinkOp, MemSourceOp, MemTables};
+use crate::metrics::LiveMetrics;
+use crate::reorder::ReorderBuffer;
+use crate::replay::{hash_program, hash_te};
+use crate::results::ResultStore;
+use crate::scheduler::BlockScheduler;
+use crate::spill_space::{available_space, estimate_spill};
+use crate::stats_store::{update_stats_file, StatsStore};
 use emsqrt_te::tree_eval::TePlan;
 
-use emsqrt_io::writers::csv::CsvWriter;
+use emsqrt_io::writers::{open_writer, BatchWriter, WriteMode, WriterOptions};
+
+/// Rows a sequential source reader discards per read while seeking forward.
+const SKIP_CHUNK_ROWS: usize = 10_000;
+
+/// Bounds of the default Parquet row group size of a sink.
+const MIN_ROW_GROUP_ROWS: usize = 64 * 1024;
+const MAX_ROW_GROUP_BYTES: usize = 128 * 1024 * 1024;
 
 #[derive(Debug, Error)]
 pub enum ExecError {
@@ -45,14 +89,35 @@ pub enum ExecError {
     Hash(String),
     #[error("storage config error: {0}")]
     Storage(String),
+    #[error("sandbox violation: {0}")]
+    Sandbox(String),
+    #[error("insufficient spill space: {0}")]
+    SpillSpace(String),
+    #[error("compare outputs: {0}")]
+    Compare(String),
+    #[error("constraint violation: {0}")]
+    Constraint(String),
+    #[error("join guard: {0}")]
+    JoinGuard(String),
+    #[error("audit log: {0}")]
+    Audit(String),
+    #[error("metrics endpoint: {0}")]
+    Metrics(String),
+    #[error("let step {0}")]
+    Let(String),
+    #[error("run cancelled before block {0}")]
+    Cancelled(u64),
 }
 
 /// Engine owns the memory budget, operator registry, and spill manager.
 pub struct Engine {
-    _cfg: EngineConfig,
+    cfg: EngineConfig,
     budget: MemoryBudgetImpl,
     registry: Registry,
     spill_mgr: Arc<Mutex<SpillManager>>,
+    cancel: CancellationToken,
+    mem_tables: MemTables,
+    live: LiveMetrics,
 }
 
 impl Engine {
@@ -63,214 +128,351 @@ impl Engine {
         // Create spill manager with configured storage backend
         let storage = build_storage_from_config(&storage_cfg)
             .map_err(|e| ExecError::Storage(e.to_string()))?;
-        let codec = Codec::None; // Default to no compression; can be made configurable
-        let spill_mgr = SpillManager::new(storage, codec, storage_cfg.root.clone());
+        let (codec, level) = match cfg.spill_codec {
+            SpillCodec::None => (Codec::None, 0),
+            SpillCodec::Zstd { level } => (Codec::Zstd, level),
+            SpillCodec::Lz4 => (Codec::Lz4, 0),
+        };
+        if !codec.is_available() {
+            return Err(ExecError::Storage(format!(
+                "spill codec '{}' is not built in (enable the emsqrt-mem feature)",
+                cfg.spill_codec
+            )));
+        }
+        let spill_mgr = SpillManager::new(storage, codec, storage_cfg.root.clone())
+            .with_compression_level(level)
+            .with_id_allocator(IdAllocator::new(cfg.seed.unwrap_or(0)))
+            .with_spill_limit(cfg.sandbox.as_ref().and_then(|s| s.max_spill_bytes))
+            .with_spill_quota(cfg.spill_quota_bytes);
+
+        let reservation = match cfg.memory_wait_ms {
+            Some(ms) => ReservationMode::Wait(std::time::Duration::from_millis(ms)),
+            None => ReservationMode::Fail,
+        };
 
         Ok(Self {
-            _cfg: cfg,
-            budget: MemoryBudgetImpl::new(cap),
+            cfg,
+            budget: MemoryBudgetImpl::new(cap).with_reservation_mode(reservation),
             registry: Registry::new(),
             spill_mgr: Arc::new(Mutex::new(spill_mgr)),
+            cancel: CancellationToken::new(),
+            mem_tables: MemTables::new(),
+            live: LiveMetrics::new(),
         })
     }
 
+    /// Token that stops `run` before its next block once cancelled. Operators
+    /// see it through `OpContext` and may stop earlier.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Stop using the engine: delete any spill segments still held and
+    /// report a failure to do so (dropping the engine does the same, but
+    /// ignores errors).
+    pub fn shutdown(self) -> Result<(), ExecError> {
+        let result = lock_spills(&self.spill_mgr).delete_all();
+        result.map_err(|e| ExecError::Storage(e.to_string()))
+    }
+
+    /// Use `token` to cancel runs, e.g. one token shared by several engines.
+    pub fn with_cancel_token(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Tables that `mem://name` sources read and `mem://name` sinks fill.
+    pub fn mem_tables(&self) -> MemTables {
+        self.mem_tables.clone()
+    }
+
+    /// Use `tables` for `mem://` sources and sinks, e.g. to share them between engines.
+    pub fn with_mem_tables(mut self, tables: MemTables) -> Self {
+        self.mem_tables = tables;
+        self
+    }
+
+    /// Build bindings with the keys of `operators` from its factories, e.g.
+    /// a downstream crate's own `Operator`s. Keys the engine configures
+    /// itself (`source`, `filter`, `join_hash`, ...) keep their built-in
+    /// operator; other keys replace earlier registrations.
+    pub fn with_operators(mut self, operators: Registry) -> Self {
+        self.registry.extend(operators);
+        self
+    }
+
+    /// Counters of this engine's runs, updated after every block.
+    pub fn live_metrics(&self) -> LiveMetrics {
+        self.live.clone()
+    }
+
+    /// Record runs in `metrics`, e.g. one set served for several engines.
+    pub fn with_live_metrics(mut self, metrics: LiveMetrics) -> Self {
+        self.live = metrics;
+        self
+    }
+
     /// Execute a prepared `PhysicalProgram` under `TePlan` and return a manifest.
     pub fn run(
         &mut self,
         program: &PhysicalProgram,
         te: &TePlan,
     ) -> Result<RunManifest, ExecError> {
-        // Hash inputs deterministically (logical → physical handled earlier).
-        let plan_hash = hash_serde(&program.plan).map_err(|e| ExecError::Hash(e.to_string()))?;
-        let bindings_hash =
-            hash_serde(&program.bindings).map_err(|e| ExecError::Hash(e.to_string()))?;
-        let te_hash = hash_serde(&te.order).map_err(|e| ExecError::Hash(e.to_string()))?;
+        self.live.start_run(te.order.len(), &self.budget);
+        let result = self.run_blocks(program, te);
+        self.live.finish_run(result.is_ok());
+        result
+    }
 
-        // Merge hashes (simple xor of bytes) to capture bindings+plan.
-        let plan_hash = xor_hashes(plan_hash, bindings_hash);
+    fn run_blocks(
+        &mut self,
+        program: &PhysicalProgram,
+        te: &TePlan,
+    ) -> Result<RunManifest, ExecError> {
+        // Hash inputs canonically (plan + bindings, and TE order).
+        let plan_hash = hash_program(program)?;
+        let te_hash = hash_te(te)?;
+
+        // Reject disallowed sources/sinks before touching any data.
+        if let Some(sandbox) = &self.cfg.sandbox {
+            check_sandbox(sandbox, program)?;
+        }
+
+        // Fail now if the worst-case spill may not fit in the spill dir.
+        if self.cfg.spill_space_check && self.cfg.spill_uri.is_none() {
+            check_spill_space(&self.cfg, program)?;
+        }
+
+        // However the run ends (error, cancellation or a panic unwinding
+        // through it), the spill segments it wrote are deleted; operators,
+        // dropped first, close their sink files. Then the sweep the cleanup
+        // policy asks for runs.
+        let _spills = NewSpills::track(&self.spill_mgr, self.cfg.spill_cleanup);
+        lock_spills(&self.spill_mgr).begin_run();
 
         // Instantiate operator table keyed by OpId.
         let mut ops: HashMap<u64, Box<dyn Operator>> = HashMap::new();
         for (op_id, binding) in &program.bindings {
-            let key = binding.key.as_str();
-            let config = &binding.config;
-            let inst: Box<dyn Operator> = match key {
-                "source" => {
-                    let source_uri =
-                        config
-                            .get("source")
-                            .and_then(|v| v.as_str())
-                            .ok_or_else(|| {
-                                ExecError::Registry(
-                                    "source operator missing 'source' in config".into(),
-                                )
-                            })?;
-
-                    // Get schema from config or use default
-                    let schema: Schema = if let Some(schema_val) = config.get("schema") {
-                        serde_json::from_value(schema_val.clone())
-                            .unwrap_or_else(|_| Schema::new(vec![]))
-                    } else {
-                        Schema::new(vec![])
-                    };
+            let inst = if binding.key == "sink" {
+                // Sinks size Parquet row groups from the block size.
+                let mut config = binding.config.clone();
+                config["block_rows"] = te.block_size.rows_per_block.into();
+                self.build_operator(&binding.key, &config)?
+            } else {
+                self.build_operator(&binding.key, &binding.config)?
+            };
+            ops.insert(op_id.get(), inst);
+        }
+        // Stages with a memory hint run under a child budget of their own.
+        let budgets: HashMap<u64, MemoryBudgetImpl> = program
+            .bindings
+            .iter()
+            .filter_map(|(op_id, binding)| Some((op_id.get(), self.binding_budget(binding)?)))
+            .collect();
+        for (op_id, op) in &ops {
+            op.open()
+                .map_err(|e| ExecError::Operator(format!("opening op {}: {}", op_id, e)))?;
+        }
 
-                    Box::new(SourceOp {
-                        source_uri: source_uri.to_string(),
-                        schema,
-                        file_position: Arc::new(Mutex::new(0)),
-                        #[cfg(feature = "parquet")]
-                        parquet_reader: Arc::new(Mutex::new(None)),
-                    })
+        // Declared source constraints to check as blocks are read.
+        let mut verifiers: HashMap<u64, ConstraintVerifier> = HashMap::new();
+        if self.cfg.verify_constraints {
+            for (op_id, binding) in &program.bindings {
+                if binding.key != "source" {
+                    continue;
                 }
-                "sink" => {
-                    let destination = config
-                        .get("destination")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("");
-                    let format = config
-                        .get("format")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("csv");
-
-                    Box::new(SinkOp {
-                        destination: destination.to_string(),
-                        format: format.to_string(),
-                        writer_initialized: std::sync::Arc::new(std::sync::Mutex::new(false)),
-                        #[cfg(feature = "parquet")]
-                        parquet_writer: std::sync::Arc::new(std::sync::Mutex::new(None)),
-                    })
-                }
-                "filter" => {
-                    let mut op = emsqrt_operators::filter::Filter::default();
-                    if let Some(expr) = config.get("expr").and_then(|v| v.as_str()) {
-                        op.expr = Some(expr.to_string());
-                    }
-                    Box::new(op)
-                }
-                "project" => {
-                    let mut op = emsqrt_operators::project::Project::default();
-                    if let Some(cols) = config.get("columns").and_then(|v| v.as_array()) {
-                        op.columns = cols
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
-                    }
-                    Box::new(op)
-                }
-                "map" => {
-                    // Map currently doesn't use config, but we could parse renames here
-                    Box::new(emsqrt_operators::map::Map::default())
-                }
-                "aggregate" => {
-                    let mut op = emsqrt_operators::agregate::Aggregate::default();
-                    op.spill_mgr = Some(self.spill_mgr.clone());
-                    // Parse group_by and aggs from config if provided
-                    if let Some(group_by) = config.get("group_by").and_then(|v| v.as_array()) {
-                        op.group_by = group_by
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
-                    }
-                    if let Some(aggs) = config.get("aggs").and_then(|v| v.as_array()) {
-                        op.aggs = aggs
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
-                    }
-                    Box::new(op)
-                }
-                "sort_external" => {
-                    let mut op = emsqrt_operators::sort::external::ExternalSort::default();
-                    op.spill_mgr = Some(self.spill_mgr.clone());
-                    // Parse sort keys from config if provided
-                    if let Some(keys) = config.get("by").and_then(|v| v.as_array()) {
-                        op.by = keys
-                            .iter()
-                            .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                            .collect();
+                let source = binding.config.get("source").and_then(|v| v.as_str());
+                let schema = binding
+                    .config
+                    .get("schema")
+                    .and_then(|v| serde_json::from_value::<Schema>(v.clone()).ok());
+                if let (Some(source), Some(schema)) = (source, schema) {
+                    if let Some(v) = ConstraintVerifier::new(source, &schema.constraints) {
+                        verifiers.insert(op_id.get(), v);
                     }
-                    Box::new(op)
-                }
-                "join_hash" => {
-                    let mut op = emsqrt_operators::join::hash::HashJoin::default();
-                    op.spill_mgr = Some(self.spill_mgr.clone());
-                    // Parse join keys from config if provided
-                    if let Some(on) = config.get("on").and_then(|v| v.as_array()) {
-                        op.on = on
-                            .iter()
-                            .filter_map(|v| {
-                                if let Some(pair) = v.as_array() {
-                                    if pair.len() == 2 {
-                                        let left = pair[0].as_str()?.to_string();
-                                        let right = pair[1].as_str()?.to_string();
-                                        return Some((left, right));
-                                    }
-                                }
-                                None
-                            })
-                            .collect();
-                    }
-                    if let Some(join_type) = config.get("join_type").and_then(|v| v.as_str()) {
-                        op.join_type = join_type.to_string();
-                    }
-                    Box::new(op)
-                }
-                "window" => {
-                    let partitions = json_to_vec_strings(config.get("partitions"));
-                    let order_by = json_to_vec_strings(config.get("order_by"));
-                    let functions = parse_window_functions(config.get("functions"));
-                    Box::new(WindowOp {
-                        partitions,
-                        order_by,
-                        functions,
-                    })
                 }
-                "lateral_explode" => {
-                    let column = config
-                        .get("column")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("value")
-                        .to_string();
-                    let alias = config
-                        .get("alias")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("exploded")
-                        .to_string();
-                    let delimiter = config
-                        .get("delimiter")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or(",")
-                        .to_string();
-                    Box::new(LateralExplodeOp {
-                        column,
-                        alias,
-                        delimiter,
-                    })
+            }
+        }
+
+        // Column statistics of source output, by source op.
+        let mut source_stats: HashMap<u64, (String, SchemaStats)> = HashMap::new();
+        if self.cfg.collect_stats || self.cfg.stats_dir.is_some() || self.cfg.stats_file.is_some() {
+            for (op_id, binding) in &program.bindings {
+                if binding.key != "source" {
+                    continue;
                 }
-                other => self.registry.make(other).ok_or_else(|| {
-                    ExecError::Registry(format!("unknown operator key '{other}'"))
-                })?,
-            };
-            ops.insert(op_id.get(), inst);
+                if let Some(source) = binding.config.get("source").and_then(|v| v.as_str()) {
+                    source_stats.insert(op_id.get(), (source.to_string(), SchemaStats::new()));
+                }
+            }
         }
 
-        // Map: BlockId → RowBatch result
-        let mut results: HashMap<u64, RowBatch> = HashMap::new();
+        // Output size pre-checks of hash joins, by join op.
+        let mut join_guards: HashMap<u64, JoinGuard> = HashMap::new();
+        if let Some(guard) = &self.cfg.join_guard {
+            for (op_id, binding) in &program.bindings {
+                if binding.key != "join_hash" {
+                    continue;
+                }
+                let join_type = binding
+                    .config
+                    .get("join_type")
+                    .and_then(|v| v.as_str())
+                    .map_or(Ok(JoinType::Inner), JoinType::parse)
+                    .map_err(ExecError::Invalid)?;
+                let on = json_to_join_keys(binding.config.get("on"));
+                let filters =
+                    join_filters(&binding.config, &self.cfg).map_err(ExecError::Invalid)?;
+                join_guards.insert(
+                    op_id.get(),
+                    JoinGuard::new(guard, on, join_type).with_filters(filters),
+                );
+            }
+        }
+
+        // Sink writes go through a reorder buffer so each sink sees its blocks
+        // in TE order, whatever order they complete in.
+        let mut reorder: HashMap<u64, ReorderBuffer> = HashMap::new();
+        let mut sink_seq: HashMap<u64, u64> = HashMap::new();
+        let mut sink_blocks: HashMap<u64, u64> = HashMap::new();
+        for b in &te.order {
+            if program
+                .bindings
+                .get(&b.op)
+                .is_some_and(|op| op.key == "sink")
+            {
+                let count = sink_blocks.entry(b.op.get()).or_insert(0);
+                sink_seq.insert(b.id.get(), *count);
+                *count += 1;
+                reorder
+                    .entry(b.op.get())
+                    .or_insert_with(|| ReorderBuffer::new(self.spill_mgr.clone()));
+            }
+        }
+
+        // The block with each operator's highest range is open-ended (see `BlockRange`).
+        let mut last_block: HashMap<u64, (u64, u64)> = HashMap::new();
+        for b in &te.order {
+            if let Some((_, end)) = b.range_rows {
+                let last = last_block.entry(b.op.get()).or_insert((end, b.id.get()));
+                if end > last.0 {
+                    *last = (end, b.id.get());
+                }
+            }
+        }
+
+        // Block outputs waiting for their dependents (spilled under pressure).
+        let mut results = ResultStore::new(self.spill_mgr.clone(), &te.order);
 
         // Start manifest
         let now_ms = now_millis();
         let mut manifest = RunManifest::new(plan_hash, te_hash, now_ms);
+        manifest.partition_hash = Some(self.cfg.partition_hasher());
+        manifest.seed = self.cfg.seed.unwrap_or(0);
+        manifest.detected_resources = self.cfg.resources_in_effect();
+        let spill_start = self.spill_totals();
+        #[cfg(feature = "tracing")]
+        tracing::info!(
+            blocks = te.order.len(),
+            mem_cap_bytes = self.cfg.mem_cap_bytes,
+            "run started"
+        );
+
+        let audit_path = self.cfg.audit_path.clone().unwrap_or_default();
+        let audit_err = |e: std::io::Error| ExecError::Audit(format!("'{}': {}", audit_path, e));
+        let mut audit = match &self.cfg.audit_path {
+            Some(path) => {
+                let mut log = AuditLog::open(path).map_err(audit_err)?;
+                log.append(&AuditEntry::Run {
+                    run_id: manifest.id.0.to_string(),
+                    plan_hash: plan_hash.to_hex(),
+                    te_hash: te_hash.to_hex(),
+                    started_ms: now_ms,
+                })
+                .map_err(audit_err)?;
+                Some(log)
+            }
+            None => None,
+        };
 
-        // Sequential TE order (starter).
-        for b in &te.order {
-            // Gather input batches from deps in order.
+        let hasher = self.cfg.partition_hasher();
+
+        let run_started = Instant::now();
+        let max_wall_time_ms = self.max_wall_time_ms();
+
+        // Blocks run one at a time, in the order the schedule policy picks.
+        let mut scheduler = BlockScheduler::new(&te.order, self.cfg.schedule_policy);
+        while let Some((index, decision)) = scheduler.next(|i| results.freed_bytes(&te.order[i])) {
+            let b = &te.order[index];
+            #[cfg(feature = "tracing")]
+            tracing::trace!(block = %b.id.get(), ready = decision.ready, priority = decision.priority, "scheduled block");
+            manifest.schedule.push(decision);
+            if self.cancel.is_cancelled() {
+                return Err(ExecError::Cancelled(b.id.get()));
+            }
+            check_wall_time(max_wall_time_ms, run_started, &format!("block {}", b.id))?;
+            let spill_read_before = self.spill_read_bytes();
+            // Gather input batches from deps in order (an exchange block's
+            // dependents get only their partition's part).
             let mut inputs: Vec<RowBatch> = Vec::with_capacity(b.deps.len());
-            for dep in &b.deps {
-                let key = dep.get();
-                let batch = results.remove(&key).ok_or_else(|| {
-                    ExecError::Invalid(format!("missing dependency block result for {}", key))
-                })?;
-                inputs.push(batch);
+            for part in results.parts_read_by(b) {
+                inputs.push(results.take_part(part, &self.budget)?);
+            }
+
+            // Exchange block: split its input once by key hash, one part per
+            // join partition. It runs no operator and is not audited; the
+            // join blocks record the parts they read.
+            if let Some(exchange) = &b.exchange {
+                let input = concat_rows(&inputs);
+                let spilled_before = self.spilled_bytes();
+                let cpu_started = ThreadTime::now();
+                let wall_started = Instant::now();
+                let (rows_in, bytes_in) = (input.num_rows() as u64, batch_bytes(&input) as u64);
+                let parts = split_partitions(input, &exchange.keys, exchange.count, &hasher)
+                    .map_err(|e| ExecError::Invalid(format!("exchange block {}: {}", b.id, e)))?;
+                let mut metrics = BTreeMap::new();
+                if results.insert_parts(b.id.get(), parts, &self.budget)? {
+                    metrics.insert("result_spilled".to_string(), 1);
+                }
+                manifest.block_costs.push(BlockCost {
+                    block_id: b.id.get(),
+                    op_id: b.op.get(),
+                    op: "exchange".to_string(),
+                    cpu_nanos: cpu_started.elapsed().as_nanos() as u64,
+                    wall_nanos: wall_started.elapsed().as_nanos() as u64,
+                    bytes_spilled: self.spilled_bytes().saturating_sub(spilled_before),
+                    bytes_spill_read: self.spill_read_bytes().saturating_sub(spill_read_before),
+                    bytes_scanned: 0,
+                    rows_in,
+                    rows_out: rows_in,
+                    bytes_in,
+                    bytes_out: bytes_in,
+                    peak_memory_bytes: 0,
+                    metrics,
+                });
+                if let Some(cost) = manifest.block_costs.last() {
+                    self.live.record_block(cost);
+                }
+                scheduler.complete(index);
+                continue;
+            }
+
+            // Hash co-partitioned block: regroup deps per side.
+            if let Some(partition) = &b.partition {
+                // A keyless self-join lists a shared input's blocks on both
+                // sides, so each dep keeps one batch per listing.
+                let mut by_dep: HashMap<u64, Vec<RowBatch>> = HashMap::new();
+                for (dep, batch) in b.deps.iter().zip(inputs.drain(..)) {
+                    by_dep.entry(dep.get()).or_default().push(batch);
+                }
+                for side in &partition.inputs {
+                    let batches: Vec<RowBatch> = side
+                        .deps
+                        .iter()
+                        .filter_map(|d| by_dep.get_mut(&d.get())?.pop())
+                        .collect();
+                    inputs.push(concat_rows(&batches));
+                }
             }
 
             // Dispatch to the operator by op id.
@@ -280,16 +482,7 @@ impl Engine {
 
             // Calculate input sizes for error context
             let input_rows: usize = inputs.iter().map(|batch| batch.num_rows()).sum();
-            let input_bytes: usize = inputs
-                .iter()
-                .map(|batch| {
-                    batch
-                        .columns
-                        .iter()
-                        .map(|col| col.values.len() * 8)
-                        .sum::<usize>()
-                })
-                .sum();
+            let input_bytes: usize = inputs.iter().map(batch_bytes).sum();
 
             // Build error context with operator and block information
             let operator_name = op.name();
@@ -302,8 +495,71 @@ impl Engine {
                 input_bytes
             );
 
-            // Try to execute with retry logic for recoverable errors
-            let out = match self.execute_block_with_retry(op.as_ref(), &inputs, &context, 3) {
+            if let Some(guard) = join_guards.get_mut(&b.op.get()) {
+                match guard.check(&inputs) {
+                    Ok(None) => {}
+                    Ok(Some(warning)) => manifest
+                        .warnings
+                        .push(format!("{}: join guard: {}", context, warning)),
+                    Err(e) => return Err(ExecError::JoinGuard(format!("{}: {}", context, e))),
+                }
+            }
+
+            let input_digests: Vec<BatchDigest> = match audit {
+                Some(_) => inputs.iter().map(BatchDigest::of).collect(),
+                None => Vec::new(),
+            };
+
+            let spilled_before = self.spilled_bytes();
+            let budget = budgets.get(&b.op.get()).unwrap_or(&self.budget);
+            budget.reset_peak();
+            let cpu_started = ThreadTime::now();
+            let wall_started = Instant::now();
+
+            // Try to execute with retry logic for recoverable errors, then the
+            // operator's configured fallback chain if the error persists.
+            let ctx = OpContext {
+                block_id: Some(b.id),
+                op_id: Some(b.op),
+                range: b.range_rows.map(|(start, end)| BlockRange {
+                    start,
+                    end,
+                    open_end: last_block.get(&b.op.get()).map(|l| l.1) == Some(b.id.get()),
+                }),
+                spill: Some(SpillScope::new(self.spill_mgr.clone())),
+                cancel: self.cancel.clone(),
+                ..Default::default()
+            };
+            let mut result = match (reorder.get_mut(&b.op.get()), sink_seq.get(&b.id.get())) {
+                (Some(buffer), Some(&seq)) => buffer
+                    .push(seq, concat_rows(&inputs), &self.budget, |batch| {
+                        self.execute_block_with_retry(
+                            op.as_ref(),
+                            &[batch],
+                            &ctx,
+                            budget,
+                            &context,
+                            3,
+                        )
+                        .map(|_| ())
+                    })
+                    .map(|()| RowBatch { columns: vec![] }),
+                _ => self.execute_block_with_retry(op.as_ref(), &inputs, &ctx, budget, &context, 3),
+            };
+            if let (Err(e), Some(binding)) = (&result, program.bindings.get(&b.op)) {
+                if e.is_recoverable() {
+                    result = self.execute_fallbacks(
+                        binding,
+                        &inputs,
+                        &ctx,
+                        budget,
+                        &context,
+                        e.clone(),
+                        &mut manifest.warnings,
+                    );
+                }
+            }
+            let out = match result {
                 Ok(batch) => batch,
                 Err(e) => {
                     // Enhance error with context and suggestions
@@ -321,20 +577,678 @@ impl Engine {
                 }
             };
 
-            // Store the result for this block (downstream deps will consume/remove it).
-            results.insert(b.id.get(), out);
+            if let Some(verifier) = verifiers.get_mut(&b.op.get()) {
+                if let Some(warning) = verifier
+                    .check(&out, &self.budget)
+                    .map_err(ExecError::Constraint)?
+                {
+                    manifest.warnings.push(warning);
+                }
+            }
+            if let Some((_, stats)) = source_stats.get_mut(&b.op.get()) {
+                stats.update_batch(&out);
+            }
+            if let Some(log) = &mut audit {
+                log.append(&AuditEntry::Block(BlockAudit {
+                    block_id: b.id.get(),
+                    op_id: b.op.get(),
+                    op: operator_name.to_string(),
+                    inputs: input_digests,
+                    output: BatchDigest::of(&out),
+                }))
+                .map_err(audit_err)?;
+            }
+
+            let cpu_nanos = cpu_started.elapsed().as_nanos() as u64;
+            let wall_nanos = wall_started.elapsed().as_nanos() as u64;
+            let rows_out = out.num_rows() as u64;
+            let bytes_out = batch_bytes(&out) as u64;
+            let peak_memory_bytes = budget.peak_bytes() as u64;
+            // Hold the result for the blocks that read it; a spilled result
+            // counts toward this block's spill bytes.
+            if results.insert(b.id.get(), out, &self.budget)? {
+                ctx.metrics.add("result_spilled", 1);
+            }
+            let mut metrics = ctx.metrics.take();
+            let bytes_scanned = metrics.remove(BYTES_SCANNED).unwrap_or(0);
+            manifest.block_costs.push(BlockCost {
+                block_id: b.id.get(),
+                op_id: b.op.get(),
+                op: operator_name.to_string(),
+                cpu_nanos,
+                wall_nanos,
+                bytes_spilled: self.spilled_bytes().saturating_sub(spilled_before),
+                bytes_spill_read: self.spill_read_bytes().saturating_sub(spill_read_before),
+                bytes_scanned,
+                rows_in: input_rows as u64,
+                rows_out,
+                bytes_in: input_bytes as u64,
+                bytes_out,
+                peak_memory_bytes,
+                metrics,
+            });
+            if let Some(cost) = manifest.block_costs.last() {
+                self.live.record_block(cost);
+            }
+            // Spill segments the operator left in its scope are removed here.
+            drop(ctx);
+            scheduler.complete(index);
 
             #[cfg(feature = "tracing")]
-            tracing::trace!(block = %b.id.get(), op = %b.op.get(), deps = b.deps.len(), "executed block");
+            tracing::debug!(block = %b.id.get(), op = %b.op.get(), deps = b.deps.len(), "executed block");
+        }
+
+        if scheduler.remaining() > 0 {
+            return Err(ExecError::Invalid(format!(
+                "{} blocks never became ready (dependency cycle in the TE plan)",
+                scheduler.remaining()
+            )));
+        }
+
+        for (op_id, buffer) in reorder.iter_mut() {
+            buffer
+                .finish()
+                .map_err(|e| ExecError::Operator(e.to_string()))?;
+            // Close the sink's writer (Parquet/Arrow footers are written here).
+            ops[op_id]
+                .finish()
+                .map_err(|e| ExecError::Operator(e.to_string()))?;
+        }
+
+        // Sources close their readers.
+        for (op_id, op) in &ops {
+            if !reorder.contains_key(op_id) {
+                op.finish()
+                    .map_err(|e| ExecError::Operator(e.to_string()))?;
+            }
         }
 
         // TODO: compute outputs digest (e.g., sinks) once sinks actually write data.
         let outputs_digest = None;
 
+        manifest.cost = Some(CostSummary::from_blocks(&manifest.block_costs));
+        let spill_end = self.spill_totals();
+        manifest.spill = Some(SpillTotals {
+            codec: self.cfg.spill_codec,
+            bytes_uncompressed: spill_end.0.saturating_sub(spill_start.0),
+            bytes_compressed: spill_end.1.saturating_sub(spill_start.1),
+            peak_bytes: lock_spills(&self.spill_mgr).peak_bytes(),
+        });
+        manifest.operators = manifest.op_totals();
+        manifest.parse_errors = parse_error_counts(program, &manifest.block_costs);
+        // A source scanned twice reads the same rows; keep the fuller pass.
+        for (source, mut stats) in source_stats.into_values() {
+            stats.drop_sketches();
+            let seen = manifest.source_stats.get(&source);
+            if seen.is_none_or(|s| s.row_count() < stats.row_count()) {
+                manifest.source_stats.insert(source, stats);
+            }
+        }
+        if let Some(path) = &self.cfg.stats_file {
+            match update_stats_file(Path::new(path), &manifest.source_stats) {
+                Ok(()) => manifest.stats_file = Some(path.clone()),
+                Err(e) => manifest
+                    .warnings
+                    .push(format!("could not save stats to '{}': {}", path, e)),
+            }
+        }
+        if let Some(dir) = &self.cfg.stats_dir {
+            // The store only speeds up later planning; failing to save is not fatal.
+            if let Err(e) = StatsStore::open(dir).record(&manifest.source_stats) {
+                manifest
+                    .warnings
+                    .push(format!("could not save stats to '{}': {}", dir, e));
+            }
+        }
+        #[cfg(feature = "tracing")]
+        {
+            for warning in &manifest.warnings {
+                tracing::warn!("{}", warning);
+            }
+            tracing::info!(
+                blocks = manifest.block_costs.len(),
+                spilled_bytes = self.spilled_bytes(),
+                "run finished"
+            );
+        }
         manifest = manifest.finish(now_millis(), outputs_digest);
         Ok(manifest)
     }
 
+    /// Evaluate a single operator binding against in-memory inputs, with the
+    /// same retries and fallback chain as `run`. Returns the output and any
+    /// fallback warnings.
+    pub fn eval_binding(
+        &self,
+        binding: &OperatorBinding,
+        inputs: &[RowBatch],
+    ) -> Result<(RowBatch, Vec<String>), ExecError> {
+        let started = Instant::now();
+        self.check_single_binding(&binding.key, &binding.config)?;
+        let op = self.build_operator(&binding.key, &binding.config)?;
+        let context = format!("operator '{}'", op.name());
+        let mut warnings = Vec::new();
+        let ctx = OpContext {
+            cancel: self.cancel.clone(),
+            ..Default::default()
+        };
+        let budget = self.binding_budget(binding);
+        let budget = budget.as_ref().unwrap_or(&self.budget);
+        let result =
+            match self.execute_block_with_retry(op.as_ref(), inputs, &ctx, budget, &context, 3) {
+                Err(e) if e.is_recoverable() => {
+                    // Fallbacks are further attempts, under the same time limit.
+                    check_wall_time(self.max_wall_time_ms(), started, "fallbacks")?;
+                    self.execute_fallbacks(
+                        binding,
+                        inputs,
+                        &ctx,
+                        budget,
+                        &context,
+                        e,
+                        &mut warnings,
+                    )
+                }
+                other => other,
+            };
+        result
+            .map(|batch| (batch, warnings))
+            .map_err(|e| ExecError::Operator(e.to_string()))
+    }
+
+    /// Evaluate the operator registered as `key`, configured as a plan
+    /// binding would be, on `inputs` as one block, then finish it.
+    ///
+    /// The operator gets a budget of its own at the configured cap, so it
+    /// neither sees nor holds bytes of other work on this engine, and any
+    /// spill segments it leaves behind are deleted. There are no retries or
+    /// fallbacks; `eval_binding` has those.
+    pub fn eval_operator(
+        &self,
+        key: &str,
+        config: &serde_json::Value,
+        inputs: &[RowBatch],
+    ) -> Result<RowBatch, ExecError> {
+        self.check_single_binding(key, config)?;
+        let _spills = NewSpills::track(&self.spill_mgr, self.cfg.spill_cleanup);
+        let op = self.build_operator(key, config)?;
+        let budget = MemoryBudgetImpl::new(self.cfg.mem_cap_bytes);
+        let ctx = OpContext {
+            spill: Some(SpillScope::new(self.spill_mgr.clone())),
+            cancel: self.cancel.clone(),
+            ..Default::default()
+        };
+        let result = op
+            .eval_block_with(inputs, &ctx, &budget)
+            .and_then(|batch| op.finish().map(|()| batch));
+        result.map_err(|e| {
+            ExecError::Operator(e.with_context(format!("operator '{}'", key)).to_string())
+        })
+    }
+
+    /// Sandbox checks of a one-binding evaluation, as `run` makes them for a
+    /// program of that one step: its paths must be allowed, and a wall time
+    /// limit of zero leaves no time to evaluate it.
+    fn check_single_binding(&self, key: &str, config: &serde_json::Value) -> Result<(), ExecError> {
+        if let Some(sandbox) = &self.cfg.sandbox {
+            check_binding_sandbox(sandbox, key, config)?;
+        }
+        check_wall_time(
+            self.max_wall_time_ms(),
+            Instant::now(),
+            &format!("operator '{}'", key),
+        )
+    }
+
+    /// The sandbox's limit on one run's (or evaluation's) wall time.
+    fn max_wall_time_ms(&self) -> Option<u64> {
+        self.cfg.sandbox.as_ref().and_then(|s| s.max_wall_time_ms)
+    }
+
+    /// Parquet settings of a sink, with row group bounds defaulted from the
+    /// block size (`block_rows` in the sink config) and the memory cap.
+    fn sink_parquet_config(&self, config: &serde_json::Value) -> ParquetSinkConfig {
+        let block_rows = config
+            .get("block_rows")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as usize;
+        let mut parquet = self.cfg.parquet.clone();
+        parquet
+            .row_group_rows
+            .get_or_insert(block_rows.max(MIN_ROW_GROUP_ROWS));
+        parquet
+            .row_group_bytes
+            .get_or_insert((self.cfg.mem_cap_bytes / 8).min(MAX_ROW_GROUP_BYTES));
+        parquet
+    }
+
+    /// Instantiate the operator bound to `key`, configured from its JSON payload.
+    fn build_operator(
+        &self,
+        key: &str,
+        config: &serde_json::Value,
+    ) -> Result<Box<dyn Operator>, ExecError> {
+        let inst: Box<dyn Operator> = match key {
+            "source" => {
+                let source_uri =
+                    config
+                        .get("source")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| {
+                            ExecError::Registry("source operator missing 'source' in config".into())
+                        })?;
+
+                // Get schema from config or use default
+                let schema: Schema = if let Some(schema_val) = config.get("schema") {
+                    serde_json::from_value(schema_val.clone())
+                        .unwrap_or_else(|_| Schema::new(vec![]))
+                } else {
+                    Schema::new(vec![])
+                };
+
+                if let Some(table) = mem_table_name(source_uri) {
+                    return Ok(Box::new(MemSourceOp {
+                        table: table.to_string(),
+                        schema,
+                        tables: self.mem_tables.clone(),
+                        position: Mutex::new(0),
+                    }));
+                }
+
+                let projection = config
+                    .get("projection")
+                    .map(|v| json_to_vec_strings(Some(v)));
+                // Pushed-down predicates see raw values, before null normalization.
+                let predicates: Vec<FieldPredicate> = config
+                    .get("predicates")
+                    .filter(|_| schema.null_options.is_default())
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .unwrap_or_default();
+
+                Box::new(SourceOp {
+                    source_uri: source_uri.to_string(),
+                    multi_file: is_multi_file(source_path(source_uri)),
+                    files: Mutex::new(None),
+                    file_index: Mutex::new(0),
+                    schema,
+                    position: Mutex::new(0),
+                    projection,
+                    predicates,
+                    jsonl_reader: Arc::new(Mutex::new(None)),
+                    text_reader: Mutex::new(None),
+                    csv_source: Mutex::new(None),
+                    sizer: Mutex::new(ReadSizer::new(self.cfg.source_batch.clone())),
+                    binary: self.cfg.binary_encoding,
+                    read_retry: self.cfg.read_retry.clone(),
+                    limit_rows: config
+                        .get("limit_rows")
+                        .and_then(|v| v.as_u64())
+                        .map(|n| n as usize),
+                    rows_read: Mutex::new(0),
+                    scanned: Arc::new(AtomicU64::new(0)),
+                    #[cfg(feature = "parquet")]
+                    parquet_reader: Arc::new(Mutex::new(None)),
+                    #[cfg(feature = "parquet")]
+                    parquet_carry: Mutex::new(None),
+                    #[cfg(feature = "avro")]
+                    avro_reader: Mutex::new(None),
+                    dead_rows: Mutex::new(Vec::new()),
+                    dead_letter: Mutex::new(None),
+                    append_dead_letter: config
+                        .get("append")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                })
+            }
+            "sink" => {
+                let destination = config
+                    .get("destination")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("");
+                let format = config
+                    .get("format")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("csv");
+
+                // Appending (follow mode) continues an existing file.
+                let append = config
+                    .get("append")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                if let Some(table) = mem_table_name(destination) {
+                    return Ok(Box::new(MemSinkOp {
+                        table: table.to_string(),
+                        tables: self.mem_tables.clone(),
+                        append,
+                        started: Mutex::new(false),
+                    }));
+                }
+
+                Box::new(SinkOp {
+                    destination: destination.to_string(),
+                    format: format.to_string(),
+                    mode: if append {
+                        WriteMode::Append
+                    } else {
+                        WriteMode::Overwrite
+                    },
+                    writer_opts: WriterOptions {
+                        binary: self.cfg.binary_encoding,
+                        parquet: self.sink_parquet_config(config),
+                        csv: self.cfg.csv.clone(),
+                    },
+                    writer: Mutex::new(None),
+                    buffer_guard: Mutex::new(None),
+                })
+            }
+            "filter" => {
+                let mut op = emsqrt_operators::filter::Filter {
+                    seed: self.cfg.seed.unwrap_or(0),
+                    arith_errors: self.cfg.arithmetic_errors,
+                    ..Default::default()
+                };
+                if let Some(expr) = config.get("expr").and_then(|v| v.as_str()) {
+                    op.expr = Some(expr.to_string());
+                }
+                Box::new(op)
+            }
+            "filter_in" => {
+                let mut op = emsqrt_operators::filter_in::FilterIn::default();
+                op.columns = json_to_vec_strings(config.get("columns"));
+                if let Some(keys) = config.get("keys").and_then(|v| v.as_str()) {
+                    op.keys_path = keys.strip_prefix("file://").unwrap_or(keys).to_string();
+                }
+                if let Some(delimiter) = config.get("delimiter").and_then(|v| v.as_str()) {
+                    op.delimiter = delimiter.to_string();
+                }
+                Box::new(op)
+            }
+            "project" => {
+                let mut op = emsqrt_operators::project::Project::default();
+                if let Some(cols) = config.get("columns").and_then(|v| v.as_array()) {
+                    op.columns = cols
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                Box::new(op)
+            }
+            "map" => {
+                let mut op = emsqrt_operators::map::Map {
+                    seed: self.cfg.seed.unwrap_or(0),
+                    arith_errors: self.cfg.arithmetic_errors,
+                    ..Default::default()
+                };
+                if let Some(expr) = config.get("expr").and_then(|v| v.as_str()) {
+                    op.expr = Some(expr.to_string());
+                }
+                Box::new(op)
+            }
+            "aggregate" => {
+                let mut op = emsqrt_operators::agregate::Aggregate {
+                    spill_mgr: Some(self.spill_mgr.clone()),
+                    partition_hash: self.cfg.partition_hasher(),
+                    deterministic: self.cfg.deterministic_output,
+                    ..Default::default()
+                };
+                // Parse group_by and aggs from config if provided
+                if let Some(group_by) = config.get("group_by").and_then(|v| v.as_array()) {
+                    op.group_by = group_by
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                if let Some(aggs) = config.get("aggs").and_then(|v| v.as_array()) {
+                    op.aggs = aggs
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                op.unique_groups = config
+                    .get("unique_groups")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                Box::new(op)
+            }
+            "sort_external" => {
+                let mut op = emsqrt_operators::sort::external::ExternalSort {
+                    spill_mgr: Some(self.spill_mgr.clone()),
+                    ..Default::default()
+                };
+                // Parse sort keys from config if provided
+                if let Some(keys) = config.get("by").and_then(|v| v.as_array()) {
+                    op.by = keys
+                        .iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect();
+                }
+                Box::new(op)
+            }
+            "join_hash" => {
+                let mut op = emsqrt_operators::join::hash::HashJoin {
+                    spill_mgr: Some(self.spill_mgr.clone()),
+                    partition_hash: self.cfg.partition_hasher(),
+                    deterministic: self.cfg.deterministic_output,
+                    ..Default::default()
+                };
+                // Parse join keys from config if provided
+                op.on = json_to_join_keys(config.get("on"));
+                if let Some(join_type) = config.get("join_type").and_then(|v| v.as_str()) {
+                    op.join_type = join_type.to_string();
+                }
+                if let Some(n) = config.get("partitions").and_then(|v| v.as_u64()) {
+                    op.num_partitions = Some(n as usize);
+                }
+                op.filters = join_filters(config, &self.cfg).map_err(ExecError::Invalid)?;
+                Box::new(op)
+            }
+            "join_merge" => {
+                let mut op = emsqrt_operators::join::merge::MergeJoin {
+                    on: json_to_join_keys(config.get("on")),
+                    join_type: "inner".to_string(),
+                    filters: join_filters(config, &self.cfg).map_err(ExecError::Invalid)?,
+                };
+                if let Some(join_type) = config.get("join_type").and_then(|v| v.as_str()) {
+                    op.join_type = join_type.to_string();
+                }
+                Box::new(op)
+            }
+            "join_nested_loop" => {
+                let mut op = emsqrt_operators::join::nested_loop::NestedLoopJoin {
+                    spill_mgr: Some(self.spill_mgr.clone()),
+                    seed: self.cfg.seed.unwrap_or(0),
+                    arith_errors: self.cfg.arithmetic_errors,
+                    ..Default::default()
+                };
+                op.condition = config
+                    .get("condition")
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string);
+                if let Some(join_type) = config.get("join_type").and_then(|v| v.as_str()) {
+                    op.join_type = join_type.to_string();
+                }
+                Box::new(op)
+            }
+            "window" => {
+                let partitions = json_to_vec_strings(config.get("partitions"));
+                let order_by = json_to_vec_strings(config.get("order_by"));
+                let functions = parse_window_functions(config.get("functions"));
+                let presorted = config
+                    .get("presorted")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                Box::new(WindowOp {
+                    partitions,
+                    order_by,
+                    functions,
+                    presorted,
+                })
+            }
+            "lateral_explode" => {
+                let column = config
+                    .get("column")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("value")
+                    .to_string();
+                let alias = config
+                    .get("alias")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("exploded")
+                    .to_string();
+                let delimiter = config
+                    .get("delimiter")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(",")
+                    .to_string();
+                Box::new(LateralExplodeOp {
+                    column,
+                    alias,
+                    delimiter,
+                })
+            }
+            "union" => {
+                let schema = config
+                    .get("schema")
+                    .and_then(|v| serde_json::from_value::<Schema>(v.clone()).ok());
+                Box::new(emsqrt_operators::union::Union { schema })
+            }
+            "top_k" => {
+                let column = config
+                    .get("column")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("value");
+                let k = config.get("k").and_then(|v| v.as_u64()).unwrap_or(10);
+                let capacity = config.get("capacity").and_then(|v| v.as_u64()).unwrap_or(0);
+                Box::new(emsqrt_operators::top_k::TopK::new(
+                    column,
+                    k as usize,
+                    capacity as usize,
+                ))
+            }
+            "limit" | "top_n" => {
+                let get =
+                    |key: &str| config.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                if key == "limit" {
+                    Box::new(emsqrt_operators::limit::Limit::new(
+                        get("limit"),
+                        get("offset"),
+                    ))
+                } else {
+                    let order_by = json_to_vec_strings(config.get("order_by"))
+                        .iter()
+                        .map(|key| SortKey::parse(key))
+                        .collect::<Result<_, _>>()
+                        .map_err(ExecError::Invalid)?;
+                    Box::new(emsqrt_operators::limit::TopN::new(
+                        order_by,
+                        get("limit"),
+                        get("offset"),
+                    ))
+                }
+            }
+            "profile" => {
+                let get = |key: &str, default: u64| {
+                    config.get(key).and_then(|v| v.as_u64()).unwrap_or(default) as usize
+                };
+                Box::new(emsqrt_operators::profile::Profile::new(
+                    get("top", 5),
+                    get("bins", 10),
+                    get("sample", 1024),
+                ))
+            }
+            other => self
+                .registry
+                .build(other, config)
+                .ok_or_else(|| ExecError::Registry(format!("unknown operator key '{other}'")))?
+                .map_err(|e| ExecError::Registry(format!("operator '{other}': {e}")))?,
+        };
+        Ok(inst)
+    }
+
+    /// A budget capped at the binding's `memory_bytes` (a stage's memory
+    /// hint) that also draws on the engine budget; `None` without a hint.
+    fn binding_budget(&self, binding: &OperatorBinding) -> Option<MemoryBudgetImpl> {
+        let bytes = binding.config.get("memory_bytes")?.as_u64()?;
+        Some(self.budget.child(bytes as usize))
+    }
+
+    /// Walk the fallback chain configured for `binding.key` after a block kept
+    /// failing with a recoverable error. Each step runs once (no retries); the
+    /// first success wins and is recorded as a manifest warning.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_fallbacks(
+        &self,
+        binding: &OperatorBinding,
+        inputs: &[RowBatch],
+        ctx: &OpContext,
+        budget: &MemoryBudgetImpl,
+        context: &str,
+        error: OpError,
+        warnings: &mut Vec<String>,
+    ) -> Result<RowBatch, OpError> {
+        let chain = match self.cfg.fallbacks.get(&binding.key) {
+            Some(chain) => chain,
+            None => return Err(error),
+        };
+
+        let mut last_error = error;
+        for action in chain {
+            let op = match action {
+                FallbackAction::Skip => {
+                    warnings.push(format!("{}: skipped block after: {}", context, last_error));
+                    return Ok(RowBatch { columns: vec![] });
+                }
+                FallbackAction::Partitions(n) => {
+                    let mut config = binding.config.clone();
+                    if let Some(obj) = config.as_object_mut() {
+                        obj.insert("partitions".into(), serde_json::json!(n));
+                    }
+                    self.build_operator(&binding.key, &config)
+                }
+                FallbackAction::Strategy(key) => self.build_operator(key, &binding.config),
+            }
+            .map_err(|e| OpError::Plan(format!("fallback {:?}: {}", action, e)))?;
+
+            match self.execute_block_with_retry(op.as_ref(), inputs, ctx, budget, context, 0) {
+                Ok(batch) => {
+                    warnings.push(format!(
+                        "{}: recovered with fallback {:?} after: {}",
+                        context, action, last_error
+                    ));
+                    return Ok(batch);
+                }
+                Err(e) if e.is_recoverable() => last_error = e,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Total bytes written to spill storage so far.
+    fn spilled_bytes(&self) -> u64 {
+        self.spill_mgr
+            .lock()
+            .map(|mgr| mgr.bytes_written())
+            .unwrap_or(0)
+    }
+
+    /// Spill bytes written so far, before and after compression.
+    fn spill_totals(&self) -> (u64, u64) {
+        self.spill_mgr
+            .lock()
+            .map(|mgr| (mgr.uncompressed_bytes_written(), mgr.bytes_written()))
+            .unwrap_or((0, 0))
+    }
+
+    /// Total bytes read back from spill storage so far.
+    fn spill_read_bytes(&self) -> u64 {
+        self.spill_mgr
+            .lock()
+            .map(|mgr| mgr.bytes_read())
+            .unwrap_or(0)
+    }
+
     /// Execute a block with retry logic for recoverable errors.
     ///
     /// Retries up to `max_retries` times for recoverable errors.
@@ -342,13 +1256,15 @@ impl Engine {
         &self,
         op: &dyn Operator,
         inputs: &[RowBatch],
+        ctx: &OpContext,
+        budget: &MemoryBudgetImpl,
         context: &str,
         max_retries: u32,
     ) -> Result<RowBatch, OpError> {
         let mut last_error = None;
 
         for attempt in 0..=max_retries {
-            match op.eval_block(inputs, &self.budget) {
+            match op.eval_block_with(inputs, ctx, budget) {
                 Ok(batch) => return Ok(batch),
                 Err(e) => {
                     if e.is_recoverable() && attempt < max_retries {
@@ -378,6 +1294,112 @@ impl Engine {
 
 // --- helpers ---
 
+/// Metric a source block records its bytes read under; the runtime moves it
+/// into the block's `bytes_scanned`.
+const BYTES_SCANNED: &str = "bytes_scanned";
+
+/// Metric name prefix (then the column name) for cells that failed to parse.
+const PARSE_ERROR_METRIC: &str = "parse_errors.";
+
+/// Sum the per-block parse error metrics by source URI and column.
+fn parse_error_counts(
+    program: &PhysicalProgram,
+    costs: &[BlockCost],
+) -> BTreeMap<String, BTreeMap<String, u64>> {
+    let mut out: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+    for cost in costs {
+        let source = program
+            .bindings
+            .get(&OpId::new(cost.op_id))
+            .and_then(|b| b.config.get("source"))
+            .and_then(|v| v.as_str());
+        let Some(source) = source else { continue };
+        for (name, count) in &cost.metrics {
+            if let Some(column) = name.strip_prefix(PARSE_ERROR_METRIC) {
+                *out.entry(source.to_string())
+                    .or_default()
+                    .entry(column.to_string())
+                    .or_insert(0) += count;
+            }
+        }
+    }
+    out
+}
+
+/// Parse a CSV cell as `data_type`; `None` if it is not a valid value.
+pub(crate) fn parse_cell(
+    data_type: &DataType,
+    value: &str,
+    binary: BinaryEncoding,
+) -> Option<Scalar> {
+    match data_type {
+        DataType::Int32 => value.parse::<i32>().ok().map(Scalar::I32),
+        DataType::Int64 => value.parse::<i64>().ok().map(Scalar::I64),
+        DataType::Float32 => value.parse::<f32>().ok().map(Scalar::F32),
+        DataType::Float64 => value.parse::<f64>().ok().map(Scalar::F64),
+        DataType::Boolean => value.parse::<bool>().ok().map(Scalar::Bool),
+        DataType::Date64 => temporal::parse_date(value).map(Scalar::Date64),
+        DataType::Timestamp => temporal::parse_timestamp(value).map(Scalar::Timestamp),
+        DataType::Decimal128 => {
+            decimal::parse_decimal(value).map(|(v, scale)| Scalar::Decimal128(v, scale))
+        }
+        // An empty cell is null rather than zero bytes, as for other types.
+        DataType::Binary if value.is_empty() => None,
+        DataType::Binary => binary.decode(value).ok().map(Scalar::Bin),
+        _ => Some(Scalar::Str(value.to_string())),
+    }
+}
+
+/// Whether `value` already has the representation of `data_type`.
+pub(crate) fn has_type(value: &Scalar, data_type: &DataType) -> bool {
+    matches!(
+        (data_type, value),
+        (DataType::Boolean, Scalar::Bool(_))
+            | (DataType::Int32, Scalar::I32(_))
+            | (DataType::Int64, Scalar::I64(_))
+            | (DataType::Float32, Scalar::F32(_))
+            | (DataType::Float64, Scalar::F64(_))
+            | (DataType::Binary, Scalar::Bin(_))
+            | (DataType::Utf8, Scalar::Str(_))
+            | (DataType::Date64, Scalar::Date64(_))
+            | (DataType::Timestamp, Scalar::Timestamp(_))
+            | (DataType::Decimal128, Scalar::Decimal128(..))
+    )
+}
+
+/// A source value as it would appear in JSON, for error messages and dead letters.
+pub(crate) fn raw_json(value: &Scalar, binary: BinaryEncoding) -> serde_json::Value {
+    match value {
+        Scalar::Null => serde_json::Value::Null,
+        Scalar::Bool(b) => (*b).into(),
+        Scalar::I32(i) => (*i).into(),
+        Scalar::I64(i) => (*i).into(),
+        Scalar::F32(f) => (*f as f64).into(),
+        Scalar::F64(f) => (*f).into(),
+        Scalar::Str(s) => s.as_str().into(),
+        Scalar::Bin(b) => binary.encode(b).into(),
+        Scalar::Date64(_) | Scalar::Timestamp(_) | Scalar::Decimal128(..) => {
+            value.to_string().into()
+        }
+    }
+}
+
+/// Apply a source's `NullOptions` to the string values of a decoded batch.
+pub(crate) fn normalize_nulls(batch: &mut RowBatch, opts: &NullOptions) {
+    if opts.is_default() {
+        return;
+    }
+    for value in batch.columns.iter_mut().flat_map(|c| c.values.iter_mut()) {
+        if let Scalar::Str(s) = value {
+            *value = match opts.normalize(s) {
+                None => Scalar::Null,
+                Some(v) if v.len() != s.len() => Scalar::Str(v.to_string()),
+                Some(_) => continue,
+            };
+        }
+    }
+}
+
 fn now_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -385,12 +1407,241 @@ fn now_millis() -> u64 {
         .as_millis() as u64
 }
 
-fn xor_hashes(a: Hash256, b: Hash256) -> Hash256 {
-    let mut out = [0u8; 32];
-    for i in 0..32 {
-        out[i] = a.0[i] ^ b.0[i];
+/// Split `batch` after its first `n` rows.
+#[cfg(feature = "parquet")]
+fn split_rows(mut batch: RowBatch, n: usize) -> (RowBatch, RowBatch) {
+    let tail = RowBatch {
+        columns: batch
+            .columns
+            .iter_mut()
+            .map(|c| Column {
+                name: c.name.clone(),
+                values: c.values.split_off(n.min(c.values.len())),
+            })
+            .collect(),
+    };
+    (batch, tail)
+}
+
+/// Approximate in-memory size of a batch: 8 bytes per fixed-width value,
+/// payload length for strings/binaries.
+pub(crate) fn batch_bytes(batch: &RowBatch) -> usize {
+    batch
+        .columns
+        .iter()
+        .flat_map(|col| col.values.iter())
+        .map(scalar_bytes)
+        .sum()
+}
+
+/// Approximate in-memory size of one value (as counted by `batch_bytes`).
+pub(crate) fn scalar_bytes(v: &Scalar) -> usize {
+    match v {
+        Scalar::Str(s) => s.len(),
+        Scalar::Bin(b) => b.len(),
+        _ => 8,
+    }
+}
+
+/// Refuse a run whose estimated spill does not fit the free space on the
+/// spill volume. Plans that spill nothing are not checked.
+fn check_spill_space(cfg: &EngineConfig, program: &PhysicalProgram) -> Result<(), ExecError> {
+    let estimate = estimate_spill(program, cfg.mem_cap_bytes);
+    if estimate.ops.is_empty() {
+        return Ok(());
+    }
+    // An unreadable volume is not a reason to refuse the run.
+    let Ok(available) = available_space(Path::new(&cfg.spill_dir)) else {
+        return Ok(());
+    };
+    estimate
+        .check(&cfg.spill_dir, available)
+        .map_err(ExecError::SpillSpace)
+}
+
+/// Check every source/sink binding against the sandbox allow/deny lists.
+pub(crate) fn check_sandbox(
+    sandbox: &SandboxConfig,
+    program: &PhysicalProgram,
+) -> Result<(), ExecError> {
+    for binding in program.bindings.values() {
+        check_binding_sandbox(sandbox, &binding.key, &binding.config)?;
+    }
+    Ok(())
+}
+
+/// Check the paths one operator binding reads and writes against the sandbox.
+fn check_binding_sandbox(
+    sandbox: &SandboxConfig,
+    key: &str,
+    config: &serde_json::Value,
+) -> Result<(), ExecError> {
+    let str_of = |name: &str| config.get(name).and_then(|v| v.as_str()).unwrap_or("");
+    match key {
+        "source" => {
+            sandbox
+                .check_read(str_of("source"))
+                .map_err(ExecError::Sandbox)?;
+            let dead_letter = config
+                .pointer("/schema/parse_errors/dead_letter")
+                .and_then(|v| v.as_str());
+            if let Some(path) = dead_letter {
+                sandbox.check_write(path).map_err(ExecError::Sandbox)?;
+            }
+        }
+        "filter_in" => {
+            sandbox
+                .check_read(str_of("keys"))
+                .map_err(ExecError::Sandbox)?;
+        }
+        "sink" => {
+            sandbox
+                .check_write(str_of("destination"))
+                .map_err(ExecError::Sandbox)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Fail once `started` is `limit` milliseconds (the sandbox's wall time) ago.
+fn check_wall_time(limit: Option<u64>, started: Instant, before: &str) -> Result<(), ExecError> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    let elapsed = started.elapsed().as_millis() as u64;
+    if elapsed >= limit {
+        return Err(ExecError::Sandbox(format!(
+            "wall time limit of {}ms reached after {}ms (before {})",
+            limit, elapsed, before
+        )));
+    }
+    Ok(())
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = lock_spills(&self.spill_mgr).delete_all();
+    }
+}
+
+/// The spill manager, even if a panic poisoned its lock.
+fn lock_spills(mgr: &Mutex<SpillManager>) -> std::sync::MutexGuard<'_, SpillManager> {
+    mgr.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Deletes, when dropped, the spill segments written since it was created,
+/// then sweeps the spill root as `cleanup` says.
+struct NewSpills {
+    mgr: Arc<Mutex<SpillManager>>,
+    before: HashSet<SegmentName>,
+    cleanup: SpillCleanup,
+}
+
+impl NewSpills {
+    fn track(mgr: &Arc<Mutex<SpillManager>>, cleanup: SpillCleanup) -> Self {
+        let before = lock_spills(mgr).list_segments().into_iter().collect();
+        Self {
+            mgr: mgr.clone(),
+            before,
+            cleanup,
+        }
+    }
+}
+
+impl Drop for NewSpills {
+    fn drop(&mut self) {
+        let mut mgr = lock_spills(&self.mgr);
+        for name in mgr.list_segments() {
+            if !self.before.contains(&name) {
+                let _ = mgr.delete_segment(&name);
+            }
+        }
+        let _ = mgr.sweep(self.cleanup == SpillCleanup::Sweep);
+    }
+}
+
+/// Stack batches with the same columns vertically (column set taken from the first).
+fn concat_rows(batches: &[RowBatch]) -> RowBatch {
+    let mut columns: Vec<Column> = match batches.iter().find(|b| !b.columns.is_empty()) {
+        Some(first) => first
+            .columns
+            .iter()
+            .map(|c| Column {
+                name: c.name.clone(),
+                values: Vec::new(),
+            })
+            .collect(),
+        None => return RowBatch { columns: vec![] },
+    };
+    for batch in batches {
+        for col in &mut columns {
+            if let Some(src) = batch.columns.iter().find(|c| c.name == col.name) {
+                col.values.extend(src.values.iter().cloned());
+            }
+        }
+    }
+    RowBatch { columns }
+}
+
+/// Split `batch` into `count` batches by the hash of its `keys` columns.
+fn split_partitions(
+    batch: RowBatch,
+    keys: &[String],
+    count: usize,
+    hasher: &PartitionHasher,
+) -> Result<Vec<RowBatch>, String> {
+    let count = count.max(1);
+    let assignments = batch.hash_columns_with(keys, count, hasher)?;
+    let mut parts: Vec<RowBatch> = (0..count)
+        .map(|_| RowBatch {
+            columns: batch
+                .columns
+                .iter()
+                .map(|c| Column {
+                    name: c.name.clone(),
+                    values: Vec::new(),
+                })
+                .collect(),
+        })
+        .collect();
+    for (i, column) in batch.columns.into_iter().enumerate() {
+        for (value, &part) in column.values.into_iter().zip(&assignments) {
+            parts[part].columns[i].values.push(value);
+        }
     }
-    Hash256(out)
+    Ok(parts)
+}
+
+/// Parse an `on` list of `[left, right]` column pairs.
+fn json_to_join_keys(value: Option<&serde_json::Value>) -> Vec<(String, String)> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| {
+                    let pair = v.as_array()?;
+                    if pair.len() != 2 {
+                        return None;
+                    }
+                    Some((pair[0].as_str()?.to_string(), pair[1].as_str()?.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The filters a join evaluates on its inputs (`left_filter`/`right_filter`).
+fn join_filters(config: &serde_json::Value, cfg: &EngineConfig) -> Result<InputFilters, String> {
+    let filters = InputFilters::parse(
+        config.get("left_filter").and_then(|v| v.as_str()),
+        config.get("right_filter").and_then(|v| v.as_str()),
+        cfg.seed.unwrap_or(0),
+    )?;
+    Ok(InputFilters {
+        arith_errors: cfg.arithmetic_errors,
+        ..filters
+    })
 }
 
 fn json_to_vec_strings(value: Option<&serde_json::Value>) -> Vec<String> {
@@ -443,13 +1694,20 @@ fn parse_window_functions(value: Option<&serde_json::Value>) -> Vec<WindowFnSpec
 
 // --- placeholder source/sink operators (until real IO is wired) ---
 
+/// Filesystem path (or glob) of a source URI.
+pub(crate) fn source_path(uri: &str) -> &str {
+    uri.strip_prefix("file://").unwrap_or(uri)
+}
+
 /// Detect file format from URI/path (by extension or explicit format parameter).
-fn detect_file_format(uri: &str, format_param: Option<&str>) -> &'static str {
+pub(crate) fn detect_file_format(uri: &str, format_param: Option<&str>) -> &'static str {
     if let Some(fmt) = format_param {
         // If format param is provided and matches known formats, return static string
         match fmt {
             "parquet" | "parq" => return "parquet",
             "csv" => return "csv",
+            "jsonl" | "ndjson" => return "jsonl",
+            "avro" => return "avro",
             _ => return "csv", // Default fallback
         }
     }
@@ -458,6 +1716,12 @@ fn detect_file_format(uri: &str, format_param: Option<&str>) -> &'static str {
     if uri.ends_with(".parquet") || uri.ends_with(".parq") {
         return "parquet";
     }
+    if uri.ends_with(".jsonl") || uri.ends_with(".ndjson") {
+        return "jsonl";
+    }
+    if uri.ends_with(".avro") {
+        return "avro";
+    }
 
     // Default to CSV
     "csv"
@@ -466,11 +1730,292 @@ fn detect_file_format(uri: &str, format_param: Option<&str>) -> &'static str {
 struct SourceOp {
     source_uri: String,
     schema: Schema,
-    // Track file position for multi-block reading (CSV)
-    file_position: Arc<Mutex<usize>>,
+    // Files of a glob or directory source, listed on first read, and the
+    // one being read; the files read as one stream of rows, in path order
+    multi_file: bool,
+    files: Mutex<Option<Arc<Vec<String>>>>,
+    file_index: Mutex<usize>,
+    // Rows handed out so far; where an unranged read continues
+    position: Mutex<usize>,
+    // JSONL pushdown (planner-supplied) and reader reused across blocks
+    projection: Option<Vec<String>>,
+    predicates: Vec<FieldPredicate>,
+    jsonl_reader: Arc<Mutex<Option<JsonlReader<ResumableFile>>>>,
+    // Delimited/fixed-width reader, for sources with a declared layout
+    text_reader: Mutex<Option<TextReader<ResumableFile>>>,
+    // CSV reader, kept open so each block continues the last
+    csv_source: Mutex<Option<CsvBlockSource>>,
+    // Rows per block, adapted to budget pressure
+    sizer: Mutex<ReadSizer>,
+    // Text encoding of Binary columns in CSV/JSONL files
+    binary: BinaryEncoding,
+    // Retries of failed file reads, resuming at the failed offset
+    read_retry: ReadRetryConfig,
+    // Optional cap on total rows read (sampling), and rows read so far
+    limit_rows: Option<usize>,
+    rows_read: Mutex<usize>,
+    // Bytes read from the source's files so far (all readers)
+    scanned: Arc<AtomicU64>,
     // Parquet reader (initialized on first read, reused for subsequent blocks)
     #[cfg(feature = "parquet")]
     parquet_reader: Arc<Mutex<Option<emsqrt_io::readers::parquet::ParquetReader>>>,
+    // Rows of a Parquet batch read past the end of the previous block
+    #[cfg(feature = "parquet")]
+    parquet_carry: Mutex<Option<RowBatch>>,
+    // Avro reader, reused across blocks
+    #[cfg(feature = "avro")]
+    avro_reader: Mutex<Option<emsqrt_io::readers::avro::AvroReader<ResumableFile>>>,
+    // Rows of the last CSV read bound for the dead-letter file, and its writer
+    dead_rows: Mutex<Vec<DeadRow>>,
+    dead_letter: Mutex<Option<std::io::BufWriter<std::fs::File>>>,
+    // Continue an existing dead-letter file (follow mode) instead of truncating it
+    append_dead_letter: bool,
+}
+
+/// A source row dropped by `on_parse_error: dead_letter`.
+struct DeadRow {
+    /// Index within the batch returned by the read.
+    row: usize,
+    line: Option<u64>,
+    column: String,
+    value: String,
+    record: serde_json::Map<String, serde_json::Value>,
+}
+
+impl SourceOp {
+    /// Open a source file for reading with the configured read retries.
+    fn open_file(&self, file_path: &str) -> emsqrt_io::error::Result<ResumableFile> {
+        Ok(ResumableFile::open(file_path, &self.read_retry)?
+            .with_scan_counter(self.scanned.clone()))
+    }
+
+    /// Append one record's raw cells (see [`push_cells`]).
+    #[allow(clippy::too_many_arguments)]
+    fn push_record<'a>(
+        &self,
+        ctx: &OpContext,
+        columns: &mut [Column],
+        cells: impl Iterator<Item = &'a str>,
+        line: Option<u64>,
+        row: usize,
+        record: impl Fn() -> serde_json::Map<String, serde_json::Value>,
+        dead_rows: &mut Vec<DeadRow>,
+    ) -> Result<(), OpError> {
+        push_cells(
+            &self.source_uri,
+            &self.schema,
+            self.binary,
+            ctx,
+            columns,
+            cells,
+            line,
+            row,
+            record,
+            dead_rows,
+        )
+    }
+
+    /// Read the next block of a delimited or fixed-width file (`schema.layout`).
+    fn read_text_block(
+        &self,
+        ctx: &OpContext,
+        file_path: &str,
+        batch_rows: usize,
+    ) -> Result<RowBatch, OpError> {
+        let mut reader_guard = self.text_reader.lock().unwrap();
+        if reader_guard.is_none() {
+            *reader_guard = Some(
+                self.open_file(file_path)
+                    .and_then(|f| TextReader::from_reader(f, &self.schema))
+                    .map_err(|e| {
+                        OpError::Exec(format!("failed to open text file '{}': {}", file_path, e))
+                    })?,
+            );
+        }
+        let reader = reader_guard.as_mut().expect("initialized above");
+
+        let mut columns = self.empty_batch().columns;
+        let mut dead_rows = self.dead_rows.lock().unwrap();
+        dead_rows.clear();
+        let mut row_count = 0;
+        while row_count < batch_rows {
+            let record = match reader.next_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => return Err(OpError::Exec(format!("text read error: {}", e))),
+            };
+            self.push_record(
+                ctx,
+                &mut columns,
+                record.values.iter().map(String::as_str),
+                Some(record.line),
+                row_count,
+                || {
+                    self.schema
+                        .fields
+                        .iter()
+                        .zip(&record.values)
+                        .map(|(f, v)| (f.name.clone(), v.as_str().into()))
+                        .collect()
+                },
+                &mut dead_rows,
+            )?;
+            row_count += 1;
+        }
+        Ok(RowBatch { columns })
+    }
+
+    /// Read the next block of an Avro file, projected to the schema's columns.
+    #[cfg(feature = "avro")]
+    fn read_avro_block(&self, file_path: &str, batch_rows: usize) -> Result<RowBatch, OpError> {
+        use emsqrt_io::readers::avro::AvroReader;
+
+        let mut reader_guard = self.avro_reader.lock().unwrap();
+        if reader_guard.is_none() {
+            let projection = (!self.schema.fields.is_empty())
+                .then(|| self.schema.fields.iter().map(|f| f.name.clone()).collect());
+            *reader_guard = Some(
+                self.open_file(file_path)
+                    .and_then(|f| AvroReader::from_reader(f, projection))
+                    .map_err(|e| {
+                        OpError::Exec(format!("failed to open Avro file '{}': {}", file_path, e))
+                    })?,
+            );
+        }
+        let reader = reader_guard.as_mut().expect("initialized above");
+        match reader.next_batch(batch_rows) {
+            Ok(Some(mut batch)) => {
+                normalize_nulls(&mut batch, &self.schema.null_options);
+                Ok(batch)
+            }
+            Ok(None) => Ok(self.empty_batch()),
+            Err(e) => Err(OpError::Exec(format!("Avro read error: {}", e))),
+        }
+    }
+
+    #[cfg(not(feature = "avro"))]
+    fn read_avro_block(&self, file_path: &str, _batch_rows: usize) -> Result<RowBatch, OpError> {
+        Err(OpError::Exec(format!(
+            "'{}' is Avro; rebuild with --features avro",
+            file_path
+        )))
+    }
+
+    /// Read the next block of a JSONL file, applying projection/predicates while
+    /// parsing and converting values to the declared field types.
+    fn read_jsonl_block(
+        &self,
+        ctx: &OpContext,
+        file_path: &str,
+        batch_rows: usize,
+    ) -> Result<RowBatch, OpError> {
+        let mut reader_guard = self.jsonl_reader.lock().unwrap();
+        if reader_guard.is_none() {
+            let mut reader = self
+                .open_file(file_path)
+                .and_then(JsonlReader::from_reader)
+                .map_err(|e| {
+                    OpError::Exec(format!("failed to open JSONL file '{}': {}", file_path, e))
+                })?;
+            if let Some(projection) = &self.projection {
+                reader = reader.with_projection(projection.clone());
+            }
+            *reader_guard = Some(
+                reader
+                    .with_flatten(self.schema.flatten)
+                    .with_types(&self.schema.fields)
+                    .with_predicates(self.predicates.clone()),
+            );
+        }
+        let reader = reader_guard.as_mut().expect("initialized above");
+
+        let batch = match reader.next_batch(batch_rows) {
+            // Records lacking declared keys read as nulls already; `align`
+            // also adds keys no record of this file has.
+            Ok(Some(batch)) if self.schema.align.fills_missing() && self.projection.is_none() => {
+                align_batch(&batch, &self.schema, self.schema.align)
+                    .map_err(|e| OpError::Schema(format!("JSONL file '{}': {}", file_path, e)))
+            }
+            Ok(Some(batch)) => Ok(batch),
+            Ok(None) => Ok(RowBatch {
+                columns: reader
+                    .schema()
+                    .fields
+                    .iter()
+                    .map(|f| Column {
+                        name: f.name.clone(),
+                        values: Vec::new(),
+                    })
+                    .collect(),
+            }),
+            Err(e) => Err(OpError::Exec(format!("JSONL read error: {}", e))),
+        };
+        let mut batch = batch?;
+        normalize_nulls(&mut batch, &self.schema.null_options);
+        self.check_json_types(ctx, &mut batch)?;
+        Ok(batch)
+    }
+
+    /// Apply each field's parse-error policy to JSONL values the reader could
+    /// not convert to the declared type, and decode `Binary` text.
+    fn check_json_types(&self, ctx: &OpContext, batch: &mut RowBatch) -> Result<(), OpError> {
+        let mut dead_rows = self.dead_rows.lock().unwrap();
+        dead_rows.clear();
+        let record = |batch: &RowBatch, row: usize| {
+            batch
+                .columns
+                .iter()
+                .map(|c| (c.name.clone(), raw_json(&c.values[row], self.binary)))
+                .collect()
+        };
+        for field in &self.schema.fields {
+            let Some(index) = batch.columns.iter().position(|c| c.name == field.name) else {
+                continue;
+            };
+            for row in 0..batch.num_rows() {
+                let value = &batch.columns[index].values[row];
+                let reason = match (&field.data_type, value) {
+                    (_, Scalar::Null) => continue,
+                    (DataType::Binary, Scalar::Str(s)) => match self.binary.decode(s) {
+                        Ok(bytes) => {
+                            batch.columns[index].values[row] = Scalar::Bin(bytes);
+                            continue;
+                        }
+                        Err(e) => format!(" ({})", e),
+                    },
+                    (data_type, value) if has_type(value, data_type) => continue,
+                    _ => String::new(),
+                };
+                ctx.metrics
+                    .add(&format!("{}{}", PARSE_ERROR_METRIC, field.name), 1);
+                match self.schema.parse_errors.policy_for(&field.name) {
+                    ParseErrorPolicy::Null => {}
+                    ParseErrorPolicy::Fail => {
+                        return Err(OpError::Exec(format!(
+                            "source '{}': column '{}' value {} is not a valid {:?}{}",
+                            self.source_uri,
+                            field.name,
+                            raw_json(value, self.binary),
+                            field.data_type,
+                            reason
+                        )));
+                    }
+                    ParseErrorPolicy::DeadLetter => dead_rows.push(DeadRow {
+                        row,
+                        line: None,
+                        column: field.name.clone(),
+                        value: match value {
+                            Scalar::Str(s) => s.clone(),
+                            other => raw_json(other, self.binary).to_string(),
+                        },
+                        record: record(batch, row),
+                    }),
+                }
+                batch.columns[index].values[row] = Scalar::Null;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Operator for SourceOp {
@@ -489,19 +2034,328 @@ impl Operator for SourceOp {
         ))
     }
     fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+    fn open(&self) -> Result<(), OpError> {
+        self.reset_readers();
+        *self.files.lock().unwrap() = None;
+        *self.file_index.lock().unwrap() = 0;
+        *self.position.lock().unwrap() = 0;
+        *self.rows_read.lock().unwrap() = 0;
+        Ok(())
+    }
+    fn finish(&self) -> Result<(), OpError> {
+        self.reset_readers();
+        if let Some(writer) = self.dead_letter.lock().unwrap().as_mut() {
+            writer.flush().map_err(|e| {
+                OpError::Exec(format!(
+                    "writing dead-letter file '{}': {}",
+                    self.schema
+                        .parse_errors
+                        .dead_letter
+                        .as_deref()
+                        .unwrap_or(""),
+                    e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+    fn eval_block_with(
         &self,
         _inputs: &[RowBatch],
-        _budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+        ctx: &OpContext,
+        budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
-        // Strip file:// prefix if present
-        let file_path = if self.source_uri.starts_with("file://") {
-            &self.source_uri[7..]
-        } else {
-            &self.source_uri
+        // Shrink/grow unplanned reads under budget pressure.
+        let sized = || {
+            self.sizer
+                .lock()
+                .unwrap()
+                .next_rows(budget.used_bytes(), budget.capacity_bytes())
+        };
+        match ctx.range {
+            // The plan's row count is an estimate, so the last block reads its
+            // range and then on to the end of the input, a sized chunk at a time.
+            Some(r) if r.open_end => {
+                let mut start = r.start as usize;
+                let mut rows = r.len() as usize;
+                let mut parts = Vec::new();
+                while let Some((batch, read)) = self.read_chunk(start, rows, ctx)? {
+                    parts.push(batch);
+                    if read < rows {
+                        break;
+                    }
+                    ctx.check_cancelled()?;
+                    start += read;
+                    rows = sized();
+                }
+                Ok(match parts.len() {
+                    0 => self.empty_batch(),
+                    1 => parts.pop().expect("one part"),
+                    _ => concat_rows(&parts),
+                })
+            }
+            Some(r) => Ok(self
+                .read_chunk(r.start as usize, r.len() as usize, ctx)?
+                .map_or_else(|| self.empty_batch(), |(batch, _)| batch)),
+            None => {
+                let start = *self.position.lock().unwrap();
+                Ok(self
+                    .read_chunk(start, sized(), ctx)?
+                    .map_or_else(|| self.empty_batch(), |(batch, _)| batch))
+            }
+        }
+    }
+}
+
+impl SourceOp {
+    /// Read up to `rows` rows from `start`, with the sample limit applied and
+    /// dead rows diverted, and the rows taken from the input (before either).
+    /// `None` once the sample limit is reached.
+    fn read_chunk(
+        &self,
+        start: usize,
+        rows: usize,
+        ctx: &OpContext,
+    ) -> Result<Option<(RowBatch, usize)>, OpError> {
+        let Some(rows) = self.rows_remaining(rows) else {
+            return Ok(None);
+        };
+        // Bytes the readers pull from storage for this block, read-ahead included.
+        let scanned_before = self.scanned.load(Ordering::Relaxed);
+        let read = self.read_range(start, rows, ctx);
+        ctx.metrics.add(
+            BYTES_SCANNED,
+            self.scanned
+                .load(Ordering::Relaxed)
+                .saturating_sub(scanned_before),
+        );
+        let mut batch = read?;
+        let read = batch.num_rows();
+        // Readers with a fixed batch size (Parquet) may overshoot a sample limit.
+        if let Some(limit) = self.limit_rows {
+            let mut read = self.rows_read.lock().unwrap();
+            let keep = batch.num_rows().min(limit.saturating_sub(*read));
+            for col in &mut batch.columns {
+                col.values.truncate(keep);
+            }
+            *read += keep;
+        }
+        self.divert_dead_rows(&mut batch)?;
+        Ok(Some((batch, read)))
+    }
+
+    /// Move the rows the last read marked for the dead-letter file out of `batch`.
+    fn divert_dead_rows(&self, batch: &mut RowBatch) -> Result<(), OpError> {
+        let dead = std::mem::take(&mut *self.dead_rows.lock().unwrap());
+        let rows = batch.num_rows();
+        let dead: Vec<DeadRow> = dead.into_iter().filter(|d| d.row < rows).collect();
+        if dead.is_empty() {
+            return Ok(());
+        }
+        let Some(path) = &self.schema.parse_errors.dead_letter else {
+            return Err(OpError::Exec(
+                "on_parse_error: dead_letter needs a 'dead_letter' path".into(),
+            ));
+        };
+        let io_err = |e: std::io::Error| {
+            OpError::Exec(format!("writing dead-letter file '{}': {}", path, e))
+        };
+
+        let mut writer = self.dead_letter.lock().unwrap();
+        if writer.is_none() {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(self.append_dead_letter)
+                .truncate(!self.append_dead_letter)
+                .open(path)
+                .map_err(io_err)?;
+            *writer = Some(std::io::BufWriter::new(file));
+        }
+        let out = writer.as_mut().expect("opened above");
+        let mut drop_row = vec![false; rows];
+        for d in &dead {
+            // Several bad cells in one row: the row is written once, for the first.
+            if std::mem::replace(&mut drop_row[d.row], true) {
+                continue;
+            }
+            let entry = serde_json::json!({
+                "source": self.source_uri,
+                "line": d.line,
+                "column": d.column,
+                "value": d.value,
+                "record": d.record,
+            });
+            writeln!(out, "{}", entry).map_err(io_err)?;
+        }
+        out.flush().map_err(io_err)?;
+
+        for col in &mut batch.columns {
+            let mut i = 0;
+            col.values.retain(|_| {
+                i += 1;
+                !drop_row[i - 1]
+            });
+        }
+        Ok(())
+    }
+
+    /// Rows the next read may return under `limit_rows`; `None` once the limit is reached.
+    fn rows_remaining(&self, batch_rows: usize) -> Option<usize> {
+        match self.limit_rows {
+            Some(limit) => {
+                let remaining = limit.saturating_sub(*self.rows_read.lock().unwrap());
+                (remaining > 0).then(|| batch_rows.min(remaining))
+            }
+            None => Some(batch_rows),
+        }
+    }
+
+    fn empty_batch(&self) -> RowBatch {
+        RowBatch {
+            columns: self
+                .schema
+                .fields
+                .iter()
+                .map(|f| Column {
+                    name: f.name.clone(),
+                    values: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The files this source reads, listed on first use.
+    fn files(&self) -> Result<Arc<Vec<String>>, OpError> {
+        let mut files = self.files.lock().unwrap();
+        if let Some(files) = &*files {
+            return Ok(files.clone());
+        }
+        let listed = list_source_files(source_path(&self.source_uri))
+            .map_err(|e| OpError::Exec(format!("source '{}': {}", self.source_uri, e)))?;
+        Ok(files.insert(Arc::new(listed)).clone())
+    }
+
+    /// Read `rows` rows starting at row `start` of the source.
+    fn read_range(&self, start: usize, rows: usize, ctx: &OpContext) -> Result<RowBatch, OpError> {
+        let files = self.files()?;
+        let mut position = self.position.lock().unwrap();
+        let mut file = self.file_index.lock().unwrap();
+        // Readers are sequential, so restart them to go back and skip rows to
+        // go forward (CSV without decoding them; the others discard rows read).
+        if start < *position {
+            self.reset_readers();
+            *position = 0;
+            *file = 0;
+        }
+        while *position < start {
+            let Some(file_path) = files.get(*file) else {
+                return Ok(self.empty_batch());
+            };
+            let skipped = if self.format(file_path) == "csv" {
+                let skipped = self.with_csv(file_path, |csv| csv.skip(start - *position))?;
+                ctx.metrics.add("rows_skipped", skipped as u64);
+                skipped
+            } else {
+                ctx.check_cancelled()?;
+                let skipped = self
+                    .read_next(ctx, file_path, (start - *position).min(SKIP_CHUNK_ROWS))?
+                    .num_rows();
+                ctx.metrics.add("rows_discarded", skipped as u64);
+                skipped
+            };
+            *position += skipped;
+            if skipped == 0 {
+                self.next_file(&mut file);
+            }
+        }
+
+        // Read on through the following files until `rows` are in hand.
+        let mut parts = Vec::new();
+        let mut dead_rows = Vec::new();
+        let mut have = 0;
+        let mut last_empty = None;
+        while have < rows {
+            let Some(file_path) = files.get(*file) else {
+                break;
+            };
+            let batch = self.read_next(ctx, file_path, rows - have)?;
+            let read = batch.num_rows();
+            if read == 0 {
+                last_empty = Some(batch);
+                self.next_file(&mut file);
+                continue;
+            }
+            dead_rows.extend(
+                std::mem::take(&mut *self.dead_rows.lock().unwrap())
+                    .into_iter()
+                    .map(|d| DeadRow {
+                        row: d.row + have,
+                        ..d
+                    }),
+            );
+            have += read;
+            parts.push(batch);
+        }
+        *self.dead_rows.lock().unwrap() = dead_rows;
+        let batch = match parts.len() {
+            0 => last_empty.unwrap_or_else(|| self.empty_batch()),
+            1 => parts.pop().expect("one part"),
+            _ => concat_rows(&parts),
         };
+        *position = start + batch.num_rows();
+        Ok(batch)
+    }
+
+    /// Move on to the next file of a multi-file source.
+    fn next_file(&self, file: &mut usize) {
+        self.reset_readers();
+        *file += 1;
+    }
+
+    /// Reader to use: "text" for a declared layout, else the declared format,
+    /// else by extension.
+    fn format(&self, file_path: &str) -> &'static str {
+        if self.schema.layout.is_some() {
+            "text"
+        } else {
+            detect_file_format(file_path, self.schema.format.map(|f| f.as_str()))
+        }
+    }
+
+    fn reset_readers(&self) {
+        *self.csv_source.lock().unwrap() = None;
+        *self.jsonl_reader.lock().unwrap() = None;
+        *self.text_reader.lock().unwrap() = None;
+        #[cfg(feature = "parquet")]
+        {
+            *self.parquet_reader.lock().unwrap() = None;
+            *self.parquet_carry.lock().unwrap() = None;
+        }
+        #[cfg(feature = "avro")]
+        {
+            *self.avro_reader.lock().unwrap() = None;
+        }
+    }
+
+    /// Read up to `batch_rows` rows, continuing where the last read stopped.
+    fn read_next(
+        &self,
+        ctx: &OpContext,
+        file_path: &str,
+        batch_rows: usize,
+    ) -> Result<RowBatch, OpError> {
+        let _format = self.format(file_path);
 
-        // Detect file format
-        let _format = detect_file_format(file_path, None);
+        if _format == "text" {
+            return self.read_text_block(ctx, file_path, batch_rows);
+        }
 
         // Handle Parquet files
         #[cfg(feature = "parquet")]
@@ -512,74 +2366,223 @@ impl Operator for SourceOp {
 
             // Initialize reader on first call
             if reader_guard.is_none() {
-                // Determine projection from schema if provided
-                let projection = if self.schema.fields.is_empty() {
-                    None // Read all columns
-                } else {
-                    Some(self.schema.fields.iter().map(|f| f.name.clone()).collect())
-                };
+                // Columns pushed down from a projection, else the declared
+                // schema, else all of them.
+                let projection = self.projection.clone().or_else(|| {
+                    (!self.schema.fields.is_empty())
+                        .then(|| self.schema.fields.iter().map(|f| f.name.clone()).collect())
+                });
 
                 let reader =
-                    ParquetReader::from_path(file_path, projection, 10000).map_err(|e| {
+                    ParquetReader::from_path(file_path, projection, batch_rows).map_err(|e| {
                         OpError::Exec(format!("failed to create Parquet reader: {}", e))
                     })?;
 
                 // If schema was not provided, infer from Parquet file
                 // For now, we use the provided schema or the reader's schema
                 *reader_guard = Some(reader);
+                // The Parquet reader seeks its own file handle; count the file once.
+                if let Ok(meta) = std::fs::metadata(file_path) {
+                    self.scanned.fetch_add(meta.len(), Ordering::Relaxed);
+                }
             }
 
-            // Read next batch
+            // Read whole Parquet batches until `batch_rows` are in hand; keep the rest
             if let Some(ref mut reader) = *reader_guard {
-                match reader.next_batch() {
-                    Ok(Some(batch)) => return Ok(batch),
-                    Ok(None) => {
-                        // End of file - return empty batch with correct schema
-                        return Ok(RowBatch {
-                            columns: self
-                                .schema
-                                .fields
-                                .iter()
-                                .map(|f| emsqrt_core::types::Column {
-                                    name: f.name.clone(),
-                                    values: Vec::new(),
-                                })
-                                .collect(),
-                        });
+                let mut carry = self.parquet_carry.lock().unwrap();
+                let mut parts: Vec<RowBatch> = carry.take().into_iter().collect();
+                let mut have: usize = parts.iter().map(|b| b.num_rows()).sum();
+                while have < batch_rows {
+                    match reader.next_batch() {
+                        Ok(Some(batch)) => {
+                            have += batch.num_rows();
+                            parts.push(batch);
+                        }
+                        Ok(None) => break,
+                        Err(e) => return Err(OpError::Exec(format!("Parquet read error: {}", e))),
                     }
-                    Err(e) => return Err(OpError::Exec(format!("Parquet read error: {}", e))),
                 }
+                if parts.is_empty() {
+                    // End of file - return empty batch with correct schema
+                    return Ok(self.empty_batch());
+                }
+                let (mut head, tail) = split_rows(concat_rows(&parts), batch_rows);
+                if tail.num_rows() > 0 {
+                    *carry = Some(tail);
+                }
+                normalize_nulls(&mut head, &self.schema.null_options);
+                return Ok(head);
             }
         }
 
-        // Read CSV file with provided schema (default/fallback)
-        use emsqrt_core::types::{Column, Scalar};
-        use std::fs::File;
+        #[cfg(not(feature = "parquet"))]
+        if _format == "parquet" {
+            return Err(OpError::Exec(format!(
+                "'{}' is Parquet; rebuild with --features parquet",
+                file_path
+            )));
+        }
+
+        if _format == "avro" {
+            return self.read_avro_block(file_path, batch_rows);
+        }
+
+        if _format == "jsonl" {
+            return self.read_jsonl_block(ctx, file_path, batch_rows);
+        }
+
+        // CSV (default/fallback), from the reader kept open across blocks
+        self.with_csv(file_path, |csv| {
+            let first = csv.position() == 0;
+            let batch = csv.next_block(ctx, batch_rows)?;
+            *self.dead_rows.lock().unwrap() = std::mem::take(&mut csv.dead_rows);
+            // One empty file among several is skipped.
+            if first && batch.num_rows() == 0 && !self.multi_file {
+                return Err(OpError::Exec("no data in CSV file".into()));
+            }
+            Ok(batch)
+        })
+    }
+
+    /// Run `f` on the CSV reader of this source, opened on first use.
+    fn with_csv<T>(
+        &self,
+        file_path: &str,
+        f: impl FnOnce(&mut CsvBlockSource) -> Result<T, OpError>,
+    ) -> Result<T, OpError> {
+        let mut guard = self.csv_source.lock().unwrap();
+        let csv = match &mut *guard {
+            Some(csv) => csv,
+            none => none.insert(CsvBlockSource::open(
+                file_path,
+                &self.source_uri,
+                &self.schema,
+                self.projection.as_deref(),
+                self.binary,
+                &self.read_retry,
+                self.scanned.clone(),
+            )?),
+        };
+        f(csv)
+    }
+}
+
+/// Append one record's raw cells (in schema field order) of source
+/// `source_uri` to `columns`: null normalization, typing, and the field's
+/// parse-error policy.
+#[allow(clippy::too_many_arguments)]
+fn push_cells<'a>(
+    source_uri: &str,
+    schema: &Schema,
+    binary: BinaryEncoding,
+    ctx: &OpContext,
+    columns: &mut [Column],
+    cells: impl Iterator<Item = &'a str>,
+    line: Option<u64>,
+    row: usize,
+    record: impl Fn() -> serde_json::Map<String, serde_json::Value>,
+    dead_rows: &mut Vec<DeadRow>,
+) -> Result<(), OpError> {
+    for ((column, field), raw) in columns.iter_mut().zip(&schema.fields).zip(cells) {
+        let Some(value) = schema.null_options.normalize(raw) else {
+            column.values.push(Scalar::Null);
+            continue;
+        };
+
+        // Parse value based on schema type; empty cells are null, not errors.
+        let scalar = match parse_cell(&field.data_type, value, binary) {
+            Some(scalar) => scalar,
+            None if value.is_empty() => Scalar::Null,
+            None => {
+                ctx.metrics
+                    .add(&format!("{}{}", PARSE_ERROR_METRIC, field.name), 1);
+                match schema.parse_errors.policy_for(&field.name) {
+                    ParseErrorPolicy::Null => {}
+                    ParseErrorPolicy::Fail => {
+                        return Err(OpError::Exec(format!(
+                            "source '{}' line {}: column '{}' value '{}' is not a valid {:?}",
+                            source_uri,
+                            line.map_or("?".to_string(), |l| l.to_string()),
+                            field.name,
+                            value,
+                            field.data_type
+                        )));
+                    }
+                    ParseErrorPolicy::DeadLetter => dead_rows.push(DeadRow {
+                        row,
+                        line,
+                        column: field.name.clone(),
+                        value: value.to_string(),
+                        record: record(),
+                    }),
+                }
+                Scalar::Null
+            }
+        };
+        column.values.push(scalar);
+    }
+    Ok(())
+}
 
-        let file = File::open(file_path).map_err(|e| {
-            OpError::Exec(format!("failed to open CSV file '{}': {}", file_path, e))
-        })?;
+/// CSV rows read block by block from one open file.
+struct CsvBlockSource {
+    file_path: String,
+    source_uri: String,
+    schema: Schema,
+    binary: BinaryEncoding,
+    reader: ::csv::Reader<ResumableFile>,
+    headers: ::csv::StringRecord,
+    // Column of each schema field in the file (None: filled by `align`)
+    col_indices: Vec<Option<usize>>,
+    position: u64,
+    // Rows of the last block bound for the dead-letter file
+    dead_rows: Vec<DeadRow>,
+}
 
-        let mut rdr = ::csv::ReaderBuilder::new()
+impl CsvBlockSource {
+    /// Open `file_path` and map the schema's fields to its header. With a
+    /// `projection`, the header is still checked against every field but
+    /// only the projected ones are read.
+    fn open(
+        file_path: &str,
+        source_uri: &str,
+        schema: &Schema,
+        projection: Option<&[String]>,
+        binary: BinaryEncoding,
+        read_retry: &ReadRetryConfig,
+        scanned: Arc<AtomicU64>,
+    ) -> Result<Self, OpError> {
+        let file = ResumableFile::open(file_path, read_retry)
+            .map_err(|e| OpError::Exec(format!("failed to open CSV file '{}': {}", file_path, e)))?
+            .with_scan_counter(scanned);
+
+        let mut reader = ::csv::ReaderBuilder::new()
             .has_headers(true)
             .flexible(true)
             .from_reader(file);
 
         // Build column index mapping from schema field names
-        let headers = rdr
+        let headers = reader
             .headers()
-            .map_err(|e| OpError::Exec(format!("failed to read CSV headers: {}", e)))?;
+            .map_err(|e| OpError::Exec(format!("failed to read CSV headers: {}", e)))?
+            .clone();
 
-        let col_indices: Vec<Option<usize>> = self
-            .schema
+        let col_indices: Vec<Option<usize>> = schema
             .fields
             .iter()
             .map(|field| headers.iter().position(|h| h.trim() == field.name.trim()))
             .collect();
 
-        // Verify all required columns are found
-        for (field, col_idx_opt) in self.schema.fields.iter().zip(col_indices.iter()) {
-            if col_idx_opt.is_none() {
+        // Verify all required columns are found, unless the scan fills them
+        let align = schema.align;
+        if align.fills_missing() {
+            let names: Vec<String> = schema.fields.iter().map(|f| f.name.clone()).collect();
+            align
+                .check(headers.iter().map(str::trim), &names)
+                .map_err(|e| OpError::Schema(format!("CSV file '{}': {}", file_path, e)))?;
+        }
+        for (field, col_idx_opt) in schema.fields.iter().zip(col_indices.iter()) {
+            if col_idx_opt.is_none() && !align.fills_missing() {
                 return Err(OpError::Exec(format!(
                     "CSV file missing required column '{}'. Available columns: {:?}",
                     field.name,
@@ -588,6 +2591,40 @@ impl Operator for SourceOp {
             }
         }
 
+        let mut schema = schema.clone();
+        let mut col_indices = col_indices;
+        if let Some(projection) = projection {
+            let keep: Vec<bool> = schema
+                .fields
+                .iter()
+                .map(|f| projection.contains(&f.name))
+                .collect();
+            let mut kept = keep.iter();
+            schema.fields.retain(|_| *kept.next().unwrap());
+            let mut kept = keep.iter();
+            col_indices.retain(|_| *kept.next().unwrap());
+        }
+
+        Ok(Self {
+            file_path: file_path.to_string(),
+            source_uri: source_uri.to_string(),
+            schema,
+            binary,
+            reader,
+            headers,
+            col_indices,
+            position: 0,
+            dead_rows: Vec::new(),
+        })
+    }
+}
+
+impl BlockSource for CsvBlockSource {
+    fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn next_block(&mut self, ctx: &OpContext, rows: usize) -> Result<RowBatch, OpError> {
         // Initialize columns based on schema
         let mut columns: Vec<Column> = self
             .schema
@@ -599,76 +2636,58 @@ impl Operator for SourceOp {
             })
             .collect();
 
-        // Read rows and populate columns
-        // Skip rows that were already read by previous blocks
-        let mut file_pos = self.file_position.lock().unwrap();
-        let skip_rows = *file_pos;
-
-        // Skip header + already-read rows
+        self.dead_rows.clear();
+        let mut record = ::csv::StringRecord::new();
         let mut row_count = 0;
-        let mut skipped = 0;
-        for result in rdr.records() {
-            // Skip rows that were read in previous blocks
-            if skipped < skip_rows {
-                skipped += 1;
-                continue; // Skip this row, don't process it
-            }
-
-            // We've skipped enough - now process this row
-
-            let record =
-                result.map_err(|e| OpError::Exec(format!("failed to read CSV record: {}", e)))?;
-
-            for (col_idx, field) in self.schema.fields.iter().enumerate() {
-                let value = if let Some(csv_col_idx) = col_indices[col_idx] {
-                    record.get(csv_col_idx).unwrap_or("")
-                } else {
-                    ""
-                };
-
-                // Parse value based on schema type
-                let scalar = match field.data_type {
-                    emsqrt_core::schema::DataType::Int32 => value
-                        .parse::<i32>()
-                        .map(Scalar::I32)
-                        .unwrap_or(Scalar::Null),
-                    emsqrt_core::schema::DataType::Int64 => value
-                        .parse::<i64>()
-                        .map(Scalar::I64)
-                        .unwrap_or(Scalar::Null),
-                    emsqrt_core::schema::DataType::Float32 => value
-                        .parse::<f32>()
-                        .map(Scalar::F32)
-                        .unwrap_or(Scalar::Null),
-                    emsqrt_core::schema::DataType::Float64 => value
-                        .parse::<f64>()
-                        .map(Scalar::F64)
-                        .unwrap_or(Scalar::Null),
-                    emsqrt_core::schema::DataType::Boolean => value
-                        .parse::<bool>()
-                        .map(Scalar::Bool)
-                        .unwrap_or(Scalar::Null),
-                    _ => Scalar::Str(value.to_string()),
-                };
-
-                columns[col_idx].values.push(scalar);
+        while row_count < rows {
+            let more = self
+                .reader
+                .read_record(&mut record)
+                .map_err(|e| OpError::Exec(format!("failed to read CSV record: {}", e)))?;
+            if !more {
+                break;
             }
 
+            let cells = self
+                .col_indices
+                .iter()
+                .map(|i| i.and_then(|i| record.get(i)).unwrap_or(""));
+            push_cells(
+                &self.source_uri,
+                &self.schema,
+                self.binary,
+                ctx,
+                &mut columns,
+                cells,
+                record.position().map(|p| p.line()),
+                row_count,
+                || {
+                    self.headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(h, v)| (h.to_string(), v.into()))
+                        .collect()
+                },
+                &mut self.dead_rows,
+            )?;
             row_count += 1;
-            if row_count >= 10000 {
-                break; // Limit batch size
-            }
         }
+        self.position += row_count as u64;
 
-        // Update file position for next block
-        *file_pos += row_count;
+        // Columns the file lacks (`align`) are null, not empty strings
+        for (column, col_idx) in columns.iter_mut().zip(&self.col_indices) {
+            if col_idx.is_none() {
+                column.values.fill(Scalar::Null);
+            }
+        }
 
         // Ensure all columns have the same number of values
         let num_rows = columns.first().map(|c| c.values.len()).unwrap_or(0);
         for col in &mut columns {
             if col.values.len() != num_rows {
                 return Err(OpError::Exec(format!(
-                    "column '{}' has {} values but expected {}",
+                    "CSV file '{}': column '{}' has {} values but expected {}",
+                    self.file_path,
                     col.name,
                     col.values.len(),
                     num_rows
@@ -676,44 +2695,37 @@ impl Operator for SourceOp {
             }
         }
 
-        // If we skipped rows but didn't read any new ones, we've reached the end
-        // This is fine - return empty batch with correct column structure
-        if row_count == 0 {
-            // If we've already read some rows in previous blocks, it's OK to return empty
-            // But we still need to return columns with the correct names so downstream operators work
-            if skip_rows > 0 {
-                // Return empty batch with correct schema (columns exist but empty)
-                return Ok(RowBatch { columns });
+        Ok(RowBatch { columns })
+    }
+
+    fn skip(&mut self, rows: usize) -> Result<usize, OpError> {
+        let mut record = ::csv::ByteRecord::new();
+        let mut skipped = 0;
+        while skipped < rows {
+            let more = self
+                .reader
+                .read_byte_record(&mut record)
+                .map_err(|e| OpError::Exec(format!("failed to read CSV record: {}", e)))?;
+            if !more {
+                break;
             }
-            // Otherwise, this is the first read and we got nothing - that's an error
-            return Err(OpError::Exec("no data in CSV file".into()));
+            skipped += 1;
         }
-
-        Ok(RowBatch { columns })
+        self.position += skipped as u64;
+        Ok(skipped)
     }
 }
 
+/// Writes its blocks through an `emsqrt-io` format writer, opened on the first
+/// block and kept open until `finish`.
 struct SinkOp {
     destination: String,
     format: String,
-    writer_initialized: std::sync::Arc<std::sync::Mutex<bool>>,
-    // Parquet writer state (when writing Parquet files)
-    #[cfg(feature = "parquet")]
-    parquet_writer:
-        std::sync::Arc<std::sync::Mutex<Option<emsqrt_io::writers::parquet::ParquetWriter>>>,
-}
-
-#[cfg(feature = "parquet")]
-impl Drop for SinkOp {
-    fn drop(&mut self) {
-        // Ensure Parquet writer is closed when SinkOp is dropped
-        if self.format == "parquet" {
-            let mut writer_guard = self.parquet_writer.lock().unwrap();
-            if let Some(writer) = writer_guard.take() {
-                let _ = writer.close(); // Ignore errors on drop
-            }
-        }
-    }
+    mode: WriteMode,
+    writer_opts: WriterOptions,
+    writer: Mutex<Option<Box<dyn BatchWriter>>>,
+    // Budget charge for the bytes the writer buffers between blocks
+    buffer_guard: Mutex<Option<emsqrt_mem::guard::BudgetGuardImpl>>,
 }
 
 impl Operator for SinkOp {
@@ -734,176 +2746,83 @@ impl Operator for SinkOp {
     fn eval_block(
         &self,
         inputs: &[RowBatch],
-        _budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+        budget: &dyn emsqrt_core::budget::MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("sink requires one input".into()))?;
 
-        // Check if input is empty (shouldn't happen, but handle gracefully)
-        if input.num_rows() == 0 {
-            // Empty batch - still write to ensure file exists, but skip if no columns
-            if input.columns.is_empty() {
-                return Ok(RowBatch { columns: vec![] });
-            }
+        // A block with no columns carries nothing to write (not even a header).
+        if input.columns.is_empty() {
+            return Ok(RowBatch { columns: vec![] });
         }
 
-        // Strip file:// prefix if present
-        let file_path = if self.destination.starts_with("file://") {
-            &self.destination[7..]
-        } else {
-            &self.destination
-        };
-
-        // Write based on format
-        // Handle Parquet format
-        #[cfg(feature = "parquet")]
-        if self.format == "parquet" {
-            use emsqrt_io::arrow_convert::emsqrt_to_arrow_schema;
-            use emsqrt_io::writers::parquet::ParquetWriter;
-            use std::sync::Arc;
-
-            let mut writer_guard = self.parquet_writer.lock().unwrap();
-
-            // Initialize writer on first write
-            if writer_guard.is_none() {
-                // Infer schema from first batch
-                if input.columns.is_empty() {
-                    return Err(OpError::Exec(
-                        "Cannot write Parquet file: empty batch with no schema".into(),
-                    ));
-                }
-
-                // Build schema from column names and types
-                let fields: Vec<emsqrt_core::schema::Field> = input
-                    .columns
-                    .iter()
-                    .map(|col| {
-                        // Infer type from first non-null value, default to Utf8
-                        let data_type = col
-                            .values
-                            .iter()
-                            .find_map(|v| match v {
-                                emsqrt_core::types::Scalar::Null => None,
-                                emsqrt_core::types::Scalar::Bool(_) => {
-                                    Some(emsqrt_core::schema::DataType::Boolean)
-                                }
-                                emsqrt_core::types::Scalar::I32(_) => {
-                                    Some(emsqrt_core::schema::DataType::Int32)
-                                }
-                                emsqrt_core::types::Scalar::I64(_) => {
-                                    Some(emsqrt_core::schema::DataType::Int64)
-                                }
-                                emsqrt_core::types::Scalar::F32(_) => {
-                                    Some(emsqrt_core::schema::DataType::Float32)
-                                }
-                                emsqrt_core::types::Scalar::F64(_) => {
-                                    Some(emsqrt_core::schema::DataType::Float64)
-                                }
-                                emsqrt_core::types::Scalar::Str(_) => {
-                                    Some(emsqrt_core::schema::DataType::Utf8)
-                                }
-                                emsqrt_core::types::Scalar::Bin(_) => {
-                                    Some(emsqrt_core::schema::DataType::Binary)
-                                }
-                            })
-                            .unwrap_or(emsqrt_core::schema::DataType::Utf8);
-
-                        emsqrt_core::schema::Field::new(&col.name, data_type, true)
-                    })
-                    .collect();
-
-                let schema = emsqrt_core::schema::Schema::new(fields);
-                let writer =
-                    ParquetWriter::from_emsqrt_schema(file_path, &schema).map_err(|e| {
-                        OpError::Exec(format!("failed to create Parquet writer: {}", e))
-                    })?;
+        let file_path = self
+            .destination
+            .strip_prefix("file://")
+            .unwrap_or(&self.destination);
+
+        let mut writer = self.writer.lock().unwrap();
+        if writer.is_none() {
+            *writer = Some(
+                open_writer(&self.format, file_path, self.mode, input, &self.writer_opts).map_err(
+                    |e| {
+                        OpError::Exec(format!(
+                            "failed to open {} sink '{}': {}",
+                            self.format, file_path, e
+                        ))
+                    },
+                )?,
+            );
+        }
+        if let Some(w) = writer.as_mut() {
+            w.write_batch(input).map_err(|e| {
+                OpError::Exec(format!(
+                    "failed to write {} batch with {} rows, {} cols: {}",
+                    self.format,
+                    input.num_rows(),
+                    input.columns.len(),
+                    e
+                ))
+            })?;
 
-                *writer_guard = Some(writer);
+            // Charge what the writer keeps buffered; when the budget cannot
+            // hold it, write it out now instead.
+            let mut guard = self.buffer_guard.lock().unwrap();
+            if guard.is_none() {
+                *guard = budget.try_acquire(0, "sink");
             }
-
-            // Write batch
-            if let Some(ref mut writer) = *writer_guard {
-                // If this is an empty batch after we've written data, close the writer
-                if input.num_rows() == 0 {
-                    // Take the writer and close it
-                    if let Some(w) = writer_guard.take() {
-                        w.close().map_err(|e| {
-                            OpError::Exec(format!("failed to close Parquet writer: {}", e))
-                        })?;
-                    }
-                } else {
-                    // Write non-empty batches
-                    writer.write_row_batch(input).map_err(|e| {
-                        OpError::Exec(format!("failed to write Parquet batch: {}", e))
-                    })?;
+            let fits = guard
+                .as_mut()
+                .is_some_and(|g| g.try_resize(w.buffered_bytes()));
+            if !fits {
+                w.flush_buffered().map_err(|e| {
+                    OpError::Exec(format!("failed to flush {} sink: {}", self.format, e))
+                })?;
+                if let Some(g) = guard.as_mut() {
+                    g.try_resize(w.buffered_bytes());
                 }
             }
-
-            return Ok(input.clone());
         }
 
-        // Write based on format
-        // For CSV, we need to append to the file if it already exists (for multiple blocks)
-        match self.format.as_str() {
-            "csv" => {
-                use std::fs::OpenOptions;
-
-                let mut initialized = self.writer_initialized.lock().unwrap();
-
-                // Determine if this is the first write or a subsequent append
-                let is_first_write = !*initialized;
-
-                let file = if *initialized {
-                    // Append mode for subsequent blocks
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(file_path)
-                        .map_err(|e| {
-                            OpError::Exec(format!(
-                                "failed to open CSV file for append '{}': {}",
-                                file_path, e
-                            ))
-                        })?
-                } else {
-                    // Create/truncate for first block
-                    *initialized = true;
-                    std::fs::File::create(file_path).map_err(|e| {
-                        OpError::Exec(format!("failed to create CSV file '{}': {}", file_path, e))
-                    })?
-                };
-
-                // Only write header on first write
-                let mut writer = if is_first_write {
-                    CsvWriter::to_writer(file)
-                } else {
-                    CsvWriter::to_writer_skip_header(file)
-                };
-
-                // Always write the batch - CsvWriter handles headers and empty batches correctly
-                // If this is the first write, header will be written
-                // If this is a subsequent write and batch is empty, nothing happens (which is fine)
-                writer.write_batch(input).map_err(|e| {
-                    OpError::Exec(format!(
-                        "failed to write CSV batch with {} rows, {} cols: {}",
-                        input.num_rows(),
-                        input.columns.len(),
-                        e
-                    ))
-                })?;
+        // Return empty batch (sink is terminal)
+        Ok(RowBatch { columns: vec![] })
+    }
 
-                // CsvWriter already flushes in write_batch, so data should be written
-            }
-            _ => {
-                return Err(OpError::Exec(format!(
-                    "unsupported sink format: {}",
-                    self.format
-                )));
-            }
+    fn finish(&self) -> Result<(), OpError> {
+        self.buffer_guard.lock().unwrap().take();
+        match self.writer.lock().unwrap().take() {
+            Some(writer) => writer.finish().map_err(|e| {
+                OpError::Exec(format!("failed to finish {} sink: {}", self.format, e))
+            }),
+            None => Ok(()),
         }
+    }
+}
 
-        // Return empty batch (sink is terminal)
-        Ok(RowBatch { columns: vec![] })
+impl Drop for SinkOp {
+    fn drop(&mut self) {
+        // A run that stopped early still closes its file (errors are moot here).
+        let _ = self.finish();
     }
 }
@@ -1,9 +1,130 @@
-//! DAG scheduler primitives (starter).
+//! DAG scheduler primitives.
 //!
-//! The initial engine runs sequentially to keep things simple. This module
-//! sketches bounded-channel types for future parallelization.
+//! [`BlockScheduler`] decides which TE block runs next. A block is ready once
+//! its dependencies have run and so has the previous block of its operator
+//! (operators such as sequential readers and constraint verifiers expect
+//! their own blocks in TE order); among ready blocks the engine's
+//! [`SchedulePolicy`] picks one, and each choice is recorded in the run
+//! manifest as a [`ScheduleDecision`].
+//!
+//! `BoundedQueue` sketches bounded channels for future parallelization.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use emsqrt_core::config::SchedulePolicy;
+use emsqrt_core::manifest::ScheduleDecision;
+use emsqrt_te::tree_eval::TeBlock;
 
-use std::collections::VecDeque;
+/// Orders the blocks of a TE plan under a [`SchedulePolicy`].
+pub struct BlockScheduler {
+    policy: SchedulePolicy,
+    /// (block id, op id) by TE position.
+    blocks: Vec<(u64, u64)>,
+    /// Prerequisites of each block that have not run yet.
+    waiting: Vec<usize>,
+    dependents: Vec<Vec<usize>>,
+    /// Blocks on the longest chain from each block to the end of the plan,
+    /// the block itself included.
+    height: Vec<u64>,
+    ready: BTreeSet<usize>,
+    remaining: usize,
+}
+
+impl BlockScheduler {
+    pub fn new(order: &[TeBlock], policy: SchedulePolicy) -> Self {
+        let position: HashMap<u64, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.id.get(), i))
+            .collect();
+        let mut dependents = vec![Vec::new(); order.len()];
+        let mut waiting = vec![0; order.len()];
+        let mut last_of_op: HashMap<u64, usize> = HashMap::new();
+        for (i, block) in order.iter().enumerate() {
+            // Unknown deps are left to the engine to report when it runs the block.
+            let mut prereqs: Vec<usize> = block
+                .deps
+                .iter()
+                .filter_map(|d| position.get(&d.get()).copied())
+                .collect();
+            prereqs.extend(last_of_op.insert(block.op.get(), i));
+            prereqs.sort_unstable();
+            prereqs.dedup();
+            waiting[i] = prereqs.len();
+            for p in prereqs {
+                dependents[p].push(i);
+            }
+        }
+
+        let mut height = vec![1u64; order.len()];
+        for i in (0..order.len()).rev() {
+            height[i] = 1 + dependents[i]
+                .iter()
+                .filter(|&&d| d > i)
+                .map(|&d| height[d])
+                .max()
+                .unwrap_or(0);
+        }
+
+        Self {
+            policy,
+            blocks: order.iter().map(|b| (b.id.get(), b.op.get())).collect(),
+            ready: (0..order.len()).filter(|&i| waiting[i] == 0).collect(),
+            waiting,
+            dependents,
+            height,
+            remaining: order.len(),
+        }
+    }
+
+    /// Take the next block to run (its TE position and the decision), or
+    /// `None` if no block is ready. `freed_bytes(i)` is how many bytes of held
+    /// block results running block `i` would release; only the memory-aware
+    /// policy calls it.
+    pub fn next(
+        &mut self,
+        freed_bytes: impl Fn(usize) -> u64,
+    ) -> Option<(usize, ScheduleDecision)> {
+        let score = |i: usize| match self.policy {
+            SchedulePolicy::Fifo => 0,
+            SchedulePolicy::CriticalPath => self.height[i],
+            SchedulePolicy::MemoryAware => freed_bytes(i),
+        };
+        // Ties go to the earliest block in TE order.
+        let mut best: Option<(usize, u64)> = None;
+        for &i in &self.ready {
+            let s = score(i);
+            if best.is_none_or(|(_, top)| s > top) {
+                best = Some((i, s));
+            }
+        }
+        let (index, priority) = best?;
+        let decision = ScheduleDecision {
+            block_id: self.blocks[index].0,
+            op_id: self.blocks[index].1,
+            ready: self.ready.len(),
+            priority,
+        };
+        self.ready.remove(&index);
+        Some((index, decision))
+    }
+
+    /// Mark the block at TE position `index` as run.
+    pub fn complete(&mut self, index: usize) {
+        self.remaining -= 1;
+        for &d in &self.dependents[index] {
+            self.waiting[d] -= 1;
+            if self.waiting[d] == 0 {
+                self.ready.insert(d);
+            }
+        }
+    }
+
+    /// Blocks not yet completed.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
 
 /// A tiny bounded queue used as a placeholder for future mpsc channels.
 /// Replace with `tokio::sync::mpsc` or crossbeam once we go async.
@@ -0,0 +1,55 @@
+//! The files behind glob and directory sources, listed for work estimation.
+//!
+//! A multi-file source has no single size to look up, so its files are
+//! listed (in the order the source reads them) with their sizes, and for
+//! Parquet their row counts, and handed to `estimate_work` in the
+//! `WorkHint`. TE blocks are then sized for the files' total.
+
+use emsqrt_core::dag::LogicalPlan;
+use emsqrt_io::files::{is_multi_file, list_source_files};
+use emsqrt_planner::{scan_sources, SourceFile, WorkHint};
+
+use crate::runtime::source_path;
+
+/// `hint` plus the files of each local glob or directory source of `plan`.
+/// Sources whose files cannot be listed are left to the other estimates.
+pub fn with_source_files(plan: &LogicalPlan, hint: Option<WorkHint>) -> Option<WorkHint> {
+    let mut listed = Vec::new();
+    for source in scan_sources(plan) {
+        let path = source_path(source);
+        if path.contains("://") || !is_multi_file(path) {
+            continue;
+        }
+        let Ok(files) = list_source_files(path) else {
+            continue;
+        };
+        let files = files
+            .into_iter()
+            .map(|path| SourceFile {
+                bytes: std::fs::metadata(&path).map_or(0, |m| m.len()),
+                rows: footer_rows(&path),
+                path,
+            })
+            .collect();
+        listed.push((source.to_string(), files));
+    }
+    if listed.is_empty() {
+        return hint;
+    }
+    let mut hint = hint.unwrap_or_default();
+    hint.source_files.extend(listed);
+    Some(hint)
+}
+
+/// Rows of a file whose footer records them.
+#[cfg(feature = "parquet")]
+fn footer_rows(path: &str) -> Option<u64> {
+    (crate::runtime::detect_file_format(path, None) == "parquet")
+        .then(|| emsqrt_io::readers::parquet::row_count(path).ok())
+        .flatten()
+}
+
+#[cfg(not(feature = "parquet"))]
+fn footer_rows(_path: &str) -> Option<u64> {
+    None
+}
@@ -0,0 +1,182 @@
+//! Spill capacity check before a run (`EngineConfig::spill_space_check`).
+//!
+//! In the worst case every spilling operator (Grace hash joins, nested-loop
+//! joins, partitioned aggregates, external sorts) whose input outgrows the memory cap writes
+//! all of that input to the spill directory. Input sizes come from the local
+//! source files under each operator, passed on unchanged by the operators in
+//! between. When the total exceeds the space free on the spill directory's
+//! volume, the run fails before reading anything rather than with ENOSPC
+//! hours in.
+
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use emsqrt_core::dag::PhysicalPlan;
+use emsqrt_core::id::OpId;
+use emsqrt_io::files::list_source_files;
+use emsqrt_planner::physical::PhysicalProgram;
+
+/// Operator keys that spill their input when it outgrows memory.
+const SPILLING_OPS: [&str; 4] = [
+    "join_hash",
+    "join_nested_loop",
+    "aggregate",
+    "sort_external",
+];
+
+/// Worst-case spill of one operator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpillingOp {
+    pub op: OpId,
+    pub key: String,
+    /// Estimated bytes of its input.
+    pub bytes: u64,
+}
+
+/// Worst-case spill volume of a program.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpillEstimate {
+    /// Operators expected to spill, in plan order.
+    pub ops: Vec<SpillingOp>,
+}
+
+impl SpillEstimate {
+    pub fn total_bytes(&self) -> u64 {
+        self.ops.iter().map(|o| o.bytes).sum()
+    }
+
+    /// Fail with an actionable message when the estimate exceeds `available`
+    /// bytes free on the volume of `spill_dir`.
+    pub fn check(&self, spill_dir: &str, available: u64) -> Result<(), String> {
+        let needed = self.total_bytes();
+        if needed <= available {
+            return Ok(());
+        }
+        let mut msg = format!(
+            "spill dir '{}' has {} free, but this plan may spill up to {} (",
+            spill_dir,
+            human_bytes(available),
+            human_bytes(needed)
+        );
+        for (i, o) in self.ops.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            let _ = write!(
+                msg,
+                "{}{} op {}: {}",
+                sep,
+                o.key,
+                o.op.get(),
+                human_bytes(o.bytes)
+            );
+        }
+        msg.push_str(
+            "). Free up space, point spill_dir (--spill-dir) at a larger volume or \
+             spill_uri at object storage, raise the memory cap, or set \
+             spill_space_check: false to run anyway",
+        );
+        Err(msg)
+    }
+}
+
+/// Worst-case spill volume of running `program` under `mem_cap_bytes`.
+pub fn estimate_spill(program: &PhysicalProgram, mem_cap_bytes: usize) -> SpillEstimate {
+    let mut estimate = SpillEstimate::default();
+    input_bytes(program, &program.plan, mem_cap_bytes as u64, &mut estimate);
+    estimate
+}
+
+/// Estimated output bytes of `node`, recording spilling operators on the way.
+fn input_bytes(
+    program: &PhysicalProgram,
+    node: &PhysicalPlan,
+    mem_cap: u64,
+    out: &mut SpillEstimate,
+) -> u64 {
+    let (op, bytes) = match node {
+        PhysicalPlan::Source { op, .. } => return source_bytes(program, *op),
+        PhysicalPlan::Unary { op, input, .. } | PhysicalPlan::Sink { op, input } => {
+            (op, input_bytes(program, input, mem_cap, out))
+        }
+        PhysicalPlan::Binary {
+            op, left, right, ..
+        } => (
+            op,
+            input_bytes(program, left, mem_cap, out) + input_bytes(program, right, mem_cap, out),
+        ),
+        PhysicalPlan::Nary { op, inputs, .. } => (
+            op,
+            inputs
+                .iter()
+                .map(|i| input_bytes(program, i, mem_cap, out))
+                .sum(),
+        ),
+    };
+    if let Some(binding) = program.bindings.get(op) {
+        // A shared subplan is visited once per reader but runs once.
+        let counted = out.ops.iter().any(|o| o.op == *op);
+        if bytes > mem_cap && SPILLING_OPS.contains(&binding.key.as_str()) && !counted {
+            out.ops.push(SpillingOp {
+                op: *op,
+                key: binding.key.clone(),
+                bytes,
+            });
+        }
+    }
+    bytes
+}
+
+/// Size of a local source (the total of a glob or directory source's files);
+/// 0 for remote, in-memory or missing sources.
+fn source_bytes(program: &PhysicalProgram, op: OpId) -> u64 {
+    let Some(source) = program
+        .bindings
+        .get(&op)
+        .and_then(|b| b.config.get("source"))
+        .and_then(|v| v.as_str())
+    else {
+        return 0;
+    };
+    let path = source.strip_prefix("file://").unwrap_or(source);
+    if path.contains("://") {
+        return 0;
+    }
+    list_source_files(path).map_or(0, |files| {
+        files
+            .iter()
+            .map(|f| std::fs::metadata(f).map_or(0, |m| m.len()))
+            .sum()
+    })
+}
+
+/// Bytes free to this user on the volume holding `dir`, or its nearest
+/// existing ancestor when `dir` has not been created yet.
+pub fn available_space(dir: &Path) -> io::Result<u64> {
+    let mut path = dir;
+    while !path.exists() {
+        path = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+    }
+    fs2::available_space(path)
+}
+
+/// `1.5 GiB`, `12.0 MiB`, `512 bytes`.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = "bytes";
+    for u in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = u;
+    }
+    if unit == "bytes" {
+        format!("{} bytes", bytes)
+    } else {
+        format!("{:.1} {}", value, unit)
+    }
+}
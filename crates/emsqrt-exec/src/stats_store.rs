@@ -0,0 +1,185 @@
+//! Warm-start statistics store: source column stats kept across runs.
+//!
+//! After a run with `EngineConfig::stats_dir` set, the stats collected for
+//! each local file source are saved under that directory, keyed by the
+//! source URI and a fingerprint of the file's contents. Later compiles call
+//! `warm_start` to attach them to scans that declare no stats, so estimates
+//! improve without an explicit ANALYZE. A file whose contents changed gets a
+//! new fingerprint and is planned without stats until it is read again.
+//!
+//! Each entry is one small JSON file, so concurrent runs never rewrite the
+//! same file for different contents.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use emsqrt_core::dag::LogicalPlan;
+use emsqrt_core::stats::{SchemaStats, SourceStats};
+use emsqrt_io::files::{is_multi_file, list_source_files};
+use emsqrt_planner::{attach_source_stats, scan_sources};
+use serde::{Deserialize, Serialize};
+
+/// Bytes hashed from each end of a file by `fingerprint`.
+pub const FINGERPRINT_CHUNK: u64 = 1 << 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    source: String,
+    fingerprint: String,
+    stats: SchemaStats,
+}
+
+/// Directory of saved source stats.
+#[derive(Debug, Clone)]
+pub struct StatsStore {
+    dir: PathBuf,
+}
+
+impl StatsStore {
+    /// Store in `dir`; it is created on the first `record`.
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `$XDG_CONFIG_HOME/emsqrt/stats`, else `~/.config/emsqrt/stats`.
+    pub fn default_dir() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("emsqrt").join("stats"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Content fingerprint of a local file source: its size and the first
+    /// and last `FINGERPRINT_CHUNK` bytes. `None` for remote, `mem://` or
+    /// unreadable sources, which the store skips. A glob or directory source
+    /// hashes each file's path and fingerprint, so adding, removing or
+    /// changing a file changes it.
+    pub fn fingerprint(source: &str) -> Option<String> {
+        let path = local_path(source)?;
+        let path_str = path.to_str()?;
+        if !is_multi_file(path_str) {
+            return file_fingerprint(path);
+        }
+        let mut hasher = blake3::Hasher::new();
+        for file in list_source_files(path_str).ok()? {
+            hasher.update(file.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(file_fingerprint(Path::new(&file))?.as_bytes());
+        }
+        Some(hasher.finalize().to_hex().to_string())
+    }
+
+    /// Stats saved for `source` as it is now; `None` if never recorded or if
+    /// the file changed since.
+    pub fn get(&self, source: &str) -> Option<SchemaStats> {
+        let fingerprint = Self::fingerprint(source)?;
+        let bytes = fs::read(self.entry_path(source, &fingerprint)).ok()?;
+        let entry: Entry = serde_json::from_slice(&bytes).ok()?;
+        (entry.source == source && entry.fingerprint == fingerprint).then_some(entry.stats)
+    }
+
+    /// Save the stats of every local file source in `stats`. Returns the
+    /// number saved.
+    pub fn record(&self, stats: &SourceStats) -> io::Result<usize> {
+        let mut saved = 0;
+        for (source, stats) in stats {
+            let Some(fingerprint) = Self::fingerprint(source) else {
+                continue;
+            };
+            fs::create_dir_all(&self.dir)?;
+            let path = self.entry_path(source, &fingerprint);
+            let entry = Entry {
+                source: source.clone(),
+                fingerprint,
+                stats: stats.clone(),
+            };
+            // Write then rename, so readers never see a partial entry.
+            let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+            fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+            fs::rename(&tmp, &path)?;
+            saved += 1;
+        }
+        Ok(saved)
+    }
+
+    /// Attach saved stats to the scans of `plan` that declare none (see
+    /// `attach_source_stats`). Returns the scans updated.
+    pub fn warm_start(&self, plan: &mut LogicalPlan) -> usize {
+        let saved: SourceStats = scan_sources(plan)
+            .into_iter()
+            .filter_map(|source| Some((source.to_string(), self.get(source)?)))
+            .collect();
+        if saved.is_empty() {
+            return 0;
+        }
+        attach_source_stats(plan, &saved)
+    }
+
+    fn entry_path(&self, source: &str, fingerprint: &str) -> PathBuf {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(source.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(fingerprint.as_bytes());
+        let key = hasher.finalize().to_hex();
+        self.dir.join(format!("{}.json", &key[..32]))
+    }
+}
+
+/// Read a stats file written by `update_stats_file`; a missing file is
+/// empty.
+pub fn read_stats_file(path: &Path) -> Result<SourceStats, String> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map_err(|e| format!("invalid stats file '{}': {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(SourceStats::new()),
+        Err(e) => Err(format!(
+            "cannot read stats file '{}': {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+/// Replace the entries of `stats`' sources in the stats file at `path`,
+/// keeping the others.
+pub fn update_stats_file(path: &Path, stats: &SourceStats) -> io::Result<()> {
+    let mut all = read_stats_file(path).map_err(io::Error::other)?;
+    all.extend(stats.clone());
+    let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+    fs::write(&tmp, serde_json::to_string_pretty(&all)?)?;
+    fs::rename(&tmp, path)
+}
+
+/// [`StatsStore::fingerprint`] of one file.
+fn file_fingerprint(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&len.to_le_bytes());
+    let mut buf = Vec::new();
+    (&mut file)
+        .take(FINGERPRINT_CHUNK)
+        .read_to_end(&mut buf)
+        .ok()?;
+    hasher.update(&buf);
+    if len > FINGERPRINT_CHUNK {
+        let tail = (len - FINGERPRINT_CHUNK).max(FINGERPRINT_CHUNK);
+        file.seek(SeekFrom::Start(tail)).ok()?;
+        buf.clear();
+        file.read_to_end(&mut buf).ok()?;
+        hasher.update(&buf);
+    }
+    Some(hasher.finalize().to_hex().to_string())
+}
+
+/// Filesystem path of a local source URI (`file://` or a plain path).
+fn local_path(source: &str) -> Option<&Path> {
+    let path = source.strip_prefix("file://").unwrap_or(source);
+    (!path.contains("://")).then(|| Path::new(path))
+}
@@ -0,0 +1,127 @@
+//! Plan snapshots for planner and TE tests (`--features testkit`).
+//!
+//! [`snapshot_pipeline`] renders what a pipeline YAML plans to: the
+//! optimized logical plan, the operator bindings and the TE block order.
+//! [`assert_snapshot`] compares that text with a file checked in next to the
+//! tests, so a change to the optimizer, lowering or TE planning shows up as a
+//! reviewed snapshot diff instead of a silent change in behaviour.
+//!
+//! A missing snapshot file is written and the assertion passes. To accept
+//! changed output, rerun the tests with `EMSQRT_UPDATE_SNAPSHOTS=1` and
+//! review the rewritten files.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline, rules};
+
+/// Set to `1` to rewrite snapshots that differ instead of failing.
+pub const UPDATE_ENV: &str = "EMSQRT_UPDATE_SNAPSHOTS";
+
+/// The optimized logical plan, bindings and TE order of `yaml`, planned
+/// under `mem_cap_bytes`, as the text [`assert_snapshot`] compares.
+pub fn snapshot_pipeline(yaml: &str, mem_cap_bytes: usize) -> Result<String, String> {
+    let parsed = parse_yaml_pipeline(yaml).map_err(|e| e.to_string())?;
+    let optimized = rules::optimize(parsed.plan);
+    let mut program = lower_to_physical(&optimized);
+    parsed.hints.apply(&mut program);
+    let work = estimate_work(&optimized, parsed.hints.work_hint().as_ref());
+    let te = parsed
+        .hints
+        .plan_te(&program, &work, mem_cap_bytes)
+        .map_err(|e| format!("TE planning failed: {}", e))?;
+
+    let mut out = String::from("# logical plan\n");
+    out.push_str(&to_json(&optimized)?);
+    out.push_str("# bindings\n");
+    out.push_str(&to_json(&program.bindings)?);
+    let _ = writeln!(
+        out,
+        "# te order ({} rows per block)",
+        te.block_size.rows_per_block
+    );
+    for b in &te.order {
+        let _ = write!(out, "b{} op{}", b.id.get(), b.op.get());
+        if let Some((start, end)) = b.range_rows {
+            let _ = write!(out, " rows {}..{}", start, end);
+        }
+        if let Some(p) = &b.partition {
+            let _ = write!(out, " partition {}/{}", p.index, p.count);
+        }
+        if let Some(x) = &b.exchange {
+            let _ = write!(out, " exchange {} by {}", x.count, x.keys.join(","));
+        }
+        let deps: Vec<String> = b.deps.iter().map(|d| format!("b{}", d.get())).collect();
+        if !deps.is_empty() {
+            let _ = write!(out, " <- {}", deps.join(", "));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Compare `actual` with the snapshot file at `path`.
+///
+/// Writes the file when it does not exist yet, or when it differs and
+/// [`UPDATE_ENV`] is `1`; otherwise a difference panics with the changed
+/// lines.
+pub fn assert_snapshot(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    let update = std::env::var(UPDATE_ENV).is_ok_and(|v| v == "1");
+    match fs::read_to_string(path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) if !update => panic!(
+            "snapshot {} changed (rerun with {}=1 to accept):\n{}",
+            path.display(),
+            UPDATE_ENV,
+            line_diff(&expected, actual)
+        ),
+        _ => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).expect("create snapshot dir");
+            }
+            fs::write(path, actual).expect("write snapshot");
+        }
+    }
+}
+
+/// Lines of `expected` missing from `actual` (`-`) and added (`+`), by
+/// longest common subsequence, with unchanged lines left out.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let (a, b): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+    // lcs[i][j]: common lines of a[i..] and b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            let _ = writeln!(out, "{:>4} - {}", i + 1, a[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(out, "{:>4} + {}", j + 1, b[j]);
+            j += 1;
+        }
+    }
+    out
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .map_err(|e| e.to_string())
+}
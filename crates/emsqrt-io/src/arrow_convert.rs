@@ -1,22 +1,26 @@
-//! Arrow conversion utilities for Parquet I/O boundaries.
+//! Arrow conversion utilities for Parquet and Arrow I/O boundaries.
 //!
 //! Converts between Arrow RecordBatch and emsqrt-core RowBatch.
-//! This is feature-gated and only compiled when `--features parquet` is enabled.
+//! This is feature-gated and only compiled when `--features arrow` (implied by
+//! `parquet`) is enabled.
 
-#[cfg(feature = "parquet")]
+#[cfg(feature = "arrow")]
 use arrow_array::{
-    Array, ArrayRef, BinaryArray, BooleanArray, Float32Array, Float64Array, Int32Array, Int64Array,
-    RecordBatch, StringArray,
+    Array, ArrayRef, BinaryArray, BooleanArray, Date32Array, Date64Array, Decimal128Array,
+    Float32Array, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray,
+    TimestampMicrosecondArray, TimestampMillisecondArray, TimestampNanosecondArray,
+    TimestampSecondArray,
 };
-#[cfg(feature = "parquet")]
+#[cfg(feature = "arrow")]
 use arrow_schema::{
-    DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef,
+    DataType as ArrowDataType, Field as ArrowField, Schema as ArrowSchema, SchemaRef, TimeUnit,
 };
-#[cfg(feature = "parquet")]
+#[cfg(feature = "arrow")]
 use std::sync::Arc;
 
 use emsqrt_core::schema::DataType;
 use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_core::{decimal, temporal};
 
 use crate::error::{Error, Result};
 
@@ -160,6 +164,63 @@ fn arrow_value_to_scalar(array: &ArrayRef, row_idx: usize) -> Result<Scalar> {
                 .ok_or_else(|| Error::Other("Failed to cast to BinaryArray".to_string()))?;
             Ok(Scalar::Bin(arr.value(row_idx).to_vec()))
         }
+        ArrowDataType::Date32 => {
+            let arr = array
+                .as_any()
+                .downcast_ref::<Date32Array>()
+                .ok_or_else(|| Error::Other("Failed to cast to Date32Array".to_string()))?;
+            Ok(Scalar::Date64(
+                arr.value(row_idx) as i64 * temporal::MS_PER_DAY,
+            ))
+        }
+        ArrowDataType::Date64 => {
+            let arr = array
+                .as_any()
+                .downcast_ref::<Date64Array>()
+                .ok_or_else(|| Error::Other("Failed to cast to Date64Array".to_string()))?;
+            Ok(Scalar::Date64(arr.value(row_idx)))
+        }
+        ArrowDataType::Timestamp(unit, _) => {
+            let micros = match unit {
+                TimeUnit::Second => array
+                    .as_any()
+                    .downcast_ref::<TimestampSecondArray>()
+                    .map(|arr| arr.value(row_idx).saturating_mul(1_000_000)),
+                TimeUnit::Millisecond => array
+                    .as_any()
+                    .downcast_ref::<TimestampMillisecondArray>()
+                    .map(|arr| arr.value(row_idx).saturating_mul(1_000)),
+                TimeUnit::Microsecond => array
+                    .as_any()
+                    .downcast_ref::<TimestampMicrosecondArray>()
+                    .map(|arr| arr.value(row_idx)),
+                TimeUnit::Nanosecond => array
+                    .as_any()
+                    .downcast_ref::<TimestampNanosecondArray>()
+                    .map(|arr| arr.value(row_idx).div_euclid(1_000)),
+            };
+            micros
+                .map(Scalar::Timestamp)
+                .ok_or_else(|| Error::Other("Failed to cast to TimestampArray".to_string()))
+        }
+        ArrowDataType::Decimal128(_, scale) => {
+            let arr = array
+                .as_any()
+                .downcast_ref::<Decimal128Array>()
+                .ok_or_else(|| Error::Other("Failed to cast to Decimal128Array".to_string()))?;
+            let value = arr.value(row_idx);
+            // A negative scale multiplies: rescale to scale 0.
+            match u8::try_from(*scale) {
+                Ok(scale) => Ok(Scalar::Decimal128(value, scale)),
+                Err(_) => 10i128
+                    .checked_pow(scale.unsigned_abs() as u32)
+                    .and_then(|m| value.checked_mul(m))
+                    .map(|v| Scalar::Decimal128(v, 0))
+                    .ok_or_else(|| {
+                        Error::Other(format!("decimal out of range at row {}", row_idx))
+                    }),
+            }
+        }
         _ => Err(Error::Other(format!(
             "Unsupported Arrow data type: {:?}",
             array.data_type()
@@ -356,6 +417,96 @@ fn scalar_column_to_arrow_array(
             }
             Ok(Arc::new(builder.finish()))
         }
+        ArrowDataType::Date64 => {
+            let mut builder = arrow_array::builder::Date64Builder::with_capacity(values.len());
+            for val in values {
+                match val {
+                    Scalar::Null => {
+                        if nullable {
+                            builder.append_null();
+                        } else {
+                            return Err(Error::Schema(
+                                "Null value in non-nullable Date64 column".to_string(),
+                            ));
+                        }
+                    }
+                    Scalar::Date64(ms) => builder.append_value(*ms),
+                    _ => {
+                        return Err(Error::Schema(format!(
+                            "Type mismatch: expected Date64, got {:?}",
+                            val
+                        )))
+                    }
+                }
+            }
+            Ok(Arc::new(builder.finish()))
+        }
+        ArrowDataType::Timestamp(TimeUnit::Microsecond, tz) => {
+            let mut builder =
+                arrow_array::builder::TimestampMicrosecondBuilder::with_capacity(values.len());
+            for val in values {
+                match val {
+                    Scalar::Null => {
+                        if nullable {
+                            builder.append_null();
+                        } else {
+                            return Err(Error::Schema(
+                                "Null value in non-nullable Timestamp column".to_string(),
+                            ));
+                        }
+                    }
+                    Scalar::Timestamp(us) => builder.append_value(*us),
+                    Scalar::Date64(ms) => builder.append_value(temporal::date_to_timestamp(*ms)),
+                    _ => {
+                        return Err(Error::Schema(format!(
+                            "Type mismatch: expected Timestamp, got {:?}",
+                            val
+                        )))
+                    }
+                }
+            }
+            Ok(Arc::new(builder.finish().with_timezone_opt(tz.clone())))
+        }
+        ArrowDataType::Decimal128(precision, scale) => {
+            let mut builder = arrow_array::builder::Decimal128Builder::with_capacity(values.len());
+            let to = u8::try_from(*scale)
+                .map_err(|_| Error::Schema(format!("negative decimal scale {}", scale)))?;
+            for val in values {
+                let value = match val {
+                    Scalar::Null => {
+                        if nullable {
+                            builder.append_null();
+                            continue;
+                        } else {
+                            return Err(Error::Schema(
+                                "Null value in non-nullable Decimal128 column".to_string(),
+                            ));
+                        }
+                    }
+                    Scalar::Decimal128(v, from) => decimal::rescale(*v, *from, to),
+                    Scalar::I32(i) => decimal::rescale(*i as i128, 0, to),
+                    Scalar::I64(i) => decimal::rescale(*i as i128, 0, to),
+                    _ => {
+                        return Err(Error::Schema(format!(
+                            "Type mismatch: expected Decimal128, got {:?}",
+                            val
+                        )))
+                    }
+                };
+                let value = value.ok_or_else(|| {
+                    Error::Schema(format!(
+                        "{} does not fit Decimal128({}, {})",
+                        val, precision, scale
+                    ))
+                })?;
+                builder.append_value(value);
+            }
+            let array = builder
+                .finish()
+                .with_precision_and_scale(*precision, *scale)
+                .map_err(|e| Error::Schema(e.to_string()))?;
+            Ok(Arc::new(array))
+        }
         _ => Err(Error::Other(format!(
             "Unsupported Arrow data type for conversion: {:?}",
             data_type
@@ -374,7 +525,9 @@ pub fn emsqrt_to_arrow_data_type(dtype: &DataType) -> ArrowDataType {
         DataType::Utf8 => ArrowDataType::Utf8,
         DataType::Binary => ArrowDataType::Binary,
         DataType::Date64 => ArrowDataType::Date64,
-        DataType::Decimal128 => ArrowDataType::Decimal128(10, 2), // Default precision/scale
+        DataType::Timestamp => ArrowDataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        // Values are rescaled to this scale on write.
+        DataType::Decimal128 => ArrowDataType::Decimal128(38, 10),
     }
 }
 
@@ -394,5 +547,5 @@ pub fn emsqrt_to_arrow_schema(schema: &emsqrt_core::schema::Schema) -> ArrowSche
     ArrowSchema::new(fields)
 }
 
-#[cfg(not(feature = "parquet"))]
-compile_error!("arrow_convert.rs was compiled without the `parquet` feature; enable `--features parquet` or exclude this module.");
+#[cfg(not(feature = "arrow"))]
+compile_error!("arrow_convert.rs was compiled without the `arrow` feature; enable `--features arrow` or exclude this module.");
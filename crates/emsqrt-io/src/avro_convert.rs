@@ -0,0 +1,410 @@
+//! Avro binary encoding and schema mapping for the Avro reader and writer
+//! (enabled with `--features avro`).
+//!
+//! Covers the subset a flat `RowBatch` can hold: a top-level record whose
+//! fields are primitives, enums, fixed, or a union of `null` and one of those.
+//! Logical types read as their underlying primitive.
+
+use std::io::{Read, Write};
+
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::Scalar;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+/// First four bytes of an Avro object container file.
+pub const MAGIC: &[u8; 4] = b"Obj\x01";
+
+/// A field type the reader can decode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AvroType {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    Enum(Vec<String>),
+    Fixed(usize),
+    Union(Vec<AvroType>),
+}
+
+/// One field of the top-level record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroField {
+    pub name: String,
+    pub ty: AvroType,
+}
+
+impl AvroType {
+    fn parse(v: &Value) -> Result<Self> {
+        match v {
+            Value::String(name) => Self::primitive(name),
+            Value::Array(branches) => {
+                let branches = branches
+                    .iter()
+                    .map(Self::parse)
+                    .collect::<Result<Vec<_>>>()?;
+                if branches.iter().any(|b| matches!(b, AvroType::Union(_))) {
+                    return Err(Error::Schema("avro unions cannot nest".into()));
+                }
+                Ok(AvroType::Union(branches))
+            }
+            Value::Object(obj) => match obj.get("type").and_then(Value::as_str) {
+                Some("enum") => Ok(AvroType::Enum(
+                    obj.get("symbols")
+                        .and_then(Value::as_array)
+                        .map(|s| {
+                            s.iter()
+                                .filter_map(|s| s.as_str().map(str::to_string))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                )),
+                Some("fixed") => obj
+                    .get("size")
+                    .and_then(Value::as_u64)
+                    .map(|n| AvroType::Fixed(n as usize))
+                    .ok_or_else(|| Error::Schema("avro fixed type needs a 'size'".into())),
+                Some("record") | Some("array") | Some("map") => Err(Error::Schema(format!(
+                    "nested avro type '{}' is not supported",
+                    obj["type"].as_str().unwrap_or_default()
+                ))),
+                // `{"type": "long", "logicalType": ...}` and friends
+                Some(_) => Self::parse(&obj["type"]),
+                None => Err(Error::Schema(format!("invalid avro type: {}", v))),
+            },
+            other => Err(Error::Schema(format!("invalid avro type: {}", other))),
+        }
+    }
+
+    fn primitive(name: &str) -> Result<Self> {
+        Ok(match name {
+            "null" => AvroType::Null,
+            "boolean" => AvroType::Boolean,
+            "int" => AvroType::Int,
+            "long" => AvroType::Long,
+            "float" => AvroType::Float,
+            "double" => AvroType::Double,
+            "bytes" => AvroType::Bytes,
+            "string" => AvroType::String,
+            other => return Err(Error::Schema(format!("unsupported avro type '{}'", other))),
+        })
+    }
+
+    /// Engine type and nullability; unions may hold `null` and one other type.
+    fn to_data_type(&self) -> Result<(DataType, bool)> {
+        Ok(match self {
+            AvroType::Null => (DataType::Utf8, true),
+            AvroType::Boolean => (DataType::Boolean, false),
+            AvroType::Int => (DataType::Int32, false),
+            AvroType::Long => (DataType::Int64, false),
+            AvroType::Float => (DataType::Float32, false),
+            AvroType::Double => (DataType::Float64, false),
+            AvroType::Bytes | AvroType::Fixed(_) => (DataType::Binary, false),
+            AvroType::String | AvroType::Enum(_) => (DataType::Utf8, false),
+            AvroType::Union(branches) => {
+                let mut values = branches.iter().filter(|b| **b != AvroType::Null);
+                match (values.next(), values.next()) {
+                    (Some(ty), None) => (ty.to_data_type()?.0, branches.len() > 1),
+                    (None, _) => (DataType::Utf8, true),
+                    (Some(_), Some(_)) => {
+                        return Err(Error::Schema(
+                            "avro unions of more than one non-null type are not supported".into(),
+                        ))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Decode one value of this type.
+    pub fn decode(&self, r: &mut impl Read) -> Result<Scalar> {
+        Ok(match self {
+            AvroType::Null => Scalar::Null,
+            AvroType::Boolean => Scalar::Bool(read_u8(r)? != 0),
+            AvroType::Int => Scalar::I32(read_long(r)? as i32),
+            AvroType::Long => Scalar::I64(read_long(r)?),
+            AvroType::Float => {
+                let mut b = [0u8; 4];
+                r.read_exact(&mut b)?;
+                Scalar::F32(f32::from_le_bytes(b))
+            }
+            AvroType::Double => {
+                let mut b = [0u8; 8];
+                r.read_exact(&mut b)?;
+                Scalar::F64(f64::from_le_bytes(b))
+            }
+            AvroType::Bytes => Scalar::Bin(read_bytes(r)?),
+            AvroType::String => Scalar::Str(read_string(r)?),
+            AvroType::Enum(symbols) => {
+                let i = read_long(r)?;
+                let symbol = usize::try_from(i).ok().and_then(|i| symbols.get(i));
+                Scalar::Str(
+                    symbol
+                        .ok_or_else(|| Error::Other(format!("avro enum index {} out of range", i)))?
+                        .clone(),
+                )
+            }
+            AvroType::Fixed(n) => {
+                let mut b = vec![0u8; *n];
+                r.read_exact(&mut b)?;
+                Scalar::Bin(b)
+            }
+            AvroType::Union(branches) => {
+                let i = read_long(r)?;
+                usize::try_from(i)
+                    .ok()
+                    .and_then(|i| branches.get(i))
+                    .ok_or_else(|| Error::Other(format!("avro union index {} out of range", i)))?
+                    .decode(r)?
+            }
+        })
+    }
+}
+
+/// Parse the writer schema of a container file: a record of flat fields.
+pub fn parse_schema(json: &str) -> Result<Vec<AvroField>> {
+    let v: Value = serde_json::from_str(json)?;
+    if v.get("type").and_then(Value::as_str) != Some("record") {
+        return Err(Error::Schema(
+            "avro schema must be a record at the top level".into(),
+        ));
+    }
+    v.get("fields")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::Schema("avro record schema has no 'fields'".into()))?
+        .iter()
+        .map(|f| {
+            let name = f
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Error::Schema("avro field has no 'name'".into()))?;
+            let ty = AvroType::parse(f.get("type").unwrap_or(&Value::Null))
+                .map_err(|e| Error::Schema(format!("avro field '{}': {}", name, e)))?;
+            Ok(AvroField {
+                name: name.to_string(),
+                ty,
+            })
+        })
+        .collect()
+}
+
+/// Engine schema for the fields of an Avro record.
+pub fn avro_to_emsqrt_schema(fields: &[AvroField]) -> Result<Schema> {
+    let fields = fields
+        .iter()
+        .map(|f| {
+            let (data_type, nullable) =
+                f.ty.to_data_type()
+                    .map_err(|e| Error::Schema(format!("avro field '{}': {}", f.name, e)))?;
+            Ok(Field::new(&f.name, data_type, nullable))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Schema::new(fields))
+}
+
+/// Avro name for a column: characters outside `[A-Za-z0-9_]` become `_`, and
+/// a leading digit gets a `_` prefix (`count_*` is written as `count__`).
+pub fn avro_name(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// Writer-side type of an engine type. Date64, Timestamp and Decimal128
+/// values are written as their text (`2024-03-01`, `123.45`).
+pub fn emsqrt_to_avro_type(data_type: &DataType) -> AvroType {
+    match data_type {
+        DataType::Boolean => AvroType::Boolean,
+        DataType::Int32 => AvroType::Int,
+        DataType::Int64 => AvroType::Long,
+        DataType::Float32 => AvroType::Float,
+        DataType::Float64 => AvroType::Double,
+        DataType::Binary => AvroType::Bytes,
+        DataType::Utf8 | DataType::Date64 | DataType::Timestamp | DataType::Decimal128 => {
+            AvroType::String
+        }
+    }
+}
+
+/// Record schema JSON for `schema`; nullable fields are `["null", T]` unions.
+pub fn emsqrt_to_avro_schema(schema: &Schema) -> Result<String> {
+    let mut names = std::collections::BTreeSet::new();
+    let fields = schema
+        .fields
+        .iter()
+        .map(|f| {
+            let name = avro_name(&f.name);
+            if !names.insert(name.clone()) {
+                return Err(Error::Schema(format!(
+                    "column '{}' maps to avro name '{}', which is already taken",
+                    f.name, name
+                )));
+            }
+            let ty = match emsqrt_to_avro_type(&f.data_type) {
+                AvroType::Boolean => "boolean",
+                AvroType::Int => "int",
+                AvroType::Long => "long",
+                AvroType::Float => "float",
+                AvroType::Double => "double",
+                AvroType::Bytes => "bytes",
+                _ => "string",
+            };
+            Ok(if f.nullable {
+                json!({ "name": name, "type": ["null", ty], "default": null })
+            } else {
+                json!({ "name": name, "type": ty })
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({ "type": "record", "name": "Row", "fields": fields }).to_string())
+}
+
+/// Encode `value` as `ty` (no union branch). Integer and float widths convert
+/// where the value fits.
+pub fn encode_value(ty: &AvroType, value: &Scalar, out: &mut Vec<u8>) -> Result<()> {
+    let mismatch = || {
+        Error::Schema(format!(
+            "cannot write {:?} as avro {:?}",
+            value.data_type(),
+            ty
+        ))
+    };
+    match (ty, value) {
+        (AvroType::Boolean, Scalar::Bool(b)) => out.push(*b as u8),
+        (AvroType::Int, Scalar::I32(v)) => write_long(out, *v as i64),
+        (AvroType::Int, Scalar::I64(v)) => {
+            write_long(out, i32::try_from(*v).map_err(|_| mismatch())? as i64)
+        }
+        (AvroType::Long, Scalar::I32(v)) => write_long(out, *v as i64),
+        (AvroType::Long, Scalar::I64(v)) => write_long(out, *v),
+        (AvroType::Float, Scalar::F32(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (AvroType::Float, Scalar::F64(v)) => out.extend_from_slice(&(*v as f32).to_le_bytes()),
+        (AvroType::Double, Scalar::F32(v)) => out.extend_from_slice(&(*v as f64).to_le_bytes()),
+        (AvroType::Double, Scalar::F64(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (AvroType::Double, Scalar::I32(v)) => out.extend_from_slice(&(*v as f64).to_le_bytes()),
+        (AvroType::Double, Scalar::I64(v)) => out.extend_from_slice(&(*v as f64).to_le_bytes()),
+        (AvroType::Bytes, Scalar::Bin(b)) => write_bytes(out, b),
+        (AvroType::Bytes, Scalar::Str(s)) => write_bytes(out, s.as_bytes()),
+        (AvroType::String, Scalar::Str(s)) => write_bytes(out, s.as_bytes()),
+        (AvroType::String, Scalar::Bool(b)) => write_bytes(out, b.to_string().as_bytes()),
+        (AvroType::String, Scalar::I32(v)) => write_bytes(out, v.to_string().as_bytes()),
+        (AvroType::String, Scalar::I64(v)) => write_bytes(out, v.to_string().as_bytes()),
+        (AvroType::String, Scalar::F32(v)) => write_bytes(out, v.to_string().as_bytes()),
+        (AvroType::String, Scalar::F64(v)) => write_bytes(out, v.to_string().as_bytes()),
+        (AvroType::String, Scalar::Date64(_) | Scalar::Timestamp(_) | Scalar::Decimal128(..)) => {
+            write_bytes(out, value.to_string().as_bytes())
+        }
+        _ => return Err(mismatch()),
+    }
+    Ok(())
+}
+
+/// Zig-zag varint `long` (also used for `int`, lengths and counts).
+pub fn write_long(out: &mut Vec<u8>, v: i64) {
+    let mut n = ((v << 1) ^ (v >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+pub fn write_bytes(out: &mut Vec<u8>, b: &[u8]) {
+    write_long(out, b.len() as i64);
+    out.extend_from_slice(b);
+}
+
+fn read_u8(r: &mut impl Read) -> Result<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+pub fn read_long(r: &mut impl Read) -> Result<i64> {
+    let mut n: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let b = read_u8(r)?;
+        n |= ((b & 0x7f) as u64) << shift;
+        if b & 0x80 == 0 {
+            return Ok((n >> 1) as i64 ^ -((n & 1) as i64));
+        }
+    }
+    Err(Error::Other("avro varint is longer than 10 bytes".into()))
+}
+
+pub fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>> {
+    let len = read_long(r)?;
+    let len =
+        usize::try_from(len).map_err(|_| Error::Other(format!("negative avro length {}", len)))?;
+    let mut b = Vec::new();
+    r.take(len as u64).read_to_end(&mut b)?;
+    if b.len() != len {
+        return Err(Error::Other("avro data ends inside a value".into()));
+    }
+    Ok(b)
+}
+
+fn read_string(r: &mut impl Read) -> Result<String> {
+    String::from_utf8(read_bytes(r)?)
+        .map_err(|_| Error::Other("avro string is not valid UTF-8".into()))
+}
+
+/// Container file metadata (`avro.schema`, `avro.codec`, ...).
+pub type Metadata = std::collections::BTreeMap<String, Vec<u8>>;
+
+/// Read the container header: metadata map and sync marker.
+pub fn read_header(r: &mut impl Read) -> Result<(Metadata, [u8; 16])> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::Other("not an avro object container file".into()));
+    }
+    let mut meta = Metadata::new();
+    loop {
+        let mut count = read_long(r)?;
+        if count == 0 {
+            break;
+        }
+        if count < 0 {
+            // A negative count is followed by the block's byte size.
+            count = -count;
+            read_long(r)?;
+        }
+        for _ in 0..count {
+            let key = read_string(r)?;
+            meta.insert(key, read_bytes(r)?);
+        }
+    }
+    let mut sync = [0u8; 16];
+    r.read_exact(&mut sync)?;
+    Ok((meta, sync))
+}
+
+/// Write the container header for `schema_json` with `codec`.
+pub fn write_header(
+    w: &mut impl Write,
+    schema_json: &str,
+    codec: &str,
+    sync: &[u8; 16],
+) -> Result<()> {
+    let mut out = MAGIC.to_vec();
+    write_long(&mut out, 2);
+    write_bytes(&mut out, b"avro.codec");
+    write_bytes(&mut out, codec.as_bytes());
+    write_bytes(&mut out, b"avro.schema");
+    write_bytes(&mut out, schema_json.as_bytes());
+    write_long(&mut out, 0);
+    out.extend_from_slice(sync);
+    w.write_all(&out)?;
+    Ok(())
+}
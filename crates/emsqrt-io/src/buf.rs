@@ -2,10 +2,22 @@
 //!
 //! For now we rely on `BufReader` with an explicit capacity to bound the in-flight
 //! buffer. Exec/planner can layer scheduling/backpressure around this as needed.
+//!
+//! `ResumableReader` sits below the buffering: it counts the bytes it has
+//! returned, and when a read fails (a stale NFS handle, a FUSE mount
+//! hiccup) it reopens its source at that offset and retries with backoff,
+//! so the layers above never see a torn stream. Persistent failures carry
+//! the source's path and byte offset. A shared scan counter, if attached,
+//! adds up the bytes read across every file of a source.
 
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use emsqrt_core::config::ReadRetryConfig;
 
 /// A thin wrapper over `BufReader` with a fixed capacity to bound in-flight bytes.
 pub struct BoundedBufReader<R: Read> {
@@ -54,3 +66,135 @@ pub fn bounded_from_path<P: AsRef<Path>>(
     let file = File::open(path)?;
     Ok(BoundedBufReader::with_capacity(cap, file))
 }
+
+/// Opens the source positioned at a byte offset.
+pub type Reopen<R> = Box<dyn FnMut(u64) -> io::Result<R> + Send>;
+
+/// A reader that resumes at its byte offset after a failed read.
+pub struct ResumableReader<R: Read> {
+    name: String,
+    reopen: Reopen<R>,
+    inner: R,
+    offset: u64,
+    retry: ReadRetryConfig,
+    scanned: Option<Arc<AtomicU64>>,
+}
+
+/// A source file read through [`ResumableReader`].
+pub type ResumableFile = ResumableReader<File>;
+
+impl<R: Read> ResumableReader<R> {
+    /// Open `name` at offset 0 with `reopen`, retrying like a read.
+    pub fn new(
+        name: impl Into<String>,
+        retry: ReadRetryConfig,
+        mut reopen: Reopen<R>,
+    ) -> io::Result<Self> {
+        let name = name.into();
+        let mut attempt = 0;
+        let inner = loop {
+            match reopen(0) {
+                Ok(inner) => break inner,
+                Err(e) if attempt < retry.max_retries && is_transient(&e) => {
+                    std::thread::sleep(backoff(&retry, attempt));
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(Self {
+            name,
+            reopen,
+            inner,
+            offset: 0,
+            retry,
+            scanned: None,
+        })
+    }
+
+    /// Also add every byte read to `counter`.
+    pub fn with_scan_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.scanned = Some(counter);
+        self
+    }
+
+    /// Bytes returned so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl ResumableFile {
+    pub fn open(path: impl AsRef<Path>, retry: &ReadRetryConfig) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let name = path.display().to_string();
+        Self::new(
+            name,
+            retry.clone(),
+            Box::new(move |offset| {
+                let mut file = File::open(&path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(file)
+            }),
+        )
+    }
+}
+
+impl<R: Read> Read for ResumableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut attempt = 0;
+        loop {
+            let err = match self.inner.read(buf) {
+                Ok(n) => {
+                    self.offset += n as u64;
+                    if let Some(scanned) = &self.scanned {
+                        scanned.fetch_add(n as u64, Ordering::Relaxed);
+                    }
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => e,
+            };
+            if attempt >= self.retry.max_retries || !is_transient(&err) {
+                return Err(io::Error::new(
+                    err.kind(),
+                    format!(
+                        "read of '{}' failed at byte {} after {} retries: {}",
+                        self.name, self.offset, attempt, err
+                    ),
+                ));
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(name = %self.name, offset = self.offset, attempt, error = %err, "retrying read");
+            std::thread::sleep(backoff(&self.retry, attempt));
+            attempt += 1;
+            // The old handle may be the broken part; a failed reopen counts
+            // as a failed attempt.
+            if let Ok(inner) = (self.reopen)(self.offset) {
+                self.inner = inner;
+            }
+        }
+    }
+}
+
+/// Wait before retry `attempt` (0-based): the initial backoff, doubled per
+/// attempt up to the maximum.
+fn backoff(retry: &ReadRetryConfig, attempt: usize) -> Duration {
+    let ms = retry
+        .initial_backoff_ms
+        .saturating_mul(1u64 << attempt.min(32))
+        .min(retry.max_backoff_ms.max(retry.initial_backoff_ms));
+    Duration::from_millis(ms)
+}
+
+/// Errors that retrying cannot fix are returned at once.
+fn is_transient(err: &io::Error) -> bool {
+    !matches!(
+        err.kind(),
+        io::ErrorKind::NotFound
+            | io::ErrorKind::PermissionDenied
+            | io::ErrorKind::InvalidInput
+            | io::ErrorKind::InvalidData
+            | io::ErrorKind::Unsupported
+    )
+}
@@ -0,0 +1,128 @@
+//! The files behind a source path.
+//!
+//! A source is one file, every file in a directory, or the files matching a
+//! glob: `*` matches any run of characters and `?` exactly one, in any path
+//! component (`data/2024-*/part-?.csv`). Directory listings and wildcard
+//! components skip hidden entries (names starting with `.` or `_`, such as
+//! `_SUCCESS` markers) unless the pattern itself starts with one. Files come
+//! out sorted by path, so every run reads them in the same order.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// Whether `path` may name several files: it has wildcards or is a directory.
+pub fn is_multi_file(path: &str) -> bool {
+    has_wildcards(path) || Path::new(path).is_dir()
+}
+
+/// The files `path` names, sorted. A plain file path is returned as is,
+/// whether or not it exists, so that opening it reports the error.
+pub fn list_source_files(path: &str) -> Result<Vec<String>> {
+    let files = if has_wildcards(path) {
+        expand_glob(path)?
+    } else if Path::new(path).is_dir() {
+        dir_files(Path::new(path))?
+    } else {
+        return Ok(vec![path.to_string()]);
+    };
+    if files.is_empty() {
+        let what = if has_wildcards(path) {
+            "no files match"
+        } else {
+            "no files in directory"
+        };
+        return Err(Error::Other(format!("{} '{}'", what, path)));
+    }
+    Ok(files
+        .into_iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect())
+}
+
+/// `*` matches any run of bytes, `?` exactly one.
+pub fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| wildcard_match(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+fn has_wildcards(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with(['.', '_'])
+}
+
+/// Expand the wildcard components of `pattern` one level at a time. A
+/// directory matched by the last component contributes its files.
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let mut bases = vec![PathBuf::new()];
+    let components: Vec<_> = path.components().collect();
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let part = component.as_os_str().to_string_lossy();
+        if !has_wildcards(&part) {
+            for base in &mut bases {
+                base.push(component);
+            }
+            continue;
+        }
+        let mut next = Vec::new();
+        for base in &bases {
+            let dir = if base.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                base.as_path()
+            };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if (is_hidden(&name) && !is_hidden(&part))
+                    || !wildcard_match(part.as_bytes(), name.as_bytes())
+                {
+                    continue;
+                }
+                // Intermediate components only descend into directories.
+                if last || base.join(&name).is_dir() {
+                    next.push(base.join(&name));
+                }
+            }
+        }
+        bases = next;
+    }
+
+    let mut files = Vec::new();
+    for base in bases {
+        if base.is_dir() {
+            files.extend(dir_files(&base)?);
+        } else if base.is_file() {
+            files.push(base);
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// The files directly in `dir` (not in its subdirectories), sorted.
+fn dir_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && !is_hidden(&entry.file_name().to_string_lossy()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
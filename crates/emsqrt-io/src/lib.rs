@@ -1,21 +1,26 @@
 #![forbid(unsafe_code)]
 //! emsqrt-io: storage adapters and streaming readers/writers.
 //!
-//! - `storage`: concrete impls of `emsqrt_mem::spill::Storage` (FS now; cloud placeholders).
+//! - `storage`: concrete impls of `emsqrt_mem::spill::Storage` (FS, in-memory, cloud).
+//! - `files`: the files behind a source path (one file, a directory, or a glob).
 //! - `buf`: bounded buffered readers (read-ahead within a max buffer cap).
 //! - `readers`: CSV/JSONL stream readers → simple `RowBatch` (no Arrow here).
-//! - `writers`: CSV/JSONL stream writers.
+//! - `writers`: CSV/JSONL/Parquet/Arrow/Avro stream writers behind `writers::open_writer`.
 //!
-//! Parquet modules are feature-gated and stubbed unless `--features parquet`.
+//! Parquet, Arrow and Avro modules are feature-gated and stubbed unless `--features parquet`,
+//! `--features arrow` or `--features avro`.
 
 pub mod buf;
+pub mod files;
 pub mod readers;
 pub mod storage;
 pub mod writers;
 
 pub mod error;
 
-#[cfg(feature = "parquet")]
+#[cfg(feature = "arrow")]
 pub mod arrow_convert;
+#[cfg(feature = "avro")]
+pub mod avro_convert;
 
-pub use storage::{build_storage_from_config, FsStorage};
+pub use storage::{build_storage_from_config, FsStorage, MemStorage};
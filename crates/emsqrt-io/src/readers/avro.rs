@@ -0,0 +1,156 @@
+//! Avro object container file reader (enabled with `--features avro`).
+//!
+//! Decodes one container block at a time, so memory is bounded by the
+//! largest block rather than the file. Supports the `null` and `deflate`
+//! codecs.
+
+use std::io::{BufReader, Read};
+
+use emsqrt_core::config::ReadRetryConfig;
+use emsqrt_core::schema::Schema;
+use emsqrt_core::types::{Column, RowBatch};
+
+use crate::avro_convert::{
+    avro_to_emsqrt_schema, parse_schema, read_bytes, read_header, read_long, AvroField,
+};
+use crate::buf::ResumableFile;
+use crate::error::{Error, Result};
+
+pub struct AvroReader<R: Read> {
+    reader: BufReader<R>,
+    fields: Vec<AvroField>,
+    schema: Schema,
+    /// Output column of each file field, if projected.
+    slots: Vec<Option<usize>>,
+    /// Names of the output columns, in projection order.
+    columns: Vec<String>,
+    deflate: bool,
+    sync: [u8; 16],
+    /// Decoded (decompressed) current block and the objects left in it.
+    block: Vec<u8>,
+    pos: usize,
+    remaining: i64,
+}
+
+impl AvroReader<ResumableFile> {
+    /// Read `path` with the default read retries.
+    pub fn from_path(path: &str, projection: Option<Vec<String>>) -> Result<Self> {
+        Self::from_reader(
+            ResumableFile::open(path, &ReadRetryConfig::default())?,
+            projection,
+        )
+    }
+}
+
+impl<R: Read> AvroReader<R> {
+    /// Read the header; `projection` selects (and orders) output columns.
+    pub fn from_reader(reader: R, projection: Option<Vec<String>>) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let (meta, sync) = read_header(&mut reader)?;
+        let schema_json = meta
+            .get("avro.schema")
+            .ok_or_else(|| Error::Schema("avro file has no 'avro.schema' metadata".into()))?;
+        let fields = parse_schema(&String::from_utf8_lossy(schema_json))?;
+        let schema = avro_to_emsqrt_schema(&fields)?;
+        let deflate = match meta.get("avro.codec").map(Vec::as_slice) {
+            None | Some(b"null") => false,
+            Some(b"deflate") => true,
+            Some(other) => {
+                return Err(Error::Config(format!(
+                    "unsupported avro codec '{}' (expected null or deflate)",
+                    String::from_utf8_lossy(other)
+                )))
+            }
+        };
+
+        let columns = projection.unwrap_or_else(|| fields.iter().map(|f| f.name.clone()).collect());
+        let mut slots = vec![None; fields.len()];
+        for (out, name) in columns.iter().enumerate() {
+            let i = fields.iter().position(|f| f.name == *name).ok_or_else(|| {
+                Error::Schema(format!(
+                    "avro file missing required column '{}'. Available columns: {:?}",
+                    name,
+                    fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>()
+                ))
+            })?;
+            slots[i] = Some(out);
+        }
+
+        Ok(Self {
+            reader,
+            fields,
+            schema,
+            slots,
+            columns,
+            deflate,
+            sync,
+            block: Vec::new(),
+            pos: 0,
+            remaining: 0,
+        })
+    }
+
+    /// Schema of the whole file (all fields, before projection).
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Up to `max_rows` rows, or `None` at end of file.
+    pub fn next_batch(&mut self, max_rows: usize) -> Result<Option<RowBatch>> {
+        let mut columns: Vec<Column> = self
+            .columns
+            .iter()
+            .map(|name| Column {
+                name: name.clone(),
+                values: Vec::new(),
+            })
+            .collect();
+        let mut rows = 0;
+        while rows < max_rows {
+            if self.remaining == 0 && !self.next_block()? {
+                break;
+            }
+            let mut data = &self.block[self.pos..];
+            let before = data.len();
+            for (field, slot) in self.fields.iter().zip(&self.slots) {
+                let value = field.ty.decode(&mut data)?;
+                if let Some(out) = slot {
+                    columns[*out].values.push(value);
+                }
+            }
+            self.pos += before - data.len();
+            self.remaining -= 1;
+            rows += 1;
+        }
+        Ok((rows > 0).then_some(RowBatch { columns }))
+    }
+
+    /// Load the next non-empty data block; false at end of file.
+    fn next_block(&mut self) -> Result<bool> {
+        loop {
+            let mut first = [0u8; 1];
+            if self.reader.read(&mut first)? == 0 {
+                return Ok(false);
+            }
+            let count = read_long(&mut (&first[..]).chain(&mut self.reader))?;
+            let raw = read_bytes(&mut self.reader)?;
+            let mut sync = [0u8; 16];
+            self.reader.read_exact(&mut sync)?;
+            if sync != self.sync {
+                return Err(Error::Other("avro block sync marker mismatch".into()));
+            }
+            self.block = if self.deflate {
+                let mut out = Vec::new();
+                flate2::read::DeflateDecoder::new(raw.as_slice()).read_to_end(&mut out)?;
+                out
+            } else {
+                raw
+            };
+            self.pos = 0;
+            self.remaining = count;
+            if count > 0 {
+                return Ok(true);
+            }
+        }
+    }
+}
@@ -4,13 +4,15 @@
 //! - No type inference (everything is Utf8 Scalar by default).
 //! - Suitable as a starter; replace with Arrow-based scans later.
 
-use std::fs::File;
 use std::io::Read;
 
 use csv as csv_crate;
 use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 
+use emsqrt_core::config::ReadRetryConfig;
+
+use crate::buf::ResumableFile;
 use crate::error::{Error, Result};
 
 pub struct CsvReader<R: Read> {
@@ -18,9 +20,10 @@ pub struct CsvReader<R: Read> {
     schema: Schema,
 }
 
-impl CsvReader<File> {
+impl CsvReader<ResumableFile> {
+    /// Read `path` with the default read retries.
     pub fn from_path(path: &str, has_headers: bool) -> Result<Self> {
-        let file = File::open(path)?;
+        let file = ResumableFile::open(path, &ReadRetryConfig::default())?;
         Self::from_reader(file, has_headers)
     }
 }
@@ -1,27 +1,55 @@
 //! Streaming NDJSON reader → `RowBatch`.
 //!
 //! Caveats:
-//! - Builds the column set from the union of keys seen so far.
-//! - All scalars are mapped to a small set of types; complex values become strings.
+//! - Builds the column set from the union of keys seen so far (unless projected).
+//! - Scalars map to a small set of types unless declared (`with_types`);
+//!   arrays, and objects unless flattened, become JSON text.
+//!
+//! Declared types convert values where that loses nothing: `3` read as
+//! `Float64` is `3.0`, `"42"` read as `Int64` is `42`, `7` read as `Utf8` is
+//! `"7"`. Dates and timestamps read from ISO text, decimals from text or a
+//! number (`"12.50"`, `12.5`). A value that does not convert keeps its JSON type, for the caller to
+//! treat as a parse error. Predicates see converted values.
+//!
+//! Pushdown: with a projection, only the listed fields are deserialized (other
+//! values are skipped without allocating) and the schema is exactly those
+//! fields. Field predicates drop rows while parsing, before any `Scalar` for
+//! the row's other columns is built.
 
-use std::fs::File;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::io::{BufRead, BufReader, Read};
 
+use emsqrt_core::config::ReadRetryConfig;
+use emsqrt_core::expr::FieldPredicate;
 use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
-use serde_json::Value;
+use emsqrt_core::{decimal, temporal};
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde_json::{Map, Value};
 
+use crate::buf::ResumableFile;
 use crate::error::Result;
 
 pub struct JsonlReader<R: Read> {
     reader: BufReader<R>,
     // We grow the schema as we see new keys (simple prototype behavior).
     schema: Schema,
+    projection: Option<Vec<String>>,
+    predicates: Vec<FieldPredicate>,
+    // Fields to deserialize: projection + predicate fields (None = all).
+    keep: Option<HashSet<String>>,
+    // Declared field types values are converted to.
+    types: HashMap<String, DataType>,
+    // Nested objects become `parent.child` fields.
+    flatten: bool,
 }
 
-impl JsonlReader<File> {
+impl JsonlReader<ResumableFile> {
+    /// Read `path` with the default read retries.
     pub fn from_path(path: &str) -> Result<Self> {
-        let f = File::open(path)?;
+        let f = ResumableFile::open(path, &ReadRetryConfig::default())?;
         Self::from_reader(f)
     }
 }
@@ -31,6 +59,120 @@ impl<R: Read> JsonlReader<R> {
         Ok(Self {
             reader: BufReader::new(reader),
             schema: Schema::new(vec![]),
+            projection: None,
+            predicates: Vec::new(),
+            keep: None,
+            types: HashMap::new(),
+            flatten: false,
+        })
+    }
+
+    /// Only materialize these fields; the schema becomes exactly this list.
+    pub fn with_projection(mut self, fields: Vec<String>) -> Self {
+        self.schema = Schema::new(
+            fields
+                .iter()
+                .map(|f| Field::new(f.clone(), DataType::Utf8, true))
+                .collect(),
+        );
+        self.projection = Some(fields);
+        self.update_keep();
+        self
+    }
+
+    /// Drop rows failing any of these predicates while parsing.
+    pub fn with_predicates(mut self, predicates: Vec<FieldPredicate>) -> Self {
+        self.predicates = predicates;
+        self.update_keep();
+        self
+    }
+
+    /// Convert the values of these fields to their declared types.
+    pub fn with_types(mut self, fields: &[Field]) -> Self {
+        self.types = fields
+            .iter()
+            .map(|f| (f.name.clone(), f.data_type.clone()))
+            .collect();
+        for field in self.schema.fields.iter_mut() {
+            if let Some(t) = self.types.get(&field.name) {
+                field.data_type = t.clone();
+            }
+        }
+        self
+    }
+
+    /// Read nested objects as `parent.child` fields instead of JSON text.
+    pub fn with_flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self.update_keep();
+        self
+    }
+
+    fn update_keep(&mut self) {
+        let flatten = self.flatten;
+        self.keep = self.projection.as_ref().map(|fields| {
+            fields
+                .iter()
+                .map(String::as_str)
+                .chain(self.predicates.iter().map(|p| p.field()))
+                // A flattened field comes from its top-level object.
+                .map(|f| match f.split_once('.') {
+                    Some((top, _)) if flatten => top.to_string(),
+                    _ => f.to_string(),
+                })
+                .collect()
+        });
+    }
+
+    fn scalar(&self, field: &str, value: Value) -> Scalar {
+        match self.types.get(field) {
+            Some(data_type) => coerce(value, data_type),
+            None => to_scalar(value),
+        }
+    }
+
+    /// Parse one line; `None` if a predicate rejects it.
+    fn parse_line(&self, line: &str) -> Result<Option<Value>> {
+        if !self.raw_line_may_match(line) {
+            return Ok(None);
+        }
+        let value = if line.trim_start().starts_with('{') {
+            let mut de = serde_json::Deserializer::from_str(line);
+            let map = ProjectedObject {
+                keep: self.keep.as_ref(),
+            }
+            .deserialize(&mut de)?;
+            de.end()?;
+            if self.flatten {
+                let mut flat = Map::new();
+                flatten_into(&mut flat, "", map);
+                Value::Object(flat)
+            } else {
+                Value::Object(map)
+            }
+        } else {
+            serde_json::from_str(line)?
+        };
+        let passes = self.predicates.iter().all(|p| {
+            let field = value.get(p.field()).cloned().unwrap_or(Value::Null);
+            p.matches(&self.scalar(p.field(), field))
+        });
+        Ok(passes.then_some(value))
+    }
+
+    /// Cheap substring test on the raw line for string predicates. Only used
+    /// when the line has no escapes, so raw and decoded text agree.
+    fn raw_line_may_match(&self, line: &str) -> bool {
+        if line.contains('\\') {
+            return true;
+        }
+        self.predicates.iter().all(|p| match p {
+            FieldPredicate::Contains { needle, .. } => line.contains(needle.as_str()),
+            FieldPredicate::Eq {
+                value: Scalar::Str(s),
+                ..
+            } => line.contains(s.as_str()),
+            _ => true,
         })
     }
 
@@ -43,42 +185,44 @@ impl<R: Read> JsonlReader<R> {
             return Ok(Some(RowBatch { columns: vec![] }));
         }
 
-        let mut lines = Vec::with_capacity(limit_rows);
-        for _ in 0..limit_rows {
-            let mut s = String::new();
-            let n = self.reader.read_line(&mut s)?;
+        let mut parsed = Vec::with_capacity(limit_rows);
+        let mut line = String::new();
+        while parsed.len() < limit_rows {
+            line.clear();
+            let n = self.reader.read_line(&mut line)?;
             if n == 0 {
                 break;
             }
-            if s.trim().is_empty() {
+            if line.trim().is_empty() {
                 continue;
             }
-            lines.push(s);
+            if let Some(v) = self.parse_line(&line)? {
+                parsed.push(v);
+            }
         }
-        if lines.is_empty() {
+        if parsed.is_empty() {
             return Ok(None);
         }
 
-        // Discover union of keys
-        use std::collections::BTreeSet;
+        // Discover union of keys (a projection fixes the schema instead)
         let mut keys = BTreeSet::<String>::new();
-        let mut parsed = Vec::with_capacity(lines.len());
-        for s in lines {
-            let v: Value = serde_json::from_str(&s)?;
-            if let Value::Object(map) = &v {
-                for k in map.keys() {
-                    keys.insert(k.clone());
+        if self.projection.is_none() {
+            for v in &parsed {
+                if let Value::Object(map) = v {
+                    for k in map.keys() {
+                        keys.insert(k.clone());
+                    }
                 }
             }
-            parsed.push(v);
         }
 
         // Ensure schema covers all keys
         for k in keys.iter() {
             if self.schema.index_of(k).is_none() {
+                let data_type = self.types.get(k).cloned().unwrap_or(DataType::Utf8);
                 self.schema
                     .fields
-                    .push(Field::new(k.clone(), DataType::Utf8, true));
+                    .push(Field::new(k.clone(), data_type, true));
             }
         }
 
@@ -95,10 +239,10 @@ impl<R: Read> JsonlReader<R> {
 
         for v in parsed {
             match v {
-                Value::Object(map) => {
+                Value::Object(mut map) => {
                     for (i, f) in self.schema.fields.iter().enumerate() {
-                        let s = map.get(&f.name).cloned().unwrap_or(Value::Null);
-                        cols[i].values.push(to_scalar(s));
+                        let s = map.remove(&f.name).unwrap_or(Value::Null);
+                        cols[i].values.push(self.scalar(&f.name, s));
                     }
                 }
                 _ => {
@@ -134,3 +278,143 @@ fn to_scalar(v: Value) -> Scalar {
         other => Str(other.to_string()),
     }
 }
+
+/// `value` as `data_type` if it converts without loss, else as read.
+fn coerce(value: Value, data_type: &DataType) -> Scalar {
+    let converted = match (&value, data_type) {
+        (Value::Null, _) => Some(Scalar::Null),
+        (Value::Number(n), DataType::Int64) => n.as_i64().map(Scalar::I64),
+        (Value::Number(n), DataType::Int32) => n
+            .as_i64()
+            .and_then(|i| i32::try_from(i).ok())
+            .map(Scalar::I32),
+        (Value::Number(n), DataType::Float64) => n.as_f64().map(Scalar::F64),
+        (Value::Number(n), DataType::Float32) => n.as_f64().map(|f| Scalar::F32(f as f32)),
+        (Value::String(s), _) if s.is_empty() && is_parsed(data_type) => Some(Scalar::Null),
+        (Value::String(s), DataType::Int64) => s.parse().ok().map(Scalar::I64),
+        (Value::String(s), DataType::Int32) => s.parse().ok().map(Scalar::I32),
+        (Value::String(s), DataType::Float64) => s.parse().ok().map(Scalar::F64),
+        (Value::String(s), DataType::Float32) => s.parse().ok().map(Scalar::F32),
+        (Value::String(s), DataType::Boolean) => s.parse().ok().map(Scalar::Bool),
+        (Value::String(s), DataType::Date64) => temporal::parse_date(s).map(Scalar::Date64),
+        (Value::String(s), DataType::Timestamp) => {
+            temporal::parse_timestamp(s).map(Scalar::Timestamp)
+        }
+        (Value::String(s), DataType::Decimal128) => {
+            decimal::parse_decimal(s).map(|(v, scale)| Scalar::Decimal128(v, scale))
+        }
+        (Value::Number(n), DataType::Decimal128) => {
+            decimal::parse_decimal(&n.to_string()).map(|(v, scale)| Scalar::Decimal128(v, scale))
+        }
+        (Value::Bool(b), DataType::Boolean) => Some(Scalar::Bool(*b)),
+        (Value::Number(_) | Value::Bool(_), DataType::Utf8) => Some(Scalar::Str(value.to_string())),
+        _ => None,
+    };
+    converted.unwrap_or_else(|| to_scalar(value))
+}
+
+/// Types whose text is parsed rather than kept as a string.
+fn is_parsed(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int32
+            | DataType::Int64
+            | DataType::Float32
+            | DataType::Float64
+            | DataType::Boolean
+            | DataType::Date64
+            | DataType::Timestamp
+            | DataType::Decimal128
+    )
+}
+
+/// Move the fields of `map` into `out`, nested objects as `prefix.key` fields.
+fn flatten_into(out: &mut Map<String, Value>, prefix: &str, map: Map<String, Value>) {
+    for (key, value) in map {
+        let name = if prefix.is_empty() {
+            key
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(nested) if !nested.is_empty() => flatten_into(out, &name, nested),
+            other => {
+                out.insert(name, other);
+            }
+        }
+    }
+}
+
+/// Deserializes a JSON object, keeping only the fields in `keep` (all if None).
+/// Skipped values are consumed with `IgnoredAny`, so they are never allocated.
+struct ProjectedObject<'a> {
+    keep: Option<&'a HashSet<String>>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for ProjectedObject<'a> {
+    type Value = Map<String, Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for ProjectedObject<'a> {
+    type Value = Map<String, Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON object")
+    }
+
+    fn visit_map<A>(self, mut access: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut map = Map::new();
+        while let Some(key) = access.next_key_seed(KeySeed)? {
+            match self.keep {
+                Some(keep) if !keep.contains(key.as_ref()) => {
+                    access.next_value::<IgnoredAny>()?;
+                }
+                _ => {
+                    let value = access.next_value::<Value>()?;
+                    map.insert(key.into_owned(), value);
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// Object key, borrowed from the input when it has no escapes.
+struct KeySeed;
+
+impl<'de> DeserializeSeed<'de> for KeySeed {
+    type Value = Cow<'de, str>;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an object key")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> std::result::Result<Self::Value, E> {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_string()))
+    }
+}
@@ -5,6 +5,9 @@
 
 pub mod csv;
 pub mod jsonl;
+pub mod text;
 
+#[cfg(feature = "avro")]
+pub mod avro;
 #[cfg(feature = "parquet")]
 pub mod parquet;
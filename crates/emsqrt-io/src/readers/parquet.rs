@@ -151,5 +151,12 @@ impl ParquetReader {
     }
 }
 
+/// Rows in a Parquet file, from its footer.
+#[cfg(feature = "parquet")]
+pub fn row_count(path: &str) -> Result<u64> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+    Ok(builder.metadata().file_metadata().num_rows().max(0) as u64)
+}
+
 #[cfg(not(feature = "parquet"))]
 compile_error!("parquet.rs was compiled without the `parquet` feature; enable `--features parquet` or exclude this module.");
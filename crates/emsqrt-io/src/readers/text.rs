@@ -0,0 +1,167 @@
+//! Delimited and fixed-width text records, for exports the CSV reader can't
+//! parse: multi-character or non-newline record delimiters, unquoted custom
+//! field separators, and column spans at fixed character offsets.
+//!
+//! The layout comes from the source schema (`Schema::layout`). Records are
+//! returned as raw cell text in schema field order; typing, null handling and
+//! parse-error policies are the caller's, as for CSV.
+
+use std::io::{BufRead, BufReader, Read};
+
+use emsqrt_core::config::ReadRetryConfig;
+use emsqrt_core::schema::{FieldLayout, Schema, TextLayout};
+
+use crate::buf::ResumableFile;
+use crate::error::{Error, Result};
+
+/// One record's cells, aligned with the schema's fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRecord {
+    /// 1-based record number in the file, counting skipped and header records.
+    pub line: u64,
+    pub values: Vec<String>,
+}
+
+pub struct TextReader<R: Read> {
+    reader: BufReader<R>,
+    layout: TextLayout,
+    /// Delimited files: the record field holding each schema field.
+    columns: Vec<usize>,
+    buf: Vec<u8>,
+    records_read: u64,
+}
+
+impl TextReader<ResumableFile> {
+    /// Read `path` with the default read retries.
+    pub fn from_path(path: &str, schema: &Schema) -> Result<Self> {
+        let f = ResumableFile::open(path, &ReadRetryConfig::default())?;
+        Self::from_reader(f, schema)
+    }
+}
+
+impl<R: Read> TextReader<R> {
+    /// Reader for `schema.layout`; skips `skip_records` and reads the header.
+    pub fn from_reader(reader: R, schema: &Schema) -> Result<Self> {
+        let layout = schema
+            .layout
+            .as_deref()
+            .cloned()
+            .ok_or_else(|| Error::Config("text reader needs a schema layout".into()))?;
+        if layout.record_delimiter.is_empty() {
+            return Err(Error::Config("record_delimiter must not be empty".into()));
+        }
+        match &layout.fields {
+            FieldLayout::Delimited { delimiter, .. } if delimiter.is_empty() => {
+                return Err(Error::Config("field delimiter must not be empty".into()));
+            }
+            FieldLayout::FixedWidth { spans } if spans.len() != schema.fields.len() => {
+                return Err(Error::Config(format!(
+                    "fixed-width layout has {} spans for {} fields",
+                    spans.len(),
+                    schema.fields.len()
+                )));
+            }
+            _ => {}
+        }
+
+        let mut this = Self {
+            reader: BufReader::new(reader),
+            columns: (0..schema.fields.len()).collect(),
+            layout,
+            buf: Vec::new(),
+            records_read: 0,
+        };
+        for _ in 0..this.layout.skip_records {
+            if this.next_raw()?.is_none() {
+                break;
+            }
+        }
+        if let FieldLayout::Delimited {
+            delimiter,
+            header: true,
+        } = this.layout.fields.clone()
+        {
+            let header = this.next_raw()?.unwrap_or_default();
+            let names: Vec<&str> = header.split(delimiter.as_str()).map(str::trim).collect();
+            this.columns = schema
+                .fields
+                .iter()
+                .map(|f| {
+                    names
+                        .iter()
+                        .position(|n| *n == f.name.trim())
+                        .ok_or_else(|| {
+                            Error::Schema(format!(
+                                "text file missing required column '{}'. Available columns: {:?}",
+                                f.name, names
+                            ))
+                        })
+                })
+                .collect::<Result<_>>()?;
+        }
+        Ok(this)
+    }
+
+    /// The next non-empty record, or `None` at end of input.
+    pub fn next_record(&mut self) -> Result<Option<TextRecord>> {
+        loop {
+            let Some(raw) = self.next_raw()? else {
+                return Ok(None);
+            };
+            if raw.is_empty() {
+                continue;
+            }
+            let values = match &self.layout.fields {
+                FieldLayout::Delimited { delimiter, .. } => {
+                    let cells: Vec<&str> = raw.split(delimiter.as_str()).collect();
+                    self.columns
+                        .iter()
+                        .map(|&i| cells.get(i).copied().unwrap_or("").to_string())
+                        .collect()
+                }
+                FieldLayout::FixedWidth { spans } => {
+                    let chars: Vec<char> = raw.chars().collect();
+                    spans
+                        .iter()
+                        .map(|s| {
+                            let start = s.offset.min(chars.len());
+                            let end = (s.offset + s.length).min(chars.len());
+                            chars[start..end].iter().collect()
+                        })
+                        .collect()
+                }
+            };
+            return Ok(Some(TextRecord {
+                line: self.records_read,
+                values,
+            }));
+        }
+    }
+
+    /// Text up to the next record delimiter (not included).
+    fn next_raw(&mut self) -> Result<Option<String>> {
+        let delim = self.layout.record_delimiter.as_bytes();
+        let last = *delim.last().expect("checked non-empty");
+        self.buf.clear();
+        loop {
+            if self.reader.read_until(last, &mut self.buf)? == 0 {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            if self.buf.ends_with(delim) {
+                self.buf.truncate(self.buf.len() - delim.len());
+                break;
+            }
+        }
+        // A "\n"-delimited file written on Windows still reads cleanly.
+        if delim == b"\n" && self.buf.last() == Some(&b'\r') {
+            self.buf.pop();
+        }
+        self.records_read += 1;
+        String::from_utf8(std::mem::take(&mut self.buf))
+            .map(Some)
+            .map_err(|_| Error::Other(format!("record {} is not valid UTF-8", self.records_read)))
+    }
+}
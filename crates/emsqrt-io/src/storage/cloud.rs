@@ -8,9 +8,9 @@ use emsqrt_core::config::StorageConfig;
 use emsqrt_mem::error::{Error as MemError, Result as MemResult};
 use emsqrt_mem::Storage;
 use futures::StreamExt;
-use object_store::client::backoff::BackoffConfig;
 use object_store::path::Path as ObjectPath;
-use object_store::{Error as ObjectStoreError, ObjectStore};
+use object_store::{BackoffConfig, Error as ObjectStoreError, ObjectStore};
+use tokio::io::AsyncWriteExt;
 use tokio::runtime::Runtime;
 use url::Url;
 
@@ -23,6 +23,10 @@ use object_store::gcp::{GoogleCloudStorage, GoogleCloudStorageBuilder};
 
 use super::RetryConfig;
 
+/// Segments of at least this many bytes are uploaded in parts (multipart
+/// upload), so a failed part is retried without resending the whole object.
+pub const MULTIPART_THRESHOLD: usize = 32 * 1024 * 1024;
+
 #[derive(Debug, thiserror::Error)]
 pub enum CloudStorageBuilderError {
     #[error("missing spill URI for {scheme} storage")]
@@ -64,6 +68,7 @@ struct CloudIdentity {
 }
 
 impl CloudIdentity {
+    #[cfg(feature = "s3")]
     fn new_s3(uri: &str) -> Result<Self, CloudStorageBuilderError> {
         let parsed = Url::parse(uri).map_err(|source| CloudStorageBuilderError::InvalidUri {
             uri: uri.to_string(),
@@ -88,6 +93,7 @@ impl CloudIdentity {
         })
     }
 
+    #[cfg(feature = "gcs")]
     fn new_gcs(uri: &str) -> Result<Self, CloudStorageBuilderError> {
         let parsed = Url::parse(uri).map_err(|source| CloudStorageBuilderError::InvalidUri {
             uri: uri.to_string(),
@@ -113,6 +119,7 @@ impl CloudIdentity {
         })
     }
 
+    #[cfg(feature = "azure")]
     fn new_azure(uri: &str) -> Result<Self, CloudStorageBuilderError> {
         let parsed = Url::parse(uri).map_err(|source| CloudStorageBuilderError::InvalidUri {
             uri: uri.to_string(),
@@ -207,6 +214,7 @@ struct CloudStorage {
     store: Arc<dyn ObjectStore>,
     identity: CloudIdentity,
     retry: RetryConfig,
+    multipart_threshold: usize,
 }
 
 impl CloudStorage {
@@ -222,6 +230,7 @@ impl CloudStorage {
             store,
             identity,
             retry,
+            multipart_threshold: MULTIPART_THRESHOLD,
         })
     }
 
@@ -241,6 +250,32 @@ impl CloudStorage {
         }
     }
 
+    /// Upload `bytes` in parts; a failed upload is aborted so no parts linger.
+    fn write_multipart(&self, obj_path: &ObjectPath, bytes: &[u8]) -> MemResult<()> {
+        self.run_with_retry(
+            || {
+                let store = Arc::clone(&self.store);
+                let obj_path = obj_path.clone();
+                async move {
+                    let (id, mut writer) = store.put_multipart(&obj_path).await?;
+                    let uploaded = match writer.write_all(bytes).await {
+                        Ok(()) => writer.shutdown().await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = uploaded {
+                        let _ = store.abort_multipart(&obj_path, &id).await;
+                        return Err(ObjectStoreError::Generic {
+                            store: "multipart upload",
+                            source: Box::new(e),
+                        });
+                    }
+                    Ok(())
+                }
+            },
+            true,
+        )
+    }
+
     fn run_with_retry<F, Fut, T>(&self, mut op: F, retry_not_found: bool) -> MemResult<T>
     where
         F: FnMut() -> Fut,
@@ -262,6 +297,8 @@ impl CloudStorage {
                         return Err(MemError::Storage(format!("{err}")));
                     }
                     attempt += 1;
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(attempt, error = %err, "retrying object store request");
                     thread::sleep(backoff);
                     backoff = std::cmp::min(backoff * 2, self.retry.max_backoff);
                 }
@@ -271,21 +308,33 @@ impl CloudStorage {
 }
 
 fn is_retryable(err: &ObjectStoreError) -> bool {
-    match err {
-        ObjectStoreError::NotFound { .. } => false,
-        ObjectStoreError::AlreadyExists { .. } => false,
-        _ => true,
-    }
+    // Errors that repeat on every attempt; anything else (network, throttling,
+    // server errors surfaced as `Generic`) is treated as transient.
+    !matches!(
+        err,
+        ObjectStoreError::NotFound { .. }
+            | ObjectStoreError::AlreadyExists { .. }
+            | ObjectStoreError::InvalidPath { .. }
+            | ObjectStoreError::NotSupported { .. }
+            | ObjectStoreError::Precondition { .. }
+            | ObjectStoreError::NotModified { .. }
+            | ObjectStoreError::NotImplemented
+            | ObjectStoreError::UnknownConfigurationKey { .. }
+    )
 }
 
 impl Storage for CloudStorage {
     fn write(&self, path: &str, bytes: &[u8]) -> MemResult<()> {
         let obj_path = self.object_path(path)?;
+        if bytes.len() >= self.multipart_threshold {
+            return self.write_multipart(&obj_path, bytes);
+        }
         let data = Bytes::copy_from_slice(bytes);
         self.run_with_retry(
             || {
                 let bytes = data.clone();
                 let store = Arc::clone(&self.store);
+                let obj_path = obj_path.clone();
                 async move { store.put(&obj_path, bytes).await.map(|_| ()) }
             },
             true,
@@ -293,16 +342,35 @@ impl Storage for CloudStorage {
     }
 
     fn read_range(&self, path: &str, offset: u64, len: usize) -> MemResult<Vec<u8>> {
+        if len == 0 {
+            // Stores reject empty ranges; only check that the offset is in bounds.
+            let size = self.size(path)?;
+            return if offset <= size {
+                Ok(Vec::new())
+            } else {
+                Err(MemError::Storage(format!(
+                    "read 0 bytes at {offset}: object '{path}' has {size} bytes"
+                )))
+            };
+        }
         let obj_path = self.object_path(path)?;
         let range = (offset as usize)..(offset as usize + len);
-        self.run_with_retry(
+        let bytes = self.run_with_retry(
             || {
                 let store = Arc::clone(&self.store);
-                async move { store.get_range(&obj_path, range.clone()).await }
+                let (obj_path, range) = (obj_path.clone(), range.clone());
+                async move { store.get_range(&obj_path, range).await }
             },
             false,
-        )
-        .map(|bytes| bytes.to_vec())
+        )?;
+        // Ranges past the end come back truncated rather than failing.
+        if bytes.len() != len {
+            return Err(MemError::Storage(format!(
+                "read {len} bytes at {offset}: object '{path}' returned {} bytes",
+                bytes.len()
+            )));
+        }
+        Ok(bytes.to_vec())
     }
 
     fn delete(&self, path: &str) -> MemResult<()> {
@@ -310,6 +378,7 @@ impl Storage for CloudStorage {
         self.run_with_retry(
             || {
                 let store = Arc::clone(&self.store);
+                let obj_path = obj_path.clone();
                 async move { store.delete(&obj_path).await }
             },
             true,
@@ -336,6 +405,7 @@ impl Storage for CloudStorage {
         self.run_with_retry(
             || {
                 let store = Arc::clone(&self.store);
+                let obj_path = obj_path.clone();
                 async move { store.head(&obj_path).await }
             },
             false,
@@ -348,6 +418,7 @@ impl Storage for CloudStorage {
         self.run_with_retry(
             || {
                 let store = Arc::clone(&self.store);
+                let obj_path = obj_path.clone();
                 async move { store.head(&obj_path).await }
             },
             false,
@@ -407,9 +478,29 @@ impl S3Storage {
         let store: AmazonS3 = builder
             .build()
             .map_err(|e| CloudStorageBuilderError::Builder(e.to_string()))?;
-        let inner = CloudStorage::new(Arc::new(store), identity, retry)?;
+        Self::with_store(Arc::new(store), cfg)
+    }
+
+    /// Storage for `cfg.uri` over an already configured client, e.g. one for
+    /// an S3-compatible endpoint or an in-memory store in tests.
+    pub fn with_store(
+        store: Arc<dyn ObjectStore>,
+        cfg: &StorageConfig,
+    ) -> Result<Self, CloudStorageBuilderError> {
+        let uri = cfg
+            .uri
+            .as_deref()
+            .ok_or(CloudStorageBuilderError::MissingUri { scheme: "s3" })?;
+        let identity = CloudIdentity::new_s3(uri)?;
+        let inner = CloudStorage::new(store, identity, retry_config_from(cfg))?;
         Ok(Self { inner })
     }
+
+    /// Upload segments of at least `bytes` in parts (default `MULTIPART_THRESHOLD`).
+    pub fn with_multipart_threshold(mut self, bytes: usize) -> Self {
+        self.inner.multipart_threshold = bytes;
+        self
+    }
 }
 
 #[cfg(feature = "gcs")]
@@ -1,12 +1,21 @@
 use std::fs::{self, File};
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use blake3::Hasher;
 use emsqrt_mem::error::{Error as MemError, Result as MemResult};
 use emsqrt_mem::Storage;
 
+/// Marker in the names of files being written; `list` skips them.
+const TEMP_MARKER: &str = ".emsqrt-tmp-";
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 /// Local filesystem storage (rooted at the host filesystem).
+///
+/// Writes go to a temporary file next to the target and are renamed into
+/// place, so readers see either the old or the new contents, never a mix.
 #[derive(Debug, Clone, Default)]
 pub struct FsStorage;
 
@@ -22,12 +31,28 @@ impl Storage for FsStorage {
         if let Some(parent) = p.parent() {
             fs::create_dir_all(parent).map_err(|e| MemError::Storage(format!("mkparent: {e}")))?;
         }
-        let mut f = File::create(p).map_err(|e| MemError::Storage(format!("create: {e}")))?;
-        f.write_all(bytes)
-            .map_err(|e| MemError::Storage(format!("write: {e}")))?;
-        f.flush()
-            .map_err(|e| MemError::Storage(format!("flush: {e}")))?;
-        Ok(())
+        let tmp = format!(
+            "{}{}{}-{}",
+            path,
+            TEMP_MARKER,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let written = File::create(&tmp)
+            .map_err(|e| MemError::Storage(format!("create: {e}")))
+            .and_then(|mut f| {
+                f.write_all(bytes)
+                    .map_err(|e| MemError::Storage(format!("write: {e}")))?;
+                f.flush()
+                    .map_err(|e| MemError::Storage(format!("flush: {e}")))
+            })
+            .and_then(|()| {
+                fs::rename(&tmp, p).map_err(|e| MemError::Storage(format!("rename: {e}")))
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
     }
 
     fn read_range(&self, path: &str, offset: u64, len: usize) -> MemResult<Vec<u8>> {
@@ -36,10 +61,8 @@ impl Storage for FsStorage {
         f.seek(SeekFrom::Start(offset))
             .map_err(|e| MemError::Storage(format!("seek: {e}")))?;
         let mut buf = vec![0u8; len];
-        let n = f
-            .read(&mut buf)
-            .map_err(|e| MemError::Storage(format!("read: {e}")))?;
-        buf.truncate(n);
+        f.read_exact(&mut buf)
+            .map_err(|e| MemError::Storage(format!("read {len} bytes at {offset}: {e}")))?;
         Ok(buf)
     }
 
@@ -74,7 +97,9 @@ impl Storage for FsStorage {
                     if path.is_dir() {
                         visit_dirs(&path, results)?;
                     } else if let Some(s) = path.to_str() {
-                        results.push(s.to_string());
+                        if !s.contains(TEMP_MARKER) {
+                            results.push(s.to_string());
+                        }
                     }
                 }
             }
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use emsqrt_mem::error::{Error as MemError, Result as MemResult};
+use emsqrt_mem::Storage;
+
+/// In-memory storage for tests of spill-heavy code.
+///
+/// Paths are plain keys; `list` matches whole path segments like the
+/// filesystem does. Writes replace an object in one step. Clones share the
+/// same objects.
+#[derive(Debug, Clone, Default)]
+pub struct MemStorage {
+    objects: Arc<RwLock<BTreeMap<String, Arc<Vec<u8>>>>>,
+}
+
+impl MemStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored objects.
+    pub fn len(&self) -> usize {
+        self.objects.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total bytes held across all objects.
+    pub fn total_bytes(&self) -> u64 {
+        self.objects
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|v| v.len() as u64)
+            .sum()
+    }
+
+    fn get(&self, path: &str) -> MemResult<Arc<Vec<u8>>> {
+        self.objects
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(path)
+            .cloned()
+            .ok_or_else(|| MemError::Storage(format!("not found: {path}")))
+    }
+}
+
+impl Storage for MemStorage {
+    fn write(&self, path: &str, bytes: &[u8]) -> MemResult<()> {
+        let data = Arc::new(bytes.to_vec());
+        self.objects
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_string(), data);
+        Ok(())
+    }
+
+    fn read_range(&self, path: &str, offset: u64, len: usize) -> MemResult<Vec<u8>> {
+        let data = self.get(path)?;
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        start
+            .checked_add(len)
+            .and_then(|end| data.get(start..end))
+            .map(|slice| slice.to_vec())
+            .ok_or_else(|| {
+                MemError::Storage(format!(
+                    "read {len} bytes at {offset}: object '{path}' has {} bytes",
+                    data.len()
+                ))
+            })
+    }
+
+    fn delete(&self, path: &str) -> MemResult<()> {
+        self.objects
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(path);
+        Ok(())
+    }
+
+    fn list(&self, prefix: &str) -> MemResult<Vec<String>> {
+        let dir = format!("{}/", prefix.trim_end_matches('/'));
+        Ok(self
+            .objects
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .filter(|k| k.as_str() == prefix || k.starts_with(&dir))
+            .cloned()
+            .collect())
+    }
+
+    fn size(&self, path: &str) -> MemResult<u64> {
+        Ok(self.get(path)?.len() as u64)
+    }
+
+    fn etag(&self, path: &str) -> MemResult<Option<String>> {
+        Ok(self
+            .get(path)
+            .ok()
+            .map(|data| blake3::hash(&data).to_hex().to_string()))
+    }
+}
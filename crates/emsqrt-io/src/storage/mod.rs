@@ -1,18 +1,29 @@
 //! Storage adapters implementing `emsqrt_mem::spill::Storage`.
 //!
 //! - `fs`: Local filesystem (default).
+//! - `mem`: In-memory objects, for tests.
 //! - `cloud`: Cloud object stores (S3/GCS/Azure) built on top of `object_store`.
 //!
 //! Also exposes `RetryConfig` and helper builders that choose the appropriate
-//! storage based on the configured spill URI (e.g. `file:///tmp`, `s3://bucket`).
+//! storage based on the configured spill URI (e.g. `file:///tmp`, `s3://bucket`,
+//! `mem://spill`).
 
 mod fs;
 pub use fs::FsStorage;
 
+mod mem;
+pub use mem::MemStorage;
+
 #[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
 mod cloud;
+#[cfg(feature = "azure")]
+pub use cloud::AzureBlobStorage;
+#[cfg(feature = "gcs")]
+pub use cloud::GcsStorage;
+#[cfg(feature = "s3")]
+pub use cloud::S3Storage;
 #[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
-pub use cloud::{AzureBlobStorage, CloudStorageBuilderError, GcsStorage, S3Storage};
+pub use cloud::{CloudStorageBuilderError, MULTIPART_THRESHOLD};
 
 use std::time::Duration;
 
@@ -87,6 +98,8 @@ pub fn build_storage_from_config(cfg: &StorageConfig) -> Result<Box<dyn Storage>
                 ))
             }
         }
+        // Spill segments kept in process memory (tests, embedding).
+        Some("mem") => Ok(Box::new(MemStorage::new())),
         Some("file") | None => {
             // Default to filesystem (treat URI as file:// or bare path).
             Ok(Box::new(FsStorage::new()))
@@ -0,0 +1,46 @@
+//! Arrow IPC file writer (enabled with `--features arrow`).
+//!
+//! Writes the Arrow IPC *file* format (footer included), readable with
+//! `pyarrow.ipc.open_file` and other Arrow implementations.
+
+use std::fs::File;
+use std::sync::Arc;
+
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::SchemaRef;
+
+use crate::arrow_convert::{emsqrt_to_arrow_schema, row_batch_to_record_batch};
+use crate::error::{Error, Result};
+use emsqrt_core::schema::Schema as EmsqrtSchema;
+use emsqrt_core::types::RowBatch;
+
+pub struct ArrowIpcWriter {
+    writer: FileWriter<File>,
+    schema: SchemaRef,
+}
+
+impl ArrowIpcWriter {
+    /// Create an Arrow IPC file at `path` with the Arrow form of `schema`.
+    pub fn from_emsqrt_schema(path: &str, schema: &EmsqrtSchema) -> Result<Self> {
+        let schema = Arc::new(emsqrt_to_arrow_schema(schema));
+        let file = File::create(path)?;
+        let writer = FileWriter::try_new(file, &schema)
+            .map_err(|e| Error::Other(format!("Failed to create Arrow writer: {}", e)))?;
+        Ok(Self { writer, schema })
+    }
+
+    /// Write a RowBatch as one Arrow record batch.
+    pub fn write_row_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        let record_batch = row_batch_to_record_batch(batch, self.schema.clone())?;
+        self.writer
+            .write(&record_batch)
+            .map_err(|e| Error::Other(format!("Failed to write Arrow batch: {}", e)))
+    }
+
+    /// Write the file footer.
+    pub fn close(mut self) -> Result<()> {
+        self.writer
+            .finish()
+            .map_err(|e| Error::Other(format!("Failed to close Arrow writer: {}", e)))
+    }
+}
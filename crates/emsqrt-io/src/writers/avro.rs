@@ -0,0 +1,132 @@
+//! Avro object container file writer (enabled with `--features avro`).
+//!
+//! Each batch becomes one container block. The sync marker is derived from
+//! the schema, so the same rows always produce the same bytes.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use emsqrt_core::schema::Schema;
+use emsqrt_core::types::{RowBatch, Scalar};
+
+use crate::avro_convert::{
+    emsqrt_to_avro_schema, emsqrt_to_avro_type, encode_value, write_bytes, write_header,
+    write_long, AvroType,
+};
+use crate::error::{Error, Result};
+
+/// Block compression of an Avro file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AvroCodec {
+    #[default]
+    Null,
+    Deflate,
+}
+
+pub struct AvroWriter<W: Write> {
+    writer: BufWriter<W>,
+    /// (column, type, nullable) in schema order.
+    fields: Vec<(String, AvroType, bool)>,
+    codec: AvroCodec,
+    sync: [u8; 16],
+}
+
+impl AvroWriter<File> {
+    /// Create an Avro file at `path` for rows of `schema`.
+    pub fn from_emsqrt_schema(path: &str, schema: &Schema, codec: AvroCodec) -> Result<Self> {
+        Self::to_writer(File::create(path)?, schema, codec)
+    }
+}
+
+impl<W: Write> AvroWriter<W> {
+    /// Write the container header for `schema` to `writer`.
+    pub fn to_writer(writer: W, schema: &Schema, codec: AvroCodec) -> Result<Self> {
+        let schema_json = emsqrt_to_avro_schema(schema)?;
+        let mut sync = [0u8; 16];
+        sync.copy_from_slice(&blake3::hash(schema_json.as_bytes()).as_bytes()[..16]);
+        let mut writer = BufWriter::new(writer);
+        let codec_name = match codec {
+            AvroCodec::Null => "null",
+            AvroCodec::Deflate => "deflate",
+        };
+        write_header(&mut writer, &schema_json, codec_name, &sync)?;
+        let fields = schema
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    f.name.clone(),
+                    emsqrt_to_avro_type(&f.data_type),
+                    f.nullable,
+                )
+            })
+            .collect();
+        Ok(Self {
+            writer,
+            fields,
+            codec,
+            sync,
+        })
+    }
+
+    /// Write `batch` as one block; columns are matched to the schema by name.
+    pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        let rows = batch.num_rows();
+        if rows == 0 {
+            return Ok(());
+        }
+        let columns = self
+            .fields
+            .iter()
+            .map(|(name, _, _)| {
+                batch
+                    .columns
+                    .iter()
+                    .find(|c| c.name == *name)
+                    .ok_or_else(|| Error::Schema(format!("batch has no column '{}'", name)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut data = Vec::new();
+        for row in 0..rows {
+            for ((name, ty, nullable), column) in self.fields.iter().zip(&columns) {
+                let value = column.values.get(row).unwrap_or(&Scalar::Null);
+                match (value, nullable) {
+                    (Scalar::Null, true) => write_long(&mut data, 0),
+                    (Scalar::Null, false) => {
+                        return Err(Error::Schema(format!(
+                            "null in non-nullable avro column '{}'",
+                            name
+                        )))
+                    }
+                    (v, nullable) => {
+                        if *nullable {
+                            write_long(&mut data, 1);
+                        }
+                        encode_value(ty, v, &mut data)
+                            .map_err(|e| Error::Schema(format!("column '{}': {}", name, e)))?;
+                    }
+                }
+            }
+        }
+        if self.codec == AvroCodec::Deflate {
+            let mut enc =
+                flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+            enc.write_all(&data)?;
+            data = enc.finish()?;
+        }
+
+        let mut block = Vec::with_capacity(data.len() + 32);
+        write_long(&mut block, rows as i64);
+        write_bytes(&mut block, &data);
+        block.extend_from_slice(&self.sync);
+        self.writer.write_all(&block)?;
+        Ok(())
+    }
+
+    /// Flush buffered blocks.
+    pub fn close(mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -1,18 +1,23 @@
 //! Streaming CSV writer from `RowBatch`.
 //!
-//! Placeholder implementation: writes header on first batch; all values via `to_string()`.
+//! Writes the header on the first batch; binary values are encoded with the
+//! writer's `BinaryEncoding` (base64 by default). Delimiter and quoting come
+//! from a `CsvSinkConfig` (`,` and quoting where needed by default).
 
 use std::fs::File;
 use std::io::Write;
 
 use csv as csv_crate;
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::{CsvQuoting, CsvSinkConfig};
 use emsqrt_core::types::RowBatch;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 pub struct CsvWriter<W: Write> {
     wtr: csv_crate::Writer<W>,
     wrote_header: bool,
+    binary: BinaryEncoding,
 }
 
 impl CsvWriter<File> {
@@ -27,6 +32,7 @@ impl<W: Write> CsvWriter<W> {
         Self {
             wtr: csv_crate::Writer::from_writer(writer),
             wrote_header: false,
+            binary: BinaryEncoding::default(),
         }
     }
 
@@ -35,9 +41,44 @@ impl<W: Write> CsvWriter<W> {
         Self {
             wtr: csv_crate::Writer::from_writer(writer),
             wrote_header: true,
+            binary: BinaryEncoding::default(),
         }
     }
 
+    /// Write with the delimiter and quoting of `config`, continuing a file
+    /// that already has its header if `skip_header`.
+    pub fn with_config(writer: W, config: &CsvSinkConfig, skip_header: bool) -> Result<Self> {
+        let delimiter = u8::try_from(config.delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "CSV delimiter {:?} is not a single ASCII character",
+                    config.delimiter
+                ))
+            })?;
+        let quote_style = match config.quoting {
+            CsvQuoting::Necessary => csv_crate::QuoteStyle::Necessary,
+            CsvQuoting::Always => csv_crate::QuoteStyle::Always,
+            CsvQuoting::NonNumeric => csv_crate::QuoteStyle::NonNumeric,
+            CsvQuoting::Never => csv_crate::QuoteStyle::Never,
+        };
+        Ok(Self {
+            wtr: csv_crate::WriterBuilder::new()
+                .delimiter(delimiter)
+                .quote_style(quote_style)
+                .from_writer(writer),
+            wrote_header: skip_header,
+            binary: BinaryEncoding::default(),
+        })
+    }
+
+    /// Encode binary values with `binary` instead of base64.
+    pub fn with_binary_encoding(mut self, binary: BinaryEncoding) -> Self {
+        self.binary = binary;
+        self
+    }
+
     pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
         let ncols = batch.columns.len();
         if !self.wrote_header {
@@ -50,7 +91,7 @@ impl<W: Write> CsvWriter<W> {
         for row_idx in 0..nrows {
             let mut row = Vec::with_capacity(ncols);
             for c in &batch.columns {
-                let s = batch_value_to_string(&c.values[row_idx]);
+                let s = batch_value_to_string(&c.values[row_idx], self.binary);
                 row.push(s);
             }
             self.wtr.write_record(&row)?;
@@ -58,9 +99,14 @@ impl<W: Write> CsvWriter<W> {
         self.wtr.flush()?;
         Ok(())
     }
+
+    /// Flush what is buffered and return the underlying writer.
+    pub fn into_inner(self) -> Result<W> {
+        self.wtr.into_inner().map_err(|e| Error::Io(e.into_error()))
+    }
 }
 
-fn batch_value_to_string(v: &emsqrt_core::types::Scalar) -> String {
+fn batch_value_to_string(v: &emsqrt_core::types::Scalar, binary: BinaryEncoding) -> String {
     use emsqrt_core::types::Scalar::*;
     match v {
         Null => "".to_string(),
@@ -70,6 +116,7 @@ fn batch_value_to_string(v: &emsqrt_core::types::Scalar) -> String {
         F32(f) => f.to_string(),
         F64(f) => f.to_string(),
         Str(s) => s.clone(),
-        Bin(b) => format!("[binary {} bytes]", b.len()), // base64 not available
+        Bin(b) => binary.encode(b),
+        Date64(_) | Timestamp(_) | Decimal128(..) => v.to_string(),
     }
 }
@@ -5,12 +5,14 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 
 use crate::error::Result;
+use emsqrt_core::binary::BinaryEncoding;
 use emsqrt_core::types::{RowBatch, Scalar};
 
 pub struct JsonlWriter<W: Write> {
     writer: BufWriter<W>,
     // header order to keep column ordering stable across batches
     columns: Vec<String>,
+    binary: BinaryEncoding,
 }
 
 impl JsonlWriter<File> {
@@ -25,9 +27,16 @@ impl<W: Write> JsonlWriter<W> {
         Self {
             writer: BufWriter::new(writer),
             columns: columns.unwrap_or_default(),
+            binary: BinaryEncoding::default(),
         }
     }
 
+    /// Encode binary values (as JSON strings) with `binary` instead of base64.
+    pub fn with_binary_encoding(mut self, binary: BinaryEncoding) -> Self {
+        self.binary = binary;
+        self
+    }
+
     /// Write a batch as one JSON object per line.
     /// If `columns` was empty, infer it from the first batch.
     pub fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
@@ -40,7 +49,7 @@ impl<W: Write> JsonlWriter<W> {
             for (ci, name) in self.columns.iter().enumerate() {
                 if let Some(col) = batch.columns.get(ci) {
                     let val = &col.values[r];
-                    obj.insert(name.clone(), scalar_to_json(val));
+                    obj.insert(name.clone(), scalar_to_json(val, self.binary));
                 }
             }
             let line = serde_json::to_string(&obj)?;
@@ -51,7 +60,7 @@ impl<W: Write> JsonlWriter<W> {
     }
 }
 
-fn scalar_to_json(v: &Scalar) -> serde_json::Value {
+fn scalar_to_json(v: &Scalar, binary: BinaryEncoding) -> serde_json::Value {
     use Scalar::*;
     match v {
         Null => serde_json::Value::Null,
@@ -61,6 +70,8 @@ fn scalar_to_json(v: &Scalar) -> serde_json::Value {
         F32(f) => serde_json::Value::from(*f as f64),
         F64(f) => serde_json::Value::from(*f),
         Str(s) => serde_json::Value::String(s.clone()),
-        Bin(b) => serde_json::Value::String(format!("[binary {} bytes]", b.len())), // base64 not available
+        Bin(b) => serde_json::Value::String(binary.encode(b)),
+        // ISO text, and decimals as text so that no digits are lost.
+        Date64(_) | Timestamp(_) | Decimal128(..) => serde_json::Value::String(v.to_string()),
     }
 }
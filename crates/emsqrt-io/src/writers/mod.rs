@@ -1,7 +1,299 @@
-//! Streaming writers.
+//! Streaming writers, and the format-writer factory sinks dispatch through.
 
 pub mod csv;
 pub mod jsonl;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "avro")]
+pub mod avro;
 #[cfg(feature = "parquet")]
 pub mod parquet;
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::{CsvSinkConfig, ParquetSinkConfig};
+use emsqrt_core::schema::{Field, Schema};
+use emsqrt_core::types::{RowBatch, Scalar};
+
+use crate::error::{Error, Result};
+
+/// How a writer treats a destination that already has data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+    /// Truncate the destination and start a new file.
+    #[default]
+    Overwrite,
+    /// Continue a non-empty destination (CSV skips its header). Formats with a
+    /// file header or footer (Parquet, Arrow, Avro) cannot be continued and
+    /// return an error.
+    Append,
+}
+
+/// Format settings a sink passes to its writer.
+#[derive(Debug, Clone, Default)]
+pub struct WriterOptions {
+    /// Text encoding of binary values in CSV and JSONL.
+    pub binary: BinaryEncoding,
+    /// Compression, encodings and statistics of Parquet files.
+    pub parquet: ParquetSinkConfig,
+    /// Delimiter, quoting and compression of CSV files.
+    pub csv: CsvSinkConfig,
+}
+
+/// A sink's file, written as is or through a gzip encoder.
+pub enum OutputFile {
+    Plain(File),
+    Gzip(flate2::write::GzEncoder<File>),
+}
+
+impl OutputFile {
+    /// Wrap `file`, gzipping at `level` (0-9) if given.
+    pub fn new(file: File, gzip_level: Option<u32>) -> Self {
+        match gzip_level {
+            Some(level) => Self::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::new(level.min(9)),
+            )),
+            None => Self::Plain(file),
+        }
+    }
+
+    /// Flush, writing the gzip trailer if compressed.
+    pub fn finish(self) -> Result<()> {
+        match self {
+            Self::Plain(mut file) => file.flush()?,
+            Self::Gzip(encoder) => encoder.finish()?.flush()?,
+        }
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// A format writer that stays open across the batches of one sink.
+pub trait BatchWriter: Send {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()>;
+
+    /// Bytes the writer holds in memory between writes (an open Parquet row
+    /// group); sinks charge them to the memory budget.
+    fn buffered_bytes(&self) -> usize {
+        0
+    }
+
+    /// Write out whatever `buffered_bytes` holds, if the format allows.
+    fn flush_buffered(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flush and finalize the file (footers for Parquet/Arrow).
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// Open a writer for `format` (`csv`, `jsonl`, `parquet`, `arrow`, `avro`) at `path`.
+///
+/// Parquet, Arrow and Avro files take their schema from `first`, the first batch
+/// the sink will write. CSV and JSONL encode binary values with `opts.binary`;
+/// the other formats store them natively. CSV files are gzipped (a new gzip
+/// member when appending) as `opts.csv` says.
+pub fn open_writer(
+    format: &str,
+    path: &str,
+    mode: WriteMode,
+    first: &RowBatch,
+    opts: &WriterOptions,
+) -> Result<Box<dyn BatchWriter>> {
+    let binary = opts.binary;
+    let continues = mode == WriteMode::Append
+        && std::fs::metadata(path)
+            .map(|m| m.len() > 0)
+            .unwrap_or(false);
+    let open = || -> Result<File> {
+        if continues {
+            Ok(OpenOptions::new().append(true).open(path)?)
+        } else {
+            Ok(File::create(path)?)
+        }
+    };
+    let no_append = |format: &str| {
+        if continues {
+            return Err(Error::Config(format!(
+                "{} sink cannot append to existing file '{}'",
+                format, path
+            )));
+        }
+        Ok(())
+    };
+
+    match format {
+        "csv" => {
+            let gzip = opts
+                .csv
+                .gzip(path)
+                .then(|| opts.csv.compression_level.unwrap_or(6));
+            let file = OutputFile::new(open()?, gzip);
+            Ok(Box::new(
+                csv::CsvWriter::with_config(file, &opts.csv, continues)?
+                    .with_binary_encoding(binary),
+            ))
+        }
+        "jsonl" => Ok(Box::new(
+            jsonl::JsonlWriter::to_writer(open()?, None).with_binary_encoding(binary),
+        )),
+        #[cfg(feature = "parquet")]
+        "parquet" => {
+            no_append(format)?;
+            let schema = infer_schema(first)?;
+            opts.parquet
+                .check_columns(schema.fields.iter().map(|f| f.name.as_str()))
+                .map_err(Error::Config)?;
+            Ok(Box::new(
+                parquet::ParquetWriter::from_emsqrt_schema_with_config(
+                    path,
+                    &schema,
+                    &opts.parquet,
+                )?,
+            ))
+        }
+        #[cfg(feature = "arrow")]
+        "arrow" => {
+            no_append(format)?;
+            let schema = infer_schema(first)?;
+            Ok(Box::new(arrow::ArrowIpcWriter::from_emsqrt_schema(
+                path, &schema,
+            )?))
+        }
+        #[cfg(feature = "avro")]
+        "avro" => {
+            no_append(format)?;
+            let schema = infer_schema(first)?;
+            Ok(Box::new(avro::AvroWriter::from_emsqrt_schema(
+                path,
+                &schema,
+                avro::AvroCodec::Null,
+            )?))
+        }
+        #[cfg(not(feature = "parquet"))]
+        "parquet" => {
+            let _ = (no_append, first);
+            Err(Error::Unimplemented(
+                "parquet sink requires the `parquet` feature",
+            ))
+        }
+        #[cfg(not(feature = "arrow"))]
+        "arrow" => Err(Error::Unimplemented(
+            "arrow sink requires the `arrow` feature",
+        )),
+        #[cfg(not(feature = "avro"))]
+        "avro" => Err(Error::Unimplemented(
+            "avro sink requires the `avro` feature",
+        )),
+        other => Err(Error::Config(format!("unsupported sink format: {}", other))),
+    }
+}
+
+/// Schema of a batch, typed from each column's first non-null value (Utf8 if
+/// all null). Every field is nullable.
+pub fn infer_schema(batch: &RowBatch) -> Result<Schema> {
+    if batch.columns.is_empty() {
+        return Err(Error::Schema(
+            "cannot infer a schema from a batch with no columns".into(),
+        ));
+    }
+    let fields = batch
+        .columns
+        .iter()
+        .map(|col| {
+            let data_type = col
+                .values
+                .iter()
+                .find(|v| !matches!(v, Scalar::Null))
+                .map_or(emsqrt_core::schema::DataType::Utf8, Scalar::data_type);
+            Field::new(&col.name, data_type, true)
+        })
+        .collect();
+    Ok(Schema::new(fields))
+}
+
+impl BatchWriter for csv::CsvWriter<OutputFile> {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        csv::CsvWriter::write_batch(self, batch)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.into_inner()?.finish()
+    }
+}
+
+impl<W: std::io::Write + Send> BatchWriter for jsonl::JsonlWriter<W> {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        jsonl::JsonlWriter::write_batch(self, batch)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl BatchWriter for parquet::ParquetWriter {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        self.write_row_batch(batch)
+    }
+
+    fn buffered_bytes(&self) -> usize {
+        parquet::ParquetWriter::buffered_bytes(self)
+    }
+
+    fn flush_buffered(&mut self) -> Result<()> {
+        self.flush()
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.close()
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl BatchWriter for arrow::ArrowIpcWriter {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        if batch.num_rows() == 0 {
+            return Ok(());
+        }
+        self.write_row_batch(batch)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.close()
+    }
+}
+
+#[cfg(feature = "avro")]
+impl<W: std::io::Write + Send> BatchWriter for avro::AvroWriter<W> {
+    fn write_batch(&mut self, batch: &RowBatch) -> Result<()> {
+        avro::AvroWriter::write_batch(self, batch)
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.close()
+    }
+}
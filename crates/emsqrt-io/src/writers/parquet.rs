@@ -3,7 +3,9 @@
 //! Supports:
 //! - Writing Arrow RecordBatch to Parquet files
 //! - Compression codecs (SNAPPY, GZIP, ZSTD, LZ4, UNCOMPRESSED)
-//! - Configurable row group size
+//! - Row groups flushed by row count and by buffered bytes
+//! - Per-column compression, encodings, dictionaries and statistics
+//!   (`ParquetSinkConfig`, from the sink's engine config)
 //! - Schema writing
 
 #[cfg(feature = "parquet")]
@@ -13,11 +15,11 @@ use arrow_schema::SchemaRef;
 #[cfg(feature = "parquet")]
 use parquet::arrow::ArrowWriter;
 #[cfg(feature = "parquet")]
-use parquet::basic::{Compression, ZstdLevel};
+use parquet::basic::{Compression, Encoding, GzipLevel, ZstdLevel};
 #[cfg(feature = "parquet")]
-use parquet::file::properties::WriterProperties;
+use parquet::file::properties::{EnabledStatistics, WriterProperties};
 #[cfg(feature = "parquet")]
-use parquet::file::properties::WriterPropertiesBuilder;
+use parquet::schema::types::ColumnPath;
 #[cfg(feature = "parquet")]
 use std::fs::File;
 #[cfg(feature = "parquet")]
@@ -25,6 +27,7 @@ use std::sync::Arc;
 
 use crate::arrow_convert::{emsqrt_to_arrow_schema, row_batch_to_record_batch};
 use crate::error::{Error, Result};
+use emsqrt_core::config::{ParquetCodec, ParquetEncoding, ParquetSinkConfig, ParquetStatistics};
 use emsqrt_core::schema::Schema as EmsqrtSchema;
 use emsqrt_core::types::RowBatch;
 
@@ -70,11 +73,14 @@ impl ParquetCompression {
 pub struct ParquetWriter {
     writer: ArrowWriter<File>,
     schema: SchemaRef,
+    /// Flush the open row group once it buffers this many bytes.
+    max_row_group_bytes: Option<usize>,
 }
 
 #[cfg(feature = "parquet")]
 impl ParquetWriter {
-    /// Create a new ParquetWriter with default settings (Snappy compression, 128MB row groups).
+    /// Create a new ParquetWriter with default settings (Snappy compression,
+    /// row groups of 1Mi rows).
     pub fn to_path(path: &str, schema: SchemaRef) -> Result<Self> {
         Self::to_path_with_options(path, schema, ParquetCompression::default(), None)
     }
@@ -104,7 +110,7 @@ impl ParquetWriter {
     /// * `path` - Path to the Parquet file
     /// * `schema` - Arrow schema for the data
     /// * `compression` - Compression codec to use
-    /// * `row_group_size` - Optional row group size in bytes (default: 128MB)
+    /// * `row_group_size` - Optional rows per row group (default: 1Mi)
     pub fn to_path_with_options(
         path: &str,
         schema: SchemaRef,
@@ -117,20 +123,85 @@ impl ParquetWriter {
         let mut props_builder =
             WriterProperties::builder().set_compression(compression.to_parquet_compression());
 
-        // Set row group size if specified
-        if let Some(size_bytes) = row_group_size {
-            props_builder = props_builder.set_max_row_group_size(size_bytes);
-        } else {
-            // Default: 128MB row groups
-            props_builder = props_builder.set_max_row_group_size(128 * 1024 * 1024);
+        if let Some(rows) = row_group_size {
+            props_builder = props_builder.set_max_row_group_size(rows);
         }
 
-        let props = props_builder.build();
+        Self::with_properties(file, schema, props_builder.build())
+    }
 
+    /// Create a new ParquetWriter from an emsqrt-core Schema with a sink's
+    /// file-wide and per-column settings.
+    pub fn from_emsqrt_schema_with_config(
+        path: &str,
+        schema: &EmsqrtSchema,
+        config: &ParquetSinkConfig,
+    ) -> Result<Self> {
+        let arrow_schema = Arc::new(emsqrt_to_arrow_schema(schema));
+        let file = File::create(path).map_err(Error::Io)?;
+
+        let mut props = WriterProperties::builder()
+            .set_compression(codec(config.compression, config.compression_level)?)
+            .set_statistics_enabled(statistics(config.statistics));
+        if let Some(dictionary) = config.dictionary {
+            props = props.set_dictionary_enabled(dictionary);
+        }
+        if let Some(rows) = config.row_group_rows {
+            props = props.set_max_row_group_size(rows);
+        }
+        for (name, col) in &config.columns {
+            let path = || ColumnPath::from(name.as_str());
+            if col.compression.is_some() || col.compression_level.is_some() {
+                let kind = col.compression.unwrap_or(config.compression);
+                let level = col.compression_level.or(config.compression_level);
+                props = props.set_column_compression(path(), codec(kind, level)?);
+            }
+            if let Some(encoding) = col.encoding {
+                props = props.set_column_encoding(path(), value_encoding(encoding));
+            }
+            if let Some(dictionary) = col.dictionary {
+                props = props.set_column_dictionary_enabled(path(), dictionary);
+            }
+            if let Some(stats) = col.statistics {
+                props = props.set_column_statistics_enabled(path(), statistics(stats));
+            }
+        }
+        Ok(Self::with_properties(file, arrow_schema, props.build())?
+            .with_max_row_group_bytes(config.row_group_bytes))
+    }
+
+    fn with_properties(file: File, schema: SchemaRef, props: WriterProperties) -> Result<Self> {
         let writer = ArrowWriter::try_new(file, schema.clone(), Some(props))
             .map_err(|e| Error::Other(format!("Failed to create Parquet writer: {}", e)))?;
 
-        Ok(Self { writer, schema })
+        Ok(Self {
+            writer,
+            schema,
+            max_row_group_bytes: None,
+        })
+    }
+
+    /// Also end a row group once it buffers `bytes` (None = by rows only).
+    pub fn with_max_row_group_bytes(mut self, bytes: Option<usize>) -> Self {
+        self.max_row_group_bytes = bytes;
+        self
+    }
+
+    /// Bytes held in memory for the row group being written.
+    pub fn buffered_bytes(&self) -> usize {
+        self.writer.memory_size()
+    }
+
+    /// Row groups written to the file so far.
+    pub fn row_groups_written(&self) -> usize {
+        self.writer.flushed_row_groups().len()
+    }
+
+    /// Write the open row group to the file, releasing its buffers.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .map_err(|e| Error::Other(format!("Failed to flush Parquet row group: {}", e)))
     }
 
     /// Write a RecordBatch to the Parquet file.
@@ -138,7 +209,10 @@ impl ParquetWriter {
         self.writer
             .write(batch)
             .map_err(|e| Error::Other(format!("Failed to write Parquet batch: {}", e)))?;
-        Ok(())
+        match self.max_row_group_bytes {
+            Some(max) if self.buffered_bytes() >= max => self.flush(),
+            _ => Ok(()),
+        }
     }
 
     /// Write a RowBatch to the Parquet file.
@@ -165,5 +239,44 @@ impl ParquetWriter {
     }
 }
 
+/// Parquet codec for a configured codec and optional level.
+#[cfg(feature = "parquet")]
+fn codec(kind: ParquetCodec, level: Option<u32>) -> Result<Compression> {
+    let bad_level = |e| Error::Config(format!("{:?} compression_level: {}", kind, e));
+    Ok(match kind {
+        ParquetCodec::Uncompressed => Compression::UNCOMPRESSED,
+        ParquetCodec::Snappy => Compression::SNAPPY,
+        ParquetCodec::Lz4 => Compression::LZ4,
+        ParquetCodec::Gzip => Compression::GZIP(match level {
+            Some(level) => GzipLevel::try_new(level).map_err(bad_level)?,
+            None => GzipLevel::default(),
+        }),
+        ParquetCodec::Zstd => Compression::ZSTD(match level {
+            Some(level) => ZstdLevel::try_new(level as i32).map_err(bad_level)?,
+            None => ZstdLevel::default(),
+        }),
+    })
+}
+
+#[cfg(feature = "parquet")]
+fn value_encoding(encoding: ParquetEncoding) -> Encoding {
+    match encoding {
+        ParquetEncoding::Plain => Encoding::PLAIN,
+        ParquetEncoding::DeltaBinaryPacked => Encoding::DELTA_BINARY_PACKED,
+        ParquetEncoding::DeltaLengthByteArray => Encoding::DELTA_LENGTH_BYTE_ARRAY,
+        ParquetEncoding::DeltaByteArray => Encoding::DELTA_BYTE_ARRAY,
+        ParquetEncoding::ByteStreamSplit => Encoding::BYTE_STREAM_SPLIT,
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn statistics(stats: ParquetStatistics) -> EnabledStatistics {
+    match stats {
+        ParquetStatistics::None => EnabledStatistics::None,
+        ParquetStatistics::Chunk => EnabledStatistics::Chunk,
+        ParquetStatistics::Page => EnabledStatistics::Page,
+    }
+}
+
 #[cfg(not(feature = "parquet"))]
 compile_error!("parquet.rs was compiled without the `parquet` feature; enable `--features parquet` or exclude this module.");
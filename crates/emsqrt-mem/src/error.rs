@@ -13,6 +13,22 @@ pub enum Error {
         used: usize,
     },
 
+    #[error("timed out after {waited_ms} ms waiting for {requested} bytes (tag '{tag}')")]
+    ReservationTimeout {
+        tag: &'static str,
+        requested: usize,
+        waited_ms: u64,
+    },
+
+    #[error("memory reservation deadlock for tag '{tag}': {waiters} waiters want {waiting_bytes} bytes in total, every holder is waiting, capacity {capacity}")]
+    Deadlock {
+        tag: &'static str,
+        requested: usize,
+        waiters: usize,
+        waiting_bytes: usize,
+        capacity: usize,
+    },
+
     #[error("allocation failed for {bytes} bytes (tag '{tag}')")]
     AllocFailed { tag: &'static str, bytes: usize },
 
@@ -30,6 +46,20 @@ pub enum Error {
 
     #[error("checksum mismatch")]
     ChecksumMismatch,
+
+    #[error("spill limit exceeded: writing {requested} bytes would exceed {limit} (already wrote {written})")]
+    SpillLimitExceeded {
+        limit: u64,
+        written: u64,
+        requested: u64,
+    },
+
+    #[error("spill quota exceeded: writing {requested} bytes would hold more than {quota} bytes in spill storage ({live} already held)")]
+    SpillQuotaExceeded {
+        quota: u64,
+        live: u64,
+        requested: u64,
+    },
 }
 
 impl Error {
@@ -85,6 +115,30 @@ impl Error {
                     "Supported codecs: uncompressed, zstd (if feature enabled), lz4 (if feature enabled)".into(),
                 ]
             }
+            Error::ReservationTimeout { .. } => {
+                vec![
+                    "Other blocks held the memory for longer than the wait timeout".into(),
+                    "Raise memory_wait_ms or lower max_parallel_tasks".into(),
+                ]
+            }
+            Error::Deadlock { .. } => {
+                vec![
+                    "Every block holding memory was waiting for more; none could finish".into(),
+                    "Lower max_parallel_tasks or increase memory_cap_bytes".into(),
+                ]
+            }
+            Error::SpillLimitExceeded { limit, .. } => {
+                vec![
+                    format!("Spill storage is capped at {} bytes for this run", limit),
+                    "Raise sandbox.max_spill_bytes or reduce the input size".into(),
+                ]
+            }
+            Error::SpillQuotaExceeded { quota, .. } => {
+                vec![
+                    format!("Spill storage may hold at most {} bytes at once", quota),
+                    "Raise spill_quota_bytes (--spill-quota) or give the run more memory so it spills less".into(),
+                ]
+            }
             _ => vec![],
         }
     }
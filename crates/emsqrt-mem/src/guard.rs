@@ -2,26 +2,113 @@
 //!
 //! Downstream crates must *always* acquire a guard before allocating. Dropping
 //! the guard returns the bytes to the budget (panic-safe).
+//!
+//! A reservation that does not fit either fails at once (the default) or
+//! waits for other holders to release bytes ([`ReservationMode::Wait`],
+//! [`MemoryBudgetImpl::acquire_timeout`]). Waiters are woken on every release.
+//! Bytes are attributed to the thread that acquired them, so when every thread
+//! holding memory is itself waiting and no request fits, nobody can make
+//! progress: the waiter that notices gives up with [`Error::Deadlock`] instead
+//! of sleeping out its timeout.
 
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
 
 use emsqrt_core::budget::{BudgetGuard, MemoryBudget};
 
+use crate::error::{Error, Result};
+
+/// How often a waiter re-checks the budget and the deadlock condition even
+/// without a release notification.
+const WAIT_POLL: Duration = Duration::from_millis(10);
+
+/// What `try_acquire` does when a reservation does not fit right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReservationMode {
+    /// Return `None` immediately.
+    #[default]
+    Fail,
+    /// Wait up to the given time for bytes to be released.
+    Wait(Duration),
+}
+
+/// Who holds and who waits for budget bytes, per thread.
+#[derive(Default)]
+struct Holders {
+    held: HashMap<ThreadId, usize>,
+    /// Bytes each waiting thread asked for.
+    waiting: HashMap<ThreadId, usize>,
+}
+
 /// Shared inner state for the budget.
 struct BudgetInner {
     capacity: usize,
     used: AtomicUsize,
+    /// Highest `used` since the last [`MemoryBudgetImpl::reset_peak`].
+    peak: AtomicUsize,
+    holders: Mutex<Holders>,
+    released: Condvar,
+    /// Budget that every reservation is also charged to (see
+    /// [`MemoryBudgetImpl::child`]).
+    parent: Option<Arc<BudgetInner>>,
 }
 
 impl BudgetInner {
-    fn new(capacity: usize) -> Self {
+    fn new(capacity: usize, parent: Option<Arc<BudgetInner>>) -> Self {
         Self {
             capacity,
             used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            holders: Mutex::new(Holders::default()),
+            released: Condvar::new(),
+            parent,
         }
     }
 
+    fn holders(&self) -> MutexGuard<'_, Holders> {
+        self.holders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Reserve `bytes` for `owner` if they fit now.
+    fn acquire_for(&self, owner: ThreadId, bytes: usize) -> bool {
+        if !self.try_acquire(bytes) {
+            return false;
+        }
+        *self.holders().held.entry(owner).or_insert(0) += bytes;
+        true
+    }
+
+    /// Return `bytes` held by `owner` and wake waiters.
+    fn release_from(&self, owner: ThreadId, bytes: usize) {
+        self.release(bytes);
+        let mut holders = self.holders();
+        if let Some(held) = holders.held.get_mut(&owner) {
+            *held = held.saturating_sub(bytes);
+            if *held == 0 {
+                holders.held.remove(&owner);
+            }
+        }
+        if !holders.waiting.is_empty() {
+            self.released.notify_all();
+        }
+    }
+
+    /// True if no waiter's request fits and every byte in use is held by a
+    /// waiting thread, so no release can come.
+    fn deadlocked(&self, holders: &Holders) -> bool {
+        let used = self.used.load(Ordering::Acquire);
+        let free = self.capacity.saturating_sub(used);
+        let held_by_waiters: usize = holders
+            .waiting
+            .keys()
+            .filter_map(|t| holders.held.get(t))
+            .sum();
+        holders.waiting.values().all(|&want| want > free) && held_by_waiters >= used
+    }
+
     fn try_acquire(&self, bytes: usize) -> bool {
         loop {
             let cur = self.used.load(Ordering::Relaxed);
@@ -34,6 +121,11 @@ impl BudgetInner {
                 .compare_exchange(cur, next, Ordering::AcqRel, Ordering::Relaxed)
                 .is_ok()
             {
+                if self.parent.as_ref().is_some_and(|p| !p.try_acquire(bytes)) {
+                    self.used.fetch_sub(bytes, Ordering::AcqRel);
+                    return false;
+                }
+                self.peak.fetch_max(next, Ordering::Relaxed);
                 return true;
             }
         }
@@ -41,6 +133,12 @@ impl BudgetInner {
 
     fn release(&self, bytes: usize) {
         self.used.fetch_sub(bytes, Ordering::AcqRel);
+        if let Some(parent) = &self.parent {
+            parent.release(bytes);
+            if !parent.holders().waiting.is_empty() {
+                parent.released.notify_all();
+            }
+        }
     }
 }
 
@@ -48,12 +146,120 @@ impl BudgetInner {
 #[derive(Clone)]
 pub struct MemoryBudgetImpl {
     inner: Arc<BudgetInner>,
+    mode: ReservationMode,
 }
 
 impl MemoryBudgetImpl {
     pub fn new(capacity_bytes: usize) -> Self {
         Self {
-            inner: Arc::new(BudgetInner::new(capacity_bytes)),
+            inner: Arc::new(BudgetInner::new(capacity_bytes, None)),
+            mode: ReservationMode::Fail,
+        }
+    }
+
+    /// A budget of `capacity_bytes` whose reservations also count against
+    /// this one, so they fail (or wait) when either is full. Its bytes are
+    /// not attributed to threads in this budget, which therefore never
+    /// reports a deadlock involving them.
+    pub fn child(&self, capacity_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(BudgetInner::new(
+                capacity_bytes,
+                Some(Arc::clone(&self.inner)),
+            )),
+            mode: self.mode,
+        }
+    }
+
+    /// Set what `try_acquire` does when a reservation does not fit. Clones
+    /// share the budget but each keeps its own mode.
+    pub fn with_reservation_mode(mut self, mode: ReservationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn reservation_mode(&self) -> ReservationMode {
+        self.mode
+    }
+
+    /// Reserve `bytes`, waiting for other holders to release memory.
+    ///
+    /// `timeout` of `None` waits until the bytes fit. Fails at once with
+    /// `BudgetExceeded` if `bytes` exceeds the capacity, with `Deadlock` if
+    /// every holder is waiting and no request fits, and with
+    /// `ReservationTimeout` when the timeout passes.
+    pub fn acquire_timeout(
+        &self,
+        bytes: usize,
+        tag: &'static str,
+        timeout: Option<Duration>,
+    ) -> Result<BudgetGuardImpl> {
+        let owner = thread::current().id();
+        if bytes == 0 || self.inner.acquire_for(owner, bytes) {
+            return Ok(self.guard(owner, bytes, tag));
+        }
+        if bytes > self.inner.capacity {
+            return Err(Error::BudgetExceeded {
+                tag,
+                requested: bytes,
+                capacity: self.inner.capacity,
+                used: self.used_bytes(),
+            });
+        }
+
+        let started = Instant::now();
+        let mut holders = self.inner.holders();
+        holders.waiting.insert(owner, bytes);
+        let result = loop {
+            // Re-check under the lock: a release between the first attempt
+            // and registering as a waiter would otherwise be missed.
+            if self.inner.try_acquire(bytes) {
+                *holders.held.entry(owner).or_insert(0) += bytes;
+                break Ok(self.guard(owner, bytes, tag));
+            }
+            if self.inner.deadlocked(&holders) {
+                break Err(Error::Deadlock {
+                    tag,
+                    requested: bytes,
+                    waiters: holders.waiting.len(),
+                    waiting_bytes: holders.waiting.values().sum(),
+                    capacity: self.inner.capacity,
+                });
+            }
+            let elapsed = started.elapsed();
+            let slice = match timeout {
+                Some(limit) if elapsed >= limit => {
+                    break Err(Error::ReservationTimeout {
+                        tag,
+                        requested: bytes,
+                        waited_ms: elapsed.as_millis() as u64,
+                    })
+                }
+                Some(limit) => (limit - elapsed).min(WAIT_POLL),
+                None => WAIT_POLL,
+            };
+            holders = self
+                .inner
+                .released
+                .wait_timeout(holders, slice)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        };
+        holders.waiting.remove(&owner);
+        result
+    }
+
+    /// Number of threads currently waiting for a reservation.
+    pub fn waiting(&self) -> usize {
+        self.inner.holders().waiting.len()
+    }
+
+    fn guard(&self, owner: ThreadId, bytes: usize, tag: &'static str) -> BudgetGuardImpl {
+        BudgetGuardImpl {
+            inner: Arc::clone(&self.inner),
+            bytes,
+            tag,
+            owner,
         }
     }
 
@@ -65,6 +271,17 @@ impl MemoryBudgetImpl {
     pub fn capacity_bytes(&self) -> usize {
         self.inner.capacity
     }
+
+    /// Most bytes reserved at once since the budget was created or
+    /// [`reset_peak`](Self::reset_peak) was last called.
+    pub fn peak_bytes(&self) -> usize {
+        self.inner.peak.load(Ordering::Relaxed)
+    }
+
+    /// Start a new peak measurement from the bytes reserved now.
+    pub fn reset_peak(&self) {
+        self.inner.peak.store(self.used_bytes(), Ordering::Relaxed);
+    }
 }
 
 /// RAII guard that accounts for a number of bytes.
@@ -73,12 +290,14 @@ pub struct BudgetGuardImpl {
     inner: Arc<BudgetInner>,
     bytes: usize,
     tag: &'static str,
+    /// Thread that acquired the bytes (for deadlock detection).
+    owner: ThreadId,
 }
 
 impl Drop for BudgetGuardImpl {
     fn drop(&mut self) {
         if self.bytes > 0 {
-            self.inner.release(self.bytes);
+            self.inner.release_from(self.owner, self.bytes);
             // NOTE: do not log here to keep drop path fast.
             self.bytes = 0;
         }
@@ -108,13 +327,13 @@ impl BudgetGuardImpl {
         if new_bytes < self.bytes {
             // Shrink: always succeeds
             let delta = self.bytes - new_bytes;
-            self.inner.release(delta);
+            self.inner.release_from(self.owner, delta);
             self.bytes = new_bytes;
             true
         } else {
             // Grow: try to acquire the additional bytes
             let delta = new_bytes - self.bytes;
-            if self.inner.try_acquire(delta) {
+            if self.inner.acquire_for(self.owner, delta) {
                 self.bytes = new_bytes;
                 true
             } else {
@@ -128,21 +347,13 @@ impl MemoryBudget for MemoryBudgetImpl {
     type Guard = BudgetGuardImpl;
 
     fn try_acquire(&self, bytes: usize, tag: &'static str) -> Option<Self::Guard> {
-        if bytes == 0 {
-            return Some(BudgetGuardImpl {
-                inner: Arc::clone(&self.inner),
-                bytes: 0,
-                tag,
-            });
-        }
-        if self.inner.try_acquire(bytes) {
-            Some(BudgetGuardImpl {
-                inner: Arc::clone(&self.inner),
-                bytes,
-                tag,
-            })
-        } else {
-            None
+        match self.mode {
+            ReservationMode::Fail => {
+                let owner = thread::current().id();
+                (bytes == 0 || self.inner.acquire_for(owner, bytes))
+                    .then(|| self.guard(owner, bytes, tag))
+            }
+            ReservationMode::Wait(timeout) => self.acquire_timeout(bytes, tag, Some(timeout)).ok(),
         }
     }
 
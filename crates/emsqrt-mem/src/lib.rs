@@ -14,6 +14,9 @@ pub mod pool;
 pub mod spill;
 pub mod tracking;
 
-pub use guard::{BudgetGuardImpl, MemoryBudgetImpl};
+#[cfg(feature = "testkit")]
+pub mod testkit;
+
+pub use guard::{BudgetGuardImpl, MemoryBudgetImpl, ReservationMode};
 pub use pool::{BufferPool, OwnedBuf};
 pub use spill::{Codec, SpillManager, Storage};
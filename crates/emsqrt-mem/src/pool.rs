@@ -32,9 +32,8 @@ impl OwnedBuf {
             })?;
 
         // Safety note: allocation can still fail even if we acquired budget bytes.
-        let mut buf = Vec::with_capacity(len);
         // Initialize length (zeroed)
-        buf.resize(len, 0u8);
+        let buf = vec![0u8; len];
 
         Ok(Self { guard, buf })
     }
@@ -23,22 +23,32 @@ impl Codec {
             _ => Err(Error::CodecUnsupported("unknown")),
         }
     }
+
+    /// Whether this build can compress and decompress with the codec.
+    pub fn is_available(self) -> bool {
+        match self {
+            Codec::None => true,
+            Codec::Zstd => cfg!(feature = "zstd"),
+            Codec::Lz4 => cfg!(feature = "lz4"),
+        }
+    }
 }
 
-pub fn compress(codec: Codec, input: &[u8]) -> Result<Vec<u8>> {
+/// Compress `input`; `level` applies to zstd only.
+pub fn compress(codec: Codec, level: i32, input: &[u8]) -> Result<Vec<u8>> {
     match codec {
         Codec::None => Ok(input.to_vec()),
         Codec::Zstd => {
             #[cfg(feature = "zstd")]
             {
-                let lvl = 3; // TODO: tune / make configurable
                 let mut out = Vec::new();
-                zstd::stream::copy_encode(input, &mut out, lvl)
+                zstd::stream::copy_encode(input, &mut out, level)
                     .map_err(|e| Error::Codec(format!("zstd: {e}")))?;
                 Ok(out)
             }
             #[cfg(not(feature = "zstd"))]
             {
+                let _ = level;
                 Err(Error::CodecUnsupported("zstd"))
             }
         }
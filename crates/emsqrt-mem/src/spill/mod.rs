@@ -6,10 +6,11 @@ pub mod codec;
 pub mod segment;
 
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 use emsqrt_core::budget::MemoryBudget;
-use emsqrt_core::id::SpillId;
+use emsqrt_core::id::{IdAllocator, SpillId};
 use emsqrt_core::types::RowBatch;
 
 use crate::error::{Error, Result};
@@ -51,9 +52,26 @@ pub trait Storage: Send + Sync {
 pub struct SpillManager {
     storage: Box<dyn Storage>,
     codec: Codec,
+    level: i32,
     root_dir: String,
+    /// Prefix of this manager's segment files, unique to it, so managers
+    /// sharing a root never write the same file.
+    instance: String,
     next_run: AtomicU32,
     segments: HashMap<SegmentName, SegmentMeta>,
+    ids: IdAllocator,
+    spill_limit: Option<u64>,
+    spill_quota: Option<u64>,
+    bytes_written: u64,
+    /// `bytes_written` when the current run began; the spill limit counts from here.
+    run_start_written: u64,
+    payload_bytes: u64,
+    bytes_read: AtomicU64,
+    /// Bytes of the segments in storage: tracked ones and orphans.
+    live_bytes: u64,
+    peak_bytes: u64,
+    /// Segments whose metadata was dropped but whose delete failed.
+    orphans: Vec<(String, u64)>,
 }
 
 impl SpillManager {
@@ -62,12 +80,104 @@ impl SpillManager {
         Self {
             storage,
             codec,
+            level: 3,
             root_dir,
+            instance: instance_prefix(),
             next_run: AtomicU32::new(0),
             segments: HashMap::new(),
+            ids: IdAllocator::default(),
+            spill_limit: None,
+            spill_quota: None,
+            bytes_written: 0,
+            run_start_written: 0,
+            payload_bytes: 0,
+            bytes_read: AtomicU64::new(0),
+            live_bytes: 0,
+            peak_bytes: 0,
+            orphans: Vec::new(),
         }
     }
 
+    /// Compress with `level` (zstd only; default 3).
+    pub fn with_compression_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Codec new segments are written with.
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Cap the total bytes written per run (None = unlimited): every byte
+    /// counts, even of segments since deleted. See [`begin_run`](Self::begin_run).
+    pub fn with_spill_limit(mut self, limit: Option<u64>) -> Self {
+        self.spill_limit = limit;
+        self
+    }
+
+    /// Cap the bytes held in storage at once (None = unlimited). Unlike the
+    /// spill limit, deleting a segment frees its bytes again.
+    ///
+    /// With both set, a write must pass both; the limit is checked first,
+    /// so a write over both fails with [`Error::SpillLimitExceeded`]. The
+    /// limit counts only this run's writes while the quota counts every
+    /// segment still held, including those an earlier run left behind, so
+    /// even a quota at or above the limit can be reached.
+    pub fn with_spill_quota(mut self, quota: Option<u64>) -> Self {
+        self.spill_quota = quota;
+        self
+    }
+
+    /// Bytes of the segments currently in storage.
+    pub fn live_bytes(&self) -> u64 {
+        self.live_bytes
+    }
+
+    /// Most bytes held in storage at once since creation or the last
+    /// [`reset_peak`](Self::reset_peak).
+    pub fn peak_bytes(&self) -> u64 {
+        self.peak_bytes
+    }
+
+    /// Start a new high-watermark from the bytes held now.
+    pub fn reset_peak(&mut self) {
+        self.peak_bytes = self.live_bytes;
+    }
+
+    /// Start a run: a new high-watermark, and the spill limit counts the
+    /// bytes written from here on.
+    pub fn begin_run(&mut self) {
+        self.reset_peak();
+        self.run_start_written = self.bytes_written;
+    }
+
+    /// Total segment bytes written so far (headers and compressed payloads).
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Total bytes of the segments written so far before compression.
+    pub fn uncompressed_bytes_written(&self) -> u64 {
+        self.payload_bytes
+    }
+
+    /// Total segment bytes read back so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// Use a run-scoped id allocator (seeded from the engine config).
+    pub fn with_id_allocator(mut self, ids: IdAllocator) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Allocate a fresh spill id for an operator invocation.
+    pub fn next_spill_id(&self) -> SpillId {
+        self.ids.next_spill_id()
+    }
+
     /// Write a RowBatch to storage and return its metadata.
     ///
     /// Steps:
@@ -88,12 +198,18 @@ impl SpillManager {
             serde_json::to_vec(batch).map_err(|e| Error::Codec(format!("json serialize: {e}")))?;
         let uncompressed_len = uncompressed.len() as u64;
 
-        // Compress
-        let compressed = codec::compress(self.codec, &uncompressed)?;
+        // Compress; a payload the codec would grow is stored as is, since
+        // readers reject compressed payloads larger than their input.
+        let (codec, compressed) = match codec::compress(self.codec, self.level, &uncompressed)? {
+            c if self.codec != Codec::None && c.len() > uncompressed.len() => {
+                (Codec::None, uncompressed)
+            }
+            c => (self.codec, c),
+        };
         let compressed_len = compressed.len() as u64;
 
         // Create header
-        let header = SegmentHeader::new(self.codec, uncompressed_len, compressed_len);
+        let header = SegmentHeader::new(codec, uncompressed_len, compressed_len);
         let header_bytes = header.to_bytes();
 
         // Compute checksum over header + payload
@@ -104,13 +220,41 @@ impl SpillManager {
 
         // Construct path and write
         let name = SegmentName::new(spill_id, run_index);
-        let path = format!("{}/{}.seg", self.root_dir, name.0);
+        let path = format!("{}/{}-{}.seg", self.root_dir, self.instance, name.0);
 
         let mut full_segment = Vec::with_capacity(header_bytes.len() + compressed.len());
         full_segment.extend_from_slice(&header_bytes);
         full_segment.extend_from_slice(&compressed);
 
+        let requested = full_segment.len() as u64;
+        if let Some(limit) = self.spill_limit {
+            let written = self.bytes_written - self.run_start_written;
+            if written + requested > limit {
+                return Err(Error::SpillLimitExceeded {
+                    limit,
+                    written,
+                    requested,
+                });
+            }
+        }
+        // Rewriting a segment replaces its bytes rather than adding to them.
+        let replaced = self.segments.get(&name).map_or(0, segment_bytes);
+        if let Some(quota) = self.spill_quota {
+            let live = self.live_bytes - replaced;
+            if live + requested > quota {
+                return Err(Error::SpillQuotaExceeded {
+                    quota,
+                    live,
+                    requested,
+                });
+            }
+        }
+
         self.storage.write(&path, &full_segment)?;
+        self.bytes_written += requested;
+        self.live_bytes = self.live_bytes - replaced + requested;
+        self.peak_bytes = self.peak_bytes.max(self.live_bytes);
+        self.payload_bytes += uncompressed_len;
 
         // Get etag from storage
         let etag = self.storage.etag(&path).ok().flatten();
@@ -118,7 +262,7 @@ impl SpillManager {
         let meta = SegmentMeta {
             name: name.clone(),
             path: path.clone(),
-            codec: self.codec,
+            codec,
             uncompressed_len,
             compressed_len,
             checksum,
@@ -150,6 +294,8 @@ impl SpillManager {
         if full_segment.len() < HEADER_LEN {
             return Err(Error::Storage("segment too short".into()));
         }
+        self.bytes_read
+            .fetch_add(full_segment.len() as u64, Ordering::Relaxed);
 
         // Verify checksum
         let mut hasher = blake3::Hasher::new();
@@ -192,15 +338,114 @@ impl SpillManager {
     }
 
     /// Delete a segment from storage and remove its metadata.
+    /// A segment that cannot be deleted is kept as an orphan for
+    /// [`sweep`](Self::sweep).
     pub fn delete_segment(&mut self, name: &SegmentName) -> Result<()> {
-        if let Some(meta) = self.segments.remove(name) {
-            self.storage.delete(&meta.path)?;
+        match self.segments.remove(name) {
+            Some(meta) => self.delete_meta(meta),
+            None => Ok(()),
+        }
+    }
+
+    fn delete_meta(&mut self, meta: SegmentMeta) -> Result<()> {
+        let bytes = segment_bytes(&meta);
+        match self.storage.delete(&meta.path) {
+            Ok(()) => {
+                self.live_bytes -= bytes;
+                Ok(())
+            }
+            Err(e) => {
+                self.orphans.push((meta.path, bytes));
+                Err(e)
+            }
         }
-        Ok(())
     }
 
     /// List all segment names currently tracked.
     pub fn list_segments(&self) -> Vec<SegmentName> {
         self.segments.keys().cloned().collect()
     }
+
+    /// Delete every tracked segment. All are attempted; the first error is
+    /// returned.
+    pub fn delete_all(&mut self) -> Result<()> {
+        let mut first_err = None;
+        let segments: Vec<SegmentMeta> = self.segments.drain().map(|(_, meta)| meta).collect();
+        for meta in segments {
+            if let Err(e) = self.delete_meta(meta) {
+                first_err.get_or_insert(e);
+            }
+        }
+        first_err.map_or(Ok(()), Err)
+    }
+
+    /// Retry the deletes of orphaned segments and, with `untracked`, delete
+    /// every other `.seg` file under the root that this manager does not
+    /// track, e.g. left by a process that crashed. Only pass `untracked` when
+    /// no other manager spills to the same root. Returns the number of
+    /// segments removed.
+    pub fn sweep(&mut self, untracked: bool) -> Result<usize> {
+        let mut removed = 0;
+        let mut first_err = None;
+        for (path, bytes) in std::mem::take(&mut self.orphans) {
+            match self.storage.delete(&path) {
+                Ok(()) => {
+                    self.live_bytes -= bytes;
+                    removed += 1;
+                }
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                    self.orphans.push((path, bytes));
+                }
+            }
+        }
+        if untracked {
+            let tracked: std::collections::HashSet<&str> =
+                self.segments.values().map(|m| m.path.as_str()).collect();
+            let stray: Vec<String> = self
+                .storage
+                .list(&self.root_dir)?
+                .into_iter()
+                .filter(|p| p.ends_with(".seg") && !tracked.contains(p.as_str()))
+                .collect();
+            for path in stray {
+                match self.storage.delete(&path) {
+                    Ok(()) => removed += 1,
+                    Err(e) => {
+                        first_err.get_or_insert(e);
+                    }
+                }
+            }
+        }
+        first_err.map_or(Ok(removed), Err)
+    }
+}
+
+/// Managers created by this process so far.
+static INSTANCES: AtomicU64 = AtomicU64::new(0);
+
+/// A prefix no other manager uses: the process id and a per-process count
+/// keep managers on one host apart, a random part those on hosts sharing a
+/// spill root. Spill ids alone do not, since every manager with the same
+/// seed issues the same ones.
+fn instance_prefix() -> String {
+    let random = std::collections::hash_map::RandomState::new().hash_one(0u8) as u32;
+    format!(
+        "{}-{}-{:08x}",
+        std::process::id(),
+        INSTANCES.fetch_add(1, Ordering::Relaxed),
+        random
+    )
+}
+
+/// Bytes a segment occupies in storage, header included.
+fn segment_bytes(meta: &SegmentMeta) -> u64 {
+    HEADER_LEN as u64 + meta.compressed_len
+}
+
+impl Drop for SpillManager {
+    fn drop(&mut self) {
+        // Segments never outlive their manager, however the run ended.
+        let _ = self.delete_all();
+    }
 }
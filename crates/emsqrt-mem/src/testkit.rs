@@ -0,0 +1,297 @@
+//! Conformance suite for [`Storage`] implementations (`--features testkit`).
+//!
+//! Spill correctness rests on a few `Storage` guarantees that are easy to get
+//! subtly wrong in a new backend:
+//!
+//! - reads return exactly the requested bytes or fail (no short reads);
+//! - a write replaces the whole object, and readers never see a mix of old and
+//!   new contents, even with concurrent writers;
+//! - deleting is idempotent;
+//! - `list` matches whole path segments and returns paths that can be read back.
+//!
+//! Run everything with [`run`] (panics listing each failure) or inspect the
+//! failures with [`check`]. Every check works under a fresh sub-path of `root`,
+//! which should be empty and writable.
+
+use std::thread;
+
+use crate::spill::Storage;
+
+/// One failed conformance check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub check: &'static str,
+    pub message: String,
+}
+
+type CheckResult = std::result::Result<(), String>;
+
+/// A single check, run against `storage` under an empty `root`.
+pub type Check = fn(&dyn Storage, &str) -> CheckResult;
+
+/// All checks, by name.
+pub const CHECKS: &[(&str, Check)] = &[
+    ("roundtrip", roundtrip),
+    ("read_slice", read_slice),
+    ("short_read_errors", short_read_errors),
+    ("empty_object", empty_object),
+    ("overwrite_replaces", overwrite_replaces),
+    ("missing_path_errors", missing_path_errors),
+    ("delete_idempotent", delete_idempotent),
+    ("list_prefix", list_prefix),
+    ("etag_stable", etag_stable),
+    ("concurrent_writes", concurrent_writes),
+];
+
+/// Run every check, each under `root/<check name>`, and return the failures.
+pub fn check(storage: &dyn Storage, root: &str) -> Vec<Failure> {
+    let root = root.trim_end_matches('/');
+    CHECKS
+        .iter()
+        .filter_map(|(name, f)| {
+            f(storage, &format!("{}/{}", root, name))
+                .err()
+                .map(|message| Failure {
+                    check: name,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Run every check and panic with a list of the failures, if any.
+pub fn run(storage: &dyn Storage, root: &str) {
+    let failures = check(storage, root);
+    if !failures.is_empty() {
+        let lines: Vec<String> = failures
+            .iter()
+            .map(|f| format!("  {}: {}", f.check, f.message))
+            .collect();
+        panic!("storage conformance failed:\n{}", lines.join("\n"));
+    }
+}
+
+fn ensure(cond: bool, message: impl FnOnce() -> String) -> CheckResult {
+    if cond {
+        Ok(())
+    } else {
+        Err(message())
+    }
+}
+
+fn payload(len: usize, seed: u8) -> Vec<u8> {
+    (0..len)
+        .map(|i| (i as u8).wrapping_mul(31) ^ seed)
+        .collect()
+}
+
+fn write(storage: &dyn Storage, path: &str, bytes: &[u8]) -> CheckResult {
+    storage
+        .write(path, bytes)
+        .map_err(|e| format!("write '{}': {}", path, e))
+}
+
+fn read(storage: &dyn Storage, path: &str, offset: u64, len: usize) -> Result<Vec<u8>, String> {
+    storage
+        .read_range(path, offset, len)
+        .map_err(|e| format!("read_range('{}', {}, {}): {}", path, offset, len, e))
+}
+
+fn roundtrip(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/nested/dir/segment.seg", root);
+    let data = payload(4096, 7);
+    write(storage, &path, &data)?;
+    ensure(read(storage, &path, 0, data.len())? == data, || {
+        "read back different bytes".into()
+    })?;
+    let size = storage.size(&path).map_err(|e| format!("size: {}", e))?;
+    ensure(size == data.len() as u64, || {
+        format!("size {} after writing {} bytes", size, data.len())
+    })
+}
+
+fn read_slice(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/slice.seg", root);
+    let data = payload(1000, 3);
+    write(storage, &path, &data)?;
+    for (offset, len) in [(0usize, 1usize), (10, 100), (999, 1), (500, 500)] {
+        ensure(
+            read(storage, &path, offset as u64, len)? == data[offset..offset + len],
+            || format!("wrong bytes for range {}..{}", offset, offset + len),
+        )?;
+    }
+    Ok(())
+}
+
+fn short_read_errors(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/short.seg", root);
+    write(storage, &path, &payload(10, 1))?;
+    for (offset, len) in [(8u64, 4usize), (10, 1), (100, 1)] {
+        if let Ok(bytes) = storage.read_range(&path, offset, len) {
+            return Err(format!(
+                "read of {} bytes at {} past the end of a 10-byte object returned {} bytes instead of an error",
+                len,
+                offset,
+                bytes.len()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn empty_object(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/empty.seg", root);
+    write(storage, &path, &[])?;
+    let size = storage.size(&path).map_err(|e| format!("size: {}", e))?;
+    ensure(size == 0, || format!("empty object has size {}", size))?;
+    ensure(read(storage, &path, 0, 0)?.is_empty(), || {
+        "zero-length read returned bytes".into()
+    })
+}
+
+fn overwrite_replaces(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/overwrite.seg", root);
+    write(storage, &path, &payload(2048, 1))?;
+    let shorter = payload(100, 2);
+    write(storage, &path, &shorter)?;
+    let size = storage.size(&path).map_err(|e| format!("size: {}", e))?;
+    ensure(size == 100, || {
+        format!(
+            "size {} after overwriting with 100 bytes (stale tail?)",
+            size
+        )
+    })?;
+    ensure(read(storage, &path, 0, 100)? == shorter, || {
+        "overwrite kept old bytes".into()
+    })
+}
+
+fn missing_path_errors(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/never-written.seg", root);
+    ensure(storage.read_range(&path, 0, 1).is_err(), || {
+        "read of a missing path succeeded".into()
+    })?;
+    ensure(storage.size(&path).is_err(), || {
+        "size of a missing path succeeded".into()
+    })
+}
+
+fn delete_idempotent(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/delete.seg", root);
+    write(storage, &path, &payload(10, 0))?;
+    storage
+        .delete(&path)
+        .map_err(|e| format!("delete: {}", e))?;
+    ensure(storage.read_range(&path, 0, 1).is_err(), || {
+        "deleted path is still readable".into()
+    })?;
+    storage
+        .delete(&path)
+        .map_err(|e| format!("second delete failed: {}", e))?;
+    storage
+        .delete(&format!("{}/never-written.seg", root))
+        .map_err(|e| format!("delete of a missing path failed: {}", e))
+}
+
+fn list_prefix(storage: &dyn Storage, root: &str) -> CheckResult {
+    let inside = [
+        format!("{}/a/b/1.seg", root),
+        format!("{}/a/b/c/2.seg", root),
+    ];
+    let outside = [format!("{}/a/bc/3.seg", root), format!("{}/a/4.seg", root)];
+    for (i, path) in inside.iter().chain(&outside).enumerate() {
+        write(storage, path, &payload(8, i as u8))?;
+    }
+
+    let mut listed = storage
+        .list(&format!("{}/a/b", root))
+        .map_err(|e| format!("list: {}", e))?;
+    listed.sort();
+    ensure(listed.len() == inside.len(), || {
+        format!(
+            "list of '{}/a/b' returned {:?}, expected the {} objects under it",
+            root,
+            listed,
+            inside.len()
+        )
+    })?;
+    // Listed paths must be usable as-is.
+    for (path, i) in listed.iter().zip(0u8..) {
+        ensure(read(storage, path, 0, 8)? == payload(8, i), || {
+            format!("listed path '{}' does not read back its object", path)
+        })?;
+    }
+
+    let missing = storage
+        .list(&format!("{}/nothing-here", root))
+        .map_err(|e| format!("list of a missing prefix failed: {}", e))?;
+    ensure(missing.is_empty(), || {
+        format!("list of a missing prefix returned {:?}", missing)
+    })
+}
+
+fn etag_stable(storage: &dyn Storage, root: &str) -> CheckResult {
+    let path = format!("{}/etag.seg", root);
+    write(storage, &path, &payload(64, 5))?;
+    let first = storage.etag(&path).map_err(|e| format!("etag: {}", e))?;
+    let second = storage.etag(&path).map_err(|e| format!("etag: {}", e))?;
+    ensure(first == second, || {
+        format!(
+            "etag changed without a write: {:?} then {:?}",
+            first, second
+        )
+    })
+}
+
+fn concurrent_writes(storage: &dyn Storage, root: &str) -> CheckResult {
+    const LEN: usize = 256 * 1024;
+    const ROUNDS: usize = 8;
+    let shared = format!("{}/shared.seg", root);
+    write(storage, &shared, &vec![0u8; LEN])?;
+
+    // Writers fill the shared object with their own byte, and also write one
+    // private object each; a reader checks it only ever sees a whole payload.
+    let errors: Vec<String> = thread::scope(|s| {
+        let writers: Vec<_> = (1..=4u8)
+            .map(|fill| {
+                let shared = shared.clone();
+                let private = format!("{}/writer-{}.seg", root, fill);
+                s.spawn(move || -> CheckResult {
+                    for _ in 0..ROUNDS {
+                        write(storage, &shared, &vec![fill; LEN])?;
+                    }
+                    write(storage, &private, &[fill; 16])
+                })
+            })
+            .collect();
+        let reader = s.spawn(|| -> CheckResult {
+            for _ in 0..ROUNDS * 4 {
+                let bytes = read(storage, &shared, 0, LEN)?;
+                ensure(bytes.iter().all(|&b| b == bytes[0]), || {
+                    "a reader saw a mix of two writes".into()
+                })?;
+            }
+            Ok(())
+        });
+        writers
+            .into_iter()
+            .chain(std::iter::once(reader))
+            .filter_map(|h| h.join().unwrap_or(Err("thread panicked".into())).err())
+            .collect()
+    });
+    if let Some(e) = errors.into_iter().next() {
+        return Err(e);
+    }
+
+    let last = read(storage, &shared, 0, LEN)?;
+    ensure(last.iter().all(|&b| b == last[0] && b != 0), || {
+        "concurrent overwrites left a mixed or stale object".into()
+    })?;
+    for fill in 1..=4u8 {
+        let private = format!("{}/writer-{}.seg", root, fill);
+        ensure(read(storage, &private, 0, 16)? == vec![fill; 16], || {
+            format!("'{}' lost its write", private)
+        })?;
+    }
+    Ok(())
+}
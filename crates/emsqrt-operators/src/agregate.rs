@@ -1,24 +1,38 @@
 //! Aggregate operator with budget-aware hash table and spill support.
 //!
-//! Implements partitioned aggregation: hash group keys to partitions,
-//! spill when budget exceeded, final merge phase.
+//! Implements partitioned aggregation: when the group table outgrows the
+//! budget, its partial accumulator states are spilled by group-key hash
+//! partition and merged per partition at the end. A partition whose merged
+//! groups still outgrow the budget is split again on further hash digits.
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::dag::Aggregation;
+use emsqrt_core::decimal;
+use emsqrt_core::hash::PartitionHasher;
+use emsqrt_core::key::encode_row_key;
 use emsqrt_core::prelude::{DataType, Field, Schema};
-use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_core::types::{scalar_cmp, Column, RowBatch, Scalar};
 use emsqrt_mem::guard::BudgetGuardImpl;
+use emsqrt_mem::spill::SegmentMeta;
 use emsqrt_mem::SpillManager;
 
+use crate::context::SpillScope;
 use crate::plan::{Footprint, OpPlan};
 use crate::traits::{OpError, Operator};
 
 /// Aggregation function specification.
+///
+/// Null semantics follow SQL: `Count` (`count(*)`) counts rows, `CountColumn`
+/// counts non-null values, and the others skip nulls and are null for a group
+/// with no non-null value.
 #[derive(Debug, Clone)]
 pub enum AggFunc {
     Count,
+    CountColumn { column: String },
     Sum { column: String },
     Min { column: String },
     Max { column: String },
@@ -26,13 +40,16 @@ pub enum AggFunc {
 }
 
 impl AggFunc {
-    /// Parse from string like "count", "sum:sales", "max:price".
+    /// Parse from string like "count", "count:id", "sum:sales", "max:price".
     pub fn parse(s: &str) -> Result<Self, String> {
         if s == "count" {
             return Ok(AggFunc::Count);
         }
         if let Some((func, col)) = s.split_once(':') {
             match func {
+                "count" => Ok(AggFunc::CountColumn {
+                    column: col.to_string(),
+                }),
                 "sum" => Ok(AggFunc::Sum {
                     column: col.to_string(),
                 }),
@@ -52,74 +69,414 @@ impl AggFunc {
         }
     }
 
-    /// Output field for this aggregation.
-    pub fn output_field(&self) -> Field {
+    /// Output field for this aggregation over `input` (see
+    /// [`Aggregation::output_field`]).
+    pub fn output_field(&self, input: &Schema) -> Field {
+        self.aggregation().output_field(input)
+    }
+
+    /// Name of the output column.
+    pub fn output_name(&self) -> String {
+        self.aggregation().output_name()
+    }
+
+    fn aggregation(&self) -> Aggregation {
         match self {
-            AggFunc::Count => Field::new("count", DataType::Int64, false),
-            AggFunc::Sum { column } => {
-                Field::new(format!("sum_{}", column), DataType::Float64, true)
-            }
-            AggFunc::Min { column } => {
-                Field::new(format!("min_{}", column), DataType::Float64, true)
-            }
-            AggFunc::Max { column } => {
-                Field::new(format!("max_{}", column), DataType::Float64, true)
-            }
-            AggFunc::Avg { column } => {
-                Field::new(format!("avg_{}", column), DataType::Float64, true)
-            }
+            AggFunc::Count => Aggregation::Count,
+            AggFunc::CountColumn { column } => Aggregation::CountColumn(column.clone()),
+            AggFunc::Sum { column } => Aggregation::Sum(column.clone()),
+            AggFunc::Min { column } => Aggregation::Min(column.clone()),
+            AggFunc::Max { column } => Aggregation::Max(column.clone()),
+            AggFunc::Avg { column } => Aggregation::Avg(column.clone()),
+        }
+    }
+
+    /// Columns of this function's partial state in a spilled partial
+    /// aggregate batch (see [`Accumulator::state`]); `index` is the
+    /// function's position in the aggregate list.
+    pub fn state_fields(&self, index: usize) -> Vec<Field> {
+        let field =
+            |part: &str, data_type| Field::new(format!("__agg{}_{}", index, part), data_type, true);
+        match self {
+            AggFunc::Count | AggFunc::CountColumn { .. } => vec![field("count", DataType::Int64)],
+            AggFunc::Sum { .. } => vec![
+                field("value", DataType::Float64),
+                field("compensation", DataType::Float64),
+                field("ints", DataType::Utf8),
+                field("decimal", DataType::Decimal128),
+            ],
+            // `Float64`, or the input's decimal or temporal type.
+            AggFunc::Min { .. } | AggFunc::Max { .. } => vec![field("value", DataType::Float64)],
+            AggFunc::Avg { .. } => vec![
+                field("sum", DataType::Float64),
+                field("compensation", DataType::Float64),
+                field("ints", DataType::Utf8),
+                field("decimal", DataType::Decimal128),
+                field("count", DataType::Int64),
+            ],
+        }
+    }
+
+    /// Input column the function reads; `None` for `count(*)`.
+    pub fn column(&self) -> Option<&str> {
+        match self {
+            AggFunc::Count => None,
+            AggFunc::CountColumn { column }
+            | AggFunc::Sum { column }
+            | AggFunc::Min { column }
+            | AggFunc::Max { column }
+            | AggFunc::Avg { column } => Some(column),
         }
     }
 }
 
-/// Aggregate value accumulator.
-#[derive(Debug, Clone)]
-pub struct AggValue {
-    pub count: u64,
-    pub sum: f64,
-    pub min: f64,
-    pub max: f64,
+/// Running sum of `sum` and `avg`: integers are added exactly in an `i128`,
+/// so `Int64` sums cannot overflow, decimals exactly at the largest scale
+/// seen, and floats with Neumaier's compensated summation, so rounding errors
+/// do not build up over millions of rows. The parts are combined once, by
+/// `value` or `finish`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExactSum {
+    pub ints: i128,
+    pub floats: f64,
+    /// Low-order bits lost from `floats` so far.
+    pub compensation: f64,
+    /// Sum of the `Decimal128` values, as `(value, scale)`.
+    pub decimal: Option<(i128, u8)>,
 }
 
-impl Default for AggValue {
-    fn default() -> Self {
-        Self {
-            count: 0,
-            sum: 0.0,
-            min: f64::INFINITY,
-            max: f64::NEG_INFINITY,
+impl ExactSum {
+    pub fn add_int(&mut self, v: i64) {
+        self.ints += v as i128;
+    }
+
+    pub fn add_float(&mut self, v: f64) {
+        let t = self.floats + v;
+        if t.is_finite() {
+            self.compensation += if self.floats.abs() >= v.abs() {
+                (self.floats - t) + v
+            } else {
+                (v - t) + self.floats
+            };
+        }
+        self.floats = t;
+    }
+
+    /// Add `value / 10^scale`; an error once the sum overflows.
+    pub fn add_decimal(&mut self, value: i128, scale: u8) -> Result<(), String> {
+        let (sum, sum_scale) = self.decimal.unwrap_or((0, scale));
+        let total = decimal::add(sum, sum_scale, value, scale).ok_or_else(|| {
+            format!(
+                "decimal sum overflow adding {}",
+                decimal::format_decimal(value, scale)
+            )
+        })?;
+        self.decimal = Some(total);
+        Ok(())
+    }
+
+    pub fn merge(&mut self, other: &ExactSum) -> Result<(), String> {
+        self.ints += other.ints;
+        self.add_float(other.floats);
+        self.compensation += other.compensation;
+        match other.decimal {
+            Some((value, scale)) => self.add_decimal(value, scale),
+            None => Ok(()),
+        }
+    }
+
+    /// The sum, rounded once to the nearest `f64`.
+    pub fn value(&self) -> f64 {
+        if !self.floats.is_finite() {
+            return self.floats;
+        }
+        let mut total = *self;
+        // The integer part in two pieces that each convert exactly.
+        let high = self.ints as f64;
+        total.add_float(high);
+        total.add_float((self.ints - high as i128) as f64);
+        if let Some((value, scale)) = self.decimal {
+            total.add_float(decimal::to_f64(value, scale));
+        }
+        total.floats + total.compensation
+    }
+
+    /// The sum as a `Decimal128` when only decimals (and integers) went in,
+    /// else as the `F64` of `value`.
+    pub fn finish(&self) -> Scalar {
+        self.exact_decimal().map_or_else(
+            || Scalar::F64(self.value()),
+            |(v, s)| Scalar::Decimal128(v, s),
+        )
+    }
+
+    /// `self / count` like `finish`; decimals keep at least
+    /// [`decimal::DIV_SCALE`] fraction digits.
+    pub fn mean(&self, count: u64) -> Scalar {
+        self.exact_decimal()
+            .and_then(|(v, s)| decimal::div(v, s, count as i128, 0))
+            .map_or_else(
+                || Scalar::F64(self.value() / count as f64),
+                |(v, s)| Scalar::Decimal128(v, s),
+            )
+    }
+
+    fn exact_decimal(&self) -> Option<(i128, u8)> {
+        let (value, scale) = self.decimal?;
+        if self.floats != 0.0 || self.compensation != 0.0 {
+            return None;
+        }
+        decimal::add(value, scale, self.ints, 0)
+    }
+}
+
+/// Running state of one aggregate function for one group. `Min` and `Max`
+/// hold numbers as `F64`, and `Decimal128`, `Date64` and `Timestamp` values
+/// as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Accumulator {
+    CountRows(u64),
+    CountValues(u64),
+    Sum(Option<ExactSum>),
+    Min(Option<Scalar>),
+    Max(Option<Scalar>),
+    Avg { sum: ExactSum, count: u64 },
+}
+
+/// Of `current` and `value`, the one ordered `keep` against the other.
+fn extreme(current: Option<Scalar>, value: Scalar, keep: Ordering) -> Scalar {
+    match (current, value) {
+        (None, value) => value,
+        // f64::min/max skip NaNs, which scalar_cmp orders last.
+        (Some(Scalar::F64(a)), Scalar::F64(b)) => Scalar::F64(match keep {
+            Ordering::Less => a.min(b),
+            _ => a.max(b),
+        }),
+        (Some(current), value) => {
+            if scalar_cmp(&value, &current) == keep {
+                value
+            } else {
+                current
+            }
         }
     }
 }
 
-impl AggValue {
-    pub fn update(&mut self, val: f64) {
-        self.count += 1;
-        self.sum += val;
-        if val < self.min {
-            self.min = val;
+fn non_numeric(value: &Scalar) -> String {
+    format!("cannot aggregate non-numeric value {:?}", value)
+}
+
+impl Accumulator {
+    pub fn new(func: &AggFunc) -> Self {
+        match func {
+            AggFunc::Count => Accumulator::CountRows(0),
+            AggFunc::CountColumn { .. } => Accumulator::CountValues(0),
+            AggFunc::Sum { .. } => Accumulator::Sum(None),
+            AggFunc::Min { .. } => Accumulator::Min(None),
+            AggFunc::Max { .. } => Accumulator::Max(None),
+            AggFunc::Avg { .. } => Accumulator::Avg {
+                sum: ExactSum::default(),
+                count: 0,
+            },
+        }
+    }
+
+    /// Add one row's value (ignored by `CountRows`; pass `Scalar::Null`).
+    pub fn update(&mut self, value: &Scalar) -> Result<(), String> {
+        if let Accumulator::CountRows(n) = self {
+            *n += 1;
+            return Ok(());
+        }
+        if matches!(value, Scalar::Null) {
+            return Ok(());
+        }
+        if let Accumulator::CountValues(n) = self {
+            *n += 1;
+            return Ok(());
+        }
+        let keep = match self {
+            Accumulator::Min(_) => Some(Ordering::Less),
+            Accumulator::Max(_) => Some(Ordering::Greater),
+            _ => None,
+        };
+        if let (Some(keep), Accumulator::Min(slot) | Accumulator::Max(slot)) = (keep, &mut *self) {
+            let value = match value {
+                Scalar::I32(i) => Scalar::F64(*i as f64),
+                Scalar::I64(i) => Scalar::F64(*i as f64),
+                Scalar::F32(f) => Scalar::F64(*f as f64),
+                Scalar::F64(_)
+                | Scalar::Decimal128(..)
+                | Scalar::Date64(_)
+                | Scalar::Timestamp(_) => value.clone(),
+                other => return Err(non_numeric(other)),
+            };
+            *slot = Some(extreme(slot.take(), value, keep));
+            return Ok(());
         }
-        if val > self.max {
-            self.max = val;
+        let add = |sum: &mut ExactSum| {
+            match value {
+                Scalar::I32(i) => sum.add_int(*i as i64),
+                Scalar::I64(i) => sum.add_int(*i),
+                Scalar::F32(f) => sum.add_float(*f as f64),
+                Scalar::F64(f) => sum.add_float(*f),
+                Scalar::Decimal128(v, scale) => return sum.add_decimal(*v, *scale),
+                other => return Err(non_numeric(other)),
+            }
+            Ok(())
+        };
+        match self {
+            Accumulator::Sum(sum) => {
+                let mut next = sum.unwrap_or_default();
+                add(&mut next)?;
+                *sum = Some(next);
+            }
+            Accumulator::Avg { sum, count } => {
+                add(sum)?;
+                *count += 1;
+            }
+            _ => unreachable!(),
         }
+        Ok(())
     }
 
-    pub fn merge(&mut self, other: &AggValue) {
-        self.count += other.count;
-        self.sum += other.sum;
-        if other.min < self.min {
-            self.min = other.min;
+    /// Combine the state of the same function over another set of rows.
+    pub fn merge(&mut self, other: &Accumulator) -> Result<(), String> {
+        match (self, other) {
+            (Accumulator::CountRows(a), Accumulator::CountRows(b))
+            | (Accumulator::CountValues(a), Accumulator::CountValues(b)) => *a += b,
+            (Accumulator::Sum(a), Accumulator::Sum(b)) => {
+                if let Some(b) = b {
+                    a.get_or_insert_with(ExactSum::default).merge(b)?;
+                }
+            }
+            (Accumulator::Min(a), Accumulator::Min(Some(b))) => {
+                *a = Some(extreme(a.take(), b.clone(), Ordering::Less))
+            }
+            (Accumulator::Max(a), Accumulator::Max(Some(b))) => {
+                *a = Some(extreme(a.take(), b.clone(), Ordering::Greater))
+            }
+            (Accumulator::Min(_), Accumulator::Min(None))
+            | (Accumulator::Max(_), Accumulator::Max(None)) => {}
+            (
+                Accumulator::Avg { sum, count },
+                Accumulator::Avg {
+                    sum: other_sum,
+                    count: other_count,
+                },
+            ) => {
+                sum.merge(other_sum)?;
+                *count += other_count;
+            }
+            (a, b) => panic!("merging mismatched accumulators {:?} and {:?}", a, b),
         }
-        if other.max > self.max {
-            self.max = other.max;
+        Ok(())
+    }
+
+    /// Partial state as scalars, one per [`AggFunc::state_fields`] column.
+    pub fn state(&self) -> Vec<Scalar> {
+        let sum_state = |sum: &ExactSum| {
+            vec![
+                Scalar::F64(sum.floats),
+                Scalar::F64(sum.compensation),
+                Scalar::Str(sum.ints.to_string()),
+                sum.decimal
+                    .map_or(Scalar::Null, |(v, s)| Scalar::Decimal128(v, s)),
+            ]
+        };
+        match self {
+            Accumulator::CountRows(n) | Accumulator::CountValues(n) => vec![Scalar::I64(*n as i64)],
+            Accumulator::Sum(Some(sum)) => sum_state(sum),
+            Accumulator::Sum(None) => vec![Scalar::Null; 4],
+            Accumulator::Min(v) | Accumulator::Max(v) => {
+                vec![v.clone().unwrap_or(Scalar::Null)]
+            }
+            Accumulator::Avg { sum, count } => {
+                let mut state = sum_state(sum);
+                state.push(Scalar::I64(*count as i64));
+                state
+            }
         }
     }
 
-    pub fn avg(&self) -> f64 {
-        if self.count > 0 {
-            self.sum / (self.count as f64)
-        } else {
-            0.0
+    /// Rebuild the accumulator of `func` from a partial state written by `state`.
+    pub fn from_state(func: &AggFunc, state: &[Scalar]) -> Result<Self, String> {
+        let count = |v: &Scalar| match v {
+            Scalar::I64(n) if *n >= 0 => Ok(*n as u64),
+            other => Err(format!("invalid count in aggregate state: {:?}", other)),
+        };
+        let float = |v: &Scalar| match v {
+            Scalar::Null => Ok(None),
+            Scalar::F64(f) => Ok(Some(*f)),
+            other => Err(format!("invalid value in aggregate state: {:?}", other)),
+        };
+        let extreme = |v: &Scalar| match v {
+            Scalar::Null => Ok(None),
+            Scalar::F64(_) | Scalar::Decimal128(..) | Scalar::Date64(_) | Scalar::Timestamp(_) => {
+                Ok(Some(v.clone()))
+            }
+            other => Err(format!("invalid value in aggregate state: {:?}", other)),
+        };
+        let sum = |floats: &Scalar, compensation: &Scalar, ints: &Scalar, dec: &Scalar| {
+            let Some(floats) = float(floats)? else {
+                return Ok(None);
+            };
+            let ints = match ints {
+                Scalar::Str(s) => s.parse().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| format!("invalid integer sum in aggregate state: {:?}", ints))?;
+            let decimal = match dec {
+                Scalar::Null => None,
+                Scalar::Decimal128(v, s) => Some((*v, *s)),
+                other => {
+                    return Err(format!(
+                        "invalid decimal sum in aggregate state: {:?}",
+                        other
+                    ))
+                }
+            };
+            Ok::<_, String>(Some(ExactSum {
+                ints,
+                floats,
+                compensation: float(compensation)?.unwrap_or(0.0),
+                decimal,
+            }))
+        };
+        Ok(match (func, state) {
+            (AggFunc::Count, [n]) => Accumulator::CountRows(count(n)?),
+            (AggFunc::CountColumn { .. }, [n]) => Accumulator::CountValues(count(n)?),
+            (AggFunc::Sum { .. }, [v, c, i, d]) => Accumulator::Sum(sum(v, c, i, d)?),
+            (AggFunc::Min { .. }, [v]) => Accumulator::Min(extreme(v)?),
+            (AggFunc::Max { .. }, [v]) => Accumulator::Max(extreme(v)?),
+            (AggFunc::Avg { .. }, [v, c, i, d, n]) => Accumulator::Avg {
+                sum: sum(v, c, i, d)?.unwrap_or_default(),
+                count: count(n)?,
+            },
+            _ => {
+                return Err(format!(
+                    "aggregate state for {:?} has {} values",
+                    func,
+                    state.len()
+                ))
+            }
+        })
+    }
+
+    /// Final value: counts are `I64`; sums and averages `Decimal128` over
+    /// decimals and `F64` otherwise; minima and maxima as held. Null without
+    /// input values.
+    pub fn finish(&self) -> Scalar {
+        match self {
+            Accumulator::CountRows(n) | Accumulator::CountValues(n) => Scalar::I64(*n as i64),
+            Accumulator::Sum(sum) => sum.map_or(Scalar::Null, |s| s.finish()),
+            Accumulator::Min(v) | Accumulator::Max(v) => v.clone().unwrap_or(Scalar::Null),
+            Accumulator::Avg { sum, count } => {
+                if *count > 0 {
+                    sum.mean(*count)
+                } else {
+                    Scalar::Null
+                }
+            }
         }
     }
 }
@@ -128,6 +485,14 @@ pub struct Aggregate {
     pub group_by: Vec<String>,
     pub aggs: Vec<String>, // e.g., "count", "sum:col"
     pub spill_mgr: Option<Arc<Mutex<SpillManager>>>,
+    /// Hash used to route groups to partitions when aggregating out of core.
+    pub partition_hash: PartitionHasher,
+    /// The input is known unique on `group_by`, so each row is its own group
+    /// and the group hash table is skipped.
+    pub unique_groups: bool,
+    /// Sort the output by group key. Otherwise groups come out in
+    /// first-seen order, or by hash partition after a spill.
+    pub deterministic: bool,
 }
 
 impl Default for Aggregate {
@@ -136,6 +501,9 @@ impl Default for Aggregate {
             group_by: Vec::new(),
             aggs: Vec::new(),
             spill_mgr: None,
+            partition_hash: PartitionHasher::default(),
+            unique_groups: false,
+            deterministic: true,
         }
     }
 }
@@ -155,7 +523,7 @@ impl Operator for Aggregate {
 
     fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
         let input_schema = input_schemas
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Plan("aggregate expects one input".into()))?;
 
         // Build output schema: group_by columns + aggregation result columns
@@ -174,7 +542,7 @@ impl Operator for Aggregate {
         for agg_str in &self.aggs {
             let agg_func = AggFunc::parse(agg_str)
                 .map_err(|e| OpError::Plan(format!("invalid agg: {}", e)))?;
-            fields.push(agg_func.output_field());
+            fields.push(agg_func.output_field(input_schema));
         }
 
         let schema = Schema::new(fields);
@@ -187,23 +555,27 @@ impl Operator for Aggregate {
         budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("missing input".into()))?;
 
         // Parse aggregation functions
         let agg_funcs: Vec<AggFunc> = self
             .aggs
             .iter()
-            .map(|s| AggFunc::parse(s).map_err(|e| OpError::Exec(e)))
+            .map(|s| AggFunc::parse(s).map_err(OpError::Exec))
             .collect::<Result<Vec<_>, _>>()?;
 
-        // Simple case: no spill manager, do in-memory aggregation
-        if self.spill_mgr.is_none() || self.group_by.len() != 1 {
-            return self.simple_aggregate(input, &agg_funcs);
+        // Simple case: no spill manager, or one group per row anyway;
+        // otherwise partitioned aggregation with spill support
+        let mut out = if self.spill_mgr.is_none() || self.unique_groups {
+            self.simple_aggregate(input, &agg_funcs)?
+        } else {
+            self.partitioned_aggregate(input, &agg_funcs, budget)?
+        };
+        if self.deterministic {
+            out.sort_by_columns(&self.group_by).map_err(OpError::Exec)?;
         }
-
-        // Partitioned aggregation with spill support
-        self.partitioned_aggregate(input, &agg_funcs, budget)
+        Ok(out)
     }
 }
 
@@ -218,90 +590,90 @@ impl Aggregate {
             return Err(OpError::Exec("group_by is empty".into()));
         }
 
-        let key_col_name = &self.group_by[0];
-        let key_col = input
-            .columns
+        let key_cols = self
+            .group_by
             .iter()
-            .find(|c| &c.name == key_col_name)
-            .ok_or_else(|| {
-                OpError::Exec(format!("group key column '{}' not found", key_col_name))
-            })?;
-
-        // Build hash map: group key -> AggValue
-        let mut groups: HashMap<String, AggValue> = HashMap::new();
-
-        for row_idx in 0..input.num_rows() {
-            let key_str = match &key_col.values[row_idx] {
-                Scalar::Str(s) => s.clone(),
-                Scalar::Null => "NULL".to_string(),
-                other => format!("{:?}", other),
-            };
+            .map(|name| {
+                input
+                    .columns
+                    .iter()
+                    .find(|c| &c.name == name)
+                    .ok_or_else(|| OpError::Exec(format!("group key column '{}' not found", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-            let agg = groups.entry(key_str).or_default();
-
-            // Update aggregations
-            for func in agg_funcs {
-                match func {
-                    AggFunc::Count => {} // Count is tracked in AggValue automatically
-                    AggFunc::Sum { column }
-                    | AggFunc::Min { column }
-                    | AggFunc::Max { column }
-                    | AggFunc::Avg { column } => {
-                        let val_col = input
+        // Input column of each function (`None` for count(*)), resolved once.
+        let value_cols = agg_funcs
+            .iter()
+            .map(|func| {
+                func.column()
+                    .map(|column| {
+                        input
                             .columns
                             .iter()
-                            .find(|c| &c.name == column)
+                            .find(|c| c.name == column)
                             .ok_or_else(|| {
                                 OpError::Exec(format!("agg column '{}' not found", column))
-                            })?;
+                            })
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let new_group = || agg_funcs.iter().map(Accumulator::new).collect::<Vec<_>>();
 
-                        let val_f64 = match &val_col.values[row_idx] {
-                            Scalar::I32(i) => *i as f64,
-                            Scalar::I64(i) => *i as f64,
-                            Scalar::F32(f) => *f as f64,
-                            Scalar::F64(f) => *f,
-                            _ => 0.0,
-                        };
+        // Groups in first-seen order; the map goes from encoded key bytes to group index.
+        let mut group_index: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut group_rows: Vec<usize> = Vec::new(); // first row of each group
+        let mut groups: Vec<Vec<Accumulator>> = Vec::new();
+        let mut key_buf = Vec::new();
 
-                        agg.update(val_f64);
+        for row_idx in 0..input.num_rows() {
+            let group = if self.unique_groups {
+                group_rows.push(row_idx);
+                groups.push(new_group());
+                groups.len() - 1
+            } else {
+                encode_row_key(&key_cols, row_idx, &mut key_buf);
+                match group_index.get(key_buf.as_slice()) {
+                    Some(&idx) => idx,
+                    None => {
+                        group_index.insert(key_buf.clone(), groups.len());
+                        group_rows.push(row_idx);
+                        groups.push(new_group());
+                        groups.len() - 1
                     }
                 }
+            };
+
+            // One accumulator per function, each fed only its own column.
+            for (acc, col) in groups[group].iter_mut().zip(&value_cols) {
+                let value = col.map_or(&Scalar::Null, |c| &c.values[row_idx]);
+                acc.update(value).map_err(|e| {
+                    OpError::Exec(format!("{} (column '{}')", e, col.map_or("", |c| &c.name)))
+                })?;
             }
         }
 
-        // Convert hash map to output columns
+        // Convert groups to output columns
         let mut output_cols = Vec::new();
 
-        // Group key column
-        let mut key_col_out = Column {
-            name: key_col_name.clone(),
-            values: Vec::with_capacity(groups.len()),
-        };
-
-        for key in groups.keys() {
-            key_col_out.values.push(Scalar::Str(key.clone()));
+        // Group key columns, taking each group's values from its first row
+        for key_col in &key_cols {
+            output_cols.push(Column {
+                name: key_col.name.clone(),
+                values: group_rows
+                    .iter()
+                    .map(|&row| key_col.values[row].clone())
+                    .collect(),
+            });
         }
-        output_cols.push(key_col_out);
 
         // Aggregation result columns
-        for func in agg_funcs {
-            let mut agg_col = Column {
-                name: func.output_field().name,
-                values: Vec::with_capacity(groups.len()),
-            };
-
-            for (_key, agg_val) in &groups {
-                let result = match func {
-                    AggFunc::Count => Scalar::I64(agg_val.count as i64),
-                    AggFunc::Sum { .. } => Scalar::F64(agg_val.sum),
-                    AggFunc::Min { .. } => Scalar::F64(agg_val.min),
-                    AggFunc::Max { .. } => Scalar::F64(agg_val.max),
-                    AggFunc::Avg { .. } => Scalar::F64(agg_val.avg()),
-                };
-                agg_col.values.push(result);
-            }
-
-            output_cols.push(agg_col);
+        for (i, func) in agg_funcs.iter().enumerate() {
+            output_cols.push(Column {
+                name: func.output_name(),
+                values: groups.iter().map(|accs| accs[i].finish()).collect(),
+            });
         }
 
         Ok(RowBatch {
@@ -309,15 +681,326 @@ impl Aggregate {
         })
     }
 
-    /// Partitioned aggregation with spill support (future enhancement).
+    /// Partitioned aggregation that spills partial states under budget pressure.
+    ///
+    /// Groups accumulate in one table while the budget allows. When it does
+    /// not, the table is split by group-key hash into `SPILL_PARTITIONS`
+    /// partial aggregate batches (keys plus accumulator states), which are
+    /// spilled, and the table starts over. Each partition is then merged
+    /// from its partial states alone, so the merge does work per spilled
+    /// group rather than per input row. Without a spill, groups come out in
+    /// first-seen order as in `simple_aggregate`.
     fn partitioned_aggregate(
         &self,
         input: &RowBatch,
         agg_funcs: &[AggFunc],
-        _budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
-        // For now, fall back to simple aggregation
-        // TODO: Implement partitioning, spill when hash table exceeds budget, merge phase
-        self.simple_aggregate(input, agg_funcs)
+        if self.group_by.is_empty() {
+            return Err(OpError::Exec("group_by is empty".into()));
+        }
+        let spill =
+            SpillScope::new(self.spill_mgr.clone().ok_or_else(|| {
+                OpError::Exec("partitioned aggregate needs a spill manager".into())
+            })?);
+        let key_cols = self
+            .group_by
+            .iter()
+            .map(|name| {
+                input
+                    .columns
+                    .iter()
+                    .find(|c| &c.name == name)
+                    .ok_or_else(|| OpError::Exec(format!("group key column '{}' not found", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let value_cols = agg_funcs
+            .iter()
+            .map(|func| {
+                func.column()
+                    .map(|column| {
+                        input
+                            .columns
+                            .iter()
+                            .find(|c| c.name == column)
+                            .ok_or_else(|| {
+                                OpError::Exec(format!("agg column '{}' not found", column))
+                            })
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut table = GroupTable::default();
+        let mut guard = budget.try_acquire(0, "aggregate");
+        let mut spilled: Vec<Vec<SegmentMeta>> = Vec::new();
+        let mut key_buf = Vec::new();
+        for row_idx in 0..input.num_rows() {
+            encode_row_key(&key_cols, row_idx, &mut key_buf);
+            let group = match table.index.get(key_buf.as_slice()) {
+                Some(&g) => g,
+                None => {
+                    let group_bytes =
+                        key_buf.len() + agg_funcs.len() * ACC_BYTES + GROUP_OVERHEAD_BYTES;
+                    let fits = guard
+                        .as_mut()
+                        .is_some_and(|g| g.try_resize(table.bytes + group_bytes));
+                    if !fits && !table.keys.is_empty() {
+                        self.spill_partials(&mut table, 0, agg_funcs, &spill, &mut spilled)?;
+                        if let Some(g) = guard.as_mut() {
+                            g.try_resize(0);
+                        }
+                    }
+                    table.bytes += group_bytes;
+                    table.insert(
+                        key_buf.clone(),
+                        key_cols.iter().map(|c| c.values[row_idx].clone()).collect(),
+                        agg_funcs,
+                    )
+                }
+            };
+            for (acc, col) in table.accs[group].iter_mut().zip(&value_cols) {
+                let value = col.map_or(&Scalar::Null, |c| &c.values[row_idx]);
+                acc.update(value).map_err(|e| {
+                    OpError::Exec(format!("{} (column '{}')", e, col.map_or("", |c| &c.name)))
+                })?;
+            }
+        }
+        if spilled.is_empty() {
+            return Ok(table.finish(&self.group_by, agg_funcs));
+        }
+        self.spill_partials(&mut table, 0, agg_funcs, &spill, &mut spilled)?;
+        drop(guard);
+
+        // Merge phase: one partition's partial states at a time.
+        let mut out = GroupTable::default().finish(&self.group_by, agg_funcs);
+        for segments in spilled {
+            self.merge_partition(segments, 1, agg_funcs, &spill, budget, &mut out)?;
+        }
+        Ok(out)
+    }
+
+    /// Merge the partial states of one spilled partition into `out`. When the
+    /// merged groups outgrow the budget, the partition is split again on the
+    /// next digits of the group-key hash and each part merged in turn.
+    fn merge_partition(
+        &self,
+        segments: Vec<SegmentMeta>,
+        level: u32,
+        agg_funcs: &[AggFunc],
+        spill: &SpillScope,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+        out: &mut RowBatch,
+    ) -> Result<(), OpError> {
+        let mut merged = GroupTable::default();
+        let mut guard = budget.try_acquire(0, "aggregate_merge");
+        let mut spilled: Vec<Vec<SegmentMeta>> = Vec::new();
+        let mut key_buf = Vec::new();
+        for meta in &segments {
+            let partial = spill.read(meta, budget)?;
+            spill.delete(&meta.name);
+            let (key_cols, state_cols) =
+                partial_columns(&partial, &self.group_by, agg_funcs).map_err(OpError::Exec)?;
+            for row in 0..partial.num_rows() {
+                encode_row_key(&key_cols, row, &mut key_buf);
+                if !merged.index.contains_key(key_buf.as_slice()) {
+                    let group_bytes =
+                        key_buf.len() + agg_funcs.len() * ACC_BYTES + GROUP_OVERHEAD_BYTES;
+                    // Merged groups take at most half the budget, leaving the
+                    // rest for reading partials back.
+                    let fits = merged.bytes + group_bytes <= budget.capacity_bytes() / 2
+                        && guard
+                            .as_mut()
+                            .is_some_and(|g| g.try_resize(merged.bytes + group_bytes));
+                    // Past the last hash digit, the partition is merged whole.
+                    if !fits && !merged.keys.is_empty() && level <= MAX_SPILL_LEVEL {
+                        self.spill_partials(&mut merged, level, agg_funcs, spill, &mut spilled)?;
+                        if let Some(g) = guard.as_mut() {
+                            g.try_resize(0);
+                        }
+                    }
+                    merged.bytes += group_bytes;
+                }
+                merged
+                    .merge_row(&key_buf, &key_cols, &state_cols, row, agg_funcs)
+                    .map_err(OpError::Exec)?;
+            }
+        }
+        if spilled.is_empty() {
+            let batch = merged.finish(&self.group_by, agg_funcs);
+            for (col, src) in out.columns.iter_mut().zip(batch.columns) {
+                col.values.extend(src.values);
+            }
+            return Ok(());
+        }
+        self.spill_partials(&mut merged, level, agg_funcs, spill, &mut spilled)?;
+        drop(guard);
+        for segments in spilled {
+            self.merge_partition(segments, level + 1, agg_funcs, spill, budget, out)?;
+        }
+        Ok(())
+    }
+
+    /// Split `table` by the `level`th hash digit of its group keys, spill each
+    /// non-empty part as a partial aggregate batch, and clear it.
+    fn spill_partials(
+        &self,
+        table: &mut GroupTable,
+        level: u32,
+        agg_funcs: &[AggFunc],
+        spill: &SpillScope,
+        spilled: &mut Vec<Vec<SegmentMeta>>,
+    ) -> Result<(), OpError> {
+        spilled.resize_with(SPILL_PARTITIONS, Vec::new);
+        let mut parts: Vec<GroupTable> = (0..SPILL_PARTITIONS)
+            .map(|_| GroupTable::default())
+            .collect();
+        let table = std::mem::take(table);
+        for ((key_bytes, keys), accs) in table.key_bytes.into_iter().zip(table.keys).zip(table.accs)
+        {
+            let refs: Vec<&Scalar> = keys.iter().collect();
+            let digit = self.partition_hash.hash_values(&refs)
+                / (SPILL_PARTITIONS as u64).pow(level)
+                % SPILL_PARTITIONS as u64;
+            let part = &mut parts[digit as usize];
+            let group = part.insert(key_bytes, keys, agg_funcs);
+            part.accs[group] = accs;
+        }
+        for (part, segments) in parts.iter().zip(spilled.iter_mut()) {
+            if !part.keys.is_empty() {
+                segments.push(spill.write(&part.partial_batch(&self.group_by, agg_funcs))?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The group key columns of a partial aggregate batch, and each function's
+/// state columns.
+fn partial_columns<'a>(
+    batch: &'a RowBatch,
+    group_by: &[String],
+    agg_funcs: &[AggFunc],
+) -> Result<(Vec<&'a Column>, Vec<Vec<&'a Column>>), String> {
+    let column = |name: &str| {
+        batch
+            .columns
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| format!("aggregate state batch has no column '{}'", name))
+    };
+    let key_cols = group_by
+        .iter()
+        .map(|name| column(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let state_cols = agg_funcs
+        .iter()
+        .enumerate()
+        .map(|(i, func)| {
+            func.state_fields(i)
+                .iter()
+                .map(|f| column(&f.name))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key_cols, state_cols))
+}
+
+/// Partitions of a spilling aggregate.
+const SPILL_PARTITIONS: usize = 16;
+/// The last group-key hash digit a spilled partition can be split on: a
+/// 64-bit hash has 16 base-16 digits.
+const MAX_SPILL_LEVEL: u32 = 15;
+/// Approximate in-memory size of one accumulator, and of a group's bookkeeping.
+const ACC_BYTES: usize = 24;
+const GROUP_OVERHEAD_BYTES: usize = 64;
+
+/// Groups of a partitioned aggregate in first-seen order.
+#[derive(Default)]
+struct GroupTable {
+    index: HashMap<Vec<u8>, usize>,
+    key_bytes: Vec<Vec<u8>>,
+    keys: Vec<Vec<Scalar>>,
+    accs: Vec<Vec<Accumulator>>,
+    /// Bytes charged to the budget for these groups.
+    bytes: usize,
+}
+
+impl GroupTable {
+    fn insert(&mut self, key_bytes: Vec<u8>, keys: Vec<Scalar>, agg_funcs: &[AggFunc]) -> usize {
+        let group = self.keys.len();
+        self.index.insert(key_bytes.clone(), group);
+        self.key_bytes.push(key_bytes);
+        self.keys.push(keys);
+        self.accs
+            .push(agg_funcs.iter().map(Accumulator::new).collect());
+        group
+    }
+
+    /// The spill format: group key columns, then each function's
+    /// `state_fields` columns, one row per group.
+    fn partial_batch(&self, group_by: &[String], agg_funcs: &[AggFunc]) -> RowBatch {
+        let mut columns: Vec<Column> = group_by
+            .iter()
+            .enumerate()
+            .map(|(k, name)| Column {
+                name: name.clone(),
+                values: self.keys.iter().map(|key| key[k].clone()).collect(),
+            })
+            .collect();
+        for (i, func) in agg_funcs.iter().enumerate() {
+            let states: Vec<Vec<Scalar>> = self.accs.iter().map(|accs| accs[i].state()).collect();
+            for (s, field) in func.state_fields(i).into_iter().enumerate() {
+                columns.push(Column {
+                    name: field.name,
+                    values: states.iter().map(|state| state[s].clone()).collect(),
+                });
+            }
+        }
+        RowBatch { columns }
+    }
+
+    /// Merge row `row` of a partial aggregate batch, whose encoded key is
+    /// `key_bytes`, into this table.
+    fn merge_row(
+        &mut self,
+        key_bytes: &[u8],
+        key_cols: &[&Column],
+        state_cols: &[Vec<&Column>],
+        row: usize,
+        agg_funcs: &[AggFunc],
+    ) -> Result<(), String> {
+        let group = match self.index.get(key_bytes) {
+            Some(&g) => g,
+            None => self.insert(
+                key_bytes.to_vec(),
+                key_cols.iter().map(|c| c.values[row].clone()).collect(),
+                agg_funcs,
+            ),
+        };
+        for ((acc, func), cols) in self.accs[group].iter_mut().zip(agg_funcs).zip(state_cols) {
+            let state: Vec<Scalar> = cols.iter().map(|c| c.values[row].clone()).collect();
+            acc.merge(&Accumulator::from_state(func, &state)?)?;
+        }
+        Ok(())
+    }
+
+    /// Output rows: group keys, then each function's final value.
+    fn finish(self, group_by: &[String], agg_funcs: &[AggFunc]) -> RowBatch {
+        let mut columns: Vec<Column> = group_by
+            .iter()
+            .enumerate()
+            .map(|(k, name)| Column {
+                name: name.clone(),
+                values: self.keys.iter().map(|key| key[k].clone()).collect(),
+            })
+            .collect();
+        for (i, func) in agg_funcs.iter().enumerate() {
+            columns.push(Column {
+                name: func.output_name(),
+                values: self.accs.iter().map(|accs| accs[i].finish()).collect(),
+            });
+        }
+        RowBatch { columns }
     }
 }
@@ -0,0 +1,166 @@
+//! Per-block execution context handed to operators.
+//!
+//! `eval_block` only sees inputs and the budget. Operators that need to know
+//! where they are in the TE plan, spill, report counters, or stop early take
+//! an `OpContext` through `Operator::eval_block_with`.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::id::{BlockId, OpId};
+use emsqrt_core::types::RowBatch;
+use emsqrt_mem::guard::BudgetGuardImpl;
+use emsqrt_mem::spill::{SegmentMeta, SegmentName};
+use emsqrt_mem::SpillManager;
+
+use crate::traits::{BlockRange, OpError};
+
+/// Everything the engine knows about the block being evaluated.
+///
+/// `Default` is a context outside any plan: no ids, no range, no spill.
+#[derive(Debug, Default)]
+pub struct OpContext {
+    pub block_id: Option<BlockId>,
+    pub op_id: Option<OpId>,
+    /// Rows of the operator's output this block covers (`TeBlock::range_rows`).
+    pub range: Option<BlockRange>,
+    /// Spill storage whose segments are removed when the block is done.
+    pub spill: Option<SpillScope>,
+    /// Operator-defined counters, reported per block in the run manifest.
+    pub metrics: OpMetrics,
+    pub cancel: CancellationToken,
+}
+
+impl OpContext {
+    /// `Err` once the run has been cancelled; long loops should call this.
+    pub fn check_cancelled(&self) -> Result<(), OpError> {
+        if self.cancel.is_cancelled() {
+            Err(OpError::Exec("run cancelled".into()))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Shared flag to stop a run between (or inside) blocks.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Named counters an operator bumps while evaluating a block.
+#[derive(Debug, Default)]
+pub struct OpMetrics {
+    counters: Mutex<BTreeMap<String, u64>>,
+}
+
+impl OpMetrics {
+    pub fn add(&self, name: &str, delta: u64) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert(0) += delta;
+    }
+
+    pub fn get(&self, name: &str) -> u64 {
+        self.counters
+            .lock()
+            .unwrap()
+            .get(name)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn take(&self) -> BTreeMap<String, u64> {
+        std::mem::take(&mut *self.counters.lock().unwrap())
+    }
+}
+
+/// A view of the engine's `SpillManager` that tracks the segments written
+/// through it and deletes the ones still held when it is dropped.
+pub struct SpillScope {
+    mgr: Arc<Mutex<SpillManager>>,
+    segments: Mutex<Vec<SegmentName>>,
+}
+
+impl std::fmt::Debug for SpillScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SpillScope")
+            .field("segments", &self.segments.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl SpillScope {
+    pub fn new(mgr: Arc<Mutex<SpillManager>>) -> Self {
+        Self {
+            mgr,
+            segments: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The underlying manager, for operators that manage segments themselves.
+    pub fn manager(&self) -> Arc<Mutex<SpillManager>> {
+        self.mgr.clone()
+    }
+
+    pub fn write(&self, batch: &RowBatch) -> Result<SegmentMeta, OpError> {
+        let mut mgr = self.mgr.lock().unwrap();
+        let (id, run) = (mgr.next_spill_id(), mgr.next_run_index());
+        let meta = mgr
+            .write_batch(batch, id, run)
+            .map_err(|e| OpError::Exec(format!("spill write: {}", e)))?;
+        self.segments.lock().unwrap().push(meta.name.clone());
+        Ok(meta)
+    }
+
+    pub fn read(
+        &self,
+        meta: &SegmentMeta,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.mgr
+            .lock()
+            .unwrap()
+            .read_batch(meta, budget)
+            .map_err(|e| OpError::Exec(format!("spill read: {}", e)))
+    }
+
+    /// Delete a segment before the scope ends.
+    pub fn delete(&self, name: &SegmentName) {
+        self.segments.lock().unwrap().retain(|n| n != name);
+        let _ = self.mgr.lock().unwrap().delete_segment(name);
+    }
+
+    /// Segments written through this scope and not yet deleted.
+    pub fn live_segments(&self) -> usize {
+        self.segments.lock().unwrap().len()
+    }
+}
+
+impl Drop for SpillScope {
+    fn drop(&mut self) {
+        let segments = std::mem::take(&mut *self.segments.lock().unwrap());
+        if let Ok(mut mgr) = self.mgr.lock() {
+            for name in segments {
+                let _ = mgr.delete_segment(&name);
+            }
+        }
+    }
+}
@@ -3,6 +3,8 @@
 //! Supports SQL-like expressions using the expression engine.
 //! Simple predicates: "col OP literal" where OP ∈ {==, !=, <, <=, >, >=}
 //! Complex predicates: "col1 > 10 AND col2 == 'active'"
+//! Seeded functions: "sample_hash(user_id, 0.1)", "rand() < 0.01"
+//! Division: "safe_div(spend, clicks) > 2", "qty / nullif(units, 0) > 1"
 //!
 //! When the `arrow` feature is enabled, uses Arrow compute kernels for better performance.
 
@@ -13,7 +15,9 @@ use arrow_schema::SchemaRef;
 #[cfg(feature = "arrow")]
 use std::sync::Arc;
 
-use emsqrt_core::expr::Expr;
+use std::sync::Mutex;
+
+use emsqrt_core::expr::{ArithErrorPolicy, EvalContext, Expr};
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::{Column, RowBatch};
 
@@ -24,6 +28,13 @@ use crate::traits::{MemoryBudget, OpError, Operator};
 pub struct Filter {
     /// Predicate expression string (parsed into Expr on demand)
     pub expr: Option<String>,
+    /// Run seed for `rand()`, `hash()` and `sample_hash()`.
+    pub seed: u64,
+    /// What a division by zero yields.
+    pub arith_errors: ArithErrorPolicy,
+    /// Rows evaluated so far, so `rand()` is keyed on stream position rather
+    /// than on the (budget-dependent) block boundaries.
+    pub rows_seen: Mutex<u64>,
 }
 
 impl Operator for Filter {
@@ -41,7 +52,7 @@ impl Operator for Filter {
 
     fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
         let schema = input_schemas
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Plan("filter expects one input".into()))?
             .clone();
         Ok(OpPlan::new(schema, self.memory_need(0, 0)))
@@ -53,7 +64,7 @@ impl Operator for Filter {
         _budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("missing input".into()))?;
 
         // If no expression, pass through
@@ -66,23 +77,20 @@ impl Operator for Filter {
             OpError::Exec(format!("failed to parse expression '{}': {}", expr_str, e))
         })?;
 
-        // Evaluate expression for each row
+        // Evaluate the predicate column at a time into a selection vector
         let num_rows = input.num_rows();
-        let mut keep = Vec::with_capacity(num_rows);
+        let mut rows_seen = self.rows_seen.lock().unwrap();
+        let ctx = EvalContext {
+            seed: self.seed,
+            row_base: *rows_seen,
+            arith_errors: self.arith_errors,
+        };
 
-        for row_idx in 0..num_rows {
-            match expr.evaluate_bool(input, row_idx) {
-                Ok(b) => keep.push(b),
-                Err(e) => {
-                    // If evaluation fails, return error instead of silently filtering
-                    // This helps catch bugs during development
-                    return Err(OpError::Exec(format!(
-                        "expression evaluation failed at row {}: {}",
-                        row_idx, e
-                    )));
-                }
-            }
-        }
+        // If evaluation fails, return error instead of silently filtering
+        let keep = expr
+            .evaluate_selection(input, &ctx)
+            .map_err(|e| OpError::Exec(format!("expression evaluation failed {}", e)))?;
+        *rows_seen += num_rows as u64;
 
         // Filter all columns
         let mut filtered_cols = Vec::new();
@@ -0,0 +1,300 @@
+//! Semi-join against a key file: keep rows whose key columns appear in it.
+//!
+//! The key file is plain text with one key per line; composite keys separate
+//! their parts with `delimiter` (default `,`). Rows match on the text form of
+//! their key values, so `42` in the file matches an Int64 `42`. Rows with a
+//! null key value never match.
+//!
+//! Memory: keys are loaded into a hash set under a budget guard. If the set
+//! does not fit, the operator keeps a fixed-size Bloom filter of the keys
+//! instead, and per block checks the rows that pass it exactly by streaming
+//! the key file once. That check only holds the block's candidate keys.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufRead, BufReader};
+use std::sync::Mutex;
+
+use emsqrt_core::key::encode_scalar;
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::BudgetGuardImpl;
+
+use crate::plan::{Footprint, OpPlan};
+use crate::traits::{MemoryBudget, OpError, Operator};
+
+/// Approximate per-key hash-set overhead on top of the encoded key bytes.
+const KEY_OVERHEAD_BYTES: usize = 48;
+const BLOOM_HASHES: u64 = 7;
+
+pub struct FilterIn {
+    /// Row columns forming the key, in key-file order.
+    pub columns: Vec<String>,
+    /// Path of the key file.
+    pub keys_path: String,
+    /// Separator between the parts of a composite key.
+    pub delimiter: String,
+    /// Bloom filter size (bits) used when the key set does not fit the budget.
+    pub bloom_bits: usize,
+    index: Mutex<Option<KeyIndex>>,
+}
+
+impl Default for FilterIn {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            keys_path: String::new(),
+            delimiter: ",".to_string(),
+            bloom_bits: 8 * 1024 * 1024,
+            index: Mutex::new(None),
+        }
+    }
+}
+
+enum KeyIndex {
+    InMemory {
+        keys: HashSet<Vec<u8>>,
+        _guard: BudgetGuardImpl,
+    },
+    Bloom {
+        bloom: Bloom,
+        _guard: BudgetGuardImpl,
+    },
+}
+
+struct Bloom {
+    bits: Vec<u64>,
+}
+
+impl Bloom {
+    fn new(num_bits: usize) -> Self {
+        Self {
+            bits: vec![0; num_bits.div_ceil(64).max(1)],
+        }
+    }
+
+    fn positions(&self, key: &[u8]) -> impl Iterator<Item = usize> {
+        let mut h = DefaultHasher::new();
+        key.hash(&mut h);
+        let hash = h.finish();
+        let (h1, h2) = (hash & 0xffff_ffff, hash >> 32);
+        let n = (self.bits.len() * 64) as u64;
+        (0..BLOOM_HASHES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % n) as usize)
+    }
+
+    fn insert(&mut self, key: &[u8]) {
+        for p in self.positions(key).collect::<Vec<_>>() {
+            self.bits[p / 64] |= 1 << (p % 64);
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        self.positions(key)
+            .all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+}
+
+impl FilterIn {
+    fn open_keys(&self) -> Result<impl Iterator<Item = Result<Vec<u8>, OpError>> + '_, OpError> {
+        let file = File::open(&self.keys_path).map_err(|e| {
+            OpError::Exec(format!(
+                "failed to open filter_in key file '{}': {}",
+                self.keys_path, e
+            ))
+        })?;
+        Ok(BufReader::new(file).lines().filter_map(move |line| {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(OpError::Exec(format!("reading key file: {}", e)))),
+            };
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                return None;
+            }
+            let mut key = Vec::new();
+            for part in line.split(self.delimiter.as_str()) {
+                encode_scalar(&Scalar::Str(part.to_string()), &mut key);
+            }
+            Some(Ok(key))
+        }))
+    }
+
+    /// Load the key set, or a Bloom filter of it if the set does not fit.
+    fn build_index(
+        &self,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<KeyIndex, OpError> {
+        let mut guard = budget
+            .try_acquire(0, "filter_in")
+            .ok_or_else(|| OpError::Exec("memory budget unavailable for filter_in".into()))?;
+        let mut keys = HashSet::new();
+        let mut bytes = 0usize;
+        for key in self.open_keys()? {
+            let key = key?;
+            bytes += key.len() + KEY_OVERHEAD_BYTES;
+            if !guard.try_resize(bytes) {
+                drop(keys);
+                drop(guard);
+                return self.build_bloom(budget);
+            }
+            keys.insert(key);
+        }
+        Ok(KeyIndex::InMemory {
+            keys,
+            _guard: guard,
+        })
+    }
+
+    fn build_bloom(
+        &self,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<KeyIndex, OpError> {
+        let guard = budget
+            .try_acquire(self.bloom_bits.div_ceil(8), "filter_in_bloom")
+            .ok_or_else(|| {
+                OpError::Exec(format!(
+                    "insufficient memory budget for filter_in Bloom filter ({} bits)",
+                    self.bloom_bits
+                ))
+            })?;
+        let mut bloom = Bloom::new(self.bloom_bits);
+        for key in self.open_keys()? {
+            bloom.insert(&key?);
+        }
+        Ok(KeyIndex::Bloom {
+            bloom,
+            _guard: guard,
+        })
+    }
+
+    /// Keys of each row (`None` where a key value is null).
+    fn row_keys(&self, input: &RowBatch) -> Result<Vec<Option<Vec<u8>>>, OpError> {
+        let cols: Vec<&Column> = self
+            .columns
+            .iter()
+            .map(|name| {
+                input
+                    .columns
+                    .iter()
+                    .find(|c| &c.name == name)
+                    .ok_or_else(|| OpError::Schema(format!("filter_in: unknown column '{}'", name)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((0..input.num_rows())
+            .map(|row| {
+                let mut key = Vec::new();
+                for col in &cols {
+                    let text = scalar_text(&col.values[row])?;
+                    encode_scalar(&Scalar::Str(text), &mut key);
+                }
+                Some(key)
+            })
+            .collect())
+    }
+}
+
+/// Text form used to compare row values against key-file entries.
+fn scalar_text(value: &Scalar) -> Option<String> {
+    match value {
+        Scalar::Null => None,
+        Scalar::Bool(b) => Some(b.to_string()),
+        Scalar::I32(i) => Some(i.to_string()),
+        Scalar::I64(i) => Some(i.to_string()),
+        Scalar::F32(f) => Some(f.to_string()),
+        Scalar::F64(f) => Some(f.to_string()),
+        Scalar::Str(s) => Some(s.clone()),
+        Scalar::Bin(b) => Some(String::from_utf8_lossy(b).into_owned()),
+        Scalar::Date64(_) | Scalar::Timestamp(_) | Scalar::Decimal128(..) => {
+            Some(value.to_string())
+        }
+    }
+}
+
+impl Operator for FilterIn {
+    fn name(&self) -> &'static str {
+        "filter_in"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        // Streaming over rows; the key index is accounted separately at load.
+        Footprint {
+            bytes_per_row: 1,
+            overhead_bytes: (self.bloom_bits / 8) as u64,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let schema = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("filter_in expects one input".into()))?
+            .clone();
+        Ok(OpPlan::new(schema, self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("missing input".into()))?;
+        if self.columns.is_empty() {
+            return Err(OpError::Plan("filter_in requires key columns".into()));
+        }
+
+        let mut index = self.index.lock().unwrap();
+        if index.is_none() {
+            *index = Some(self.build_index(budget)?);
+        }
+        let row_keys = self.row_keys(input)?;
+
+        let keep: Vec<bool> = match index.as_ref().expect("built above") {
+            KeyIndex::InMemory { keys, .. } => row_keys
+                .iter()
+                .map(|k| k.as_ref().is_some_and(|k| keys.contains(k)))
+                .collect(),
+            KeyIndex::Bloom { bloom, .. } => {
+                // Bloom candidates, verified against one pass over the key file.
+                let mut candidates: HashMap<&[u8], Vec<usize>> = HashMap::new();
+                for (row, key) in row_keys.iter().enumerate() {
+                    if let Some(key) = key.as_deref().filter(|k| bloom.may_contain(k)) {
+                        candidates.entry(key).or_default().push(row);
+                    }
+                }
+                let mut keep = vec![false; row_keys.len()];
+                if !candidates.is_empty() {
+                    for key in self.open_keys()? {
+                        if let Some(rows) = candidates.remove(key?.as_slice()) {
+                            for row in rows {
+                                keep[row] = true;
+                            }
+                            if candidates.is_empty() {
+                                break;
+                            }
+                        }
+                    }
+                }
+                keep
+            }
+        };
+
+        Ok(RowBatch {
+            columns: input
+                .columns
+                .iter()
+                .map(|col| Column {
+                    name: col.name.clone(),
+                    values: col
+                        .values
+                        .iter()
+                        .zip(&keep)
+                        .filter(|(_, k)| **k)
+                        .map(|(v, _)| v.clone())
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,108 @@
+//! Filters fused into a join's inputs.
+//!
+//! A filter directly above a join input is lowered into the join itself
+//! (`left_filter`/`right_filter` in its binding), so rows it drops are never
+//! partitioned or spilled by the join, nor materialized between blocks.
+
+use std::borrow::Cow;
+
+use emsqrt_core::expr::{ArithErrorPolicy, EvalContext, Expr};
+use emsqrt_core::types::{Column, RowBatch};
+
+use crate::traits::OpError;
+
+/// Predicates on the left (input 0) and right (input 1) side of a join.
+#[derive(Debug, Clone, Default)]
+pub struct InputFilters {
+    pub left: Option<Expr>,
+    pub right: Option<Expr>,
+    /// Run seed for `hash()` and `sample_hash()`.
+    pub seed: u64,
+    /// What a division by zero yields.
+    pub arith_errors: ArithErrorPolicy,
+}
+
+impl InputFilters {
+    /// Parse the filter expressions of each side.
+    pub fn parse(left: Option<&str>, right: Option<&str>, seed: u64) -> Result<Self, String> {
+        let parse = |expr: Option<&str>| {
+            expr.map(|e| {
+                Expr::parse(e).map_err(|err| format!("invalid join filter '{}': {}", e, err))
+            })
+            .transpose()
+        };
+        Ok(Self {
+            left: parse(left)?,
+            right: parse(right)?,
+            seed,
+            arith_errors: ArithErrorPolicy::default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
+    /// Which rows of `batch`, the join's input `side`, pass its filter;
+    /// `None` when that side has none.
+    pub fn mask(&self, side: usize, batch: &RowBatch) -> Result<Option<Vec<bool>>, OpError> {
+        let expr = match side {
+            0 => &self.left,
+            _ => &self.right,
+        };
+        let Some(expr) = expr else {
+            return Ok(None);
+        };
+        // Fused filters never use `rand()`, so the stream position is unused.
+        let ctx = EvalContext {
+            seed: self.seed,
+            row_base: 0,
+            arith_errors: self.arith_errors,
+        };
+        expr.evaluate_selection(batch, &ctx).map(Some).map_err(|e| {
+            OpError::Exec(format!(
+                "join {} filter failed {}",
+                if side == 0 { "left" } else { "right" },
+                e
+            ))
+        })
+    }
+
+    /// `inputs` with each side's filter applied; borrowed when there are none.
+    pub fn apply<'a>(&self, inputs: &'a [RowBatch]) -> Result<Cow<'a, [RowBatch]>, OpError> {
+        if self.is_empty() {
+            return Ok(Cow::Borrowed(inputs));
+        }
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(side, batch)| {
+                Ok(match self.mask(side, batch)? {
+                    Some(keep) => select_rows(batch, &keep),
+                    None => batch.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Cow::Owned)
+    }
+}
+
+/// The rows of `batch` whose `keep` flag is set.
+pub(crate) fn select_rows(batch: &RowBatch, keep: &[bool]) -> RowBatch {
+    RowBatch {
+        columns: batch
+            .columns
+            .iter()
+            .map(|col| Column {
+                name: col.name.clone(),
+                values: col
+                    .values
+                    .iter()
+                    .zip(keep)
+                    .filter(|(_, &k)| k)
+                    .map(|(v, _)| v.clone())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
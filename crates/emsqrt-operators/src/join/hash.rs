@@ -1,14 +1,33 @@
 //! Grace-partitioned hash join with build/probe phases.
-
+//!
+//! Filters fused into the inputs (see [`InputFilters`]) are evaluated while
+//! the inputs are partitioned, so rows they drop are never spilled.
+//!
+//! The in-memory join emits rows in probe order: each left row with its
+//! matches in right input order, then unmatched right rows. A Grace join
+//! emits them partition by partition; with `deterministic` set, its output
+//! is put back in the same order.
+//!
+//! As in SQL, a row with a null in any key column matches no row: inner
+//! joins drop it and outer joins pad it.
+
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::dag;
+use emsqrt_core::hash::PartitionHasher;
+use emsqrt_core::key::encode_row_key;
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::error::Error as MemError;
 use emsqrt_mem::guard::BudgetGuardImpl;
+use emsqrt_mem::spill::SegmentMeta;
 use emsqrt_mem::SpillManager;
 
+use crate::join::filter::{select_rows, InputFilters};
+use crate::join::right_names;
 use crate::plan::{Footprint, OpPlan};
 use crate::traits::{OpError, Operator};
 
@@ -33,18 +52,46 @@ impl JoinType {
     }
 }
 
+impl From<JoinType> for dag::JoinType {
+    fn from(join_type: JoinType) -> Self {
+        match join_type {
+            JoinType::Inner => dag::JoinType::Inner,
+            JoinType::Left => dag::JoinType::Left,
+            JoinType::Right => dag::JoinType::Right,
+            JoinType::Full => dag::JoinType::Full,
+        }
+    }
+}
+
 pub struct HashJoin {
     pub on: Vec<(String, String)>, // (left_col, right_col)
     pub join_type: String,         // "inner", "left", "right", "full"
     pub spill_mgr: Option<Arc<Mutex<SpillManager>>>,
+    /// Hash used to route rows to Grace partitions (must match on both sides).
+    pub partition_hash: PartitionHasher,
+    /// Fixed Grace partition count; `None` sizes partitions from the input.
+    pub num_partitions: Option<usize>,
+    /// Filters on the inputs, applied before any row is partitioned.
+    pub filters: InputFilters,
+    /// Restore probe order after a Grace join rather than leaving rows in
+    /// partition order.
+    pub deterministic: bool,
 }
 
+/// Hidden columns carrying each row's input position through a Grace join.
+const LEFT_ROW: &str = "__join_left_row";
+const RIGHT_ROW: &str = "__join_right_row";
+
 impl Default for HashJoin {
     fn default() -> Self {
         Self {
             on: Vec::new(),
             join_type: "inner".to_string(),
             spill_mgr: None,
+            partition_hash: PartitionHasher::default(),
+            num_partitions: None,
+            filters: InputFilters::default(),
+            deterministic: true,
         }
     }
 }
@@ -82,27 +129,9 @@ impl Operator for HashJoin {
             return Err(OpError::Plan("hash join expects two inputs".into()));
         }
 
-        // Derive output schema by concatenating left + right schemas
-        let left_schema = &input_schemas[0];
-        let right_schema = &input_schemas[1];
-
-        let mut fields = Vec::new();
-
-        // Add left fields
-        for field in &left_schema.fields {
-            fields.push(field.clone());
-        }
-
-        // Add right fields (with suffix if name conflicts)
-        for field in &right_schema.fields {
-            let mut new_field = field.clone();
-            if fields.iter().any(|f| f.name == field.name) {
-                new_field.name = format!("{}_right", field.name);
-            }
-            fields.push(new_field);
-        }
-
-        let out_schema = Schema::new(fields);
+        let join_type = JoinType::parse(&self.join_type).map_err(OpError::Plan)?;
+        let out_schema =
+            dag::JoinType::from(join_type).output_schema(&input_schemas[0], &input_schemas[1]);
         Ok(OpPlan::new(out_schema, self.memory_need(0, 0)))
     }
 
@@ -127,15 +156,36 @@ impl Operator for HashJoin {
         // 2. Inputs are small (< 100k rows each)
         // Otherwise use Grace hash join with partitioning
 
-        let right_rows = right.num_rows() as u64;
-        let left_rows = left.num_rows() as u64;
+        let left_keep = self.filters.mask(0, left)?;
+        let right_keep = self.filters.mask(1, right)?;
+        let kept = |keep: &Option<Vec<bool>>, batch: &RowBatch| {
+            keep.as_ref()
+                .map_or(batch.num_rows(), |k| k.iter().filter(|&&b| b).count()) as u64
+        };
+        let right_rows = kept(&right_keep, right);
+        let left_rows = kept(&left_keep, left);
 
         // Use simple join for small inputs or when no spill manager
         if self.spill_mgr.is_none() || (right_rows < 100_000 && left_rows < 100_000) {
-            self.simple_hash_join(left, right, join_type)
+            let select = |batch, keep: Option<Vec<bool>>| match keep {
+                Some(keep) => Cow::Owned(select_rows(batch, &keep)),
+                None => Cow::Borrowed(batch),
+            };
+            self.simple_hash_join(
+                &select(left, left_keep),
+                &select(right, right_keep),
+                join_type,
+            )
         } else {
             // Large inputs and spill manager available - use Grace hash join
-            self.grace_hash_join(left, right, join_type, budget)
+            let keep = (left_keep.as_deref(), right_keep.as_deref());
+            if !self.deterministic {
+                return self.grace_hash_join(left, right, keep, join_type, budget);
+            }
+            let left = with_row_numbers(left, LEFT_ROW);
+            let right = with_row_numbers(right, RIGHT_ROW);
+            let out = self.grace_hash_join(&left, &right, keep, join_type, budget)?;
+            Ok(into_probe_order(out))
         }
     }
 }
@@ -152,109 +202,87 @@ impl HashJoin {
             return Err(OpError::Exec("join keys are empty".into()));
         }
 
-        // Extract join key columns
-        let (left_key_name, right_key_name) = &self.on[0]; // Support single key for now
-
-        let left_key_col = left
-            .columns
+        // Extract join key columns (all pairs form one composite key)
+        let left_key_cols = self
+            .on
             .iter()
-            .find(|c| &c.name == left_key_name)
-            .ok_or_else(|| OpError::Exec(format!("left join key '{}' not found", left_key_name)))?;
-
-        let right_key_col = right
-            .columns
+            .map(|(l, _)| find_key_column(left, l, "left"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let right_key_cols = self
+            .on
             .iter()
-            .find(|c| &c.name == right_key_name)
-            .ok_or_else(|| {
-                OpError::Exec(format!("right join key '{}' not found", right_key_name))
-            })?;
+            .map(|(_, r)| find_key_column(right, r, "right"))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Build phase: hash table on right side
-        let mut hash_table: HashMap<String, Vec<usize>> = HashMap::new();
+        // Build phase: hash table on right side, keyed by encoded key bytes.
+        // A null key equals nothing (SQL semantics), so such rows are left
+        // out of the table and can only be padded.
+        let mut hash_table: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+        let mut key_buf = Vec::new();
 
-        for (row_idx, val) in right_key_col.values.iter().enumerate() {
-            let key_str = scalar_to_string(val);
-            hash_table.entry(key_str).or_default().push(row_idx);
+        for row_idx in 0..right.num_rows() {
+            if has_null_key(&right_key_cols, row_idx) {
+                continue;
+            }
+            encode_row_key(&right_key_cols, row_idx, &mut key_buf);
+            match hash_table.get_mut(key_buf.as_slice()) {
+                Some(rows) => rows.push(row_idx),
+                None => {
+                    hash_table.insert(key_buf.clone(), vec![row_idx]);
+                }
+            }
         }
 
-        // Probe phase: scan left side and emit matches
-        let mut output_rows: Vec<(usize, Option<usize>)> = Vec::new(); // (left_idx, right_idx)
+        // Probe phase: each output row is a (left, right) pair of row
+        // indices, `None` on the side a right/full or left/full join pads.
+        let mut output_rows: Vec<(Option<usize>, Option<usize>)> = Vec::new();
+        let mut matched_right = vec![false; right.num_rows()];
 
-        for (left_idx, left_val) in left_key_col.values.iter().enumerate() {
-            let key_str = scalar_to_string(left_val);
-
-            if let Some(right_indices) = hash_table.get(&key_str) {
-                // Match found: emit (left_idx, right_idx) for each match
-                for &right_idx in right_indices {
-                    output_rows.push((left_idx, Some(right_idx)));
-                }
+        for left_idx in 0..left.num_rows() {
+            let matches = if has_null_key(&left_key_cols, left_idx) {
+                None
             } else {
-                // No match
-                if join_type == JoinType::Left || join_type == JoinType::Full {
-                    output_rows.push((left_idx, None));
-                }
-            }
-        }
+                encode_row_key(&left_key_cols, left_idx, &mut key_buf);
+                hash_table.get(key_buf.as_slice())
+            };
 
-        // Handle right-only rows for right/full joins
-        if join_type == JoinType::Right || join_type == JoinType::Full {
-            let mut matched_right: Vec<bool> = vec![false; right.num_rows()];
-            for (_, right_idx) in &output_rows {
-                if let Some(idx) = right_idx {
-                    matched_right[*idx] = true;
+            match matches {
+                Some(right_indices) => {
+                    for &right_idx in right_indices {
+                        output_rows.push((Some(left_idx), Some(right_idx)));
+                        matched_right[right_idx] = true;
+                    }
                 }
-            }
-
-            for (right_idx, &matched) in matched_right.iter().enumerate() {
-                if !matched {
-                    output_rows.push((0, Some(right_idx))); // Use dummy left_idx for right-only
+                None if matches!(join_type, JoinType::Left | JoinType::Full) => {
+                    output_rows.push((Some(left_idx), None));
                 }
+                None => {}
             }
         }
 
-        // Build output columns
-        let mut output_cols = Vec::new();
+        // Then the right rows no left row matched, for right/full joins
+        if matches!(join_type, JoinType::Right | JoinType::Full) {
+            output_rows.extend(
+                (0..right.num_rows())
+                    .filter(|&right_idx| !matched_right[right_idx])
+                    .map(|right_idx| (None, Some(right_idx))),
+            );
+        }
 
-        // Left columns
+        // Build output columns: left columns, then right ones (with a
+        // suffix where names conflict), null on the padded side
+        let mut output_cols = Vec::with_capacity(left.columns.len() + right.columns.len());
         for col in &left.columns {
-            let mut new_col = Column {
+            output_cols.push(Column {
                 name: col.name.clone(),
-                values: Vec::with_capacity(output_rows.len()),
-            };
-
-            for (left_idx, right_idx) in &output_rows {
-                if right_idx.is_none() || join_type != JoinType::Right {
-                    new_col.values.push(col.values[*left_idx].clone());
-                } else {
-                    new_col.values.push(Scalar::Null); // Right-only row
-                }
-            }
-
-            output_cols.push(new_col);
+                values: gather(col, output_rows.iter().map(|&(l, _)| l)),
+            });
         }
-
-        // Right columns (with suffix if name conflicts)
-        for col in &right.columns {
-            let col_name = if left.columns.iter().any(|c| c.name == col.name) {
-                format!("{}_right", col.name)
-            } else {
-                col.name.clone()
-            };
-
-            let mut new_col = Column {
+        for (col, col_name) in right.columns.iter().zip(right_names(left, right)) {
+            output_cols.push(Column {
                 name: col_name,
-                values: Vec::with_capacity(output_rows.len()),
-            };
-
-            for (_, right_idx) in &output_rows {
-                if let Some(idx) = right_idx {
-                    new_col.values.push(col.values[*idx].clone());
-                } else {
-                    new_col.values.push(Scalar::Null); // Left-only row
-                }
-            }
-
-            output_cols.push(new_col);
+                values: gather(col, output_rows.iter().map(|&(_, r)| r)),
+            });
         }
 
         Ok(RowBatch {
@@ -262,18 +290,20 @@ impl HashJoin {
         })
     }
 
-    /// Partition a RowBatch into multiple partitions based on join keys.
+    /// Partition a RowBatch into multiple partitions based on join keys,
+    /// leaving out rows whose `keep` flag is unset.
     ///
     /// Returns a vector of RowBatches, one per partition.
     fn partition_batch(
         &self,
         batch: &RowBatch,
+        keep: Option<&[bool]>,
         join_key_names: &[String],
         num_partitions: usize,
     ) -> Result<Vec<RowBatch>, OpError> {
         // Compute partition indices for each row
         let partition_indices = batch
-            .hash_columns(join_key_names, num_partitions)
+            .hash_columns_with(join_key_names, num_partitions, &self.partition_hash)
             .map_err(|e| OpError::Exec(format!("partition failed: {}", e)))?;
 
         // Initialize empty batches for each partition
@@ -292,6 +322,9 @@ impl HashJoin {
 
         // Distribute rows to partitions
         for (row_idx, &part_idx) in partition_indices.iter().enumerate() {
+            if keep.is_some_and(|k| !k[row_idx]) {
+                continue;
+            }
             for (col_idx, col) in batch.columns.iter().enumerate() {
                 partitions[part_idx].columns[col_idx]
                     .values
@@ -305,7 +338,8 @@ impl HashJoin {
     /// Grace hash join with partitioning for large datasets.
     ///
     /// Algorithm:
-    /// 1. Partition both inputs by join keys into N partitions
+    /// 1. Partition both inputs by join keys into N partitions, dropping rows
+    ///    that fail the fused input filters (`keep`)
     /// 2. Spill partitions to disk
     /// 3. For each partition pair (left[i], right[i]):
     ///    - Load left partition into memory (build hash table)
@@ -315,6 +349,7 @@ impl HashJoin {
         &self,
         left: &RowBatch,
         right: &RowBatch,
+        keep: (Option<&[bool]>, Option<&[bool]>),
         join_type: JoinType,
         budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
@@ -334,37 +369,40 @@ impl HashJoin {
         // Determine number of partitions (aim for partitions that fit in memory)
         // Use a conservative estimate: each partition should be < 1MB
         let estimated_bytes_per_row = 64;
-        let left_total_bytes = (left.num_rows() as u64) * estimated_bytes_per_row;
-        let right_total_bytes = (right.num_rows() as u64) * estimated_bytes_per_row;
+        let kept_rows = |batch: &RowBatch, keep: Option<&[bool]>| {
+            keep.map_or(batch.num_rows(), |k| k.iter().filter(|&&b| b).count()) as u64
+        };
+        let left_total_bytes = kept_rows(left, keep.0) * estimated_bytes_per_row;
+        let right_total_bytes = kept_rows(right, keep.1) * estimated_bytes_per_row;
 
         // Target partition size: try to keep each partition under 1MB
         let target_partition_bytes = 1024 * 1024; // 1MB
-        let num_partitions = ((left_total_bytes.max(right_total_bytes) / target_partition_bytes)
-            .max(1) as usize)
-            .min(256); // Cap at 256 partitions
+        let num_partitions = match self.num_partitions {
+            Some(n) => n.max(1),
+            None => ((left_total_bytes.max(right_total_bytes) / target_partition_bytes).max(1)
+                as usize)
+                .min(256), // Cap at 256 partitions
+        };
 
         // Partition both inputs
-        let left_partitions = self.partition_batch(left, &left_key_names, num_partitions)?;
-        let right_partitions = self.partition_batch(right, &right_key_names, num_partitions)?;
+        let left_partitions =
+            self.partition_batch(left, keep.0, &left_key_names, num_partitions)?;
+        let right_partitions =
+            self.partition_batch(right, keep.1, &right_key_names, num_partitions)?;
 
         // Spill partitions to disk
-        let mut left_segments: Vec<Vec<emsqrt_mem::spill::SegmentMeta>> = Vec::new();
-        let mut right_segments: Vec<Vec<emsqrt_mem::spill::SegmentMeta>> = Vec::new();
+        let mut left_segments: Vec<Vec<SegmentMeta>> = Vec::new();
+        let mut right_segments: Vec<Vec<SegmentMeta>> = Vec::new();
 
         let mut spill_mgr_guard = spill_mgr.lock().unwrap();
-        let spill_id = emsqrt_core::id::SpillId::new(1); // Use a fixed ID for this join
+        let spill_id = spill_mgr_guard.next_spill_id();
 
         for (part_idx, left_part) in left_partitions.iter().enumerate() {
             if left_part.num_rows() > 0 {
                 let run_idx = spill_mgr_guard.next_run_index();
                 let meta = spill_mgr_guard
                     .write_batch(left_part, spill_id, run_idx)
-                    .map_err(|e| {
-                        OpError::Exec(format!(
-                            "failed to spill left partition {}: {}",
-                            part_idx, e
-                        ))
-                    })?;
+                    .map_err(|e| spill_error("left", part_idx, e))?;
                 if left_segments.len() <= part_idx {
                     left_segments.resize(part_idx + 1, Vec::new());
                 }
@@ -377,12 +415,7 @@ impl HashJoin {
                 let run_idx = spill_mgr_guard.next_run_index();
                 let meta = spill_mgr_guard
                     .write_batch(right_part, spill_id, run_idx)
-                    .map_err(|e| {
-                        OpError::Exec(format!(
-                            "failed to spill right partition {}: {}",
-                            part_idx, e
-                        ))
-                    })?;
+                    .map_err(|e| spill_error("right", part_idx, e))?;
                 if right_segments.len() <= part_idx {
                     right_segments.resize(part_idx + 1, Vec::new());
                 }
@@ -396,134 +429,65 @@ impl HashJoin {
         let mut all_results = Vec::new();
 
         for part_idx in 0..num_partitions {
-            // Load left partition(s) into memory (build phase)
-            let mut left_build = RowBatch {
-                columns: Vec::new(),
-            };
-
-            if part_idx < left_segments.len() {
-                let spill_mgr_guard = spill_mgr.lock().unwrap();
-                for segment_meta in &left_segments[part_idx] {
-                    let batch = spill_mgr_guard
-                        .read_batch(segment_meta, budget)
-                        .map_err(|e| {
-                            OpError::Exec(format!(
-                                "failed to read left partition {}: {}",
-                                part_idx, e
-                            ))
-                        })?;
-
-                    if left_build.columns.is_empty() {
-                        left_build = batch;
-                    } else {
-                        // Concatenate batches (append rows)
-                        // Append rows from batch to left_build
-                        for (col_idx, col) in batch.columns.iter().enumerate() {
-                            left_build.columns[col_idx]
-                                .values
-                                .extend_from_slice(&col.values);
-                        }
-                    }
+            // Load the left partition into memory (build phase). Each
+            // partition's segments are deleted once read, so a join holds at
+            // most its spilled input on disk, shrinking as it goes.
+            let left_build = read_partition(spill_mgr, &left_segments, "left", part_idx, budget)?;
+            release_segments(spill_mgr, left_segments.get(part_idx));
+            // The whole right partition is probed at once, so that a left
+            // row is padded (left/full joins) only if no right row matched.
+            let right_probe =
+                read_partition(spill_mgr, &right_segments, "right", part_idx, budget)?;
+            release_segments(spill_mgr, right_segments.get(part_idx));
+
+            match (left_build, right_probe) {
+                (Some(left_build), Some(right_probe)) => {
+                    all_results.push(self.simple_hash_join(
+                        &left_build,
+                        &right_probe,
+                        join_type,
+                    )?);
                 }
-                drop(spill_mgr_guard);
-            }
-
-            // If left partition is empty, skip (no matches possible for inner/left joins)
-            if left_build.columns.is_empty() {
-                if join_type == JoinType::Right || join_type == JoinType::Full {
-                    // For right/full joins, we need to output unmatched right rows
-                    // Load right partition and output all rows with NULL left side
-                    if part_idx < right_segments.len() {
-                        let spill_mgr_guard = spill_mgr.lock().unwrap();
-                        for segment_meta in &right_segments[part_idx] {
-                            let right_batch = spill_mgr_guard
-                                .read_batch(segment_meta, budget)
-                                .map_err(|e| {
-                                    OpError::Exec(format!(
-                                        "failed to read right partition {}: {}",
-                                        part_idx, e
-                                    ))
-                                })?;
-
-                            // Create result with NULL left columns
-                            let mut result_cols = Vec::new();
-
-                            // Left columns (all NULL)
-                            for col in &left.columns {
-                                result_cols.push(Column {
-                                    name: col.name.clone(),
-                                    values: vec![Scalar::Null; right_batch.num_rows()],
-                                });
-                            }
-
-                            // Right columns
-                            for col in &right_batch.columns {
-                                let col_name = if left.columns.iter().any(|c| c.name == col.name) {
-                                    format!("{}_right", col.name)
-                                } else {
-                                    col.name.clone()
-                                };
-                                result_cols.push(Column {
-                                    name: col_name,
-                                    values: col.values.clone(),
-                                });
-                            }
-
-                            all_results.push(RowBatch {
-                                columns: result_cols,
-                            });
-                        }
-                        drop(spill_mgr_guard);
+                // Right rows only: right/full joins pad them with null left columns
+                (None, Some(right_probe))
+                    if matches!(join_type, JoinType::Right | JoinType::Full) =>
+                {
+                    let mut result_cols: Vec<Column> = left
+                        .columns
+                        .iter()
+                        .map(|col| Column {
+                            name: col.name.clone(),
+                            values: vec![Scalar::Null; right_probe.num_rows()],
+                        })
+                        .collect();
+                    let names = right_names(left, &right_probe);
+                    for (col, col_name) in right_probe.columns.into_iter().zip(names) {
+                        result_cols.push(Column {
+                            name: col_name,
+                            values: col.values,
+                        });
                     }
+                    all_results.push(RowBatch {
+                        columns: result_cols,
+                    });
                 }
-                continue;
-            }
-
-            // Stream right partition(s) and probe (probe phase)
-            if part_idx < right_segments.len() {
-                let spill_mgr_guard = spill_mgr.lock().unwrap();
-                for segment_meta in &right_segments[part_idx] {
-                    let right_probe =
-                        spill_mgr_guard
-                            .read_batch(segment_meta, budget)
-                            .map_err(|e| {
-                                OpError::Exec(format!(
-                                    "failed to read right partition {}: {}",
-                                    part_idx, e
-                                ))
-                            })?;
-
-                    // Perform hash join on this partition pair
-                    let partition_result =
-                        self.simple_hash_join(&left_build, &right_probe, join_type)?;
-                    all_results.push(partition_result);
-                }
-                drop(spill_mgr_guard);
-            } else if join_type == JoinType::Left || join_type == JoinType::Full {
-                // Right partition is empty but left has rows - output left rows with NULL right
-                let mut result_cols = Vec::new();
-
-                // Left columns
-                for col in &left_build.columns {
-                    result_cols.push(col.clone());
-                }
-
-                // Right columns (all NULL)
-                for col in &right.columns {
-                    let col_name = if left.columns.iter().any(|c| c.name == col.name) {
-                        format!("{}_right", col.name)
-                    } else {
-                        col.name.clone()
-                    };
-                    result_cols.push(Column {
-                        name: col_name,
-                        values: vec![Scalar::Null; left_build.num_rows()],
+                // Left rows only: left/full joins pad them with null right columns
+                (Some(left_build), None)
+                    if matches!(join_type, JoinType::Left | JoinType::Full) =>
+                {
+                    let rows = left_build.num_rows();
+                    let mut result_cols = left_build.columns;
+                    for col_name in right_names(left, right) {
+                        result_cols.push(Column {
+                            name: col_name,
+                            values: vec![Scalar::Null; rows],
+                        });
+                    }
+                    all_results.push(RowBatch {
+                        columns: result_cols,
                     });
                 }
-
-                all_results.push(RowBatch {
-                    columns: result_cols,
-                });
+                _ => {}
             }
         }
 
@@ -537,12 +501,7 @@ impl HashJoin {
                     values: Vec::new(),
                 });
             }
-            for col in &right.columns {
-                let col_name = if left.columns.iter().any(|c| c.name == col.name) {
-                    format!("{}_right", col.name)
-                } else {
-                    col.name.clone()
-                };
+            for col_name in right_names(left, right) {
                 columns.push(Column {
                     name: col_name,
                     values: Vec::new(),
@@ -570,16 +529,112 @@ impl HashJoin {
     }
 }
 
-/// Convert a scalar to a string for hash key (simplified).
-fn scalar_to_string(s: &Scalar) -> String {
-    match s {
-        Scalar::Null => "NULL".to_string(),
-        Scalar::Bool(b) => b.to_string(),
-        Scalar::I32(i) => i.to_string(),
-        Scalar::I64(i) => i.to_string(),
-        Scalar::F32(f) => f.to_string(),
-        Scalar::F64(f) => f.to_string(),
-        Scalar::Str(s) => s.clone(),
-        Scalar::Bin(b) => format!("[binary {} bytes]", b.len()),
+/// The rows spilled for one partition of one side, concatenated; `None` if
+/// the side has no rows in it.
+fn read_partition(
+    spill_mgr: &Mutex<SpillManager>,
+    segments: &[Vec<SegmentMeta>],
+    side: &str,
+    part_idx: usize,
+    budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+) -> Result<Option<RowBatch>, OpError> {
+    let Some(segments) = segments.get(part_idx) else {
+        return Ok(None);
+    };
+    let spill_mgr = spill_mgr.lock().unwrap();
+    let mut out: Option<RowBatch> = None;
+    for segment_meta in segments {
+        let batch = spill_mgr.read_batch(segment_meta, budget).map_err(|e| {
+            OpError::Exec(format!(
+                "failed to read {} partition {}: {}",
+                side, part_idx, e
+            ))
+        })?;
+        match out.as_mut() {
+            None => out = Some(batch),
+            Some(out) => {
+                for (col, more) in out.columns.iter_mut().zip(batch.columns) {
+                    col.values.extend(more.values);
+                }
+            }
+        }
     }
+    Ok(out)
+}
+
+/// Whether any key column of `row` is null.
+fn has_null_key(key_cols: &[&Column], row: usize) -> bool {
+    key_cols
+        .iter()
+        .any(|col| matches!(col.values[row], Scalar::Null))
+}
+
+/// Values of `col` at `rows`, null where a row has no index (the padded side
+/// of an outer join).
+fn gather(col: &Column, rows: impl Iterator<Item = Option<usize>>) -> Vec<Scalar> {
+    rows.map(|idx| idx.map_or(Scalar::Null, |i| col.values[i].clone()))
+        .collect()
+}
+
+/// A failed partition write is worth retrying unless the spill quota
+/// refused it.
+fn spill_error(side: &str, part_idx: usize, e: MemError) -> OpError {
+    let msg = format!("failed to spill {} partition {}: {}", side, part_idx, e);
+    match e {
+        MemError::SpillQuotaExceeded { .. } => OpError::Exec(msg),
+        _ => OpError::Recoverable(msg),
+    }
+}
+
+/// Delete a Grace join partition's segments once they have been read.
+fn release_segments(spill_mgr: &Mutex<SpillManager>, segments: Option<&Vec<SegmentMeta>>) {
+    let mut mgr = spill_mgr.lock().unwrap();
+    for meta in segments.into_iter().flatten() {
+        let _ = mgr.delete_segment(&meta.name);
+    }
+}
+
+/// `batch` plus a column `name` holding each row's position.
+fn with_row_numbers(batch: &RowBatch, name: &str) -> RowBatch {
+    let mut tagged = batch.clone();
+    tagged.columns.push(Column {
+        name: name.to_string(),
+        values: (0..batch.num_rows() as i64).map(Scalar::I64).collect(),
+    });
+    tagged
+}
+
+/// Reorder a Grace join's output of tagged inputs as the in-memory join
+/// would emit it (by left row, then right row, with right-only rows last)
+/// and drop the position columns.
+fn into_probe_order(mut out: RowBatch) -> RowBatch {
+    let mut take = |name: &str| {
+        out.columns
+            .iter()
+            .position(|c| c.name == name)
+            .map(|i| out.columns.remove(i).values)
+            .unwrap_or_default()
+    };
+    let position = |v: Option<&Scalar>| match v {
+        Some(Scalar::I64(i)) => Some(*i),
+        _ => None,
+    };
+    let (left_rows, right_rows) = (take(LEFT_ROW), take(RIGHT_ROW));
+    let mut order: Vec<usize> = (0..out.num_rows()).collect();
+    order.sort_by_key(|&row| {
+        let left = position(left_rows.get(row));
+        (left.is_none(), left, position(right_rows.get(row)))
+    });
+    for col in &mut out.columns {
+        col.values = order.iter().map(|&row| col.values[row].clone()).collect();
+    }
+    out
+}
+
+fn find_key_column<'a>(batch: &'a RowBatch, name: &str, side: &str) -> Result<&'a Column, OpError> {
+    batch
+        .columns
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| OpError::Exec(format!("{} join key '{}' not found", side, name)))
 }
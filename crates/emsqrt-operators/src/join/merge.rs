@@ -7,9 +7,12 @@
 
 use std::cmp::Ordering;
 
+use emsqrt_core::dag;
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::{RowBatch, Scalar};
 
+use crate::join::filter::InputFilters;
+use crate::join::right_names;
 use crate::plan::{Footprint, OpPlan};
 use crate::traits::{MemoryBudget, OpError, Operator};
 
@@ -17,6 +20,8 @@ use crate::traits::{MemoryBudget, OpError, Operator};
 pub struct MergeJoin {
     pub on: Vec<(String, String)>, // (left_col, right_col)
     pub join_type: String,         // "inner", "left", "right", "full"
+    /// Filters on the inputs, applied before merging.
+    pub filters: InputFilters,
 }
 
 impl Operator for MergeJoin {
@@ -37,27 +42,8 @@ impl Operator for MergeJoin {
             return Err(OpError::Plan("merge join expects two inputs".into()));
         }
 
-        // Derive output schema by concatenating left + right schemas
-        let left_schema = &input_schemas[0];
-        let right_schema = &input_schemas[1];
-
-        let mut fields = Vec::new();
-
-        // Add left fields
-        for field in &left_schema.fields {
-            fields.push(field.clone());
-        }
-
-        // Add right fields (with suffix if name conflicts)
-        for field in &right_schema.fields {
-            let mut new_field = field.clone();
-            if left_schema.fields.iter().any(|f| f.name == field.name) {
-                new_field.name = format!("{}_right", field.name);
-            }
-            fields.push(new_field);
-        }
-
-        let out_schema = Schema::new(fields);
+        let out_schema = dag::JoinType::from(parse_join_type(&self.join_type)?)
+            .output_schema(&input_schemas[0], &input_schemas[1]);
         Ok(OpPlan::new(out_schema, self.memory_need(0, 0)))
     }
 
@@ -70,6 +56,7 @@ impl Operator for MergeJoin {
             return Err(OpError::Exec("merge join needs two block inputs".into()));
         }
 
+        let inputs = self.filters.apply(inputs)?;
         let left = &inputs[0];
         let right = &inputs[1];
 
@@ -124,6 +111,17 @@ enum JoinType {
     Full,
 }
 
+impl From<JoinType> for dag::JoinType {
+    fn from(join_type: JoinType) -> Self {
+        match join_type {
+            JoinType::Inner => dag::JoinType::Inner,
+            JoinType::Left => dag::JoinType::Left,
+            JoinType::Right => dag::JoinType::Right,
+            JoinType::Full => dag::JoinType::Full,
+        }
+    }
+}
+
 /// Perform streaming merge join on two sorted RowBatches.
 fn merge_join_sorted(
     left: &RowBatch,
@@ -155,15 +153,11 @@ fn merge_join_sorted(
     }
 
     // Initialize right columns (with suffix if name conflicts)
-    for col in &right.columns {
-        let mut new_col = emsqrt_core::types::Column {
-            name: col.name.clone(),
+    for name in right_names(left, right) {
+        output_cols.push(emsqrt_core::types::Column {
+            name,
             values: Vec::new(),
-        };
-        if left.columns.iter().any(|c| c.name == col.name) {
-            new_col.name = format!("{}_right", col.name);
-        }
-        output_cols.push(new_col);
+        });
     }
 
     // Two-pointer merge algorithm
@@ -359,32 +353,18 @@ fn compare_scalar_tuples(a: &[Scalar], b: &[Scalar]) -> Ordering {
                     return cmp;
                 }
             }
+            // Dates, timestamps, decimals and mixed types.
             _ => {
-                // Mixed types: compare by type order
-                let x_order = scalar_type_order(x);
-                let y_order = scalar_type_order(y);
-                return x_order.cmp(&y_order);
+                let cmp = emsqrt_core::types::scalar_cmp(x, y);
+                if cmp != Ordering::Equal {
+                    return cmp;
+                }
             }
         }
     }
     a.len().cmp(&b.len())
 }
 
-/// Get type order for scalar (for mixed-type comparisons).
-fn scalar_type_order(s: &Scalar) -> u8 {
-    use emsqrt_core::types::Scalar::*;
-    match s {
-        Null => 0,
-        Bool(_) => 1,
-        I32(_) => 2,
-        I64(_) => 3,
-        F32(_) => 4,
-        F64(_) => 5,
-        Str(_) => 6,
-        Bin(_) => 7,
-    }
-}
-
 /// Emit a row from source batch to output columns.
 fn emit_row(
     source: &RowBatch,
@@ -1,4 +1,21 @@
 //! Join operators (module).
 
+pub mod filter;
 pub mod hash;
 pub mod merge;
+pub mod nested_loop;
+
+use emsqrt_core::dag::RIGHT_SUFFIX;
+use emsqrt_core::schema::dedup_names;
+use emsqrt_core::types::RowBatch;
+
+/// Output names of `right`'s columns after `left`'s, as
+/// `JoinType::output_schema` names them.
+pub(crate) fn right_names(left: &RowBatch, right: &RowBatch) -> Vec<String> {
+    let names = left
+        .columns
+        .iter()
+        .chain(&right.columns)
+        .map(|c| c.name.clone());
+    dedup_names(names, RIGHT_SUFFIX).split_off(left.columns.len())
+}
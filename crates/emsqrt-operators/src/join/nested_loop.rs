@@ -0,0 +1,288 @@
+//! Block nested-loop join on an arbitrary condition (a theta join), or on
+//! none (a cross join).
+//!
+//! The left input is taken in chunks, and each chunk is paired with every
+//! chunk of the right input. The condition is evaluated over the candidate
+//! pairs, whose columns are named as in the join's output (right columns
+//! whose name is taken get the `_right` suffix). Rows come out in probe
+//! order, like the in-memory hash join: each left row with its matches in
+//! right input order (or padded with nulls in a left join).
+//!
+//! The right input is held in memory when the budget has room for it;
+//! otherwise it is spilled in chunks and read back for every left chunk.
+
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::dag;
+use emsqrt_core::expr::{ArithErrorPolicy, EvalContext, Expr};
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::BudgetGuardImpl;
+use emsqrt_mem::spill::SegmentMeta;
+use emsqrt_mem::SpillManager;
+
+use crate::join::filter::select_rows;
+use crate::join::hash::JoinType;
+use crate::join::right_names;
+use crate::plan::{Footprint, OpPlan};
+use crate::sort::run::{empty_like, row_bytes};
+use crate::traits::{OpError, Operator};
+
+pub struct NestedLoopJoin {
+    /// Condition over the output columns; `None` keeps every pair.
+    pub condition: Option<String>,
+    /// "inner" or "left".
+    pub join_type: String,
+    pub spill_mgr: Option<Arc<Mutex<SpillManager>>>,
+    /// Candidate pairs (left chunk rows × right chunk rows) evaluated at once.
+    pub pairs_per_chunk: usize,
+    /// Run seed for `hash()` and `sample_hash()` in the condition.
+    pub seed: u64,
+    /// What a division by zero yields.
+    pub arith_errors: ArithErrorPolicy,
+}
+
+impl Default for NestedLoopJoin {
+    fn default() -> Self {
+        Self {
+            condition: None,
+            join_type: "inner".to_string(),
+            spill_mgr: None,
+            pairs_per_chunk: 64 * 1024,
+            seed: 0,
+            arith_errors: ArithErrorPolicy::default(),
+        }
+    }
+}
+
+/// Where a chunk of the right input is read from.
+enum RightChunk {
+    Memory(Range<usize>),
+    Spilled(SegmentMeta),
+}
+
+impl Operator for NestedLoopJoin {
+    fn name(&self) -> &'static str {
+        "join_nested_loop"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        // One chunk of candidate pairs, plus the right input when it fits.
+        Footprint {
+            bytes_per_row: 2,
+            overhead_bytes: 1024 * 1024,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        if input_schemas.len() != 2 {
+            return Err(OpError::Plan("nested-loop join expects two inputs".into()));
+        }
+        let join_type = self.parse_join_type().map_err(OpError::Plan)?;
+        let out_schema =
+            dag::JoinType::from(join_type).output_schema(&input_schemas[0], &input_schemas[1]);
+        Ok(OpPlan::new(out_schema, self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        if inputs.len() != 2 {
+            return Err(OpError::Exec(
+                "nested-loop join needs two block inputs".into(),
+            ));
+        }
+        let (left, right) = (&inputs[0], &inputs[1]);
+        let join_type = self.parse_join_type().map_err(OpError::Exec)?;
+        let condition = self
+            .condition
+            .as_deref()
+            .map(|p| {
+                Expr::parse(p)
+                    .map_err(|e| OpError::Exec(format!("invalid join condition '{}': {}", p, e)))
+            })
+            .transpose()?;
+        let names = right_names(left, right);
+
+        // Square-ish chunks keep each batch of candidate pairs near `pairs_per_chunk`.
+        let pairs = self.pairs_per_chunk.max(1);
+        let right_chunk_rows = (pairs as f64).sqrt().max(1.0) as usize;
+        let left_chunk_rows = (pairs / right_chunk_rows).max(1);
+
+        let right_bytes: usize = (0..right.num_rows()).map(|r| row_bytes(right, r)).sum();
+        let right_guard = budget.try_acquire(right_bytes, "join_nested_loop");
+        let ranges = (0..right.num_rows())
+            .step_by(right_chunk_rows)
+            .map(|start| start..(start + right_chunk_rows).min(right.num_rows()));
+        let chunks: Vec<RightChunk> = match (&right_guard, &self.spill_mgr) {
+            (None, Some(spill_mgr)) => {
+                let mut mgr = spill_mgr.lock().unwrap();
+                let spill_id = mgr.next_spill_id();
+                ranges
+                    .map(|range| {
+                        let run = mgr.next_run_index();
+                        mgr.write_batch(&slice(right, range), spill_id, run)
+                            .map(RightChunk::Spilled)
+                            .map_err(|e| {
+                                OpError::Recoverable(format!(
+                                    "failed to spill nested-loop join input: {}",
+                                    e
+                                ))
+                            })
+                    })
+                    .collect::<Result<_, _>>()?
+            }
+            _ => ranges.map(RightChunk::Memory).collect(),
+        };
+
+        let ctx = EvalContext {
+            seed: self.seed,
+            row_base: 0,
+            arith_errors: self.arith_errors,
+        };
+        let mut out = output_like(left, right, &names);
+        for left_start in (0..left.num_rows()).step_by(left_chunk_rows) {
+            let left_rows = left_start..(left_start + left_chunk_rows).min(left.num_rows());
+            // Matching pairs of this left chunk, and the left row of each.
+            let mut matched = output_like(left, right, &names);
+            let mut matched_left: Vec<usize> = Vec::new();
+            for chunk in &chunks {
+                let spilled;
+                let right_chunk = match chunk {
+                    RightChunk::Memory(range) => &slice(right, range.clone()),
+                    RightChunk::Spilled(meta) => {
+                        let mgr = self.spill_mgr.as_ref().expect("spilled without a manager");
+                        spilled = mgr.lock().unwrap().read_batch(meta, budget).map_err(|e| {
+                            OpError::Exec(format!("failed to read nested-loop join input: {}", e))
+                        })?;
+                        &spilled
+                    }
+                };
+                let candidates = pair_rows(left, left_rows.clone(), right_chunk, &names);
+                let keep = match &condition {
+                    Some(expr) => expr.evaluate_selection(&candidates, &ctx).map_err(|e| {
+                        OpError::Exec(format!("join condition evaluation failed: {}", e))
+                    })?,
+                    None => vec![true; candidates.num_rows()],
+                };
+                let per_left = right_chunk.num_rows();
+                for (pair, _) in keep.iter().enumerate().filter(|(_, &k)| k) {
+                    matched_left.push(left_rows.start + pair / per_left);
+                }
+                append(&mut matched, select_rows(&candidates, &keep));
+            }
+
+            if join_type == JoinType::Left {
+                let mut hit = vec![false; left_rows.len()];
+                for &l in &matched_left {
+                    hit[l - left_rows.start] = true;
+                }
+                for l in left_rows.clone().filter(|l| !hit[l - left_rows.start]) {
+                    for (col, src) in matched.columns.iter_mut().zip(&left.columns) {
+                        col.values.push(src.values[l].clone());
+                    }
+                    for col in &mut matched.columns[left.columns.len()..] {
+                        col.values.push(Scalar::Null);
+                    }
+                    matched_left.push(l);
+                }
+            }
+
+            // Probe order: by left row; the stable sort keeps right input order.
+            let mut order: Vec<usize> = (0..matched_left.len()).collect();
+            order.sort_by_key(|&i| matched_left[i]);
+            for (col, from) in out.columns.iter_mut().zip(&matched.columns) {
+                col.values
+                    .extend(order.iter().map(|&i| from.values[i].clone()));
+            }
+        }
+
+        if let Some(spill_mgr) = &self.spill_mgr {
+            let mut mgr = spill_mgr.lock().unwrap();
+            for chunk in &chunks {
+                if let RightChunk::Spilled(meta) = chunk {
+                    let _ = mgr.delete_segment(&meta.name);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl NestedLoopJoin {
+    fn parse_join_type(&self) -> Result<JoinType, String> {
+        match JoinType::parse(&self.join_type)? {
+            join_type @ (JoinType::Inner | JoinType::Left) => Ok(join_type),
+            _ => Err(format!(
+                "nested-loop join supports inner and left joins, not '{}'",
+                self.join_type
+            )),
+        }
+    }
+}
+
+/// Rows `range` of `batch`.
+fn slice(batch: &RowBatch, range: Range<usize>) -> RowBatch {
+    RowBatch {
+        columns: batch
+            .columns
+            .iter()
+            .map(|c| Column {
+                name: c.name.clone(),
+                values: c.values[range.clone()].to_vec(),
+            })
+            .collect(),
+    }
+}
+
+/// An empty batch with the join's output columns.
+fn output_like(left: &RowBatch, right: &RowBatch, names: &[String]) -> RowBatch {
+    let mut out = empty_like(left);
+    out.columns
+        .extend(right.columns.iter().zip(names).map(|(_, name)| Column {
+            name: name.clone(),
+            values: Vec::new(),
+        }));
+    out
+}
+
+/// Every pair of `left_rows` and `right`'s rows, by left row then right row.
+fn pair_rows(
+    left: &RowBatch,
+    left_rows: Range<usize>,
+    right: &RowBatch,
+    names: &[String],
+) -> RowBatch {
+    let n = right.num_rows();
+    let mut columns: Vec<Column> = left
+        .columns
+        .iter()
+        .map(|c| Column {
+            name: c.name.clone(),
+            values: left_rows
+                .clone()
+                .flat_map(|l| std::iter::repeat_n(&c.values[l], n).cloned())
+                .collect(),
+        })
+        .collect();
+    columns.extend(right.columns.iter().zip(names).map(|(c, name)| {
+        Column {
+            name: name.clone(),
+            values: left_rows
+                .clone()
+                .flat_map(|_| c.values.iter().cloned())
+                .collect(),
+        }
+    }));
+    RowBatch { columns }
+}
+
+fn append(into: &mut RowBatch, batch: RowBatch) {
+    for (col, from) in into.columns.iter_mut().zip(batch.columns) {
+        col.values.extend(from.values);
+    }
+}
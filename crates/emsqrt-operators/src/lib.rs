@@ -7,18 +7,25 @@
 //! - Each operator exposes a planning surface (`OpPlan`) with an estimated
 //    footprint model so TE can choose block sizes and the engine can enforce caps.
 
+pub mod context;
 pub mod plan;
 pub mod registry;
 pub mod traits;
 
 pub mod agregate;
 pub mod filter;
+pub mod filter_in;
+pub mod limit;
 pub mod map;
+pub mod profile;
 pub mod project;
+pub mod top_k;
+pub mod union;
 
 pub mod join;
 pub mod sort;
 pub mod window;
 
+pub use context::{CancellationToken, OpContext, OpMetrics, SpillScope};
 pub use plan::{Footprint, OpPlan};
-pub use traits::{BlockStream, OpError, Operator};
+pub use traits::{BlockSource, BlockStream, MemoryBudget, OpError, Operator};
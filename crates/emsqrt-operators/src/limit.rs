@@ -0,0 +1,345 @@
+//! `limit`/`offset`, in input order or by sort keys.
+//!
+//! [`Limit`] passes on the rows of its input whose positions fall in
+//! `[offset, offset + limit)`. The operator's blocks run in TE order, so a
+//! running row count places each block; a retried block keeps the position
+//! it had.
+//!
+//! [`TopN`] keeps the first `offset + limit` rows by its sort keys in a
+//! bounded heap (ties keep input order) instead of sorting the whole input.
+//! The operator's last block emits them in order; earlier blocks emit no
+//! rows.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use emsqrt_core::dag::SortKey;
+use emsqrt_core::id::BlockId;
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{scalar_cmp, Column, RowBatch, Scalar};
+use emsqrt_mem::guard::BudgetGuardImpl;
+
+use crate::context::OpContext;
+use crate::plan::{Footprint, OpPlan};
+use crate::sort::run::row_bytes;
+use crate::traits::{MemoryBudget, OpError, Operator};
+
+#[derive(Default)]
+struct LimitState {
+    /// Input rows placed so far.
+    seen: u64,
+    /// Position of each block's first row.
+    starts: HashMap<BlockId, u64>,
+}
+
+#[derive(Default)]
+pub struct Limit {
+    pub limit: usize,
+    pub offset: usize,
+    state: Mutex<LimitState>,
+}
+
+impl Limit {
+    pub fn new(limit: usize, offset: usize) -> Self {
+        Self {
+            limit,
+            offset,
+            ..Default::default()
+        }
+    }
+}
+
+impl Operator for Limit {
+    fn name(&self) -> &'static str {
+        "limit"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        Footprint {
+            bytes_per_row: 0,
+            overhead_bytes: 0,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let schema = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("limit expects one input".into()))?
+            .clone();
+        Ok(OpPlan::new(schema, self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+
+    fn eval_block_with(
+        &self,
+        inputs: &[RowBatch],
+        ctx: &OpContext,
+        _budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("missing input".into()))?;
+        let rows = input.num_rows();
+        let start = {
+            let mut state = self.state.lock().unwrap();
+            match ctx.block_id.and_then(|id| state.starts.get(&id).copied()) {
+                Some(start) => start,
+                None => {
+                    let start = state.seen;
+                    state.seen += rows as u64;
+                    if let Some(id) = ctx.block_id {
+                        state.starts.insert(id, start);
+                    }
+                    start
+                }
+            }
+        };
+        let local = |position: usize| (position as u64).saturating_sub(start).min(rows as u64);
+        let from = local(self.offset) as usize;
+        let to = local(self.offset.saturating_add(self.limit)) as usize;
+        Ok(RowBatch {
+            columns: input
+                .columns
+                .iter()
+                .map(|c| Column {
+                    name: c.name.clone(),
+                    values: c.values[from..to].to_vec(),
+                })
+                .collect(),
+        })
+    }
+
+    fn open(&self) -> Result<(), OpError> {
+        *self.state.lock().unwrap() = LimitState::default();
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), OpError> {
+        *self.state.lock().unwrap() = LimitState::default();
+        Ok(())
+    }
+}
+
+/// A row kept by [`TopN`]; the greatest entry is the first to go.
+struct Entry {
+    /// Sort key values, each with whether it sorts descending.
+    keys: Vec<(Scalar, bool)>,
+    /// Arrival order, so ties keep input order.
+    seq: u64,
+    row: Vec<Scalar>,
+    bytes: usize,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.keys
+            .iter()
+            .zip(&other.keys)
+            .map(|((a, descending), (b, _))| {
+                let order = scalar_cmp(a, b);
+                if *descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            })
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+            .then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+#[derive(Default)]
+struct TopNState {
+    heap: BinaryHeap<Entry>,
+    seq: u64,
+    merged: HashSet<BlockId>,
+    /// Holds the kept rows' bytes.
+    guard: Option<BudgetGuardImpl>,
+}
+
+/// The first `offset + limit` rows by `order_by`, kept in a bounded heap.
+#[derive(Default)]
+pub struct TopN {
+    pub order_by: Vec<SortKey>,
+    pub limit: usize,
+    pub offset: usize,
+    state: Mutex<TopNState>,
+}
+
+impl TopN {
+    pub fn new(order_by: Vec<SortKey>, limit: usize, offset: usize) -> Self {
+        Self {
+            order_by,
+            limit,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    fn keep(&self) -> usize {
+        self.offset.saturating_add(self.limit)
+    }
+}
+
+impl Operator for TopN {
+    fn name(&self) -> &'static str {
+        "top_n"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        // The kept rows, whatever the input size.
+        Footprint {
+            bytes_per_row: 0,
+            overhead_bytes: (self.keep() * 256) as u64,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let schema = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("top_n expects one input".into()))?;
+        if let Some(key) = self
+            .order_by
+            .iter()
+            .find(|k| schema.index_of(&k.column).is_none())
+        {
+            return Err(OpError::Plan(format!(
+                "top_n sort column '{}' not in input schema",
+                key.column
+            )));
+        }
+        Ok(OpPlan::new(schema.clone(), self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+
+    fn eval_block_with(
+        &self,
+        inputs: &[RowBatch],
+        ctx: &OpContext,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("missing input".into()))?;
+        let key_columns = self
+            .order_by
+            .iter()
+            .map(|key| {
+                input
+                    .columns
+                    .iter()
+                    .position(|c| c.name == key.column)
+                    .map(|i| (i, key.descending))
+                    .ok_or_else(|| {
+                        OpError::Exec(format!("top_n sort column '{}' not found", key.column))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut state = self.state.lock().unwrap();
+        // A retried block is folded in once.
+        if ctx.block_id.is_none_or(|id| state.merged.insert(id)) {
+            let keep = self.keep();
+            for r in 0..input.num_rows() {
+                let entry = Entry {
+                    keys: key_columns
+                        .iter()
+                        .map(|&(i, descending)| (input.columns[i].values[r].clone(), descending))
+                        .collect(),
+                    seq: state.seq,
+                    row: Vec::new(),
+                    bytes: row_bytes(input, r),
+                };
+                state.seq += 1;
+                if state.heap.len() >= keep && state.heap.peek().is_none_or(|worst| entry >= *worst)
+                {
+                    continue;
+                }
+                let entry = Entry {
+                    row: input.columns.iter().map(|c| c.values[r].clone()).collect(),
+                    ..entry
+                };
+                state.heap.push(entry);
+                if state.heap.len() > keep {
+                    state.heap.pop();
+                }
+            }
+            let bytes = state.heap.iter().map(|e| e.bytes).sum();
+            let reserved = match state.guard.as_mut() {
+                Some(guard) => guard.try_resize(bytes),
+                None => {
+                    state.guard = budget.try_acquire(bytes, "top_n");
+                    state.guard.is_some()
+                }
+            };
+            if !reserved {
+                return Err(OpError::Exec(format!(
+                    "memory budget unavailable for the {} rows top_n keeps ({} bytes)",
+                    state.heap.len(),
+                    bytes
+                )));
+            }
+        }
+
+        let last = ctx.range.as_ref().is_none_or(|r| r.open_end);
+        let mut columns: Vec<Column> = input
+            .columns
+            .iter()
+            .map(|c| Column {
+                name: c.name.clone(),
+                values: Vec::new(),
+            })
+            .collect();
+        if last {
+            let mut kept: Vec<&Entry> = state.heap.iter().collect();
+            kept.sort();
+            for entry in kept.into_iter().skip(self.offset) {
+                for (column, value) in columns.iter_mut().zip(&entry.row) {
+                    column.values.push(value.clone());
+                }
+            }
+        }
+        Ok(RowBatch { columns })
+    }
+
+    fn open(&self) -> Result<(), OpError> {
+        *self.state.lock().unwrap() = TopNState::default();
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), OpError> {
+        // Release the kept rows and their budget.
+        *self.state.lock().unwrap() = TopNState::default();
+        Ok(())
+    }
+}
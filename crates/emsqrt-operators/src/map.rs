@@ -1,16 +1,38 @@
-//! Map operator with column renaming support.
+//! Map operator: renames and computed columns.
+//!
+//! The list is comma-separated `expr [AS alias]` items (see
+//! `Expr::parse_list`), applied in order:
+//! "old_name AS new_name" renames a column in place,
+//! "qty * price AS total" appends a computed column (replacing any column
+//! already called `total`), and a bare column is kept as is.
 
+use std::sync::Mutex;
+
+use emsqrt_core::expr::{map_schema, ArithErrorPolicy, EvalContext, Expr};
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::{Column, RowBatch};
-use std::collections::HashMap;
 
 use crate::plan::{Footprint, OpPlan};
 use crate::traits::{MemoryBudget, OpError, Operator};
 
 #[derive(Default)]
 pub struct Map {
-    /// Column rename map: old_name -> new_name
-    pub renames: HashMap<String, String>,
+    /// Map list string (parsed on demand); `None` passes rows through.
+    pub expr: Option<String>,
+    /// Run seed for `rand()`, `hash()` and `sample_hash()`.
+    pub seed: u64,
+    /// What a division by zero yields.
+    pub arith_errors: ArithErrorPolicy,
+    /// Rows evaluated so far, so `rand()` is keyed on stream position rather
+    /// than on the (budget-dependent) block boundaries.
+    pub rows_seen: Mutex<u64>,
+}
+
+impl Map {
+    fn items(&self, expr_str: &str) -> Result<Vec<(Expr, Option<String>)>, String> {
+        Expr::parse_list(expr_str)
+            .map_err(|e| format!("failed to parse map list '{}': {}", expr_str, e))
+    }
 }
 
 impl Operator for Map {
@@ -27,18 +49,16 @@ impl Operator for Map {
     }
 
     fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
-        let mut schema = input_schemas
-            .get(0)
-            .ok_or_else(|| OpError::Plan("map expects one input".into()))?
-            .clone();
-
-        // Apply renames to the schema
-        for field in &mut schema.fields {
-            if let Some(new_name) = self.renames.get(&field.name) {
-                field.name = new_name.clone();
-            }
-        }
-
+        let input = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("map expects one input".into()))?;
+        let schema = match self.expr {
+            Some(ref expr_str) => self
+                .items(expr_str)
+                .and_then(|items| map_schema(&items, input))
+                .map_err(OpError::Plan)?,
+            None => input.clone(),
+        };
         Ok(OpPlan::new(schema, self.memory_need(0, 0)))
     }
 
@@ -48,30 +68,53 @@ impl Operator for Map {
         _budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("missing input".into()))?;
 
-        // If no renames, pass through
-        if self.renames.is_empty() {
+        // If no list, pass through
+        let Some(ref expr_str) = self.expr else {
             return Ok(input.clone());
-        }
+        };
+        let items = self.items(expr_str).map_err(OpError::Exec)?;
 
-        // Apply renames to columns
-        let mut renamed_cols = Vec::new();
-        for col in &input.columns {
-            let new_name = self
-                .renames
-                .get(&col.name)
-                .cloned()
-                .unwrap_or_else(|| col.name.clone());
-            renamed_cols.push(Column {
-                name: new_name,
-                values: col.values.clone(),
+        let mut rows_seen = self.rows_seen.lock().unwrap();
+        let ctx = EvalContext {
+            seed: self.seed,
+            row_base: *rows_seen,
+            arith_errors: self.arith_errors,
+        };
+
+        let mut out = input.clone();
+        for (expr, alias) in &items {
+            let Some(alias) = alias else {
+                continue;
+            };
+            if let Expr::Column(name) = expr {
+                let idx = out
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == name)
+                    .ok_or_else(|| OpError::Exec(format!("map: column '{}' not found", name)))?;
+                out.columns[idx].name = alias.clone();
+                let mut i = 0;
+                out.columns.retain(|c| {
+                    i += 1;
+                    i - 1 == idx || &c.name != alias
+                });
+                continue;
+            }
+            // Computed columns see the columns earlier items produced.
+            let values = expr
+                .evaluate_batch_with(&out, &ctx)
+                .map_err(|e| OpError::Exec(format!("map column '{}' failed {}", alias, e)))?
+                .values;
+            out.columns.retain(|c| &c.name != alias);
+            out.columns.push(Column {
+                name: alias.clone(),
+                values,
             });
         }
-
-        Ok(RowBatch {
-            columns: renamed_cols,
-        })
+        *rows_seen += input.num_rows() as u64;
+        Ok(out)
     }
 }
@@ -0,0 +1,419 @@
+//! Per-column profile of the rows an operator sees (`emsqrt profile`).
+//!
+//! One pass in fixed memory per column:
+//! - `ColumnStats` counts rows and nulls and tracks min/max exactly;
+//! - a k-minimum-values sketch of value hashes estimates distinct values,
+//!   exactly while there are at most `DISTINCT_SKETCH` of them;
+//! - a Space-Saving sketch (see `top_k`) finds the most frequent values,
+//!   of which those known to occur more than once are reported;
+//! - a uniform reservoir sample of numeric values is binned into an
+//!   equal-width histogram between the exact min and max.
+//!
+//! The operator's last block emits one row per input column; earlier blocks
+//! emit no rows. Top values and histogram bins are JSON text columns.
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use emsqrt_core::hash::{PartitionHashKind, PartitionHasher};
+use emsqrt_core::id::BlockId;
+use emsqrt_core::prelude::{DataType, Field, Schema};
+use emsqrt_core::stats::ColumnStats;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::BudgetGuardImpl;
+
+use crate::context::OpContext;
+use crate::plan::{Footprint, OpPlan};
+use crate::top_k::SpaceSaving;
+use crate::traits::{MemoryBudget, OpError, Operator};
+
+/// Hashes kept by the distinct-count sketch.
+pub const DISTINCT_SKETCH: usize = 4096;
+
+/// Approximate in-memory size of one Space-Saving counter / one kept hash.
+const COUNTER_BYTES: usize = 128;
+const HASH_BYTES: usize = 32;
+
+/// K-minimum-values estimate of the number of distinct values.
+#[derive(Debug, Clone)]
+pub struct DistinctSketch {
+    k: usize,
+    hashes: BTreeSet<u64>,
+}
+
+impl DistinctSketch {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k: k.max(2),
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: &Scalar) {
+        // xxHash64 is stable across versions, so estimates are reproducible.
+        let hash = PartitionHasher::new(PartitionHashKind::Xxhash64, 0).hash_values(&[value]);
+        if self.hashes.len() < self.k {
+            self.hashes.insert(hash);
+        } else if hash < *self.hashes.last().expect("full sketch has hashes")
+            && self.hashes.insert(hash)
+        {
+            self.hashes.pop_last();
+        }
+    }
+
+    /// Exact below `k` distinct values; otherwise `(k - 1) / kth`, with the
+    /// k-th smallest hash scaled to `(0, 1]`.
+    pub fn estimate(&self) -> u64 {
+        if self.hashes.len() < self.k {
+            return self.hashes.len() as u64;
+        }
+        let kth = *self.hashes.last().expect("full sketch has hashes") as f64 + 1.0;
+        ((self.k - 1) as f64 * 2f64.powi(64) / kth).round() as u64
+    }
+}
+
+/// Uniform sample of at most `capacity` values (Algorithm R), with a fixed
+/// seed so a profile of the same input is the same.
+#[derive(Debug, Clone)]
+pub struct Reservoir {
+    capacity: usize,
+    seen: u64,
+    values: Vec<f64>,
+    rng: u64,
+}
+
+impl Reservoir {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            values: Vec::new(),
+            rng: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    pub fn insert(&mut self, value: f64) {
+        self.seen += 1;
+        if self.values.len() < self.capacity {
+            self.values.push(value);
+            return;
+        }
+        let slot = self.next() % self.seen;
+        if slot < self.capacity as u64 {
+            self.values[slot as usize] = value;
+        }
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Values offered so far (sampled or not).
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    // splitmix64
+    fn next(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// A most frequent value: `count` may exceed the true count by `error`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: u64,
+    pub error: u64,
+}
+
+/// Histogram bin `[lower, upper)`; the last bin includes `upper`. Counts
+/// are scaled from the sample to all non-null values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+/// `bins` equal-width bins over `[min, max]` of `sample`, scaled to `total`.
+pub fn histogram(sample: &[f64], min: f64, max: f64, bins: usize, total: u64) -> Vec<HistogramBin> {
+    if sample.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    // A single value gets a single bin.
+    let bins = if max > min { bins } else { 1 };
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0u64; bins];
+    for v in sample {
+        let bin = if width > 0.0 {
+            (((v - min) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        counts[bin] += 1;
+    }
+    let scale = total as f64 / sample.len() as f64;
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| HistogramBin {
+            lower: min + width * i as f64,
+            upper: if i + 1 == bins {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: (count as f64 * scale).round() as u64,
+        })
+        .collect()
+}
+
+fn as_f64(value: &Scalar) -> Option<f64> {
+    match value {
+        Scalar::I32(v) => Some(*v as f64),
+        Scalar::I64(v) => Some(*v as f64),
+        Scalar::F32(v) => Some(*v as f64),
+        Scalar::F64(v) => Some(*v).filter(|v| v.is_finite()),
+        _ => None,
+    }
+}
+
+struct ColumnProfiler {
+    name: String,
+    data_type: Option<DataType>,
+    stats: ColumnStats,
+    distinct: DistinctSketch,
+    top: SpaceSaving,
+    sample: Reservoir,
+}
+
+impl ColumnProfiler {
+    fn update(&mut self, value: &Scalar) {
+        self.stats.update(value);
+        if matches!(value, Scalar::Null) {
+            return;
+        }
+        self.data_type.get_or_insert_with(|| value.data_type());
+        self.distinct.insert(value);
+        self.top.insert(value);
+        if let Some(v) = as_f64(value) {
+            self.sample.insert(v);
+        }
+    }
+}
+
+/// The per-column state of a run and the blocks already folded into it.
+#[derive(Default)]
+struct ProfileState {
+    columns: Vec<ColumnProfiler>,
+    seen: HashSet<BlockId>,
+    _guard: Option<BudgetGuardImpl>,
+}
+
+#[derive(Default)]
+pub struct Profile {
+    /// Most frequent values reported per column.
+    pub top: usize,
+    /// Histogram bins of numeric columns.
+    pub bins: usize,
+    /// Numeric values sampled per column for the histogram.
+    pub sample: usize,
+    state: Mutex<ProfileState>,
+}
+
+impl Profile {
+    pub fn new(top: usize, bins: usize, sample: usize) -> Self {
+        Self {
+            top,
+            bins,
+            sample,
+            ..Default::default()
+        }
+    }
+
+    /// Space-Saving counters per column: ten per reported value, at least 64.
+    fn capacity(&self) -> usize {
+        (self.top * 10).max(64)
+    }
+
+    fn column_bytes(&self) -> usize {
+        self.capacity() * COUNTER_BYTES + DISTINCT_SKETCH * HASH_BYTES + self.sample * 8
+    }
+
+    fn output(&self, columns: &[ColumnProfiler]) -> Result<RowBatch, OpError> {
+        let mut out: Vec<Vec<Scalar>> = vec![Vec::new(); 9];
+        for c in columns {
+            let top: Vec<TopValue> = c
+                .top
+                .top(self.top)
+                .into_iter()
+                .filter(|(_, count, error)| count - error > 1)
+                .map(|(value, count, error)| TopValue {
+                    value: value.to_string(),
+                    count,
+                    error,
+                })
+                .collect();
+            let bins = match (
+                c.stats.min.as_ref().and_then(as_f64),
+                c.stats.max.as_ref().and_then(as_f64),
+            ) {
+                (Some(min), Some(max)) => {
+                    histogram(c.sample.values(), min, max, self.bins, c.sample.seen())
+                }
+                _ => Vec::new(),
+            };
+            let json = |v: serde_json::Result<String>| {
+                v.map(Scalar::Str)
+                    .map_err(|e| OpError::Exec(format!("encoding profile: {}", e)))
+            };
+            let text = |v: &Option<Scalar>| {
+                v.as_ref()
+                    .map_or(Scalar::Null, |v| Scalar::Str(v.to_string()))
+            };
+            let row = [
+                Scalar::Str(c.name.clone()),
+                c.data_type
+                    .as_ref()
+                    .map_or(Scalar::Null, |t| Scalar::Str(format!("{:?}", t))),
+                Scalar::I64(c.stats.total_count as i64),
+                Scalar::I64(c.stats.null_count as i64),
+                Scalar::I64(c.distinct.estimate() as i64),
+                text(&c.stats.min),
+                text(&c.stats.max),
+                json(serde_json::to_string(&top))?,
+                json(serde_json::to_string(&bins))?,
+            ];
+            for (values, v) in out.iter_mut().zip(row) {
+                values.push(v);
+            }
+        }
+        Ok(RowBatch {
+            columns: output_schema()
+                .fields
+                .into_iter()
+                .zip(out)
+                .map(|(f, values)| Column {
+                    name: f.name,
+                    values,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// One row per profiled column.
+pub fn output_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("column", DataType::Utf8, false),
+        Field::new("type", DataType::Utf8, true),
+        Field::new("rows", DataType::Int64, false),
+        Field::new("nulls", DataType::Int64, false),
+        Field::new("distinct", DataType::Int64, false),
+        Field::new("min", DataType::Utf8, true),
+        Field::new("max", DataType::Utf8, true),
+        Field::new("top_values", DataType::Utf8, false),
+        Field::new("histogram", DataType::Utf8, false),
+    ])
+}
+
+impl Operator for Profile {
+    fn name(&self) -> &'static str {
+        "profile"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        // Sketches of a fixed size per column, whatever the input size.
+        Footprint {
+            bytes_per_row: 0,
+            overhead_bytes: self.column_bytes() as u64,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("profile expects one input".into()))?;
+        Ok(OpPlan::new(output_schema(), self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+
+    fn eval_block_with(
+        &self,
+        inputs: &[RowBatch],
+        ctx: &OpContext,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("missing input".into()))?;
+        let mut state = self.state.lock().unwrap();
+        if state._guard.is_none() {
+            let bytes = input.columns.len().max(1) * self.column_bytes();
+            state._guard = Some(budget.try_acquire(bytes, "profile").ok_or_else(|| {
+                OpError::Exec("memory budget unavailable for profile sketches".into())
+            })?);
+            state.columns = input
+                .columns
+                .iter()
+                .map(|c| ColumnProfiler {
+                    name: c.name.clone(),
+                    data_type: None,
+                    stats: ColumnStats::new(),
+                    distinct: DistinctSketch::new(DISTINCT_SKETCH),
+                    top: SpaceSaving::new(self.capacity()),
+                    sample: Reservoir::new(self.sample),
+                })
+                .collect();
+        }
+        let columns = state
+            .columns
+            .iter()
+            .map(|p| {
+                input
+                    .columns
+                    .iter()
+                    .find(|c| c.name == p.name)
+                    .ok_or_else(|| OpError::Exec(format!("profile column '{}' not found", p.name)))
+            })
+            .collect::<Result<Vec<&Column>, _>>()?;
+        // A retried block is counted once.
+        if ctx.block_id.is_none_or(|id| state.seen.insert(id)) {
+            for (profiler, column) in state.columns.iter_mut().zip(columns) {
+                ctx.check_cancelled()?;
+                column.values.iter().for_each(|v| profiler.update(v));
+            }
+        }
+        let last = ctx.range.as_ref().is_none_or(|r| r.open_end);
+        if last {
+            self.output(&state.columns)
+        } else {
+            self.output(&[])
+        }
+    }
+
+    fn open(&self) -> Result<(), OpError> {
+        *self.state.lock().unwrap() = ProfileState::default();
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), OpError> {
+        // Release the sketches and their budget.
+        *self.state.lock().unwrap() = ProfileState::default();
+        Ok(())
+    }
+}
@@ -31,7 +31,7 @@ impl Operator for Project {
 
     fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
         let input = input_schemas
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Plan("project expects one input".into()))?;
         if self.columns.is_empty() {
             return Ok(OpPlan::new(input.clone(), self.memory_need(0, 0)));
@@ -52,7 +52,7 @@ impl Operator for Project {
         _budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("missing input".into()))?;
         if self.columns.is_empty() {
             return Ok(input.clone());
@@ -1,19 +1,39 @@
-//! Minimal operator registry for planner/exec wiring.
+//! Operator registry for planner/exec wiring.
 //!
-//! This is intentionally simple: it maps string keys to boxed operator instances.
-//! Replace with a richer factory when adding config params per operator.
+//! Maps binding keys to factories that build boxed operators from the
+//! binding's JSON config. Downstream crates register their own `Operator`
+//! implementations under custom keys and hand the registry to the engine
+//! (`Engine::with_operators`), which builds any binding whose key it does
+//! not know itself from here.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::agregate::Aggregate;
 use crate::filter::Filter;
+use crate::filter_in::FilterIn;
+use crate::limit::{Limit, TopN};
 use crate::map::Map;
+use crate::profile::Profile;
 use crate::project::Project;
-use crate::traits::Operator;
+use crate::top_k::TopK;
+use crate::traits::{OpError, Operator};
+use crate::union::Union;
 use crate::window::{LateralExplodeOp, WindowOp};
 
+/// Builds an operator from its binding's config (`null` when it has none).
+pub type OperatorFactory =
+    Arc<dyn Fn(&serde_json::Value) -> Result<Box<dyn Operator>, OpError> + Send + Sync>;
+
+#[derive(Clone)]
 pub struct Registry {
-    makers: HashMap<&'static str, fn() -> Box<dyn Operator>>,
+    makers: HashMap<String, OperatorFactory>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Registry {
@@ -22,6 +42,7 @@ impl Registry {
             makers: HashMap::new(),
         };
         r.register("filter", || Box::new(Filter::default()));
+        r.register("filter_in", || Box::new(FilterIn::default()));
         r.register("map", || Box::new(Map::default()));
         r.register("project", || Box::new(Project::default()));
         r.register("aggregate", || Box::new(Aggregate::default()));
@@ -34,16 +55,69 @@ impl Registry {
         r.register("join_merge", || {
             Box::new(crate::join::merge::MergeJoin::default())
         });
+        r.register("join_nested_loop", || {
+            Box::new(crate::join::nested_loop::NestedLoopJoin::default())
+        });
         r.register("window", || Box::new(WindowOp::default()));
         r.register("lateral_explode", || Box::new(LateralExplodeOp::default()));
+        r.register("union", || Box::new(Union::default()));
+        r.register("top_k", || Box::new(TopK::default()));
+        r.register("limit", || Box::new(Limit::default()));
+        r.register("top_n", || Box::new(TopN::default()));
+        r.register("profile", || Box::new(Profile::default()));
         r
     }
 
-    pub fn register(&mut self, key: &'static str, f: fn() -> Box<dyn Operator>) {
-        self.makers.insert(key, f);
+    /// A registry without the built-in operators, for plugins to fill.
+    pub fn empty() -> Self {
+        Self {
+            makers: HashMap::new(),
+        }
+    }
+
+    /// Register an operator that takes no configuration under `key`,
+    /// replacing any factory already there.
+    pub fn register(&mut self, key: impl Into<String>, f: fn() -> Box<dyn Operator>) {
+        self.register_with_config(key, move |_| Ok(f()));
+    }
+
+    /// Register a factory that configures its operator from the binding's
+    /// JSON config under `key`, replacing any factory already there. A
+    /// config the operator cannot use is an `OpError::Plan`.
+    pub fn register_with_config<F>(&mut self, key: impl Into<String>, f: F)
+    where
+        F: Fn(&serde_json::Value) -> Result<Box<dyn Operator>, OpError> + Send + Sync + 'static,
+    {
+        self.makers.insert(key.into(), Arc::new(f));
+    }
+
+    /// Add every factory of `other`, replacing those under the same keys.
+    pub fn extend(&mut self, other: Registry) {
+        self.makers.extend(other.makers);
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.makers.contains_key(key)
     }
 
+    /// Registered keys, sorted.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.makers.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// The operator under `key` with a `null` config.
     pub fn make(&self, key: &str) -> Option<Box<dyn Operator>> {
-        self.makers.get(key).map(|f| f())
+        self.build(key, &serde_json::Value::Null)?.ok()
+    }
+
+    /// The operator under `key` built from `config`; `None` for an unknown key.
+    pub fn build(
+        &self,
+        key: &str,
+        config: &serde_json::Value,
+    ) -> Option<Result<Box<dyn Operator>, OpError>> {
+        self.makers.get(key).map(|f| f(config))
     }
 }
@@ -0,0 +1,91 @@
+//! K-way merge of sorted runs with a loser tree.
+
+use std::cmp::Ordering;
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_mem::guard::BudgetGuardImpl;
+
+use crate::context::SpillScope;
+use crate::traits::OpError;
+
+use super::run::{RunMeta, RunReader, RunWriter};
+
+/// Tournament tree over `k` sorted sources.
+///
+/// `winner()` is the source with the smallest head; each internal node
+/// keeps the loser of the match played there. After the winner's source
+/// advances, `replay` plays it back up its own path only: `log2(k)`
+/// comparisons per row, where a binary heap needs up to twice that.
+/// Ties go to the lower source index, so merging runs in input order is
+/// stable.
+#[derive(Debug, Clone)]
+pub struct LoserTree {
+    /// `tree[0]` is the winner, `tree[1..k]` the losers.
+    tree: Vec<usize>,
+}
+
+impl LoserTree {
+    /// Build the tree; `cmp(a, b)` orders the heads of sources `a` and `b`.
+    pub fn new(k: usize, cmp: impl Fn(usize, usize) -> Ordering) -> Self {
+        // Slot value `k` stands for a source smaller than all others, so
+        // each leaf played in loses to it and stays behind as a loser.
+        let mut tree = Self { tree: vec![k; k] };
+        for leaf in (0..k).rev() {
+            tree.play(leaf, &cmp);
+        }
+        tree
+    }
+
+    pub fn winner(&self) -> usize {
+        self.tree[0]
+    }
+
+    /// Re-seat the winner after its source moved to its next head.
+    pub fn replay(&mut self, cmp: impl Fn(usize, usize) -> Ordering) {
+        let winner = self.winner();
+        self.play(winner, &cmp);
+    }
+
+    fn play(&mut self, leaf: usize, cmp: &impl Fn(usize, usize) -> Ordering) {
+        let k = self.tree.len();
+        let beats =
+            |a: usize, b: usize| a == k || (b != k && cmp(a, b).then(a.cmp(&b)) == Ordering::Less);
+        let mut winner = leaf;
+        let mut node = (leaf + k) / 2;
+        while node > 0 {
+            if beats(self.tree[node], winner) {
+                std::mem::swap(&mut self.tree[node], &mut winner);
+            }
+            node /= 2;
+        }
+        self.tree[0] = winner;
+    }
+}
+
+/// Merge `runs` into `out`, reading one page per run at a time.
+pub fn merge_runs(
+    runs: &[RunMeta],
+    sort_keys: &[String],
+    spill: &SpillScope,
+    budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    out: &mut RunWriter<'_>,
+) -> Result<(), OpError> {
+    let mut readers = runs
+        .iter()
+        .map(|run| RunReader::open(run, sort_keys, spill, budget))
+        .collect::<Result<Vec<_>, _>>()?;
+    if readers.is_empty() {
+        return Ok(());
+    }
+    let mut tree = LoserTree::new(readers.len(), |a, b| readers[a].cmp_heads(&readers[b]));
+    loop {
+        let source = tree.winner();
+        let Some((page, row)) = readers[source].head() else {
+            // The smallest head is past its run's end, so all runs are.
+            return Ok(());
+        };
+        out.push(page, row)?;
+        readers[source].advance(spill, budget)?;
+        tree.replay(|a, b| readers[a].cmp_heads(&readers[b]));
+    }
+}
@@ -1,4 +1,5 @@
 //! Sort operators (module).
 
 pub mod external;
+pub mod merge;
 pub mod run;
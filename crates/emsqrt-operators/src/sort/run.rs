@@ -1,126 +1,274 @@
-//! Run generation utilities for external sort.
+//! Sorted runs for external sort.
 //!
-//! Accumulates rows in memory (up to budget), sorts them, and writes to spill.
+//! `RunGenerator` cuts its input into runs of at most `run_bytes`, sorts
+//! each in memory and spills it as pages of at most `page_bytes`, so a
+//! merge only ever holds one page per run. `RunWriter` writes merged rows
+//! the same way and `RunReader` streams a run back page by page.
+
+use std::collections::VecDeque;
 
 use emsqrt_core::budget::MemoryBudget;
-use emsqrt_core::id::SpillId;
-use emsqrt_core::types::RowBatch;
+use emsqrt_core::types::{scalar_cmp, Column, RowBatch, Scalar};
 use emsqrt_mem::guard::BudgetGuardImpl;
 use emsqrt_mem::spill::SegmentMeta;
-use emsqrt_mem::SpillManager;
 
+use crate::context::SpillScope;
 use crate::traits::OpError;
 
-/// Configuration for run generation.
-#[derive(Default)]
-pub struct RunGenConfig {
-    pub max_in_mem_rows: usize,
-}
-
 /// Metadata for a sorted run on disk.
 #[derive(Clone, Debug)]
 pub struct RunMeta {
     pub rows: u64,
-    pub segment: SegmentMeta,
+    /// The run's pages, in order.
+    pub pages: Vec<SegmentMeta>,
+}
+
+/// Approximate in-memory size of one row of `batch`.
+pub fn row_bytes(batch: &RowBatch, row: usize) -> usize {
+    batch
+        .columns
+        .iter()
+        .map(|c| scalar_bytes(&c.values[row]))
+        .sum()
+}
+
+fn scalar_bytes(value: &Scalar) -> usize {
+    std::mem::size_of::<Scalar>()
+        + match value {
+            Scalar::Str(s) => s.len(),
+            Scalar::Bin(b) => b.len(),
+            _ => 0,
+        }
+}
+
+/// A batch with `template`'s columns and no rows.
+pub fn empty_like(template: &RowBatch) -> RowBatch {
+    RowBatch {
+        columns: template
+            .columns
+            .iter()
+            .map(|c| Column {
+                name: c.name.clone(),
+                values: Vec::new(),
+            })
+            .collect(),
+    }
 }
 
 /// Generator for sorted runs.
 ///
-/// Accumulates rows in memory, sorts when capacity reached, writes to spill.
+/// Accumulates rows up to `run_bytes`, then sorts them and spills them as
+/// one run.
 pub struct RunGenerator {
-    spill_id: SpillId,
     sort_keys: Vec<String>,
-    accumulator: Vec<RowBatch>,
-    accum_rows: usize,
-    max_rows: usize,
+    run_bytes: usize,
+    page_bytes: usize,
+    pending: Option<RowBatch>,
+    pending_bytes: usize,
     runs: Vec<RunMeta>,
 }
 
 impl RunGenerator {
-    pub fn new(spill_id: SpillId, sort_keys: Vec<String>, max_rows: usize) -> Self {
+    pub fn new(sort_keys: Vec<String>, run_bytes: usize, page_bytes: usize) -> Self {
         Self {
-            spill_id,
             sort_keys,
-            accumulator: Vec::new(),
-            accum_rows: 0,
-            max_rows,
+            run_bytes,
+            page_bytes,
+            pending: None,
+            pending_bytes: 0,
             runs: Vec::new(),
         }
     }
 
-    /// Add a batch to the accumulator. Flushes if capacity exceeded.
-    pub fn add_batch(
-        &mut self,
-        batch: RowBatch,
-        spill_mgr: &mut SpillManager,
-        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
-    ) -> Result<(), OpError> {
-        let batch_rows = batch.num_rows();
-        self.accumulator.push(batch);
-        self.accum_rows += batch_rows;
-
-        if self.accum_rows >= self.max_rows {
-            self.flush_run(spill_mgr, budget)?;
+    /// Add a batch's rows, spilling a run whenever `run_bytes` fill up.
+    pub fn add_batch(&mut self, batch: &RowBatch, spill: &SpillScope) -> Result<(), OpError> {
+        for row in 0..batch.num_rows() {
+            let bytes = row_bytes(batch, row);
+            if self.pending_bytes + bytes > self.run_bytes && self.pending_bytes > 0 {
+                self.flush_run(spill)?;
+            }
+            let pending = self.pending.get_or_insert_with(|| empty_like(batch));
+            for (out, col) in pending.columns.iter_mut().zip(&batch.columns) {
+                out.values.push(col.values[row].clone());
+            }
+            self.pending_bytes += bytes;
         }
-
         Ok(())
     }
 
-    /// Flush the current accumulator to a sorted run on disk.
-    fn flush_run(
-        &mut self,
-        spill_mgr: &mut SpillManager,
-        _budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
-    ) -> Result<(), OpError> {
-        if self.accumulator.is_empty() {
+    /// Sort the pending rows and spill them as a run.
+    fn flush_run(&mut self, spill: &SpillScope) -> Result<(), OpError> {
+        let Some(mut run) = self.pending.take() else {
             return Ok(());
+        };
+        self.pending_bytes = 0;
+        run.sort_by_columns(&self.sort_keys)
+            .map_err(|e| OpError::Exec(format!("sort failed: {}", e)))?;
+
+        let mut writer = RunWriter::new(&run, self.page_bytes, Some(spill));
+        for row in 0..run.num_rows() {
+            writer.push(&run, row)?;
         }
+        if let Some(meta) = writer.finish_run()? {
+            self.runs.push(meta);
+        }
+        Ok(())
+    }
 
-        // Concatenate all accumulated batches into one
-        let mut merged = RowBatch {
-            columns: self.accumulator[0].columns.clone(),
-        };
+    /// Flush the remaining rows and return all runs, in input order.
+    pub fn finalize(mut self, spill: &SpillScope) -> Result<Vec<RunMeta>, OpError> {
+        self.flush_run(spill)?;
+        Ok(self.runs)
+    }
+}
 
-        for batch in &self.accumulator[1..] {
-            for (i, col) in batch.columns.iter().enumerate() {
-                if i < merged.columns.len() {
-                    merged.columns[i].values.extend(col.values.iter().cloned());
-                }
-            }
+/// Appends rows to a run, spilling a page whenever `page_bytes` fill up.
+/// Without a spill scope the rows simply collect in memory.
+pub struct RunWriter<'a> {
+    spill: Option<&'a SpillScope>,
+    page_bytes: usize,
+    page: RowBatch,
+    buffered_bytes: usize,
+    pages: Vec<SegmentMeta>,
+    rows: u64,
+}
+
+impl<'a> RunWriter<'a> {
+    pub fn new(template: &RowBatch, page_bytes: usize, spill: Option<&'a SpillScope>) -> Self {
+        Self {
+            spill,
+            page_bytes,
+            page: empty_like(template),
+            buffered_bytes: 0,
+            pages: Vec::new(),
+            rows: 0,
         }
+    }
 
-        // Sort the merged batch
-        merged
-            .sort_by_columns(&self.sort_keys)
-            .map_err(|e| OpError::Exec(format!("sort failed: {}", e)))?;
+    pub fn push(&mut self, batch: &RowBatch, row: usize) -> Result<(), OpError> {
+        for (out, col) in self.page.columns.iter_mut().zip(&batch.columns) {
+            out.values.push(col.values[row].clone());
+        }
+        self.rows += 1;
+        self.buffered_bytes += row_bytes(batch, row);
+        if self.spill.is_some() && self.buffered_bytes >= self.page_bytes {
+            self.flush_page()?;
+        }
+        Ok(())
+    }
+
+    fn flush_page(&mut self) -> Result<(), OpError> {
+        if let Some(spill) = self.spill {
+            if self.page.num_rows() > 0 {
+                let empty = empty_like(&self.page);
+                let page = std::mem::replace(&mut self.page, empty);
+                self.pages.push(spill.write(&page)?);
+            }
+        }
+        self.buffered_bytes = 0;
+        Ok(())
+    }
 
-        // Write to spill
-        let run_index = spill_mgr.next_run_index();
-        let segment = spill_mgr
-            .write_batch(&merged, self.spill_id, run_index)
-            .map_err(|e| OpError::Exec(format!("spill write: {}", e)))?;
+    /// The spilled run, or `None` if no rows were written.
+    pub fn finish_run(mut self) -> Result<Option<RunMeta>, OpError> {
+        self.flush_page()?;
+        Ok((self.rows > 0).then_some(RunMeta {
+            rows: self.rows,
+            pages: self.pages,
+        }))
+    }
 
-        let run_meta = RunMeta {
-            rows: merged.num_rows() as u64,
-            segment,
-        };
+    /// The rows written, for a writer without a spill scope.
+    pub fn into_batch(self) -> RowBatch {
+        self.page
+    }
+}
 
-        self.runs.push(run_meta);
+/// Streams a spilled run one page at a time, deleting pages once read.
+pub struct RunReader {
+    pages: VecDeque<SegmentMeta>,
+    key_indices: Vec<usize>,
+    page: RowBatch,
+    row: usize,
+}
 
-        // Clear accumulator
-        self.accumulator.clear();
-        self.accum_rows = 0;
+impl RunReader {
+    pub fn open(
+        run: &RunMeta,
+        sort_keys: &[String],
+        spill: &SpillScope,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<Self, OpError> {
+        let mut reader = Self {
+            pages: run.pages.iter().cloned().collect(),
+            key_indices: Vec::new(),
+            page: RowBatch { columns: vec![] },
+            row: 0,
+        };
+        reader.load_page(spill, budget)?;
+        reader.key_indices = sort_keys
+            .iter()
+            .map(|key| {
+                reader
+                    .page
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == key)
+                    .ok_or_else(|| OpError::Exec(format!("sort key '{}' not found", key)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(reader)
+    }
 
+    fn load_page(
+        &mut self,
+        spill: &SpillScope,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<(), OpError> {
+        self.row = 0;
+        match self.pages.pop_front() {
+            Some(meta) => {
+                self.page = spill.read(&meta, budget)?;
+                spill.delete(&meta.name);
+            }
+            None => self.page.columns.iter_mut().for_each(|c| c.values.clear()),
+        }
         Ok(())
     }
 
-    /// Finalize run generation by flushing any remaining rows.
-    pub fn finalize(
+    /// The page holding the current row, and its index; `None` once the run
+    /// is exhausted.
+    pub fn head(&self) -> Option<(&RowBatch, usize)> {
+        (self.row < self.page.num_rows()).then_some((&self.page, self.row))
+    }
+
+    pub fn advance(
         &mut self,
-        spill_mgr: &mut SpillManager,
+        spill: &SpillScope,
         budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
-    ) -> Result<Vec<RunMeta>, OpError> {
-        self.flush_run(spill_mgr, budget)?;
-        Ok(self.runs.clone())
+    ) -> Result<(), OpError> {
+        self.row += 1;
+        if self.row >= self.page.num_rows() && !self.pages.is_empty() {
+            self.load_page(spill, budget)?;
+        }
+        Ok(())
+    }
+
+    /// Compare the current rows of two readers by the sort keys; exhausted
+    /// readers sort last.
+    pub fn cmp_heads(&self, other: &RunReader) -> std::cmp::Ordering {
+        use std::cmp::Ordering;
+        match (self.head(), other.head()) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some((a, i)), Some((b, j))) => self
+                .key_indices
+                .iter()
+                .zip(&other.key_indices)
+                .map(|(&x, &y)| scalar_cmp(&a.columns[x].values[i], &b.columns[y].values[j]))
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal),
+        }
     }
 }
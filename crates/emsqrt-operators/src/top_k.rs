@@ -0,0 +1,311 @@
+//! Approximate top-K frequent values of a column under fixed memory.
+//!
+//! A Space-Saving sketch keeps at most `capacity` counters. A value without
+//! a counter takes over the smallest one once the sketch is full and
+//! inherits its count as a possible overestimate (`count_error`), so every
+//! value occurring more than `rows / capacity` times keeps a counter, and a
+//! reported `count` exceeds the true count by at most its `count_error`.
+//!
+//! Each block's rows are summarized in a sketch of their own, which is then
+//! merged into the operator's running sketch. The operator's last block
+//! emits the `k` most frequent values; earlier blocks emit no rows.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Mutex;
+
+use emsqrt_core::id::BlockId;
+use emsqrt_core::key::encode_scalar;
+use emsqrt_core::prelude::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::BudgetGuardImpl;
+
+use crate::context::OpContext;
+use crate::plan::{Footprint, OpPlan};
+use crate::traits::{MemoryBudget, OpError, Operator};
+
+/// Approximate in-memory size of one counter (key, value and bookkeeping).
+const COUNTER_BYTES: usize = 128;
+
+#[derive(Debug, Clone)]
+struct Counter {
+    value: Scalar,
+    count: u64,
+    error: u64,
+}
+
+/// Space-Saving summary of a stream of values.
+#[derive(Debug, Clone)]
+pub struct SpaceSaving {
+    capacity: usize,
+    counters: HashMap<Vec<u8>, Counter>,
+    /// `(count, key)` of every counter, smallest first.
+    by_count: BTreeSet<(u64, Vec<u8>)>,
+}
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            counters: HashMap::new(),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    /// Count one occurrence of `value`.
+    pub fn insert(&mut self, value: &Scalar) {
+        let mut key = Vec::new();
+        encode_scalar(value, &mut key);
+        if let Some(counter) = self.counters.get_mut(&key) {
+            self.by_count.remove(&(counter.count, key.clone()));
+            counter.count += 1;
+            self.by_count.insert((counter.count, key));
+            return;
+        }
+        // Full: the new value replaces the smallest counter.
+        let floor = if self.counters.len() < self.capacity {
+            0
+        } else {
+            let (count, evicted) = self.by_count.pop_first().expect("full sketch has counters");
+            self.counters.remove(&evicted);
+            count
+        };
+        self.by_count.insert((floor + 1, key.clone()));
+        self.counters.insert(
+            key,
+            Counter {
+                value: value.clone(),
+                count: floor + 1,
+                error: floor,
+            },
+        );
+    }
+
+    /// Fold `other` into this sketch. A value missing from a full sketch may
+    /// have occurred up to that sketch's smallest count, which is added to
+    /// both its count and its error.
+    pub fn merge(&mut self, other: &SpaceSaving) {
+        let floor = |s: &SpaceSaving| {
+            if s.counters.len() < s.capacity {
+                0
+            } else {
+                s.by_count.first().map_or(0, |(count, _)| *count)
+            }
+        };
+        let (mine, theirs) = (floor(self), floor(other));
+        let mut merged: HashMap<Vec<u8>, Counter> = HashMap::new();
+        for (key, counter) in &self.counters {
+            let (count, error) = other
+                .counters
+                .get(key)
+                .map_or((theirs, theirs), |c| (c.count, c.error));
+            merged.insert(
+                key.clone(),
+                Counter {
+                    value: counter.value.clone(),
+                    count: counter.count + count,
+                    error: counter.error + error,
+                },
+            );
+        }
+        for (key, counter) in &other.counters {
+            merged.entry(key.clone()).or_insert_with(|| Counter {
+                value: counter.value.clone(),
+                count: counter.count + mine,
+                error: counter.error + mine,
+            });
+        }
+        // Keep the largest counts; ties go to the smaller key.
+        let mut ranked: Vec<(Vec<u8>, Counter)> = merged.into_iter().collect();
+        ranked.sort_by(|(ka, a), (kb, b)| b.count.cmp(&a.count).then_with(|| ka.cmp(kb)));
+        ranked.truncate(self.capacity);
+        self.by_count = ranked.iter().map(|(k, c)| (c.count, k.clone())).collect();
+        self.counters = ranked.into_iter().collect();
+    }
+
+    /// The `k` values with the largest counts, as `(value, count, error)`,
+    /// largest first; ties are ordered by value.
+    pub fn top(&self, k: usize) -> Vec<(Scalar, u64, u64)> {
+        let mut ranked: Vec<&(u64, Vec<u8>)> = self.by_count.iter().collect();
+        ranked.sort_by(|(ca, ka), (cb, kb)| cb.cmp(ca).then_with(|| ka.cmp(kb)));
+        ranked
+            .into_iter()
+            .take(k)
+            .map(|(_, key)| {
+                let c = &self.counters[key];
+                (c.value.clone(), c.count, c.error)
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.counters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+    }
+}
+
+/// The running sketch of a run and the blocks already folded into it.
+#[derive(Default)]
+struct TopKState {
+    sketch: Option<SpaceSaving>,
+    merged: HashSet<BlockId>,
+    _guard: Option<BudgetGuardImpl>,
+}
+
+#[derive(Default)]
+pub struct TopK {
+    pub column: String,
+    pub k: usize,
+    /// Counters in the sketch (0 = ten per reported value, at least 64).
+    pub capacity: usize,
+    state: Mutex<TopKState>,
+}
+
+impl TopK {
+    pub fn new(column: impl Into<String>, k: usize, capacity: usize) -> Self {
+        Self {
+            column: column.into(),
+            k,
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        if self.capacity == 0 {
+            (self.k * 10).max(64)
+        } else {
+            self.capacity
+        }
+    }
+
+    fn output(&self, rows: Vec<(Scalar, u64, u64)>) -> RowBatch {
+        let mut value = Vec::with_capacity(rows.len());
+        let mut count = Vec::with_capacity(rows.len());
+        let mut error = Vec::with_capacity(rows.len());
+        for (v, c, e) in rows {
+            value.push(v);
+            count.push(Scalar::I64(c as i64));
+            error.push(Scalar::I64(e as i64));
+        }
+        RowBatch {
+            columns: vec![
+                Column {
+                    name: self.column.clone(),
+                    values: value,
+                },
+                Column {
+                    name: "count".into(),
+                    values: count,
+                },
+                Column {
+                    name: "count_error".into(),
+                    values: error,
+                },
+            ],
+        }
+    }
+}
+
+impl Operator for TopK {
+    fn name(&self) -> &'static str {
+        "top_k"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        // The running sketch and one block's sketch, whatever the input size.
+        Footprint {
+            bytes_per_row: 0,
+            overhead_bytes: (2 * self.capacity() * COUNTER_BYTES) as u64,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let input = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("top_k expects one input".into()))?;
+        let field = input
+            .fields
+            .iter()
+            .find(|f| f.name == self.column)
+            .ok_or_else(|| {
+                OpError::Plan(format!(
+                    "top_k column '{}' not in input schema",
+                    self.column
+                ))
+            })?;
+        let schema = Schema::new(vec![
+            field.clone(),
+            Field::new("count", DataType::Int64, false),
+            Field::new("count_error", DataType::Int64, false),
+        ]);
+        Ok(OpPlan::new(schema, self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block_with(inputs, &OpContext::default(), budget)
+    }
+
+    fn eval_block_with(
+        &self,
+        inputs: &[RowBatch],
+        ctx: &OpContext,
+        budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let input = inputs
+            .first()
+            .ok_or_else(|| OpError::Exec("missing input".into()))?;
+        let column = input
+            .columns
+            .iter()
+            .find(|c| c.name == self.column)
+            .ok_or_else(|| OpError::Exec(format!("top_k column '{}' not found", self.column)))?;
+        let sketch_bytes = self.capacity() * COUNTER_BYTES;
+        let _block_guard = budget
+            .try_acquire(sketch_bytes, "top_k")
+            .ok_or_else(|| OpError::Exec("memory budget unavailable for top_k sketch".into()))?;
+
+        // Nulls never match a join key, so they are not counted.
+        let mut block = SpaceSaving::new(self.capacity());
+        for value in column.values.iter().filter(|v| !matches!(v, Scalar::Null)) {
+            block.insert(value);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.sketch.is_none() {
+            state._guard = Some(budget.try_acquire(sketch_bytes, "top_k").ok_or_else(|| {
+                OpError::Exec("memory budget unavailable for top_k sketch".into())
+            })?);
+        }
+        // A retried block is folded in once.
+        if ctx.block_id.is_none_or(|id| state.merged.insert(id)) {
+            match &mut state.sketch {
+                Some(sketch) => sketch.merge(&block),
+                None => state.sketch = Some(block),
+            }
+        }
+        let last = ctx.range.as_ref().is_none_or(|r| r.open_end);
+        let rows = match (&state.sketch, last) {
+            (Some(sketch), true) => sketch.top(self.k),
+            _ => Vec::new(),
+        };
+        Ok(self.output(rows))
+    }
+
+    fn open(&self) -> Result<(), OpError> {
+        *self.state.lock().unwrap() = TopKState::default();
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), OpError> {
+        // Release the sketch and its budget.
+        *self.state.lock().unwrap() = TopKState::default();
+        Ok(())
+    }
+}
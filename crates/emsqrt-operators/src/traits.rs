@@ -11,6 +11,7 @@ pub use emsqrt_core::budget::MemoryBudget;
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::RowBatch;
 
+use crate::context::OpContext;
 use crate::plan::{Footprint, OpPlan};
 
 use thiserror::Error;
@@ -19,7 +20,7 @@ use thiserror::Error;
 /// The exec crate will replace this with bounded MPMC channels.
 pub type BlockStream = Vec<RowBatch>;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum OpError {
     #[error("planning error: {0}")]
     Plan(String),
@@ -92,6 +93,27 @@ impl OpError {
     }
 }
 
+/// Rows `[start, end)` of an operator's output that one TE block covers
+/// (`TeBlock::range_rows`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRange {
+    pub start: u64,
+    pub end: u64,
+    /// Last block of its operator: the plan's row count is an estimate, so
+    /// this block also returns every row past `end`.
+    pub open_end: bool,
+}
+
+impl BlockRange {
+    pub fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// Trait that all operators must implement.
 ///
 /// Invariants:
@@ -118,4 +140,48 @@ pub trait Operator: Send + Sync + 'static {
         inputs: &[RowBatch],
         budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError>;
+
+    /// Evaluate one TE block with its execution context.
+    ///
+    /// Sources read exactly `ctx.range`, which makes block boundaries match
+    /// the plan and re-evaluating a block idempotent. Operators that derive
+    /// their output from `inputs` ignore the context (the default).
+    fn eval_block_with(
+        &self,
+        inputs: &[RowBatch],
+        _ctx: &OpContext,
+        budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        self.eval_block(inputs, budget)
+    }
+
+    /// Called once before the operator's first block of a run. Sources reset
+    /// their readers here; other operators have nothing to do (the default).
+    fn open(&self) -> Result<(), OpError> {
+        Ok(())
+    }
+
+    /// Called once after the operator's last block of a run. Sinks finalize
+    /// their output and sources close their readers here; other operators
+    /// have nothing to do (the default).
+    fn finish(&self) -> Result<(), OpError> {
+        Ok(())
+    }
+}
+
+/// A stateful reader that hands out an input's rows block by block, in
+/// order. A source keeps one open for the whole run, so each block continues
+/// where the previous one stopped instead of reopening the input and
+/// skipping the rows already read.
+pub trait BlockSource: Send {
+    /// Rows handed out or skipped so far: where the next block starts.
+    fn position(&self) -> u64;
+
+    /// Up to `rows` rows from `position()`; fewer, possibly none, at the end
+    /// of the input.
+    fn next_block(&mut self, ctx: &OpContext, rows: usize) -> Result<RowBatch, OpError>;
+
+    /// Move past up to `rows` rows without decoding them; returns how many
+    /// were skipped.
+    fn skip(&mut self, rows: usize) -> Result<usize, OpError>;
 }
@@ -0,0 +1,145 @@
+//! Union operator: concatenate blocks from inputs that share a schema.
+//!
+//! Used for templated/multi-file scans, where each file is its own source and
+//! every union block receives exactly one upstream block. When the schema's
+//! `align` policy fills missing columns, inputs with different column sets
+//! are aligned to the combined schema (`emsqrt_core::align`).
+//! TODOs:
+//! - Widen compatible types (e.g., Int32 + Int64) instead of requiring exact names.
+
+use emsqrt_core::align::{align_columns, unify_schemas};
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{Column, RowBatch};
+
+use crate::plan::{Footprint, OpPlan};
+use crate::traits::{MemoryBudget, OpError, Operator};
+
+#[derive(Default)]
+pub struct Union {
+    /// Expected output columns; when unset, the first non-empty input decides.
+    pub schema: Option<Schema>,
+}
+
+impl Operator for Union {
+    fn name(&self) -> &'static str {
+        "union"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        Footprint {
+            bytes_per_row: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let first = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("union expects at least one input".into()))?;
+        if let Some(schema) = self.schema.as_ref().filter(|s| s.align.fills_missing()) {
+            let unified = unify_schemas(input_schemas).map_err(OpError::Schema)?;
+            let out = if schema.fields.is_empty() {
+                unified.with_align(schema.align)
+            } else {
+                schema.clone()
+            };
+            return Ok(OpPlan::new(out, self.memory_need(0, 0)));
+        }
+        for (i, schema) in input_schemas.iter().enumerate().skip(1) {
+            if schema != first {
+                return Err(OpError::Schema(format!(
+                    "union input {} schema does not match input 0",
+                    i
+                )));
+            }
+        }
+        let out = self.schema.clone().unwrap_or_else(|| first.clone());
+        Ok(OpPlan::new(out, self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        _budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let names: Vec<String> = match &self.schema {
+            Some(schema) if !schema.fields.is_empty() => {
+                schema.fields.iter().map(|f| f.name.clone()).collect()
+            }
+            _ => match inputs.iter().find(|b| !b.columns.is_empty()) {
+                Some(first) => first.columns.iter().map(|c| c.name.clone()).collect(),
+                None => return Ok(RowBatch { columns: vec![] }),
+            },
+        };
+
+        if let Some(align) = self.schema.as_ref().map(|s| s.align) {
+            if align.fills_missing() {
+                let mut names = names;
+                if self.schema.as_ref().is_some_and(|s| s.fields.is_empty()) {
+                    // No declared columns: take every column any input has.
+                    for col in inputs.iter().flat_map(|b| &b.columns) {
+                        if !names.contains(&col.name) {
+                            names.push(col.name.clone());
+                        }
+                    }
+                }
+                let mut out = RowBatch {
+                    columns: names
+                        .iter()
+                        .map(|name| Column {
+                            name: name.clone(),
+                            values: Vec::new(),
+                        })
+                        .collect(),
+                };
+                for (i, batch) in inputs.iter().enumerate() {
+                    if batch.columns.is_empty() {
+                        continue;
+                    }
+                    let aligned = align_columns(batch, &names, align)
+                        .map_err(|e| OpError::Schema(format!("union input {}: {}", i, e)))?;
+                    for (col, src) in out.columns.iter_mut().zip(aligned.columns) {
+                        col.values.extend(src.values);
+                    }
+                }
+                return Ok(out);
+            }
+        }
+
+        let mut out: Vec<Column> = names
+            .iter()
+            .map(|name| Column {
+                name: name.clone(),
+                values: Vec::new(),
+            })
+            .collect();
+        for (i, batch) in inputs.iter().enumerate() {
+            // Column-less batches carry no rows; nothing to check.
+            if batch.columns.is_empty() {
+                continue;
+            }
+            if batch.columns.len() != names.len() {
+                return Err(OpError::Schema(format!(
+                    "union input {} has {} columns, expected {}",
+                    i,
+                    batch.columns.len(),
+                    names.len()
+                )));
+            }
+            for col in &mut out {
+                let src = batch
+                    .columns
+                    .iter()
+                    .find(|c| c.name == col.name)
+                    .ok_or_else(|| {
+                        OpError::Schema(format!(
+                            "union input {} is missing column '{}'",
+                            i, col.name
+                        ))
+                    })?;
+                col.values.extend(src.values.iter().cloned());
+            }
+        }
+        Ok(RowBatch { columns: out })
+    }
+}
@@ -1,6 +1,6 @@
-use std::cmp::Ordering;
 use std::collections::HashMap;
 
+use emsqrt_core::key::{encode_row_key, encode_scalar};
 use emsqrt_core::prelude::Schema;
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 use emsqrt_mem::guard::BudgetGuardImpl;
@@ -13,6 +13,8 @@ pub struct WindowOp {
     pub partitions: Vec<String>,
     pub order_by: Vec<String>,
     pub functions: Vec<WindowFnSpec>,
+    /// Rows arrive ordered by (partitions, order_by); skip the sort.
+    pub presorted: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -66,7 +68,7 @@ impl Operator for WindowOp {
         _budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("window operator missing input batch".into()))?;
         let num_rows = input.num_rows();
         let mut output = input.clone();
@@ -76,21 +78,24 @@ impl Operator for WindowOp {
             name_to_index.insert(col.name.clone(), idx);
         }
 
-        let mut tuples: Vec<(Vec<Scalar>, usize)> = (0..num_rows)
-            .map(|row_idx| {
-                let mut key = Vec::new();
-                for key_col in self.partitions.iter().chain(self.order_by.iter()) {
-                    if let Some(&col_idx) = name_to_index.get(key_col) {
-                        key.push(input.columns[col_idx].values[row_idx].clone());
-                    } else {
-                        key.push(Scalar::Null);
-                    }
-                }
-                (key, row_idx)
+        let partition_cols = self
+            .partitions
+            .iter()
+            .map(|name| {
+                name_to_index
+                    .get(name)
+                    .map(|&idx| &input.columns[idx])
+                    .ok_or_else(|| OpError::Schema(format!("partition column '{name}' not found")))
             })
-            .collect();
-        tuples.sort_by(|(a, _), (b, _)| scalar_tuple_cmp(a, b));
-        let order_indices: Vec<usize> = tuples.into_iter().map(|(_, idx)| idx).collect();
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Visit rows in (partitions, order_by) order, sorting by the encoded
+        // key unless the input already arrives that way.
+        let order_indices: Vec<usize> = if self.presorted {
+            (0..num_rows).collect()
+        } else {
+            self.sorted_order(input, &name_to_index)
+        };
 
         let mut computed_columns: Vec<Vec<Scalar>> = self
             .functions
@@ -98,17 +103,17 @@ impl Operator for WindowOp {
             .map(|_| vec![Scalar::Null; num_rows])
             .collect();
 
-        let mut current_partition: Vec<Scalar> = Vec::new();
+        let mut current_partition: Vec<u8> = Vec::new();
+        let mut part_key: Vec<u8> = Vec::new();
         let mut partition_initialized = false;
         let mut row_counter: i64 = 0;
         let mut running_sums: Vec<f64> = vec![0.0; self.functions.len()];
 
         for sorted_pos in order_indices {
-            let part_key =
-                extract_partition_key(input, &self.partitions, sorted_pos, &name_to_index)?;
+            encode_row_key(&partition_cols, sorted_pos, &mut part_key);
             if !partition_initialized || part_key != current_partition {
                 partition_initialized = true;
-                current_partition = part_key;
+                std::mem::swap(&mut current_partition, &mut part_key);
                 row_counter = 0;
                 for sum in running_sums.iter_mut() {
                     *sum = 0.0;
@@ -126,7 +131,7 @@ impl Operator for WindowOp {
                             OpError::Schema(format!("sum column '{column}' not found"))
                         })?;
                         let value = value_as_f64(&input.columns[*col_idx].values[sorted_pos])
-                            .map_err(|e| OpError::Exec(e))?;
+                            .map_err(OpError::Exec)?;
                         running_sums[fn_idx] += value;
                         computed_columns[fn_idx][sorted_pos] = Scalar::F64(running_sums[fn_idx]);
                     }
@@ -134,7 +139,7 @@ impl Operator for WindowOp {
             }
         }
 
-        for (spec, values) in self.functions.iter().zip(computed_columns.into_iter()) {
+        for (spec, values) in self.functions.iter().zip(computed_columns) {
             output.columns.push(Column {
                 name: spec.alias.clone(),
                 values,
@@ -145,6 +150,29 @@ impl Operator for WindowOp {
     }
 }
 
+impl WindowOp {
+    /// Row indices sorted by the encoded (partitions, order_by) key; missing
+    /// order columns sort as NULL and ties keep input order.
+    fn sorted_order(&self, input: &RowBatch, name_to_index: &HashMap<String, usize>) -> Vec<usize> {
+        let mut tuples: Vec<(Vec<u8>, usize)> = (0..input.num_rows())
+            .map(|row_idx| {
+                let mut key = Vec::new();
+                for key_col in self.partitions.iter().chain(self.order_by.iter()) {
+                    match name_to_index.get(key_col) {
+                        Some(&col_idx) => {
+                            encode_scalar(&input.columns[col_idx].values[row_idx], &mut key)
+                        }
+                        None => encode_scalar(&Scalar::Null, &mut key),
+                    }
+                }
+                (key, row_idx)
+            })
+            .collect();
+        tuples.sort();
+        tuples.into_iter().map(|(_, idx)| idx).collect()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LateralExplodeOp {
     pub column: String,
@@ -193,7 +221,7 @@ impl Operator for LateralExplodeOp {
         _budget: &dyn MemoryBudget<Guard = BudgetGuardImpl>,
     ) -> Result<RowBatch, OpError> {
         let input = inputs
-            .get(0)
+            .first()
             .ok_or_else(|| OpError::Exec("lateral operator missing input".into()))?;
 
         let mut name_to_index = HashMap::new();
@@ -243,25 +271,6 @@ impl Operator for LateralExplodeOp {
     }
 }
 
-fn extract_partition_key(
-    batch: &RowBatch,
-    partitions: &[String],
-    row_idx: usize,
-    name_map: &HashMap<String, usize>,
-) -> Result<Vec<Scalar>, OpError> {
-    if partitions.is_empty() {
-        return Ok(vec![]);
-    }
-    let mut key = Vec::with_capacity(partitions.len());
-    for name in partitions {
-        let idx = name_map
-            .get(name)
-            .ok_or_else(|| OpError::Schema(format!("partition column '{name}' not found")))?;
-        key.push(batch.columns[*idx].values[row_idx].clone());
-    }
-    Ok(key)
-}
-
 fn value_as_f64(value: &Scalar) -> Result<f64, String> {
     match value {
         Scalar::Null => Ok(0.0),
@@ -269,37 +278,11 @@ fn value_as_f64(value: &Scalar) -> Result<f64, String> {
         Scalar::I64(v) => Ok(*v as f64),
         Scalar::F32(v) => Ok(*v as f64),
         Scalar::F64(v) => Ok(*v),
+        Scalar::Decimal128(v, scale) => Ok(emsqrt_core::decimal::to_f64(*v, *scale)),
         other => Err(format!("unsupported numeric type for sum: {:?}", other)),
     }
 }
 
-fn scalar_tuple_cmp(a: &[Scalar], b: &[Scalar]) -> Ordering {
-    for (x, y) in a.iter().zip(b.iter()) {
-        match scalar_cmp(x, y) {
-            Ordering::Equal => continue,
-            other => return other,
-        }
-    }
-    a.len().cmp(&b.len())
-}
-
-fn scalar_cmp(x: &Scalar, y: &Scalar) -> Ordering {
-    use Scalar::*;
-    match (x, y) {
-        (Null, Null) => Ordering::Equal,
-        (Null, _) => Ordering::Less,
-        (_, Null) => Ordering::Greater,
-        (Bool(a), Bool(b)) => a.cmp(b),
-        (I32(a), I32(b)) => a.cmp(b),
-        (I64(a), I64(b)) => a.cmp(b),
-        (F32(a), F32(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-        (F64(a), F64(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
-        (Str(a), Str(b)) => a.cmp(b),
-        (Bin(a), Bin(b)) => a.cmp(b),
-        _ => Ordering::Equal,
-    }
-}
-
 fn scalar_to_string(value: &Scalar) -> String {
     match value {
         Scalar::Null => "".to_string(),
@@ -310,5 +293,6 @@ fn scalar_to_string(value: &Scalar) -> String {
         Scalar::F64(v) => v.to_string(),
         Scalar::Str(s) => s.clone(),
         Scalar::Bin(bytes) => format!("{:?}", bytes),
+        Scalar::Date64(_) | Scalar::Timestamp(_) | Scalar::Decimal128(..) => value.to_string(),
     }
 }
@@ -0,0 +1,79 @@
+//! Compiled plan: `PhysicalProgram` + `TePlan` serialized for caching and
+//! remote submission.
+//!
+//! `emsqrt compile` writes one of these; `emsqrt run --plan` (or a remote
+//! worker) executes it without re-parsing or re-planning the pipeline. The
+//! encoding is JSON with an explicit `format_version`, so schedulers can
+//! inspect it and readers can reject formats they don't understand.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use emsqrt_te::TePlan;
+
+use crate::dsl::yaml::PipelineConfig;
+use crate::physical::PhysicalProgram;
+
+/// Bump whenever the serialized shape changes incompatibly.
+pub const COMPILED_PLAN_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum CompiledPlanError {
+    #[error("compiled plan encoding: {0}")]
+    Encoding(#[from] serde_json::Error),
+    #[error("compiled plan format version {found} is not supported (expected {expected})")]
+    UnsupportedVersion { found: u32, expected: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompiledPlan {
+    pub format_version: u32,
+    /// Engine version that compiled the plan (informational).
+    pub engine_version: String,
+    /// Memory cap the TE plan was sized for.
+    pub mem_cap_bytes: usize,
+    /// Pipeline `config` block, applied again at run time.
+    #[serde(default)]
+    pub config: PipelineConfig,
+    pub program: PhysicalProgram,
+    pub te: TePlan,
+}
+
+/// Just enough of the document to check the version before a full parse.
+#[derive(Deserialize)]
+struct Header {
+    format_version: u32,
+}
+
+impl CompiledPlan {
+    pub fn new(
+        program: PhysicalProgram,
+        te: TePlan,
+        config: PipelineConfig,
+        mem_cap_bytes: usize,
+    ) -> Self {
+        Self {
+            format_version: COMPILED_PLAN_FORMAT_VERSION,
+            engine_version: emsqrt_core::VERSION.to_string(),
+            mem_cap_bytes,
+            config,
+            program,
+            te,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CompiledPlanError> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CompiledPlanError> {
+        let header: Header = serde_json::from_slice(bytes)?;
+        if header.format_version != COMPILED_PLAN_FORMAT_VERSION {
+            return Err(CompiledPlanError::UnsupportedVersion {
+                found: header.format_version,
+                expected: COMPILED_PLAN_FORMAT_VERSION,
+            });
+        }
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
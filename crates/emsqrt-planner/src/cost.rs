@@ -8,6 +8,7 @@
 
 use emsqrt_core::dag::LogicalPlan;
 use emsqrt_core::schema::Schema;
+use emsqrt_core::stats::SourceStats;
 use emsqrt_te::WorkEstimate;
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +19,28 @@ pub struct WorkHint {
     pub source_rows: Vec<(String, u64)>,
     /// Bytes at sources (if known); map by source URI.
     pub source_bytes: Vec<(String, u64)>,
+    /// The files behind glob and directory sources; map by source URI.
+    #[serde(default)]
+    pub source_files: Vec<(String, Vec<SourceFile>)>,
+}
+
+/// One file of a multi-file source.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub path: String,
+    pub bytes: u64,
+    /// Rows, where the file says without being read (Parquet footers).
+    #[serde(default)]
+    pub rows: Option<u64>,
+}
+
+impl WorkHint {
+    fn files_of(&self, source: &str) -> Option<&[SourceFile]> {
+        self.source_files
+            .iter()
+            .find(|(s, _)| s == source)
+            .map(|(_, files)| files.as_slice())
+    }
 }
 
 pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstimate {
@@ -40,15 +63,21 @@ pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstima
         use LogicalPlan::*;
         match lp {
             Scan { source, schema } => {
-                // Use hints if available; otherwise guess 0 (unknown).
+                // Use hints if available, then the row counts of every file of
+                // a multi-file source, then collected stats; otherwise guess 0
+                // (unknown).
+                let files = hints.and_then(|h| h.files_of(source));
                 let rows = hints
                     .and_then(|h| h.source_rows.iter().find(|(s, _)| s == source))
                     .map(|(_, r)| *r)
+                    .or_else(|| files.and_then(|f| f.iter().map(|f| f.rows).sum()))
+                    .or_else(|| schema.stats.as_ref().and_then(|s| s.row_count()))
                     .unwrap_or(0);
 
                 let bytes = hints
                     .and_then(|h| h.source_bytes.iter().find(|(s, _)| s == source))
                     .map(|(_, b)| *b)
+                    .or_else(|| files.map(|f| f.iter().map(|f| f.bytes).sum()))
                     .unwrap_or(rows * schema_size_bytes(schema));
 
                 *acc_rows += rows;
@@ -63,10 +92,28 @@ pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstima
                 let out_rows = ((in_rows as f64) * selectivity) as u64;
                 out_rows.max(1)
             }
+            // Key-file membership selectivity is unknown; assume all rows pass.
             Map { input, .. }
+            | FilterIn { input, .. }
             | Project { input, .. }
             | Window { input, .. }
             | Lateral { input, .. } => walk(input, hints, acc_rows, acc_bytes, max_fan_in),
+            TopK { input, k, .. } => {
+                let in_rows = walk(input, hints, acc_rows, acc_bytes, max_fan_in);
+                (*k as u64).min(in_rows).max(1)
+            }
+            Limit {
+                input,
+                limit,
+                offset,
+                ..
+            } => {
+                let in_rows = walk(input, hints, acc_rows, acc_bytes, max_fan_in);
+                in_rows
+                    .saturating_sub(*offset as u64)
+                    .min(*limit as u64)
+                    .max(1)
+            }
             Join {
                 left, right, on, ..
             } => {
@@ -78,6 +125,24 @@ pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstima
                 let join_card = estimate_join_cardinality(left, right, on, l, r);
                 join_card.max(1)
             }
+            ThetaJoin {
+                left,
+                right,
+                condition,
+                ..
+            } => {
+                *max_fan_in = (*max_fan_in).max(2);
+                let l = walk(left, hints, acc_rows, acc_bytes, max_fan_in);
+                let r = walk(right, hints, acc_rows, acc_bytes, max_fan_in);
+
+                // Every pair for a cross join; a third of them past an
+                // (unestimated) inequality.
+                let pairs = l.saturating_mul(r);
+                match condition {
+                    Some(_) => (pairs / 3).max(1),
+                    None => pairs.max(1),
+                }
+            }
             Aggregate {
                 input, group_by, ..
             } => {
@@ -88,6 +153,11 @@ pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstima
                 groups.max(1)
             }
             Sink { input, .. } => walk(input, hints, acc_rows, acc_bytes, max_fan_in),
+            // Union blocks pass each input block through, so fan-in stays 1.
+            Union { inputs } => inputs
+                .iter()
+                .map(|i| walk(i, hints, acc_rows, acc_bytes, max_fan_in))
+                .sum(),
         }
     }
 
@@ -105,10 +175,72 @@ pub fn estimate_work(plan: &LogicalPlan, hints: Option<&WorkHint>) -> WorkEstima
     }
 }
 
+/// Give each scan without declared stats the stats collected for its source
+/// by an earlier run (`RunManifest::source_stats`). Returns the scans updated.
+///
+/// Call before lowering so the physical plan carries them too.
+pub fn attach_source_stats(plan: &mut LogicalPlan, stats: &SourceStats) -> usize {
+    use LogicalPlan::*;
+    match plan {
+        Scan { source, schema } => match stats.get(source.as_str()) {
+            Some(collected) if schema.stats.is_none() => {
+                schema.stats = Some(collected.clone());
+                1
+            }
+            _ => 0,
+        },
+        Filter { input, .. }
+        | FilterIn { input, .. }
+        | Map { input, .. }
+        | Project { input, .. }
+        | Aggregate { input, .. }
+        | Window { input, .. }
+        | Lateral { input, .. }
+        | TopK { input, .. }
+        | Limit { input, .. }
+        | Sink { input, .. } => attach_source_stats(input, stats),
+        Join { left, right, .. } | ThetaJoin { left, right, .. } => {
+            attach_source_stats(left, stats) + attach_source_stats(right, stats)
+        }
+        Union { inputs } => inputs
+            .iter_mut()
+            .map(|i| attach_source_stats(i, stats))
+            .sum(),
+    }
+}
+
+/// Sources of every scan in `plan`, in plan order (repeats included).
+pub fn scan_sources(plan: &LogicalPlan) -> Vec<&str> {
+    use LogicalPlan::*;
+    match plan {
+        Scan { source, .. } => vec![source.as_str()],
+        Filter { input, .. }
+        | FilterIn { input, .. }
+        | Map { input, .. }
+        | Project { input, .. }
+        | Aggregate { input, .. }
+        | Window { input, .. }
+        | Lateral { input, .. }
+        | TopK { input, .. }
+        | Limit { input, .. }
+        | Sink { input, .. } => scan_sources(input),
+        Join { left, right, .. } | ThetaJoin { left, right, .. } => {
+            let mut sources = scan_sources(left);
+            sources.extend(scan_sources(right));
+            sources
+        }
+        Union { inputs } => inputs.iter().flat_map(|i| scan_sources(i)).collect(),
+    }
+}
+
 /// Estimate filter selectivity (fraction of rows that pass the filter).
 ///
 /// Uses column statistics if available, otherwise falls back to heuristics.
 fn estimate_filter_selectivity(expr: &str, input_plan: &LogicalPlan) -> f64 {
+    if let Some(selectivity) = estimate_between_in_selectivity(expr, input_plan) {
+        return selectivity;
+    }
+
     // Simple heuristic: try to parse the expression and use stats if available
     // For now, parse simple predicates like "col OP literal"
     let ops = ["==", "!=", "<=", ">=", "<", ">"];
@@ -150,6 +282,48 @@ fn estimate_filter_selectivity(expr: &str, input_plan: &LogicalPlan) -> f64 {
     0.5
 }
 
+/// Selectivity of `col [NOT] BETWEEN lo AND hi` and `col [NOT] IN (...)` over
+/// literals, from the column's statistics.
+fn estimate_between_in_selectivity(expr: &str, input_plan: &LogicalPlan) -> Option<f64> {
+    use emsqrt_core::expr::Expr;
+
+    let parsed = Expr::parse(expr).ok()?;
+    let (column, negated) = match &parsed {
+        Expr::Between { expr, negated, .. } | Expr::InList { expr, negated, .. } => {
+            match expr.as_ref() {
+                Expr::Column(name) => (name, *negated),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let col_stats = get_schema_from_plan(input_plan)?
+        .stats
+        .as_ref()?
+        .get(column)?;
+
+    let selectivity = match &parsed {
+        Expr::Between { low, high, .. } => match (low.as_ref(), high.as_ref()) {
+            (Expr::Literal(low), Expr::Literal(high)) => {
+                col_stats.estimate_range_selectivity(Some(low), Some(high))
+            }
+            _ => return None,
+        },
+        Expr::InList { list, .. } => {
+            if !list.iter().all(|v| matches!(v, Expr::Literal(_))) {
+                return None;
+            }
+            (list.len() as f64 * col_stats.estimate_equality_selectivity()).min(1.0)
+        }
+        _ => return None,
+    };
+    Some(if negated {
+        1.0 - selectivity
+    } else {
+        selectivity
+    })
+}
+
 /// Estimate join cardinality (number of output rows).
 ///
 /// Uses distinct_count from statistics if available, otherwise uses heuristics.
@@ -178,8 +352,8 @@ fn estimate_join_cardinality(
                     // Estimate: rows * rows / max(distinct_left, distinct_right)
                     // This is a simplified model assuming uniform distribution
                     let max_distinct = left_distinct.max(right_distinct);
-                    if max_distinct > 0 {
-                        return (left_rows * right_rows / max_distinct).min(left_rows * right_rows);
+                    if let Some(est) = (left_rows * right_rows).checked_div(max_distinct) {
+                        return est.min(left_rows * right_rows);
                     }
                 }
             }
@@ -233,46 +407,22 @@ fn get_schema_from_plan(plan: &LogicalPlan) -> Option<&Schema> {
     use LogicalPlan::*;
     match plan {
         Scan { schema, .. } => Some(schema),
-        Filter { input, .. } => get_schema_from_plan(input),
+        Filter { input, .. } | FilterIn { input, .. } => get_schema_from_plan(input),
         Map { input, .. } | Project { input, .. } => get_schema_from_plan(input),
-        Join { left, .. } => get_schema_from_plan(left), // Use left schema as approximation
+        Join { left, .. } | ThetaJoin { left, .. } => get_schema_from_plan(left), // Use left schema as approximation
         Aggregate { input, .. } => get_schema_from_plan(input),
-        Sink { input, .. } | Window { input, .. } | Lateral { input, .. } => {
-            get_schema_from_plan(input)
-        }
+        Sink { input, .. }
+        | Window { input, .. }
+        | Lateral { input, .. }
+        | TopK { input, .. }
+        | Limit { input, .. } => get_schema_from_plan(input),
+        Union { inputs } => inputs.first().and_then(get_schema_from_plan),
     }
 }
 
-/// Parse a literal string as a Scalar value.
+/// Parse a literal string as a Scalar value, with the expression parser's
+/// typing rules; anything else is taken as a bare string.
 fn parse_literal_as_scalar(literal: &str) -> Result<emsqrt_core::types::Scalar, String> {
-    use emsqrt_core::types::Scalar;
-
-    // Try to parse as different types
-    if let Ok(i) = literal.parse::<i32>() {
-        return Ok(Scalar::I32(i));
-    }
-    if let Ok(i) = literal.parse::<i64>() {
-        return Ok(Scalar::I64(i));
-    }
-    if let Ok(f) = literal.parse::<f32>() {
-        return Ok(Scalar::F32(f));
-    }
-    if let Ok(f) = literal.parse::<f64>() {
-        return Ok(Scalar::F64(f));
-    }
-    if let Ok(b) = literal.parse::<bool>() {
-        return Ok(Scalar::Bool(b));
-    }
-
-    // Try removing quotes for strings
-    let trimmed = literal.trim();
-    if (trimmed.starts_with('"') && trimmed.ends_with('"'))
-        || (trimmed.starts_with('\'') && trimmed.ends_with('\''))
-    {
-        let unquoted = &trimmed[1..trimmed.len() - 1];
-        return Ok(Scalar::Str(unquoted.to_string()));
-    }
-
-    // Default to string
-    Ok(Scalar::Str(literal.to_string()))
+    Ok(emsqrt_core::expr::parse_literal(literal)
+        .unwrap_or_else(|_| emsqrt_core::types::Scalar::Str(literal.to_string())))
 }
@@ -0,0 +1,169 @@
+//! `let` steps: pipeline variables computed from data.
+//!
+//! ```yaml
+//! steps:
+//!   - op: let
+//!     name: max_loaded_date
+//!     reduce: max
+//!     column: date
+//!     default: "1970-01-01"
+//!     steps:
+//!       - op: scan
+//!         source: "warehouse/loaded.csv"
+//!         schema: [ { name: "date", type: "Utf8" } ]
+//!   - op: scan
+//!     source: "landing/events.csv"
+//!     schema: [ ... ]
+//!   - op: filter
+//!     expr: "date > ${max_loaded_date}"
+//! ```
+//!
+//! Each `let` runs its own sub-pipeline (a scan plus row-wise steps, without a
+//! sink) and reduces the rows to one scalar. The planner does not read data,
+//! so the values are computed by the executor (`emsqrt_exec::lets`) before the
+//! pipeline is planned, and handed back as parameters holding expression
+//! literals. A `let` may use the variables of the `let`s before it; a
+//! `--param` of the same name replaces the computed value.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use emsqrt_core::dag::LogicalPlan;
+use emsqrt_core::types::Scalar;
+
+use crate::dsl::stages::{parse_document, Document};
+use crate::dsl::template::TemplateParams;
+use crate::dsl::yaml::{build_plan, Step};
+
+/// How a `let` turns its sub-pipeline's rows into one value. Nulls are
+/// skipped by every reduction except `count` of rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LetReduce {
+    /// The sub-pipeline must yield at most one row.
+    #[default]
+    Single,
+    Max,
+    Min,
+    Sum,
+    /// Non-null values of `column`, or rows when no column is named.
+    Count,
+}
+
+/// One `let` step of a pipeline.
+#[derive(Debug, Clone)]
+pub struct LetStage {
+    pub name: String,
+    pub reduce: LetReduce,
+    pub column: Option<String>,
+    pub default: Option<Scalar>,
+    steps: Vec<Step>,
+    // This and the later lets, whose variables the sub-pipeline can't use
+    pending: BTreeSet<String>,
+}
+
+impl LetStage {
+    /// The sub-pipeline, with variables and source templates filled from
+    /// `params` (which must hold the values of the earlier `let`s).
+    pub fn plan(&self, params: &TemplateParams) -> Result<LogicalPlan, serde_yaml::Error> {
+        build_plan(self.steps.clone(), params, &mut self.pending.clone())
+    }
+}
+
+/// The `let` steps of a YAML pipeline, in order.
+pub fn let_stages(yaml_src: &str) -> Result<Vec<LetStage>, serde_yaml::Error> {
+    use serde::de::Error as _;
+
+    let steps = match parse_document(yaml_src)? {
+        Document::V1(doc) => doc.steps,
+        Document::V2(doc) => doc.let_steps(),
+    };
+    collect_lets(&steps).map_err(serde_yaml::Error::custom)
+}
+
+pub(crate) fn collect_lets(steps: &[Step]) -> Result<Vec<LetStage>, String> {
+    let mut stages: Vec<LetStage> = Vec::new();
+    for step in steps {
+        let Step::Let {
+            name,
+            steps,
+            reduce,
+            column,
+            default,
+        } = step
+        else {
+            continue;
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "let name '{}' must be letters, digits and '_'",
+                name
+            ));
+        }
+        if stages.iter().any(|s| s.name == *name) {
+            return Err(format!("let '{}' is defined twice", name));
+        }
+        if let Some(inner) = steps
+            .iter()
+            .find(|s| matches!(s, Step::Let { .. } | Step::Sink { .. }))
+        {
+            let op = if matches!(inner, Step::Let { .. }) {
+                "let"
+            } else {
+                "sink"
+            };
+            return Err(format!("let '{}' steps cannot include a {}", name, op));
+        }
+        let default = default
+            .as_ref()
+            .map(|v| {
+                yaml_scalar(v).ok_or_else(|| format!("let '{}' default must be a scalar", name))
+            })
+            .transpose()?;
+        stages.push(LetStage {
+            name: name.clone(),
+            reduce: *reduce,
+            column: column.clone(),
+            default,
+            steps: steps.clone(),
+            pending: BTreeSet::new(),
+        });
+    }
+    for i in 0..stages.len() {
+        let later: BTreeSet<String> = stages[i..].iter().map(|s| s.name.clone()).collect();
+        stages[i].pending = later;
+    }
+    Ok(stages)
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<Scalar> {
+    use serde_yaml::Value;
+    match value {
+        Value::Bool(b) => Some(Scalar::Bool(*b)),
+        Value::Number(n) => n
+            .as_i64()
+            .map(Scalar::I64)
+            .or_else(|| n.as_f64().map(Scalar::F64)),
+        Value::String(s) => Some(Scalar::Str(s.clone())),
+        _ => None,
+    }
+}
+
+/// `value` spelled as an expression literal of the same type, the form in
+/// which `let` values are substituted for `${name}`.
+pub fn let_literal(value: &Scalar) -> Result<String, String> {
+    match value {
+        Scalar::Bool(b) => Ok(b.to_string()),
+        Scalar::I32(v) => Ok(format!("{}i32", v)),
+        Scalar::I64(v) => Ok(format!("{}i64", v)),
+        Scalar::F32(v) if v.is_finite() => Ok(format!("{:?}f32", v)),
+        Scalar::F64(v) if v.is_finite() => Ok(format!("{:?}f64", v)),
+        Scalar::Str(s) if !s.contains('\'') => Ok(format!("'{}'", s)),
+        Scalar::Str(s) if !s.contains('"') => Ok(format!("\"{}\"", s)),
+        other => Err(format!(
+            "{:?} cannot be written as an expression literal",
+            other
+        )),
+    }
+}
@@ -1,3 +1,6 @@
-//! DSL front-ends. Currently only a tiny YAML pipeline is supported.
+//! DSL front-ends: YAML pipelines (versions 1 and 2).
 
+pub mod lets;
+pub mod stages;
+pub mod template;
 pub mod yaml;
@@ -0,0 +1,549 @@
+//! Version 2 pipelines: named stages wired together by `inputs:`.
+//!
+//! Example:
+//! ```yaml
+//! version: 2
+//! stages:
+//!   - name: orders
+//!     op: scan
+//!     source: "data/orders.csv"
+//!     schema: [ {name: "customer_id", type: "Int64"}, {name: "total", type: "Float64"} ]
+//!   - name: customers
+//!     op: scan
+//!     source: "data/customers.csv"
+//!     schema: [ {name: "id", type: "Int64"}, {name: "country", type: "Utf8"} ]
+//!   - name: enriched
+//!     op: join
+//!     inputs: [orders, customers]
+//!     on: [ {left: customer_id, right: id} ]
+//!     join_type: left
+//!     resources: { memory_bytes: 268435456 }
+//!   - name: by_country
+//!     op: aggregate
+//!     group_by: [country]
+//!     aggs: [count, "sum:total"]
+//!   - name: out
+//!     op: sink
+//!     destination: "out/by_country.csv"
+//!     format: csv
+//! ```
+//!
+//! Every v1 step is a stage `op` with the same options, and `join`,
+//! `cross_join`, `aggregate` and `union` combine or reduce stages. A `join`
+//! matches rows on `on` keys, or on a `condition` over its output columns
+//! (e.g. `ts >= start AND ts < end`; inner or left only). A stage reads the
+//! stages named in `inputs`, which must come before it; without `inputs` it
+//! reads the stage before it (scans read none). Exactly one stage, normally
+//! the sink, is read by no other stage. A stage read by several stages
+//! appears under each of them in the logical plan; lowering folds the copies
+//! back into one operator that feeds them all. A `let` stage binds its name
+//! as a variable for the stages after it and is not an input.
+//!
+//! `resources:` sets a stage's `memory_bytes` and `parallelism`; they go to
+//! the operator the stage lowers to (see [`StageHint`]).
+//!
+//! Documents without `version` are v1 (`steps:`); [`migrate_v1`] rewrites
+//! one as v2 and [`load_pipeline`] reads either.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_yaml::{Mapping, Value};
+
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan};
+use emsqrt_core::expr::Expr;
+
+use crate::dsl::template::TemplateParams;
+use crate::dsl::yaml::{build_step, step_op, Pipeline, PipelineConfig, Step};
+use crate::hints::{PlanHints, StageHint, StageResources};
+use crate::lower::lower_to_physical;
+
+/// Newest pipeline format version.
+pub const PIPELINE_VERSION: u32 = 2;
+
+/// A version 2 pipeline document.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineV2 {
+    pub version: u32,
+    #[serde(default)]
+    pub config: Option<PipelineConfig>,
+    #[serde(default)]
+    pub hints: Option<PlanHints>,
+    pub stages: Vec<Stage>,
+}
+
+/// A named operator and the stages it reads.
+#[derive(Debug, Clone)]
+pub struct Stage {
+    pub name: String,
+    /// Stages read, in order; empty reads the stage before (none for scans).
+    pub inputs: Vec<String>,
+    pub resources: StageResources,
+    pub op: StageOp,
+}
+
+#[derive(Debug, Clone)]
+pub enum StageOp {
+    /// A v1 step; a `let` step is named after its stage.
+    Step(Step),
+    Join {
+        on: Vec<(String, String)>,
+        join_type: JoinType,
+    },
+    /// A join on a `condition`, or a cross join without one.
+    ThetaJoin {
+        condition: Option<String>,
+        join_type: JoinType,
+    },
+    Aggregate {
+        group_by: Vec<String>,
+        aggs: Vec<Aggregation>,
+    },
+    Union,
+}
+
+impl StageOp {
+    /// The `op` name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StageOp::Step(step) => step_op(step),
+            StageOp::Join { .. }
+            | StageOp::ThetaJoin {
+                condition: Some(_), ..
+            } => "join",
+            StageOp::ThetaJoin {
+                condition: None, ..
+            } => "cross_join",
+            StageOp::Aggregate { .. } => "aggregate",
+            StageOp::Union => "union",
+        }
+    }
+
+    /// Whether `n` inputs suit the op.
+    fn takes_inputs(&self, n: usize) -> bool {
+        match self {
+            StageOp::Step(Step::Scan { .. } | Step::Let { .. }) => n == 0,
+            StageOp::Step(_) | StageOp::Aggregate { .. } => n == 1,
+            StageOp::Join { .. } | StageOp::ThetaJoin { .. } => n == 2,
+            StageOp::Union => n >= 2,
+        }
+    }
+}
+
+/// The options of the ops v1 has no step for.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+enum DagOp {
+    Join {
+        #[serde(default)]
+        on: Vec<JoinKeyDef>,
+        #[serde(default)]
+        condition: Option<String>,
+        #[serde(default)]
+        join_type: Option<String>,
+    },
+    CrossJoin {},
+    Aggregate {
+        #[serde(default)]
+        group_by: Vec<String>,
+        aggs: Vec<String>,
+    },
+    Union {},
+}
+
+/// A join key: one column name on both sides, or `{left, right}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JoinKeyDef {
+    Same(String),
+    Pair { left: String, right: String },
+}
+
+fn parse_join_type(s: &str) -> Result<JoinType, String> {
+    match s {
+        "inner" => Ok(JoinType::Inner),
+        "left" => Ok(JoinType::Left),
+        "right" => Ok(JoinType::Right),
+        "full" => Ok(JoinType::Full),
+        _ => Err(format!(
+            "unknown join_type '{}' (expected inner, left, right or full)",
+            s
+        )),
+    }
+}
+
+impl TryFrom<DagOp> for StageOp {
+    type Error = String;
+
+    fn try_from(op: DagOp) -> Result<Self, String> {
+        Ok(match op {
+            DagOp::Join {
+                on,
+                condition: Some(condition),
+                join_type,
+            } => {
+                if !on.is_empty() {
+                    return Err("join takes keys in 'on' or a 'condition', not both".into());
+                }
+                Expr::parse(&condition)
+                    .map_err(|e| format!("invalid join condition '{}': {}", condition, e))?;
+                let join_type = join_type
+                    .as_deref()
+                    .map_or(Ok(JoinType::Inner), parse_join_type)?;
+                if !matches!(join_type, JoinType::Inner | JoinType::Left) {
+                    return Err("a join on a 'condition' is inner or left".into());
+                }
+                StageOp::ThetaJoin {
+                    condition: Some(condition),
+                    join_type,
+                }
+            }
+            DagOp::Join {
+                on,
+                condition: None,
+                join_type,
+            } => {
+                if on.is_empty() {
+                    return Err("join needs at least one key in 'on', or a 'condition'".into());
+                }
+                StageOp::Join {
+                    on: on
+                        .into_iter()
+                        .map(|key| match key {
+                            JoinKeyDef::Same(col) => (col.clone(), col),
+                            JoinKeyDef::Pair { left, right } => (left, right),
+                        })
+                        .collect(),
+                    join_type: join_type
+                        .as_deref()
+                        .map_or(Ok(JoinType::Inner), parse_join_type)?,
+                }
+            }
+            DagOp::Aggregate { group_by, aggs } => StageOp::Aggregate {
+                group_by,
+                aggs: aggs
+                    .iter()
+                    .map(|spec| Aggregation::parse(spec))
+                    .collect::<Result<_, _>>()?,
+            },
+            DagOp::CrossJoin {} => StageOp::ThetaJoin {
+                condition: None,
+                join_type: JoinType::Inner,
+            },
+            DagOp::Union {} => StageOp::Union,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Stage {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let mut map = Mapping::deserialize(d)?;
+        let name: String = match map.remove("name") {
+            Some(v) => serde_yaml::from_value(v).map_err(D::Error::custom)?,
+            None => return Err(D::Error::custom("every stage needs a 'name'")),
+        };
+        let in_stage =
+            |e: &dyn std::fmt::Display| D::Error::custom(format!("stage '{}': {}", name, e));
+        let inputs: Vec<String> = match map.remove("inputs") {
+            Some(v) => serde_yaml::from_value(v).map_err(|e| in_stage(&e))?,
+            None => Vec::new(),
+        };
+        let resources: StageResources = match map.remove("resources") {
+            Some(v) => serde_yaml::from_value(v).map_err(|e| in_stage(&e))?,
+            None => StageResources::default(),
+        };
+        let op = match map.get("op").and_then(Value::as_str) {
+            Some("join" | "cross_join" | "aggregate" | "union") => {
+                let op: DagOp =
+                    serde_yaml::from_value(Value::Mapping(map)).map_err(|e| in_stage(&e))?;
+                StageOp::try_from(op).map_err(|e| in_stage(&e))?
+            }
+            other => {
+                if other == Some("let") {
+                    map.insert("name".into(), name.clone().into());
+                }
+                StageOp::Step(
+                    serde_yaml::from_value(Value::Mapping(map)).map_err(|e| in_stage(&e))?,
+                )
+            }
+        };
+        Ok(Stage {
+            name,
+            inputs,
+            resources,
+            op,
+        })
+    }
+}
+
+impl PipelineV2 {
+    /// The `let` stages, as v1 `let` steps.
+    pub fn let_steps(&self) -> Vec<Step> {
+        self.stages
+            .iter()
+            .filter_map(|stage| match &stage.op {
+                StageOp::Step(step @ Step::Let { .. }) => Some(step.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A pipeline document of either version.
+#[derive(Debug, Clone)]
+pub enum Document {
+    V1(Pipeline),
+    V2(PipelineV2),
+}
+
+/// Just the version of a document.
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(default)]
+    version: Option<u32>,
+    #[serde(default)]
+    stages: Option<serde::de::IgnoredAny>,
+}
+
+/// Parse a pipeline document of the version it declares (1 without one).
+pub fn parse_document(yaml_src: &str) -> Result<Document, serde_yaml::Error> {
+    let probe: VersionProbe = serde_yaml::from_str(yaml_src)?;
+    match probe.version {
+        None if probe.stages.is_some() => Err(serde_yaml::Error::custom(
+            "a pipeline with 'stages' needs 'version: 2'",
+        )),
+        None | Some(1) => serde_yaml::from_str(yaml_src).map(Document::V1),
+        Some(2) => serde_yaml::from_str(yaml_src).map(Document::V2),
+        Some(v) => Err(serde_yaml::Error::custom(format!(
+            "unsupported pipeline version {} (this build reads versions 1 and {})",
+            v, PIPELINE_VERSION
+        ))),
+    }
+}
+
+/// Read a pipeline of either version as v2, migrating v1 documents.
+pub fn load_pipeline(yaml_src: &str) -> Result<PipelineV2, serde_yaml::Error> {
+    match parse_document(yaml_src)? {
+        Document::V2(doc) => Ok(doc),
+        Document::V1(_) => {
+            let migrated = migrate_v1(yaml_src)?;
+            serde_yaml::from_str(&migrated)
+        }
+    }
+}
+
+/// Rewrite a v1 document as v2: `steps` become `stages` named after their
+/// op (`filter`, `filter_2`, ...; a `let` keeps its name), each reading the
+/// one before as the step did. Other keys are kept; comments are not.
+pub fn migrate_v1(yaml_src: &str) -> Result<String, serde_yaml::Error> {
+    if let Document::V2(_) = parse_document(yaml_src)? {
+        return Err(serde_yaml::Error::custom(format!(
+            "the pipeline is already version {}",
+            PIPELINE_VERSION
+        )));
+    }
+    let Value::Mapping(doc) = serde_yaml::from_str::<Value>(yaml_src)? else {
+        return Err(serde_yaml::Error::custom("a pipeline is a mapping"));
+    };
+    let mut out = Mapping::new();
+    out.insert("version".into(), PIPELINE_VERSION.into());
+    for (key, value) in doc {
+        match key.as_str() {
+            Some("version") => {}
+            Some("steps") => {
+                out.insert("stages".into(), migrate_steps(value)?);
+            }
+            _ => {
+                out.insert(key, value);
+            }
+        }
+    }
+    serde_yaml::to_string(&out)
+}
+
+fn migrate_steps(steps: Value) -> Result<Value, serde_yaml::Error> {
+    let Value::Sequence(steps) = steps else {
+        return Err(serde_yaml::Error::custom("'steps' is a list"));
+    };
+    let op = |step: &Mapping| step.get("op").and_then(Value::as_str).map(str::to_string);
+    // Generated names must not take a let's name.
+    let mut taken: BTreeSet<String> = steps
+        .iter()
+        .filter_map(Value::as_mapping)
+        .filter(|step| op(step).as_deref() == Some("let"))
+        .filter_map(|step| step.get("name").and_then(Value::as_str).map(str::to_string))
+        .collect();
+    let mut stages = Vec::with_capacity(steps.len());
+    for step in steps {
+        let Value::Mapping(step) = step else {
+            return Err(serde_yaml::Error::custom("each step is a mapping"));
+        };
+        let op = op(&step).ok_or_else(|| serde_yaml::Error::custom("each step needs an 'op'"))?;
+        let mut stage = Mapping::new();
+        if op != "let" {
+            let name = (1..)
+                .map(|n| match n {
+                    1 => op.clone(),
+                    n => format!("{}_{}", op, n),
+                })
+                .find(|name| !taken.contains(name))
+                .unwrap();
+            taken.insert(name.clone());
+            stage.insert("name".into(), name.into());
+        }
+        stage.extend(step);
+        stages.push(Value::Mapping(stage));
+    }
+    Ok(Value::Sequence(stages))
+}
+
+/// Build the plan of `stages`, adding the stages' resources to `hints`.
+pub(crate) fn build_stages(
+    stages: Vec<Stage>,
+    params: &TemplateParams,
+    hints: &mut PlanHints,
+) -> Result<LogicalPlan, serde_yaml::Error> {
+    let err =
+        |stage: &str, msg: String| serde_yaml::Error::custom(format!("stage '{}': {}", stage, msg));
+
+    let mut seen = BTreeSet::new();
+    for stage in &stages {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if stage.name.is_empty() || !stage.name.chars().all(valid) {
+            return Err(serde_yaml::Error::custom(format!(
+                "stage name '{}' must be letters, digits, '_' and '-'",
+                stage.name
+            )));
+        }
+        if !seen.insert(stage.name.clone()) {
+            return Err(serde_yaml::Error::custom(format!(
+                "stage '{}' is defined twice",
+                stage.name
+            )));
+        }
+    }
+
+    // Names of the lets not reached yet, whose variables may not be used.
+    let mut pending: BTreeSet<String> = stages
+        .iter()
+        .filter(|s| matches!(s.op, StageOp::Step(Step::Let { .. })))
+        .map(|s| s.name.clone())
+        .collect();
+    let mut plans: BTreeMap<String, LogicalPlan> = BTreeMap::new();
+    // Stages in order, and whether another stage reads them.
+    let mut read: Vec<(String, bool)> = Vec::new();
+
+    for stage in stages {
+        let Stage {
+            name,
+            inputs,
+            resources,
+            op,
+        } = stage;
+        if let StageOp::Step(Step::Let { .. }) = op {
+            if !inputs.is_empty() {
+                return Err(err(&name, "a let has no inputs".into()));
+            }
+            if !params.contains_key(&name) {
+                return Err(err(
+                    &name,
+                    format!(
+                        "let has no value; evaluate the pipeline's let stages first (emsqrt_exec::lets::resolve_lets) or pass --param {}=...",
+                        name
+                    ),
+                ));
+            }
+            pending.remove(&name);
+            continue;
+        }
+
+        let inputs = match (inputs.is_empty(), read.last()) {
+            (true, Some((prev, _))) if !op.takes_inputs(0) => vec![prev.clone()],
+            _ => inputs,
+        };
+        if !op.takes_inputs(inputs.len()) {
+            return Err(err(
+                &name,
+                format!("{} cannot read {} input(s)", op.name(), inputs.len()),
+            ));
+        }
+        let mut input_plans = Vec::with_capacity(inputs.len());
+        for input in &inputs {
+            let Some(plan) = plans.get(input) else {
+                return Err(err(
+                    &name,
+                    format!("input '{}' is not a stage defined before it", input),
+                ));
+            };
+            input_plans.push(plan.clone());
+            if let Some(entry) = read.iter_mut().find(|(n, _)| n == input) {
+                entry.1 = true;
+            }
+        }
+
+        let plan = match op {
+            StageOp::Step(step) => build_step(step, input_plans.pop(), params, &pending)
+                .map_err(|e| err(&name, e.to_string()))?,
+            StageOp::Join { on, join_type } => {
+                let right = input_plans.pop().unwrap();
+                let left = input_plans.pop().unwrap();
+                LogicalPlan::Join {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    on,
+                    join_type,
+                }
+            }
+            StageOp::ThetaJoin {
+                condition,
+                join_type,
+            } => {
+                let right = input_plans.pop().unwrap();
+                let left = input_plans.pop().unwrap();
+                LogicalPlan::ThetaJoin {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    condition,
+                    join_type,
+                }
+            }
+            StageOp::Aggregate { group_by, aggs } => LogicalPlan::Aggregate {
+                input: Box::new(input_plans.pop().unwrap()),
+                group_by,
+                aggs,
+            },
+            StageOp::Union => LogicalPlan::Union {
+                inputs: input_plans,
+            },
+        };
+        if !resources.is_empty() {
+            // The node's own binding is the last one lowering allocates.
+            let program = lower_to_physical(&plan);
+            if let Some(binding) = program.bindings.values().next_back() {
+                hints.stages.push(StageHint::new(&name, binding, resources));
+            }
+        }
+        plans.insert(name.clone(), plan);
+        read.push((name, false));
+    }
+
+    let outputs: Vec<&str> = read
+        .iter()
+        .filter(|(_, is_read)| !is_read)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    match outputs.as_slice() {
+        [output] => Ok(plans.remove(*output).unwrap()),
+        [] => Err(serde_yaml::Error::custom("the pipeline has no stages")),
+        several => Err(serde_yaml::Error::custom(format!(
+            "stages {} are not read by any stage; a pipeline has one output",
+            several
+                .iter()
+                .map(|s| format!("'{}'", s))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
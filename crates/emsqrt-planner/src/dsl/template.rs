@@ -0,0 +1,152 @@
+//! Source templates: expand `data/{date}/hour={00..23}.csv` into a file list.
+//!
+//! Placeholders:
+//! - `{name}`: replaced by the parameter `name` (e.g. from `--param date=2024-01-01`).
+//! - `{a..b}`: inclusive integer range; zero-padded when `a` has a leading zero.
+//!
+//! Multiple ranges expand to their cartesian product, in left-to-right order.
+//! Sink destinations take `{name}` placeholders but no ranges (see
+//! [`expand_destination`]).
+//!
+//! Expressions use `${name}` instead, see [`substitute_variables`].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Parameters supplied at plan time (CLI `--param key=value`).
+pub type TemplateParams = BTreeMap<String, String>;
+
+/// Expand a source template into concrete sources, in deterministic order.
+pub fn expand_source_template(
+    template: &str,
+    params: &TemplateParams,
+) -> Result<Vec<String>, String> {
+    expand_template("source template", template, params)
+}
+
+/// Fill the `{name}` placeholders of a sink destination from `params`, so
+/// `out/{date}.csv` writes one file per `--param date=...`.
+pub fn expand_destination(destination: &str, params: &TemplateParams) -> Result<String, String> {
+    let mut paths = expand_template("sink destination", destination, params)?;
+    if paths.len() != 1 {
+        return Err(format!(
+            "sink destination '{}' cannot use a range; it names one file",
+            destination
+        ));
+    }
+    Ok(paths.pop().unwrap())
+}
+
+fn expand_template(
+    kind: &str,
+    template: &str,
+    params: &TemplateParams,
+) -> Result<Vec<String>, String> {
+    let mut out = vec![String::new()];
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..]
+            .find('}')
+            .map(|i| open + i)
+            .ok_or_else(|| format!("unclosed '{{' in {} '{}'", kind, template))?;
+        let literal = &rest[..open];
+        let token = rest[open + 1..close].trim();
+        for prefix in &mut out {
+            prefix.push_str(literal);
+        }
+
+        if let Some((lo, hi)) = token.split_once("..") {
+            let values = expand_range(lo.trim(), hi.trim())
+                .map_err(|e| format!("in {} '{}': {}", kind, template, e))?;
+            out = out
+                .iter()
+                .flat_map(|prefix| values.iter().map(move |v| format!("{}{}", prefix, v)))
+                .collect();
+        } else {
+            let value = params.get(token).ok_or_else(|| {
+                format!(
+                    "{} '{}' needs parameter '{}' (pass --param {}=...)",
+                    kind, template, token, token
+                )
+            })?;
+            for prefix in &mut out {
+                prefix.push_str(value);
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+
+    for prefix in &mut out {
+        prefix.push_str(rest);
+    }
+    Ok(out)
+}
+
+/// Replace each `${name}` in the expression `expr` with the text of
+/// parameter `name`. `pending` names `let` variables that are not defined
+/// yet at this point of the pipeline.
+pub fn substitute_variables(
+    expr: &str,
+    params: &TemplateParams,
+    pending: &BTreeSet<String>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(expr.len());
+    let mut rest = expr;
+    while let Some(open) = rest.find("${") {
+        out.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .map(|i| open + i)
+            .ok_or_else(|| format!("unclosed '${{' in expression '{}'", expr))?;
+        let name = rest[open + 2..close].trim();
+        if pending.contains(name) {
+            return Err(format!(
+                "expression '{}' uses ${{{}}} before its let step",
+                expr, name
+            ));
+        }
+        let value = params.get(name).ok_or_else(|| {
+            format!(
+                "expression '{}' uses undefined variable ${{{}}} (add a let step or pass --param {}=...)",
+                expr, name, name
+            )
+        })?;
+        out.push_str(value);
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn expand_range(lo: &str, hi: &str) -> Result<Vec<String>, String> {
+    let start: i64 = lo
+        .parse()
+        .map_err(|_| format!("invalid range start '{}'", lo))?;
+    let end: i64 = hi
+        .parse()
+        .map_err(|_| format!("invalid range end '{}'", hi))?;
+    if end < start {
+        return Err(format!("empty range {{{}..{}}}", lo, hi));
+    }
+    let width = if lo.len() > 1 && lo.starts_with('0') {
+        lo.len()
+    } else {
+        0
+    };
+    Ok((start..=end)
+        .map(|v| format!("{:0width$}", v, width = width))
+        .collect())
+}
+
+/// Parse `key=value` strings (as passed on the command line) into parameters.
+pub fn parse_template_params(pairs: &[String]) -> Result<TemplateParams, String> {
+    pairs
+        .iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .filter(|(k, _)| !k.is_empty())
+                .ok_or_else(|| format!("invalid parameter '{}', expected key=value", pair))
+        })
+        .collect()
+}
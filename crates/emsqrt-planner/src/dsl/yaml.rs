@@ -1,4 +1,6 @@
-//! Minimal YAML → LogicalPlan parser for *linear* pipelines.
+//! YAML → LogicalPlan parser. Version 1 pipelines, below, are a linear
+//! list of `steps`; version 2 pipelines name their stages and wire them into
+//! a DAG (see [`crate::dsl::stages`]).
 //!
 //! Example:
 //! ```yaml
@@ -11,19 +13,48 @@
 //!   - project: { columns: ["ts","uid"] }
 //!   - sink: { destination: "out/filtered.csv", format: "csv" }
 //! ```
+//!
+//! A scan may take `source_template: "data/{date}/hour={00..23}.csv"` instead of
+//! `source`; see [`crate::dsl::template`] for the placeholder syntax. A sink's
+//! `destination` may use `{name}` placeholders too.
+//!
+//! `filter` and `map` expressions may use `${name}` variables: `--param`
+//! values, or scalars computed by earlier `let` steps (see [`crate::dsl::lets`]).
+
+use std::collections::{BTreeMap, BTreeSet};
 
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 
-use emsqrt_core::dag::{LogicalPlan, WindowExpr, WindowFrame, WindowFunction};
-use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::align::AlignPolicy;
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::{
+    CsvSinkConfig, FallbackAction, JoinGuardConfig, ParquetSinkConfig, ReadRetryConfig,
+    SchedulePolicy, SourceBatchConfig, SpillCleanup, SpillCodec,
+};
+use emsqrt_core::dag::{LogicalPlan, SortKey, WindowExpr, WindowFrame, WindowFunction};
+use emsqrt_core::error::Error as CoreError;
+use emsqrt_core::expr::ArithErrorPolicy;
+use emsqrt_core::schema::{
+    Constraints, DataType, Field, FieldLayout, FixedWidthSpan, NullOptions, ParseErrorOptions,
+    ParseErrorPolicy, Schema, SourceFormat, TextLayout,
+};
 
+use crate::dsl::lets::{collect_lets, LetReduce};
+use crate::dsl::stages::{build_stages, parse_document, Document};
+use crate::dsl::template::{
+    expand_destination, expand_source_template, substitute_variables, TemplateParams,
+};
+use crate::hints::PlanHints;
 use crate::logical::LogicalPlan as L;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pipeline {
     #[serde(default)]
     pub config: Option<PipelineConfig>,
+    /// Estimate/planner overrides (see [`PlanHints`]).
+    #[serde(default)]
+    pub hints: Option<PlanHints>,
     pub steps: Vec<Step>,
 }
 
@@ -32,13 +63,52 @@ pub struct Pipeline {
 pub enum Step {
     #[serde(rename = "scan")]
     Scan {
-        source: String,
+        #[serde(default)]
+        source: Option<String>,
+        /// Templated file list, e.g. `data/{date}/hour={00..23}.csv`.
+        #[serde(default)]
+        source_template: Option<String>,
         schema: Vec<FieldDef>,
+        /// Declared keys and sort order of the data (checked under `--verify`).
+        #[serde(default)]
+        constraints: Constraints,
+        /// Columns the file is already ordered by (shorthand for
+        /// `constraints.sorted_by`); lets the planner skip sorts.
+        #[serde(default)]
+        sorted_by: Vec<String>,
+        /// `empty_as_null`, `null_tokens` and `trim_whitespace`.
+        #[serde(flatten)]
+        null_options: NullOptions,
+        /// Default for fields without their own `on_parse_error`.
+        #[serde(default, deserialize_with = "parse_error_policy")]
+        on_parse_error: ParseErrorPolicy,
+        /// JSONL file for rows dropped by a `dead_letter` policy.
+        #[serde(default)]
+        dead_letter: Option<String>,
+        /// `format`, `delimiter`, `record_delimiter`, `header`, `skip_records`.
+        #[serde(flatten)]
+        layout: Box<LayoutOptions>,
+        /// Files missing declared columns: `exact` (error), `fill_missing`
+        /// (nulls; extra file columns are an error) or `fill_and_drop`.
+        #[serde(default)]
+        align: AlignPolicy,
+        /// JSONL: read nested objects as `parent.child` columns.
+        #[serde(default)]
+        flatten: bool,
     },
 
     #[serde(rename = "filter")]
     Filter { expr: String },
 
+    /// Keep rows whose `columns` appear in the key file `keys`.
+    #[serde(rename = "filter_in")]
+    FilterIn {
+        columns: Vec<String>,
+        keys: String,
+        #[serde(default)]
+        delimiter: Option<String>,
+    },
+
     #[serde(rename = "project")]
     Project { columns: Vec<String> },
 
@@ -62,6 +132,44 @@ pub enum Step {
         #[serde(default)]
         delimiter: Option<String>,
     },
+
+    /// The `k` most frequent values of `column` with approximate counts.
+    #[serde(rename = "top_k")]
+    TopK {
+        column: String,
+        k: usize,
+        /// Counters in the sketch (default: ten per reported value).
+        #[serde(default)]
+        capacity: Option<usize>,
+    },
+
+    /// The first `limit` rows after skipping `offset`, in input order or by
+    /// `order_by` keys (`col` or `col desc`).
+    #[serde(rename = "limit")]
+    Limit {
+        limit: usize,
+        #[serde(default)]
+        offset: usize,
+        #[serde(default)]
+        order_by: Vec<String>,
+    },
+
+    /// Bind `name` to a scalar computed by its own sub-pipeline (a scan and
+    /// row-wise steps, no sink); later expressions read it as `${name}`.
+    #[serde(rename = "let")]
+    Let {
+        name: String,
+        steps: Vec<Step>,
+        /// How the sub-pipeline's rows become one value (default `single`).
+        #[serde(default)]
+        reduce: LetReduce,
+        /// Column holding the value; needed when the output has several.
+        #[serde(default)]
+        column: Option<String>,
+        /// Value when the sub-pipeline yields none (no rows, or all null).
+        #[serde(default)]
+        default: Option<serde_yaml::Value>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,22 +188,164 @@ pub struct FieldDef {
     pub data_type: String,
     #[serde(default)]
     pub nullable: bool,
+    #[serde(default, deserialize_with = "field_parse_error_policy")]
+    pub on_parse_error: Option<ParseErrorPolicy>,
+    /// `fixed_width` scans: first character of the column (default: where
+    /// the previous field ends).
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// `fixed_width` scans: characters in the column.
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+// An unquoted `on_parse_error: null` is YAML null; read it as the `null` policy.
+fn parse_error_policy<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<ParseErrorPolicy, D::Error> {
+    Ok(Option::<ParseErrorPolicy>::deserialize(d)?.unwrap_or_default())
+}
+
+fn field_parse_error_policy<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<Option<ParseErrorPolicy>, D::Error> {
+    parse_error_policy(d).map(Some)
+}
+
+/// An unquoted `null` names the null policy rather than leaving it unset.
+fn arithmetic_error_policy<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> Result<Option<ArithErrorPolicy>, D::Error> {
+    ArithErrorPolicy::deserialize(d).map(Some)
 }
 
 fn parse_dtype(s: &str) -> DataType {
-    match s {
-        "Boolean" | "bool" => DataType::Boolean,
-        "Int32" | "i32" => DataType::Int32,
-        "Int64" | "i64" => DataType::Int64,
-        "Float32" | "f32" => DataType::Float32,
-        "Float64" | "f64" => DataType::Float64,
-        "Binary" | "bytes" => DataType::Binary,
-        _ => DataType::Utf8,
+    DataType::parse(s).unwrap_or(DataType::Utf8)
+}
+
+/// Schema from comma-separated `name:Type` columns (`id:Int64,name`), as
+/// given on the command line. Type names are those of a scan's schema; a
+/// bare name is `Utf8`. Every column is nullable.
+pub fn parse_schema_spec(spec: &str) -> Result<Schema, String> {
+    let fields = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|column| {
+            let (name, ty) = column.split_once(':').unwrap_or((column, "Utf8"));
+            let (name, ty) = (name.trim(), ty.trim());
+            // `parse_dtype` reads an unknown name as Utf8; here a typo is an error.
+            let data_type = DataType::parse(ty)
+                .ok_or_else(|| format!("unknown type '{}' for column '{}'", ty, name))?;
+            Ok(Field::new(name, data_type, true))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if fields.is_empty() {
+        return Err("schema names no columns".into());
+    }
+    Schema::try_new(fields).map_err(|e| e.to_string())
+}
+
+/// How a scan splits its text, as written on the step.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayoutOptions {
+    /// `delimited` or `fixed_width` for text the CSV reader can't parse, or
+    /// `csv`, `jsonl`, `parquet` or `avro`; omitted, the reader follows the
+    /// file extension.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Field separator of a `delimited` scan.
+    #[serde(default)]
+    pub delimiter: Option<String>,
+    /// Record separator of a `delimited`/`fixed_width` scan (default "\n").
+    #[serde(default)]
+    pub record_delimiter: Option<String>,
+    /// Whether a `delimited` file starts with a header record (default true).
+    #[serde(default)]
+    pub header: Option<bool>,
+    /// Leading records to drop before the header/data.
+    #[serde(default)]
+    pub skip_records: usize,
+}
+
+/// The scan's `TextLayout`, or else the file format it names (`None` for
+/// sources read by extension).
+fn scan_layout(
+    opts: LayoutOptions,
+    fields: &[FieldDef],
+) -> Result<(Option<TextLayout>, Option<SourceFormat>), String> {
+    let has_spans = fields
+        .iter()
+        .any(|f| f.offset.is_some() || f.length.is_some());
+    let file_format = opts.format.as_deref().and_then(SourceFormat::parse);
+    if opts.format.is_none() || file_format.is_some() {
+        if opts.delimiter.is_some()
+            || opts.record_delimiter.is_some()
+            || opts.header.is_some()
+            || opts.skip_records > 0
+            || has_spans
+        {
+            return Err(
+                "text layout options need 'format: delimited' or 'format: fixed_width' on the scan"
+                    .into(),
+            );
+        }
+        return Ok((None, file_format));
     }
+    let format = opts.format.unwrap_or_default();
+    let record_delimiter = opts.record_delimiter.unwrap_or_else(|| "\n".into());
+    if record_delimiter.is_empty() {
+        return Err("scan 'record_delimiter' must not be empty".into());
+    }
+    let fields = match format.as_str() {
+        "delimited" => {
+            if has_spans {
+                return Err("'offset'/'length' apply to fixed_width scans only".into());
+            }
+            let delimiter = opts
+                .delimiter
+                .filter(|d| !d.is_empty())
+                .ok_or("delimited scan needs a non-empty 'delimiter'")?;
+            FieldLayout::Delimited {
+                delimiter,
+                header: opts.header.unwrap_or(true),
+            }
+        }
+        "fixed_width" => {
+            if opts.delimiter.is_some() || opts.header.is_some() {
+                return Err("'delimiter'/'header' apply to delimited scans only".into());
+            }
+            let mut next = 0;
+            let mut spans = Vec::with_capacity(fields.len());
+            for f in fields {
+                let length = f
+                    .length
+                    .ok_or_else(|| format!("fixed_width field '{}' needs a 'length'", f.name))?;
+                let offset = f.offset.unwrap_or(next);
+                next = offset + length;
+                spans.push(FixedWidthSpan { offset, length });
+            }
+            FieldLayout::FixedWidth { spans }
+        }
+        other => {
+            return Err(format!(
+                "unknown scan format '{}' (expected csv, jsonl, parquet, avro, delimited or fixed_width)",
+                other
+            ))
+        }
+    };
+    Ok((
+        Some(TextLayout {
+            record_delimiter,
+            skip_records: opts.skip_records,
+            fields,
+        }),
+        None,
+    ))
 }
 
-fn to_schema(fields: &[FieldDef]) -> Schema {
-    Schema::new(
+fn to_schema(fields: &[FieldDef]) -> Result<Schema, CoreError> {
+    Schema::try_new(
         fields
             .iter()
             .map(|f| Field {
@@ -107,8 +357,7 @@ fn to_schema(fields: &[FieldDef]) -> Schema {
     )
 }
 
-/// Parse YAML string into a `LogicalPlan`.
-/// This supports *linear* pipelines only; joins/branches not yet supported.
+/// Engine settings a pipeline file may override (`config:`).
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct PipelineConfig {
@@ -120,24 +369,114 @@ pub struct PipelineConfig {
     pub spill_aws_session_token: Option<String>,
     pub spill_gcs_service_account: Option<String>,
     pub spill_azure_access_key: Option<String>,
+    /// Per-operator fallback chains, e.g. `join_hash: [{partitions: 64}, skip]`.
+    pub fallbacks: BTreeMap<String, Vec<FallbackAction>>,
+    /// Adaptive source read sizing (rows per block and pressure watermarks).
+    pub source_batch: Option<SourceBatchConfig>,
+    /// Estimated output bounds for hash joins, checked before each join block.
+    pub join_guard: Option<JoinGuardConfig>,
+    /// Text encoding of binary values in CSV and JSONL files.
+    pub binary_encoding: Option<BinaryEncoding>,
+    /// Compression, encodings and statistics of Parquet sinks.
+    pub parquet: Option<ParquetSinkConfig>,
+    /// Delimiter, quoting and compression of CSV sinks.
+    pub csv: Option<CsvSinkConfig>,
+    /// Which ready block runs next (`fifo`, `critical_path`, `memory_aware`).
+    pub schedule_policy: Option<SchedulePolicy>,
+    /// What a division by zero yields (`error`, `null`, `default(x)`). Left
+    /// out when unset, since a written `null` reads back as the null policy.
+    #[serde(
+        deserialize_with = "arithmetic_error_policy",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub arithmetic_errors: Option<ArithErrorPolicy>,
+    /// Retries of failed source file reads (count and backoff).
+    pub read_retry: Option<ReadRetryConfig>,
+    /// Compression of spill segments (`none`, `lz4`, `zstd`, `zstd:level`).
+    pub spill_codec: Option<SpillCodec>,
+    /// Most spill bytes held in storage at once.
+    pub spill_quota_bytes: Option<u64>,
+    /// Leftover segments the end-of-run sweep deletes (`run`, `sweep`).
+    pub spill_cleanup: Option<SpillCleanup>,
+    /// Check the plan's worst-case spill volume against free space first.
+    pub spill_space_check: Option<bool>,
+    /// Sort aggregate output by group key and keep join output in probe order.
+    pub deterministic_output: Option<bool>,
+    /// Address (`host:port`) of the Prometheus `/metrics` endpoint.
+    pub metrics_addr: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ParsedPipeline {
     pub plan: LogicalPlan,
     pub config: PipelineConfig,
+    pub hints: PlanHints,
 }
 
+/// Parse a pipeline of either version into a `LogicalPlan`.
 pub fn parse_yaml_pipeline(yaml_src: &str) -> Result<ParsedPipeline, serde_yaml::Error> {
-    let doc: Pipeline = serde_yaml::from_str(yaml_src)?;
+    parse_yaml_pipeline_with_params(yaml_src, &TemplateParams::new())
+}
+
+/// Like [`parse_yaml_pipeline`], expanding `source_template` scans with `params`.
+///
+/// A template that expands to several files becomes a `Union` of per-file scans
+/// sharing the declared schema, so TE plans each file as its own blocks.
+pub fn parse_yaml_pipeline_with_params(
+    yaml_src: &str,
+    params: &TemplateParams,
+) -> Result<ParsedPipeline, serde_yaml::Error> {
+    use serde::de::Error as _;
+
+    let doc = match parse_document(yaml_src)? {
+        Document::V1(doc) => doc,
+        Document::V2(doc) => {
+            collect_lets(&doc.let_steps()).map_err(serde_yaml::Error::custom)?;
+            let mut hints = doc.hints.unwrap_or_default();
+            let plan = build_stages(doc.stages, params, &mut hints)?;
+            return Ok(ParsedPipeline {
+                plan,
+                config: doc.config.unwrap_or_default(),
+                hints,
+            });
+        }
+    };
+    let mut pending: BTreeSet<String> = collect_lets(&doc.steps)
+        .map_err(serde_yaml::Error::custom)?
+        .into_iter()
+        .map(|stage| stage.name)
+        .collect();
+    let plan = build_plan(doc.steps, params, &mut pending)?;
+    Ok(ParsedPipeline {
+        plan,
+        config: doc.config.unwrap_or_default(),
+        hints: doc.hints.unwrap_or_default(),
+    })
+}
+
+/// Chain `steps` into a plan. `pending` holds the names of `let` steps not
+/// reached yet, whose variables may not be used so far.
+pub(crate) fn build_plan(
+    steps: Vec<Step>,
+    params: &TemplateParams,
+    pending: &mut BTreeSet<String>,
+) -> Result<LogicalPlan, serde_yaml::Error> {
+    use serde::de::Error as _;
+
     let mut cur: Option<LogicalPlan> = None;
 
-    for step in doc.steps {
+    for step in steps {
+        if let Step::Let { name, .. } = &step {
+            if !params.contains_key(name) {
+                return Err(serde_yaml::Error::custom(format!(
+                    "let '{}' has no value; evaluate the pipeline's let steps first (emsqrt_exec::lets::resolve_lets) or pass --param {}=...",
+                    name, name
+                )));
+            }
+            pending.remove(name);
+            continue;
+        }
         cur = Some(match (step, cur) {
-            (Step::Scan { source, schema }, None) => L::Scan {
-                source,
-                schema: to_schema(&schema),
-            },
             (Step::Scan { .. }, Some(_)) => {
                 // serde_yaml::Error doesn't have a custom method, so we'll just parse error
                 return Err(
@@ -145,84 +484,259 @@ pub fn parse_yaml_pipeline(yaml_src: &str) -> Result<ParsedPipeline, serde_yaml:
                         .unwrap_err(),
                 );
             }
-            (Step::Filter { expr }, Some(input)) => L::Filter {
-                input: Box::new(input),
-                expr,
+            (s, None) if !matches!(s, Step::Scan { .. }) => {
+                // Any non-scan step without a prior plan is invalid in v1 pipelines.
+                // Return a parse error since serde_yaml::Error doesn't have a constructor
+                return Err(serde_yaml::from_str::<()>(&format!(
+                    "invalid: first step must be 'scan', got {:?}",
+                    s
+                ))
+                .unwrap_err());
+            }
+            (step, input) => build_step(step, input, params, pending)?,
+        });
+    }
+
+    cur.ok_or_else(|| serde_yaml::from_str::<()>("invalid: empty pipeline").unwrap_err())
+}
+
+/// The plan node of one step over `input` (`None` for a scan).
+pub(crate) fn build_step(
+    step: Step,
+    input: Option<LogicalPlan>,
+    params: &TemplateParams,
+    pending: &BTreeSet<String>,
+) -> Result<LogicalPlan, serde_yaml::Error> {
+    use serde::de::Error as _;
+
+    let vars = |expr: String, pending: &BTreeSet<String>| {
+        substitute_variables(&expr, params, pending).map_err(serde_yaml::Error::custom)
+    };
+    Ok(match (step, input) {
+        (
+            Step::Scan {
+                source,
+                source_template,
+                schema,
+                mut constraints,
+                sorted_by,
+                null_options,
+                on_parse_error,
+                dead_letter,
+                layout,
+                align,
+                flatten,
             },
-            (Step::Project { columns }, Some(input)) => L::Project {
-                input: Box::new(input),
+            None,
+        ) => {
+            let (layout, format) =
+                scan_layout(*layout, &schema).map_err(serde_yaml::Error::custom)?;
+            if flatten && (layout.is_some() || format.is_some_and(|f| f != SourceFormat::Jsonl)) {
+                return Err(serde_yaml::Error::custom(
+                    "scan 'flatten' applies to JSONL sources only",
+                ));
+            }
+            if !sorted_by.is_empty() {
+                if !constraints.sorted_by.is_empty() && constraints.sorted_by != sorted_by {
+                    return Err(serde_yaml::Error::custom(
+                        "scan 'sorted_by' disagrees with 'constraints.sorted_by'",
+                    ));
+                }
+                constraints.sorted_by = sorted_by;
+            }
+            if let Some(col) = constraints
+                .sorted_by
+                .iter()
+                .find(|c| !schema.iter().any(|f| &f.name == *c))
+            {
+                return Err(serde_yaml::Error::custom(format!(
+                    "sorted_by column '{}' is not in the scan schema",
+                    col
+                )));
+            }
+            let parse_errors = ParseErrorOptions {
+                on_parse_error,
+                columns: schema
+                    .iter()
+                    .filter_map(|f| f.on_parse_error.map(|p| (f.name.clone(), p)))
+                    .collect(),
+                dead_letter,
+            };
+            if parse_errors.uses_dead_letter() && parse_errors.dead_letter.is_none() {
+                return Err(serde_yaml::Error::custom(
+                    "on_parse_error: dead_letter needs a 'dead_letter' path on the scan",
+                ));
+            }
+            let schema = to_schema(&schema)
+                .map_err(|e| serde_yaml::Error::custom(format!("scan: {}", e)))?
+                .with_constraints(constraints)
+                .with_null_options(null_options)
+                .with_parse_errors(parse_errors)
+                .with_layout(layout)
+                .with_format(format)
+                .with_flatten(flatten)
+                .with_align(align);
+            let sources = match (source, source_template) {
+                (Some(source), None) => vec![source],
+                (None, Some(template)) => {
+                    expand_source_template(&template, params).map_err(serde_yaml::Error::custom)?
+                }
+                (Some(_), Some(_)) => {
+                    return Err(serde_yaml::Error::custom(
+                        "scan takes either 'source' or 'source_template', not both",
+                    ))
+                }
+                (None, None) => {
+                    return Err(serde_yaml::Error::custom(
+                        "scan needs a 'source' or 'source_template'",
+                    ))
+                }
+            };
+            let mut scans: Vec<LogicalPlan> = sources
+                .into_iter()
+                .map(|source| L::Scan {
+                    source,
+                    schema: schema.clone(),
+                })
+                .collect();
+            if scans.len() == 1 {
+                scans.pop().unwrap()
+            } else {
+                L::Union { inputs: scans }
+            }
+        }
+        (Step::Filter { expr }, Some(input)) => L::Filter {
+            input: Box::new(input),
+            expr: vars(expr, pending)?,
+        },
+        (
+            Step::FilterIn {
                 columns,
+                keys,
+                delimiter,
             },
-            (Step::Map { expr }, Some(input)) => L::Map {
-                input: Box::new(input),
-                expr,
-            },
-            (
-                Step::Sink {
-                    destination,
-                    format,
-                },
-                Some(input),
-            ) => L::Sink {
-                input: Box::new(input),
+            Some(input),
+        ) => L::FilterIn {
+            input: Box::new(input),
+            columns,
+            keys,
+            delimiter,
+        },
+        (Step::Project { columns }, Some(input)) => L::Project {
+            input: Box::new(input),
+            columns,
+        },
+        (Step::Map { expr }, Some(input)) => L::Map {
+            input: Box::new(input),
+            expr: vars(expr, pending)?,
+        },
+        (
+            Step::Sink {
                 destination,
                 format,
             },
-            (
-                Step::Window {
-                    partitions,
-                    order_by,
-                    functions,
-                },
-                Some(input),
-            ) => L::Window {
-                input: Box::new(input),
+            Some(input),
+        ) => L::Sink {
+            input: Box::new(input),
+            destination: expand_destination(&destination, params)
+                .map_err(serde_yaml::Error::custom)?,
+            format,
+        },
+        (
+            Step::Window {
                 partitions,
                 order_by,
-                functions: functions
-                    .into_iter()
-                    .map(|def| WindowExpr {
-                        alias: def.alias,
-                        function: match def.kind.as_str() {
-                            "row_number" => WindowFunction::RowNumber,
-                            "sum" => WindowFunction::Sum {
-                                column: def.column.unwrap_or_else(|| "value".into()),
-                            },
-                            _ => WindowFunction::RowNumber,
-                        },
-                        frame: WindowFrame::default(),
-                    })
-                    .collect(),
+                functions,
             },
-            (
-                Step::Lateral {
-                    column,
-                    alias,
-                    delimiter,
-                },
-                Some(input),
-            ) => L::Lateral {
-                input: Box::new(input),
+            Some(input),
+        ) => L::Window {
+            input: Box::new(input),
+            partitions,
+            order_by,
+            functions: functions
+                .into_iter()
+                .map(|def| WindowExpr {
+                    alias: def.alias,
+                    function: match def.kind.as_str() {
+                        "row_number" => WindowFunction::RowNumber,
+                        "sum" => WindowFunction::Sum {
+                            column: def.column.unwrap_or_else(|| "value".into()),
+                        },
+                        _ => WindowFunction::RowNumber,
+                    },
+                    frame: WindowFrame::default(),
+                })
+                .collect(),
+        },
+        (
+            Step::Lateral {
                 column,
                 alias,
                 delimiter,
             },
-            (s, None) => {
-                // Any non-scan step without a prior plan is invalid in linear pipelines.
-                // Return a parse error since serde_yaml::Error doesn't have a constructor
-                return Err(serde_yaml::from_str::<()>(&format!(
-                    "invalid: first step must be 'scan', got {:?}",
-                    s
-                ))
-                .unwrap_err());
-            }
-        });
-    }
-
-    let plan =
-        cur.ok_or_else(|| serde_yaml::from_str::<()>("invalid: empty pipeline").unwrap_err())?;
-    Ok(ParsedPipeline {
-        plan,
-        config: doc.config.unwrap_or_default(),
+            Some(input),
+        ) => L::Lateral {
+            input: Box::new(input),
+            column,
+            alias,
+            delimiter,
+        },
+        (
+            Step::TopK {
+                column,
+                k,
+                capacity,
+            },
+            Some(input),
+        ) => L::TopK {
+            input: Box::new(input),
+            column,
+            k,
+            capacity,
+        },
+        (
+            Step::Limit {
+                limit,
+                offset,
+                order_by,
+            },
+            Some(input),
+        ) => L::Limit {
+            input: Box::new(input),
+            limit,
+            offset,
+            order_by: order_by
+                .iter()
+                .map(|key| SortKey::parse(key))
+                .collect::<Result<_, _>>()
+                .map_err(serde_yaml::Error::custom)?,
+        },
+        (Step::Let { .. }, _) => unreachable!("let steps are not operators"),
+        (Step::Scan { .. }, Some(_)) => {
+            return Err(serde_yaml::Error::custom("a scan reads no input"))
+        }
+        (s, None) => {
+            return Err(serde_yaml::Error::custom(format!(
+                "'{}' needs an input",
+                step_op(&s)
+            )))
+        }
     })
 }
+
+/// The `op` name of a step.
+pub(crate) fn step_op(step: &Step) -> &'static str {
+    match step {
+        Step::Scan { .. } => "scan",
+        Step::Filter { .. } => "filter",
+        Step::FilterIn { .. } => "filter_in",
+        Step::Project { .. } => "project",
+        Step::Map { .. } => "map",
+        Step::Sink { .. } => "sink",
+        Step::Window { .. } => "window",
+        Step::Lateral { .. } => "lateral",
+        Step::TopK { .. } => "top_k",
+        Step::Limit { .. } => "limit",
+        Step::Let { .. } => "let",
+    }
+}
@@ -0,0 +1,601 @@
+//! EXPLAIN output: the operator tree and TE block DAG of a planned pipeline.
+//!
+//! `ExplainGraph` collects what `emsqrt explain` shows into one structure,
+//! rendered as JSON (`to_json`), Graphviz DOT (`to_dot`) or a self-contained
+//! HTML page with inline SVG (`to_html`). No file or clock access, so it also
+//! runs in browser (wasm32) builds.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use emsqrt_core::dag::{LogicalPlan, PhysicalPlan};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_te::{TePlan, WorkEstimate};
+use serde::{Deserialize, Serialize};
+
+use crate::lineage::{column_lineage, SinkLineage};
+use crate::physical::PhysicalProgram;
+
+/// Config fields worth showing next to an operator, most telling first.
+const DETAIL_FIELDS: &[&str] = &[
+    "source",
+    "destination",
+    "expr",
+    "on",
+    "condition",
+    "group_by",
+    "columns",
+    "keys",
+];
+
+/// Detail strings longer than this are cut in DOT/HTML labels.
+const LABEL_CHARS: usize = 32;
+
+/// Plan summary, operators and blocks of one EXPLAIN.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainGraph {
+    pub mem_cap_bytes: usize,
+    pub work: WorkEstimate,
+    pub rows_per_block: u64,
+    pub max_frontier: Option<usize>,
+    /// Operators, inputs before the operators that read them.
+    pub operators: Vec<ExplainOperator>,
+    /// TE blocks in execution order.
+    pub blocks: Vec<ExplainBlock>,
+    /// Source columns feeding each sink column (see `with_lineage`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Vec<SinkLineage>>,
+    /// The plan `program` was lowered from (see `with_logical_plan`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logical_plan: Option<LogicalPlan>,
+    /// The physical plan and its operator bindings, for tools that need more
+    /// than `operators` shows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<PhysicalProgram>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainOperator {
+    pub op_id: u64,
+    /// Operator key the runtime instantiates (e.g. "filter", "join_hash").
+    pub key: String,
+    /// Coarse kind used for colouring: source, sink, filter, transform, join,
+    /// aggregate, union or other.
+    pub category: String,
+    /// The most telling config value (source URI, predicate, join keys, ...).
+    pub detail: Option<String>,
+    pub inputs: Vec<u64>,
+    pub columns: Vec<String>,
+    /// Resolved output schema: `columns` with their types and nullability.
+    /// Empty for sinks.
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    pub blocks: usize,
+    /// Rows the operator's blocks cover, when their ranges are known.
+    pub est_rows: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplainBlock {
+    pub block_id: u64,
+    pub op_id: u64,
+    pub deps: Vec<u64>,
+    pub range_rows: Option<(u64, u64)>,
+    pub est_rows: Option<u64>,
+    /// `est_rows` times the estimated width of the block's schema.
+    pub est_bytes: Option<u64>,
+    /// `(index, count)` for hash-partitioned blocks.
+    pub partition: Option<(usize, usize)>,
+    /// Parts an exchange block splits its input into (a join's hash split).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<usize>,
+}
+
+impl ExplainGraph {
+    pub fn new(
+        program: &PhysicalProgram,
+        work: &WorkEstimate,
+        te: &TePlan,
+        mem_cap_bytes: usize,
+    ) -> Self {
+        let blocks: Vec<ExplainBlock> = te
+            .order
+            .iter()
+            .map(|b| {
+                let est_rows = b.range_rows.map(|(start, end)| end.saturating_sub(start));
+                ExplainBlock {
+                    block_id: b.id.get(),
+                    op_id: b.op.get(),
+                    deps: b.deps.iter().map(|d| d.get()).collect(),
+                    range_rows: b.range_rows,
+                    est_rows,
+                    est_bytes: est_rows.map(|rows| rows * row_width(&b.schema)),
+                    partition: b.partition.as_ref().map(|p| (p.index, p.count)),
+                    exchange: b.exchange.as_ref().map(|x| x.count),
+                }
+            })
+            .collect();
+
+        let mut operators = Vec::new();
+        collect_operators(&program.plan, program, &mut operators);
+        for op in &mut operators {
+            let mine: Vec<&ExplainBlock> = blocks
+                .iter()
+                .filter(|b| b.op_id == op.op_id && b.exchange.is_none())
+                .collect();
+            op.blocks = mine.len();
+            op.est_rows = mine.iter().map(|b| b.est_rows).sum();
+        }
+
+        Self {
+            mem_cap_bytes,
+            work: *work,
+            rows_per_block: te.block_size.rows_per_block,
+            max_frontier: te.max_frontier_hint,
+            operators,
+            blocks,
+            lineage: None,
+            logical_plan: None,
+            program: Some(program.clone()),
+        }
+    }
+
+    /// Include `plan`, the logical plan `program` was lowered from.
+    pub fn with_logical_plan(mut self, plan: &LogicalPlan) -> Self {
+        self.logical_plan = Some(plan.clone());
+        self
+    }
+
+    /// Include the column lineage of `plan`, the logical plan `program` was
+    /// lowered from.
+    pub fn with_lineage(mut self, plan: &LogicalPlan) -> Self {
+        self.lineage = Some(column_lineage(plan));
+        self
+    }
+
+    /// Output schema of each operator but the sinks, one `Op N (key):`
+    /// header then one `name Type [NOT NULL]` line per column.
+    pub fn render_schemas(&self) -> String {
+        let mut out = String::new();
+        for op in self.operators.iter().filter(|op| op.category != "sink") {
+            let _ = writeln!(out, "Op {} ({}):", op.op_id, op.key);
+            for f in &op.fields {
+                let _ = writeln!(out, "  {}", describe_field(f));
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("explain graph serializes")
+    }
+
+    /// Graphviz digraph with one cluster for operators and one for blocks.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from(
+            "digraph explain {\n  rankdir=TB;\n  node [style=filled, fontname=\"Helvetica\"];\n",
+        );
+        out.push_str("  subgraph cluster_operators {\n    label=\"Operators\";\n");
+        for op in &self.operators {
+            let mut label = format!("#{} {}", op.op_id, dot_escape(&op.key));
+            if let Some(detail) = &op.detail {
+                label.push_str("\\n");
+                label.push_str(&dot_escape(&truncate(detail)));
+            }
+            let _ = writeln!(
+                out,
+                "    op{} [shape=box, label=\"{}\", fillcolor=\"{}\"];",
+                op.op_id,
+                label,
+                color(&op.category)
+            );
+            for input in &op.inputs {
+                let _ = writeln!(out, "    op{} -> op{};", input, op.op_id);
+            }
+        }
+        out.push_str("  }\n  subgraph cluster_blocks {\n    label=\"TE blocks\";\n");
+        let categories = self.categories();
+        for b in &self.blocks {
+            let rows = b
+                .est_rows
+                .map(|r| format!("\\n{} rows", r))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "    b{} [shape=ellipse, label=\"B{} (op {}){}\", fillcolor=\"{}\"];",
+                b.block_id,
+                b.block_id,
+                b.op_id,
+                rows,
+                color(categories.get(&b.op_id).copied().unwrap_or("other"))
+            );
+            for dep in &b.deps {
+                let _ = writeln!(out, "    b{} -> b{};", dep, b.block_id);
+            }
+        }
+        out.push_str("  }\n}\n");
+        out
+    }
+
+    /// Standalone HTML page: summary, colour legend, operator and block
+    /// graphs as inline SVG (hover a node for details), and a block table.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>EM-√ plan</title>\n<style>\n\
+             body { font-family: Helvetica, Arial, sans-serif; margin: 24px; color: #222; }\n\
+             table { border-collapse: collapse; margin-bottom: 16px; }\n\
+             td, th { border: 1px solid #ccc; padding: 4px 8px; text-align: left; font-size: 13px; }\n\
+             .legend span { display: inline-block; padding: 2px 8px; margin-right: 6px; border-radius: 4px; }\n\
+             svg text { font-size: 12px; pointer-events: none; }\n\
+             svg line { stroke: #888; stroke-width: 1.2; }\n\
+             </style>\n</head>\n<body>\n<h1>Pipeline Execution Plan</h1>\n",
+        );
+
+        out.push_str("<table>\n");
+        let frontier = self
+            .max_frontier
+            .map(|f| f.to_string())
+            .unwrap_or_else(|| "-".into());
+        for (name, value) in [
+            ("Memory cap", format!("{} bytes", self.mem_cap_bytes)),
+            ("Estimated rows", self.work.total_rows.to_string()),
+            ("Estimated bytes", self.work.total_bytes.to_string()),
+            ("Max fan-in", self.work.max_fan_in.to_string()),
+            ("Rows per block", self.rows_per_block.to_string()),
+            ("Blocks", self.blocks.len().to_string()),
+            ("Max frontier", frontier),
+        ] {
+            let _ = writeln!(out, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+        }
+        out.push_str("</table>\n<div class=\"legend\">\n");
+        for category in CATEGORIES {
+            let _ = writeln!(
+                out,
+                "<span style=\"background:{}\">{}</span>",
+                color(category),
+                category
+            );
+        }
+        out.push_str("</div>\n");
+
+        // Operators: a layer per distance from the sources.
+        let op_depth = layers(
+            self.operators
+                .iter()
+                .map(|op| (op.op_id, op.inputs.clone())),
+        );
+        let op_nodes: Vec<SvgNode> = self
+            .operators
+            .iter()
+            .map(|op| {
+                let mut tooltip = format!("op {} ({})", op.op_id, op.key);
+                if let Some(detail) = &op.detail {
+                    let _ = write!(tooltip, "\n{}", detail);
+                }
+                let fields: Vec<String> = op.fields.iter().map(describe_field).collect();
+                let _ = write!(tooltip, "\ncolumns: {}", fields.join(", "));
+                let _ = write!(tooltip, "\nblocks: {}", op.blocks);
+                if let Some(rows) = op.est_rows {
+                    let _ = write!(tooltip, "\nestimated rows: {}", rows);
+                }
+                SvgNode {
+                    id: op.op_id,
+                    depth: op_depth[&op.op_id],
+                    label: format!("#{} {}", op.op_id, op.key),
+                    sublabel: op.detail.as_deref().map(truncate),
+                    tooltip,
+                    fill: color(&op.category),
+                    edges_from: op.inputs.clone(),
+                }
+            })
+            .collect();
+        out.push_str("<h2>Operators</h2>\n");
+        out.push_str(&svg(&op_nodes, 200, 44));
+
+        let block_depth = layers(self.blocks.iter().map(|b| (b.block_id, b.deps.clone())));
+        let categories = self.categories();
+        let block_nodes: Vec<SvgNode> = self
+            .blocks
+            .iter()
+            .map(|b| SvgNode {
+                id: b.block_id,
+                depth: block_depth[&b.block_id],
+                label: format!("B{}", b.block_id),
+                sublabel: b.est_rows.map(|r| format!("{} rows", r)),
+                tooltip: block_summary(b),
+                fill: color(categories.get(&b.op_id).copied().unwrap_or("other")),
+                edges_from: b.deps.clone(),
+            })
+            .collect();
+        out.push_str("<h2>TE blocks</h2>\n");
+        out.push_str(&svg(&block_nodes, 96, 40));
+
+        out.push_str(
+            "<table>\n<tr><th>Block</th><th>Op</th><th>Deps</th><th>Rows</th>\
+             <th>Est. rows</th><th>Est. bytes</th><th>Partition</th></tr>\n",
+        );
+        let or_dash = |v: Option<String>| v.unwrap_or_else(|| "-".into());
+        for b in &self.blocks {
+            let deps: Vec<String> = b.deps.iter().map(|d| d.to_string()).collect();
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                b.block_id,
+                b.op_id,
+                deps.join(", "),
+                or_dash(b.range_rows.map(|(s, e)| format!("{}..{}", s, e))),
+                or_dash(b.est_rows.map(|r| r.to_string())),
+                or_dash(b.est_bytes.map(|r| r.to_string())),
+                or_dash(b.partition.map(|(i, n)| format!("{}/{}", i, n))),
+            );
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+
+    fn categories(&self) -> BTreeMap<u64, &str> {
+        self.operators
+            .iter()
+            .map(|op| (op.op_id, op.category.as_str()))
+            .collect()
+    }
+}
+
+const CATEGORIES: [&str; 8] = [
+    "source",
+    "filter",
+    "transform",
+    "join",
+    "aggregate",
+    "union",
+    "sink",
+    "other",
+];
+
+fn category(key: &str) -> &'static str {
+    match key {
+        "source" => "source",
+        "sink" => "sink",
+        "filter" | "filter_in" => "filter",
+        "map" | "project" | "lateral_explode" => "transform",
+        "aggregate" | "window" | "sort" => "aggregate",
+        "union" => "union",
+        k if k.starts_with("join") => "join",
+        _ => "other",
+    }
+}
+
+fn color(category: &str) -> &'static str {
+    match category {
+        "source" => "#a6cee3",
+        "filter" => "#fdbf6f",
+        "transform" => "#cab2d6",
+        "join" => "#fb9a99",
+        "aggregate" => "#b2df8a",
+        "union" => "#ffff99",
+        "sink" => "#1f78b4",
+        _ => "#dddddd",
+    }
+}
+
+/// Post-order walk: each operator after its inputs.
+fn collect_operators(
+    plan: &PhysicalPlan,
+    program: &PhysicalProgram,
+    out: &mut Vec<ExplainOperator>,
+) {
+    use PhysicalPlan::*;
+    let (op, inputs, schema): (_, Vec<&PhysicalPlan>, Option<&Schema>) = match plan {
+        Source { op, schema } => (op, vec![], Some(schema)),
+        Unary { op, input, schema } => (op, vec![input.as_ref()], Some(schema)),
+        Binary {
+            op,
+            left,
+            right,
+            schema,
+        } => (op, vec![left.as_ref(), right.as_ref()], Some(schema)),
+        Sink { op, input } => (op, vec![input.as_ref()], None),
+        Nary { op, inputs, schema } => (op, inputs.iter().collect(), Some(schema)),
+    };
+    // A shared subplan is listed once, under its first reader.
+    if out.iter().any(|o| o.op_id == op.get()) {
+        return;
+    }
+    for input in &inputs {
+        collect_operators(input, program, out);
+    }
+    let binding = program.bindings.get(op);
+    let key = binding.map(|b| b.key.clone()).unwrap_or_default();
+    let detail = binding.and_then(|b| {
+        DETAIL_FIELDS.iter().find_map(|f| {
+            b.config
+                .get(*f)
+                .filter(|v| !v.is_null())
+                .map(|v| match v.as_str() {
+                    Some(s) => s.to_string(),
+                    None => v.to_string(),
+                })
+        })
+    });
+    out.push(ExplainOperator {
+        op_id: op.get(),
+        category: category(&key).to_string(),
+        key,
+        detail,
+        inputs: inputs.iter().map(|p| p.op().get()).collect(),
+        columns: schema
+            .map(|s| s.fields.iter().map(|f| f.name.clone()).collect())
+            .unwrap_or_default(),
+        fields: schema.map(|s| s.fields.clone()).unwrap_or_default(),
+        blocks: 0,
+        est_rows: None,
+    });
+}
+
+/// Rough bytes per row of `schema`: fixed widths, 32 bytes for strings/binary.
+/// `id Int64 NOT NULL`, `name Utf8`.
+fn describe_field(field: &Field) -> String {
+    let not_null = if field.nullable { "" } else { " NOT NULL" };
+    format!("{} {:?}{}", field.name, field.data_type, not_null)
+}
+
+fn row_width(schema: &Schema) -> u64 {
+    schema
+        .fields
+        .iter()
+        .map(|f| match f.data_type {
+            DataType::Boolean => 1,
+            DataType::Int32 | DataType::Float32 => 4,
+            DataType::Int64 | DataType::Float64 | DataType::Date64 | DataType::Timestamp => 8,
+            DataType::Decimal128 => 16,
+            DataType::Utf8 | DataType::Binary => 32,
+        })
+        .sum::<u64>()
+        .max(1)
+}
+
+fn block_summary(b: &ExplainBlock) -> String {
+    let mut s = format!("block {} of op {}", b.block_id, b.op_id);
+    if let Some((start, end)) = b.range_rows {
+        let _ = write!(s, "\nrows {}..{}", start, end);
+    }
+    if let Some(bytes) = b.est_bytes {
+        let _ = write!(s, "\n~{} bytes", bytes);
+    }
+    if let Some((i, n)) = b.partition {
+        let _ = write!(s, "\npartition {}/{}", i, n);
+    }
+    if let Some(n) = b.exchange {
+        let _ = write!(s, "\nexchange into {} parts", n);
+    }
+    if !b.deps.is_empty() {
+        let deps: Vec<String> = b.deps.iter().map(|d| d.to_string()).collect();
+        let _ = write!(s, "\ndeps: {}", deps.join(", "));
+    }
+    s
+}
+
+/// Longest distance from a node without inputs; nodes come after their inputs.
+fn layers(nodes: impl Iterator<Item = (u64, Vec<u64>)>) -> BTreeMap<u64, usize> {
+    let mut depth = BTreeMap::new();
+    for (id, inputs) in nodes {
+        let d = inputs
+            .iter()
+            .filter_map(|i| depth.get(i))
+            .map(|d| d + 1)
+            .max()
+            .unwrap_or(0);
+        depth.insert(id, d);
+    }
+    depth
+}
+
+struct SvgNode {
+    id: u64,
+    depth: usize,
+    label: String,
+    sublabel: Option<String>,
+    tooltip: String,
+    fill: &'static str,
+    edges_from: Vec<u64>,
+}
+
+/// Lay `nodes` out in rows by depth (inputs on top) and draw them.
+fn svg(nodes: &[SvgNode], width: u32, height: u32) -> String {
+    const GAP_X: u32 = 16;
+    const GAP_Y: u32 = 36;
+    const MARGIN: u32 = 8;
+
+    let mut per_row: BTreeMap<usize, u32> = BTreeMap::new();
+    let mut pos: BTreeMap<u64, (u32, u32)> = BTreeMap::new();
+    for n in nodes {
+        let col = per_row.entry(n.depth).or_insert(0);
+        let x = MARGIN + *col * (width + GAP_X);
+        let y = MARGIN + n.depth as u32 * (height + GAP_Y);
+        pos.insert(n.id, (x, y));
+        *col += 1;
+    }
+    let cols = per_row.values().copied().max().unwrap_or(0);
+    let rows = per_row.len() as u32;
+    let total_w = 2 * MARGIN + cols * (width + GAP_X);
+    let total_h = 2 * MARGIN + rows * (height + GAP_Y);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        total_w, total_h
+    );
+    for n in nodes {
+        let (x, y) = pos[&n.id];
+        for from in &n.edges_from {
+            if let Some(&(fx, fy)) = pos.get(from) {
+                let _ = writeln!(
+                    out,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+                    fx + width / 2,
+                    fy + height,
+                    x + width / 2,
+                    y
+                );
+            }
+        }
+    }
+    for n in nodes {
+        let (x, y) = pos[&n.id];
+        let _ = writeln!(
+            out,
+            "<g><title>{}</title><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"#555\"/>",
+            html_escape(&n.tooltip),
+            x,
+            y,
+            width,
+            height,
+            n.fill
+        );
+        let line_y = if n.sublabel.is_some() {
+            y + 17
+        } else {
+            y + height / 2 + 4
+        };
+        let _ = writeln!(
+            out,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+            x + width / 2,
+            line_y,
+            html_escape(&n.label)
+        );
+        if let Some(sub) = &n.sublabel {
+            let _ = writeln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"#444\">{}</text>",
+                x + width / 2,
+                y + 33,
+                html_escape(sub)
+            );
+        }
+        out.push_str("</g>\n");
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn truncate(s: &str) -> String {
+    if s.chars().count() <= LABEL_CHARS {
+        return s.to_string();
+    }
+    let mut cut: String = s.chars().take(LABEL_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
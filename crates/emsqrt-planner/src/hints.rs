@@ -0,0 +1,190 @@
+//! Plan-level hints: user corrections to estimates and planner choices.
+//!
+//! Parsed from the optional `hints:` block of a pipeline YAML:
+//! ```yaml
+//! hints:
+//!   sources:
+//!     "data/events.csv": { rows: 50000000, bytes: 4000000000 }
+//!   join_strategy: merge
+//!   rows_per_block: 250000
+//!   parallelism: { filter: 4 }
+//! ```
+//!
+//! Source hints feed [`WorkHint`]; the block size replaces the cost-model
+//! choice in TE planning; join strategy and parallelism are applied to the
+//! lowered operator bindings, as are the `resources:` of v2 pipeline stages
+//! (see [`StageHint`]).
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use emsqrt_te::tree_eval::PlanError;
+use emsqrt_te::{choose_block_size, plan_te_with_block_size, BlockSizeHint, TePlan, WorkEstimate};
+
+use crate::cost::WorkHint;
+use crate::physical::{OperatorBinding, PhysicalProgram};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlanHints {
+    /// Known sizes per source URI (as written in the scan step).
+    pub sources: BTreeMap<String, SourceHint>,
+    /// Join operator to use instead of the default hash join.
+    pub join_strategy: Option<JoinStrategy>,
+    /// Rows per TE block, replacing the cost-model choice.
+    pub rows_per_block: Option<u64>,
+    /// Parallelism per stage, keyed by operator key (e.g. `filter: 4`).
+    pub parallelism: BTreeMap<String, usize>,
+    /// Resources of v2 pipeline stages, taken from the stages themselves.
+    #[serde(skip)]
+    pub stages: Vec<StageHint>,
+}
+
+/// Per-stage resources of a v2 pipeline (`resources:` on a stage).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StageResources {
+    /// Cap on the bytes the stage's operator reserves, within the engine's
+    /// memory cap; spilling operators spill sooner under it.
+    pub memory_bytes: Option<u64>,
+    /// Parallelism of the stage's operator (as `hints.parallelism`).
+    pub parallelism: Option<usize>,
+}
+
+impl StageResources {
+    pub fn is_empty(&self) -> bool {
+        self.memory_bytes.is_none() && self.parallelism.is_none()
+    }
+}
+
+/// A stage's resources and the operator binding it lowers to: the key and
+/// the config the stage sets itself, without what lowering adds from the
+/// stage's surroundings. A stage the optimizer folds into another operator
+/// (a filter run inside a join, a narrowed projection) matches nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageHint {
+    pub stage: String,
+    pub key: String,
+    pub config: serde_json::Map<String, serde_json::Value>,
+    pub resources: StageResources,
+}
+
+/// Binding config that lowering derives from a node's inputs rather than
+/// the node itself.
+const CONTEXT_FIELDS: [&str; 6] = [
+    "projection",
+    "predicates",
+    "unique_groups",
+    "presorted",
+    "left_filter",
+    "right_filter",
+];
+
+impl StageHint {
+    /// The hint for `stage`, whose node lowers to `binding`.
+    pub fn new(stage: &str, binding: &OperatorBinding, resources: StageResources) -> Self {
+        let mut config = binding.config.as_object().cloned().unwrap_or_default();
+        config.retain(|k, _| !CONTEXT_FIELDS.contains(&k.as_str()));
+        Self {
+            stage: stage.to_string(),
+            key: binding.key.clone(),
+            config,
+            resources,
+        }
+    }
+
+    fn matches(&self, binding: &OperatorBinding) -> bool {
+        let is_join = |key: &str| key == "join_hash" || key == "join_merge";
+        (self.key == binding.key || is_join(&self.key) && is_join(&binding.key))
+            && self
+                .config
+                .iter()
+                .all(|(k, v)| binding.config.get(k) == Some(v))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SourceHint {
+    pub rows: Option<u64>,
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinStrategy {
+    Hash,
+    Merge,
+}
+
+impl JoinStrategy {
+    fn operator_key(self) -> &'static str {
+        match self {
+            JoinStrategy::Hash => "join_hash",
+            JoinStrategy::Merge => "join_merge",
+        }
+    }
+}
+
+impl PlanHints {
+    /// Source sizes as a `WorkHint` for `estimate_work`, if any were given.
+    pub fn work_hint(&self) -> Option<WorkHint> {
+        if self.sources.is_empty() {
+            return None;
+        }
+        let mut hint = WorkHint::default();
+        for (source, h) in &self.sources {
+            if let Some(rows) = h.rows {
+                hint.source_rows.push((source.clone(), rows));
+            }
+            if let Some(bytes) = h.bytes {
+                hint.source_bytes.push((source.clone(), bytes));
+            }
+        }
+        Some(hint)
+    }
+
+    /// Rewrite operator bindings for the join strategy and per-stage parallelism.
+    pub fn apply(&self, program: &mut PhysicalProgram) {
+        for binding in program.bindings.values_mut() {
+            if let Some(strategy) = self.join_strategy {
+                if binding.key == "join_hash" || binding.key == "join_merge" {
+                    binding.key = strategy.operator_key().to_string();
+                }
+            }
+            if let Some(&parallelism) = self.parallelism.get(&binding.key) {
+                if let Some(obj) = binding.config.as_object_mut() {
+                    obj.insert("parallelism".to_string(), parallelism.into());
+                }
+            }
+            let matched: Vec<&StageHint> =
+                self.stages.iter().filter(|s| s.matches(binding)).collect();
+            let Some(obj) = binding.config.as_object_mut() else {
+                continue;
+            };
+            for stage in matched {
+                if let Some(bytes) = stage.resources.memory_bytes {
+                    obj.insert("memory_bytes".to_string(), bytes.into());
+                }
+                if let Some(parallelism) = stage.resources.parallelism {
+                    obj.insert("parallelism".to_string(), parallelism.into());
+                }
+            }
+        }
+    }
+
+    /// TE-plan `program`, honoring `rows_per_block` when set.
+    pub fn plan_te(
+        &self,
+        program: &PhysicalProgram,
+        work: &WorkEstimate,
+        mem_cap_bytes: usize,
+    ) -> Result<TePlan, PlanError> {
+        let b = match self.rows_per_block {
+            Some(rows_per_block) => BlockSizeHint { rows_per_block },
+            None => choose_block_size(mem_cap_bytes, work),
+        };
+        plan_te_with_block_size(&program.plan, work, b, &program.join_keys())
+    }
+}
@@ -13,15 +13,35 @@
 //!
 //! NOTE: We deliberately avoid pulling heavy dependencies (no Arrow/IO here).
 
+pub mod compiled;
 pub mod cost;
 pub mod dsl;
+pub mod explain;
+pub mod hints;
+pub mod lineage;
 pub mod logical;
 pub mod lower;
 pub mod physical;
 pub mod rules;
 
-pub use cost::{estimate_work, WorkHint};
-pub use dsl::yaml::{parse_yaml_pipeline, ParsedPipeline, PipelineConfig};
+pub use compiled::{CompiledPlan, CompiledPlanError, COMPILED_PLAN_FORMAT_VERSION};
+pub use cost::{attach_source_stats, estimate_work, scan_sources, SourceFile, WorkHint};
+pub use dsl::lets::{let_literal, let_stages, LetReduce, LetStage};
+pub use dsl::stages::{
+    load_pipeline, migrate_v1, parse_document, Document, PipelineV2, Stage, StageOp,
+    PIPELINE_VERSION,
+};
+pub use dsl::template::{
+    expand_destination, expand_source_template, parse_template_params, substitute_variables,
+    TemplateParams,
+};
+pub use dsl::yaml::{
+    parse_schema_spec, parse_yaml_pipeline, parse_yaml_pipeline_with_params, ParsedPipeline,
+    PipelineConfig,
+};
+pub use explain::{ExplainBlock, ExplainGraph, ExplainOperator};
+pub use hints::{JoinStrategy, PlanHints, SourceHint, StageHint, StageResources};
+pub use lineage::{column_lineage, render_lineage, ColumnLineage, SinkLineage, SourceColumn};
 pub use logical::{Aggregation, JoinType, LogicalPlan};
 pub use lower::lower_to_physical;
 pub use physical::{OperatorBinding, PhysicalProgram};
@@ -0,0 +1,304 @@
+//! Column-level lineage: which source columns feed each sink column.
+//!
+//! Lineage follows values, not row selection: a filter's predicate columns
+//! do not feed the filter's output. Columns copied or renamed along the way
+//! are direct; aggregates, window functions, exploded lists and `map`
+//! expressions are derived.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use emsqrt_core::dag::{Aggregation, LogicalPlan, WindowFunction, RIGHT_SUFFIX};
+use emsqrt_core::expr::Expr;
+use emsqrt_core::schema::dedup_names;
+use serde::{Deserialize, Serialize};
+
+/// A column of a scanned source.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SourceColumn {
+    pub source: String,
+    pub column: String,
+}
+
+/// Where one output column comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnLineage {
+    pub column: String,
+    pub sources: Vec<SourceColumn>,
+    /// Computed from its sources rather than copied from one.
+    pub derived: bool,
+}
+
+/// The lineage of every column a sink writes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkLineage {
+    pub destination: String,
+    pub columns: Vec<ColumnLineage>,
+}
+
+/// Lineage of each sink in `plan`, in plan order.
+pub fn column_lineage(plan: &LogicalPlan) -> Vec<SinkLineage> {
+    let mut sinks = Vec::new();
+    collect_sinks(plan, &mut sinks);
+    sinks
+}
+
+/// Lineage as text: one line per sink, then one per column.
+pub fn render_lineage(sinks: &[SinkLineage]) -> String {
+    let mut out = String::new();
+    for sink in sinks {
+        let _ = writeln!(out, "{}", sink.destination);
+        for col in &sink.columns {
+            let sources: Vec<String> = col
+                .sources
+                .iter()
+                .map(|s| format!("{}:{}", s.source, s.column))
+                .collect();
+            let sources = if sources.is_empty() {
+                "(no source column)".to_string()
+            } else {
+                sources.join(", ")
+            };
+            let _ = writeln!(
+                out,
+                "  {} <- {}{}",
+                col.column,
+                sources,
+                if col.derived { " (derived)" } else { "" }
+            );
+        }
+    }
+    out
+}
+
+fn collect_sinks(plan: &LogicalPlan, out: &mut Vec<SinkLineage>) {
+    if let LogicalPlan::Sink {
+        input, destination, ..
+    } = plan
+    {
+        out.push(SinkLineage {
+            destination: destination.clone(),
+            columns: lineage_of(input),
+        });
+    }
+    for child in children(plan) {
+        collect_sinks(child, out);
+    }
+}
+
+fn children(plan: &LogicalPlan) -> Vec<&LogicalPlan> {
+    use LogicalPlan::*;
+    match plan {
+        Scan { .. } => vec![],
+        Filter { input, .. }
+        | FilterIn { input, .. }
+        | Map { input, .. }
+        | Project { input, .. }
+        | Aggregate { input, .. }
+        | Window { input, .. }
+        | Lateral { input, .. }
+        | TopK { input, .. }
+        | Limit { input, .. }
+        | Sink { input, .. } => vec![input],
+        Join { left, right, .. } | ThetaJoin { left, right, .. } => vec![left, right],
+        Union { inputs } => inputs.iter().collect(),
+    }
+}
+
+/// Output columns of `plan` with their lineage, in output order.
+fn lineage_of(plan: &LogicalPlan) -> Vec<ColumnLineage> {
+    use LogicalPlan::*;
+    match plan {
+        Scan { source, schema } => schema
+            .fields
+            .iter()
+            .map(|f| ColumnLineage {
+                column: f.name.clone(),
+                sources: vec![SourceColumn {
+                    source: source.clone(),
+                    column: f.name.clone(),
+                }],
+                derived: false,
+            })
+            .collect(),
+        Filter { input, .. }
+        | FilterIn { input, .. }
+        | Limit { input, .. }
+        | Sink { input, .. } => lineage_of(input),
+        Map { input, expr } => {
+            let mut cols = lineage_of(input);
+            // An invalid list fails the run; its columns stay as they were.
+            let items = Expr::parse_list(expr).unwrap_or_default();
+            for (e, alias) in items {
+                let Some(alias) = alias else {
+                    continue;
+                };
+                match e {
+                    // A plain column under a new name is a rename.
+                    Expr::Column(name) => {
+                        if let Some(idx) = cols.iter().position(|c| c.column == name) {
+                            cols[idx].column = alias.clone();
+                            let mut i = 0;
+                            cols.retain(|c| {
+                                i += 1;
+                                i - 1 == idx || c.column != alias
+                            });
+                        }
+                    }
+                    e => {
+                        let input_cols: Vec<String> = e.referenced_columns();
+                        let derived = ColumnLineage {
+                            column: alias.clone(),
+                            sources: merge_sources(&cols, &input_cols),
+                            derived: true,
+                        };
+                        cols.retain(|c| c.column != alias);
+                        cols.push(derived);
+                    }
+                }
+            }
+            cols
+        }
+        Project { input, columns } => {
+            let cols = lineage_of(input);
+            columns
+                .iter()
+                .map(|name| {
+                    cols.iter()
+                        .find(|c| &c.column == name)
+                        .cloned()
+                        .unwrap_or_else(|| ColumnLineage {
+                            column: name.clone(),
+                            sources: vec![],
+                            derived: false,
+                        })
+                })
+                .collect()
+        }
+        Join { left, right, .. } | ThetaJoin { left, right, .. } => {
+            // Right columns named as the join operators name them.
+            let mut cols = lineage_of(left);
+            cols.extend(lineage_of(right));
+            let names = dedup_names(cols.iter().map(|c| c.column.clone()), RIGHT_SUFFIX);
+            for (col, name) in cols.iter_mut().zip(names) {
+                col.column = name;
+            }
+            cols
+        }
+        Aggregate {
+            input,
+            group_by,
+            aggs,
+        } => {
+            let cols = lineage_of(input);
+            let mut out: Vec<ColumnLineage> = group_by
+                .iter()
+                .map(|name| ColumnLineage {
+                    column: name.clone(),
+                    sources: merge_sources(&cols, std::slice::from_ref(name)),
+                    derived: false,
+                })
+                .collect();
+            for agg in aggs {
+                let (column, input_col) = match agg {
+                    Aggregation::Count => ("count".to_string(), None),
+                    Aggregation::CountColumn(c) => (format!("count_{}", c), Some(c)),
+                    Aggregation::Sum(c) => (format!("sum_{}", c), Some(c)),
+                    Aggregation::Avg(c) => (format!("avg_{}", c), Some(c)),
+                    Aggregation::Min(c) => (format!("min_{}", c), Some(c)),
+                    Aggregation::Max(c) => (format!("max_{}", c), Some(c)),
+                };
+                out.push(ColumnLineage {
+                    column,
+                    sources: merge_sources(&cols, input_col.map_or(&[][..], std::slice::from_ref)),
+                    derived: true,
+                });
+            }
+            out
+        }
+        Window {
+            input,
+            partitions,
+            order_by,
+            functions,
+        } => {
+            let mut cols = lineage_of(input);
+            for f in functions {
+                // A row number depends only on where the row falls in its partition.
+                let inputs: Vec<String> = match &f.function {
+                    WindowFunction::RowNumber => {
+                        partitions.iter().chain(order_by).cloned().collect()
+                    }
+                    WindowFunction::Sum { column } => vec![column.clone()],
+                };
+                let derived = ColumnLineage {
+                    column: f.alias.clone(),
+                    sources: merge_sources(&cols, &inputs),
+                    derived: true,
+                };
+                cols.push(derived);
+            }
+            cols
+        }
+        Lateral {
+            input,
+            column,
+            alias,
+            ..
+        } => {
+            let mut cols = lineage_of(input);
+            let exploded = ColumnLineage {
+                column: alias.clone(),
+                sources: merge_sources(&cols, std::slice::from_ref(column)),
+                derived: true,
+            };
+            cols.push(exploded);
+            cols
+        }
+        TopK { input, column, .. } => {
+            let cols = lineage_of(input);
+            let value = cols
+                .iter()
+                .find(|c| &c.column == column)
+                .cloned()
+                .unwrap_or_else(|| ColumnLineage {
+                    column: column.clone(),
+                    sources: vec![],
+                    derived: false,
+                });
+            let counts = ["count", "count_error"].map(|name| ColumnLineage {
+                column: name.to_string(),
+                sources: value.sources.clone(),
+                derived: true,
+            });
+            std::iter::once(value).chain(counts).collect()
+        }
+        Union { inputs } => {
+            let mut cols: Vec<ColumnLineage> = Vec::new();
+            for input in inputs {
+                for col in lineage_of(input) {
+                    match cols.iter_mut().find(|c| c.column == col.column) {
+                        Some(existing) => {
+                            let sources: BTreeSet<SourceColumn> =
+                                existing.sources.drain(..).chain(col.sources).collect();
+                            existing.sources = sources.into_iter().collect();
+                            existing.derived |= col.derived;
+                        }
+                        None => cols.push(col),
+                    }
+                }
+            }
+            cols
+        }
+    }
+}
+
+/// The sources of the named columns of `cols`, sorted and deduplicated.
+fn merge_sources(cols: &[ColumnLineage], names: &[String]) -> Vec<SourceColumn> {
+    let sources: BTreeSet<SourceColumn> = names
+        .iter()
+        .filter_map(|name| cols.iter().find(|c| &c.column == name))
+        .flat_map(|c| c.sources.iter().cloned())
+        .collect();
+    sources.into_iter().collect()
+}
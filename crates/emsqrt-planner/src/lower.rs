@@ -2,15 +2,19 @@
 //! - assign stable `OpId`s
 //! - build a `PhysicalPlan` tree
 //! - emit bindings `OpId → {key, config}`
+//! - fold identical subplans into one, so a scan (or any subplan) feeding
+//!   several operators runs once
 //!
 //! The actual operator instances will be constructed later by the exec
 //! using `emsqrt-operators`' registry and the `key` + `config` here.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
-use emsqrt_core::dag::{LogicalPlan, PhysicalPlan, WindowFrameBound, WindowFunction};
+use emsqrt_core::align::{unify_schemas, AlignPolicy};
+use emsqrt_core::dag::{JoinType, LogicalPlan, PhysicalPlan, WindowFrameBound, WindowFunction};
+use emsqrt_core::expr::{map_schema, Expr};
 use emsqrt_core::id::OpId;
-use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::schema::{Constraints, DataType, Field, ParseErrorPolicy, Schema, SourceFormat};
 
 use crate::physical::{OperatorBinding, PhysicalProgram};
 
@@ -18,7 +22,7 @@ use crate::physical::{OperatorBinding, PhysicalProgram};
 /// Strategy:
 /// - Assign an OpId per node.
 /// - Pick a default operator key based on node kind (e.g., "filter").
-/// - Propagate schemas in a simplistic way (filter/map preserve; join uses left).
+/// - Propagate schemas in a simplistic way (filter preserves; map renames and appends; aggregates and joins as their operators do).
 pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
     let mut next_id = 1u64;
     let mut bindings = BTreeMap::<OpId, OperatorBinding>::new();
@@ -29,41 +33,6 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
         id
     }
 
-    fn schema_of(lp: &LogicalPlan) -> Schema {
-        use LogicalPlan::*;
-        match lp {
-            Scan { schema, .. } => schema.clone(),
-            Filter { input, .. }
-            | Map { input, .. }
-            | Project { input, .. }
-            | Aggregate { input, .. }
-            | Sink { input, .. } => schema_of(input),
-            Window {
-                input, functions, ..
-            } => {
-                let mut schema = schema_of(input);
-                for expr in functions {
-                    let data_type = match &expr.function {
-                        WindowFunction::RowNumber => DataType::Int64,
-                        WindowFunction::Sum { .. } => DataType::Float64,
-                    };
-                    schema
-                        .fields
-                        .push(Field::new(expr.alias.clone(), data_type, true));
-                }
-                schema
-            }
-            Lateral { input, alias, .. } => {
-                let mut schema = schema_of(input);
-                schema
-                    .fields
-                    .push(Field::new(alias.clone(), DataType::Utf8, true));
-                schema
-            }
-            Join { left, .. } => schema_of(left), // TODO: real join schema
-        }
-    }
-
     fn lower_rec(
         lp: &LogicalPlan,
         next_id: &mut u64,
@@ -79,10 +48,16 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                         key: "source".to_string(),
                         config: serde_json::json!({
                             "source": source,
-                            "schema": serde_json::to_value(&schema).unwrap_or(serde_json::json!({}))
+                            "schema": serde_json::to_value(schema).unwrap_or(serde_json::json!({}))
                         }),
                     },
                 );
+                // JSONL readers only materialize the declared fields.
+                if reads_jsonl(source, schema) && !schema.fields.is_empty() {
+                    let projection: Vec<&str> =
+                        schema.fields.iter().map(|f| f.name.as_str()).collect();
+                    set_binding_config(bindings, op, "projection", serde_json::json!(projection));
+                }
                 PhysicalPlan::Source {
                     op,
                     schema: schema.clone(),
@@ -90,6 +65,7 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
             }
             Filter { input, expr } => {
                 let child = lower_rec(input, next_id, bindings);
+                push_jsonl_predicates(input, &child, expr, bindings);
                 let op = alloc_id(next_id);
                 bindings.insert(
                     op,
@@ -104,6 +80,31 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                     schema: schema_of(lp),
                 }
             }
+            FilterIn {
+                input,
+                columns,
+                keys,
+                delimiter,
+            } => {
+                let child = lower_rec(input, next_id, bindings);
+                let op = alloc_id(next_id);
+                bindings.insert(
+                    op,
+                    OperatorBinding {
+                        key: "filter_in".to_string(),
+                        config: serde_json::json!({
+                            "columns": columns,
+                            "keys": keys,
+                            "delimiter": delimiter.clone().unwrap_or_else(|| ",".into())
+                        }),
+                    },
+                );
+                PhysicalPlan::Unary {
+                    op,
+                    input: Box::new(child),
+                    schema: schema_of(lp),
+                }
+            }
             Map { input, expr } => {
                 let child = lower_rec(input, next_id, bindings);
                 let op = alloc_id(next_id);
@@ -122,6 +123,9 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
             }
             Project { input, columns } => {
                 let child = lower_rec(input, next_id, bindings);
+                // Parquet and CSV readers decode only the columns the
+                // projection (and any filters between it and the scan) read.
+                push_scan_projection(input, &child, columns.clone(), bindings);
                 let op = alloc_id(next_id);
                 bindings.insert(
                     op,
@@ -149,6 +153,7 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                     .iter()
                     .map(|a| match a {
                         emsqrt_core::dag::Aggregation::Count => "count".to_string(),
+                        emsqrt_core::dag::Aggregation::CountColumn(col) => format!("count:{}", col),
                         emsqrt_core::dag::Aggregation::Sum(col) => format!("sum:{}", col),
                         emsqrt_core::dag::Aggregation::Avg(col) => format!("avg:{}", col),
                         emsqrt_core::dag::Aggregation::Min(col) => format!("min:{}", col),
@@ -166,6 +171,10 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                         }),
                     },
                 );
+                // Every group is a single row: no need to hash-dedup keys.
+                if !group_by.is_empty() && schema_of(input).constraints.is_unique_on(group_by) {
+                    set_binding_config(bindings, op, "unique_groups", serde_json::json!(true));
+                }
                 PhysicalPlan::Unary {
                     op,
                     input: Box::new(child),
@@ -221,6 +230,10 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                         }),
                     },
                 );
+                // Input already in (partitions, order_by) order: skip the sort.
+                if window_presorted(&schema_of(input), lp) {
+                    set_binding_config(bindings, op, "presorted", serde_json::json!(true));
+                }
                 PhysicalPlan::Unary {
                     op,
                     input: Box::new(child),
@@ -252,15 +265,133 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                     schema: schema_of(lp),
                 }
             }
-            Join { left, right, .. } => {
+            // Sort keys make it a top-n, kept in a bounded heap rather than
+            // sorting the whole input.
+            Limit {
+                input,
+                limit,
+                offset,
+                order_by,
+            } => {
+                let child = lower_rec(input, next_id, bindings);
+                let op = alloc_id(next_id);
+                let binding = if order_by.is_empty() {
+                    OperatorBinding {
+                        key: "limit".to_string(),
+                        config: serde_json::json!({ "limit": limit, "offset": offset }),
+                    }
+                } else {
+                    let order_by: Vec<String> = order_by.iter().map(ToString::to_string).collect();
+                    OperatorBinding {
+                        key: "top_n".to_string(),
+                        config: serde_json::json!({
+                            "order_by": order_by,
+                            "limit": limit,
+                            "offset": offset
+                        }),
+                    }
+                };
+                bindings.insert(op, binding);
+                PhysicalPlan::Unary {
+                    op,
+                    input: Box::new(child),
+                    schema: schema_of(lp),
+                }
+            }
+            TopK {
+                input,
+                column,
+                k,
+                capacity,
+            } => {
+                let child = lower_rec(input, next_id, bindings);
+                let op = alloc_id(next_id);
+                bindings.insert(
+                    op,
+                    OperatorBinding {
+                        key: "top_k".to_string(),
+                        config: serde_json::json!({
+                            "column": column,
+                            "k": k,
+                            "capacity": capacity
+                        }),
+                    },
+                );
+                PhysicalPlan::Unary {
+                    op,
+                    input: Box::new(child),
+                    schema: schema_of(lp),
+                }
+            }
+            Join {
+                left,
+                right,
+                on,
+                join_type,
+            } => {
+                // A filter directly above a join input runs inside the join,
+                // so the rows it drops are never partitioned or spilled.
+                // Filters using `rand()` stay separate operators, as their
+                // values depend on the rows' stream positions.
+                let mut lower_side = |side: &LogicalPlan| match fusable_filter(side) {
+                    Some((input, expr)) => {
+                        let child = lower_rec(input, next_id, bindings);
+                        push_jsonl_predicates(input, &child, expr, bindings);
+                        (child, Some(expr.to_string()))
+                    }
+                    None => (lower_rec(side, next_id, bindings), None),
+                };
+                let (l, left_filter) = lower_side(left);
+                let (r, right_filter) = lower_side(right);
+                let op = alloc_id(next_id);
+                // Inputs already sorted by the join keys can be merge-joined.
+                let (left_keys, right_keys): (Vec<String>, Vec<String>) =
+                    on.iter().cloned().unzip();
+                let presorted = schema_of(left).constraints.is_sorted_by(&left_keys)
+                    && schema_of(right).constraints.is_sorted_by(&right_keys);
+                let join_type = join_type_name(*join_type);
+                bindings.insert(
+                    op,
+                    OperatorBinding {
+                        key: if presorted { "join_merge" } else { "join_hash" }.to_string(),
+                        config: serde_json::json!({
+                            "on": on,
+                            "join_type": join_type
+                        }),
+                    },
+                );
+                if let Some(expr) = left_filter {
+                    set_binding_config(bindings, op, "left_filter", expr.into());
+                }
+                if let Some(expr) = right_filter {
+                    set_binding_config(bindings, op, "right_filter", expr.into());
+                }
+                PhysicalPlan::Binary {
+                    op,
+                    left: Box::new(l),
+                    right: Box::new(r),
+                    schema: schema_of(lp),
+                }
+            }
+            // No keys to partition or sort by: a block nested loop joins
+            // each left block with the whole right input.
+            ThetaJoin {
+                left,
+                right,
+                condition,
+                join_type,
+            } => {
                 let l = lower_rec(left, next_id, bindings);
                 let r = lower_rec(right, next_id, bindings);
                 let op = alloc_id(next_id);
                 bindings.insert(
                     op,
                     OperatorBinding {
-                        key: "join_hash".to_string(), // default to hash join; rules may switch to merge later
-                        config: serde_json::json!({}),
+                        key: "join_nested_loop".to_string(),
+                        config: serde_json::json!({
+                            "condition": condition,
+                            "join_type": join_type_name(*join_type)
+                        }),
                     },
                 );
                 PhysicalPlan::Binary {
@@ -270,6 +401,28 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
                     schema: schema_of(lp),
                 }
             }
+            Union { inputs } => {
+                let children: Vec<PhysicalPlan> = inputs
+                    .iter()
+                    .map(|i| lower_rec(i, next_id, bindings))
+                    .collect();
+                let op = alloc_id(next_id);
+                let schema = schema_of(lp);
+                bindings.insert(
+                    op,
+                    OperatorBinding {
+                        key: "union".to_string(),
+                        config: serde_json::json!({
+                            "schema": serde_json::to_value(&schema).unwrap_or(serde_json::json!({}))
+                        }),
+                    },
+                );
+                PhysicalPlan::Nary {
+                    op,
+                    inputs: children,
+                    schema,
+                }
+            }
             Sink {
                 input,
                 destination,
@@ -296,5 +449,404 @@ pub fn lower_to_physical(lp: &LogicalPlan) -> PhysicalProgram {
     }
 
     let plan = lower_rec(lp, &mut next_id, &mut bindings);
+    let plan = share_subplans(plan, &mut bindings, &mut HashMap::new());
     PhysicalProgram::new(plan, bindings)
 }
+
+/// Replace each subplan identical to an earlier one (same operators, configs,
+/// schemas and inputs) with that one, dropping its bindings. The plan then
+/// reads the earlier subplan's `OpId`s in both places, and TE gives it one
+/// set of blocks. Bindings are compared after lowering has pushed each
+/// reader's projection and predicates into them, so subplans read
+/// differently stay apart.
+fn share_subplans(
+    node: PhysicalPlan,
+    bindings: &mut BTreeMap<OpId, OperatorBinding>,
+    seen: &mut HashMap<String, PhysicalPlan>,
+) -> PhysicalPlan {
+    use PhysicalPlan::*;
+    let mut share = |child: PhysicalPlan| share_subplans(child, bindings, seen);
+    let node = match node {
+        Source { .. } => node,
+        Unary { op, input, schema } => Unary {
+            op,
+            input: Box::new(share(*input)),
+            schema,
+        },
+        Binary {
+            op,
+            left,
+            right,
+            schema,
+        } => Binary {
+            op,
+            left: Box::new(share(*left)),
+            right: Box::new(share(*right)),
+            schema,
+        },
+        Nary { op, inputs, schema } => Nary {
+            op,
+            inputs: inputs.into_iter().map(share).collect(),
+            schema,
+        },
+        // A plan has one sink, at its root: nothing to share it with.
+        Sink { op, input } => {
+            return Sink {
+                op,
+                input: Box::new(share(*input)),
+            }
+        }
+    };
+    let Some(binding) = bindings.get(&node.op()) else {
+        return node;
+    };
+    let schema = match &node {
+        Source { schema, .. }
+        | Unary { schema, .. }
+        | Binary { schema, .. }
+        | Nary { schema, .. } => serde_json::to_value(schema).unwrap_or_default(),
+        Sink { .. } => unreachable!(),
+    };
+    let inputs: Vec<u64> = node.children().iter().map(|c| c.op().get()).collect();
+    let signature = serde_json::json!({
+        "key": binding.key,
+        "config": binding.config,
+        "schema": schema,
+        "inputs": inputs,
+    })
+    .to_string();
+    match seen.get(&signature) {
+        Some(earlier) => {
+            bindings.remove(&node.op());
+            earlier.clone()
+        }
+        None => {
+            seen.insert(signature, node.clone());
+            node
+        }
+    }
+}
+
+/// Output schema of a node, carrying the input constraints it preserves.
+pub(crate) fn schema_of(lp: &LogicalPlan) -> Schema {
+    use LogicalPlan::*;
+    match lp {
+        Scan { schema, .. } => schema.clone(),
+        // Row subsets keep uniqueness and order.
+        Filter { input, .. } | FilterIn { input, .. } | Sink { input, .. } => schema_of(input),
+        // A row subset too; a top-n comes out in its sort order.
+        Limit {
+            input, order_by, ..
+        } => {
+            let mut schema = schema_of(input);
+            if !order_by.is_empty() {
+                schema.constraints.sorted_by = if order_by.iter().any(|k| k.descending) {
+                    Vec::new()
+                } else {
+                    order_by.iter().map(|k| k.column.clone()).collect()
+                };
+            }
+            schema
+        }
+        // Constraints on columns the list neither renames nor computes
+        // survive, so a map keeps sort order for merge joins. An invalid
+        // list keeps the input fields; the operator reports it when it runs.
+        Map { input, expr } => {
+            let schema = schema_of(input);
+            let Ok(items) = Expr::parse_list(expr) else {
+                return Schema::new_with_stats(schema.fields, schema.stats);
+            };
+            let mut touched = Vec::new();
+            for (item, alias) in &items {
+                if let (Expr::Column(column), Some(_)) = (item, alias) {
+                    touched.push(column.as_str());
+                }
+                touched.extend(alias.as_deref());
+            }
+            let untouched: Vec<String> = schema
+                .fields
+                .iter()
+                .map(|f| f.name.clone())
+                .filter(|name| !touched.contains(&name.as_str()))
+                .collect();
+            let constraints = schema.constraints.project(&untouched);
+            match map_schema(&items, &schema) {
+                Ok(mapped) => mapped.with_constraints(constraints),
+                Err(_) => Schema::new_with_stats(schema.fields, schema.stats),
+            }
+        }
+        Project { input, columns } => {
+            let schema = schema_of(input);
+            let constraints = schema.constraints.project(columns);
+            schema.with_constraints(constraints)
+        }
+        // One row per group; output order follows hashing, not the input.
+        // Group keys, then one column per aggregation, as the operator
+        // emits them.
+        Aggregate {
+            input,
+            group_by,
+            aggs,
+        } => {
+            let schema = schema_of(input);
+            let fields = group_by
+                .iter()
+                .filter_map(|key| schema.index_of(key).map(|i| schema.fields[i].clone()))
+                .chain(aggs.iter().map(|agg| agg.output_field(&schema)))
+                .collect();
+            Schema::new(fields).with_constraints(Constraints {
+                primary_key: group_by.clone(),
+                ..Default::default()
+            })
+        }
+        Window {
+            input, functions, ..
+        } => {
+            let mut schema = schema_of(input);
+            for expr in functions {
+                let data_type = match &expr.function {
+                    WindowFunction::RowNumber => DataType::Int64,
+                    WindowFunction::Sum { .. } => DataType::Float64,
+                };
+                schema
+                    .fields
+                    .push(Field::new(expr.alias.clone(), data_type, true));
+            }
+            // Rows keep their input positions, but the input order only
+            // means something here if the operator did not have to sort.
+            if !window_presorted(&schema, lp) {
+                schema.constraints.sorted_by.clear();
+            }
+            schema
+        }
+        Lateral { input, alias, .. } => {
+            let mut schema = schema_of(input);
+            schema
+                .fields
+                .push(Field::new(alias.clone(), DataType::Utf8, true));
+            // Exploding repeats input rows in place.
+            let constraints = schema.constraints.sorted_only();
+            schema.with_constraints(constraints)
+        }
+        // One row per reported value, most frequent first.
+        TopK { input, column, .. } => {
+            let field = schema_of(input)
+                .fields
+                .into_iter()
+                .find(|f| &f.name == column)
+                .unwrap_or_else(|| Field::new(column.clone(), DataType::Utf8, true));
+            Schema::new(vec![
+                field,
+                Field::new("count", DataType::Int64, false),
+                Field::new("count_error", DataType::Int64, false),
+            ])
+            .with_constraints(Constraints {
+                primary_key: vec![column.clone()],
+                ..Default::default()
+            })
+        }
+        Join {
+            left,
+            right,
+            join_type,
+            ..
+        }
+        | ThetaJoin {
+            left,
+            right,
+            join_type,
+            ..
+        } => join_type.output_schema(&schema_of(left), &schema_of(right)),
+        Union { inputs } => {
+            let schemas: Vec<Schema> = inputs.iter().map(schema_of).collect();
+            let Some(first) = schemas.first() else {
+                return Schema::new(vec![]);
+            };
+            // Inputs aligned by their scans' policy cover every column;
+            // otherwise they must all match the first.
+            let align = schemas
+                .iter()
+                .map(|s| s.align)
+                .find(AlignPolicy::fills_missing)
+                .unwrap_or_default();
+            let fields = match unify_schemas(&schemas) {
+                Ok(unified) if align.fills_missing() => unified.fields,
+                _ => first.fields.clone(),
+            };
+            Schema::new_with_stats(fields, first.stats.clone()).with_align(align)
+        }
+    }
+}
+
+/// A join type as operator bindings spell it.
+fn join_type_name(join_type: JoinType) -> &'static str {
+    match join_type {
+        JoinType::Inner => "inner",
+        JoinType::Left => "left",
+        JoinType::Right => "right",
+        JoinType::Full => "full",
+    }
+}
+
+/// Whether a scan source is newline-delimited JSON (by extension).
+pub fn is_jsonl_source(source: &str) -> bool {
+    source.ends_with(".jsonl") || source.ends_with(".ndjson")
+}
+
+/// Whether a scan is read as JSONL: by its declared format, else by extension.
+fn reads_jsonl(source: &str, schema: &Schema) -> bool {
+    schema.layout.is_none()
+        && match schema.format {
+            Some(format) => format == SourceFormat::Jsonl,
+            None => is_jsonl_source(source),
+        }
+}
+
+/// Whether a scan is read as Parquet: by its declared format, else by extension.
+fn reads_parquet(source: &str, schema: &Schema) -> bool {
+    schema.layout.is_none()
+        && match schema.format {
+            Some(format) => format == SourceFormat::Parquet,
+            None => source.ends_with(".parquet") || source.ends_with(".parq"),
+        }
+}
+
+/// Whether a scan is read as CSV: by its declared format, else for any
+/// extension the other readers do not claim.
+fn reads_csv(source: &str, schema: &Schema) -> bool {
+    schema.layout.is_none()
+        && match schema.format {
+            Some(format) => format == SourceFormat::Csv,
+            None => {
+                !is_jsonl_source(source)
+                    && !reads_parquet(source, schema)
+                    && !source.ends_with(".avro")
+            }
+        }
+}
+
+/// Set the `projection` of the Parquet or CSV scan under `input` (lowered
+/// to `child`) to `columns` plus those read by filters in between, and
+/// those the scan still checks: declared constraints, and columns whose
+/// parse errors fail the run or dead-letter the row. Nothing is set if a
+/// filter does not parse or anything else sits above the scan.
+fn push_scan_projection(
+    input: &LogicalPlan,
+    child: &PhysicalPlan,
+    mut columns: Vec<String>,
+    bindings: &mut BTreeMap<OpId, OperatorBinding>,
+) {
+    match (input, child) {
+        (LogicalPlan::Scan { source, schema }, PhysicalPlan::Source { op, .. })
+            if reads_parquet(source, schema) || reads_csv(source, schema) =>
+        {
+            let constraints = &schema.constraints;
+            let checked =
+                constraints
+                    .primary_key
+                    .iter()
+                    .chain(constraints.unique.iter().flatten())
+                    .chain(&constraints.sorted_by)
+                    .cloned()
+                    .chain(schema.fields.iter().map(|f| f.name.clone()).filter(|name| {
+                        schema.parse_errors.policy_for(name) != ParseErrorPolicy::Null
+                    }));
+            for column in checked.collect::<Vec<_>>() {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+            set_binding_config(bindings, *op, "projection", serde_json::json!(columns));
+        }
+        (LogicalPlan::Filter { input, expr }, PhysicalPlan::Unary { input: child, .. }) => {
+            let Ok(parsed) = Expr::parse(expr) else {
+                return;
+            };
+            for column in parsed.referenced_columns() {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+            push_scan_projection(input, child, columns, bindings);
+        }
+        _ => {}
+    }
+}
+
+/// The input and predicate of `side` when it is a filter a join can run
+/// itself: one whose predicate does not use `rand()`.
+fn fusable_filter(side: &LogicalPlan) -> Option<(&LogicalPlan, &str)> {
+    match side {
+        LogicalPlan::Filter { input, expr }
+            if Expr::parse(expr).is_ok_and(|e| !e.uses_row_position()) =>
+        {
+            Some((input, expr))
+        }
+        _ => None,
+    }
+}
+
+/// Push the `col == literal` conjuncts of filter `expr` over `input` into the
+/// reader when `input` is a JSONL scan (lowered to `child`); the filter stays.
+fn push_jsonl_predicates(
+    input: &LogicalPlan,
+    child: &PhysicalPlan,
+    expr: &str,
+    bindings: &mut BTreeMap<OpId, OperatorBinding>,
+) {
+    // Look through the projection pruning puts over a scan.
+    let (input, child) = match (input, child) {
+        (LogicalPlan::Project { input, .. }, PhysicalPlan::Unary { input: child, .. }) => {
+            (&**input, &**child)
+        }
+        other => other,
+    };
+    let (LogicalPlan::Scan { source, schema }, PhysicalPlan::Source { op, .. }) = (input, child)
+    else {
+        return;
+    };
+    if !reads_jsonl(source, schema) {
+        return;
+    }
+    if let Ok(parsed) = Expr::parse(expr) {
+        let predicates = parsed.pushdown_predicates();
+        if !predicates.is_empty() {
+            set_binding_config(
+                bindings,
+                *op,
+                "predicates",
+                serde_json::to_value(predicates).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+/// True if a window's input (with schema `input`) is already ordered by its
+/// partition keys followed by its order keys.
+fn window_presorted(input: &Schema, window: &LogicalPlan) -> bool {
+    match window {
+        LogicalPlan::Window {
+            partitions,
+            order_by,
+            ..
+        } => {
+            let keys: Vec<String> = partitions.iter().chain(order_by).cloned().collect();
+            input.constraints.is_sorted_by(&keys)
+        }
+        _ => false,
+    }
+}
+
+fn set_binding_config(
+    bindings: &mut BTreeMap<OpId, OperatorBinding>,
+    op: OpId,
+    key: &str,
+    value: serde_json::Value,
+) {
+    if let Some(obj) = bindings
+        .get_mut(&op)
+        .and_then(|binding| binding.config.as_object_mut())
+    {
+        obj.insert(key.to_string(), value);
+    }
+}
@@ -30,4 +30,30 @@ impl PhysicalProgram {
     pub fn new(plan: PhysicalPlan, bindings: BTreeMap<OpId, OperatorBinding>) -> Self {
         Self { plan, bindings }
     }
+
+    /// Join keys of every binding that carries an `on` list of `[left, right]` pairs,
+    /// and an empty list for each nested-loop join, which has none.
+    /// Feed this to `emsqrt_te::plan_te_with_join_keys` to co-partition join blocks.
+    pub fn join_keys(&self) -> BTreeMap<OpId, Vec<(String, String)>> {
+        self.bindings
+            .iter()
+            .filter_map(|(op, binding)| {
+                if binding.key == "join_nested_loop" {
+                    return Some((*op, Vec::new()));
+                }
+                let on = binding.config.get("on")?.as_array()?;
+                let pairs: Vec<(String, String)> = on
+                    .iter()
+                    .filter_map(|pair| {
+                        let pair = pair.as_array()?;
+                        Some((
+                            pair.first()?.as_str()?.to_string(),
+                            pair.get(1)?.as_str()?.to_string(),
+                        ))
+                    })
+                    .collect();
+                (!pairs.is_empty()).then_some((*op, pairs))
+            })
+            .collect()
+    }
 }
@@ -1,11 +1,371 @@
 //! Simple optimization rules (pushdown/reorder/strategy).
 
+use std::collections::{BTreeSet, HashMap};
+
+use emsqrt_core::dag::{Aggregation, WindowFunction, RIGHT_SUFFIX};
+use emsqrt_core::expr::Expr;
+use emsqrt_core::schema::{dedup_names, Schema};
+
 use crate::logical::LogicalPlan;
+use crate::lower::schema_of;
 
 /// Apply a sequence of lightweight rewrites to the logical plan.
 pub fn optimize(plan: LogicalPlan) -> LogicalPlan {
     // Apply projection pushdown rule
-    projection_pushdown(plan)
+    let plan = projection_pushdown(plan);
+    prune_columns(plan)
+}
+
+/// Projection pruning: put a `Project` of only the columns the operators
+/// above read right over each scan (merging it with a `Project` already
+/// there), so unused columns are not carried through joins and sorts.
+/// Lowering pushes these projections into Parquet and CSV readers.
+///
+/// Reads are over-approximated: an expression that does not parse reads
+/// everything, and a scan always keeps one column so rows still count.
+/// Copies of a subplan read in several places keep the columns all of their
+/// readers need, so they stay shareable.
+pub fn prune_columns(plan: LogicalPlan) -> LogicalPlan {
+    let mut reads = Reads::Collect(HashMap::new());
+    prune(&mut reads, plan.clone(), None);
+    let Reads::Collect(merged) = reads else {
+        unreachable!()
+    };
+    prune(&mut Reads::Apply(merged), plan, None)
+}
+
+/// Columns a node's consumer reads; `None` means all of them.
+type Required = Option<BTreeSet<String>>;
+
+/// Columns read from each subplan, keyed by its `Debug` text. Pruning runs
+/// twice: first collecting what the readers of every copy of a subplan need,
+/// then narrowing each copy to the union, so copies of a subplan read in
+/// several places stay identical and lowering can run them once.
+enum Reads {
+    Collect(HashMap<String, Required>),
+    Apply(HashMap<String, Required>),
+}
+
+fn prune(reads: &mut Reads, plan: LogicalPlan, required: Required) -> LogicalPlan {
+    use LogicalPlan::*;
+
+    let required = match reads {
+        Reads::Collect(merged) => {
+            let entry = merged
+                .entry(format!("{:?}", plan))
+                .or_insert_with(|| Some(BTreeSet::new()));
+            *entry = match (entry.take(), &required) {
+                (Some(mut all), Some(r)) => {
+                    all.extend(r.iter().cloned());
+                    Some(all)
+                }
+                _ => None,
+            };
+            required
+        }
+        Reads::Apply(merged) => merged
+            .get(&format!("{:?}", plan))
+            .cloned()
+            .unwrap_or(required),
+    };
+
+    // `required` plus `columns`, or `None` if the consumer reads everything.
+    let plus = |required: &Required, columns: &[String]| -> Required {
+        required
+            .as_ref()
+            .map(|r| r.iter().chain(columns).cloned().collect())
+    };
+    let expr_columns = |expr: &str| Expr::parse(expr).ok().map(|e| e.referenced_columns());
+
+    match plan {
+        Scan { source, schema } => {
+            let Some(required) = required else {
+                return Scan { source, schema };
+            };
+            let mut columns: Vec<String> = schema
+                .fields
+                .iter()
+                .map(|f| f.name.clone())
+                .filter(|name| required.contains(name))
+                .collect();
+            if columns.is_empty() {
+                columns.extend(schema.fields.first().map(|f| f.name.clone()));
+            }
+            if columns.len() == schema.fields.len() {
+                return Scan { source, schema };
+            }
+            Project {
+                input: Box::new(Scan { source, schema }),
+                columns,
+            }
+        }
+        Project { input, columns } => {
+            let mut columns = match &required {
+                Some(r) => columns.into_iter().filter(|c| r.contains(c)).collect(),
+                None => columns,
+            };
+            if columns.is_empty() {
+                // Nothing above reads a column; keep one so rows still count.
+                columns.extend(schema_of(&input).fields.first().map(|f| f.name.clone()));
+            }
+            let needed = columns.iter().cloned().collect();
+            match prune(reads, *input, Some(needed)) {
+                // A projection of a projection keeps the outer columns.
+                Project {
+                    input,
+                    columns: inner,
+                } if columns.iter().all(|c| inner.contains(c)) => Project { input, columns },
+                input => Project {
+                    input: Box::new(input),
+                    columns,
+                },
+            }
+        }
+        Filter { input, expr } => {
+            let needed = expr_columns(&expr).and_then(|cols| plus(&required, &cols));
+            Filter {
+                input: Box::new(prune(reads, *input, needed)),
+                expr,
+            }
+        }
+        FilterIn {
+            input,
+            columns,
+            keys,
+            delimiter,
+        } => FilterIn {
+            input: Box::new(prune(reads, *input, plus(&required, &columns))),
+            columns,
+            keys,
+            delimiter,
+        },
+        Map { input, expr } => {
+            // Computed and renamed columns come from the list; the rest
+            // pass through from the input.
+            let needed = Expr::parse_list(&expr).ok().and_then(|items| {
+                let mut needed = required?;
+                for (_, alias) in &items {
+                    if let Some(alias) = alias {
+                        needed.remove(alias);
+                    }
+                }
+                for (item, _) in &items {
+                    needed.extend(item.referenced_columns());
+                }
+                Some(needed)
+            });
+            Map {
+                input: Box::new(prune(reads, *input, needed)),
+                expr,
+            }
+        }
+        Aggregate {
+            input,
+            group_by,
+            aggs,
+        } => {
+            let needed = group_by
+                .iter()
+                .cloned()
+                .chain(aggs.iter().filter_map(|agg| match agg {
+                    Aggregation::Count => None,
+                    Aggregation::CountColumn(c)
+                    | Aggregation::Sum(c)
+                    | Aggregation::Avg(c)
+                    | Aggregation::Min(c)
+                    | Aggregation::Max(c) => Some(c.clone()),
+                }))
+                .collect();
+            Aggregate {
+                input: Box::new(prune(reads, *input, Some(needed))),
+                group_by,
+                aggs,
+            }
+        }
+        Window {
+            input,
+            partitions,
+            order_by,
+            functions,
+        } => {
+            let needed = required.map(|mut needed| {
+                for function in &functions {
+                    needed.remove(&function.alias);
+                }
+                needed.extend(partitions.iter().chain(&order_by).cloned());
+                for function in &functions {
+                    if let WindowFunction::Sum { column } = &function.function {
+                        needed.insert(column.clone());
+                    }
+                }
+                needed
+            });
+            Window {
+                input: Box::new(prune(reads, *input, needed)),
+                partitions,
+                order_by,
+                functions,
+            }
+        }
+        Lateral {
+            input,
+            column,
+            alias,
+            delimiter,
+        } => {
+            let needed = required.map(|mut needed| {
+                needed.remove(&alias);
+                needed.insert(column.clone());
+                needed
+            });
+            Lateral {
+                input: Box::new(prune(reads, *input, needed)),
+                column,
+                alias,
+                delimiter,
+            }
+        }
+        TopK {
+            input,
+            column,
+            k,
+            capacity,
+        } => TopK {
+            input: Box::new(prune(reads, *input, Some(BTreeSet::from([column.clone()])))),
+            column,
+            k,
+            capacity,
+        },
+        Join {
+            left,
+            right,
+            on,
+            join_type,
+        } => {
+            let (left_needed, right_needed) = join_inputs_needed(
+                &left,
+                &right,
+                &on,
+                &column_names(&join_type.output_schema(&schema_of(&left), &schema_of(&right))),
+                required,
+            );
+            Join {
+                left: Box::new(prune(reads, *left, left_needed)),
+                right: Box::new(prune(reads, *right, right_needed)),
+                on,
+                join_type,
+            }
+        }
+        ThetaJoin {
+            left,
+            right,
+            condition,
+            join_type,
+        } => {
+            // The condition reads output columns, like the join's consumers.
+            let required = match &condition {
+                Some(condition) => expr_columns(condition).and_then(|cols| plus(&required, &cols)),
+                None => required,
+            };
+            let (left_needed, right_needed) = join_inputs_needed(
+                &left,
+                &right,
+                &[],
+                &column_names(&join_type.output_schema(&schema_of(&left), &schema_of(&right))),
+                required,
+            );
+            ThetaJoin {
+                left: Box::new(prune(reads, *left, left_needed)),
+                right: Box::new(prune(reads, *right, right_needed)),
+                condition,
+                join_type,
+            }
+        }
+        Limit {
+            input,
+            limit,
+            offset,
+            order_by,
+        } => {
+            let sort_columns: Vec<String> = order_by.iter().map(|k| k.column.clone()).collect();
+            Limit {
+                input: Box::new(prune(reads, *input, plus(&required, &sort_columns))),
+                limit,
+                offset,
+                order_by,
+            }
+        }
+        Sink {
+            input,
+            destination,
+            format,
+        } => Sink {
+            input: Box::new(prune(reads, *input, None)),
+            destination,
+            format,
+        },
+        Union { inputs } => Union {
+            inputs: inputs
+                .into_iter()
+                .map(|input| prune(reads, input, required.clone()))
+                .collect(),
+        },
+    }
+}
+
+/// Columns a join reads from each side to produce the `required` columns
+/// of `output` (its output names) plus its keys. Right columns renamed for
+/// a clash keep their names only while the clashing columns stay, so when
+/// pruning would rename one, both sides keep all their columns.
+fn join_inputs_needed(
+    left: &LogicalPlan,
+    right: &LogicalPlan,
+    on: &[(String, String)],
+    output: &[String],
+    required: Required,
+) -> (Required, Required) {
+    let Some(required) = required else {
+        return (None, None);
+    };
+    let left_names = column_names(&schema_of(left));
+    let right_names = column_names(&schema_of(right));
+    let split = left_names.len().min(output.len());
+    let (left_out, right_out) = output.split_at(split);
+
+    let keep = |names: &[String], out: &[String], keys: &mut dyn Iterator<Item = &String>| {
+        let mut needed: BTreeSet<String> = names
+            .iter()
+            .zip(out)
+            .filter(|(_, out)| required.contains(*out))
+            .map(|(name, _)| name.clone())
+            .collect();
+        needed.extend(keys.cloned());
+        needed
+    };
+    let left_needed = keep(&left_names, left_out, &mut on.iter().map(|(l, _)| l));
+    let right_needed = keep(&right_names, right_out, &mut on.iter().map(|(_, r)| r));
+
+    // Output names of the kept columns must not change.
+    let kept = |names: &[String], needed: &BTreeSet<String>, out: &[String]| {
+        names
+            .iter()
+            .zip(out)
+            .filter(|(name, _)| needed.contains(*name))
+            .map(|(name, out)| (name.clone(), out.clone()))
+            .collect::<Vec<_>>()
+    };
+    let (kept_in, kept_out): (Vec<String>, Vec<String>) = kept(&left_names, &left_needed, left_out)
+        .into_iter()
+        .chain(kept(&right_names, &right_needed, right_out))
+        .unzip();
+    if dedup_names(kept_in, RIGHT_SUFFIX) != kept_out {
+        return (None, None);
+    }
+    (Some(left_needed), Some(right_needed))
+}
+
+fn column_names(schema: &Schema) -> Vec<String> {
+    schema.fields.iter().map(|f| f.name.clone()).collect()
 }
 
 /// Simple projection pushdown: Project(Filter(x)) → Filter(Project(x)) when safe.
@@ -30,6 +390,17 @@ fn projection_pushdown(plan: LogicalPlan) -> LogicalPlan {
             input: Box::new(projection_pushdown(*input)),
             expr,
         },
+        FilterIn {
+            input,
+            columns,
+            keys,
+            delimiter,
+        } => FilterIn {
+            input: Box::new(projection_pushdown(*input)),
+            columns,
+            keys,
+            delimiter,
+        },
         Map { input, expr } => Map {
             input: Box::new(projection_pushdown(*input)),
             expr,
@@ -65,6 +436,17 @@ fn projection_pushdown(plan: LogicalPlan) -> LogicalPlan {
             alias,
             delimiter,
         },
+        TopK {
+            input,
+            column,
+            k,
+            capacity,
+        } => TopK {
+            input: Box::new(projection_pushdown(*input)),
+            column,
+            k,
+            capacity,
+        },
         Join {
             left,
             right,
@@ -76,6 +458,28 @@ fn projection_pushdown(plan: LogicalPlan) -> LogicalPlan {
             on,
             join_type,
         },
+        ThetaJoin {
+            left,
+            right,
+            condition,
+            join_type,
+        } => ThetaJoin {
+            left: Box::new(projection_pushdown(*left)),
+            right: Box::new(projection_pushdown(*right)),
+            condition,
+            join_type,
+        },
+        Limit {
+            input,
+            limit,
+            offset,
+            order_by,
+        } => Limit {
+            input: Box::new(projection_pushdown(*input)),
+            limit,
+            offset,
+            order_by,
+        },
         Sink {
             input,
             destination,
@@ -85,6 +489,9 @@ fn projection_pushdown(plan: LogicalPlan) -> LogicalPlan {
             destination,
             format,
         },
+        Union { inputs } => Union {
+            inputs: inputs.into_iter().map(projection_pushdown).collect(),
+        },
         // Leaf nodes
         Scan { .. } => plan,
     }
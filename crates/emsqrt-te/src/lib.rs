@@ -21,4 +21,7 @@ pub mod verify;
 
 pub use cost::{NodeCost, WorkEstimate};
 pub use schedule::{choose_block_size, BlockSizeHint};
-pub use tree_eval::{plan_te, TeBlock, TePlan};
+pub use tree_eval::{
+    plan_te, plan_te_with_block_size, plan_te_with_join_keys, BlockPartition, Exchange, JoinKeys,
+    PartitionedInput, TeBlock, TePlan,
+};
@@ -9,6 +9,8 @@
 //! - Use `BlockSizeHint` to cut streams into approximately equal row/byte blocks.
 //! - Emit dependency edges to ensure correctness and bounded frontier.
 
+use std::collections::{BTreeMap, HashMap};
+
 use emsqrt_core::dag::PhysicalPlan;
 use emsqrt_core::id::{BlockId, OpId};
 use emsqrt_core::prelude::Schema;
@@ -29,8 +31,50 @@ pub struct TeBlock {
     pub deps: Vec<BlockId>,
     /// Optional [start,end) row offsets (planner-supplied / estimated).
     pub range_rows: Option<(u64, u64)>,
+    /// Hash co-partitioning of a binary block's inputs (None = index-aligned deps).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partition: Option<BlockPartition>,
+    /// Set on an exchange block, which splits its one dep's output by key hash
+    /// instead of running `op` (the join it feeds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<Exchange>,
+}
+
+/// Hash partition `index` of `count` for a binary block.
+///
+/// A keyed side lists the exchange blocks that split its upstream blocks, and
+/// the executor hands the block only part `index` of each, so left/right rows
+/// sharing a key always meet in the same block. A side without keys is taken
+/// whole: a keyless join (e.g. a nested-loop join) lists one left block and
+/// every right block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockPartition {
+    pub index: usize,
+    pub count: usize,
+    /// One entry per operator input, in operator order (left, right).
+    pub inputs: Vec<PartitionedInput>,
+}
+
+/// Split of one upstream block's output into `count` parts by a hash of
+/// `keys`; part i is read only by partition i of the join.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub keys: Vec<String>,
+    pub count: usize,
 }
 
+/// Upstream blocks feeding one side of a co-partitioned block, and its key columns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionedInput {
+    pub deps: Vec<BlockId>,
+    pub keys: Vec<String>,
+}
+
+/// Join keys per binary operator: (left_col, right_col) pairs. An empty list
+/// marks a keyless join, whose blocks each pair one left block with the
+/// whole right input.
+pub type JoinKeys = BTreeMap<OpId, Vec<(String, String)>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TePlan {
     /// Chosen block size hint used by this plan.
@@ -57,16 +101,47 @@ impl TePlan {
 /// - For unary nodes: chunks pipeline 1-to-1 (chunk i depends on input chunk i)
 /// - For binary nodes: chunks are aligned (join chunk i depends on left[i] and right[i])
 /// - Each block gets a monotonic row range hint.
+/// - A subplan shared by several nodes (same `OpId`s) is planned once; its
+///   output blocks feed every reader.
 pub fn plan_te(
     phys: &PhysicalPlan,
     est: &WorkEstimate,
     mem_cap_bytes: usize,
+) -> Result<TePlan, PlanError> {
+    plan_te_with_join_keys(phys, est, mem_cap_bytes, &JoinKeys::new())
+}
+
+/// Like [`plan_te`], but binary nodes with known join keys are co-partitioned:
+/// every left/right block feeds an exchange block that splits it once by a
+/// hash of the join keys, and join block i carries a [`BlockPartition`] and
+/// reads only part i of each split. Join blocks still wait on every exchange
+/// block, but each row is hashed and moved once per join.
+pub fn plan_te_with_join_keys(
+    phys: &PhysicalPlan,
+    est: &WorkEstimate,
+    mem_cap_bytes: usize,
+    join_keys: &JoinKeys,
 ) -> Result<TePlan, PlanError> {
     let b = choose_block_size(mem_cap_bytes, est);
+    plan_te_with_block_size(phys, est, b, join_keys)
+}
+
+/// Like [`plan_te_with_join_keys`], with an explicit block size instead of the
+/// cost-model choice (e.g. a user override from pipeline hints).
+pub fn plan_te_with_block_size(
+    phys: &PhysicalPlan,
+    est: &WorkEstimate,
+    b: BlockSizeHint,
+    join_keys: &JoinKeys,
+) -> Result<TePlan, PlanError> {
+    let b = BlockSizeHint {
+        rows_per_block: b.rows_per_block.max(1),
+    };
     let mut order = Vec::<TeBlock>::new();
     let mut next_block_id = 0u64;
 
     // Helper structure to track which blocks were created for each node
+    #[derive(Clone)]
     struct BlockRange {
         blocks: Vec<BlockId>,
         estimated_rows: u64,
@@ -78,14 +153,21 @@ pub fn plan_te(
         next_block_id: &mut u64,
         rows_per_block: u64,
         est: &WorkEstimate,
+        join_keys: &JoinKeys,
+        shared: &mut HashMap<OpId, BlockRange>,
     ) -> Result<BlockRange, PlanError> {
         use PhysicalPlan::*;
-        match node {
+        // A subplan read by several nodes gets its blocks once; every
+        // reader depends on the same blocks.
+        if let Some(range) = shared.get(&node.op()) {
+            return Ok(range.clone());
+        }
+        let range = match node {
             Source { op, schema } => {
                 // Estimate: use total_rows from work estimate divided by number of sources
                 // For now, assume single source gets all rows
                 let estimated_rows = est.total_rows.max(rows_per_block);
-                let num_blocks = ((estimated_rows + rows_per_block - 1) / rows_per_block).max(1);
+                let num_blocks = estimated_rows.div_ceil(rows_per_block).max(1);
 
                 let mut blocks = Vec::new();
                 for i in 0..num_blocks {
@@ -101,6 +183,8 @@ pub fn plan_te(
                         schema: schema.clone(),
                         deps: vec![],
                         range_rows: Some((start, end)),
+                        partition: None,
+                        exchange: None,
                     });
                     blocks.push(id);
                 }
@@ -111,7 +195,15 @@ pub fn plan_te(
                 })
             }
             Unary { op, input, schema } => {
-                let child_range = walk(input, order, next_block_id, rows_per_block, est)?;
+                let child_range = walk(
+                    input,
+                    order,
+                    next_block_id,
+                    rows_per_block,
+                    est,
+                    join_keys,
+                    shared,
+                )?;
 
                 // Create same number of blocks as input (1-to-1 pipeline)
                 let estimated_rows = child_range.estimated_rows; // Pass through for unary
@@ -130,6 +222,8 @@ pub fn plan_te(
                         schema: schema.clone(),
                         deps: vec![input_block],
                         range_rows: Some((start, end)),
+                        partition: None,
+                        exchange: None,
                     });
                     blocks.push(id);
                 }
@@ -145,14 +239,78 @@ pub fn plan_te(
                 right,
                 schema,
             } => {
-                let left_range = walk(left, order, next_block_id, rows_per_block, est)?;
-                let right_range = walk(right, order, next_block_id, rows_per_block, est)?;
+                let left_range = walk(
+                    left,
+                    order,
+                    next_block_id,
+                    rows_per_block,
+                    est,
+                    join_keys,
+                    shared,
+                )?;
+                let right_range = walk(
+                    right,
+                    order,
+                    next_block_id,
+                    rows_per_block,
+                    est,
+                    join_keys,
+                    shared,
+                )?;
 
                 // Align chunks: create blocks matching the max of left/right block counts
-                // For simplicity, each join block depends on corresponding left/right blocks
-                let num_blocks = left_range.blocks.len().max(right_range.blocks.len());
+                // For simplicity, each join block depends on corresponding left/right blocks.
+                // A keyless join takes one block per left block.
+                let keyless = join_keys.get(op).is_some_and(|keys| keys.is_empty());
+                let num_blocks = if keyless {
+                    left_range.blocks.len()
+                } else {
+                    left_range.blocks.len().max(right_range.blocks.len())
+                };
                 let estimated_rows = left_range.estimated_rows.max(right_range.estimated_rows);
 
+                // Keyed join: split every input block once by key hash. Each
+                // side gets its own exchange blocks, even over a shared input.
+                let sides = match join_keys.get(op) {
+                    Some(keys) if !keys.is_empty() => {
+                        let left_keys: Vec<String> = keys.iter().map(|(l, _)| l.clone()).collect();
+                        let right_keys: Vec<String> = keys.iter().map(|(_, r)| r.clone()).collect();
+                        let mut sides = Vec::new();
+                        for (range, keys) in [(&left_range, left_keys), (&right_range, right_keys)]
+                        {
+                            let mut exchanges = Vec::new();
+                            for &input_block in &range.blocks {
+                                let schema = order
+                                    .iter()
+                                    .find(|b| b.id == input_block)
+                                    .map(|b| b.schema.clone())
+                                    .unwrap_or_else(|| schema.clone());
+                                let id = BlockId::new(*next_block_id);
+                                *next_block_id += 1;
+                                order.push(TeBlock {
+                                    id,
+                                    op: *op,
+                                    schema,
+                                    deps: vec![input_block],
+                                    range_rows: None,
+                                    partition: None,
+                                    exchange: Some(Exchange {
+                                        keys: keys.clone(),
+                                        count: num_blocks,
+                                    }),
+                                });
+                                exchanges.push(id);
+                            }
+                            sides.push(PartitionedInput {
+                                deps: exchanges,
+                                keys,
+                            });
+                        }
+                        Some(sides)
+                    }
+                    _ => None,
+                };
+
                 let mut blocks = Vec::new();
                 for i in 0..num_blocks {
                     let start = (i as u64) * rows_per_block;
@@ -161,14 +319,50 @@ pub fn plan_te(
                     let id = BlockId::new(*next_block_id);
                     *next_block_id += 1;
 
-                    // Depend on corresponding blocks from left and right
-                    let mut deps = Vec::new();
-                    if i < left_range.blocks.len() {
-                        deps.push(left_range.blocks[i]);
-                    }
-                    if i < right_range.blocks.len() {
-                        deps.push(right_range.blocks[i]);
-                    }
+                    let (deps, partition) = match join_keys.get(op) {
+                        Some(keys) if !keys.is_empty() => {
+                            // Co-partitioned: part i of every exchange block of both sides
+                            let inputs = sides.clone().unwrap_or_default();
+                            let deps = inputs.iter().flat_map(|s| s.deps.clone()).collect();
+                            let partition = BlockPartition {
+                                index: i,
+                                count: num_blocks,
+                                inputs,
+                            };
+                            (deps, Some(partition))
+                        }
+                        Some(_) => {
+                            // Keyless: left block i against the whole right input
+                            let mut deps = vec![left_range.blocks[i]];
+                            deps.extend(right_range.blocks.iter().copied());
+                            let partition = BlockPartition {
+                                index: i,
+                                count: num_blocks,
+                                inputs: vec![
+                                    PartitionedInput {
+                                        deps: vec![left_range.blocks[i]],
+                                        keys: Vec::new(),
+                                    },
+                                    PartitionedInput {
+                                        deps: right_range.blocks.clone(),
+                                        keys: Vec::new(),
+                                    },
+                                ],
+                            };
+                            (deps, Some(partition))
+                        }
+                        None => {
+                            // Depend on corresponding blocks from left and right
+                            let mut deps = Vec::new();
+                            if i < left_range.blocks.len() {
+                                deps.push(left_range.blocks[i]);
+                            }
+                            if i < right_range.blocks.len() {
+                                deps.push(right_range.blocks[i]);
+                            }
+                            (deps, None)
+                        }
+                    };
 
                     order.push(TeBlock {
                         id,
@@ -176,6 +370,8 @@ pub fn plan_te(
                         schema: schema.clone(),
                         deps,
                         range_rows: Some((start, end)),
+                        partition,
+                        exchange: None,
                     });
                     blocks.push(id);
                 }
@@ -185,8 +381,57 @@ pub fn plan_te(
                     estimated_rows,
                 })
             }
+            Nary { op, inputs, schema } => {
+                // Each input block feeds exactly one output block (bounded fan-in of 1).
+                let mut blocks = Vec::new();
+                let mut estimated_rows = 0u64;
+                for input in inputs {
+                    let child_range = walk(
+                        input,
+                        order,
+                        next_block_id,
+                        rows_per_block,
+                        est,
+                        join_keys,
+                        shared,
+                    )?;
+                    for (i, &input_block) in child_range.blocks.iter().enumerate() {
+                        let start = estimated_rows + (i as u64) * rows_per_block;
+                        let end = (estimated_rows + (i as u64 + 1) * rows_per_block)
+                            .min(estimated_rows + child_range.estimated_rows);
+
+                        let id = BlockId::new(*next_block_id);
+                        *next_block_id += 1;
+
+                        order.push(TeBlock {
+                            id,
+                            op: *op,
+                            schema: schema.clone(),
+                            deps: vec![input_block],
+                            range_rows: Some((start, end)),
+                            partition: None,
+                            exchange: None,
+                        });
+                        blocks.push(id);
+                    }
+                    estimated_rows += child_range.estimated_rows;
+                }
+
+                Ok(BlockRange {
+                    blocks,
+                    estimated_rows,
+                })
+            }
             Sink { op, input } => {
-                let child_range = walk(input, order, next_block_id, rows_per_block, est)?;
+                let child_range = walk(
+                    input,
+                    order,
+                    next_block_id,
+                    rows_per_block,
+                    est,
+                    join_keys,
+                    shared,
+                )?;
 
                 // Sink typically processes each input block (1-to-1)
                 let mut blocks = Vec::new();
@@ -203,6 +448,8 @@ pub fn plan_te(
                         schema: Schema::new(vec![]), // sinks don't produce rows
                         deps: vec![input_block],
                         range_rows: Some((start, end)),
+                        partition: None,
+                        exchange: None,
                     });
                     blocks.push(id);
                 }
@@ -212,10 +459,20 @@ pub fn plan_te(
                     estimated_rows: child_range.estimated_rows,
                 })
             }
-        }
+        }?;
+        shared.insert(node.op(), range.clone());
+        Ok(range)
     }
 
-    let _ = walk(phys, &mut order, &mut next_block_id, b.rows_per_block, est)?;
+    let _ = walk(
+        phys,
+        &mut order,
+        &mut next_block_id,
+        b.rows_per_block,
+        est,
+        join_keys,
+        &mut HashMap::new(),
+    )?;
 
     // Compute frontier bound using the new compute_max_frontier helper
     use crate::frontier::compute_max_frontier;
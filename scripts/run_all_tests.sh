@@ -88,12 +88,26 @@ if cargo check --features parquet --no-default-features 2>/dev/null; then
 else
     echo -e "${YELLOW}Skipping Parquet/Arrow tests (feature not available)${NC}"
 fi
+if cargo check --features avro --no-default-features 2>/dev/null; then
+    run_test_suite "Avro I/O Tests" "cargo test --test avro_io_tests --features avro --no-default-features"
+else
+    echo -e "${YELLOW}Skipping Avro tests (feature not available)${NC}"
+fi
 
 # 10. CLI Tests
 echo "======== PHASE 10: CLI TESTS ========"
 run_test_suite "CLI YAML Parsing Tests" "cargo test --test cli_yaml_tests --no-default-features"
 run_test_suite "CLI Validation Tests" "cargo test --test cli_validation_tests --no-default-features"
 
+# 11. WASM Build
+echo "======== PHASE 11: WASM BUILD ========"
+if ! rustup target list --installed 2>/dev/null | grep -q wasm32-unknown-unknown; then
+    rustup target add wasm32-unknown-unknown
+fi
+run_test_suite "WASM Planner Build" "cargo check --target wasm32-unknown-unknown -p emsqrt-core -p emsqrt-te -p emsqrt-planner"
+# Catches APIs that compile for wasm32 but panic when called.
+run_test_suite "WASM Portability Tests" "cargo test --test wasm_portability_tests --no-default-features"
+
 # Summary
 echo ""
 echo "======================================"
@@ -0,0 +1,491 @@
+//! Aggregate accumulators: per-function state with SQL null semantics
+
+mod test_data_gen;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_operators::agregate::{Accumulator, AggFunc, Aggregate};
+use emsqrt_operators::Operator;
+use test_data_gen::create_temp_spill_dir;
+
+/// `(k, v)` rows; `None` is a null `v`.
+const ROWS: &[(&str, Option<i64>)] = &[
+    ("a", Some(3)),
+    ("a", None),
+    ("b", None),
+    ("a", Some(-1)),
+    ("c", Some(7)),
+    ("b", None),
+    ("a", Some(5)),
+];
+
+fn input() -> RowBatch {
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "k".into(),
+                values: ROWS
+                    .iter()
+                    .map(|(k, _)| Scalar::Str(k.to_string()))
+                    .collect(),
+            },
+            Column {
+                name: "v".into(),
+                values: ROWS
+                    .iter()
+                    .map(|(_, v)| v.map_or(Scalar::Null, Scalar::I64))
+                    .collect(),
+            },
+        ],
+    }
+}
+
+/// Naive reference: per group, (count(*), count(v), sum, min, max, avg).
+fn reference() -> BTreeMap<&'static str, [Scalar; 6]> {
+    let mut groups: BTreeMap<&str, (i64, Vec<f64>)> = BTreeMap::new();
+    for (k, v) in ROWS {
+        let entry = groups.entry(k).or_default();
+        entry.0 += 1;
+        entry.1.extend(v.map(|v| v as f64));
+    }
+    groups
+        .into_iter()
+        .map(|(k, (rows, vals))| {
+            let or_null = |v: Option<f64>| v.map_or(Scalar::Null, Scalar::F64);
+            let sum = (!vals.is_empty()).then(|| vals.iter().sum::<f64>());
+            let result = [
+                Scalar::I64(rows),
+                Scalar::I64(vals.len() as i64),
+                or_null(sum),
+                or_null(vals.iter().copied().reduce(f64::min)),
+                or_null(vals.iter().copied().reduce(f64::max)),
+                or_null(sum.map(|s| s / vals.len() as f64)),
+            ];
+            (k, result)
+        })
+        .collect()
+}
+
+#[test]
+fn test_grouped_aggregates_match_reference() {
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: ["count", "count:v", "sum:v", "min:v", "max:v", "avg:v"]
+            .map(String::from)
+            .to_vec(),
+        ..Default::default()
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let out = agg.eval_block(&[input()], &budget).unwrap();
+
+    let names: Vec<_> = out.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["k", "count", "count_v", "sum_v", "min_v", "max_v", "avg_v"]
+    );
+
+    let expected = reference();
+    assert_eq!(out.num_rows(), expected.len());
+    for row in 0..out.num_rows() {
+        let Scalar::Str(key) = &out.columns[0].values[row] else {
+            panic!("group key is not a string");
+        };
+        let actual: Vec<_> = out.columns[1..]
+            .iter()
+            .map(|c| c.values[row].clone())
+            .collect();
+        assert_eq!(actual, expected[key.as_str()], "group {}", key);
+    }
+}
+
+#[test]
+fn test_all_null_group_is_null_except_counts() {
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: ["count", "count:v", "sum:v", "avg:v"]
+            .map(String::from)
+            .to_vec(),
+        ..Default::default()
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let out = agg.eval_block(&[input()], &budget).unwrap();
+
+    // Group "b" is second in first-seen order and has only null values.
+    let b: Vec<_> = out.columns.iter().map(|c| c.values[1].clone()).collect();
+    assert_eq!(
+        b,
+        vec![
+            Scalar::Str("b".into()),
+            Scalar::I64(2),
+            Scalar::I64(0),
+            Scalar::Null,
+            Scalar::Null,
+        ]
+    );
+}
+
+#[test]
+fn test_accumulator_merge_matches_single_pass() {
+    let funcs = ["count", "count:v", "sum:v", "min:v", "max:v", "avg:v"]
+        .map(|f| AggFunc::parse(f).unwrap());
+    let values: Vec<_> = ROWS
+        .iter()
+        .map(|(_, v)| v.map_or(Scalar::Null, Scalar::I64))
+        .collect();
+
+    for func in &funcs {
+        let mut whole = Accumulator::new(func);
+        let mut left = Accumulator::new(func);
+        let mut right = Accumulator::new(func);
+        for (i, v) in values.iter().enumerate() {
+            whole.update(v).unwrap();
+            if i < 3 { &mut left } else { &mut right }
+                .update(v)
+                .unwrap();
+        }
+        left.merge(&right).unwrap();
+        assert_eq!(left.finish(), whole.finish(), "{:?}", func);
+    }
+}
+
+#[test]
+fn test_non_numeric_value_is_an_error() {
+    let mut acc = Accumulator::new(&AggFunc::parse("sum:v").unwrap());
+    assert!(acc.update(&Scalar::Str("x".into())).is_err());
+
+    // count(col) only looks at nullness, so any type is fine.
+    let mut acc = Accumulator::new(&AggFunc::parse("count:v").unwrap());
+    acc.update(&Scalar::Str("x".into())).unwrap();
+    acc.update(&Scalar::Null).unwrap();
+    assert_eq!(acc.finish(), Scalar::I64(1));
+}
+
+#[test]
+fn test_accumulator_state_round_trips() {
+    let funcs = ["count", "count:v", "sum:v", "min:v", "max:v", "avg:v"]
+        .map(|f| AggFunc::parse(f).unwrap());
+    for func in &funcs {
+        let empty = Accumulator::new(func);
+        let mut acc = Accumulator::new(func);
+        for v in [Scalar::I64(4), Scalar::Null, Scalar::I64(-2)] {
+            acc.update(&v).unwrap();
+        }
+        for acc in [empty, acc] {
+            let state = acc.state();
+            assert_eq!(state.len(), func.state_fields(0).len(), "{:?}", func);
+            let restored = Accumulator::from_state(func, &state).unwrap();
+            assert_eq!(restored.finish(), acc.finish(), "{:?}", func);
+        }
+    }
+    let avg = AggFunc::parse("avg:v").unwrap();
+    assert!(Accumulator::from_state(&avg, &[Scalar::F64(1.0)]).is_err());
+}
+
+#[test]
+fn test_sums_are_exact_for_large_integers_and_compensated_for_floats() {
+    let sum = AggFunc::parse("sum:v").unwrap();
+    let avg = AggFunc::parse("avg:v").unwrap();
+
+    // i64::MAX twice overflows an i64 and loses the +1 in a plain f64 sum.
+    let ints = [
+        Scalar::I64(i64::MAX),
+        Scalar::I64(i64::MAX),
+        Scalar::I64(-i64::MAX),
+    ];
+    let mut acc = Accumulator::new(&sum);
+    for v in &ints {
+        acc.update(v).unwrap();
+    }
+    assert_eq!(acc.finish(), Scalar::F64(i64::MAX as f64));
+    let restored = Accumulator::from_state(&sum, &acc.state()).unwrap();
+    assert_eq!(restored, acc);
+
+    // 1e16 + 1.0 + ... + 1.0 - 1e16: naive summation drops every 1.0.
+    let mut floats = vec![Scalar::F64(1e16)];
+    floats.extend((0..1000).map(|_| Scalar::F64(1.0)));
+    floats.push(Scalar::F64(-1e16));
+    let mut acc = Accumulator::new(&sum);
+    let mut left = Accumulator::new(&avg);
+    let mut right = Accumulator::new(&avg);
+    for (i, v) in floats.iter().enumerate() {
+        acc.update(v).unwrap();
+        if i % 2 == 0 { &mut left } else { &mut right }
+            .update(v)
+            .unwrap();
+    }
+    assert_eq!(acc.finish(), Scalar::F64(1000.0));
+    left.merge(&Accumulator::from_state(&avg, &right.state()).unwrap())
+        .unwrap();
+    assert_eq!(left.finish(), Scalar::F64(1000.0 / 1002.0));
+
+    // Non-finite inputs still propagate.
+    let mut acc = Accumulator::new(&sum);
+    acc.update(&Scalar::F64(f64::INFINITY)).unwrap();
+    acc.update(&Scalar::I64(1)).unwrap();
+    assert_eq!(acc.finish(), Scalar::F64(f64::INFINITY));
+}
+
+#[test]
+fn test_decimal_sums_and_averages_are_exact() {
+    let sum = AggFunc::parse("sum:v").unwrap();
+    let avg = AggFunc::parse("avg:v").unwrap();
+
+    // 0.1 + 0.2 + 0.25 + 2, split across a spilled partial state.
+    let values = [
+        Scalar::Decimal128(1, 1),
+        Scalar::Decimal128(2, 1),
+        Scalar::Null,
+        Scalar::Decimal128(25, 2),
+        Scalar::I64(2),
+    ];
+    for (func, expected) in [
+        (&sum, Scalar::Decimal128(255, 2)),
+        // 2.55 / 4, rounded half away from zero at six places.
+        (&avg, Scalar::Decimal128(637_500, 6)),
+    ] {
+        let mut left = Accumulator::new(func);
+        let mut right = Accumulator::new(func);
+        for (i, v) in values.iter().enumerate() {
+            if i < 2 { &mut left } else { &mut right }
+                .update(v)
+                .unwrap();
+        }
+        let right = Accumulator::from_state(func, &right.state()).unwrap();
+        left.merge(&right).unwrap();
+        assert_eq!(left.finish(), expected, "{:?}", func);
+    }
+
+    // A float among the decimals gives a float result.
+    let mut acc = Accumulator::new(&sum);
+    acc.update(&Scalar::Decimal128(15, 1)).unwrap();
+    acc.update(&Scalar::F64(0.5)).unwrap();
+    assert_eq!(acc.finish(), Scalar::F64(2.0));
+
+    let mut acc = Accumulator::new(&sum);
+    acc.update(&Scalar::Decimal128(i128::MAX, 0)).unwrap();
+    assert!(acc.update(&Scalar::Decimal128(1, 0)).is_err());
+}
+
+#[test]
+fn test_min_max_keep_decimal_and_temporal_values() {
+    let min = AggFunc::parse("min:v").unwrap();
+    let max = AggFunc::parse("max:v").unwrap();
+    let cases = [
+        (
+            vec![
+                Scalar::Decimal128(15, 1),
+                Scalar::Null,
+                Scalar::Decimal128(149, 2),
+                Scalar::Decimal128(-3, 0),
+                Scalar::Decimal128(1500, 3),
+            ],
+            Scalar::Decimal128(-3, 0),
+            Scalar::Decimal128(15, 1),
+        ),
+        (
+            vec![
+                Scalar::Date64(86_400_000),
+                Scalar::Date64(0),
+                Scalar::Date64(-86_400_000),
+            ],
+            Scalar::Date64(-86_400_000),
+            Scalar::Date64(86_400_000),
+        ),
+        (
+            vec![
+                Scalar::Timestamp(1_000_000),
+                Scalar::Timestamp(5),
+                Scalar::Timestamp(2_000_000),
+            ],
+            Scalar::Timestamp(5),
+            Scalar::Timestamp(2_000_000),
+        ),
+    ];
+    for (values, low, high) in cases {
+        for (func, expected) in [(&min, &low), (&max, &high)] {
+            let mut whole = Accumulator::new(func);
+            let mut left = Accumulator::new(func);
+            let mut right = Accumulator::new(func);
+            for (i, v) in values.iter().enumerate() {
+                whole.update(v).unwrap();
+                if i % 2 == 0 { &mut left } else { &mut right }
+                    .update(v)
+                    .unwrap();
+            }
+            assert_eq!(&whole.finish(), expected, "{:?} of {:?}", func, values);
+            let right = Accumulator::from_state(func, &right.state()).unwrap();
+            left.merge(&right).unwrap();
+            assert_eq!(&left.finish(), expected, "{:?} of {:?}", func, values);
+        }
+    }
+
+    // Numbers still come out as F64.
+    let mut acc = Accumulator::new(&max);
+    acc.update(&Scalar::I32(3)).unwrap();
+    acc.update(&Scalar::F64(2.5)).unwrap();
+    assert_eq!(acc.finish(), Scalar::F64(3.0));
+    assert!(acc.update(&Scalar::Bool(true)).is_err());
+}
+
+#[test]
+fn test_output_types_follow_the_input_column() {
+    let input = Schema::new(vec![
+        Field::new("k", DataType::Utf8, false),
+        Field::new("d", DataType::Decimal128, true),
+        Field::new("t", DataType::Date64, true),
+        Field::new("n", DataType::Int64, true),
+    ]);
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: [
+            "count:d", "sum:d", "avg:d", "min:d", "max:t", "sum:n", "min:n",
+        ]
+        .map(String::from)
+        .to_vec(),
+        ..Default::default()
+    };
+    let plan = agg.plan(&[input]).unwrap();
+    let types: Vec<_> = plan.output_schema.fields[1..]
+        .iter()
+        .map(|f| f.data_type.clone())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            DataType::Int64,
+            DataType::Decimal128,
+            DataType::Decimal128,
+            DataType::Decimal128,
+            DataType::Date64,
+            DataType::Float64,
+            DataType::Float64,
+        ]
+    );
+}
+
+/// Aggregate 2000 rows over 500 groups of `(k, v)`.
+fn many_groups(agg: &Aggregate, budget_bytes: usize) -> BTreeMap<i64, Vec<Scalar>> {
+    let input = RowBatch {
+        columns: vec![
+            Column {
+                name: "k".into(),
+                values: (0..2000).map(|i| Scalar::I64(i % 500)).collect(),
+            },
+            Column {
+                name: "v".into(),
+                values: (0..2000)
+                    .map(|i| {
+                        if i % 7 == 0 {
+                            Scalar::Null
+                        } else {
+                            Scalar::I64(i)
+                        }
+                    })
+                    .collect(),
+            },
+        ],
+    };
+    let budget = MemoryBudgetImpl::new(budget_bytes);
+    let out = agg.eval_block(&[input], &budget).unwrap();
+    assert_eq!(out.num_rows(), 500);
+    (0..out.num_rows())
+        .map(|row| {
+            let Scalar::I64(k) = out.columns[0].values[row] else {
+                panic!("group key is not an integer");
+            };
+            let rest = out.columns[1..].iter().map(|c| c.values[row].clone());
+            (k, rest.collect())
+        })
+        .collect()
+}
+
+#[test]
+fn test_spilled_partial_states_merge_to_the_in_memory_result() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let spill_mgr = Arc::new(Mutex::new(mgr));
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: ["count", "count:v", "sum:v", "min:v", "max:v", "avg:v"]
+            .map(String::from)
+            .to_vec(),
+        spill_mgr: Some(Arc::clone(&spill_mgr)),
+        ..Default::default()
+    };
+
+    let roomy = many_groups(&agg, 1 << 24);
+    assert_eq!(spill_mgr.lock().unwrap().bytes_written(), 0);
+    // A few KiB holds only a few dozen groups at a time.
+    let tight = many_groups(&agg, 4 << 10);
+    assert!(spill_mgr.lock().unwrap().bytes_written() > 0);
+    assert!(spill_mgr.lock().unwrap().list_segments().is_empty());
+    assert_eq!(tight, roomy);
+
+    let simple = Aggregate {
+        spill_mgr: None,
+        ..agg
+    };
+    assert_eq!(many_groups(&simple, 1 << 24), roomy);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_high_cardinality_merge_repartitions_under_the_budget() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let spill_mgr = Arc::new(Mutex::new(mgr));
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: vec!["count".into(), "sum:v".into()],
+        spill_mgr: Some(Arc::clone(&spill_mgr)),
+        ..Default::default()
+    };
+    // 4000 groups; a partition's share (~250) is still far over the ~35
+    // groups half of 16 KiB holds, so each is split again while merging.
+    let input = RowBatch {
+        columns: vec![
+            Column {
+                name: "k".into(),
+                values: (0..8000).map(|i| Scalar::I64(i % 4000)).collect(),
+            },
+            Column {
+                name: "v".into(),
+                values: (0..8000).map(Scalar::I64).collect(),
+            },
+        ],
+    };
+    let out = agg
+        .eval_block(&[input], &MemoryBudgetImpl::new(16 << 10))
+        .unwrap();
+    assert!(spill_mgr.lock().unwrap().bytes_written() > 0);
+    assert!(spill_mgr.lock().unwrap().list_segments().is_empty());
+
+    assert_eq!(out.num_rows(), 4000);
+    let mut rows: Vec<(Scalar, Scalar, Scalar)> = (0..out.num_rows())
+        .map(|r| {
+            let value = |c: usize| out.columns[c].values[r].clone();
+            (value(0), value(1), value(2))
+        })
+        .collect();
+    rows.sort_by_key(|(k, _, _)| match k {
+        Scalar::I64(k) => *k,
+        _ => panic!("group key is not an integer"),
+    });
+    for (k, (key, count, sum)) in rows.into_iter().enumerate() {
+        assert_eq!(key, Scalar::I64(k as i64));
+        assert_eq!(count, Scalar::I64(2));
+        assert_eq!(sum, Scalar::F64((2 * k + 4000) as f64));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
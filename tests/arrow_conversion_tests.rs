@@ -325,6 +325,49 @@ fn test_emsqrt_to_arrow_schema() {
     assert!(arrow_schema.field(1).is_nullable());
 }
 
+#[cfg(feature = "parquet")]
+#[test]
+fn test_date_timestamp_decimal_round_trip() {
+    let row_batch = RowBatch {
+        columns: vec![
+            Column {
+                name: "day".to_string(),
+                values: vec![Scalar::Date64(19_783 * 86_400_000), Scalar::Null],
+            },
+            Column {
+                name: "ts".to_string(),
+                values: vec![Scalar::Timestamp(1_709_296_200_250_000), Scalar::Null],
+            },
+            Column {
+                name: "amount".to_string(),
+                values: vec![Scalar::Decimal128(-1250, 2), Scalar::Decimal128(7, 0)],
+            },
+        ],
+    };
+    let schema = Arc::new(emsqrt_to_arrow_schema(&Schema::new(vec![
+        Field::new("day", DataType::Date64, true),
+        Field::new("ts", DataType::Timestamp, true),
+        Field::new("amount", DataType::Decimal128, false),
+    ])));
+    assert_eq!(
+        schema.field(2).data_type(),
+        &ArrowDataType::Decimal128(38, 10)
+    );
+
+    let record_batch = row_batch_to_record_batch(&row_batch, schema).unwrap();
+    let back = record_batch_to_row_batch(&record_batch).unwrap();
+    assert_eq!(back.columns[0].values, row_batch.columns[0].values);
+    assert_eq!(back.columns[1].values, row_batch.columns[1].values);
+    // Decimals come back at the column's scale, equal in value.
+    assert_eq!(
+        back.columns[2].values,
+        vec![
+            Scalar::Decimal128(-125_000_000_000, 10),
+            Scalar::Decimal128(70_000_000_000, 10),
+        ]
+    );
+}
+
 #[cfg(not(feature = "parquet"))]
 #[test]
 fn test_parquet_feature_required() {
@@ -0,0 +1,132 @@
+//! Per-block audit logs of input and output hashes (`EngineConfig::audit_path`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::audit::{first_divergence, read_audit, AuditRun, BatchDigest, BlockAudit};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn ids(name: &str, values: &[i64]) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: name.to_string(),
+            values: values.iter().copied().map(Scalar::I64).collect(),
+        }],
+    }
+}
+
+#[test]
+fn test_batch_digest_covers_names_and_values() {
+    let digest = BatchDigest::of(&ids("id", &[1, 2, 3]));
+    assert_eq!(digest.rows, 3);
+    assert_eq!(digest.hash.len(), 64);
+    assert_eq!(digest, BatchDigest::of(&ids("id", &[1, 2, 3])));
+    assert_ne!(digest, BatchDigest::of(&ids("id", &[1, 2, 4])));
+    assert_ne!(digest, BatchDigest::of(&ids("id", &[3, 2, 1])));
+    assert_ne!(digest, BatchDigest::of(&ids("key", &[1, 2, 3])));
+}
+
+fn block(block_id: u64, input: &[i64], output: &[i64]) -> BlockAudit {
+    BlockAudit {
+        block_id,
+        op_id: block_id,
+        op: "filter".to_string(),
+        inputs: vec![BatchDigest::of(&ids("id", input))],
+        output: BatchDigest::of(&ids("id", output)),
+    }
+}
+
+fn audit_run(blocks: Vec<BlockAudit>) -> AuditRun {
+    AuditRun {
+        run_id: "run".to_string(),
+        plan_hash: "plan".to_string(),
+        te_hash: "te".to_string(),
+        started_ms: 0,
+        blocks,
+    }
+}
+
+#[test]
+fn test_first_divergence_names_the_block_and_batch() {
+    let base = audit_run(vec![block(0, &[1, 2], &[2]), block(1, &[2], &[2])]);
+    assert_eq!(first_divergence(&base, &base.clone()), None);
+
+    // Same inputs, different output: the operator at block 0 diverged.
+    let other = audit_run(vec![block(0, &[1, 2], &[1, 2]), block(1, &[1, 2], &[1, 2])]);
+    let d = first_divergence(&base, &other).unwrap();
+    assert_eq!((d.index, d.block_id), (0, 0));
+    assert_eq!(d.reason, "output has 1 vs 2 rows");
+
+    let other = audit_run(vec![block(0, &[1, 2], &[2]), block(1, &[3], &[2])]);
+    let d = first_divergence(&base, &other).unwrap();
+    assert_eq!(d.block_id, 1);
+    assert_eq!(d.reason, "input 0 hashes differ (1 rows)");
+
+    let short = audit_run(vec![block(0, &[1, 2], &[2])]);
+    let d = first_divergence(&base, &short).unwrap();
+    assert_eq!((d.index, d.block_id), (1, 1));
+    assert!(d.reason.contains("stops after 1 blocks"), "{}", d.reason);
+}
+
+fn run(dir: &str, audit: &str) -> usize {
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+  - op: filter
+    expr: "id > 2"
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    );
+    let plan = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        audit_path: Some(audit.to_string()),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    te.order.len()
+}
+
+#[test]
+fn test_runs_append_to_the_audit_and_replays_match() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let audit = format!("{}/audit.jsonl", dir);
+    fs::write(format!("{}/in.csv", dir), "id\n1\n2\n3\n4\n").unwrap();
+
+    let blocks = run(&dir, &audit);
+    run(&dir, &audit);
+    let runs = read_audit(&audit).unwrap();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0].blocks.len(), blocks);
+    assert_eq!(runs[0].plan_hash, runs[1].plan_hash);
+    assert_ne!(runs[0].run_id, runs[1].run_id);
+    let filter = runs[0].blocks.iter().find(|b| b.op == "filter").unwrap();
+    assert_eq!(filter.inputs[0].rows, 4);
+    assert_eq!(filter.output.rows, 2);
+    assert_eq!(first_divergence(&runs[0], &runs[1]), None);
+
+    // Changed source data shows up at the first block that read it.
+    fs::write(format!("{}/in.csv", dir), "id\n1\n2\n3\n5\n").unwrap();
+    run(&dir, &audit);
+    let runs = read_audit(&audit).unwrap();
+    let d = first_divergence(&runs[0], &runs[2]).unwrap();
+    assert_eq!(d.op, "source");
+    assert_eq!(d.reason, "output hashes differ (4 rows)");
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,269 @@
+//! Avro object container files: reader, writer, and `.avro` sources/sinks
+#![cfg(feature = "avro")]
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::avro_convert::{write_bytes, write_header, write_long};
+use emsqrt_io::readers::avro::AvroReader;
+use emsqrt_io::writers::avro::{AvroCodec, AvroWriter};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("score", DataType::Float64, true),
+        Field::new("ok", DataType::Boolean, false),
+        Field::new("raw", DataType::Binary, true),
+    ])
+}
+
+fn batch(ids: std::ops::Range<i64>) -> RowBatch {
+    let col = |name: &str, f: &dyn Fn(i64) -> Scalar| Column {
+        name: name.into(),
+        values: ids.clone().map(f).collect(),
+    };
+    RowBatch {
+        columns: vec![
+            col("id", &Scalar::I64),
+            col("name", &|i| {
+                if i % 3 == 0 {
+                    Scalar::Null
+                } else {
+                    Scalar::Str(format!("n{}", i))
+                }
+            }),
+            col("score", &|i| Scalar::F64(i as f64 / 2.0)),
+            col("ok", &|i| Scalar::Bool(i % 2 == 0)),
+            col("raw", &|i| Scalar::Bin(vec![i as u8; 2])),
+        ],
+    }
+}
+
+#[test]
+fn test_round_trip_across_blocks_and_codecs() {
+    for codec in [AvroCodec::Null, AvroCodec::Deflate] {
+        let mut buf = Vec::new();
+        let mut writer = AvroWriter::to_writer(&mut buf, &schema(), codec).unwrap();
+        writer.write_batch(&batch(0..5)).unwrap();
+        writer.write_batch(&batch(5..5)).unwrap();
+        writer.write_batch(&batch(5..9)).unwrap();
+        writer.close().unwrap();
+
+        let mut reader = AvroReader::from_reader(buf.as_slice(), None).unwrap();
+        assert_eq!(reader.schema(), &schema());
+        // Batches of 4 span the 5-row and 4-row blocks.
+        let mut sizes = Vec::new();
+        let mut ids = Vec::new();
+        let mut names = Vec::new();
+        while let Some(b) = reader.next_batch(4).unwrap() {
+            sizes.push(b.num_rows());
+            ids.extend(b.columns[0].values.clone());
+            names.extend(b.columns[1].values.clone());
+        }
+        assert_eq!(sizes, [4, 4, 1]);
+        assert_eq!(ids, batch(0..9).columns[0].values);
+        assert_eq!(names, batch(0..9).columns[1].values);
+    }
+}
+
+#[test]
+fn test_projection_selects_and_orders_columns() {
+    let mut buf = Vec::new();
+    let mut writer = AvroWriter::to_writer(&mut buf, &schema(), AvroCodec::Null).unwrap();
+    writer.write_batch(&batch(0..3)).unwrap();
+    writer.close().unwrap();
+
+    let projection = Some(vec!["ok".to_string(), "id".to_string()]);
+    let mut reader = AvroReader::from_reader(buf.as_slice(), projection).unwrap();
+    let b = reader.next_batch(10).unwrap().unwrap();
+    let names: Vec<&str> = b.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["ok", "id"]);
+    assert_eq!(
+        b.columns[0].values,
+        [Scalar::Bool(true), Scalar::Bool(false), Scalar::Bool(true)]
+    );
+    assert!(reader.next_batch(10).unwrap().is_none());
+
+    let missing = Some(vec!["nope".to_string()]);
+    let err = AvroReader::from_reader(buf.as_slice(), missing)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("missing required column 'nope'"), "{}", err);
+}
+
+#[test]
+fn test_reads_enums_fixed_logical_types_and_union_order() {
+    // As another Avro implementation might write it.
+    let schema = r#"{"type":"record","name":"Event","fields":[
+        {"name":"kind","type":{"type":"enum","name":"Kind","symbols":["CLICK","VIEW"]}},
+        {"name":"at","type":{"type":"long","logicalType":"timestamp-millis"}},
+        {"name":"key","type":{"type":"fixed","name":"Key","size":2}},
+        {"name":"note","type":["string","null"]}]}"#;
+    let sync = [7u8; 16];
+    let mut file = Vec::new();
+    write_header(&mut file, schema, "null", &sync).unwrap();
+    let mut data = Vec::new();
+    for (kind, at, note) in [(1, -5i64, Some("hi")), (0, 1_700_000_000_000, None)] {
+        write_long(&mut data, kind);
+        write_long(&mut data, at);
+        data.extend_from_slice(b"ab");
+        match note {
+            Some(n) => {
+                write_long(&mut data, 0);
+                write_bytes(&mut data, n.as_bytes());
+            }
+            None => write_long(&mut data, 1),
+        }
+    }
+    write_long(&mut file, 2);
+    write_bytes(&mut file, &data);
+    file.extend_from_slice(&sync);
+
+    let mut reader = AvroReader::from_reader(file.as_slice(), None).unwrap();
+    let types: Vec<_> = reader
+        .schema()
+        .fields
+        .iter()
+        .map(|f| (f.data_type.clone(), f.nullable))
+        .collect();
+    assert_eq!(
+        types,
+        [
+            (DataType::Utf8, false),
+            (DataType::Int64, false),
+            (DataType::Binary, false),
+            (DataType::Utf8, true),
+        ]
+    );
+    let b = reader.next_batch(10).unwrap().unwrap();
+    assert_eq!(
+        b.columns[0].values,
+        [Scalar::Str("VIEW".into()), Scalar::Str("CLICK".into())]
+    );
+    assert_eq!(
+        b.columns[1].values,
+        [Scalar::I64(-5), Scalar::I64(1_700_000_000_000)]
+    );
+    assert_eq!(b.columns[2].values[0], Scalar::Bin(b"ab".to_vec()));
+    assert_eq!(
+        b.columns[3].values,
+        [Scalar::Str("hi".into()), Scalar::Null]
+    );
+
+    // A corrupted sync marker is reported rather than misread.
+    let len = file.len();
+    file[len - 1] ^= 1;
+    let mut reader = AvroReader::from_reader(file.as_slice(), None).unwrap();
+    assert!(reader.next_batch(10).is_err());
+}
+
+#[test]
+fn test_unsupported_files_and_values_are_errors() {
+    let header = |schema: &str, codec: &str| {
+        let mut file = Vec::new();
+        write_header(&mut file, schema, codec, &[0u8; 16]).unwrap();
+        AvroReader::from_reader(file.as_slice(), None)
+            .err()
+            .map(|e| e.to_string())
+    };
+    let flat = r#"{"type":"record","name":"R","fields":[{"name":"a","type":"int"}]}"#;
+    assert!(header(flat, "null").is_none());
+    assert!(header(flat, "snappy")
+        .unwrap()
+        .contains("unsupported avro codec 'snappy'"));
+    let nested = r#"{"type":"record","name":"R","fields":[{"name":"a","type":{"type":"array","items":"int"}}]}"#;
+    assert!(header(nested, "null").unwrap().contains("avro field 'a'"));
+    let wide = r#"{"type":"record","name":"R","fields":[{"name":"a","type":["int","string"]}]}"#;
+    assert!(header(wide, "null")
+        .unwrap()
+        .contains("more than one non-null type"));
+    assert!(AvroReader::from_reader(&b"PAR1"[..], None).is_err());
+
+    let strict = Schema::new(vec![Field::new("a", DataType::Int64, false)]);
+    let mut writer = AvroWriter::to_writer(Vec::new(), &strict, AvroCodec::Null).unwrap();
+    let nulls = RowBatch {
+        columns: vec![Column {
+            name: "a".into(),
+            values: vec![Scalar::Null],
+        }],
+    };
+    let err = writer.write_batch(&nulls).unwrap_err().to_string();
+    assert!(err.contains("non-nullable avro column 'a'"), "{}", err);
+}
+
+#[test]
+fn test_pipeline_writes_and_scans_avro() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id,name\n1,a\n2,\n3,c\n4,d\n5,e\n").unwrap();
+    let avro = format!("{}/out.avro", dir);
+    let output = format!("{}/out.jsonl", dir);
+    let run = |yaml: String| {
+        let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+        let program = lower_to_physical(&lp);
+        let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            ..Default::default()
+        };
+        Engine::new(config).unwrap().run(&program, &te).unwrap();
+    };
+
+    run(format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+  - op: filter
+    expr: "id > 1"
+  - op: sink
+    destination: "{avro}"
+    format: "avro"
+"#
+    ));
+    assert!(fs::read(&avro).unwrap().starts_with(b"Obj\x01"));
+
+    run(format!(
+        r#"
+steps:
+  - op: scan
+    source: "{avro}"
+    schema:
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "id", type: "Int64" }}
+  - op: sink
+    destination: "{output}"
+    format: "jsonl"
+"#
+    ));
+    let lines: Vec<String> = fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            r#"{"id":2,"name":""}"#,
+            r#"{"id":3,"name":"c"}"#,
+            r#"{"id":4,"name":"d"}"#,
+            r#"{"id":5,"name":"e"}"#,
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,155 @@
+//! Column-at-a-time expression evaluation (`evaluate_batch`, `evaluate_selection`)
+
+use emsqrt_core::expr::{ArithErrorPolicy, EvalContext, Expr};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::filter::Filter;
+use emsqrt_operators::Operator;
+
+fn batch() -> RowBatch {
+    let column = |name: &str, values: Vec<Scalar>| Column {
+        name: name.into(),
+        values,
+    };
+    RowBatch {
+        columns: vec![
+            column(
+                "a",
+                vec![
+                    Scalar::I32(6),
+                    Scalar::I32(0),
+                    Scalar::Null,
+                    Scalar::I32(-3),
+                    Scalar::I32(9),
+                ],
+            ),
+            column(
+                "b",
+                vec![
+                    Scalar::I64(2),
+                    Scalar::I64(0),
+                    Scalar::I64(5),
+                    Scalar::I64(0),
+                    Scalar::I64(3),
+                ],
+            ),
+            column(
+                "kind",
+                ["x", "y", "x", "", "z"]
+                    .iter()
+                    .map(|s| Scalar::Str(s.to_string()))
+                    .collect(),
+            ),
+            column(
+                "price",
+                vec![
+                    Scalar::F64(1.5),
+                    Scalar::F64(2.0),
+                    Scalar::Null,
+                    Scalar::F64(0.25),
+                    Scalar::F64(10.0),
+                ],
+            ),
+        ],
+    }
+}
+
+#[test]
+fn test_batch_results_match_row_evaluation() {
+    let batch = batch();
+    let ctx = EvalContext {
+        seed: 7,
+        row_base: 100,
+        arith_errors: ArithErrorPolicy::Null,
+    };
+    for source in [
+        "a",
+        "42",
+        "a + b * 2",
+        "price * a - 1",
+        "a / b",
+        "safe_div(a, b) + nullif(b, 0)",
+        "hash(kind)",
+        "rand()",
+        "a IS NULL OR kind == 'x'",
+        "NOT (a > 0 AND b > 0)",
+        "a BETWEEN 0 AND 6",
+        "kind NOT IN ('x', 'z')",
+        "sample_hash(kind, 0.5)",
+        "kind",
+    ] {
+        let expr = Expr::parse(source).unwrap();
+        let rows: Vec<Scalar> = (0..batch.num_rows())
+            .map(|row| expr.evaluate_with(&batch, row, &ctx).unwrap())
+            .collect();
+        let column = expr.evaluate_batch_with(&batch, &ctx).unwrap();
+        assert_eq!(column.values, rows, "{}", source);
+
+        let passes: Vec<bool> = (0..batch.num_rows())
+            .map(|row| expr.evaluate_bool_with(&batch, row, &ctx).unwrap())
+            .collect();
+        assert_eq!(
+            expr.evaluate_selection(&batch, &ctx).unwrap(),
+            passes,
+            "{}",
+            source
+        );
+    }
+
+    let column = Expr::parse("b").unwrap().evaluate_batch(&batch).unwrap();
+    assert_eq!(column.name, "b");
+    assert_eq!(column.values, batch.columns[1].values);
+}
+
+#[test]
+fn test_selection_short_circuits_per_row() {
+    let batch = batch();
+    let ctx = EvalContext::default();
+    // Division by zero fails under the default policy, but only rows the
+    // guard lets through are divided.
+    let guarded = Expr::parse("b != 0 AND a / b > 1").unwrap();
+    assert_eq!(
+        guarded.evaluate_selection(&batch, &ctx).unwrap(),
+        [true, false, false, false, true]
+    );
+    let guarded = Expr::parse("b == 0 OR a / b > 1").unwrap();
+    assert_eq!(
+        guarded.evaluate_selection(&batch, &ctx).unwrap(),
+        [true, true, false, true, true]
+    );
+    // IN stops at the first matching item.
+    let guarded = Expr::parse("b IN (0, a / b)").unwrap();
+    assert!(guarded.evaluate_selection(&batch, &ctx).is_ok());
+
+    let unguarded = Expr::parse("a / b > 1").unwrap();
+    assert_eq!(
+        unguarded.evaluate_selection(&batch, &ctx).unwrap_err(),
+        "at row 1: division by zero"
+    );
+}
+
+#[test]
+fn test_filter_keeps_selected_rows_and_names_failing_rows() {
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let filter = Filter {
+        expr: Some("kind == 'x' OR price > 5".into()),
+        ..Default::default()
+    };
+    let out = filter.eval_block(&[batch()], &budget).unwrap();
+    assert_eq!(
+        out.columns[0].values,
+        [Scalar::I32(6), Scalar::Null, Scalar::I32(9)]
+    );
+
+    let missing = Filter {
+        expr: Some("a > 1 AND nope == 2".into()),
+        ..Default::default()
+    };
+    let err = missing.eval_block(&[batch()], &budget).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("expression evaluation failed at row 0: column 'nope' not found"),
+        "{}",
+        err
+    );
+}
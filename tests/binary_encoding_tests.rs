@@ -0,0 +1,172 @@
+//! Binary values in text formats (`EngineConfig::binary_encoding`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::binary::BinaryEncoding;
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::{Engine, ExecError, MemTables};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_encodings_round_trip() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    for enc in [BinaryEncoding::Base64, BinaryEncoding::Hex] {
+        assert_eq!(enc.decode(&enc.encode(&bytes)).unwrap(), bytes, "{:?}", enc);
+        assert_eq!(enc.decode("").unwrap(), Vec::<u8>::new());
+    }
+    assert_eq!(BinaryEncoding::Base64.encode(b"\x00\xffhi"), "AP9oaQ==");
+    assert_eq!(BinaryEncoding::Hex.encode(b"\x00\xffhi"), "00ff6869");
+    assert_eq!(BinaryEncoding::Hex.decode("00FF").unwrap(), [0, 255]);
+
+    assert!(BinaryEncoding::Base64.decode("not base64!").is_err());
+    assert!(BinaryEncoding::Hex.decode("abc").is_err());
+    assert!(BinaryEncoding::Hex.decode("zz").is_err());
+    assert_eq!(BinaryEncoding::parse("HEX"), Some(BinaryEncoding::Hex));
+    assert_eq!(BinaryEncoding::parse("utf8"), None);
+}
+
+#[test]
+fn test_hex_sorts_like_the_bytes() {
+    let mut values: Vec<Vec<u8>> = vec![vec![0xff], vec![0x00, 0x01], vec![0x10], vec![0x00]];
+    let mut encoded: Vec<String> = values
+        .iter()
+        .map(|v| BinaryEncoding::Hex.encode(v))
+        .collect();
+    values.sort();
+    encoded.sort();
+    let decoded: Vec<Vec<u8>> = encoded
+        .iter()
+        .map(|e| BinaryEncoding::Hex.decode(e).unwrap())
+        .collect();
+    assert_eq!(decoded, values);
+}
+
+fn run(
+    dir: &str,
+    input: &str,
+    destination: &str,
+    format: &str,
+    binary: BinaryEncoding,
+) -> Result<MemTables, ExecError> {
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    on_parse_error: fail
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "payload", type: "Binary", nullable: true }}
+  - op: sink
+    destination: "{destination}"
+    format: "{format}"
+"#
+    );
+    let plan = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        binary_encoding: binary,
+        ..Default::default()
+    };
+    let tables = MemTables::new();
+    Engine::new(config)?
+        .with_mem_tables(tables.clone())
+        .run(&program, &te)?;
+    Ok(tables)
+}
+
+#[test]
+fn test_csv_and_jsonl_keep_binary_bytes() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id,payload\n1,AP9oaQ==\n2,\n3,\n").unwrap();
+
+    // Base64 cells decode to the bytes; empty cells are null.
+    let tables = run(&dir, &input, "mem://out", "csv", BinaryEncoding::Base64).unwrap();
+    let out = tables.get("out").unwrap();
+    let payload: Vec<Scalar> = out
+        .iter()
+        .flat_map(|b| b.columns[1].values.clone())
+        .collect();
+    assert_eq!(
+        payload,
+        [
+            Scalar::Bin(b"\x00\xffhi".to_vec()),
+            Scalar::Null,
+            Scalar::Null
+        ]
+    );
+
+    // Written back out, the encoded text survives both formats.
+    let csv_out = format!("{}/out.csv", dir);
+    run(&dir, &input, &csv_out, "csv", BinaryEncoding::Base64).unwrap();
+    assert_eq!(
+        fs::read_to_string(&csv_out).unwrap(),
+        "id,payload\n1,AP9oaQ==\n2,\n3,\n"
+    );
+    let jsonl_out = format!("{}/out.jsonl", dir);
+    run(&dir, &csv_out, &jsonl_out, "jsonl", BinaryEncoding::Base64).unwrap();
+    let first = fs::read_to_string(&jsonl_out).unwrap();
+    assert!(
+        first.starts_with(r#"{"id":1,"payload":"AP9oaQ=="}"#),
+        "{}",
+        first
+    );
+    let roundtrip = format!("{}/roundtrip.csv", dir);
+    run(&dir, &jsonl_out, &roundtrip, "csv", BinaryEncoding::Base64).unwrap();
+    assert_eq!(
+        fs::read_to_string(&roundtrip).unwrap(),
+        "id,payload\n1,AP9oaQ==\n2,\n3,\n"
+    );
+
+    // Hex reads and writes hex.
+    let hex_in = format!("{}/hex.csv", dir);
+    fs::write(&hex_in, "id,payload\n1,00FF6869\n").unwrap();
+    run(&dir, &hex_in, &csv_out, "csv", BinaryEncoding::Hex).unwrap();
+    assert_eq!(
+        fs::read_to_string(&csv_out).unwrap(),
+        "id,payload\n1,00ff6869\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_invalid_binary_text_is_a_parse_error() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.jsonl", dir);
+    fs::write(&input, "{\"id\":1,\"payload\":\"xyz\"}\n").unwrap();
+    let err = run(&dir, &input, "mem://out", "csv", BinaryEncoding::Hex).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("column 'payload' value \"xyz\""), "{}", msg);
+    assert!(msg.contains("invalid hex"), "{}", msg);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_binary_encoding_parses_from_pipeline_config() {
+    let yaml = r#"
+config:
+  binary_encoding: hex
+steps:
+  - op: scan
+    source: "data.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let config = parse_yaml_pipeline(yaml).unwrap().config;
+    assert_eq!(config.binary_encoding, Some(BinaryEncoding::Hex));
+}
@@ -0,0 +1,65 @@
+//! Canonical plan hashing (order- and number-normalized)
+
+use std::collections::HashMap;
+
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::hash::{hash_canonical, hash_serde};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::replay::{hash_program, hash_te};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use serde_json::json;
+
+#[test]
+fn test_map_order_does_not_change_hash() {
+    let mut a: HashMap<String, u64> = HashMap::new();
+    let mut b: HashMap<String, u64> = HashMap::with_capacity(1024);
+    for i in 0..64 {
+        a.insert(format!("k{}", i), i);
+    }
+    for i in (0..64).rev() {
+        b.insert(format!("k{}", i), i);
+    }
+    assert_eq!(hash_canonical(&a).unwrap(), hash_canonical(&b).unwrap());
+}
+
+#[test]
+fn test_numbers_are_normalized() {
+    let h = |v: serde_json::Value| hash_canonical(&v).unwrap();
+    assert_eq!(h(json!({"rows": 2.0})), h(json!({"rows": 2})));
+    assert_eq!(h(json!(-0.0)), h(json!(0)));
+    assert_eq!(h(json!(u64::MAX)), h(json!(u64::MAX)));
+    assert_ne!(h(json!(0.5)), h(json!(0.25)));
+    assert_ne!(h(json!(1)), h(json!("1")));
+}
+
+#[test]
+fn test_structure_is_unambiguous() {
+    let h = |v: serde_json::Value| hash_canonical(&v).unwrap();
+    assert_ne!(h(json!(["ab", "c"])), h(json!(["a", "bc"])));
+    assert_ne!(h(json!([[1], 2])), h(json!([1, [2]])));
+    assert_ne!(h(json!({"a": null})), h(json!({})));
+}
+
+#[test]
+fn test_canonical_hash_is_versioned_apart_from_json_hash() {
+    let v = json!({"a": 1});
+    assert_ne!(hash_canonical(&v).unwrap(), hash_serde(&v).unwrap());
+}
+
+#[test]
+fn test_program_and_te_hashes_are_reproducible() {
+    let lp = L::Filter {
+        input: Box::new(L::Scan {
+            source: "data.csv".into(),
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        expr: "id > 3".into(),
+    };
+    let plan = |lp: &L| {
+        let program = lower_to_physical(lp);
+        let te = plan_te(&program.plan, &estimate_work(lp, None), 1 << 20).unwrap();
+        (hash_program(&program).unwrap(), hash_te(&te).unwrap())
+    };
+    assert_eq!(plan(&lp), plan(&lp));
+}
@@ -0,0 +1,156 @@
+//! Hash co-partitioning of join blocks planned by TE
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Write;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::Engine;
+use emsqrt_planner::lower_to_physical;
+use emsqrt_te::{plan_te, plan_te_with_join_keys, WorkEstimate};
+use test_data_gen::create_temp_spill_dir;
+
+// 30k rows at 1 byte/row with fan-in 2 and a 70KB cap gives 10k-row blocks,
+// which matches the CSV source's per-block batch size.
+const ROWS: usize = 30_000;
+const MEM_CAP: usize = 70_000;
+
+fn work() -> WorkEstimate {
+    WorkEstimate {
+        total_rows: ROWS as u64,
+        total_bytes: ROWS as u64,
+        max_fan_in: 2,
+    }
+}
+
+fn write_csv(path: &str, ids: impl Iterator<Item = usize>, col: &str) {
+    let mut file = fs::File::create(path).expect("create csv");
+    writeln!(file, "id,{}", col).unwrap();
+    for i in ids {
+        writeln!(file, "{},{}{}", i, col, i).unwrap();
+    }
+}
+
+fn join_pipeline(dir: &str) -> (L, String) {
+    let left_path = format!("{}/left.csv", dir);
+    let right_path = format!("{}/right.csv", dir);
+    let out_path = format!("{}/out.csv", dir);
+    // Right side is in reverse order, so index-aligned blocks share no keys
+    write_csv(&left_path, 0..ROWS, "l");
+    write_csv(&right_path, (0..ROWS).rev(), "r");
+
+    let schema = |col: &str| {
+        Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(col, DataType::Utf8, false),
+        ])
+    };
+    let join = L::Join {
+        left: Box::new(L::Scan {
+            source: format!("file://{}", left_path),
+            schema: schema("l"),
+        }),
+        right: Box::new(L::Scan {
+            source: format!("file://{}", right_path),
+            schema: schema("r"),
+        }),
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: JoinType::Inner,
+    };
+    let sink = L::Sink {
+        input: Box::new(join),
+        destination: format!("file://{}", out_path),
+        format: "csv".to_string(),
+    };
+    (sink, out_path)
+}
+
+#[test]
+fn test_join_blocks_are_co_partitioned() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let (plan, _) = join_pipeline(&dir);
+    let phys = lower_to_physical(&plan);
+
+    let keys = phys.join_keys();
+    assert_eq!(keys.len(), 1);
+
+    let te = plan_te_with_join_keys(&phys.plan, &work(), MEM_CAP, &keys).unwrap();
+    // Each of the 3 left and 3 right blocks is split once, 3 ways
+    let exchanges: Vec<_> = te.order.iter().filter(|b| b.exchange.is_some()).collect();
+    assert_eq!(exchanges.len(), 6);
+    for block in &exchanges {
+        let exchange = block.exchange.as_ref().unwrap();
+        assert_eq!(exchange.count, 3);
+        assert_eq!(exchange.keys, vec!["id".to_string()]);
+        assert_eq!(block.deps.len(), 1);
+    }
+    let exchange_ids: Vec<_> = exchanges.iter().map(|b| b.id).collect();
+
+    let join_blocks: Vec<_> = te.order.iter().filter(|b| b.partition.is_some()).collect();
+    assert_eq!(join_blocks.len(), 3);
+    for (i, block) in join_blocks.iter().enumerate() {
+        let partition = block.partition.as_ref().unwrap();
+        assert_eq!(partition.index, i);
+        assert_eq!(partition.count, 3);
+        assert_eq!(partition.inputs.len(), 2);
+        assert_eq!(partition.inputs[0].keys, vec!["id".to_string()]);
+        // Join blocks read only the exchange blocks (part i of each)
+        assert_eq!(block.deps, exchange_ids);
+    }
+    emsqrt_te::verify::assert_topological(&te);
+
+    // Without keys the plan stays index-aligned
+    let aligned = plan_te(&phys.plan, &work(), MEM_CAP).unwrap();
+    assert!(aligned.order.iter().all(|b| b.partition.is_none()));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_co_partitioned_join_matches_all_keys() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let (plan, out_path) = join_pipeline(&dir);
+    let phys = lower_to_physical(&plan);
+    let te = plan_te_with_join_keys(&phys.plan, &work(), MEM_CAP, &phys.join_keys()).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config).expect("engine init");
+    engine.run(&phys, &te).expect("run");
+
+    let output = fs::read_to_string(&out_path).expect("read output");
+    // Header + one row per key, even though matching rows sit in different blocks
+    assert_eq!(output.lines().count(), ROWS + 1);
+
+    // Each input row is moved through an exchange once, not once per join block
+    let manifest = engine.run(&phys, &te).expect("rerun");
+    let exchanged: u64 = manifest
+        .block_costs
+        .iter()
+        .filter(|c| c.op == "exchange")
+        .map(|c| c.rows_in)
+        .sum();
+    assert_eq!(exchanged, 2 * ROWS as u64);
+    let join_ids: Vec<u64> = te
+        .order
+        .iter()
+        .filter(|b| b.partition.is_some())
+        .map(|b| b.id.get())
+        .collect();
+    let joined: u64 = manifest
+        .block_costs
+        .iter()
+        .filter(|c| join_ids.contains(&c.block_id))
+        .map(|c| c.rows_in)
+        .sum();
+    assert_eq!(joined, 2 * ROWS as u64);
+
+    let _ = fs::remove_dir_all(&dir);
+}
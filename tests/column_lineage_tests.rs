@@ -0,0 +1,154 @@
+//! Column-level lineage of sink columns (`emsqrt explain --lineage`)
+
+use emsqrt_core::dag::{Aggregation, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_planner::logical::JoinType;
+use emsqrt_planner::{
+    column_lineage, estimate_work, lower_to_physical, parse_yaml_pipeline, render_lineage,
+    ColumnLineage, ExplainGraph, SourceColumn,
+};
+use emsqrt_te::plan_te;
+
+fn scan(source: &str, columns: &[&str]) -> L {
+    L::Scan {
+        source: source.into(),
+        schema: Schema::new(
+            columns
+                .iter()
+                .map(|c| Field::new(*c, DataType::Int64, true))
+                .collect(),
+        ),
+    }
+}
+
+fn from(sources: &[(&str, &str)]) -> Vec<SourceColumn> {
+    sources
+        .iter()
+        .map(|(source, column)| SourceColumn {
+            source: source.to_string(),
+            column: column.to_string(),
+        })
+        .collect()
+}
+
+fn col(column: &str, sources: &[(&str, &str)], derived: bool) -> ColumnLineage {
+    ColumnLineage {
+        column: column.into(),
+        sources: from(sources),
+        derived,
+    }
+}
+
+#[test]
+fn test_lineage_through_map_project_window_and_lateral() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "orders.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+      - { name: "qty", type: "Int64" }
+      - { name: "price", type: "Float64" }
+      - { name: "tags", type: "Utf8" }
+  - op: filter
+    expr: "qty > 0"
+  - op: map
+    expr: "id AS order_id, qty * price AS total"
+  - op: window
+    partitions: ["order_id"]
+    order_by: ["total"]
+    functions:
+      - { type: "row_number", alias: "rank" }
+  - op: lateral
+    column: "tags"
+    alias: "tag"
+  - op: project
+    columns: ["order_id", "total", "rank", "tag", "missing"]
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let plan = parse_yaml_pipeline(yaml).unwrap().plan;
+    let lineage = column_lineage(&plan);
+    assert_eq!(lineage.len(), 1);
+    assert_eq!(lineage[0].destination, "out.csv");
+    let o = "orders.csv";
+    assert_eq!(
+        lineage[0].columns,
+        vec![
+            col("order_id", &[(o, "id")], false),
+            col("total", &[(o, "price"), (o, "qty")], true),
+            col("rank", &[(o, "id"), (o, "price"), (o, "qty")], true),
+            col("tag", &[(o, "tags")], true),
+            col("missing", &[], false),
+        ]
+    );
+    assert_eq!(
+        render_lineage(&lineage),
+        "out.csv\n  order_id <- orders.csv:id\n  total <- orders.csv:price, orders.csv:qty (derived)\n  rank <- orders.csv:id, orders.csv:price, orders.csv:qty (derived)\n  tag <- orders.csv:tags (derived)\n  missing <- (no source column)\n"
+    );
+}
+
+#[test]
+fn test_lineage_through_joins_aggregates_and_unions() {
+    let joined = L::Join {
+        left: Box::new(scan("a.csv", &["id", "v"])),
+        right: Box::new(L::Union {
+            inputs: vec![scan("b1.csv", &["id", "w"]), scan("b2.csv", &["id", "w"])],
+        }),
+        on: vec![("id".into(), "id".into())],
+        join_type: JoinType::Inner,
+    };
+    let plan = L::Sink {
+        input: Box::new(L::Aggregate {
+            input: Box::new(joined.clone()),
+            group_by: vec!["id_right".into()],
+            aggs: vec![Aggregation::Count, Aggregation::Sum("w".into())],
+        }),
+        destination: "agg.csv".into(),
+        format: "csv".into(),
+    };
+    let lineage = column_lineage(&plan);
+    assert_eq!(
+        lineage[0].columns,
+        vec![
+            col("id_right", &[("b1.csv", "id"), ("b2.csv", "id")], false),
+            col("count", &[], true),
+            col("sum_w", &[("b1.csv", "w"), ("b2.csv", "w")], true),
+        ]
+    );
+
+    let plan = L::Sink {
+        input: Box::new(joined),
+        destination: "joined.csv".into(),
+        format: "csv".into(),
+    };
+    let names: Vec<String> = column_lineage(&plan)[0]
+        .columns
+        .iter()
+        .map(|c| c.column.clone())
+        .collect();
+    assert_eq!(names, ["id", "v", "id_right", "w"]);
+}
+
+#[test]
+fn test_explain_json_includes_lineage_on_request() {
+    let plan = L::Sink {
+        input: Box::new(scan("in.csv", &["id"])),
+        destination: "out.csv".into(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&plan);
+    let work = estimate_work(&plan, None);
+    let te = plan_te(&program.plan, &work, 1 << 26).unwrap();
+
+    let graph = ExplainGraph::new(&program, &work, &te, 1 << 26);
+    assert!(!graph.to_json().contains("lineage"));
+    let json: serde_json::Value =
+        serde_json::from_str(&graph.with_lineage(&plan).to_json()).unwrap();
+    assert_eq!(json["lineage"][0]["destination"], "out.csv");
+    assert_eq!(
+        json["lineage"][0]["columns"][0]["sources"][0]["column"],
+        "id"
+    );
+}
@@ -0,0 +1,151 @@
+//! Order-insensitive output diffing (`emsqrt compare-outputs`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::{compare_outputs, CompareOptions, DiffKind, ExecError, OutputDiff};
+use test_data_gen::create_temp_spill_dir;
+
+fn compare(
+    dir: &str,
+    left: &str,
+    right: &str,
+    opts: &CompareOptions,
+) -> Result<OutputDiff, ExecError> {
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    compare_outputs(
+        &format!("{}/{}", dir, left),
+        &format!("{}/{}", dir, right),
+        opts,
+        &config,
+    )
+}
+
+fn write(dir: &str, name: &str, content: &str) {
+    fs::write(format!("{}/{}", dir, name), content).unwrap();
+}
+
+fn keyed(key: &str) -> CompareOptions {
+    CompareOptions {
+        key: vec![key.to_string()],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_row_order_is_ignored() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    write(&dir, "a.csv", "id,name\n1,a\n2,b\n3,c\n");
+    write(&dir, "b.csv", "name,id\nc,3\na,1\nb,2\n");
+
+    let diff = compare(&dir, "a.csv", "b.csv", &CompareOptions::default()).unwrap();
+    assert!(diff.is_identical(), "{:?}", diff);
+    assert_eq!((diff.left_rows, diff.right_rows), (3, 3));
+    assert_eq!(diff.columns, vec!["id", "name"]);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_keyed_diff_across_spilled_runs() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let mut left = String::from("id,v\n");
+    let mut right = String::from("id,v\n");
+    for i in (0..3000).rev() {
+        left.push_str(&format!("{},{}\n", i, i * 2));
+    }
+    for i in 0..3000 {
+        match i {
+            // 10 and 11 removed, 20 changed, 3000 and 3001 added.
+            10 | 11 => {}
+            20 => right.push_str("20,-1\n"),
+            _ => right.push_str(&format!("{},{}\n", i, i * 2)),
+        }
+    }
+    right.push_str("3000,6000\n3001,6002\n");
+    write(&dir, "a.csv", &left);
+    write(&dir, "b.csv", &right);
+
+    let opts = CompareOptions {
+        run_rows: 700,
+        ..keyed("id")
+    };
+    let diff = compare(&dir, "a.csv", "b.csv", &opts).unwrap();
+    assert_eq!((diff.added, diff.removed, diff.changed), (2, 2, 1));
+    assert_eq!(diff.examples.len(), 5);
+    assert_eq!(diff.examples[0].kind, DiffKind::Removed);
+    assert_eq!(
+        diff.examples[2].right,
+        Some(vec![Scalar::I64(20), Scalar::I64(-1)])
+    );
+    assert_eq!(diff.examples[2].kind, DiffKind::Changed);
+
+    // Without a key, the changed row shows up as one removal plus one addition.
+    let whole = compare(&dir, "a.csv", "b.csv", &CompareOptions::default()).unwrap();
+    assert_eq!((whole.added, whole.removed, whole.changed), (3, 3, 0));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_duplicate_rows_are_counted() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    write(&dir, "a.csv", "id\n1\n1\n2\n");
+    write(&dir, "b.csv", "id\n2\n1\n");
+
+    let diff = compare(&dir, "a.csv", "b.csv", &CompareOptions::default()).unwrap();
+    assert_eq!((diff.added, diff.removed), (0, 1));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_values_compare_across_formats() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    write(
+        &dir,
+        "a.csv",
+        "id,score,ok,note\n1,2.0,true,\n2,0.5,false,x\n",
+    );
+    write(
+        &dir,
+        "b.jsonl",
+        "{\"id\":2,\"score\":0.5,\"ok\":false,\"note\":\"x\"}\n{\"id\":1,\"score\":2,\"ok\":true,\"note\":null}\n",
+    );
+
+    let diff = compare(&dir, "a.csv", "b.jsonl", &keyed("id")).unwrap();
+    assert!(diff.is_identical(), "{:?}", diff);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_schema_changes_are_reported() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    write(&dir, "a.csv", "id,old\n1,x\n");
+    write(&dir, "b.csv", "id,new\n1,y\n");
+
+    let diff = compare(&dir, "a.csv", "b.csv", &keyed("id")).unwrap();
+    assert_eq!(diff.columns, vec!["id"]);
+    assert_eq!(diff.schema_changes.len(), 2);
+    assert_eq!((diff.added, diff.removed, diff.changed), (0, 0, 0));
+    assert!(!diff.is_identical());
+
+    assert!(matches!(
+        compare(&dir, "a.csv", "b.csv", &keyed("old")),
+        Err(ExecError::Compare(_))
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+}
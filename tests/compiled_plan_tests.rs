@@ -0,0 +1,130 @@
+//! Compiled plan (PhysicalProgram + TePlan) serialization
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Write;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_exec::Engine;
+use emsqrt_planner::{
+    estimate_work, lower_to_physical, parse_yaml_pipeline, rules, CompiledPlan, CompiledPlanError,
+    COMPILED_PLAN_FORMAT_VERSION,
+};
+use emsqrt_te::plan_te_with_join_keys;
+use test_data_gen::create_temp_spill_dir;
+
+const MEM_CAP: usize = 1 << 20;
+
+fn compile(yaml: &str) -> CompiledPlan {
+    let parsed = parse_yaml_pipeline(yaml).unwrap();
+    let optimized = rules::optimize(parsed.plan.clone());
+    let program = lower_to_physical(&optimized);
+    let work = estimate_work(&optimized, None);
+    let te = plan_te_with_join_keys(&program.plan, &work, MEM_CAP, &program.join_keys()).unwrap();
+    CompiledPlan::new(program, te, parsed.config, MEM_CAP)
+}
+
+fn pipeline(dir: &str, out: &str) -> String {
+    let input = format!("{}/in.csv", dir);
+    let mut f = fs::File::create(&input).unwrap();
+    writeln!(f, "id,name").unwrap();
+    for i in 0..50 {
+        writeln!(f, "{},n{}", i, i).unwrap();
+    }
+    format!(
+        r#"
+config:
+  fallbacks:
+    join_hash: [skip]
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+  - op: filter
+    expr: "id >= 10"
+  - op: sink
+    destination: "{dir}/{out}"
+    format: "csv"
+"#
+    )
+}
+
+#[test]
+fn test_compiled_plan_round_trips() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let compiled = compile(&pipeline(&dir, "out.csv"));
+
+    let bytes = compiled.to_bytes().unwrap();
+    let loaded = CompiledPlan::from_bytes(&bytes).unwrap();
+
+    assert_eq!(loaded.format_version, COMPILED_PLAN_FORMAT_VERSION);
+    assert_eq!(loaded.mem_cap_bytes, MEM_CAP);
+    assert_eq!(loaded.te.order.len(), compiled.te.order.len());
+    assert_eq!(
+        loaded.program.bindings.len(),
+        compiled.program.bindings.len()
+    );
+    assert_eq!(loaded.config.fallbacks, compiled.config.fallbacks);
+    // Re-encoding is byte-for-byte stable
+    assert_eq!(loaded.to_bytes().unwrap(), bytes);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_loaded_plan_runs_like_fresh_plan() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let run = |plan: &CompiledPlan| {
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            mem_cap_bytes: plan.mem_cap_bytes,
+            ..Default::default()
+        };
+        Engine::new(config)
+            .unwrap()
+            .run(&plan.program, &plan.te)
+            .unwrap()
+    };
+
+    let fresh = compile(&pipeline(&dir, "fresh.csv"));
+    let fresh_manifest = run(&fresh);
+
+    let loaded = compile(&pipeline(&dir, "loaded.csv")).to_bytes().unwrap();
+    let loaded = CompiledPlan::from_bytes(&loaded).unwrap();
+    let loaded_manifest = run(&loaded);
+
+    assert_eq!(fresh_manifest.te_hash, loaded_manifest.te_hash);
+    assert_eq!(
+        fs::read_to_string(format!("{}/fresh.csv", dir)).unwrap(),
+        fs::read_to_string(format!("{}/loaded.csv", dir)).unwrap()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_unknown_format_version_is_rejected() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let compiled = compile(&pipeline(&dir, "out.csv"));
+
+    let mut doc: serde_json::Value = serde_json::from_slice(&compiled.to_bytes().unwrap()).unwrap();
+    doc["format_version"] = serde_json::json!(COMPILED_PLAN_FORMAT_VERSION + 1);
+    let bytes = serde_json::to_vec(&doc).unwrap();
+
+    match CompiledPlan::from_bytes(&bytes) {
+        Err(CompiledPlanError::UnsupportedVersion { found, expected }) => {
+            assert_eq!(found, COMPILED_PLAN_FORMAT_VERSION + 1);
+            assert_eq!(expected, COMPILED_PLAN_FORMAT_VERSION);
+        }
+        other => panic!("expected version error, got {:?}", other.map(|_| ())),
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,112 @@
+//! Container resource limits as engine defaults (`emsqrt_exec::resources`)
+
+mod test_data_gen;
+
+use std::fs;
+use std::path::Path;
+
+use emsqrt_core::config::{DetectedLimit, EngineConfig};
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::resources::{detect_limits, ContainerLimits};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn write(root: &Path, file: &str, contents: &str) {
+    let path = root.join(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn test_cgroup_limits_are_read_from_v2_and_v1_files() {
+    let dir = create_temp_spill_dir();
+    let v2 = Path::new(&dir).join("v2");
+    write(&v2, "memory.max", "1073741824\n");
+    write(&v2, "cpu.max", "150000 100000\n");
+    let limits = detect_limits(&v2);
+    assert_eq!(limits.memory, Some((1 << 30, v2.join("memory.max"))));
+    assert_eq!(limits.cpus, Some((2, v2.join("cpu.max"))));
+
+    let v1 = Path::new(&dir).join("v1");
+    write(&v1, "memory/memory.limit_in_bytes", "268435456\n");
+    write(&v1, "cpu/cpu.cfs_quota_us", "400000\n");
+    write(&v1, "cpu/cpu.cfs_period_us", "100000\n");
+    let limits = detect_limits(&v1);
+    assert_eq!(limits.memory.map(|m| m.0), Some(1 << 28));
+    assert_eq!(limits.cpus.map(|c| c.0), Some(4));
+
+    // Unlimited cgroups and hosts without cgroup files impose nothing.
+    write(&v2, "memory.max", "max\n");
+    write(&v2, "cpu.max", "max 100000\n");
+    write(&v1, "memory/memory.limit_in_bytes", "9223372036854771712\n");
+    write(&v1, "cpu/cpu.cfs_quota_us", "-1\n");
+    assert_eq!(detect_limits(&v2), ContainerLimits::default());
+    assert_eq!(detect_limits(&v1), ContainerLimits::default());
+    assert_eq!(
+        detect_limits(&Path::new(&dir).join("none")),
+        ContainerLimits::default()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_limits_default_unconfigured_settings_and_reach_the_manifest() {
+    let dir = create_temp_spill_dir();
+    let root = Path::new(&dir).join("cgroup");
+    write(&root, "memory.max", "1073741824\n");
+    write(&root, "cpu.max", "200000 100000\n");
+    let limits = detect_limits(&root);
+
+    // A configured setting keeps its value.
+    let mut config = EngineConfig::default();
+    limits.apply(&mut config, |setting| setting == "max_parallel_tasks");
+    assert_eq!(config.mem_cap_bytes, 768 << 20);
+    assert_eq!(config.max_parallel_tasks, 4);
+    assert_eq!(
+        config.detected_resources,
+        [DetectedLimit {
+            setting: "mem_cap_bytes".into(),
+            value: 768 << 20,
+            limit: 1 << 30,
+            source: root.join("memory.max").display().to_string(),
+        }]
+    );
+
+    let mut config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    limits.apply(&mut config, |_| false);
+    assert_eq!(config.max_parallel_tasks, 2);
+    // Overridden after detection (e.g. by --memory-cap): not recorded.
+    config.mem_cap_bytes = 64 << 20;
+    let in_effect: Vec<String> = config
+        .resources_in_effect()
+        .into_iter()
+        .map(|d| d.setting)
+        .collect();
+    assert_eq!(in_effect, ["max_parallel_tasks"]);
+
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id\n1\n2\n").unwrap();
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: input,
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 64 << 20).unwrap();
+    let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert_eq!(manifest.detected_resources.len(), 1);
+    assert_eq!(manifest.detected_resources[0].value, 2);
+    assert!(manifest.detected_resources[0].source.ends_with("cpu.max"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
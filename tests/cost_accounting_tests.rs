@@ -0,0 +1,109 @@
+//! Per-block CPU time and cost accounting in the run manifest
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Write;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::manifest::{BlockCost, CostSummary};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_cost_summary_formula() {
+    let gb = 1024 * 1024 * 1024;
+    let blocks = vec![
+        BlockCost {
+            cpu_nanos: 1_500_000_000,
+            bytes_scanned: 2 * gb,
+            ..Default::default()
+        },
+        BlockCost {
+            cpu_nanos: 500_000_000,
+            bytes_spilled: gb,
+            ..Default::default()
+        },
+    ];
+    let summary = CostSummary::from_blocks(&blocks);
+    assert!((summary.cpu_seconds - 2.0).abs() < 1e-9);
+    assert!((summary.gb_spilled - 1.0).abs() < 1e-9);
+    assert!((summary.gb_scanned - 2.0).abs() < 1e-9);
+    assert!((summary.cost - 5.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_manifest_records_block_costs() {
+    let root = create_temp_spill_dir();
+    fs::create_dir_all(&root).unwrap();
+    let input = format!("{}/data.csv", root);
+    let mut f = fs::File::create(&input).unwrap();
+    writeln!(f, "id,name").unwrap();
+    for i in 0..100 {
+        writeln!(f, "{},name_{}", i, i).unwrap();
+    }
+    let file_bytes = fs::metadata(&input).unwrap().len();
+
+    let plan = L::Sink {
+        input: Box::new(L::Scan {
+            source: input,
+            schema: Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, false),
+            ]),
+        }),
+        destination: format!("{}/out.csv", root),
+        format: "csv".to_string(),
+    };
+    let phys = lower_to_physical(&plan);
+    let te = plan_te(&phys.plan, &estimate_work(&plan, None), 1 << 20).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", root),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(&phys, &te).unwrap();
+
+    assert_eq!(manifest.block_costs.len(), te.order.len());
+    let scanned: u64 = manifest
+        .block_costs
+        .iter()
+        .filter(|b| b.op == "source")
+        .map(|b| b.bytes_scanned)
+        .sum();
+    // The reader's byte count, not the size of the decoded batches.
+    assert_eq!(scanned, file_bytes);
+    // CPU time is the executing thread's, so it cannot exceed the block's wall time.
+    for b in &manifest.block_costs {
+        assert!(
+            b.cpu_nanos <= b.wall_nanos + 1_000_000,
+            "block {}: cpu {}ns > wall {}ns",
+            b.block_id,
+            b.cpu_nanos,
+            b.wall_nanos
+        );
+    }
+    assert!(manifest
+        .block_costs
+        .iter()
+        .filter(|b| b.op != "source")
+        .all(|b| b.bytes_scanned == 0));
+
+    let cost = manifest.cost.as_ref().expect("cost summary");
+    assert_eq!(*cost, CostSummary::from_blocks(&manifest.block_costs));
+
+    // Round-trips through JSON, and older manifests without cost fields still parse.
+    let json = serde_json::to_value(&manifest).unwrap();
+    let mut legacy = json.clone();
+    legacy.as_object_mut().unwrap().remove("block_costs");
+    legacy.as_object_mut().unwrap().remove("cost");
+    let parsed: emsqrt_core::manifest::RunManifest = serde_json::from_value(legacy).unwrap();
+    assert!(parsed.block_costs.is_empty());
+    assert!(parsed.cost.is_none());
+
+    let _ = fs::remove_dir_all(&root);
+}
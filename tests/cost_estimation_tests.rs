@@ -47,6 +47,7 @@ fn test_filter_selectivity_with_stats() {
     let hints = WorkHint {
         source_rows: vec![("test.csv".to_string(), 1000)],
         source_bytes: vec![],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
@@ -69,6 +70,7 @@ fn test_filter_equality_selectivity() {
     let hints = WorkHint {
         source_rows: vec![("test.csv".to_string(), 1000)],
         source_bytes: vec![],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
@@ -101,6 +103,7 @@ fn test_join_cardinality_with_stats() {
             ("right.csv".to_string(), 200),
         ],
         source_bytes: vec![],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
@@ -124,6 +127,7 @@ fn test_aggregate_groups_with_stats() {
     let hints = WorkHint {
         source_rows: vec![("test.csv".to_string(), 1000)],
         source_bytes: vec![],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
@@ -148,6 +152,7 @@ fn test_cost_estimation_without_stats() {
     let hints = WorkHint {
         source_rows: vec![("test.csv".to_string(), 1000)],
         source_bytes: vec![],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
@@ -167,9 +172,36 @@ fn test_cost_estimation_with_hints() {
     let hints = WorkHint {
         source_rows: vec![("test.csv".to_string(), 5000)],
         source_bytes: vec![("test.csv".to_string(), 100000)],
+        ..Default::default()
     };
 
     let work = estimate_work(&plan, Some(&hints));
     assert_eq!(work.total_rows, 5000);
     assert_eq!(work.total_bytes, 100000);
 }
+
+#[test]
+fn test_between_and_in_selectivity() {
+    let rows = |expr: &str| {
+        let plan = L::Filter {
+            input: Box::new(L::Scan {
+                source: "test.csv".to_string(),
+                schema: create_schema_with_stats(),
+            }),
+            expr: expr.to_string(),
+        };
+        let hints = WorkHint {
+            source_rows: vec![("test.csv".to_string(), 1000)],
+            source_bytes: vec![],
+            ..Default::default()
+        };
+        estimate_work(&plan, Some(&hints)).total_rows
+    };
+
+    // Out of the [18, 65] range entirely.
+    assert_eq!(rows("age BETWEEN 70 AND 80"), 1);
+    assert!(rows("age BETWEEN 20 AND 30") < 500);
+    // Two of three distinct statuses.
+    assert_eq!(rows("status IN ('active', 'pending')"), 666);
+    assert_eq!(rows("status NOT IN ('active')"), 666);
+}
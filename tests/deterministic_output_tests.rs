@@ -0,0 +1,158 @@
+//! Deterministic output order of aggregates and Grace hash joins
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{scalar_cmp, Column, RowBatch, Scalar};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::spill::{Codec, SpillManager};
+use emsqrt_operators::agregate::Aggregate;
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::parse_yaml_pipeline;
+use test_data_gen::create_temp_spill_dir;
+
+fn spill_manager(dir: &str) -> Arc<Mutex<SpillManager>> {
+    fs::create_dir_all(dir).unwrap();
+    let mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.to_string());
+    Arc::new(Mutex::new(mgr))
+}
+
+fn ints(name: &str, values: impl Iterator<Item = i64>) -> Column {
+    Column {
+        name: name.into(),
+        values: values.map(Scalar::I64).collect(),
+    }
+}
+
+/// Rows of `batch` as value tuples.
+fn rows(batch: &RowBatch) -> Vec<Vec<Scalar>> {
+    (0..batch.num_rows())
+        .map(|row| {
+            batch
+                .columns
+                .iter()
+                .map(|c| c.values[row].clone())
+                .collect()
+        })
+        .collect()
+}
+
+fn sorted(mut rows: Vec<Vec<Scalar>>) -> Vec<Vec<Scalar>> {
+    rows.sort_by(|a, b| {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| scalar_cmp(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    rows
+}
+
+#[test]
+fn test_spilled_aggregate_groups_come_out_sorted_by_key() {
+    let dir = create_temp_spill_dir();
+    let agg = Aggregate {
+        group_by: vec!["k".into()],
+        aggs: vec!["count".into(), "sum:v".into()],
+        spill_mgr: Some(spill_manager(&dir)),
+        ..Default::default()
+    };
+    let input = RowBatch {
+        columns: vec![
+            ints("k", (0..4000).map(|i| (i * 7919) % 500)),
+            ints("v", 0..4000),
+        ],
+    };
+    // A few KiB forces the groups through spilled hash partitions.
+    let run = |agg: &Aggregate| {
+        let budget = MemoryBudgetImpl::new(4 << 10);
+        rows(
+            &agg.eval_block(std::slice::from_ref(&input), &budget)
+                .unwrap(),
+        )
+    };
+
+    let ordered = run(&agg);
+    let keys: Vec<Scalar> = ordered.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(keys, (0..500).map(Scalar::I64).collect::<Vec<_>>());
+    assert_eq!(run(&agg), ordered);
+
+    let unordered = run(&Aggregate {
+        deterministic: false,
+        ..agg
+    });
+    assert_ne!(unordered, ordered);
+    assert_eq!(sorted(unordered), sorted(ordered));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_grace_join_output_follows_probe_order() {
+    let dir = create_temp_spill_dir();
+    // Enough left rows for the Grace path; keys are shuffled so partition
+    // order and probe order differ, and each right key matches twice.
+    let left = RowBatch {
+        columns: vec![
+            ints("id", (0..120_000).map(|i| (i * 7919) % 120_000)),
+            ints("v", 0..120_000),
+        ],
+    };
+    let right = RowBatch {
+        columns: vec![
+            ints("id", (0..20_000).map(|i| (i % 10_000) * 3)),
+            ints("w", 0..20_000),
+        ],
+    };
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+
+    for join_type in ["inner", "left"] {
+        let in_memory = HashJoin {
+            on: vec![("id".into(), "id".into())],
+            join_type: join_type.into(),
+            ..Default::default()
+        };
+        let grace = HashJoin {
+            on: in_memory.on.clone(),
+            join_type: join_type.into(),
+            spill_mgr: Some(spill_manager(&dir)),
+            num_partitions: Some(8),
+            ..Default::default()
+        };
+        let inputs = [left.clone(), right.clone()];
+        let expected = rows(&in_memory.eval_block(&inputs, &budget).unwrap());
+
+        let out = grace.eval_block(&inputs, &budget).unwrap();
+        let names: Vec<&str> = out.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "v", "id_right", "w"], "{}", join_type);
+        assert_eq!(rows(&out), expected, "{}", join_type);
+
+        let unordered = HashJoin {
+            deterministic: false,
+            ..grace
+        };
+        let out = rows(&unordered.eval_block(&inputs, &budget).unwrap());
+        assert_ne!(out, expected, "{}", join_type);
+        assert_eq!(sorted(out), sorted(expected), "{}", join_type);
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_deterministic_output_is_on_unless_turned_off() {
+    assert!(EngineConfig::default().deterministic_output);
+    let yaml = "config:\n  deterministic_output: false\nsteps:\n  - op: scan\n    source: \"in.csv\"\n    schema:\n      - { name: \"id\", type: \"Int64\" }\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n";
+    assert_eq!(
+        parse_yaml_pipeline(yaml)
+            .unwrap()
+            .config
+            .deterministic_output,
+        Some(false)
+    );
+}
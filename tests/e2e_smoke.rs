@@ -45,8 +45,10 @@ fn e2e_smoke() {
     let work = estimate_work(&lp, None);
     let te = plan_te(&phys_prog.plan, &work, 64 * 1024 * 1024).unwrap();
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = format!("{}/spill", temp_dir);
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", temp_dir),
+        ..Default::default()
+    };
     let mut eng = Engine::new(config).expect("engine init");
     let manifest = eng.run(&phys_prog, &te).unwrap();
     assert!(manifest.started_ms <= manifest.finished_ms);
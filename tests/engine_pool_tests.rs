@@ -0,0 +1,189 @@
+//! Concurrent pipelines under one shared budget (EnginePool)
+
+mod test_data_gen;
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{EnginePool, ExecError, PoolConfig, PoolJob};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use test_data_gen::{aggregate_pipeline, create_temp_spill_dir};
+
+const MB: usize = 1024 * 1024;
+
+fn job(dir: &str, name: &str, mem_cap_bytes: usize) -> PoolJob {
+    let input = format!("{}/{}.csv", dir, name);
+    fs::write(&input, "id,name\n1,a\n2,b\n").unwrap();
+
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: format!("file://{}", input),
+            schema: Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, false),
+            ]),
+        }),
+        destination: format!("file://{}/{}.out.csv", dir, name),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let work = estimate_work(&lp, None);
+    let te = plan_te(&program.plan, &work, mem_cap_bytes).unwrap();
+    let config = EngineConfig {
+        mem_cap_bytes,
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    PoolJob::new(config, program, te)
+}
+
+fn wait_until(mut cond: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !cond() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for pool state"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn test_pool_runs_pipelines_and_releases_reservations() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: 64 * MB,
+        max_concurrent: 4,
+    });
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| pool.submit(job(&dir, &format!("p{}", i), 32 * MB)).unwrap())
+        .collect();
+    for handle in handles {
+        let manifest = handle.join().unwrap();
+        assert!(manifest.started_ms <= manifest.finished_ms);
+    }
+    assert_eq!(pool.budget().used_bytes(), 0);
+    assert_eq!(pool.running(), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_pool_rejects_jobs_larger_than_the_pool() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: 16 * MB,
+        max_concurrent: 2,
+    });
+
+    assert!(pool.submit(job(&dir, "big", 32 * MB)).is_err());
+    assert!(pool
+        .submit(job(&dir, "zero", 8 * MB).with_weight(0))
+        .is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_pool_admits_higher_weight_first() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: 64 * MB,
+        max_concurrent: 1,
+    });
+
+    // Memory held by the embedding service blocks admission entirely.
+    let held = pool.budget().try_acquire(64 * MB, "service").unwrap();
+    let low = pool.submit(job(&dir, "low", 32 * MB)).unwrap();
+    let high = pool
+        .submit(job(&dir, "high", 32 * MB).with_weight(4))
+        .unwrap();
+    wait_until(|| pool.waiting() == 2);
+    assert_eq!(pool.running(), 0);
+    drop(held);
+
+    let low = low.join().unwrap();
+    let high = high.join().unwrap();
+    assert!(
+        high.finished_ms <= low.started_ms,
+        "weight-4 job should run before the weight-1 job"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cancelled_or_dropped_jobs_leave_the_queue() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: 64 * MB,
+        max_concurrent: 1,
+    });
+
+    let held = pool.budget().try_acquire(64 * MB, "service").unwrap();
+    let cancelled = pool.submit(job(&dir, "cancelled", 32 * MB)).unwrap();
+    let dropped = pool.submit(job(&dir, "dropped", 32 * MB)).unwrap();
+    wait_until(|| pool.waiting() == 2);
+
+    cancelled.cancel();
+    let err = cancelled.join().unwrap_err();
+    assert!(matches!(err, ExecError::Cancelled(_)), "{}", err);
+    // Dropping a handle cancels its job and waits for the thread.
+    drop(dropped);
+    assert_eq!(pool.waiting(), 0);
+    assert_eq!(pool.running(), 0);
+    drop(held);
+    assert!(!Path::new(&format!("{}/dropped.out.csv", dir)).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spilling_jobs_share_a_spill_dir() {
+    let dir = create_temp_spill_dir();
+    let pool = EnginePool::new(PoolConfig {
+        mem_cap_bytes: 64 * MB,
+        max_concurrent: 4,
+    });
+    let config = EngineConfig {
+        mem_cap_bytes: 16 * MB,
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+
+    // Every job issues the same spill ids into the same directory.
+    let dirs: Vec<String> = (0..4).map(|i| format!("{}/job{}", dir, i)).collect();
+    let handles: Vec<_> = dirs
+        .iter()
+        .map(|job_dir| {
+            fs::create_dir_all(job_dir).unwrap();
+            let (program, te) = aggregate_pipeline(job_dir, Some(4 << 10));
+            pool.submit(PoolJob::new(config.clone(), program, te))
+                .unwrap()
+        })
+        .collect();
+    for handle in handles {
+        let manifest = handle.join().unwrap();
+        assert!(manifest.spill.unwrap().bytes_compressed > 0);
+    }
+    let expected = fs::read_to_string(format!("{}/out.csv", dirs[0])).unwrap();
+    assert_eq!(expected.lines().count(), 501);
+    for job_dir in &dirs[1..] {
+        assert_eq!(
+            fs::read_to_string(format!("{}/out.csv", job_dir)).unwrap(),
+            expected
+        );
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
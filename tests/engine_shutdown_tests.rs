@@ -0,0 +1,189 @@
+//! Spill segments and sinks are cleaned up however an engine or run ends
+
+mod test_data_gen;
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::physical::{OperatorBinding, PhysicalProgram};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// Spill segment files under `dir`.
+fn segments(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .map(|e| e.unwrap().path())
+        .map(|p| {
+            if p.is_dir() {
+                segments(&p)
+            } else {
+                usize::from(p.extension().is_some_and(|x| x == "seg"))
+            }
+        })
+        .sum()
+}
+
+fn engine(dir: &str) -> Engine {
+    engine_with_quota(dir, None)
+}
+
+fn engine_with_quota(dir: &str, quota: Option<u64>) -> Engine {
+    Engine::new(EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        spill_quota_bytes: quota,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn sort_binding() -> OperatorBinding {
+    OperatorBinding {
+        key: "sort_external".into(),
+        config: serde_json::json!({ "by": ["id"] }),
+    }
+}
+
+/// A hash join big enough to take the Grace path.
+fn grace_join() -> (OperatorBinding, Vec<RowBatch>) {
+    let binding = OperatorBinding {
+        key: "join_hash".into(),
+        config: serde_json::json!({ "on": [["id", "id"]], "partitions": 4 }),
+    };
+    let side: Vec<i64> = (0..100_000).collect();
+    (binding, vec![ids(&side), ids(&side)])
+}
+
+fn ids(values: &[i64]) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: values.iter().map(|&v| Scalar::I64(v)).collect(),
+        }],
+    }
+}
+
+#[test]
+fn test_spill_manager_deletes_segments_when_dropped() {
+    let dir = create_temp_spill_dir();
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    for run in 0..2 {
+        let id = mgr.next_spill_id();
+        mgr.write_batch(&ids(&[1, 2]), id, run).unwrap();
+    }
+    assert_eq!(segments(Path::new(&dir)), 2);
+    drop(mgr);
+    assert_eq!(segments(Path::new(&dir)), 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_dropping_or_shutting_down_an_engine_deletes_its_spills() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let spill = Path::new(&dir).join("spill");
+
+    // A Grace join deletes its partitions as it reads them back.
+    let (join, inputs) = grace_join();
+    let engine = engine(&dir);
+    let (joined, _) = engine.eval_binding(&join, &inputs).unwrap();
+    assert_eq!(joined.num_rows(), 100_000);
+    assert_eq!(segments(&spill), 0);
+    drop(engine);
+
+    // Its partitions of equal sides take the same space; a quota of three
+    // quarters of both fails it after it spilled the left one, which the
+    // engine then still holds.
+    let both = {
+        let mgr = Arc::new(Mutex::new(SpillManager::new(
+            Box::new(FsStorage::new()),
+            Codec::None,
+            format!("{}/probe", dir),
+        )));
+        let probe = HashJoin {
+            on: vec![("id".into(), "id".into())],
+            spill_mgr: Some(mgr.clone()),
+            num_partitions: Some(4),
+            ..Default::default()
+        };
+        let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+        probe.eval_block(&inputs, &budget).unwrap();
+        let peak = mgr.lock().unwrap().peak_bytes();
+        peak
+    };
+    let engine = engine_with_quota(&dir, Some(both * 3 / 4));
+    assert!(engine.eval_binding(&join, &inputs).is_err());
+    assert!(segments(&spill) > 0);
+    drop(engine);
+    assert_eq!(segments(&spill), 0);
+
+    let engine = engine_with_quota(&dir, Some(both * 3 / 4));
+    assert!(engine.eval_binding(&join, &inputs).is_err());
+    assert!(segments(&spill) > 0);
+    engine.shutdown().unwrap();
+    assert_eq!(segments(&spill), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// `input → sort → sink`.
+fn sort_program(input: &str, output: &str) -> (PhysicalProgram, emsqrt_te::tree_eval::TePlan) {
+    let lp = L::Sink {
+        input: Box::new(L::Filter {
+            input: Box::new(L::Scan {
+                source: input.to_string(),
+                schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+            }),
+            expr: "id > 0".into(),
+        }),
+        destination: output.to_string(),
+        format: "csv".into(),
+    };
+    let mut program = lower_to_physical(&lp);
+    // There is no logical sort; put the external sort where the filter was.
+    for binding in program.bindings.values_mut() {
+        if binding.key == "filter" {
+            *binding = sort_binding();
+        }
+    }
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    (program, te)
+}
+
+#[test]
+fn test_runs_delete_their_spills_whether_they_succeed_or_fail() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let spill = Path::new(&dir).join("spill");
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id\n3\n1\n2\n").unwrap();
+
+    let output = format!("{}/out.csv", dir);
+    let (program, te) = sort_program(&input, &output);
+    let mut engine = engine(&dir);
+    engine.run(&program, &te).unwrap();
+    assert_eq!(fs::read_to_string(&output).unwrap(), "id\n1\n2\n3\n");
+    assert_eq!(segments(&spill), 0);
+
+    // The sink cannot open its destination (a directory), after the sort spilled.
+    let output = format!("{}/taken", dir);
+    fs::create_dir_all(&output).unwrap();
+    let (program, te) = sort_program(&input, &output);
+    assert!(engine.run(&program, &te).is_err());
+    assert_eq!(segments(&spill), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,131 @@
+//! Evaluating one registered operator on in-memory batches (`Engine::eval_operator`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, ExecError};
+use serde_json::json;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(columns: &[(&str, Vec<i64>)]) -> RowBatch {
+    RowBatch {
+        columns: columns
+            .iter()
+            .map(|(name, values)| Column {
+                name: name.to_string(),
+                values: values.iter().copied().map(Scalar::I64).collect(),
+            })
+            .collect(),
+    }
+}
+
+fn engine(dir: &str, mem_cap_bytes: usize) -> Engine {
+    Engine::new(EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        mem_cap_bytes,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+/// Rows of `batch` as sorted tuples, for order-insensitive comparison.
+fn rows(batch: &RowBatch) -> Vec<Vec<Scalar>> {
+    let mut rows: Vec<Vec<Scalar>> = (0..batch.num_rows())
+        .map(|r| batch.columns.iter().map(|c| c.values[r].clone()).collect())
+        .collect();
+    rows.sort_by(|a, b| format!("{:?}", a).cmp(&format!("{:?}", b)));
+    rows
+}
+
+#[test]
+fn test_eval_operator_runs_a_config() {
+    let dir = create_temp_spill_dir();
+    let engine = engine(&dir, 1 << 26);
+    let input = batch(&[("id", vec![1, 2, 3, 4])]);
+
+    let out = engine
+        .eval_operator(
+            "filter",
+            &json!({ "expr": "id > 2" }),
+            std::slice::from_ref(&input),
+        )
+        .unwrap();
+    assert_eq!(out.columns[0].values, [Scalar::I64(3), Scalar::I64(4)]);
+
+    match engine.eval_operator("no_such_op", &json!({}), std::slice::from_ref(&input)) {
+        Err(ExecError::Registry(msg)) => assert!(msg.contains("no_such_op"), "{}", msg),
+        other => panic!(
+            "expected a registry error, got {:?}",
+            other.map(|b| b.num_rows())
+        ),
+    }
+    let err = engine
+        .eval_operator("filter", &json!({ "expr": "nope > 2" }), &[input])
+        .unwrap_err();
+    assert!(err.to_string().contains("operator 'filter'"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_join_strategies_agree() {
+    let dir = create_temp_spill_dir();
+    let engine = engine(&dir, 1 << 26);
+    let inputs = [
+        batch(&[("id", vec![1, 2, 2, 3, 5]), ("l", vec![10, 20, 21, 30, 50])]),
+        batch(&[("id", vec![2, 3, 3, 4]), ("r", vec![200, 300, 301, 400])]),
+    ];
+    let config = json!({ "on": [["id", "id"]], "join_type": "inner" });
+
+    let hash = engine.eval_operator("join_hash", &config, &inputs).unwrap();
+    let merge = engine
+        .eval_operator("join_merge", &config, &inputs)
+        .unwrap();
+    assert_eq!(hash.num_rows(), 4);
+    assert_eq!(rows(&hash), rows(&merge));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spill_is_scoped_to_the_call() {
+    let dir = create_temp_spill_dir();
+    let input = batch(&[
+        ("k", (0..4_000).map(|i| i % 1_000).collect()),
+        ("v", (0..4_000).collect()),
+    ]);
+    let config = json!({ "group_by": ["k"], "aggs": ["count", "sum:v"] });
+
+    let roomy = engine(&dir, 1 << 26)
+        .eval_operator("aggregate", &config, std::slice::from_ref(&input))
+        .unwrap();
+    // Too small for every group at once: the aggregate spills.
+    let tight = engine(&dir, 64 << 10);
+    let out = tight.eval_operator("aggregate", &config, &[input]).unwrap();
+    assert_eq!(rows(&out), rows(&roomy));
+
+    let spill_files = walk(&format!("{}/spill", dir));
+    assert!(spill_files.is_empty(), "{:?}", spill_files);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn walk(dir: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .flat_map(|e| {
+            let path = e.path().to_string_lossy().into_owned();
+            if e.path().is_dir() {
+                walk(&path)
+            } else {
+                vec![path]
+            }
+        })
+        .collect()
+}
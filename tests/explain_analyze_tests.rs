@@ -0,0 +1,102 @@
+//! Runtime counters behind `emsqrt explain --analyze`
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::hash::Hash256;
+use emsqrt_core::manifest::{BlockCost, RunManifest};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn run(dir: &str, limit_rows: Option<usize>) -> RunManifest {
+    let input = format!("{}/in.csv", dir);
+    let mut csv = String::from("id\n");
+    for i in 0..50 {
+        csv.push_str(&format!("{}\n", i));
+    }
+    fs::write(&input, csv).unwrap();
+
+    let lp = L::Sink {
+        input: Box::new(L::Filter {
+            input: Box::new(L::Scan {
+                source: input,
+                schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+            }),
+            expr: "id >= 10".into(),
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".into(),
+    };
+    let mut program = lower_to_physical(&lp);
+    if let Some(limit) = limit_rows {
+        for binding in program.bindings.values_mut() {
+            if binding.key == "source" {
+                binding.config["limit_rows"] = limit.into();
+            }
+        }
+    }
+    let work = estimate_work(&lp, None);
+    let te = plan_te(&program.plan, &work, 64 * 1024 * 1024).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap()
+}
+
+#[test]
+fn test_manifest_records_rows_per_operator() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let totals = run(&dir, None).op_totals();
+    let ops: Vec<(&str, u64)> = totals.iter().map(|t| (t.op.as_str(), t.rows_out)).collect();
+    assert_eq!(ops[0], ("source", 50));
+    assert_eq!(ops[1], ("filter", 40));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_source_limit_rows_samples_input() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let totals = run(&dir, Some(20)).op_totals();
+    assert_eq!(totals[0].rows_out, 20);
+    assert_eq!(totals[1].rows_out, 10);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_op_totals_sum_blocks_in_first_seen_order() {
+    let block = |op_id: u64, op: &str, rows_out: u64| BlockCost {
+        op_id,
+        op: op.to_string(),
+        rows_out,
+        wall_nanos: 10,
+        ..Default::default()
+    };
+    let mut manifest = RunManifest::new(Hash256([0; 32]), Hash256([0; 32]), 0);
+    manifest.block_costs = vec![
+        block(2, "source", 5),
+        block(1, "source", 7),
+        block(2, "source", 3),
+    ];
+
+    let totals = manifest.op_totals();
+    assert_eq!(totals.len(), 2);
+    assert_eq!(
+        (totals[0].op_id, totals[0].rows_out, totals[0].blocks),
+        (2, 8, 2)
+    );
+    assert_eq!(totals[0].wall_nanos, 20);
+    assert_eq!((totals[1].op_id, totals[1].rows_out), (1, 7));
+}
@@ -0,0 +1,208 @@
+//! `ExplainGraph`: the structure behind `emsqrt explain --format json|dot|html`
+
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_operators::agregate::Aggregate;
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::Operator;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline, ExplainGraph};
+
+const PIPELINE: &str = r#"
+hints:
+  sources:
+    "data/events.csv": { rows: 10000 }
+  rows_per_block: 4096
+steps:
+  - op: scan
+    source: "data/events.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+      - { name: "name", type: "Utf8" }
+  - op: filter
+    expr: "id < 10"
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+
+fn graph() -> ExplainGraph {
+    let parsed = parse_yaml_pipeline(PIPELINE).unwrap();
+    let mut program = lower_to_physical(&parsed.plan);
+    parsed.hints.apply(&mut program);
+    let work = estimate_work(&parsed.plan, parsed.hints.work_hint().as_ref());
+    let te = parsed.hints.plan_te(&program, &work, 1 << 26).unwrap();
+    ExplainGraph::new(&program, &work, &te, 1 << 26)
+}
+
+#[test]
+fn test_graph_collects_operators_and_blocks() {
+    let g = graph();
+    let keys: Vec<&str> = g.operators.iter().map(|o| o.key.as_str()).collect();
+    assert_eq!(keys, ["source", "filter", "sink"]);
+
+    let source = &g.operators[0];
+    assert_eq!(source.category, "source");
+    assert_eq!(source.detail.as_deref(), Some("data/events.csv"));
+    assert_eq!(source.columns, ["id", "name"]);
+    let source_blocks: Vec<_> = g
+        .blocks
+        .iter()
+        .filter(|b| b.op_id == source.op_id)
+        .collect();
+    assert!(source.blocks > 1);
+    assert_eq!(source.blocks, source_blocks.len());
+    assert_eq!(
+        source.est_rows,
+        Some(source_blocks.iter().map(|b| b.est_rows.unwrap()).sum())
+    );
+    assert_eq!(g.operators[1].inputs, [source.op_id]);
+    assert_eq!(g.operators[1].detail.as_deref(), Some("id < 10"));
+
+    assert_eq!(g.rows_per_block, 4096);
+    let first = &g.blocks[0];
+    assert_eq!(first.range_rows, Some((0, 4096)));
+    // Int64 (8) + Utf8 (32) bytes per row.
+    assert_eq!(first.est_bytes, Some(4096 * 40));
+    assert!(g
+        .blocks
+        .iter()
+        .all(|b| b.deps.iter().all(|d| *d < b.block_id)));
+}
+
+#[test]
+fn test_json_round_trips() {
+    let g = graph();
+    let back: ExplainGraph = serde_json::from_str(&g.to_json()).unwrap();
+    assert_eq!(back.blocks.len(), g.blocks.len());
+    assert_eq!(back.operators[2].key, "sink");
+
+    // The full plans ride along for tools that want more than `operators`.
+    let plan = parse_yaml_pipeline(PIPELINE).unwrap().plan;
+    let json: serde_json::Value =
+        serde_json::from_str(&g.with_logical_plan(&plan).to_json()).unwrap();
+    assert!(json["logical_plan"]["Sink"]["input"]["Filter"].is_object());
+    assert!(json["program"]["plan"]["Sink"].is_object());
+    let bindings = json["program"]["bindings"].as_object().unwrap();
+    assert_eq!(bindings.len(), 3);
+    assert_eq!(bindings["2"]["config"]["expr"], "id < 10");
+}
+
+#[test]
+fn test_dot_has_operator_and_block_edges() {
+    let g = graph();
+    let dot = g.to_dot();
+    assert!(dot.starts_with("digraph explain {"));
+    let (src, filter) = (g.operators[0].op_id, g.operators[1].op_id);
+    assert!(dot.contains(&format!("op{} -> op{};", src, filter)));
+    for b in &g.blocks {
+        for d in &b.deps {
+            assert!(dot.contains(&format!("b{} -> b{};", d, b.block_id)));
+        }
+    }
+}
+
+#[test]
+fn test_html_is_self_contained_and_escaped() {
+    let html = graph().to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<svg"));
+    assert!(!html.contains("<script") && !html.contains("src=\"http"));
+    // The filter predicate is text, not markup.
+    assert!(html.contains("id &lt; 10"));
+    assert!(!html.contains("id < 10"));
+}
+
+#[test]
+fn test_join_and_aggregate_schemas_match_their_operators() {
+    let scan = |source: &str, value: &str| L::Scan {
+        source: source.into(),
+        schema: Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(value, DataType::Int32, false),
+        ]),
+    };
+    let join = L::Join {
+        left: Box::new(scan("orders.csv", "qty")),
+        right: Box::new(scan("returns.csv", "qty")),
+        on: vec![("id".into(), "id".into())],
+        join_type: JoinType::Left,
+    };
+    let lp = L::Sink {
+        input: Box::new(L::Aggregate {
+            input: Box::new(join),
+            group_by: vec!["id".into()],
+            aggs: vec![Aggregation::Count, Aggregation::Sum("qty_right".into())],
+        }),
+        destination: "out.csv".into(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let work = estimate_work(&lp, None);
+    let te = emsqrt_te::plan_te(&program.plan, &work, 1 << 26).unwrap();
+    let g = ExplainGraph::new(&program, &work, &te, 1 << 26);
+
+    let op = |key: &str| g.operators.iter().find(|o| o.key == key).unwrap();
+    let sources: Vec<Schema> = g
+        .operators
+        .iter()
+        .filter(|o| o.key == "source")
+        .map(|o| Schema::new(o.fields.clone()))
+        .collect();
+    // Right columns are renamed on collision and nullable under a left join.
+    let join = op("join_hash");
+    assert_eq!(
+        join.fields,
+        [
+            Field::new("id", DataType::Int64, false),
+            Field::new("qty", DataType::Int32, false),
+            Field::new("id_right", DataType::Int64, true),
+            Field::new("qty_right", DataType::Int32, true),
+        ]
+    );
+    let hash = HashJoin {
+        on: vec![("id".into(), "id".into())],
+        join_type: "left".into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        hash.plan(&sources).unwrap().output_schema.fields,
+        join.fields
+    );
+
+    let agg = op("aggregate");
+    assert_eq!(
+        agg.fields,
+        [
+            Field::new("id", DataType::Int64, false),
+            Field::new("count", DataType::Int64, false),
+            Field::new("sum_qty_right", DataType::Float64, true),
+        ]
+    );
+    let aggregate = Aggregate {
+        group_by: vec!["id".into()],
+        aggs: vec!["count".into(), "sum:qty_right".into()],
+        ..Default::default()
+    };
+    let join_schema = Schema::new(join.fields.clone());
+    assert_eq!(
+        aggregate
+            .plan(std::slice::from_ref(&join_schema))
+            .unwrap()
+            .output_schema
+            .fields,
+        agg.fields
+    );
+
+    let text = g.render_schemas();
+    assert!(
+        text.contains(
+            "(aggregate):\n  id Int64 NOT NULL\n  count Int64 NOT NULL\n  sum_qty_right Float64\n"
+        ),
+        "{}",
+        text
+    );
+    assert!(!text.contains("(sink)"), "{}", text);
+    assert!(g
+        .to_html()
+        .contains("columns: id Int64 NOT NULL, qty Int32 NOT NULL, id_right Int64"));
+}
@@ -1,7 +1,6 @@
 //! Expression evaluation tests
 
 use emsqrt_core::expr::Expr;
-use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 
 fn create_test_batch() -> RowBatch {
@@ -75,15 +74,15 @@ fn test_evaluate_comparison() {
 
     // Row 0: age=25 > 18 -> true
     let result = expr.evaluate_bool(&batch, 0).unwrap();
-    assert_eq!(result, true);
+    assert!(result);
 
     // Row 1: age=18 > 18 -> false
     let result = expr.evaluate_bool(&batch, 1).unwrap();
-    assert_eq!(result, false);
+    assert!(!result);
 
     // Row 2: age=30 > 18 -> true
     let result = expr.evaluate_bool(&batch, 2).unwrap();
-    assert_eq!(result, true);
+    assert!(result);
 }
 
 #[test]
@@ -93,11 +92,11 @@ fn test_evaluate_equality() {
 
     // Row 0: name="Alice" -> true
     let result = expr.evaluate_bool(&batch, 0).unwrap();
-    assert_eq!(result, true);
+    assert!(result);
 
     // Row 1: name="Bob" -> false
     let result = expr.evaluate_bool(&batch, 1).unwrap();
-    assert_eq!(result, false);
+    assert!(!result);
 }
 
 #[test]
@@ -141,11 +140,11 @@ fn test_evaluate_logical_and() {
 
     // Row 0: age=25 > 20 (true) AND price=10.5 < 15 (true) -> true
     let result = expr.evaluate_bool(&batch, 0).unwrap();
-    assert_eq!(result, true);
+    assert!(result);
 
     // Row 1: age=18 > 20 (false) AND price=20.0 < 15 (false) -> false
     let result = expr.evaluate_bool(&batch, 1).unwrap();
-    assert_eq!(result, false);
+    assert!(!result);
 }
 
 #[test]
@@ -155,11 +154,11 @@ fn test_evaluate_logical_or() {
 
     // Row 0: age=25 < 20 (false) OR price=10.5 > 15 (false) -> false
     let result = expr.evaluate_bool(&batch, 0).unwrap();
-    assert_eq!(result, false);
+    assert!(!result);
 
     // Row 1: age=18 < 20 (true) OR price=20.0 > 15 (true) -> true
     let result = expr.evaluate_bool(&batch, 1).unwrap();
-    assert_eq!(result, true);
+    assert!(result);
 }
 
 #[test]
@@ -187,7 +186,7 @@ fn test_evaluate_null_handling() {
 
     // Row 3 has null age - should evaluate to false
     let result = expr.evaluate_bool(&batch, 3).unwrap();
-    assert_eq!(result, false);
+    assert!(!result);
 }
 
 #[test]
@@ -206,3 +205,46 @@ fn test_evaluate_division_by_zero() {
     assert!(result.is_err());
     assert!(result.unwrap_err().contains("division by zero"));
 }
+
+fn matching_rows(expr: &str) -> Vec<usize> {
+    let batch = create_test_batch();
+    let expr = Expr::parse(expr).unwrap();
+    (0..batch.num_rows())
+        .filter(|&i| expr.evaluate_bool(&batch, i).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_evaluate_between() {
+    assert_eq!(matching_rows("age BETWEEN 18 AND 25"), vec![0, 1]);
+    assert_eq!(matching_rows("price between 10 and 16"), vec![0, 2]);
+    assert_eq!(
+        matching_rows("age NOT BETWEEN 18 AND 25 AND quantity < 4"),
+        vec![2]
+    );
+    assert_eq!(
+        matching_rows("quantity BETWEEN 2 AND 3 OR name == \"David\""),
+        vec![0, 1, 3]
+    );
+}
+
+#[test]
+fn test_evaluate_in_list() {
+    assert_eq!(matching_rows("quantity IN (1, 4)"), vec![2, 3]);
+    assert_eq!(
+        matching_rows("name IN ('Bob', 'A, B', \"David\")"),
+        vec![1, 3]
+    );
+    assert_eq!(
+        matching_rows("name NOT IN ('Bob') AND age IN (25, 30)"),
+        vec![0, 2]
+    );
+    assert_eq!(matching_rows("quantity * 2 IN (2, -8, 6)"), vec![1, 2]);
+}
+
+#[test]
+fn test_between_in_parse_errors() {
+    assert!(Expr::parse("age IN ()").is_err());
+    assert!(Expr::parse("age IN 1, 2").is_err());
+    assert!(Expr::parse("age BETWEEN 1").is_err());
+}
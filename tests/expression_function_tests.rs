@@ -0,0 +1,296 @@
+//! Built-in string, math, conversion and date functions
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::expr::{map_schema, Expr, Func};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::temporal::{parse_date, parse_timestamp};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline, rules};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use test_data_gen::create_temp_spill_dir;
+
+fn batch() -> RowBatch {
+    let values = vec![
+        ("name", Scalar::Str("  Ada Lövelace ".into())),
+        ("nick", Scalar::Null),
+        ("n", Scalar::I64(-1250)),
+        ("x", Scalar::F64(-2.5)),
+        ("price", Scalar::Decimal128(-12345, 3)),
+        ("day", Scalar::Date64(parse_date("2024-02-29").unwrap())),
+        (
+            "ts",
+            Scalar::Timestamp(parse_timestamp("2024-02-29T13:45:30.5").unwrap()),
+        ),
+    ];
+    RowBatch {
+        columns: values
+            .into_iter()
+            .map(|(name, value)| Column {
+                name: name.to_string(),
+                values: vec![value],
+            })
+            .collect(),
+    }
+}
+
+fn eval(expr: &str) -> Result<Scalar, String> {
+    Expr::parse(expr)?.evaluate(&batch(), 0)
+}
+
+fn text(expr: &str) -> String {
+    eval(expr).unwrap().to_string()
+}
+
+#[test]
+fn test_parse_function_calls() {
+    let expr = Expr::parse("UPPER(trim(name))").unwrap();
+    assert!(matches!(
+        expr,
+        Expr::Call { func: Func::Upper, ref args }
+            if matches!(&args[0], Expr::Call { func: Func::Trim, .. })
+    ));
+    // The type of a cast may be bare or quoted; either is stored by name.
+    for cast in ["cast(n, i64)", "cast(n, 'Int64')", "cast(n, \"Int64\")"] {
+        match Expr::parse(cast).unwrap() {
+            Expr::Call {
+                func: Func::Cast,
+                args,
+            } => assert_eq!(args[1], Expr::Literal(Scalar::Str("Int64".into()))),
+            other => panic!("expected a cast, got {:?}", other),
+        }
+    }
+    assert_eq!(
+        Expr::parse("substr(name)").unwrap_err(),
+        "substr() takes 2 to 3 argument(s), got 1"
+    );
+    assert_eq!(
+        Expr::parse("coalesce()").unwrap_err(),
+        "coalesce() takes at least 1 argument(s), got 0"
+    );
+    assert_eq!(
+        Expr::parse("upper(a, b)").unwrap_err(),
+        "upper() takes 1 argument(s), got 2"
+    );
+    assert!(Expr::parse("cast(n, integer)")
+        .unwrap_err()
+        .contains("type name"));
+}
+
+#[test]
+fn test_string_functions() {
+    assert_eq!(text("upper(trim(name))"), "ADA LÖVELACE");
+    assert_eq!(text("lower(name)"), "  ada lövelace ");
+    // Positions count characters from 1; a start before 1 still counts
+    // toward the length.
+    assert_eq!(text("substr(trim(name), 5, 4)"), "Löve");
+    assert_eq!(text("substr(trim(name), 9)"), "lace");
+    assert_eq!(text("substr(trim(name), 0, 2)"), "A");
+    assert_eq!(text("substr(trim(name), 20, 2)"), "");
+    assert!(eval("substr(name, 1, -1)")
+        .unwrap_err()
+        .contains("negative"));
+    assert_eq!(eval("upper(nick)").unwrap(), Scalar::Null);
+    assert!(eval("upper(n)").unwrap_err().contains("upper()"));
+
+    assert_eq!(
+        text("concat(trim(name), nick, ': ', n, ' ', day)"),
+        "Ada Lövelace: -1250 2024-02-29"
+    );
+    assert_eq!(text("coalesce(nick, trim(name), 'x')"), "Ada Lövelace");
+    assert_eq!(eval("coalesce(nick, nick)").unwrap(), Scalar::Null);
+}
+
+#[test]
+fn test_math_functions() {
+    assert_eq!(eval("abs(n)").unwrap(), Scalar::I64(1250));
+    assert_eq!(eval("abs(x)").unwrap(), Scalar::F64(2.5));
+    assert_eq!(text("abs(price)"), "12.345");
+
+    // Half away from zero, in the argument's type.
+    assert_eq!(eval("round(x)").unwrap(), Scalar::F64(-3.0));
+    assert_eq!(eval("round(2.345, 2)").unwrap(), Scalar::F64(2.35));
+    assert_eq!(eval("round(n, -2)").unwrap(), Scalar::I64(-1300));
+    assert_eq!(eval("round(n, 2)").unwrap(), Scalar::I64(-1250));
+    assert_eq!(text("round(price, 2)"), "-12.35");
+    assert_eq!(text("round(price)"), "-12");
+    assert_eq!(text("round(price, -1)"), "-10");
+    assert_eq!(text("round(price, 5)"), "-12.345");
+
+    assert_eq!(eval("floor(x)").unwrap(), Scalar::F64(-3.0));
+    assert_eq!(eval("ceil(x)").unwrap(), Scalar::F64(-2.0));
+    assert_eq!(eval("floor(price)").unwrap(), Scalar::Decimal128(-13, 0));
+    assert_eq!(eval("ceil(price)").unwrap(), Scalar::Decimal128(-12, 0));
+    assert_eq!(eval("floor(n)").unwrap(), Scalar::I64(-1250));
+    assert!(eval("abs(name)").unwrap_err().contains("abs()"));
+    assert_eq!(eval("round(nick, 2)").unwrap(), Scalar::Null);
+}
+
+#[test]
+fn test_cast() {
+    assert_eq!(eval("cast('42', Int64)").unwrap(), Scalar::I64(42));
+    assert_eq!(eval("cast(x, i32)").unwrap(), Scalar::I32(-2));
+    assert_eq!(eval("cast(price, Int64)").unwrap(), Scalar::I64(-12));
+    assert_eq!(eval("cast(n, Float64)").unwrap(), Scalar::F64(-1250.0));
+    assert_eq!(eval("cast('TRUE', bool)").unwrap(), Scalar::Bool(true));
+    assert_eq!(
+        eval("cast(n, string)").unwrap(),
+        Scalar::Str("-1250".into())
+    );
+    assert_eq!(text("cast(x, decimal)"), "-2.5");
+    assert_eq!(text("cast('1.50', decimal) * 2"), "3.00");
+    assert_eq!(text("cast(ts, date)"), "2024-02-29");
+    assert_eq!(text("cast(day, timestamp)"), "2024-02-29T00:00:00");
+    assert_eq!(
+        text("cast('2024-03-01T08:00:00+01:00', timestamp)"),
+        "2024-03-01T07:00:00"
+    );
+    assert_eq!(eval("cast(nick, Int64)").unwrap(), Scalar::Null);
+    assert_eq!(
+        eval("cast(name, Int64)").unwrap_err(),
+        "cannot cast Str(\"  Ada Lövelace \") to Int64"
+    );
+    assert!(eval("cast(1e300, Int32)").is_err());
+}
+
+#[test]
+fn test_date_functions() {
+    // Weeks start on Monday; a date stays a date.
+    assert_eq!(text("date_trunc('week', day)"), "2024-02-26");
+    assert_eq!(text("date_trunc('month', day)"), "2024-02-01");
+    assert_eq!(text("date_trunc('quarter', day)"), "2024-01-01");
+    assert_eq!(text("date_trunc('YEAR', ts)"), "2024-01-01T00:00:00");
+    assert_eq!(text("date_trunc('hour', ts)"), "2024-02-29T13:00:00");
+    assert_eq!(text("date_trunc('second', ts)"), "2024-02-29T13:45:30");
+    assert_eq!(
+        text("date_trunc('day', '1969-12-31T23:00:00')"),
+        "1969-12-31T00:00:00"
+    );
+    assert!(eval("date_trunc('fortnight', day)")
+        .unwrap_err()
+        .contains("'fortnight'"));
+
+    // Boundaries crossed, not whole units elapsed.
+    assert_eq!(eval("date_diff('day', day, ts)").unwrap(), Scalar::I64(0));
+    assert_eq!(eval("date_diff('hour', day, ts)").unwrap(), Scalar::I64(13));
+    assert_eq!(
+        eval("date_diff('day', '2024-02-28T23:00:00', day)").unwrap(),
+        Scalar::I64(1)
+    );
+    assert_eq!(
+        eval("date_diff('month', '2024-01-31', day)").unwrap(),
+        Scalar::I64(1)
+    );
+    assert_eq!(
+        eval("date_diff('year', ts, '2023-12-31')").unwrap(),
+        Scalar::I64(-1)
+    );
+    assert_eq!(
+        eval("date_diff('quarter', '2023-12-31', ts)").unwrap(),
+        Scalar::I64(1)
+    );
+    assert_eq!(
+        eval("date_diff('week', '2024-02-25', day)").unwrap(),
+        Scalar::I64(1)
+    );
+    assert_eq!(eval("date_diff('day', nick, day)").unwrap(), Scalar::Null);
+}
+
+#[test]
+fn test_function_result_types() {
+    let schema = Schema::new(vec![
+        Field::new("name", DataType::Utf8, true),
+        Field::new("price", DataType::Decimal128, true),
+        Field::new("day", DataType::Date64, true),
+        Field::new("ts", DataType::Timestamp, true),
+    ]);
+    let items = Expr::parse_list(
+        "upper(name) AS a, round(price, 1) AS b, cast(name, Int32) AS c, \
+         date_trunc('month', day) AS d, date_trunc('month', ts) AS e, \
+         date_diff('day', day, ts) AS f, coalesce(price, 1) AS g",
+    )
+    .unwrap();
+    let out = map_schema(&items, &schema).unwrap();
+    let types: Vec<_> = out.fields[4..]
+        .iter()
+        .map(|f| f.data_type.clone())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            DataType::Utf8,
+            DataType::Decimal128,
+            DataType::Int32,
+            DataType::Date64,
+            DataType::Timestamp,
+            DataType::Int64,
+            DataType::Decimal128,
+        ]
+    );
+}
+
+#[test]
+fn test_functions_in_filter_and_map() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/in.csv", dir),
+        "name,amount,at\n\
+         \x20alice ,10.456,2024-01-15T09:30:00\n\
+         BOB,-3.5,2024-02-01T00:00:00\n\
+         carol,,2024-03-31T23:59:59\n",
+    )
+    .unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "amount", type: "decimal" }}
+      - {{ name: "at", type: "timestamp" }}
+  - op: filter
+    expr: "lower(trim(name)) != 'carol' AND date_trunc('month', at) >= '2024-01-01'"
+  - op: map
+    expr: "upper(trim(name)) AS name, round(coalesce(amount, 0), 1) AS rounded, cast(date_trunc('month', at), date) AS month"
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: csv
+"#
+    );
+    let plan = rules::optimize(parse_yaml_pipeline(&yaml).unwrap().plan);
+    let program = lower_to_physical(&plan);
+    let work = WorkEstimate {
+        total_rows: 3,
+        total_bytes: 3,
+        max_fan_in: 1,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block: 2 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    let out = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        [
+            "amount,at,name,rounded,month",
+            "10.456,2024-01-15T09:30:00,ALICE,10.5,2024-01-01",
+            "-3.5,2024-02-01T00:00:00,BOB,-3.5,2024-02-01",
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -1,7 +1,7 @@
 //! Expression parsing and AST construction tests
 
 use emsqrt_core::expr::{BinOp, Expr, UnaryOp};
-use emsqrt_core::types::{RowBatch, Scalar};
+use emsqrt_core::types::Scalar;
 
 #[test]
 fn test_parse_simple_column() {
@@ -62,8 +62,6 @@ fn test_parse_binary_arithmetic() {
 
 #[test]
 fn test_parse_binary_logical() {
-    // Note: Simple parser finds first operator, so "AND" might not be parsed correctly
-    // if there are other operators first. Test simpler case.
     let expr = Expr::parse("age AND status").unwrap();
     match expr {
         Expr::BinaryOp { op, left, right } => {
@@ -77,13 +75,11 @@ fn test_parse_binary_logical() {
 
 #[test]
 fn test_parse_invalid_expression() {
-    // Current simple parser will parse anything as a column name if it can't parse as literal
-    // Test with malformed operator syntax instead
-    // This test documents current parser limitations - more robust parsing will be added later
-    let result = Expr::parse("col >");
-    // Parser might succeed or fail depending on implementation
-    // Just verify it doesn't panic
-    let _ = result;
+    for bad in [
+        "col >", "(a > 1", "a > 1)", "a b", "AND a", "a IS 1", "'open", "a # b",
+    ] {
+        assert!(Expr::parse(bad).is_err(), "{}", bad);
+    }
 }
 
 #[test]
@@ -110,3 +106,176 @@ fn test_unaryop_parse() {
     assert_eq!(UnaryOp::parse("IS NOT NULL"), Ok(UnaryOp::IsNotNull));
     assert!(UnaryOp::parse("invalid").is_err());
 }
+
+fn literal(s: &str) -> Scalar {
+    match Expr::parse(s).unwrap() {
+        Expr::Literal(v) => v,
+        other => panic!("expected literal for {:?}, got {:?}", s, other),
+    }
+}
+
+#[test]
+fn test_parse_literal_type_selection() {
+    assert_eq!(literal("2147483647"), Scalar::I32(i32::MAX));
+    assert_eq!(literal("2147483648"), Scalar::I64(2147483648));
+    assert_eq!(literal("-2147483648"), Scalar::I32(i32::MIN));
+    assert_eq!(literal("100i64"), Scalar::I64(100));
+    assert_eq!(literal("-7i32"), Scalar::I32(-7));
+    assert_eq!(literal("2.5f32"), Scalar::F32(2.5));
+    assert_eq!(literal("2.5f64"), Scalar::F64(2.5));
+    assert_eq!(literal("2.5"), Scalar::F32(2.5));
+    assert_eq!(literal("0.1"), Scalar::F64(0.1));
+    assert_eq!(literal("0xff"), Scalar::I32(255));
+    assert_eq!(literal("0xFFFFFFFF"), Scalar::I64(0xFFFF_FFFF));
+    assert_eq!(literal("0x10i64"), Scalar::I64(16));
+}
+
+#[test]
+fn test_parse_literal_scientific_notation() {
+    assert_eq!(literal("1e9"), Scalar::F32(1e9));
+    assert_eq!(literal("1.5E-3"), Scalar::F64(1.5e-3));
+    assert_eq!(literal("1e9i64"), Scalar::I64(1_000_000_000));
+    assert_eq!(literal(".5"), Scalar::F32(0.5));
+
+    // The exponent sign is not a subtraction.
+    match Expr::parse("x > 2.5e-3").unwrap() {
+        Expr::BinaryOp { op, right, .. } => {
+            assert_eq!(op, BinOp::Gt);
+            assert_eq!(*right, Expr::Literal(Scalar::F64(2.5e-3)));
+        }
+        other => panic!("expected comparison, got {:?}", other),
+    }
+    assert!(matches!(
+        Expr::parse("x - 1").unwrap(),
+        Expr::BinaryOp { op: BinOp::Sub, .. }
+    ));
+}
+
+#[test]
+fn test_parse_date_literal() {
+    assert_eq!(
+        literal("date '2024-02-29'"),
+        Scalar::Str("2024-02-29".into())
+    );
+    assert_eq!(
+        literal("DATE \"2024-1-5\""),
+        Scalar::Str("2024-01-05".into())
+    );
+    match Expr::parse("day >= date '2024-01-15'").unwrap() {
+        Expr::BinaryOp { op, left, right } => {
+            assert_eq!(op, BinOp::Ge);
+            assert_eq!(*left, Expr::Column("day".into()));
+            assert_eq!(*right, Expr::Literal(Scalar::Str("2024-01-15".into())));
+        }
+        other => panic!("expected comparison, got {:?}", other),
+    }
+    assert!(Expr::parse("date '2023-02-29'").is_err());
+    assert!(Expr::parse("date '2024-13-01'").is_err());
+}
+
+#[test]
+fn test_invalid_numeric_literals_are_errors() {
+    assert!(Expr::parse("3000000000i32").is_err());
+    assert!(Expr::parse("99999999999999999999").is_err());
+    assert!(Expr::parse("2.5i64").is_err());
+    assert!(Expr::parse("12abc").is_err());
+    assert!(Expr::parse("1e999").is_err());
+}
+
+/// Fully parenthesized rendering of an expression's tree.
+fn tree(expr: &Expr) -> String {
+    match expr {
+        Expr::Column(name) => name.clone(),
+        Expr::Literal(value) => format!("{:?}", value),
+        Expr::BinaryOp { op, left, right } => {
+            format!("({} {:?} {})", tree(left), op, tree(right))
+        }
+        Expr::UnaryOp { op, arg } => format!("({:?} {})", op, tree(arg)),
+        Expr::Call { func, args } => {
+            let args: Vec<String> = args.iter().map(tree).collect();
+            format!("{:?}({})", func, args.join(", "))
+        }
+        Expr::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => format!(
+            "({} {}Between {} {})",
+            tree(expr),
+            if *negated { "Not" } else { "" },
+            tree(low),
+            tree(high)
+        ),
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => {
+            let list: Vec<String> = list.iter().map(tree).collect();
+            format!(
+                "({} {}In [{}])",
+                tree(expr),
+                if *negated { "Not" } else { "" },
+                list.join(", ")
+            )
+        }
+    }
+}
+
+fn parsed(s: &str) -> String {
+    tree(&Expr::parse(s).unwrap())
+}
+
+#[test]
+fn test_parse_arithmetic_precedence_and_associativity() {
+    assert_eq!(parsed("a + b * c"), "(a Add (b Mul c))");
+    assert_eq!(parsed("a * b + c"), "((a Mul b) Add c)");
+    assert_eq!(parsed("a - b - c"), "((a Sub b) Sub c)");
+    assert_eq!(parsed("a / b * c"), "((a Div b) Mul c)");
+    assert_eq!(parsed("(a + b) * c"), "((a Add b) Mul c)");
+    assert_eq!(parsed("a*-2"), "(a Mul I32(-2))");
+    assert_eq!(parsed("-a * b"), "((I32(0) Sub a) Mul b)");
+    assert_eq!(parsed("price * qty > 100"), "((price Mul qty) Gt I32(100))");
+}
+
+#[test]
+fn test_parse_nested_logical_expressions() {
+    assert_eq!(
+        parsed("(x > 1) AND (y < 2 OR z == 3)"),
+        "((x Gt I32(1)) And ((y Lt I32(2)) Or (z Eq I32(3))))"
+    );
+    // AND binds tighter than OR, in either order and either case.
+    assert_eq!(parsed("a OR b AND c"), "(a Or (b And c))");
+    assert_eq!(parsed("a and b or c"), "((a And b) Or c)");
+    assert_eq!(parsed("a && b || c && d"), "((a And b) Or (c And d))");
+    assert_eq!(parsed("((a))"), "a");
+}
+
+#[test]
+fn test_parse_not_and_null_checks() {
+    assert_eq!(parsed("NOT a = 1 AND b"), "((Not (a Eq I32(1))) And b)");
+    assert_eq!(parsed("NOT (a OR b)"), "(Not (a Or b))");
+    assert_eq!(parsed("!done"), "(Not done)");
+    assert_eq!(parsed("a IS NULL"), "(IsNull a)");
+    assert_eq!(
+        parsed("a + 1 is not null OR b IS NULL"),
+        "((IsNotNull (a Add I32(1))) Or (IsNull b))"
+    );
+}
+
+#[test]
+fn test_parse_between_in_and_calls_inside_larger_expressions() {
+    assert_eq!(
+        parsed("a BETWEEN 1 AND 5 AND b NOT IN ('x', 'y')"),
+        "((a Between I32(1) I32(5)) And (b NotIn [Str(\"x\"), Str(\"y\")]))"
+    );
+    assert_eq!(
+        parsed("a + 1 NOT BETWEEN b * 2 AND 10"),
+        "((a Add I32(1)) NotBetween (b Mul I32(2)) I32(10))"
+    );
+    assert_eq!(
+        parsed("safe_div(a + b, 2) > 1 OR hash(c) IN (1, 2 + 3)"),
+        "((SafeDiv((a Add b), I32(2)) Gt I32(1)) Or (Hash(c) In [I32(1), (I32(2) Add I32(3))]))"
+    );
+}
@@ -6,10 +6,11 @@ use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 use emsqrt_io::storage::FsStorage;
 use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
-use emsqrt_operators::sort::external::ExternalSort;
-use emsqrt_operators::traits::{MemoryBudget, Operator};
+use emsqrt_operators::sort::external::{ExternalSort, SortMemory};
+use emsqrt_operators::sort::merge::LoserTree;
+use emsqrt_operators::traits::Operator;
 use std::sync::{Arc, Mutex};
-use test_data_gen::{create_temp_spill_dir, generate_random_batch, generate_sorted_batch};
+use test_data_gen::{create_temp_spill_dir, generate_random_batch};
 
 fn setup_sort_operator(
     codec: Codec,
@@ -44,25 +45,17 @@ fn verify_sorted(batch: &RowBatch, key_col: &str) -> bool {
 
         // Simple comparison for common types
         match (prev, curr) {
-            (Scalar::I32(a), Scalar::I32(b)) => {
-                if a > b {
-                    return false;
-                }
+            (Scalar::I32(a), Scalar::I32(b)) if a > b => {
+                return false;
             }
-            (Scalar::I64(a), Scalar::I64(b)) => {
-                if a > b {
-                    return false;
-                }
+            (Scalar::I64(a), Scalar::I64(b)) if a > b => {
+                return false;
             }
-            (Scalar::F64(a), Scalar::F64(b)) => {
-                if a > b {
-                    return false;
-                }
+            (Scalar::F64(a), Scalar::F64(b)) if a > b => {
+                return false;
             }
-            (Scalar::Str(a), Scalar::Str(b)) => {
-                if a > b {
-                    return false;
-                }
+            (Scalar::Str(a), Scalar::Str(b)) if a > b => {
+                return false;
             }
             (Scalar::Null, _) => {
                 // Nulls sort first, so ok
@@ -89,7 +82,7 @@ fn test_sort_in_memory() {
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024); // 10MB - plenty for in-memory sort
 
     // Create unsorted batch (small enough to fit in memory)
-    let mut batch = RowBatch {
+    let batch = RowBatch {
         columns: vec![
             Column {
                 name: "sort_key".to_string(),
@@ -117,7 +110,7 @@ fn test_sort_in_memory() {
     };
 
     let result = sort_op
-        .eval_block(&[batch.clone()], &budget)
+        .eval_block(std::slice::from_ref(&batch), &budget)
         .expect("Sort failed");
 
     // Verify sorted
@@ -273,7 +266,7 @@ fn test_sort_large_keys() {
     std::fs::create_dir_all(&spill_dir).expect("Failed to create spill dir");
 
     let (sort_op, _spill_mgr) = setup_sort_operator(Codec::None, spill_dir.clone());
-    let budget = MemoryBudgetImpl::new(1 * 1024 * 1024); // 1MB
+    let budget = MemoryBudgetImpl::new(1024 * 1024); // 1MB
 
     // Create batch with large string keys
     let mut values = Vec::new();
@@ -390,3 +383,96 @@ fn test_sort_already_sorted() {
 
     cleanup_spill_dir(&spill_dir);
 }
+
+#[test]
+fn test_fan_in_follows_the_memory_cap() {
+    let big = SortMemory::for_capacity(256 << 20);
+    assert_eq!(big.page_bytes, 1 << 20);
+    assert_eq!(big.run_bytes, 64 << 20);
+    assert_eq!(big.fan_in, 127);
+
+    let small = SortMemory::for_capacity(4 << 10);
+    assert_eq!(small.fan_in, 2);
+    assert!(small.run_bytes >= small.page_bytes);
+}
+
+#[test]
+fn test_loser_tree_merges_like_a_stable_sort() {
+    let sources: Vec<Vec<i64>> = vec![vec![1, 4, 4, 9], vec![], vec![2, 4, 7], vec![0, 4]];
+    let mut heads = vec![0usize; sources.len()];
+    let cmp = |heads: &[usize], a: usize, b: usize| match (
+        sources[a].get(heads[a]),
+        sources[b].get(heads[b]),
+    ) {
+        (None, None) => std::cmp::Ordering::Equal,
+        (None, _) => std::cmp::Ordering::Greater,
+        (_, None) => std::cmp::Ordering::Less,
+        (Some(x), Some(y)) => x.cmp(y),
+    };
+    let mut tree = LoserTree::new(sources.len(), |a, b| cmp(&heads, a, b));
+    let mut merged = Vec::new();
+    while let Some(&v) = sources[tree.winner()].get(heads[tree.winner()]) {
+        merged.push((v, tree.winner()));
+        heads[tree.winner()] += 1;
+        tree.replay(|a, b| cmp(&heads, a, b));
+    }
+    assert_eq!(
+        merged,
+        [
+            (0, 3),
+            (1, 0),
+            (2, 2),
+            (4, 0),
+            (4, 0),
+            (4, 2),
+            (4, 3),
+            (7, 2),
+            (9, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_multi_pass_merge_under_a_tiny_budget() {
+    let spill_dir = create_temp_spill_dir();
+    std::fs::create_dir_all(&spill_dir).expect("Failed to create spill dir");
+    let (sort_op, spill_mgr) = setup_sort_operator(Codec::None, spill_dir.clone());
+    // Fan-in 2 with runs of a few dozen rows: several merge passes.
+    let budget = MemoryBudgetImpl::new(4 * 1024);
+    assert_eq!(SortMemory::for_capacity(4 * 1024).fan_in, 2);
+
+    let n = 3000;
+    let keys: Vec<i64> = (0..n).map(|i| (i * 7919) % 100).collect();
+    let batch = RowBatch {
+        columns: vec![
+            Column {
+                name: "sort_key".to_string(),
+                values: keys.iter().map(|&k| Scalar::I64(k)).collect(),
+            },
+            Column {
+                name: "pos".to_string(),
+                values: (0..n).map(Scalar::I64).collect(),
+            },
+        ],
+    };
+    let result = sort_op
+        .eval_block(&[batch], &budget)
+        .expect("multi-pass sort failed");
+
+    let mut expected: Vec<(i64, i64)> = keys.into_iter().zip(0..).collect();
+    expected.sort_by_key(|&(k, _)| k);
+    let actual: Vec<(i64, i64)> = result.columns[0]
+        .values
+        .iter()
+        .zip(&result.columns[1].values)
+        .map(|(k, p)| match (k, p) {
+            (Scalar::I64(k), Scalar::I64(p)) => (*k, *p),
+            other => panic!("unexpected row {:?}", other),
+        })
+        .collect();
+    // Equal keys keep their input order.
+    assert_eq!(actual, expected);
+    assert!(spill_mgr.lock().unwrap().list_segments().is_empty());
+
+    cleanup_spill_dir(&spill_dir);
+}
@@ -1,6 +1,5 @@
 //! Filter operator with expression engine tests
 
-use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 use emsqrt_mem::guard::MemoryBudgetImpl;
 use emsqrt_operators::filter::Filter;
@@ -42,8 +41,10 @@ fn create_test_batch() -> RowBatch {
 
 #[test]
 fn test_filter_simple_comparison() {
-    let mut filter = Filter::default();
-    filter.expr = Some("age > 18".to_string());
+    let filter = Filter {
+        expr: Some("age > 18".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -55,8 +56,10 @@ fn test_filter_simple_comparison() {
 
 #[test]
 fn test_filter_equality() {
-    let mut filter = Filter::default();
-    filter.expr = Some("status == \"active\"".to_string());
+    let filter = Filter {
+        expr: Some("status == \"active\"".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -71,8 +74,10 @@ fn test_filter_complex_expression() {
     // Note: Current simple parser may not correctly parse "age > 18 AND status == \"active\""
     // It finds operators in order, so "==" might be parsed before "AND"
     // This test documents current limitation
-    let mut filter = Filter::default();
-    filter.expr = Some("age > 18 AND status == \"active\"".to_string());
+    let filter = Filter {
+        expr: Some("age > 18 AND status == \"active\"".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -84,8 +89,10 @@ fn test_filter_complex_expression() {
 
 #[test]
 fn test_filter_arithmetic_in_predicate() {
-    let mut filter = Filter::default();
-    filter.expr = Some("price * 2 > 20".to_string());
+    let filter = Filter {
+        expr: Some("price * 2 > 20".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -114,8 +121,10 @@ fn test_filter_no_expression() {
 fn test_filter_invalid_expression() {
     // Current simple parser may accept invalid syntax as column names
     // This test documents current limitation
-    let mut filter = Filter::default();
-    filter.expr = Some("invalid syntax !!!".to_string());
+    let filter = Filter {
+        expr: Some("invalid syntax !!!".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -127,8 +136,10 @@ fn test_filter_invalid_expression() {
 
 #[test]
 fn test_filter_missing_column() {
-    let mut filter = Filter::default();
-    filter.expr = Some("nonexistent > 10".to_string());
+    let filter = Filter {
+        expr: Some("nonexistent > 10".to_string()),
+        ..Default::default()
+    };
 
     let input = create_test_batch();
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
@@ -136,8 +147,8 @@ fn test_filter_missing_column() {
 
     // Filter may error or return empty result when column doesn't exist
     // Current implementation may skip rows with evaluation errors (conservative)
-    match result {
-        Ok(batch) => assert_eq!(batch.num_rows(), 0), // No rows match when column missing
-        Err(_) => {}                                  // Error is also acceptable
+    // (an error is also acceptable)
+    if let Ok(batch) = result {
+        assert_eq!(batch.num_rows(), 0); // No rows match when column missing
     }
 }
@@ -0,0 +1,145 @@
+//! filter_in: semi-join against a key file
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::{EngineConfig, SandboxConfig};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::filter_in::FilterIn;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch() -> RowBatch {
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "id".into(),
+                values: (0..100).map(Scalar::I64).chain([Scalar::Null]).collect(),
+            },
+            Column {
+                name: "region".into(),
+                values: (0..101)
+                    .map(|i| Scalar::Str(if i % 2 == 0 { "eu" } else { "us" }.into()))
+                    .collect(),
+            },
+        ],
+    }
+}
+
+fn ids(batch: &RowBatch) -> Vec<Scalar> {
+    batch.columns[0].values.clone()
+}
+
+fn filter_in(dir: &str, keys: &str, columns: &[&str]) -> FilterIn {
+    let path = format!("{}/keys.txt", dir);
+    fs::write(&path, keys).unwrap();
+    let mut op = FilterIn::default();
+    op.columns = columns.iter().map(|c| c.to_string()).collect();
+    op.keys_path = path;
+    op
+}
+
+#[test]
+fn test_keeps_rows_with_keys_in_file() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let op = filter_in(&dir, "3\n42\n\n999\n", &["id"]);
+
+    let out = op
+        .eval_block(&[batch()], &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    assert_eq!(ids(&out), vec![Scalar::I64(3), Scalar::I64(42)]);
+    assert_eq!(out.columns[1].values.len(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_composite_keys_use_delimiter() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let mut op = filter_in(&dir, "4|eu\n5|eu\n7|us\n", &["id", "region"]);
+    op.delimiter = "|".into();
+
+    let out = op
+        .eval_block(&[batch()], &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    assert_eq!(ids(&out), vec![Scalar::I64(4), Scalar::I64(7)]);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_bloom_fallback_is_exact_when_keys_do_not_fit() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let keys: String = (0..1000).step_by(10).map(|i| format!("{}\n", i)).collect();
+    let mut op = filter_in(&dir, &keys, &["id"]);
+    op.bloom_bits = 256;
+
+    // Room for the 32-byte Bloom filter but not for 100 hashed keys.
+    let budget = MemoryBudgetImpl::new(512);
+    let out = op.eval_block(&[batch()], &budget).unwrap();
+    let expected: Vec<Scalar> = (0..100).step_by(10).map(Scalar::I64).collect();
+    assert_eq!(ids(&out), expected);
+    assert_eq!(budget.used_bytes(), 32);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_filter_in_pipeline_step() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(format!("{}/in.csv", dir), "id,name\n1,a\n2,b\n3,c\n4,d\n").unwrap();
+    fs::write(format!("{}/keys.txt", dir), "2\n4\n").unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+  - op: filter_in
+    columns: ["id"]
+    keys: "{dir}/keys.txt"
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    );
+    let parsed = parse_yaml_pipeline(&yaml).unwrap();
+    let program = lower_to_physical(&parsed.plan);
+    let te = plan_te(&program.plan, &estimate_work(&parsed.plan, None), 1 << 26).unwrap();
+
+    let run = |sandbox: Option<SandboxConfig>| {
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            sandbox,
+            ..Default::default()
+        };
+        Engine::new(config).unwrap().run(&program, &te)
+    };
+    run(None).unwrap();
+    let out = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        vec!["id,name", "2,b", "4,d"]
+    );
+
+    // The key file is a read, so the sandbox allowlist applies to it.
+    let sandbox = SandboxConfig {
+        read_allow: vec![format!("{}/in.csv", dir)],
+        sink_allow: vec![dir.clone()],
+        ..Default::default()
+    };
+    assert!(matches!(run(Some(sandbox)), Err(ExecError::Sandbox(_))));
+
+    let _ = fs::remove_dir_all(&dir);
+}
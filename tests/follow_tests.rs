@@ -0,0 +1,175 @@
+//! Continuous micro-batch mode (`emsqrt run --follow`)
+
+mod test_data_gen;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::Duration;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{ExecError, FollowOptions, Follower};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn scan(source: &str) -> L {
+    L::Scan {
+        source: source.to_string(),
+        schema: Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("v", DataType::Int64, false),
+        ]),
+    }
+}
+
+fn sink(input: L, destination: &str, format: &str) -> L {
+    L::Sink {
+        input: Box::new(input),
+        destination: destination.to_string(),
+        format: format.into(),
+    }
+}
+
+fn follower(lp: &L, dir: &str) -> Result<Follower, ExecError> {
+    let program = lower_to_physical(lp);
+    let te = plan_te(&program.plan, &estimate_work(lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Follower::new(program, te, config)
+}
+
+fn append(path: &str, text: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_appended_rows_become_micro_batches() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    append(&input, "id,v\n1,10\n2,20\n3,30\n");
+
+    let lp = sink(
+        L::Filter {
+            input: Box::new(scan(&input)),
+            expr: "v > 10".into(),
+        },
+        &output,
+        "csv",
+    );
+    let mut follower = follower(&lp, &dir).unwrap();
+
+    let first = follower.poll().unwrap().unwrap();
+    assert_eq!((first.index, first.rows_in), (0, 3));
+    assert_eq!(first.manifest.micro_batch, Some(0));
+    assert_eq!(fs::read_to_string(&output).unwrap(), "id,v\n2,20\n3,30\n");
+    assert!(follower.poll().unwrap().is_none());
+
+    // The unterminated last line waits for its newline.
+    append(&input, "4,40\n5,5\n6,6");
+    let second = follower.poll().unwrap().unwrap();
+    assert_eq!((second.index, second.rows_in), (1, 2));
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "id,v\n2,20\n3,30\n4,40\n"
+    );
+
+    append(&input, "0\n");
+    let third = follower.poll().unwrap().unwrap();
+    assert_eq!(third.rows_in, 1);
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "id,v\n2,20\n3,30\n4,40\n6,60\n"
+    );
+    assert_eq!(follower.batches(), 3);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_new_files_matching_pattern_are_picked_up() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(format!("{}/in", dir)).unwrap();
+    let output = format!("{}/out.csv", dir);
+    append(&format!("{}/in/a.jsonl", dir), "{\"id\":1,\"v\":1}\n");
+    append(&format!("{}/in/skip.csv", dir), "id,v\n9,9\n");
+
+    let lp = sink(scan(&format!("{}/in/*.jsonl", dir)), &output, "csv");
+    let mut follower = follower(&lp, &dir).unwrap();
+    assert_eq!(follower.poll().unwrap().unwrap().rows_in, 1);
+
+    append(
+        &format!("{}/in/b.jsonl", dir),
+        "{\"id\":2,\"v\":4}\n{\"id\":3,\"v\":9}\n",
+    );
+    assert_eq!(follower.poll().unwrap().unwrap().rows_in, 2);
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "id,v\n1,1\n2,4\n3,9\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_run_stops_when_idle() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    append(&input, "id,v\n1,10\n");
+
+    let lp = sink(scan(&input), &format!("{}/out.csv", dir), "csv");
+    let mut follower = follower(&lp, &dir).unwrap();
+    let opts = FollowOptions {
+        poll_interval: Duration::from_millis(5),
+        idle_timeout: Some(Duration::from_millis(30)),
+        ..Default::default()
+    };
+    let mut seen = Vec::new();
+    let batches = follower
+        .run(&opts, |batch| seen.push(batch.manifest.micro_batch))
+        .unwrap();
+    assert_eq!(batches, 1);
+    assert_eq!(seen, vec![Some(0)]);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_non_incremental_pipelines_are_rejected() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    append(&input, "id,v\n1,10\n");
+
+    let aggregate = sink(
+        L::Aggregate {
+            input: Box::new(scan(&input)),
+            group_by: vec!["id".into()],
+            aggs: vec![Aggregation::Count],
+        },
+        &format!("{}/out.csv", dir),
+        "csv",
+    );
+    assert!(matches!(
+        follower(&aggregate, &dir),
+        Err(ExecError::Invalid(msg)) if msg.contains("aggregate")
+    ));
+    let parquet = sink(scan(&input), &format!("{}/out.parquet", dir), "parquet");
+    assert!(matches!(
+        follower(&parquet, &dir),
+        Err(ExecError::Invalid(_))
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+}
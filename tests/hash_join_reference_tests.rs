@@ -0,0 +1,203 @@
+//! HashJoin against a brute-force nested-loop join, for all four join types
+//! over randomly generated inputs (duplicate keys, empty sides, composite
+//! keys, null keys and null payloads)
+
+mod test_data_gen;
+
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::spill::{Codec, SpillManager};
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::traits::Operator;
+use test_data_gen::create_temp_spill_dir;
+
+const JOIN_TYPES: [&str; 4] = ["inner", "left", "right", "full"];
+
+/// Small deterministic generator (xorshift64*), so a failing case replays.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n.max(1)
+    }
+}
+
+/// `rows` rows of key columns `k` (and `k2` if `composite`) drawn from
+/// `keys` values, and a payload column `payload`; keys and payload are
+/// sometimes null.
+fn random_side(rng: &mut Rng, rows: usize, keys: u64, composite: bool, payload: &str) -> RowBatch {
+    let mut columns = vec![Column {
+        name: "k".into(),
+        values: (0..rows)
+            .map(|_| match rng.below(8) {
+                0 => Scalar::Null,
+                _ => Scalar::I32(rng.below(keys) as i32),
+            })
+            .collect(),
+    }];
+    if composite {
+        columns.push(Column {
+            name: "k2".into(),
+            values: (0..rows)
+                .map(|_| match rng.below(8) {
+                    0 => Scalar::Null,
+                    _ => Scalar::Str(format!("s{}", rng.below(2))),
+                })
+                .collect(),
+        });
+    }
+    columns.push(Column {
+        name: payload.into(),
+        values: (0..rows)
+            .map(|i| match rng.below(5) {
+                0 => Scalar::Null,
+                _ => Scalar::I64(i as i64),
+            })
+            .collect(),
+    });
+    RowBatch { columns }
+}
+
+fn rows(batch: &RowBatch) -> Vec<Vec<Scalar>> {
+    (0..batch.num_rows())
+        .map(|r| batch.columns.iter().map(|c| c.values[r].clone()).collect())
+        .collect()
+}
+
+/// Nested-loop join emitting the in-memory join's order: each left row with
+/// its matches in right order (or padded), then unmatched right rows. A key
+/// with a null in it matches nothing.
+fn reference_join(
+    left: &RowBatch,
+    right: &RowBatch,
+    keys: &[&str],
+    join_type: &str,
+) -> Vec<Vec<Scalar>> {
+    let key_of = |batch: &RowBatch, row: usize| -> Vec<Scalar> {
+        keys.iter()
+            .map(|k| {
+                let col = batch.columns.iter().find(|c| c.name == *k).unwrap();
+                col.values[row].clone()
+            })
+            .collect()
+    };
+    let (left_rows, right_rows) = (rows(left), rows(right));
+    let null_left = vec![Scalar::Null; left.columns.len()];
+    let null_right = vec![Scalar::Null; right.columns.len()];
+    let pad_left = matches!(join_type, "right" | "full");
+    let pad_right = matches!(join_type, "left" | "full");
+
+    let mut out = Vec::new();
+    let mut right_matched = vec![false; right_rows.len()];
+    for (l, left_row) in left_rows.iter().enumerate() {
+        let mut matched = false;
+        for (r, right_row) in right_rows.iter().enumerate() {
+            let key = key_of(left, l);
+            if !key.contains(&Scalar::Null) && key == key_of(right, r) {
+                matched = true;
+                right_matched[r] = true;
+                out.push([left_row.clone(), right_row.clone()].concat());
+            }
+        }
+        if !matched && pad_right {
+            out.push([left_row.clone(), null_right.clone()].concat());
+        }
+    }
+    if pad_left {
+        for (r, right_row) in right_rows.iter().enumerate() {
+            if !right_matched[r] {
+                out.push([null_left.clone(), right_row.clone()].concat());
+            }
+        }
+    }
+    out
+}
+
+fn join(keys: &[&str], join_type: &str) -> HashJoin {
+    HashJoin {
+        on: keys
+            .iter()
+            .map(|k| (k.to_string(), k.to_string()))
+            .collect(),
+        join_type: join_type.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_in_memory_join_matches_reference_for_all_join_types() {
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for case in 0..200 {
+        let composite = rng.below(3) == 0;
+        let keys: &[&str] = if composite { &["k", "k2"] } else { &["k"] };
+        let key_space = 1 + rng.below(8);
+        // Either side may be empty.
+        let left_rows = rng.below(25) as usize;
+        let right_rows = rng.below(25) as usize;
+        let left = random_side(&mut rng, left_rows, key_space, composite, "lv");
+        let right = random_side(&mut rng, right_rows, key_space, composite, "rv");
+
+        for join_type in JOIN_TYPES {
+            let out = join(keys, join_type)
+                .eval_block(&[left.clone(), right.clone()], &budget)
+                .unwrap();
+            assert_eq!(
+                out.columns.len(),
+                left.columns.len() + right.columns.len(),
+                "case {} {}",
+                case,
+                join_type
+            );
+            assert_eq!(
+                rows(&out),
+                reference_join(&left, &right, keys, join_type),
+                "case {} {} join\nleft: {:?}\nright: {:?}",
+                case,
+                join_type,
+                rows(&left),
+                rows(&right)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_grace_join_matches_in_memory_join_for_all_join_types() {
+    let dir = create_temp_spill_dir();
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+    let mut rng = Rng(42);
+    // Past the in-memory join's 100k-row threshold, with keys only one side
+    // has and null keys on both.
+    let left = random_side(&mut rng, 100_500, 150_000, false, "lv");
+    let right = random_side(&mut rng, 100_500, 150_000, false, "rv");
+
+    for join_type in JOIN_TYPES {
+        let expected = join(&["k"], join_type)
+            .eval_block(&[left.clone(), right.clone()], &budget)
+            .unwrap();
+        let mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+        let grace = HashJoin {
+            spill_mgr: Some(Arc::new(Mutex::new(mgr))),
+            num_partitions: Some(8),
+            deterministic: true,
+            ..join(&["k"], join_type)
+        };
+        let out = grace
+            .eval_block(&[left.clone(), right.clone()], &budget)
+            .unwrap();
+        assert_eq!(rows(&out), rows(&expected), "{} join", join_type);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -79,8 +79,10 @@ fn test_scan_filter_project_sink() {
     let te = plan_te(&phys_prog.plan, &work, 64 * 1024 * 1024).expect("TE planning failed");
 
     // Execute
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
 
@@ -101,7 +103,7 @@ fn test_scan_filter_project_sink() {
         output_lines.len() > 1,
         "Output should have header and data (got {} lines, content: {:?})",
         output_lines.len(),
-        if output_lines.len() > 0 {
+        if !output_lines.is_empty() {
             output_lines[0]
         } else {
             ""
@@ -177,8 +179,10 @@ fn test_sort_aggregate_pipeline() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 32 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
 
@@ -234,6 +238,7 @@ fn test_simple_map_pipeline() {
             F64(f) => f.to_string(),
             Str(s) => s.clone(),
             Bin(b) => format!("[binary {} bytes]", b.len()),
+            Date64(_) | Timestamp(_) | Decimal128(..) => v.to_string(),
         }
     }
 
@@ -274,8 +279,10 @@ fn test_simple_map_pipeline() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 16 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
 
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
@@ -342,6 +349,7 @@ fn test_project_column_selection() {
             F64(f) => f.to_string(),
             Str(s) => s.clone(),
             Bin(b) => format!("[binary {} bytes]", b.len()),
+            Date64(_) | Timestamp(_) | Decimal128(..) => v.to_string(),
         }
     }
 
@@ -384,8 +392,10 @@ fn test_project_column_selection() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 16 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
 
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
@@ -442,8 +452,10 @@ fn test_multiple_filters() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 16 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
 
@@ -541,8 +553,10 @@ fn test_parquet_scan_filter_project_sink() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 16 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let mut config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
 
@@ -610,8 +624,10 @@ fn test_csv_to_parquet_conversion() {
     let work = estimate_work(&optimized, None);
     let te = plan_te(&phys_prog.plan, &work, 16 * 1024 * 1024).expect("TE planning failed");
 
-    let mut config = EngineConfig::default();
-    config.spill_dir = temp_dir.clone();
+    let config = EngineConfig {
+        spill_dir: temp_dir.clone(),
+        ..Default::default()
+    };
     let mut engine = Engine::new(config).expect("engine init");
     let manifest = engine.run(&phys_prog, &te).expect("Execution failed");
 
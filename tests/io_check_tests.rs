@@ -0,0 +1,165 @@
+//! I/O preflight (`emsqrt validate --check-io`): sources sampled against
+//! their declared schema, sink destinations checked writable
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_exec::{check_io, IoIssue};
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline};
+use test_data_gen::create_temp_spill_dir;
+
+fn issues(yaml: &str) -> Vec<IoIssue> {
+    let parsed = parse_yaml_pipeline(yaml).unwrap();
+    let program = lower_to_physical(&parsed.plan);
+    check_io(&program, &EngineConfig::default(), 100)
+}
+
+#[test]
+fn test_reports_every_csv_and_sink_issue_at_once() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/in.csv", dir),
+        "id,name,age\n1,a,30\nx,b,old\n3,c,\n4,d,forty\n",
+    )
+    .unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64", nullable: false }}
+      - {{ name: "age", type: "Int32", nullable: true }}
+      - {{ name: "email", type: "Utf8", nullable: true }}
+  - op: sink
+    destination: "{dir}/missing/out.csv"
+    format: "csv"
+"#
+    );
+    let found: Vec<String> = issues(&yaml).iter().map(|i| i.message.clone()).collect();
+    assert_eq!(found.len(), 4, "{:?}", found);
+    assert!(
+        found[0].contains("missing column(s) 'email'"),
+        "{}",
+        found[0]
+    );
+    assert!(
+        found[1].contains("column 'id': 1 of 4 sampled values are not a valid Int64")
+            && found[1].contains("line 3: 'x'"),
+        "{}",
+        found[1]
+    );
+    // Empty cells are nulls, not parse errors.
+    assert!(
+        found[2].contains("column 'age': 2 of 4") && found[2].contains("'old'"),
+        "{}",
+        found[2]
+    );
+    assert!(found[3].contains("does not exist"), "{}", found[3]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_clean_pipeline_has_no_issues_and_leaves_sinks_alone() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(format!("{}/parts", dir)).unwrap();
+    fs::write(format!("{}/parts/a.csv", dir), "id,v\n1,x\n").unwrap();
+    fs::write(format!("{}/parts/b.csv", dir), "v,id\ny,2\n").unwrap();
+    fs::write(
+        format!("{}/events.jsonl", dir),
+        "{\"id\": 1, \"ok\": true}\n",
+    )
+    .unwrap();
+    fs::write(format!("{}/existing.csv", dir), "keep me\n").unwrap();
+    let pipeline = |source: &str, schema: &str| {
+        format!(
+            r#"
+steps:
+  - op: scan
+    source: "{dir}/{source}"
+    schema:
+{schema}
+  - op: sink
+    destination: "{dir}/existing.csv"
+    format: "csv"
+"#
+        )
+    };
+    let id = r#"      - { name: "id", type: "Int64", nullable: false }"#;
+    let globbed = pipeline("parts/*.csv", id);
+    let jsonl = pipeline(
+        "events.jsonl",
+        &format!("{id}\n      - {{ name: \"ok\", type: \"Bool\", nullable: false }}"),
+    );
+    assert!(issues(&globbed).is_empty(), "{:?}", issues(&globbed));
+    assert!(issues(&jsonl).is_empty(), "{:?}", issues(&jsonl));
+    assert_eq!(
+        fs::read_to_string(format!("{}/existing.csv", dir)).unwrap(),
+        "keep me\n"
+    );
+
+    // The writability probe does not leave a new destination behind.
+    fs::remove_file(format!("{}/existing.csv", dir)).unwrap();
+    assert!(issues(&globbed).is_empty());
+    assert!(fs::metadata(format!("{}/existing.csv", dir)).is_err());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_jsonl_types_missing_files_and_unknown_formats() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/events.jsonl", dir),
+        "{\"id\": 1, \"at\": \"2024-01-02\"}\n{\"id\": \"two\", \"at\": \"soon\"}\n",
+    )
+    .unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/events.jsonl"
+    schema:
+      - {{ name: "id", type: "Int64", nullable: false }}
+      - {{ name: "at", type: "Date64", nullable: false }}
+  - op: sink
+    destination: "{dir}/out.xlsx"
+    format: "xlsx"
+"#
+    );
+    let found = issues(&yaml);
+    let messages: Vec<&str> = found.iter().map(|i| i.message.as_str()).collect();
+    assert_eq!(messages.len(), 3, "{:?}", messages);
+    assert!(
+        messages[0].contains("column 'id': 1 of 2"),
+        "{}",
+        messages[0]
+    );
+    assert!(messages[0].contains("\"two\""), "{}", messages[0]);
+    assert!(
+        messages[1].contains("column 'at': 1 of 2"),
+        "{}",
+        messages[1]
+    );
+    assert!(messages[2].contains("unsupported sink format 'xlsx'"));
+
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/none/*.csv"
+    schema:
+      - {{ name: "id", type: "Int64", nullable: false }}
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    );
+    let found = issues(&yaml);
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].to_string().contains("none/*.csv"));
+    let _ = fs::remove_dir_all(&dir);
+}
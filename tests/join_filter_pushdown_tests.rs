@@ -0,0 +1,191 @@
+//! Filters on join inputs run inside the join, before rows are partitioned
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::expr::Expr;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::spill::{Codec, SpillManager};
+use emsqrt_operators::join::filter::InputFilters;
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::join::merge::MergeJoin;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::logical::{JoinType, LogicalPlan as L};
+use emsqrt_planner::lower_to_physical;
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_and_or_short_circuit() {
+    let batch = RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: vec![Scalar::I64(1)],
+        }],
+    };
+    // The right side would fail (no such column) if it were evaluated.
+    let and = Expr::parse("id > 5 AND missing > 1").unwrap();
+    assert_eq!(and.evaluate(&batch, 0).unwrap(), Scalar::Bool(false));
+    let or = Expr::parse("id == 1 OR missing > 1").unwrap();
+    assert_eq!(or.evaluate(&batch, 0).unwrap(), Scalar::Bool(true));
+    assert!(Expr::parse("id == 1 AND missing > 1")
+        .unwrap()
+        .evaluate(&batch, 0)
+        .is_err());
+}
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("v", DataType::Int64, false),
+    ])
+}
+
+fn join_over(left: L) -> L {
+    L::Join {
+        left: Box::new(left),
+        right: Box::new(L::Scan {
+            source: "right.csv".into(),
+            schema: schema(),
+        }),
+        on: vec![("id".into(), "id".into())],
+        join_type: JoinType::Inner,
+    }
+}
+
+#[test]
+fn test_lowering_moves_input_filters_into_the_join() {
+    let scan = L::Scan {
+        source: "left.csv".into(),
+        schema: schema(),
+    };
+    let program = lower_to_physical(&join_over(L::Filter {
+        input: Box::new(scan.clone()),
+        expr: "v > 10".into(),
+    }));
+    assert!(program.bindings.values().all(|b| b.key != "filter"));
+    let join = program
+        .bindings
+        .values()
+        .find(|b| b.key == "join_hash")
+        .unwrap();
+    assert_eq!(join.config["left_filter"], "v > 10");
+    assert!(join.config.get("right_filter").is_none());
+
+    // `rand()` depends on stream position, so that filter stays an operator.
+    let program = lower_to_physical(&join_over(L::Filter {
+        input: Box::new(scan),
+        expr: "rand() < 0.5".into(),
+    }));
+    assert!(program.bindings.values().any(|b| b.key == "filter"));
+    let join = program
+        .bindings
+        .values()
+        .find(|b| b.key == "join_hash")
+        .unwrap();
+    assert!(join.config.get("left_filter").is_none());
+}
+
+fn batch(ids: impl Iterator<Item = i64>, col: &str) -> RowBatch {
+    let ids: Vec<i64> = ids.collect();
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "id".into(),
+                values: ids.iter().map(|&i| Scalar::I64(i)).collect(),
+            },
+            Column {
+                name: col.into(),
+                values: ids.iter().map(|&i| Scalar::I64(i * 2)).collect(),
+            },
+        ],
+    }
+}
+
+fn sorted_ids(batch: &RowBatch) -> Vec<i64> {
+    let mut ids: Vec<i64> = batch.columns[0]
+        .values
+        .iter()
+        .map(|v| match v {
+            Scalar::I64(i) => *i,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    ids.sort_unstable();
+    ids
+}
+
+#[test]
+fn test_grace_join_partitions_only_rows_passing_the_filters() {
+    let temp = create_temp_spill_dir();
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+    let left = batch(0..150_000, "a");
+    let right = batch(0..150_000, "b");
+
+    let run = |name: &str, filters: InputFilters| {
+        let dir = format!("{}/{}", temp, name);
+        fs::create_dir_all(&dir).unwrap();
+        let mgr = Arc::new(Mutex::new(SpillManager::new(
+            Box::new(FsStorage::new()),
+            Codec::None,
+            dir.clone(),
+        )));
+        let join = HashJoin {
+            on: vec![("id".into(), "id".into())],
+            spill_mgr: Some(mgr.clone()),
+            num_partitions: Some(4),
+            filters,
+            ..Default::default()
+        };
+        let out = join
+            .eval_block(&[left.clone(), right.clone()], &budget)
+            .unwrap();
+        // Every partition is spilled before the first is read back.
+        let spilled = mgr.lock().unwrap().peak_bytes();
+        (sorted_ids(&out), spilled)
+    };
+
+    // The left side keeps enough rows for the Grace path.
+    let filters =
+        InputFilters::parse(Some("id >= 40000"), Some("b < 100000 OR id > 140000"), 0).unwrap();
+    let (fused, fused_bytes) = run("fused", filters);
+    let (unfiltered, unfiltered_bytes) = run("unfiltered", InputFilters::default());
+
+    let expected: Vec<i64> = (40_000..50_000).chain(140_001..150_000).collect();
+    assert_eq!(fused, expected);
+    assert_eq!(unfiltered.len(), 150_000);
+    assert!(
+        fused_bytes * 3 < unfiltered_bytes * 2,
+        "{} vs {}",
+        fused_bytes,
+        unfiltered_bytes
+    );
+
+    let _ = fs::remove_dir_all(&temp);
+}
+
+#[test]
+fn test_merge_join_applies_input_filters() {
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+    let join = MergeJoin {
+        on: vec![("id".into(), "id".into())],
+        join_type: "left".into(),
+        filters: InputFilters::parse(Some("id >= 2"), Some("b != 6"), 0).unwrap(),
+    };
+    let out = join
+        .eval_block(&[batch(0..5, "a"), batch(0..5, "b")], &budget)
+        .unwrap();
+    // Left rows 0 and 1 are filtered out; right row 3 (b == 6) is too, so
+    // left row 3 has no match.
+    assert_eq!(sorted_ids(&out), [2, 3, 4]);
+    let b = out.columns.iter().find(|c| c.name == "b").unwrap();
+    assert_eq!(b.values[1], Scalar::Null);
+
+    let bad = InputFilters::parse(Some("id > 1 AND"), None, 0).unwrap_err();
+    assert!(bad.contains("invalid join filter 'id > 1 AND'"), "{}", bad);
+}
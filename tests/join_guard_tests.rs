@@ -0,0 +1,188 @@
+//! Sampling-based output size pre-checks of hash joins (`EngineConfig::join_guard`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::{EngineConfig, JoinGuardAction, JoinGuardConfig};
+use emsqrt_core::dag::{JoinType as PlanJoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::join_guard::estimate_join_output;
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_operators::join::hash::JoinType;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn keys(ids: impl IntoIterator<Item = i64>) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: ids.into_iter().map(Scalar::I64).collect(),
+        }],
+    }
+}
+
+fn on() -> Vec<(String, String)> {
+    vec![("id".to_string(), "id".to_string())]
+}
+
+#[test]
+fn test_estimate_is_exact_when_samples_cover_the_inputs() {
+    let left = keys([1, 1, 2, 3]);
+    let right = keys([1, 1, 1, 2, 4]);
+    let est = estimate_join_output(&left, &right, &on(), JoinType::Inner, 100).unwrap();
+    assert_eq!(est.rows, 7);
+    assert_eq!(est.bytes, 7 * 16);
+    assert_eq!(est.sampled, (4, 5));
+
+    // Outer joins keep at least their preserved side.
+    let none = keys([9, 9, 9]);
+    let est = estimate_join_output(&left, &none, &on(), JoinType::Left, 100).unwrap();
+    assert_eq!(est.rows, 4);
+    let est = estimate_join_output(&left, &none, &on(), JoinType::Full, 100).unwrap();
+    assert_eq!(est.rows, 4);
+
+    let err = estimate_join_output(
+        &left,
+        &keys([1]),
+        &[("id".into(), "nope".into())],
+        JoinType::Inner,
+        10,
+    )
+    .unwrap_err();
+    assert!(err.contains("right join key 'nope'"), "{}", err);
+}
+
+#[test]
+fn test_sampled_estimate_scales_to_the_full_inputs() {
+    // Ten keys of 1000 rows each on both sides: 10 million output rows.
+    let side = keys((0..10_000).map(|i| i / 1000));
+    let est = estimate_join_output(&side, &side, &on(), JoinType::Inner, 1_000).unwrap();
+    assert_eq!(est.sampled, (1_000, 1_000));
+    assert_eq!(est.rows, 10_000_000);
+}
+
+fn join_plan(dir: &str) -> L {
+    let write = |name: &str, rows: usize| {
+        let path = format!("{}/{}.csv", dir, name);
+        let body: String = (0..rows).map(|i| format!("1,{}{}\n", name, i)).collect();
+        fs::write(&path, format!("id,{}\n{}", name, body)).unwrap();
+        L::Scan {
+            source: path,
+            schema: Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new(name, DataType::Utf8, false),
+            ]),
+        }
+    };
+    // Every row shares one key: 20 x 30 = 600 output rows.
+    L::Sink {
+        input: Box::new(L::Join {
+            left: Box::new(write("l", 20)),
+            right: Box::new(write("r", 30)),
+            on: on(),
+            join_type: PlanJoinType::Inner,
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".to_string(),
+    }
+}
+
+fn run(dir: &str, guard: JoinGuardConfig) -> Result<emsqrt_core::manifest::RunManifest, ExecError> {
+    let plan = join_plan(dir);
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        join_guard: Some(guard),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te)
+}
+
+#[test]
+fn test_guard_fails_or_warns_on_many_to_many_joins() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let bounded = JoinGuardConfig {
+        max_output_rows: Some(100),
+        ..Default::default()
+    };
+    match run(&dir, bounded.clone()) {
+        Err(ExecError::JoinGuard(msg)) => {
+            assert!(msg.contains("600 rows (max_output_rows 100)"), "{}", msg)
+        }
+        other => panic!("expected a join guard failure, got {:?}", other),
+    }
+    assert!(!fs::exists(format!("{}/out.csv", dir)).unwrap_or(false));
+
+    let manifest = run(
+        &dir,
+        JoinGuardConfig {
+            on_exceed: JoinGuardAction::Warn,
+            ..bounded
+        },
+    )
+    .unwrap();
+    assert_eq!(manifest.warnings.len(), 1);
+    assert!(
+        manifest.warnings[0].contains("join guard"),
+        "{}",
+        manifest.warnings[0]
+    );
+    let output = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+    assert_eq!(output.lines().count(), 601);
+
+    // Byte bounds apply too; generous bounds pass silently.
+    let err = run(
+        &dir,
+        JoinGuardConfig {
+            max_output_bytes: Some(1_000),
+            ..Default::default()
+        },
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("max_output_bytes 1000"), "{}", err);
+    let manifest = run(
+        &dir,
+        JoinGuardConfig {
+            max_output_rows: Some(600),
+            max_output_bytes: Some(1 << 20),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert!(manifest.warnings.is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_join_guard_parses_from_pipeline_config() {
+    let yaml = r#"
+config:
+  join_guard:
+    max_output_rows: 1000000
+    on_exceed: warn
+steps:
+  - op: scan
+    source: "data.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let guard = parse_yaml_pipeline(yaml)
+        .unwrap()
+        .config
+        .join_guard
+        .unwrap();
+    assert_eq!(guard.max_output_rows, Some(1_000_000));
+    assert_eq!(guard.max_output_bytes, None);
+    assert_eq!(guard.sample_rows, 10_000);
+    assert_eq!(guard.on_exceed, JoinGuardAction::Warn);
+}
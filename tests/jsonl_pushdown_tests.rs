@@ -0,0 +1,149 @@
+//! Projection and predicate pushdown into the JSONL reader
+
+mod test_data_gen;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::expr::{Expr, FieldPredicate};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::Engine;
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use std::fs;
+use test_data_gen::create_temp_spill_dir;
+
+const EVENTS: &str = r#"{"id": 1, "kind": "click", "payload": {"x": [1, 2, 3]}, "user": "alice"}
+{"id": 2, "kind": "view", "payload": "ignored", "user": "bob"}
+{"id": 3, "kind": "click", "payload": null, "user": "carol"}
+{"id": 4, "kind": "view!", "user": "dave"}
+"#;
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("user", DataType::Utf8, false),
+    ])
+}
+
+#[test]
+fn test_reader_projection_skips_unlisted_fields() {
+    let mut reader = JsonlReader::from_reader(EVENTS.as_bytes())
+        .unwrap()
+        .with_projection(vec!["id".into(), "user".into()]);
+    let batch = reader.next_batch(100).unwrap().unwrap();
+
+    let names: Vec<&str> = batch.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "user"]);
+    assert_eq!(batch.num_rows(), 4);
+    assert!(reader.next_batch(100).unwrap().is_none());
+}
+
+#[test]
+fn test_reader_predicates_drop_rows_while_parsing() {
+    let mut reader = JsonlReader::from_reader(EVENTS.as_bytes())
+        .unwrap()
+        .with_projection(vec!["user".into()])
+        .with_predicates(vec![FieldPredicate::Eq {
+            field: "kind".into(),
+            value: Scalar::Str("click".into()),
+        }]);
+    let batch = reader.next_batch(100).unwrap().unwrap();
+    assert_eq!(
+        batch.columns[0].values,
+        vec![Scalar::Str("alice".into()), Scalar::Str("carol".into())]
+    );
+
+    // `Contains` matches substrings of the decoded value.
+    let mut reader = JsonlReader::from_reader(EVENTS.as_bytes())
+        .unwrap()
+        .with_predicates(vec![FieldPredicate::Contains {
+            field: "kind".into(),
+            needle: "view!".into(),
+        }]);
+    let batch = reader.next_batch(100).unwrap().unwrap();
+    assert_eq!(batch.num_rows(), 1);
+}
+
+#[test]
+fn test_pushdown_predicates_only_take_top_level_equalities() {
+    let expr = Expr::parse("kind == 'click' AND id > 1").unwrap();
+    assert_eq!(
+        expr.pushdown_predicates(),
+        vec![FieldPredicate::Eq {
+            field: "kind".into(),
+            value: Scalar::Str("click".into()),
+        }]
+    );
+
+    let expr = Expr::parse("kind == 'click' OR id == 2").unwrap();
+    assert!(expr.pushdown_predicates().is_empty());
+}
+
+#[test]
+fn test_lowering_sets_jsonl_scan_config() {
+    let lp = L::Filter {
+        input: Box::new(L::Scan {
+            source: "file:///data/events.jsonl".into(),
+            schema: schema(),
+        }),
+        expr: "user == 'bob'".into(),
+    };
+    let prog = lower_to_physical(&lp);
+    let scan = prog
+        .bindings
+        .values()
+        .find(|b| b.key == "source")
+        .expect("source binding");
+    assert_eq!(scan.config["projection"], serde_json::json!(["id", "user"]));
+    assert_eq!(scan.config["predicates"][0]["field"], "user");
+
+    // CSV scans are left alone.
+    let lp = L::Scan {
+        source: "file:///data/events.csv".into(),
+        schema: schema(),
+    };
+    let prog = lower_to_physical(&lp);
+    assert!(prog
+        .bindings
+        .values()
+        .all(|b| b.config.get("projection").is_none()));
+}
+
+#[test]
+fn test_engine_filters_jsonl_with_pushdown() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/events.jsonl", dir);
+    let output = format!("{}/out.csv", dir);
+    fs::write(&input, EVENTS).unwrap();
+
+    let lp = L::Sink {
+        input: Box::new(L::Filter {
+            input: Box::new(L::Scan {
+                source: format!("file://{}", input),
+                schema: schema(),
+            }),
+            expr: "user == 'carol'".into(),
+        }),
+        destination: format!("file://{}", output),
+        format: "csv".into(),
+    };
+    let prog = lower_to_physical(&lp);
+    let work = estimate_work(&lp, None);
+    let te = plan_te(&prog.plan, &work, 64 * 1024 * 1024).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config).unwrap();
+    engine.run(&prog, &te).unwrap();
+
+    let written = fs::read_to_string(&output).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines, vec!["id,user", "3,carol"]);
+
+    let _ = fs::remove_dir_all(&dir);
+}
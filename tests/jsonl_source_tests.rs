@@ -0,0 +1,213 @@
+//! JSONL scans: `format: jsonl`, declared-type conversion and `flatten`
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::{DataType, Field, SourceFormat};
+use emsqrt_core::types::{RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn column<'a>(batch: &'a RowBatch, name: &str) -> &'a [Scalar] {
+    &batch
+        .columns
+        .iter()
+        .find(|c| c.name == name)
+        .unwrap_or_else(|| panic!("no column '{}'", name))
+        .values
+}
+
+#[test]
+fn test_reader_converts_to_declared_types_and_flattens() {
+    let lines = r#"{"id": "1", "score": 3, "zip": 2100, "user": {"name": "ann", "geo": {"lat": 1.5}}}
+{"id": 2, "score": "2.5", "zip": "02139", "user": {"name": "bo"}, "tags": [1, 2]}
+{"id": 3.5, "score": null, "zip": true, "user": "nobody"}
+"#;
+    let fields = [
+        Field::new("id", DataType::Int64, false),
+        Field::new("score", DataType::Float64, true),
+        Field::new("zip", DataType::Utf8, true),
+    ];
+    let mut reader = JsonlReader::from_reader(lines.as_bytes())
+        .unwrap()
+        .with_flatten(true)
+        .with_types(&fields);
+    let batch = reader.next_batch(10).unwrap().unwrap();
+
+    // A value that does not convert keeps its JSON type.
+    assert_eq!(
+        column(&batch, "id"),
+        [Scalar::I64(1), Scalar::I64(2), Scalar::F64(3.5)]
+    );
+    assert_eq!(
+        column(&batch, "score"),
+        [Scalar::F64(3.0), Scalar::F64(2.5), Scalar::Null]
+    );
+    assert_eq!(
+        column(&batch, "zip"),
+        [
+            Scalar::Str("2100".into()),
+            Scalar::Str("02139".into()),
+            Scalar::Str("true".into())
+        ]
+    );
+    assert_eq!(
+        column(&batch, "user.name"),
+        [
+            Scalar::Str("ann".into()),
+            Scalar::Str("bo".into()),
+            Scalar::Null
+        ]
+    );
+    assert_eq!(column(&batch, "user.geo.lat")[0], Scalar::F64(1.5));
+    // Non-objects stay as they are; arrays are JSON text.
+    assert_eq!(column(&batch, "user")[2], Scalar::Str("nobody".into()));
+    assert_eq!(column(&batch, "tags")[1], Scalar::Str("[1,2]".into()));
+}
+
+#[test]
+fn test_flattened_projection_reads_nested_fields() {
+    let lines = "{\"id\": 1, \"user\": {\"name\": \"ann\", \"age\": 30}, \"other\": {\"x\": 1}}\n";
+    let mut reader = JsonlReader::from_reader(lines.as_bytes())
+        .unwrap()
+        .with_flatten(true)
+        .with_projection(vec!["id".into(), "user.age".into()])
+        .with_types(&[Field::new("user.age", DataType::Int32, true)]);
+    let batch = reader.next_batch(10).unwrap().unwrap();
+    let names: Vec<&str> = batch.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["id", "user.age"]);
+    assert_eq!(column(&batch, "user.age"), [Scalar::I32(30)]);
+}
+
+fn run(dir: &str, input: &str, scan_options: &str) -> Result<String, String> {
+    let output = format!("{}/out.csv", dir);
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+{scan_options}
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "user.name", type: "Utf8" }}
+      - {{ name: "score", type: "Float64", nullable: true }}
+  - op: filter
+    expr: "id > 1"
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    );
+    let plan = parse_yaml_pipeline(&yaml).map_err(|e| e.to_string())?.plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .map_err(|e| e.to_string())?;
+    Ok(fs::read_to_string(&output).unwrap())
+}
+
+#[test]
+fn test_jsonl_scan_by_declared_format() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    // No .jsonl extension: only `format: jsonl` makes this a JSONL source.
+    let input = format!("{}/events.log", dir);
+    fs::write(
+        &input,
+        concat!(
+            "{\"id\": \"1\", \"user\": {\"name\": \"ann\"}, \"score\": 1}\n",
+            "{\"id\": 2, \"user\": {\"name\": \"bo\"}, \"score\": \"2.5\"}\n",
+            "{\"id\": \"3\", \"user\": {\"name\": \"cy\"}}\n",
+        ),
+    )
+    .unwrap();
+
+    let out = run(&dir, &input, "    format: jsonl\n    flatten: true").unwrap();
+    assert_eq!(out, "id,user.name,score\n2,bo,2.5\n3,cy,\n");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_unconvertible_values_follow_the_parse_error_policy() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.jsonl", dir);
+    fs::write(
+        &input,
+        concat!(
+            "{\"id\": 2, \"user\": {\"name\": \"ann\"}, \"score\": \"high\"}\n",
+            "{\"id\": 3, \"user\": {\"name\": \"bo\"}, \"score\": 4}\n",
+        ),
+    )
+    .unwrap();
+
+    // Default policy: the value becomes null.
+    let out = run(&dir, &input, "    flatten: true").unwrap();
+    assert_eq!(out, "id,user.name,score\n2,ann,\n3,bo,4\n");
+
+    let err = run(&dir, &input, "    flatten: true\n    on_parse_error: fail").unwrap_err();
+    assert!(
+        err.contains("column 'score' value \"high\" is not a valid Float64"),
+        "{}",
+        err
+    );
+
+    let dead = format!("{}/dead.jsonl", dir);
+    let options = format!(
+        "    flatten: true\n    on_parse_error: dead_letter\n    dead_letter: \"{}\"",
+        dead
+    );
+    let out = run(&dir, &input, &options).unwrap();
+    assert_eq!(out, "id,user.name,score\n3,bo,4\n");
+    let entry: serde_json::Value =
+        serde_json::from_str(fs::read_to_string(&dead).unwrap().trim()).unwrap();
+    assert_eq!(entry["column"], "score");
+    assert_eq!(entry["value"], "high");
+    assert_eq!(entry["record"]["user.name"], "ann");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_scan_format_parses_from_yaml() {
+    let scan = |options: &str| {
+        parse_yaml_pipeline(&format!(
+            "steps:\n  - op: scan\n    source: \"data.txt\"\n{}\n    schema:\n      - {{ name: \"id\", type: \"Int64\" }}\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n",
+            options
+        ))
+        .map(|p| p.plan)
+        .map_err(|e| e.to_string())
+    };
+    let plan = scan("    format: ndjson\n    flatten: true").unwrap();
+    let emsqrt_planner::logical::LogicalPlan::Sink { input, .. } = plan else {
+        panic!("expected a sink");
+    };
+    let emsqrt_planner::logical::LogicalPlan::Scan { schema, .. } = *input else {
+        panic!("expected a scan");
+    };
+    assert_eq!(schema.format, Some(SourceFormat::Jsonl));
+    assert!(schema.flatten);
+
+    let err = scan("    format: csv\n    flatten: true").unwrap_err();
+    assert!(
+        err.contains("'flatten' applies to JSONL sources only"),
+        "{}",
+        err
+    );
+    let err = scan("    format: xml").unwrap_err();
+    assert!(err.contains("unknown scan format 'xml'"), "{}", err);
+    let err = scan("    format: jsonl\n    delimiter: \"|\"").unwrap_err();
+    assert!(err.contains("text layout options"), "{}", err);
+}
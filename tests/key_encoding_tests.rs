@@ -0,0 +1,178 @@
+//! Binary key encoding for hash join / aggregate keys
+
+use emsqrt_core::hash::PartitionHasher;
+use emsqrt_core::key::{encode_key, encode_scalar};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::agregate::Aggregate;
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::traits::Operator;
+
+fn key(values: &[Scalar]) -> Vec<u8> {
+    let refs: Vec<&Scalar> = values.iter().collect();
+    let mut buf = Vec::new();
+    encode_key(&refs, &mut buf);
+    buf
+}
+
+fn col(name: &str, values: Vec<Scalar>) -> Column {
+    Column {
+        name: name.to_string(),
+        values,
+    }
+}
+
+#[test]
+fn test_encoding_preserves_order() {
+    let ordered = [
+        Scalar::Null,
+        Scalar::Bool(false),
+        Scalar::Bool(true),
+        Scalar::I64(i64::MIN),
+        Scalar::I32(-1),
+        Scalar::I64(0),
+        Scalar::I32(7),
+        Scalar::I64(i64::MAX),
+        Scalar::F64(f64::NEG_INFINITY),
+        Scalar::F64(-2.5),
+        Scalar::F32(0.0),
+        Scalar::F64(1.5),
+        Scalar::F64(f64::INFINITY),
+        Scalar::F64(f64::NAN),
+        Scalar::Str(String::new()),
+        Scalar::Str("a".into()),
+        Scalar::Str("a\0".into()),
+        Scalar::Str("a\u{1}".into()),
+        Scalar::Str("ab".into()),
+        Scalar::Bin(vec![0]),
+    ];
+    for pair in ordered.windows(2) {
+        let (a, b) = (key(&pair[..1]), key(&pair[1..]));
+        assert!(a < b, "{:?} should sort before {:?}", pair[0], pair[1]);
+    }
+}
+
+#[test]
+fn test_equal_values_share_a_key() {
+    assert_eq!(key(&[Scalar::I32(42)]), key(&[Scalar::I64(42)]));
+    assert_eq!(key(&[Scalar::F32(0.5)]), key(&[Scalar::F64(0.5)]));
+    assert_eq!(key(&[Scalar::F64(-0.0)]), key(&[Scalar::F64(0.0)]));
+    assert_ne!(key(&[Scalar::I64(1)]), key(&[Scalar::F64(1.0)]));
+    assert_ne!(key(&[Scalar::Null]), key(&[Scalar::Str("NULL".into())]));
+
+    // Column boundaries are unambiguous
+    assert_ne!(
+        key(&[Scalar::Str("a".into()), Scalar::Str("bc".into())]),
+        key(&[Scalar::Str("ab".into()), Scalar::Str("c".into())])
+    );
+
+    // Partition hashing follows key equality
+    let hasher = PartitionHasher::default();
+    assert_eq!(
+        hasher.hash_values(&[&Scalar::I32(42)]),
+        hasher.hash_values(&[&Scalar::I64(42)])
+    );
+}
+
+#[test]
+fn test_encode_scalar_appends() {
+    let mut buf = vec![0xAA];
+    encode_scalar(&Scalar::Bool(true), &mut buf);
+    assert_eq!(buf, vec![0xAA, 1, 1]);
+}
+
+#[test]
+fn test_hash_join_on_multiple_keys() {
+    let left = RowBatch {
+        columns: vec![
+            col("a", vec![Scalar::I64(1), Scalar::I64(1), Scalar::I64(2)]),
+            col(
+                "b",
+                vec![
+                    Scalar::Str("x".into()),
+                    Scalar::Str("y".into()),
+                    Scalar::Str("x".into()),
+                ],
+            ),
+        ],
+    };
+    let right = RowBatch {
+        columns: vec![
+            col("a2", vec![Scalar::I32(1), Scalar::I32(2)]),
+            col("b2", vec![Scalar::Str("y".into()), Scalar::Str("y".into())]),
+            col("v", vec![Scalar::I64(10), Scalar::I64(20)]),
+        ],
+    };
+    let join = HashJoin {
+        on: vec![("a".into(), "a2".into()), ("b".into(), "b2".into())],
+        ..Default::default()
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let out = join.eval_block(&[left, right], &budget).unwrap();
+
+    // Only (1, "y") matches on both columns
+    assert_eq!(out.num_rows(), 1);
+    let v = out.columns.iter().find(|c| c.name == "v").unwrap();
+    assert_eq!(v.values, vec![Scalar::I64(10)]);
+}
+
+#[test]
+fn test_aggregate_keeps_typed_multi_column_keys() {
+    let input = RowBatch {
+        columns: vec![
+            col(
+                "k1",
+                vec![
+                    Scalar::I64(1),
+                    Scalar::I64(1),
+                    Scalar::I64(2),
+                    Scalar::I64(1),
+                ],
+            ),
+            col(
+                "k2",
+                vec![
+                    Scalar::Str("a".into()),
+                    Scalar::Str("b".into()),
+                    Scalar::Str("a".into()),
+                    Scalar::Str("a".into()),
+                ],
+            ),
+            col(
+                "v",
+                vec![
+                    Scalar::I64(1),
+                    Scalar::I64(2),
+                    Scalar::I64(3),
+                    Scalar::I64(4),
+                ],
+            ),
+        ],
+    };
+    let agg = Aggregate {
+        group_by: vec!["k1".into(), "k2".into()],
+        aggs: vec!["sum:v".into()],
+        ..Default::default()
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let out = agg.eval_block(&[input], &budget).unwrap();
+
+    // Groups come out in first-seen order with their original types
+    assert_eq!(out.columns[0].name, "k1");
+    assert_eq!(
+        out.columns[0].values,
+        vec![Scalar::I64(1), Scalar::I64(1), Scalar::I64(2)]
+    );
+    assert_eq!(
+        out.columns[1].values,
+        vec![
+            Scalar::Str("a".into()),
+            Scalar::Str("b".into()),
+            Scalar::Str("a".into())
+        ]
+    );
+    assert_eq!(
+        out.columns[2].values,
+        vec![Scalar::F64(5.0), Scalar::F64(2.0), Scalar::F64(3.0)]
+    );
+}
@@ -0,0 +1,234 @@
+//! `let` steps: pipeline variables computed from data, used as `${name}`
+
+mod test_data_gen;
+
+use std::collections::BTreeSet;
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::expr::parse_literal;
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::{resolve_lets, Engine};
+use emsqrt_planner::{
+    estimate_work, let_literal, lower_to_physical, parse_yaml_pipeline,
+    parse_yaml_pipeline_with_params, substitute_variables, TemplateParams,
+};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_substitute_variables() {
+    let params: TemplateParams = [("day".to_string(), "'2024-01-02'".to_string())].into();
+    let none = BTreeSet::new();
+    assert_eq!(
+        substitute_variables("date >= ${day} AND date < ${ day }", &params, &none).unwrap(),
+        "date >= '2024-01-02' AND date < '2024-01-02'"
+    );
+    let err = substitute_variables("n > ${limit}", &params, &none).unwrap_err();
+    assert!(err.contains("undefined variable ${limit}"), "{}", err);
+    let pending = BTreeSet::from(["day".to_string()]);
+    let err = substitute_variables("date > ${day}", &params, &pending).unwrap_err();
+    assert!(err.contains("before its let step"), "{}", err);
+}
+
+#[test]
+fn test_let_literals_parse_back_to_the_value() {
+    for value in [
+        Scalar::I64(5),
+        Scalar::I32(-3),
+        Scalar::F64(2.5),
+        Scalar::F64(1e20),
+        Scalar::Bool(true),
+        Scalar::Str("2024-01-03".into()),
+        Scalar::Str("it's".into()),
+    ] {
+        let literal = let_literal(&value).unwrap();
+        assert_eq!(parse_literal(&literal).unwrap(), value, "{}", literal);
+    }
+    assert!(let_literal(&Scalar::Str("'\"".into())).is_err());
+    assert!(let_literal(&Scalar::F64(f64::NAN)).is_err());
+}
+
+fn pipeline(dir: &str, lets: &str, filter: &str) -> String {
+    format!(
+        r#"
+steps:
+{lets}
+  - op: scan
+    source: "{dir}/events.csv"
+    schema:
+      - {{ name: "date", type: "Utf8" }}
+      - {{ name: "n", type: "Int64" }}
+  - op: filter
+    expr: "{filter}"
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    )
+}
+
+const MAX_LOADED: &str = r#"
+  - op: let
+    name: max_loaded
+    reduce: max
+    column: date
+    default: "0000-01-01"
+    steps:
+      - op: scan
+        source: "{dir}/loaded.csv"
+        schema:
+          - { name: "date", type: "Utf8" }
+          - { name: "n", type: "Int64" }
+      - op: filter
+        expr: "n > 0""#;
+
+fn config(dir: &str) -> EngineConfig {
+    EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    }
+}
+
+fn run(dir: &str, yaml: &str) -> Result<String, String> {
+    let config = config(dir);
+    let params = resolve_lets(yaml, &TemplateParams::new(), &config).map_err(|e| e.to_string())?;
+    let plan = parse_yaml_pipeline_with_params(yaml, &params)
+        .map_err(|e| e.to_string())?
+        .plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .map_err(|e| e.to_string())?;
+    Ok(fs::read_to_string(format!("{}/out.csv", dir)).unwrap())
+}
+
+#[test]
+fn test_incremental_filter_on_max_loaded_date() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/events.csv", dir),
+        "date,n\n2024-01-01,1\n2024-01-02,2\n2024-01-03,3\n2024-01-04,4\n",
+    )
+    .unwrap();
+    let lets = MAX_LOADED.replace("{dir}", &dir);
+    let yaml = pipeline(&dir, &lets, "date > ${max_loaded}");
+
+    fs::write(
+        format!("{}/loaded.csv", dir),
+        "date,n\n2024-01-02,2\n2024-01-01,1\n",
+    )
+    .unwrap();
+    assert_eq!(
+        run(&dir, &yaml).unwrap(),
+        "date,n\n2024-01-03,3\n2024-01-04,4\n"
+    );
+
+    // Nothing loaded yet (only rows the let filters out): the default lets
+    // every row through.
+    fs::write(format!("{}/loaded.csv", dir), "date,n\n2024-01-09,0\n").unwrap();
+    assert_eq!(run(&dir, &yaml).unwrap().lines().count(), 5);
+
+    // Without a default, an empty result is an error.
+    let no_default = yaml.replace("    default: \"0000-01-01\"\n", "");
+    let err = run(&dir, &no_default).unwrap_err();
+    assert!(err.contains("let step 'max_loaded'"), "{}", err);
+    assert!(err.contains("give the let a 'default'"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_later_lets_use_earlier_values() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/events.csv", dir),
+        "date,n\n2024-01-01,5\n2024-01-02,2\n2024-01-03,9\n2024-01-04,1\n",
+    )
+    .unwrap();
+    // Rows holding over a quarter of the total; `big` counts them.
+    let lets = format!(
+        r#"
+  - op: let
+    name: total
+    reduce: sum
+    column: n
+    steps:
+      - op: scan
+        source: "{dir}/events.csv"
+        schema:
+          - {{ name: "date", type: "Utf8" }}
+          - {{ name: "n", type: "Int64" }}
+  - op: let
+    name: big
+    reduce: count
+    steps:
+      - op: scan
+        source: "{dir}/events.csv"
+        schema:
+          - {{ name: "date", type: "Utf8" }}
+          - {{ name: "n", type: "Int64" }}
+      - op: filter
+        expr: "n * 4 > ${{total}}"
+"#
+    );
+    let yaml = pipeline(&dir, &lets, "n * 4 > ${total} AND ${big} == 2");
+    let params = resolve_lets(&yaml, &TemplateParams::new(), &config(&dir)).unwrap();
+    assert_eq!(params["total"], "17i64");
+    assert_eq!(params["big"], "2i64");
+    assert_eq!(
+        run(&dir, &yaml).unwrap(),
+        "date,n\n2024-01-01,5\n2024-01-03,9\n"
+    );
+
+    // A --param of the same name replaces the computed value.
+    let given: TemplateParams = [("total".to_string(), "40".to_string())].into();
+    let params = resolve_lets(&yaml, &given, &config(&dir)).unwrap();
+    assert_eq!(params["total"], "40");
+    assert_eq!(params["big"], "0i64");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_let_errors() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        format!("{}/events.csv", dir),
+        "date,n\n2024-01-01,1\n2024-01-02,2\n",
+    )
+    .unwrap();
+    fs::write(format!("{}/loaded.csv", dir), "date,n\n2024-01-01,1\n").unwrap();
+    let lets = MAX_LOADED.replace("{dir}", &dir);
+
+    // Planning needs the values of the let steps.
+    let yaml = pipeline(&dir, &lets, "date > ${max_loaded}");
+    let err = parse_yaml_pipeline(&yaml).unwrap_err().to_string();
+    assert!(err.contains("let 'max_loaded' has no value"), "{}", err);
+
+    // A single-valued let over several rows.
+    let single = lets
+        .replace("    reduce: max\n", "")
+        .replace("loaded.csv", "events.csv");
+    let err = run(&dir, &pipeline(&dir, &single, "date > ${max_loaded}")).unwrap_err();
+    assert!(err.contains("yields 2 rows"), "{}", err);
+
+    // Several columns and no 'column' to pick one.
+    let wide = lets.replace("    column: date\n", "");
+    let err = run(&dir, &pipeline(&dir, &wide, "date > ${max_loaded}")).unwrap_err();
+    assert!(err.contains("name one with 'column'"), "{}", err);
+
+    // Variables are only defined after their let step.
+    let late = format!(
+        "steps:\n  - op: scan\n    source: \"{dir}/events.csv\"\n    schema:\n      - {{ name: \"date\", type: \"Utf8\" }}\n  - op: filter\n    expr: \"date > ${{max_loaded}}\"\n{lets}\n  - op: sink\n    destination: \"{dir}/out.csv\"\n    format: \"csv\"\n"
+    );
+    let err = run(&dir, &late).unwrap_err();
+    assert!(err.contains("before its let step"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,171 @@
+//! `limit`/`offset` and top-n by sort keys
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::SortKey;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::limit::TopN;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline, rules};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use test_data_gen::create_temp_spill_dir;
+
+const ROWS: i64 = 1000;
+
+/// Score of row `id`: scattered, with ties.
+fn score(id: i64) -> i64 {
+    (id * 7919) % 97
+}
+
+/// Run `scan(in.csv) → limit step → sink` in 40-row blocks; returns the
+/// operator keys and the output lines.
+fn run(dir: &str, limit_step: &str) -> (Vec<String>, Vec<String>) {
+    let rows: String = (0..ROWS)
+        .map(|id| format!("{},{}\n", id, score(id)))
+        .collect();
+    fs::write(format!("{}/in.csv", dir), format!("id,score\n{}", rows)).unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "score", type: "Int64" }}
+  - {{ op: limit, {limit_step} }}
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: csv
+"#
+    );
+    let plan = rules::optimize(parse_yaml_pipeline(&yaml).unwrap().plan);
+    let program = lower_to_physical(&plan);
+    let work = WorkEstimate {
+        total_rows: ROWS as u64,
+        total_bytes: ROWS as u64,
+        max_fan_in: 1,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block: 40 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    let keys = program.bindings.values().map(|b| b.key.clone()).collect();
+    let out = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+    (keys, out.lines().skip(1).map(str::to_string).collect())
+}
+
+fn line(id: i64) -> String {
+    format!("{},{}", id, score(id))
+}
+
+#[test]
+fn test_limit_and_offset_span_blocks() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    // Rows 95..125 cross the blocks starting at 80, 120.
+    let (keys, lines) = run(&dir, "limit: 30, offset: 95");
+    assert_eq!(keys, ["source", "limit", "sink"]);
+    assert_eq!(lines, (95..125).map(line).collect::<Vec<_>>());
+
+    // Past the end: what is left.
+    let (_, lines) = run(&dir, "limit: 25, offset: 990");
+    assert_eq!(lines, (990..ROWS).map(line).collect::<Vec<_>>());
+    let (_, lines) = run(&dir, "limit: 0");
+    assert!(lines.is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_sorted_limit_keeps_a_bounded_top_n() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let (keys, lines) = run(&dir, "limit: 12, offset: 5, order_by: [score desc, id]");
+    assert_eq!(keys, ["source", "top_n", "sink"]);
+    let mut expected: Vec<i64> = (0..ROWS).collect();
+    expected.sort_by_key(|&id| (-score(id), id));
+    assert_eq!(
+        lines,
+        expected[5..17]
+            .iter()
+            .map(|&id| line(id))
+            .collect::<Vec<_>>()
+    );
+
+    // Ties keep input order without a second key.
+    let (_, lines) = run(&dir, "limit: 15, order_by: [score]");
+    let mut expected: Vec<i64> = (0..ROWS).collect();
+    expected.sort_by_key(|&id| score(id));
+    assert_eq!(
+        lines,
+        expected[..15]
+            .iter()
+            .map(|&id| line(id))
+            .collect::<Vec<_>>()
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_sort_keys_and_top_n_budget() {
+    assert_eq!(
+        SortKey::parse("score DESC").unwrap(),
+        SortKey {
+            column: "score".into(),
+            descending: true,
+        }
+    );
+    assert!(!SortKey::parse("id asc").unwrap().descending);
+    assert_eq!(
+        SortKey::parse("score desc").unwrap().to_string(),
+        "score desc"
+    );
+    let err = SortKey::parse("score down").unwrap_err();
+    assert!(err.contains("expected 'asc' or 'desc'"), "{}", err);
+
+    let yaml = r#"
+steps:
+  - { op: scan, source: "in.csv", schema: [ { name: "id", type: "Int64" } ] }
+  - { op: limit, limit: 3, order_by: ["id sideways"] }
+  - { op: sink, destination: "out.csv", format: csv }
+"#;
+    let err = parse_yaml_pipeline(yaml).unwrap_err().to_string();
+    assert!(err.contains("expected 'asc' or 'desc'"), "{}", err);
+
+    // The kept rows draw on the budget.
+    let input = RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: (0..100).map(Scalar::I64).collect(),
+        }],
+    };
+    let top = TopN::new(vec![SortKey::parse("id desc").unwrap()], 50, 0);
+    let err = top
+        .eval_block(std::slice::from_ref(&input), &MemoryBudgetImpl::new(256))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("memory budget unavailable"), "{}", err);
+    let out = TopN::new(vec![SortKey::parse("id desc").unwrap()], 3, 0)
+        .eval_block(&[input], &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    assert_eq!(
+        out.columns[0].values,
+        [99, 98, 97].map(Scalar::I64).to_vec()
+    );
+}
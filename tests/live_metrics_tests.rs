@@ -0,0 +1,126 @@
+//! Live run metrics in the Prometheus text format
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{Engine, LiveMetrics};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// Run `scan → filter → sink` over 100 rows twice, recording into `metrics`.
+fn run_twice(dir: &str, metrics: &LiveMetrics) {
+    let input = format!("{}/in.csv", dir);
+    let rows: String = (0..100).map(|i| format!("{}\n", i)).collect();
+    fs::write(&input, format!("id\n{}", rows)).unwrap();
+    let lp = L::Sink {
+        input: Box::new(L::Filter {
+            input: Box::new(L::Scan {
+                source: input,
+                schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+            }),
+            expr: "id >= 40".into(),
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        mem_cap_bytes: 1 << 26,
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config)
+        .unwrap()
+        .with_live_metrics(metrics.clone());
+    for _ in 0..2 {
+        engine.run(&program, &te).unwrap();
+    }
+}
+
+/// The value of the sample line starting with `series`.
+fn sample(text: &str, series: &str) -> f64 {
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("no {} in\n{}", series, text))
+        .parse()
+        .unwrap()
+}
+
+#[test]
+fn test_live_metrics_accumulate_across_runs() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let metrics = LiveMetrics::new();
+    run_twice(&dir, &metrics);
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE emsqrt_blocks_completed_total counter"));
+    assert_eq!(sample(&text, "emsqrt_runs_started_total"), 2.0);
+    assert_eq!(sample(&text, "emsqrt_runs_completed_total"), 2.0);
+    assert_eq!(sample(&text, "emsqrt_runs_failed_total"), 0.0);
+    let run_blocks = sample(&text, "emsqrt_run_blocks");
+    assert_eq!(sample(&text, "emsqrt_run_blocks_completed"), run_blocks);
+    assert_eq!(
+        sample(&text, "emsqrt_blocks_completed_total"),
+        2.0 * run_blocks
+    );
+    assert_eq!(
+        sample(&text, "emsqrt_memory_cap_bytes"),
+        (1u64 << 26) as f64
+    );
+    assert_eq!(sample(&text, "emsqrt_memory_reserved_bytes"), 0.0);
+    assert_eq!(
+        sample(
+            &text,
+            "emsqrt_operator_rows_in_total{op_id=\"2\",op=\"filter\"}"
+        ),
+        200.0
+    );
+    assert_eq!(
+        sample(
+            &text,
+            "emsqrt_operator_rows_out_total{op_id=\"2\",op=\"filter\"}"
+        ),
+        120.0
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "metrics-server")]
+#[test]
+fn test_metrics_endpoint_serves_prometheus_text() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let metrics = LiveMetrics::new();
+    let server = emsqrt_exec::metrics::serve("127.0.0.1:0", metrics.clone()).unwrap();
+    run_twice(&dir, &metrics);
+
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    let body = response.split("\r\n\r\n").nth(1).unwrap();
+    assert_eq!(sample(body, "emsqrt_runs_completed_total"), 2.0);
+    assert!(get("/other").starts_with("HTTP/1.1 404"));
+
+    // A taken address is reported, not ignored.
+    let taken = server.local_addr().to_string();
+    assert!(emsqrt_exec::metrics::serve(&taken, metrics).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,181 @@
+//! Map operator: `expr AS alias` renames and computed columns
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{LogicalPlan as L, PhysicalPlan};
+use emsqrt_core::expr::{map_schema, Expr};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::map::Map;
+use emsqrt_operators::Operator;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("qty", DataType::Int32, false),
+        Field::new("price", DataType::Float64, true),
+        Field::new("name", DataType::Utf8, true),
+    ])
+}
+
+fn fields(schema: &Schema) -> Vec<(&str, &DataType)> {
+    schema
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), &f.data_type))
+        .collect()
+}
+
+#[test]
+fn test_map_list_parses_and_types_its_columns() {
+    let items =
+        Expr::parse_list("id AS order_id, qty * price AS total, name, qty > 2 AS bulk").unwrap();
+    assert_eq!(items.len(), 4);
+    assert_eq!(items[2], (Expr::Column("name".into()), None));
+
+    let out = map_schema(&items, &schema()).unwrap();
+    assert_eq!(
+        fields(&out),
+        [
+            ("order_id", &DataType::Int64),
+            ("qty", &DataType::Int32),
+            ("price", &DataType::Float64),
+            ("name", &DataType::Utf8),
+            ("total", &DataType::Float64),
+            ("bulk", &DataType::Boolean),
+        ]
+    );
+
+    // Later items see earlier ones; a computed column replaces its namesake.
+    let items =
+        Expr::parse_list("qty + 1 AS qty, qty * 2 AS double, name + '!' AS name, hash(id) AS h")
+            .unwrap();
+    let out = map_schema(&items, &schema()).unwrap();
+    assert_eq!(
+        fields(&out),
+        [
+            ("id", &DataType::Int64),
+            ("price", &DataType::Float64),
+            ("qty", &DataType::Int32),
+            ("double", &DataType::Int32),
+            ("name", &DataType::Utf8),
+            ("h", &DataType::Int64),
+        ]
+    );
+
+    let err = Expr::parse_list("id, qty * price").unwrap_err();
+    assert_eq!(err, "'qty * price' needs an alias ('qty * price AS name')");
+    assert!(Expr::parse_list("id AS").is_err());
+    assert!(Expr::parse_list("id AS a b").is_err());
+    let missing = Expr::parse_list("nope AS x").unwrap();
+    assert_eq!(
+        map_schema(&missing, &schema()).unwrap_err(),
+        "column 'nope' not found"
+    );
+    let text_math = Expr::parse_list("name - 1 AS x").unwrap();
+    assert!(map_schema(&text_math, &schema()).is_err());
+}
+
+#[test]
+fn test_operator_renames_and_computes_columns() {
+    use Scalar::*;
+    let batch = RowBatch {
+        columns: vec![
+            Column {
+                name: "a".into(),
+                values: vec![I32(1), I32(2), Null],
+            },
+            Column {
+                name: "b".into(),
+                values: vec![I64(10), I64(0), I64(5)],
+            },
+        ],
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let map = Map {
+        expr: Some("a AS x, x + b AS total, total * 2 AS total, b > 1 AS big".into()),
+        ..Default::default()
+    };
+    let out = map
+        .eval_block(std::slice::from_ref(&batch), &budget)
+        .unwrap();
+    let names: Vec<&str> = out.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["x", "b", "total", "big"]);
+    assert_eq!(out.columns[0].values, batch.columns[0].values);
+    assert_eq!(out.columns[2].values, [I64(22), I64(4), Null]);
+    assert_eq!(out.columns[3].values, [Bool(true), Bool(false), Bool(true)]);
+
+    // No list passes rows through.
+    let out = Map::default()
+        .eval_block(std::slice::from_ref(&batch), &budget)
+        .unwrap();
+    assert_eq!(out.columns[1].values, batch.columns[1].values);
+    assert_eq!(out.columns[1].name, "b");
+
+    let failing = Map {
+        expr: Some("b / a AS ratio, a / b AS broken".into()),
+        ..Default::default()
+    };
+    let err = failing.eval_block(&[batch], &budget).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("map column 'broken' failed at row 1: division by zero"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_map_step_writes_computed_columns() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/orders.csv", dir);
+    fs::write(&input, "id,qty,price,name\n1,2,1.5,pen\n2,4,0.25,clip\n").unwrap();
+    let output = format!("{}/out.csv", dir);
+
+    let lp = L::Sink {
+        input: Box::new(L::Map {
+            input: Box::new(L::Scan {
+                source: input.clone(),
+                schema: schema(),
+            }),
+            expr: "id AS order_id, qty * price AS total".into(),
+        }),
+        destination: output.clone(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let PhysicalPlan::Sink { input: map, .. } = &program.plan else {
+        panic!("expected a sink");
+    };
+    let PhysicalPlan::Unary { schema, .. } = map.as_ref() else {
+        panic!("expected the map");
+    };
+    assert_eq!(
+        fields(schema)
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>(),
+        ["order_id", "qty", "price", "name", "total"]
+    );
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "order_id,qty,price,name,total\n1,2,1.5,pen,3\n2,4,0.25,clip,1\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,125 @@
+//! `mem://` sources and sinks, and the in-memory spill backend
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, MemTables};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+
+fn batch(ids: &[i64], names: &[&str]) -> RowBatch {
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "name".into(),
+                values: names.iter().map(|n| Scalar::Str(n.to_string())).collect(),
+            },
+            Column {
+                name: "id".into(),
+                values: ids.iter().map(|&v| Scalar::I64(v)).collect(),
+            },
+        ],
+    }
+}
+
+/// Run `yaml` on an engine spilling to `mem://`, sharing `tables`.
+fn run(yaml: &str, tables: &MemTables) -> Result<(), emsqrt_exec::ExecError> {
+    let lp = parse_yaml_pipeline(yaml).unwrap().plan;
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 20).unwrap();
+    let config = EngineConfig {
+        spill_uri: Some("mem://spill".into()),
+        ..Default::default()
+    };
+    Engine::new(config)?
+        .with_mem_tables(tables.clone())
+        .run(&program, &te)
+        .map(|_| ())
+}
+
+fn ids(batches: &[RowBatch]) -> Vec<Scalar> {
+    batches
+        .iter()
+        .flat_map(|b| b.columns[0].values.clone())
+        .collect()
+}
+
+const PIPELINE: &str = r#"
+steps:
+  - op: scan
+    source: "mem://people"
+    schema:
+      - { name: "id", type: "Int64" }
+      - { name: "name", type: "Utf8" }
+  - op: filter
+    expr: "id > 1"
+  - op: sink
+    destination: "mem://out"
+    format: "csv"
+"#;
+
+#[test]
+fn test_pipeline_runs_in_memory() {
+    let tables = MemTables::new();
+    tables.insert(
+        "people",
+        vec![batch(&[1, 2], &["a", "b"]), batch(&[3, 4], &["c", "d"])],
+    );
+
+    run(PIPELINE, &tables).unwrap();
+
+    let out = tables.take("out").unwrap();
+    // Columns follow the scan schema, not the table's order.
+    assert_eq!(out[0].columns[0].name, "id");
+    assert_eq!(
+        ids(&out),
+        vec![Scalar::I64(2), Scalar::I64(3), Scalar::I64(4)]
+    );
+    // The source table is left as it was.
+    assert_eq!(tables.get("people").unwrap().len(), 2);
+}
+
+#[test]
+fn test_sink_replaces_table_each_run() {
+    let tables = MemTables::new();
+    tables.insert("people", vec![batch(&[5], &["e"])]);
+    tables.insert("out", vec![batch(&[9, 9], &["x", "y"])]);
+
+    run(PIPELINE, &tables).unwrap();
+    assert_eq!(ids(&tables.get("out").unwrap()), vec![Scalar::I64(5)]);
+
+    // No rows pass: the table exists but is empty.
+    tables.insert("people", vec![batch(&[1], &["a"])]);
+    run(PIPELINE, &tables).unwrap();
+    assert!(ids(&tables.get("out").unwrap()).is_empty());
+}
+
+#[test]
+fn test_missing_table_and_column_fail() {
+    let tables = MemTables::new();
+    let err = run(PIPELINE, &tables).unwrap_err();
+    assert!(err.to_string().contains("mem table 'people'"), "{}", err);
+
+    tables.insert(
+        "people",
+        vec![RowBatch {
+            columns: vec![Column {
+                name: "id".into(),
+                values: vec![Scalar::I64(1)],
+            }],
+        }],
+    );
+    let err = run(PIPELINE, &tables).unwrap_err();
+    assert!(err.to_string().contains("no column 'name'"), "{}", err);
+}
+
+#[test]
+fn test_engine_tables_are_shared_with_clones() {
+    let engine = Engine::new(EngineConfig {
+        spill_uri: Some("mem://spill".into()),
+        ..Default::default()
+    })
+    .unwrap();
+    let tables = engine.mem_tables();
+    tables.insert("t", vec![batch(&[1], &["a"])]);
+    assert_eq!(engine.mem_tables().names(), vec!["t".to_string()]);
+}
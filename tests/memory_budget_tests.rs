@@ -1,9 +1,11 @@
 //! Memory budget enforcement tests
 
 use emsqrt_core::budget::{BudgetGuard, MemoryBudget};
-use emsqrt_mem::MemoryBudgetImpl;
-use std::sync::Arc;
+use emsqrt_mem::error::Error;
+use emsqrt_mem::{MemoryBudgetImpl, ReservationMode};
+use std::sync::{Arc, Barrier};
 use std::thread;
+use std::time::{Duration, Instant};
 
 #[test]
 fn test_budget_acquire_release() {
@@ -89,7 +91,7 @@ fn test_budget_concurrent_access() {
     let mut handles = vec![];
 
     // Spawn 10 threads, each acquiring and releasing 50KB
-    for i in 0..10 {
+    for _i in 0..10 {
         let budget_clone: Arc<MemoryBudgetImpl> = Arc::clone(&budget);
         let handle = thread::spawn(move || {
             // Try to acquire 50KB
@@ -275,3 +277,125 @@ fn test_budget_high_contention() {
     // All memory should be released
     assert_eq!(budget.used_bytes(), 0);
 }
+
+#[test]
+fn test_wait_is_granted_on_release() {
+    let budget = MemoryBudgetImpl::new(100);
+    let holder = budget.try_acquire(80, "holder").unwrap();
+
+    let waiter = {
+        let budget = budget.clone();
+        thread::spawn(move || {
+            budget
+                .acquire_timeout(50, "waiter", Some(Duration::from_secs(10)))
+                .map(|g| g.bytes())
+        })
+    };
+    while budget.waiting() == 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    drop(holder);
+
+    assert_eq!(waiter.join().unwrap().unwrap(), 50);
+    assert_eq!(budget.used_bytes(), 0);
+    assert_eq!(budget.waiting(), 0);
+}
+
+#[test]
+fn test_wait_times_out_and_rejects_oversized_requests() {
+    let budget = MemoryBudgetImpl::new(100);
+    // Held by a thread that is not waiting, so this is not a deadlock.
+    let holder = {
+        let budget = budget.clone();
+        thread::spawn(move || {
+            let _guard = budget.try_acquire(80, "holder").unwrap();
+            thread::sleep(Duration::from_millis(300));
+        })
+    };
+    while budget.used_bytes() == 0 {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    let err = budget
+        .acquire_timeout(50, "waiter", Some(Duration::from_millis(30)))
+        .err()
+        .unwrap();
+    assert!(
+        matches!(err, Error::ReservationTimeout { requested: 50, .. }),
+        "{}",
+        err
+    );
+
+    let err = budget.acquire_timeout(101, "huge", None).err().unwrap();
+    assert!(matches!(err, Error::BudgetExceeded { .. }), "{}", err);
+    holder.join().unwrap();
+}
+
+#[test]
+fn test_waiters_holding_the_whole_cap_are_a_deadlock() {
+    let budget = MemoryBudgetImpl::new(100);
+    let barrier = Arc::new(Barrier::new(2));
+    let started = Instant::now();
+
+    // Each thread holds part of the cap and then waits for more than is free.
+    let handles: Vec<_> = [50usize, 40]
+        .into_iter()
+        .map(|held| {
+            let budget = budget.clone();
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                let _held = budget.try_acquire(held, "held").unwrap();
+                barrier.wait();
+                budget
+                    .acquire_timeout(30, "more", Some(Duration::from_secs(30)))
+                    .map(|g| g.bytes())
+            })
+        })
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    // One waiter gives up; its released bytes let the other through.
+    let deadlocks = results
+        .iter()
+        .filter(|r| matches!(r, Err(Error::Deadlock { .. })))
+        .count();
+    assert_eq!(deadlocks, 1, "{:?}", results);
+    assert!(results.iter().any(|r| matches!(r, Ok(30))));
+    assert!(started.elapsed() < Duration::from_secs(10));
+    assert_eq!(budget.used_bytes(), 0);
+}
+
+#[test]
+fn test_wait_mode_try_acquire_fails_fast_when_alone() {
+    let budget = MemoryBudgetImpl::new(100)
+        .with_reservation_mode(ReservationMode::Wait(Duration::from_secs(30)));
+    let _all = budget.try_acquire(100, "all").unwrap();
+
+    // Only this thread holds memory, so waiting could never succeed.
+    let started = Instant::now();
+    assert!(budget.try_acquire(1, "more").is_none());
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_child_budget_is_capped_by_itself_and_its_parent() {
+    let parent = MemoryBudgetImpl::new(1000);
+    let child = parent.child(600);
+
+    let mut guard = child.try_acquire(500, "child").unwrap();
+    assert_eq!((child.used_bytes(), parent.used_bytes()), (500, 500));
+    // Over the child's own cap, though the parent has room.
+    assert!(!guard.try_resize(700));
+    assert!(child.try_acquire(200, "child").is_none());
+    assert_eq!(parent.used_bytes(), 500);
+
+    // Within the child's cap, but the parent is full.
+    let other = parent.try_acquire(450, "other").unwrap();
+    assert!(child.try_acquire(100, "child").is_none());
+    assert_eq!((child.used_bytes(), parent.used_bytes()), (500, 950));
+
+    drop(other);
+    assert!(guard.try_resize(600));
+    drop(guard);
+    assert_eq!((child.used_bytes(), parent.used_bytes()), (0, 0));
+}
@@ -1,7 +1,5 @@
 //! Merge join operator tests
 
-use emsqrt_core::dag::JoinType;
-use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
 use emsqrt_mem::guard::MemoryBudgetImpl;
 use emsqrt_operators::join::merge::MergeJoin;
@@ -49,9 +47,11 @@ fn create_sorted_right_batch() -> RowBatch {
 
 #[test]
 fn test_merge_join_inner() {
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "inner".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "inner".to_string(),
+        ..Default::default()
+    };
 
     let left = create_sorted_left_batch();
     let right = create_sorted_right_batch();
@@ -66,9 +66,11 @@ fn test_merge_join_inner() {
 
 #[test]
 fn test_merge_join_left() {
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "left".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "left".to_string(),
+        ..Default::default()
+    };
 
     let left = create_sorted_left_batch();
     let right = create_sorted_right_batch();
@@ -82,9 +84,11 @@ fn test_merge_join_left() {
 
 #[test]
 fn test_merge_join_right() {
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "right".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "right".to_string(),
+        ..Default::default()
+    };
 
     let left = create_sorted_left_batch();
     let right = create_sorted_right_batch();
@@ -98,9 +102,11 @@ fn test_merge_join_right() {
 
 #[test]
 fn test_merge_join_full() {
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "full".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "full".to_string(),
+        ..Default::default()
+    };
 
     let left = create_sorted_left_batch();
     let right = create_sorted_right_batch();
@@ -144,9 +150,11 @@ fn test_merge_join_duplicate_keys() {
         ],
     };
 
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "inner".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "inner".to_string(),
+        ..Default::default()
+    };
 
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
     let result = join.eval_block(&[left, right], &budget).unwrap();
@@ -171,9 +179,11 @@ fn test_merge_join_empty_inputs() {
         }],
     };
 
-    let mut join = MergeJoin::default();
-    join.on = vec![("id".to_string(), "id".to_string())];
-    join.join_type = "inner".to_string();
+    let join = MergeJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        join_type: "inner".to_string(),
+        ..Default::default()
+    };
 
     let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
     let result = join.eval_block(&[left, right], &budget).unwrap();
@@ -0,0 +1,142 @@
+//! Glob and directory scan sources
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{with_source_files, Engine};
+use emsqrt_io::files::list_source_files;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use test_data_gen::create_temp_spill_dir;
+
+fn scan_to_csv(source: &str, destination: &str) -> L {
+    L::Sink {
+        input: Box::new(L::Scan {
+            source: source.to_string(),
+            schema: Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("day", DataType::Utf8, false),
+            ]),
+        }),
+        destination: destination.to_string(),
+        format: "csv".into(),
+    }
+}
+
+/// Run `lp` in `rows_per_block`-row source blocks; `total_rows` places them.
+fn run(dir: &str, lp: &L, total_rows: u64, rows_per_block: u64) -> Result<(), String> {
+    let program = lower_to_physical(lp);
+    let work = WorkEstimate {
+        total_rows,
+        total_bytes: total_rows,
+        max_fan_in: 1,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn test_glob_reads_files_in_path_order_across_blocks() {
+    let dir = create_temp_spill_dir();
+    let data = format!("{}/data", dir);
+    fs::create_dir_all(&data).unwrap();
+    // Written out of order; 2024-01-02 has only a header.
+    let days = [("2024-01-03", 4), ("2024-01-01", 9), ("2024-01-02", 0)];
+    let mut id = 0;
+    for (day, rows) in days {
+        let body: String = (0..rows)
+            .map(|_| {
+                id += 1;
+                format!("{},{}\n", id, day)
+            })
+            .collect();
+        fs::write(format!("{}/{}.csv", data, day), format!("id,day\n{}", body)).unwrap();
+    }
+    fs::write(format!("{}/2023-12-31.csv", data), "id,day\n99,old\n").unwrap();
+    fs::write(format!("{}/_2024-01-04.csv", data), "id,day\n98,hidden\n").unwrap();
+
+    let source = format!("file://{}/2024-*.csv", data);
+    let files = list_source_files(&source["file://".len()..]).unwrap();
+    let names: Vec<&str> = files.iter().map(|f| &f[data.len() + 1..]).collect();
+    assert_eq!(
+        names,
+        ["2024-01-01.csv", "2024-01-02.csv", "2024-01-03.csv"]
+    );
+
+    // The files' sizes reach the work estimate.
+    let destination = format!("{}/out.csv", dir);
+    let lp = scan_to_csv(&source, &destination);
+    let hint = with_source_files(&lp, None).unwrap();
+    let (listed, files) = &hint.source_files[0];
+    assert_eq!(listed, &source);
+    assert_eq!(files.len(), 3);
+    assert!(files.iter().all(|f| f.rows.is_none()));
+    let total: u64 = files.iter().map(|f| f.bytes).sum();
+    assert_eq!(estimate_work(&lp, Some(&hint)).total_bytes, total);
+
+    // 5-row blocks: the first spans the end of 2024-01-01, the empty file
+    // is skipped and the last block reads to the end of 2024-01-03.
+    run(&dir, &lp, 13, 5).unwrap();
+    let out = fs::read_to_string(&destination).unwrap();
+    let mut expected = vec!["id,day".to_string()];
+    expected.extend((5..=13).map(|i| format!("{},2024-01-01", i)));
+    expected.extend((1..=4).map(|i| format!("{},2024-01-03", i)));
+    assert_eq!(out.lines().collect::<Vec<_>>(), expected);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_directory_and_nested_glob_sources() {
+    let dir = create_temp_spill_dir();
+    for (part, day, ids) in [("b", "2", 3..5), ("a", "1", 0..3)] {
+        let sub = format!("{}/in/day={}", dir, day);
+        fs::create_dir_all(&sub).unwrap();
+        let body: String = ids.map(|i| format!("{},{}\n", i, day)).collect();
+        fs::write(
+            format!("{}/part-{}.csv", sub, part),
+            format!("id,day\n{}", body),
+        )
+        .unwrap();
+        fs::write(format!("{}/.part-{}.csv.crc", sub, part), "x").unwrap();
+    }
+
+    let destination = format!("{}/out.csv", dir);
+    let lp = scan_to_csv(&format!("{}/in/day=*/part-?.csv", dir), &destination);
+    run(&dir, &lp, 5, 2).unwrap();
+    let out = fs::read_to_string(&destination).unwrap();
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        ["id,day", "0,1", "1,1", "2,1", "3,2", "4,2"]
+    );
+
+    // A directory reads its own files, without the hidden checksum file.
+    let lp = scan_to_csv(&format!("{}/in/day=2", dir), &destination);
+    run(&dir, &lp, 2, 1).unwrap();
+    let out = fs::read_to_string(&destination).unwrap();
+    assert_eq!(out.lines().collect::<Vec<_>>(), ["id,day", "3,2", "4,2"]);
+
+    let lp = scan_to_csv(&format!("{}/in/day=*/*.parquet", dir), &destination);
+    let err = run(&dir, &lp, 1, 1).unwrap_err();
+    assert!(err.contains("no files match"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
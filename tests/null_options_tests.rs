@@ -0,0 +1,125 @@
+//! Source null normalization: `empty_as_null`, `null_tokens`, `trim_whitespace`
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::NullOptions;
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// Run `scan(input) → sink(jsonl)` with the given extra scan options and
+/// return the output lines.
+fn run(dir: &str, input: &str, options: &str) -> Vec<String> {
+    let output = format!("{}/out.jsonl", dir);
+    let _ = fs::remove_file(&output);
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "name", type: "Utf8", nullable: true }}
+      - {{ name: "n", type: "Int64", nullable: true }}
+{options}
+  - op: sink
+    destination: "{output}"
+    format: "jsonl"
+"#
+    );
+    let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_null_options_normalize() {
+    let opts = NullOptions {
+        empty_as_null: true,
+        null_tokens: vec!["NA".into(), "\\N".into()],
+        trim_whitespace: true,
+    };
+    assert_eq!(opts.normalize("  x "), Some("x"));
+    assert_eq!(opts.normalize("   "), None);
+    assert_eq!(opts.normalize(" NA"), None);
+    assert_eq!(opts.normalize("\\N"), None);
+    assert_eq!(opts.normalize("na"), Some("na"));
+    assert_eq!(NullOptions::default().normalize(" "), Some(" "));
+}
+
+#[test]
+fn test_csv_defaults_keep_cells() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "name,n\n,1\nNA, 2\n").unwrap();
+
+    // Empty strings stay strings; " 2" does not parse, so it is null.
+    assert_eq!(
+        run(&dir, &input, ""),
+        vec![r#"{"n":1,"name":""}"#, r#"{"n":null,"name":"NA"}"#]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_csv_null_options() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "name,n\n,1\nNA, 2\n  b ,\\N\n").unwrap();
+
+    let options = r#"    empty_as_null: true
+    null_tokens: ["NA", "\\N"]
+    trim_whitespace: true"#;
+    assert_eq!(
+        run(&dir, &input, options),
+        vec![
+            r#"{"n":1,"name":null}"#,
+            r#"{"n":2,"name":null}"#,
+            r#"{"n":null,"name":"b"}"#,
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_jsonl_string_values_are_normalized() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.jsonl", dir);
+    fs::write(
+        &input,
+        "{\"name\":\"\",\"n\":1}\n{\"name\":\" NA \",\"n\":2}\n{\"name\":\" c\",\"n\":3}\n",
+    )
+    .unwrap();
+
+    let options = r#"    empty_as_null: true
+    null_tokens: ["NA"]
+    trim_whitespace: true"#;
+    assert_eq!(
+        run(&dir, &input, options),
+        vec![
+            r#"{"n":1,"name":null}"#,
+            r#"{"n":2,"name":null}"#,
+            r#"{"n":3,"name":"c"}"#,
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,115 @@
+//! Per-block operator context: range, spill scope, metrics, cancellation
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_operators::filter::Filter;
+use emsqrt_operators::{OpContext, Operator, SpillScope};
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(values: &[i64]) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: values.iter().map(|&v| Scalar::I64(v)).collect(),
+        }],
+    }
+}
+
+#[test]
+fn test_default_context_matches_eval_block() {
+    let op = Filter {
+        expr: Some("id > 1".into()),
+        ..Default::default()
+    };
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let input = [batch(&[1, 2, 3])];
+
+    let plain = op.eval_block(&input, &budget).unwrap();
+    let with_ctx = op
+        .eval_block_with(&input, &OpContext::default(), &budget)
+        .unwrap();
+    assert_eq!(plain.columns[0].values, with_ctx.columns[0].values);
+    assert_eq!(with_ctx.num_rows(), 2);
+}
+
+#[test]
+fn test_spill_scope_removes_segments_on_drop() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let spill = Arc::new(Mutex::new(SpillManager::new(
+        Box::new(FsStorage::new()),
+        Codec::None,
+        dir.clone(),
+    )));
+    let budget = MemoryBudgetImpl::new(1 << 20);
+
+    let ctx = OpContext {
+        spill: Some(SpillScope::new(spill.clone())),
+        ..Default::default()
+    };
+    let scope = ctx.spill.as_ref().unwrap();
+    let kept = scope.write(&batch(&[1, 2])).unwrap();
+    let deleted = scope.write(&batch(&[3])).unwrap();
+    assert_eq!(
+        scope.read(&kept, &budget).unwrap().columns[0].values,
+        batch(&[1, 2]).columns[0].values
+    );
+    scope.delete(&deleted.name);
+    assert_eq!(scope.live_segments(), 1);
+    assert_eq!(spill.lock().unwrap().list_segments().len(), 1);
+
+    drop(ctx);
+    assert!(spill.lock().unwrap().list_segments().is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cancelled_run_stops_before_first_block() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    fs::write(&input, "id\n1\n2\n").unwrap();
+
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: input,
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        destination: output.clone(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 20).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config).unwrap();
+    let token = engine.cancel_token();
+    token.cancel();
+    let err = engine.run(&program, &te).unwrap_err();
+    assert!(
+        matches!(err, ExecError::Cancelled(id) if id == te.order[0].id.get()),
+        "{}",
+        err
+    );
+    assert!(!std::path::Path::new(&output).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
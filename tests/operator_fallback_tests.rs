@@ -0,0 +1,106 @@
+//! Operator fallback chains for persistent recoverable failures
+
+mod test_data_gen;
+
+use std::collections::BTreeMap;
+
+use emsqrt_core::config::{EngineConfig, FallbackAction, SandboxConfig};
+use emsqrt_core::schema::DataType;
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_planner::parse_yaml_pipeline;
+use emsqrt_planner::physical::OperatorBinding;
+use test_data_gen::{create_temp_spill_dir, generate_sorted_batch};
+
+// Large enough for HashJoin to pick the Grace (spilling) path.
+const ROWS: usize = 100_001;
+
+fn join_binding() -> OperatorBinding {
+    OperatorBinding {
+        key: "join_hash".to_string(),
+        config: serde_json::json!({
+            "on": [["id", "id"]],
+            "join_type": "inner",
+        }),
+    }
+}
+
+/// An engine whose spill storage refuses any write, so Grace join keeps failing.
+fn engine_without_spill(fallbacks: Vec<FallbackAction>) -> Engine {
+    let mut chains = BTreeMap::new();
+    if !fallbacks.is_empty() {
+        chains.insert("join_hash".to_string(), fallbacks);
+    }
+    let config = EngineConfig {
+        spill_dir: create_temp_spill_dir(),
+        sandbox: Some(SandboxConfig {
+            max_spill_bytes: Some(0),
+            ..Default::default()
+        }),
+        fallbacks: chains,
+        ..Default::default()
+    };
+    Engine::new(config).unwrap()
+}
+
+fn inputs() -> Vec<emsqrt_core::types::RowBatch> {
+    vec![
+        generate_sorted_batch(ROWS, "id", DataType::Int64),
+        generate_sorted_batch(ROWS, "id", DataType::Int64),
+    ]
+}
+
+#[test]
+fn test_persistent_failure_without_fallbacks_errors() {
+    let engine = engine_without_spill(vec![]);
+    match engine.eval_binding(&join_binding(), &inputs()) {
+        Err(ExecError::Operator(msg)) => assert!(msg.contains("failed to spill"), "{}", msg),
+        other => panic!("expected spill failure, got {:?}", other.map(|(b, _)| b)),
+    }
+}
+
+#[test]
+fn test_fallback_chain_switches_strategy() {
+    // Smaller partitions still need spilling, so the chain moves on to merge join.
+    let engine = engine_without_spill(vec![
+        FallbackAction::Partitions(512),
+        FallbackAction::Strategy("join_merge".to_string()),
+    ]);
+    let (out, warnings) = engine.eval_binding(&join_binding(), &inputs()).unwrap();
+    assert_eq!(out.num_rows(), ROWS);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("join_merge"), "{}", warnings[0]);
+}
+
+#[test]
+fn test_skip_fallback_emits_empty_batch_with_warning() {
+    let engine = engine_without_spill(vec![FallbackAction::Skip]);
+    let (out, warnings) = engine.eval_binding(&join_binding(), &inputs()).unwrap();
+    assert_eq!(out.num_rows(), 0);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("skipped block"), "{}", warnings[0]);
+}
+
+#[test]
+fn test_fallbacks_parse_from_yaml() {
+    let yaml = r#"
+config:
+  fallbacks:
+    join_hash:
+      - partitions: 64
+      - strategy: join_merge
+      - skip
+steps:
+  - op: scan
+    source: "data.csv"
+    schema: [ { name: "id", type: "Int64" } ]
+"#;
+    let parsed = parse_yaml_pipeline(yaml).unwrap();
+    assert_eq!(
+        parsed.config.fallbacks["join_hash"],
+        vec![
+            FallbackAction::Partitions(64),
+            FallbackAction::Strategy("join_merge".to_string()),
+            FallbackAction::Skip,
+        ]
+    );
+}
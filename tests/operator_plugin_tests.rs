@@ -0,0 +1,210 @@
+//! Operators from outside the runtime, registered with `Engine::with_operators`
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::prelude::Schema;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_operators::registry::Registry;
+use emsqrt_operators::{Footprint, MemoryBudget, OpError, OpPlan, Operator};
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline, rules};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use serde_json::json;
+use test_data_gen::create_temp_spill_dir;
+
+/// Replaces every value of `column` with `mask`.
+struct Redact {
+    column: String,
+    mask: String,
+}
+
+impl Operator for Redact {
+    fn name(&self) -> &'static str {
+        "redact"
+    }
+
+    fn memory_need(&self, _rows: u64, _bytes: u64) -> Footprint {
+        Footprint {
+            bytes_per_row: 1,
+            overhead_bytes: 0,
+        }
+    }
+
+    fn plan(&self, input_schemas: &[Schema]) -> Result<OpPlan, OpError> {
+        let input = input_schemas
+            .first()
+            .ok_or_else(|| OpError::Plan("redact expects one input".into()))?;
+        Ok(OpPlan::new(input.clone(), self.memory_need(0, 0)))
+    }
+
+    fn eval_block(
+        &self,
+        inputs: &[RowBatch],
+        _budget: &dyn MemoryBudget<Guard = emsqrt_mem::guard::BudgetGuardImpl>,
+    ) -> Result<RowBatch, OpError> {
+        let mut out = inputs[0].clone();
+        let column = out
+            .columns
+            .iter_mut()
+            .find(|c| c.name == self.column)
+            .ok_or_else(|| OpError::Schema(format!("unknown column '{}'", self.column)))?;
+        for value in &mut column.values {
+            *value = Scalar::Str(self.mask.clone());
+        }
+        Ok(out)
+    }
+}
+
+fn plugins() -> Registry {
+    let mut registry = Registry::empty();
+    registry.register_with_config("acme_redact", |config| {
+        let column = config
+            .get("column")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| OpError::Plan("acme_redact needs a 'column'".into()))?;
+        let mask = config.get("mask").and_then(|v| v.as_str()).unwrap_or("***");
+        Ok(Box::new(Redact {
+            column: column.to_string(),
+            mask: mask.to_string(),
+        }))
+    });
+    registry
+}
+
+fn engine(dir: &str) -> Engine {
+    Engine::new(EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_registry_builds_from_config() {
+    let mut registry = plugins();
+    registry.register("acme_noop", || {
+        Box::new(emsqrt_operators::project::Project::default())
+    });
+    assert_eq!(registry.keys(), ["acme_noop", "acme_redact"]);
+    assert!(!Registry::empty().contains("filter"));
+    assert!(Registry::new().contains("filter"));
+
+    let op = registry
+        .build("acme_redact", &json!({ "column": "ssn" }))
+        .unwrap()
+        .unwrap();
+    assert_eq!(op.name(), "redact");
+    assert!(registry.build("acme_redact", &json!({})).unwrap().is_err());
+    // `make` passes a null config, which this factory rejects.
+    assert!(registry.make("acme_redact").is_none());
+    assert!(registry.make("acme_noop").is_some());
+    assert!(registry.build("acme_other", &json!({})).is_none());
+}
+
+#[test]
+fn test_engine_evaluates_registered_operators() {
+    let dir = create_temp_spill_dir();
+    let input = RowBatch {
+        columns: vec![Column {
+            name: "ssn".into(),
+            values: vec![Scalar::Str("123".into()), Scalar::Null],
+        }],
+    };
+
+    // Unknown until registered.
+    let config = json!({ "column": "ssn", "mask": "x" });
+    match engine(&dir).eval_operator("acme_redact", &config, std::slice::from_ref(&input)) {
+        Err(ExecError::Registry(msg)) => assert!(msg.contains("acme_redact"), "{}", msg),
+        other => panic!(
+            "expected a registry error, got {:?}",
+            other.map(|b| b.num_rows())
+        ),
+    }
+
+    let engine = engine(&dir).with_operators(plugins());
+    let out = engine
+        .eval_operator("acme_redact", &config, std::slice::from_ref(&input))
+        .unwrap();
+    assert_eq!(
+        out.columns[0].values,
+        [Scalar::Str("x".into()), Scalar::Str("x".into())]
+    );
+    let err = engine
+        .eval_operator("acme_redact", &json!({}), &[input])
+        .unwrap_err();
+    assert!(err.to_string().contains("needs a 'column'"), "{}", err);
+
+    // A built-in key keeps the built-in operator.
+    let mut shadow = Registry::empty();
+    shadow.register_with_config("filter", |_| Err(OpError::Plan("shadowed".into())));
+    let engine = engine.with_operators(shadow);
+    let input = RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: vec![Scalar::I64(1), Scalar::I64(2)],
+        }],
+    };
+    let out = engine
+        .eval_operator("filter", &json!({ "expr": "id > 1" }), &[input])
+        .unwrap();
+    assert_eq!(out.num_rows(), 1);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_run_with_a_registered_operator() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(format!("{}/in.csv", dir), "name,ssn\nada,123\nbob,456\n").unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "ssn", type: "Utf8" }}
+  - {{ op: map, expr: "name, ssn" }}
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: csv
+"#
+    );
+    let plan = rules::optimize(parse_yaml_pipeline(&yaml).unwrap().plan);
+    let mut program = lower_to_physical(&plan);
+    // Bind the plan's map step to the plugin instead.
+    let binding = program
+        .bindings
+        .values_mut()
+        .find(|b| b.key == "map")
+        .unwrap();
+    binding.key = "acme_redact".into();
+    binding.config = json!({ "column": "ssn" });
+
+    let work = WorkEstimate {
+        total_rows: 2,
+        total_bytes: 2,
+        max_fan_in: 1,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block: 1 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    engine(&dir)
+        .with_operators(plugins())
+        .run(&program, &te)
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(format!("{}/out.csv", dir)).unwrap(),
+        "name,ssn\nada,***\nbob,***\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -327,3 +327,366 @@ fn test_parquet_multi_batch_read() {
 fn test_parquet_feature_required() {
     // This test file requires the parquet feature to be enabled
 }
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_per_column_settings() {
+    use emsqrt_core::config::{
+        ParquetCodec, ParquetColumnConfig, ParquetEncoding, ParquetSinkConfig, ParquetStatistics,
+    };
+    use emsqrt_io::writers::{open_writer, WriteMode, WriterOptions};
+    use parquet::basic::{Compression, Encoding};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let temp_dir = create_temp_spill_dir();
+    fs::create_dir_all(&temp_dir).expect("Failed to create temp dir");
+    let parquet_file = format!("{}/test_columns.parquet", temp_dir);
+
+    let mut config = ParquetSinkConfig {
+        compression: ParquetCodec::Lz4,
+        statistics: ParquetStatistics::None,
+        ..Default::default()
+    };
+    config.columns.insert(
+        "name".to_string(),
+        ParquetColumnConfig {
+            compression: Some(ParquetCodec::Zstd),
+            compression_level: Some(9),
+            encoding: Some(ParquetEncoding::DeltaLengthByteArray),
+            dictionary: Some(false),
+            statistics: Some(ParquetStatistics::Chunk),
+        },
+    );
+    let opts = WriterOptions {
+        parquet: config,
+        ..Default::default()
+    };
+    let batch = create_test_data();
+    let mut writer = open_writer(
+        "parquet",
+        &parquet_file,
+        WriteMode::Overwrite,
+        &batch,
+        &opts,
+    )
+    .expect("Failed to create writer");
+    writer.write_batch(&batch).expect("Failed to write");
+    writer.finish().expect("Failed to close");
+
+    let reader = SerializedFileReader::new(fs::File::open(&parquet_file).unwrap()).unwrap();
+    let row_group = reader.metadata().row_group(0);
+    let id = row_group.column(0);
+    assert_eq!(id.compression(), Compression::LZ4);
+    assert!(id.statistics().is_none());
+    let name = row_group.column(1);
+    // Files record the codec but not its level.
+    assert!(matches!(name.compression(), Compression::ZSTD(_)));
+    assert!(name
+        .encodings()
+        .contains(&Encoding::DELTA_LENGTH_BYTE_ARRAY));
+    assert!(!name.encodings().contains(&Encoding::RLE_DICTIONARY));
+    assert!(name.statistics().is_some());
+
+    // Settings for a column the output lacks are a mistake, not a no-op.
+    let mut typo = opts.clone();
+    typo.parquet
+        .columns
+        .insert("nmae".to_string(), ParquetColumnConfig::default());
+    let err = open_writer(
+        "parquet",
+        &parquet_file,
+        WriteMode::Overwrite,
+        &batch,
+        &typo,
+    )
+    .err()
+    .expect("unknown column should fail");
+    assert!(err.to_string().contains("column 'nmae'"), "{}", err);
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_parquet_settings_parse_from_pipeline_config() {
+    use emsqrt_core::config::{ParquetCodec, ParquetEncoding, ParquetStatistics};
+
+    let yaml = r#"
+config:
+  parquet:
+    compression: zstd
+    compression_level: 3
+    statistics: chunk
+    row_group_rows: 1000000
+    columns:
+      payload: { compression: uncompressed, encoding: plain, dictionary: false }
+steps:
+  - op: scan
+    source: "data.csv"
+    schema:
+      - { name: "payload", type: "Binary" }
+  - op: sink
+    destination: "out.parquet"
+    format: "parquet"
+"#;
+    let parquet = emsqrt_planner::parse_yaml_pipeline(yaml)
+        .unwrap()
+        .config
+        .parquet
+        .unwrap();
+    assert_eq!(parquet.compression, ParquetCodec::Zstd);
+    assert_eq!(parquet.compression_level, Some(3));
+    assert_eq!(parquet.statistics, ParquetStatistics::Chunk);
+    assert_eq!(parquet.dictionary, None);
+    let payload = &parquet.columns["payload"];
+    assert_eq!(payload.compression, Some(ParquetCodec::Uncompressed));
+    assert_eq!(payload.encoding, Some(ParquetEncoding::Plain));
+    assert_eq!(payload.dictionary, Some(false));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_row_groups_flush_on_buffered_bytes() {
+    let temp_dir = create_temp_spill_dir();
+    fs::create_dir_all(&temp_dir).expect("Failed to create temp dir");
+    let parquet_file = format!("{}/test_flush.parquet", temp_dir);
+
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let mut writer = ParquetWriter::from_emsqrt_schema(&parquet_file, &schema)
+        .expect("Failed to create writer")
+        .with_max_row_group_bytes(Some(16 * 1024));
+    for chunk in 0..10i64 {
+        let batch = RowBatch {
+            columns: vec![Column {
+                name: "id".to_string(),
+                values: (chunk * 10_000..(chunk + 1) * 10_000)
+                    .map(Scalar::I64)
+                    .collect(),
+            }],
+        };
+        writer.write_row_batch(&batch).expect("Failed to write");
+        assert!(writer.buffered_bytes() < 16 * 1024 || writer.row_groups_written() == 0);
+    }
+    // Row groups went to the file as they filled, not at close.
+    assert!(
+        writer.row_groups_written() >= 5,
+        "{}",
+        writer.row_groups_written()
+    );
+    writer.close().expect("Failed to close");
+
+    let mut reader =
+        ParquetReader::from_path(&parquet_file, None, 1 << 20).expect("Failed to create reader");
+    let mut rows = 0;
+    while let Some(batch) = reader.next_batch().expect("Failed to read") {
+        rows += batch.num_rows();
+    }
+    assert_eq!(rows, 100_000);
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_sink_row_group_bounds() {
+    use emsqrt_core::config::{EngineConfig, ParquetSinkConfig};
+    use emsqrt_exec::Engine;
+    use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let temp_dir = create_temp_spill_dir();
+    fs::create_dir_all(&temp_dir).expect("Failed to create temp dir");
+    let input = format!("{}/in.csv", temp_dir);
+    let body: String = (0..5_000).map(|i| format!("{}\n", i)).collect();
+    fs::write(&input, format!("id\n{}", body)).unwrap();
+    let output = format!("{}/out.parquet", temp_dir);
+
+    let row_groups = |parquet: ParquetSinkConfig| {
+        // Five blocks of 1000 rows.
+        let yaml = format!(
+            r#"
+hints:
+  sources:
+    "{input}": {{ rows: 5000 }}
+  rows_per_block: 1000
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+  - op: sink
+    destination: "{output}"
+    format: "parquet"
+"#
+        );
+        let parsed = parse_yaml_pipeline(&yaml).unwrap();
+        let program = lower_to_physical(&parsed.plan);
+        let work = estimate_work(&parsed.plan, parsed.hints.work_hint().as_ref());
+        let te = parsed.hints.plan_te(&program, &work, 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", temp_dir),
+            parquet,
+            ..Default::default()
+        };
+        Engine::new(config).unwrap().run(&program, &te).unwrap();
+        let reader = SerializedFileReader::new(fs::File::open(&output).unwrap()).unwrap();
+        let groups = reader.metadata().row_groups().to_vec();
+        let rows: i64 = groups.iter().map(|g| g.num_rows()).sum();
+        assert_eq!(rows, 5_000);
+        groups.len()
+    };
+
+    // Defaults: a small input is one row group.
+    assert_eq!(row_groups(ParquetSinkConfig::default()), 1);
+    assert_eq!(
+        row_groups(ParquetSinkConfig {
+            row_group_rows: Some(2_000),
+            ..Default::default()
+        }),
+        3
+    );
+    // Any buffered bytes over the bound end the row group after each block.
+    assert_eq!(
+        row_groups(ParquetSinkConfig {
+            row_group_bytes: Some(1),
+            ..Default::default()
+        }),
+        5
+    );
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
+
+#[test]
+fn test_parquet_scan_projection_is_pushed_down() {
+    use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline};
+
+    let projection = |source: &str, filter: &str| {
+        let yaml = format!(
+            "steps:\n  - op: scan\n    source: \"{source}\"\n    schema:\n      - {{ name: \"id\", type: \"Int64\" }}\n      - {{ name: \"name\", type: \"Utf8\" }}\n      - {{ name: \"score\", type: \"Float64\" }}\n{filter}  - op: project\n    columns: [\"name\"]\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n"
+        );
+        let program = lower_to_physical(&parse_yaml_pipeline(&yaml).unwrap().plan);
+        let source = program
+            .bindings
+            .values()
+            .find(|b| b.key == "source")
+            .unwrap();
+        source.config.get("projection").cloned()
+    };
+    assert_eq!(
+        projection("in.parquet", ""),
+        Some(serde_json::json!(["name"]))
+    );
+    // Columns the filter reads are decoded too.
+    let filter = "  - op: filter\n    expr: \"score > 1.5 AND hash(name) != 0\"\n";
+    assert_eq!(
+        projection("in.parq", filter),
+        Some(serde_json::json!(["name", "score"]))
+    );
+    assert_eq!(projection("in.csv", ""), Some(serde_json::json!(["name"])));
+}
+
+#[cfg(not(feature = "parquet"))]
+#[test]
+fn test_parquet_scan_requires_the_feature() {
+    use emsqrt_core::config::EngineConfig;
+    use emsqrt_exec::Engine;
+    use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+    use emsqrt_te::plan_te;
+    use test_data_gen::create_temp_spill_dir;
+
+    let temp_dir = create_temp_spill_dir();
+    std::fs::create_dir_all(&temp_dir).unwrap();
+    let input = format!("{}/in.parquet", temp_dir);
+    std::fs::write(&input, "PAR1").unwrap();
+    let yaml = format!(
+        "steps:\n  - op: scan\n    source: \"{input}\"\n    schema:\n      - {{ name: \"id\", type: \"Int64\" }}\n  - op: sink\n    destination: \"{temp_dir}/out.csv\"\n    format: \"csv\"\n"
+    );
+    let plan = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", temp_dir),
+        ..Default::default()
+    };
+    let err = Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("rebuild with --features parquet"), "{}", err);
+
+    let _ = std::fs::remove_dir_all(&temp_dir);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_parquet_pipeline_round_trip() {
+    use emsqrt_core::config::EngineConfig;
+    use emsqrt_exec::Engine;
+    use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+    use emsqrt_te::plan_te;
+
+    let temp_dir = create_temp_spill_dir();
+    fs::create_dir_all(&temp_dir).unwrap();
+    let run = |yaml: &str| {
+        let parsed = parse_yaml_pipeline(yaml).unwrap();
+        let program = lower_to_physical(&parsed.plan);
+        let te = plan_te(&program.plan, &estimate_work(&parsed.plan, None), 1 << 26).unwrap();
+        let mut config = EngineConfig {
+            spill_dir: format!("{}/spill", temp_dir),
+            ..Default::default()
+        };
+        if let Some(parquet) = parsed.config.parquet {
+            config.parquet = parquet;
+        }
+        Engine::new(config).unwrap().run(&program, &te).unwrap();
+    };
+
+    fs::write(
+        format!("{}/in.csv", temp_dir),
+        "id,name,score\n1,ann,1.0\n2,bo,2.5\n3,cy,3.5\n",
+    )
+    .unwrap();
+    run(&format!(
+        r#"
+config:
+  parquet:
+    compression: gzip
+    row_group_rows: 2
+steps:
+  - op: scan
+    source: "{temp_dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "score", type: "Float64" }}
+  - op: sink
+    destination: "{temp_dir}/mid.parquet"
+    format: "parquet"
+"#
+    ));
+    run(&format!(
+        r#"
+steps:
+  - op: scan
+    source: "{temp_dir}/mid.parquet"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "score", type: "Float64" }}
+  - op: filter
+    expr: "score > 2"
+  - op: project
+    columns: ["name"]
+  - op: sink
+    destination: "{temp_dir}/out.csv"
+    format: "csv"
+"#
+    ));
+    assert_eq!(
+        fs::read_to_string(format!("{}/out.csv", temp_dir)).unwrap(),
+        "name\nbo\ncy\n"
+    );
+
+    let _ = fs::remove_dir_all(&temp_dir);
+}
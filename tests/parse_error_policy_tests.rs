@@ -0,0 +1,125 @@
+//! `on_parse_error` policies for numeric CSV cells: null, fail, dead_letter
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+const INPUT: &str = "id,n,score\n1,10,0.5\n2,x,0.25\n3,30,bad\n4,,1.5\n5,50,2.5\n";
+
+/// Run `scan(in.csv) → sink(out.csv)`; `scan_options` and `n_options` are
+/// spliced into the scan step and the `n` field definition.
+fn run(dir: &str, scan_options: &str, n_options: &str) -> Result<RunManifest, ExecError> {
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, INPUT).unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "n", type: "Int64", nullable: true {n_options} }}
+      - {{ name: "score", type: "Float64", nullable: true }}
+{scan_options}
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    );
+    let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te)
+}
+
+fn output(dir: &str) -> String {
+    fs::read_to_string(format!("{}/out.csv", dir)).unwrap()
+}
+
+#[test]
+fn test_default_policy_nulls_and_counts() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let manifest = run(&dir, "", "").unwrap();
+    assert_eq!(output(&dir).lines().count(), 6);
+    // The empty cell is a null, not a parse error.
+    let counts = &manifest.parse_errors[&format!("{}/in.csv", dir)];
+    assert_eq!(counts.get("n"), Some(&1));
+    assert_eq!(counts.get("score"), Some(&1));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_fail_policy_names_the_cell() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let err = run(&dir, "    on_parse_error: fail", "").unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("line 3"), "{}", msg);
+    assert!(msg.contains("column 'n' value 'x'"), "{}", msg);
+
+    // A per-column `null` overrides the source default.
+    let err = run(&dir, "    on_parse_error: fail", ", on_parse_error: null").unwrap_err();
+    assert!(err.to_string().contains("column 'score' value 'bad'"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_dead_letter_policy_diverts_rows() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let dead_letter = format!("{}/bad.jsonl", dir);
+
+    let scan = format!(
+        "    on_parse_error: dead_letter\n    dead_letter: \"{}\"",
+        dead_letter
+    );
+    let manifest = run(&dir, &scan, "").unwrap();
+    assert_eq!(output(&dir), "id,n,score\n1,10,0.5\n4,,1.5\n5,50,2.5\n");
+
+    let lines: Vec<serde_json::Value> = fs::read_to_string(&dead_letter)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["column"], "n");
+    assert_eq!(lines[0]["value"], "x");
+    assert_eq!(lines[0]["line"], 3);
+    assert_eq!(lines[0]["record"]["id"], "2");
+    assert_eq!(lines[1]["column"], "score");
+    assert_eq!(manifest.parse_errors.values().next().unwrap().len(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_dead_letter_policy_requires_a_path() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "in.csv"
+    schema:
+      - { name: "n", type: "Int64", on_parse_error: dead_letter }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let err = parse_yaml_pipeline(yaml).unwrap_err();
+    assert!(err.to_string().contains("dead_letter"), "{}", err);
+}
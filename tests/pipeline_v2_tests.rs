@@ -0,0 +1,241 @@
+//! Version 2 pipelines: named stages, `inputs:` and per-stage resources
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan as L};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{
+    estimate_work, load_pipeline, lower_to_physical, migrate_v1, parse_yaml_pipeline, rules,
+};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// `orders` joined to `customers`, counted per country, with `join_extra`
+/// spliced into the join stage.
+fn join_pipeline(dir: &str, join_extra: &str) -> String {
+    format!(
+        r#"
+version: 2
+stages:
+  - name: orders
+    op: scan
+    source: "{dir}/orders.csv"
+    schema:
+      - {{ name: "customer_id", type: "Int64" }}
+      - {{ name: "total", type: "Float64" }}
+  - name: customers
+    op: scan
+    source: "{dir}/customers.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "country", type: "Utf8" }}
+  - name: enriched
+    op: join
+    inputs: [orders, customers]
+    on: [{{ left: customer_id, right: id }}]
+    join_type: left
+    {join_extra}
+  - name: by_country
+    op: aggregate
+    group_by: [country]
+    aggs: [count, "sum:total"]
+  - name: out
+    op: sink
+    destination: "{dir}/out.csv"
+    format: csv
+"#
+    )
+}
+
+fn stage_error(yaml: &str) -> String {
+    parse_yaml_pipeline(yaml).unwrap_err().to_string()
+}
+
+#[test]
+fn test_v2_stages_build_a_join_and_aggregate() {
+    let plan = parse_yaml_pipeline(&join_pipeline("d", "")).unwrap().plan;
+    let L::Sink { input, .. } = plan else {
+        panic!("expected a sink")
+    };
+    let L::Aggregate {
+        input,
+        group_by,
+        aggs,
+    } = *input
+    else {
+        panic!("expected an aggregate")
+    };
+    assert_eq!(group_by, ["country"]);
+    assert_eq!(aggs, [Aggregation::Count, Aggregation::Sum("total".into())]);
+    let L::Join {
+        left,
+        right,
+        on,
+        join_type,
+    } = *input
+    else {
+        panic!("expected a join")
+    };
+    assert!(matches!(*left, L::Scan { ref source, .. } if source == "d/orders.csv"));
+    assert!(matches!(*right, L::Scan { ref source, .. } if source == "d/customers.csv"));
+    assert_eq!(on, [("customer_id".to_string(), "id".to_string())]);
+    assert_eq!(join_type, JoinType::Left);
+}
+
+#[test]
+fn test_v2_stage_wiring_errors() {
+    let two_scans = r#"
+version: 2
+stages:
+  - { name: a, op: scan, source: "a.csv", schema: [ { name: "id", type: "Int64" } ] }
+  - { name: b, op: scan, source: "b.csv", schema: [ { name: "id", type: "Int64" } ] }
+"#;
+    assert!(stage_error(two_scans).contains("'a', 'b'"));
+
+    let base = join_pipeline("d", "");
+    let cases = [
+        (
+            "[orders, customers]",
+            "[orders, nope]",
+            "input 'nope' is not a stage defined before it",
+        ),
+        (
+            "[orders, customers]",
+            "[orders]",
+            "join cannot read 1 input(s)",
+        ),
+        (
+            "[orders, customers]",
+            "[out, customers]",
+            "input 'out' is not a stage defined before it",
+        ),
+        (
+            "join_type: left",
+            "join_type: sideways",
+            "unknown join_type 'sideways'",
+        ),
+        ("join_type: left", "colour: blue", "stage 'enriched'"),
+    ];
+    for (from, to, expected) in cases {
+        let err = stage_error(&base.replace(from, to));
+        assert!(err.contains(expected), "{}: {}", to, err);
+    }
+
+    let dup = join_pipeline("d", "").replace("name: by_country", "name: orders");
+    assert!(stage_error(&dup).contains("stage 'orders' is defined twice"));
+
+    let unversioned = join_pipeline("d", "").replace("version: 2\n", "");
+    assert!(stage_error(&unversioned).contains("needs 'version: 2'"));
+    let future = join_pipeline("d", "").replace("version: 2", "version: 3");
+    assert!(stage_error(&future).contains("unsupported pipeline version 3"));
+}
+
+#[test]
+fn test_migrated_v1_pipeline_plans_the_same() {
+    let v1 = r#"
+config:
+  spill_space_check: false
+steps:
+  - op: scan
+    source: "in.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+      - { name: "score", type: "Float64" }
+  - op: filter
+    expr: "score > 1.5"
+  - op: map
+    expr: "score * 2 AS double"
+  - op: filter
+    expr: "id > 3"
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let migrated = migrate_v1(v1).unwrap();
+    let doc = load_pipeline(&migrated).unwrap();
+    let names: Vec<&str> = doc.stages.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["scan", "filter", "map", "filter_2", "sink"]);
+    assert_eq!(doc.config.unwrap().spill_space_check, Some(false));
+
+    let before = parse_yaml_pipeline(v1).unwrap().plan;
+    let after = parse_yaml_pipeline(&migrated).unwrap().plan;
+    assert_eq!(format!("{:?}", after), format!("{:?}", before));
+    // v1 documents load as v2 directly, too.
+    assert_eq!(load_pipeline(v1).unwrap().stages.len(), 5);
+
+    assert!(migrate_v1(&migrated)
+        .unwrap_err()
+        .to_string()
+        .contains("already version 2"));
+}
+
+#[test]
+fn test_stage_memory_caps_its_operator() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let orders: String = (0..4000)
+        .map(|i| format!("{},{}.5\n", (i * 7919) % 2000, i % 10))
+        .collect();
+    fs::write(
+        format!("{}/orders.csv", dir),
+        format!("customer_id,total\n{}", orders),
+    )
+    .unwrap();
+    let customers: String = (0..2000).map(|i| format!("{},c{}\n", i, i)).collect();
+    fs::write(
+        format!("{}/customers.csv", dir),
+        format!("id,country\n{}", customers),
+    )
+    .unwrap();
+
+    // Runs with the aggregate stage limited to `memory_bytes`; returns the
+    // output and the bytes the aggregate spilled.
+    let run = |memory_bytes: u64| {
+        let yaml = join_pipeline(&dir, "").replace(
+            "aggs: [count, \"sum:total\"]\n",
+            &format!(
+                "aggs: [count, \"sum:total\"]\n    resources: {{ memory_bytes: {} }}\n",
+                memory_bytes
+            ),
+        );
+        let parsed = parse_yaml_pipeline(&yaml).unwrap();
+        let lp = rules::optimize(parsed.plan);
+        let mut program = lower_to_physical(&lp);
+        parsed.hints.apply(&mut program);
+        for binding in program.bindings.values() {
+            let expected = (binding.key == "aggregate").then(|| memory_bytes.into());
+            assert_eq!(binding.config.get("memory_bytes"), expected.as_ref());
+        }
+
+        let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            ..Default::default()
+        };
+        let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+        let spilled: u64 = manifest
+            .block_costs
+            .iter()
+            .filter(|c| c.op == "aggregate")
+            .map(|c| c.bytes_spilled)
+            .sum();
+        (
+            fs::read_to_string(format!("{}/out.csv", dir)).unwrap(),
+            spilled,
+        )
+    };
+
+    let (roomy, spilled) = run(64 << 20);
+    assert_eq!(spilled, 0);
+    assert!(roomy.contains("c7,2,"), "{}", roomy);
+
+    // The engine has memory to spare, but the aggregate may use 4 KiB.
+    let (tight, spilled) = run(4 << 10);
+    assert!(spilled > 0);
+    assert_eq!(tight, roomy);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,114 @@
+//! Plan-level `hints:` block in pipeline YAML
+
+use emsqrt_core::dag::{JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_planner::{
+    estimate_work, lower_to_physical, parse_yaml_pipeline, JoinStrategy, PlanHints, SourceHint,
+};
+
+const PIPELINE: &str = r#"
+hints:
+  sources:
+    "data/events.csv": { rows: 1000000, bytes: 64000000 }
+  join_strategy: merge
+  rows_per_block: 4096
+  parallelism:
+    filter: 4
+steps:
+  - op: scan
+    source: "data/events.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: filter
+    expr: "id > 10"
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+
+fn schema() -> Schema {
+    Schema::new(vec![Field::new("id", DataType::Int64, false)])
+}
+
+#[test]
+fn test_hints_parse_from_yaml() {
+    let parsed = parse_yaml_pipeline(PIPELINE).unwrap();
+    let hints = parsed.hints;
+    assert_eq!(
+        hints.sources["data/events.csv"],
+        SourceHint {
+            rows: Some(1_000_000),
+            bytes: Some(64_000_000),
+        }
+    );
+    assert_eq!(hints.join_strategy, Some(JoinStrategy::Merge));
+    assert_eq!(hints.rows_per_block, Some(4096));
+    assert_eq!(hints.parallelism["filter"], 4);
+}
+
+#[test]
+fn test_source_hints_drive_work_estimate() {
+    let parsed = parse_yaml_pipeline(PIPELINE).unwrap();
+    let without = estimate_work(&parsed.plan, None);
+    let with = estimate_work(&parsed.plan, parsed.hints.work_hint().as_ref());
+    assert_eq!(without.total_bytes, 0);
+    assert_eq!(with.total_bytes, 64_000_000);
+    assert!(with.total_rows > without.total_rows);
+}
+
+#[test]
+fn test_block_size_override_shapes_te_plan() {
+    let parsed = parse_yaml_pipeline(PIPELINE).unwrap();
+    let program = lower_to_physical(&parsed.plan);
+    let work = estimate_work(&parsed.plan, parsed.hints.work_hint().as_ref());
+
+    let te = parsed
+        .hints
+        .plan_te(&program, &work, 512 * 1024 * 1024)
+        .unwrap();
+    assert_eq!(te.block_size.rows_per_block, 4096);
+
+    let default_te = PlanHints::default()
+        .plan_te(&program, &work, 512 * 1024 * 1024)
+        .unwrap();
+    assert_ne!(default_te.block_size.rows_per_block, 4096);
+    assert!(te.order.len() > default_te.order.len());
+}
+
+#[test]
+fn test_join_strategy_and_parallelism_rewrite_bindings() {
+    // The filter sits above the join: one directly on a join input would run
+    // inside the join rather than as a `filter` operator.
+    let lp = L::Filter {
+        input: Box::new(L::Join {
+            left: Box::new(L::Scan {
+                source: "left.csv".into(),
+                schema: schema(),
+            }),
+            right: Box::new(L::Scan {
+                source: "right.csv".into(),
+                schema: schema(),
+            }),
+            on: vec![("id".into(), "id".into())],
+            join_type: JoinType::Inner,
+        }),
+        expr: "id > 0".into(),
+    };
+    let mut program = lower_to_physical(&lp);
+    let hints = PlanHints {
+        join_strategy: Some(JoinStrategy::Merge),
+        parallelism: [("filter".to_string(), 2)].into_iter().collect(),
+        ..Default::default()
+    };
+    hints.apply(&mut program);
+
+    let keys: Vec<&str> = program.bindings.values().map(|b| b.key.as_str()).collect();
+    assert!(keys.contains(&"join_merge"));
+    assert!(!keys.contains(&"join_hash"));
+    let filter = program
+        .bindings
+        .values()
+        .find(|b| b.key == "filter")
+        .unwrap();
+    assert_eq!(filter.config["parallelism"], 2);
+}
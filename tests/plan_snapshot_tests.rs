@@ -0,0 +1,58 @@
+//! Plan snapshots (`emsqrt_exec::testkit`) of the example pipelines
+//!
+//! A failure here means planning output changed: review the printed diff and
+//! rerun with `EMSQRT_UPDATE_SNAPSHOTS=1` to accept it.
+
+mod test_data_gen;
+
+use std::fs;
+use std::panic;
+
+use emsqrt_exec::testkit::{assert_snapshot, snapshot_pipeline};
+use test_data_gen::create_temp_spill_dir;
+
+const MEM_CAP: usize = 64 << 20;
+
+fn check_example(name: &str) {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let yaml = fs::read_to_string(format!("{}/examples/{}.yaml", root, name)).unwrap();
+    let snapshot = snapshot_pipeline(&yaml, MEM_CAP).unwrap();
+    assert_snapshot(format!("{}/tests/snapshots/{}.snap", root, name), &snapshot);
+}
+
+#[test]
+fn test_example_plans_match_snapshots() {
+    check_example("simple_pipeline");
+    check_example("join_pipeline");
+    check_example("parquet_pipeline");
+}
+
+#[test]
+fn test_changed_plans_fail_with_a_line_diff() {
+    let dir = create_temp_spill_dir();
+    let path = format!("{}/plan.snap", dir);
+    let yaml = |limit: u32| {
+        format!(
+            "steps:\n  - op: scan\n    source: \"in.csv\"\n    schema:\n      - {{ name: \"id\", type: \"Int64\" }}\n  - op: filter\n    expr: \"id < {}\"\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n",
+            limit
+        )
+    };
+    let before = snapshot_pipeline(&yaml(10), MEM_CAP).unwrap();
+    assert!(before.starts_with("# logical plan\n"));
+    assert!(before.contains("# bindings\n") && before.contains("# te order ("));
+
+    // A missing snapshot is written; the same output then passes.
+    assert_snapshot(&path, &before);
+    assert_eq!(fs::read_to_string(&path).unwrap(), before);
+    assert_snapshot(&path, &snapshot_pipeline(&yaml(10), MEM_CAP).unwrap());
+
+    let after = snapshot_pipeline(&yaml(20), MEM_CAP).unwrap();
+    let err = panic::catch_unwind(|| assert_snapshot(&path, &after)).unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.contains("EMSQRT_UPDATE_SNAPSHOTS=1"), "{}", msg);
+    assert!(msg.contains(" - ") && msg.contains("id < 10"), "{}", msg);
+    assert!(msg.contains(" + ") && msg.contains("id < 20"), "{}", msg);
+    assert_eq!(fs::read_to_string(&path).unwrap(), before);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,134 @@
+//! Pre-sorted sources: `sorted_by` on scan and the sorts it lets the planner skip
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// `scan(input) → window(partition g, order o) → sink(output)`, with
+/// `scan_options` spliced into the scan step.
+fn pipeline(input: &str, output: &str, scan_options: &str) -> String {
+    format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "g", type: "Utf8" }}
+      - {{ name: "o", type: "Int64" }}
+      - {{ name: "v", type: "Float64" }}
+{scan_options}
+  - op: window
+    partitions: ["g"]
+    order_by: ["o"]
+    functions:
+      - {{ alias: "rn", type: "row_number" }}
+      - {{ alias: "running", type: "sum", column: "v" }}
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    )
+}
+
+fn window_binding(yaml: &str) -> serde_json::Value {
+    let lp = parse_yaml_pipeline(yaml).unwrap().plan;
+    let program = lower_to_physical(&lp);
+    program
+        .bindings
+        .values()
+        .find(|b| b.key == "window")
+        .unwrap()
+        .config
+        .clone()
+}
+
+#[test]
+fn test_sorted_by_is_a_scan_constraint() {
+    let yaml = pipeline("in.csv", "out.csv", "    sorted_by: [\"g\", \"o\"]");
+    let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let mut node = &lp;
+    while let L::Sink { input, .. } | L::Window { input, .. } = node {
+        node = input;
+    }
+    let L::Scan { schema, .. } = node else {
+        panic!("expected a scan, got {:?}", node);
+    };
+    assert_eq!(schema.constraints.sorted_by, ["g", "o"]);
+
+    // The long form agrees with the shorthand or is rejected.
+    let both = "    sorted_by: [\"g\"]\n    constraints:\n      sorted_by: [\"g\"]";
+    assert!(parse_yaml_pipeline(&pipeline("in.csv", "out.csv", both)).is_ok());
+    let clash = "    sorted_by: [\"g\"]\n    constraints:\n      sorted_by: [\"o\"]";
+    let err = parse_yaml_pipeline(&pipeline("in.csv", "out.csv", clash)).unwrap_err();
+    assert!(err.to_string().contains("disagrees"), "{}", err);
+
+    let unknown = "    sorted_by: [\"missing\"]";
+    let err = parse_yaml_pipeline(&pipeline("in.csv", "out.csv", unknown)).unwrap_err();
+    assert!(err.to_string().contains("'missing'"), "{}", err);
+}
+
+#[test]
+fn test_window_skips_sort_only_for_matching_order() {
+    let sorted = window_binding(&pipeline(
+        "in.csv",
+        "out.csv",
+        "    sorted_by: [\"g\", \"o\"]",
+    ));
+    assert_eq!(sorted["presorted"], true);
+
+    // Sorted by a longer key still covers (g, o).
+    let longer = "    sorted_by: [\"g\", \"o\", \"v\"]";
+    assert_eq!(
+        window_binding(&pipeline("in.csv", "out.csv", longer))["presorted"],
+        true
+    );
+
+    // Sorted by the order key alone does not group partitions.
+    let partial = window_binding(&pipeline("in.csv", "out.csv", "    sorted_by: [\"o\"]"));
+    assert!(partial.get("presorted").is_none());
+    assert!(window_binding(&pipeline("in.csv", "out.csv", ""))
+        .get("presorted")
+        .is_none());
+}
+
+#[test]
+fn test_presorted_window_matches_sorted_window() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(
+        &input,
+        "g,o,v\na,1,1.5\na,2,2\na,3,4\nb,1,10\nb,5,1\nc,2,3\n",
+    )
+    .unwrap();
+
+    let run = |options: &str, output: &str| {
+        let yaml = pipeline(&input, output, options);
+        let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+        let program = lower_to_physical(&lp);
+        let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            ..Default::default()
+        };
+        Engine::new(config).unwrap().run(&program, &te).unwrap();
+        fs::read_to_string(output).unwrap()
+    };
+
+    let sorted = run("", &format!("{}/sorted.csv", dir));
+    let presorted = run(
+        "    sorted_by: [\"g\", \"o\"]",
+        &format!("{}/presorted.csv", dir),
+    );
+    assert_eq!(presorted, sorted);
+    assert!(presorted.contains("b,5,1,2,11"), "{}", presorted);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,170 @@
+//! Data profiling (`emsqrt profile`): sketches, the profile operator, reports
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::DataType;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{profile_source, ProfileOptions};
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::profile::{histogram, DistinctSketch, Profile, Reservoir};
+use emsqrt_operators::traits::BlockRange;
+use emsqrt_operators::{OpContext, Operator};
+use emsqrt_planner::parse_schema_spec;
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_sketches_count_distinct_values_and_sample_uniformly() {
+    let mut small = DistinctSketch::new(1024);
+    for i in 0..3000 {
+        small.insert(&Scalar::I64(i % 700));
+    }
+    assert_eq!(small.estimate(), 700);
+
+    let mut large = DistinctSketch::new(1024);
+    for i in 0..100_000 {
+        large.insert(&Scalar::Str(format!("user-{}", i)));
+    }
+    let estimate = large.estimate() as f64;
+    assert!((estimate - 100_000.0).abs() < 10_000.0, "{}", estimate);
+
+    let mut sample = Reservoir::new(100);
+    (0..10_000).for_each(|v| sample.insert(v as f64));
+    assert_eq!(sample.seen(), 10_000);
+    assert_eq!(sample.values().len(), 100);
+    // Values from the whole stream, not just its start.
+    assert!(sample.values().iter().any(|&v| v >= 5000.0));
+
+    let bins = histogram(&[0.0, 1.0, 2.0, 9.0, 10.0], 0.0, 10.0, 2, 50);
+    let counts: Vec<(f64, f64, u64)> = bins.iter().map(|b| (b.lower, b.upper, b.count)).collect();
+    assert_eq!(counts, [(0.0, 5.0, 30), (5.0, 10.0, 20)]);
+    assert_eq!(histogram(&[3.0, 3.0], 3.0, 3.0, 10, 2).len(), 1);
+}
+
+#[test]
+fn test_operator_reports_on_its_last_block_only() {
+    let op = Profile::new(2, 4, 64);
+    let budget = MemoryBudgetImpl::new(1 << 22);
+    let input = RowBatch {
+        columns: vec![Column {
+            name: "k".into(),
+            values: vec![Scalar::I64(1), Scalar::Null, Scalar::I64(1), Scalar::I64(2)],
+        }],
+    };
+    let block = |open_end| OpContext {
+        range: Some(BlockRange {
+            start: 0,
+            end: 4,
+            open_end,
+        }),
+        ..Default::default()
+    };
+    let first = op
+        .eval_block_with(std::slice::from_ref(&input), &block(false), &budget)
+        .unwrap();
+    assert_eq!(first.num_rows(), 0);
+    let last = op.eval_block_with(&[input], &block(true), &budget).unwrap();
+    assert_eq!(last.num_rows(), 1);
+    let value = |name: &str| {
+        last.columns
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.values[0].clone())
+            .unwrap()
+    };
+    assert_eq!(value("rows"), Scalar::I64(8));
+    assert_eq!(value("nulls"), Scalar::I64(2));
+    assert_eq!(value("distinct"), Scalar::I64(2));
+    assert_eq!(value("type"), Scalar::Str("Int64".into()));
+    // Only values known to repeat are listed.
+    assert_eq!(
+        value("top_values"),
+        Scalar::Str(
+            r#"[{"value":"1","count":4,"error":0},{"value":"2","count":2,"error":0}]"#.into()
+        )
+    );
+}
+
+#[test]
+fn test_schema_spec_parses_types_and_rejects_typos() {
+    let schema = parse_schema_spec("id:Int64, name ,score:f64").unwrap();
+    let fields: Vec<(&str, &DataType, bool)> = schema
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), &f.data_type, f.nullable))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("id", &DataType::Int64, true),
+            ("name", &DataType::Utf8, true),
+            ("score", &DataType::Float64, true)
+        ]
+    );
+    assert!(parse_schema_spec("id:Integer")
+        .unwrap_err()
+        .contains("unknown type 'Integer'"));
+    assert!(parse_schema_spec(" , ").is_err());
+}
+
+#[test]
+fn test_profile_source_reports_every_column() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/people.csv", dir);
+    let mut csv = String::from("id,city,age\n");
+    for i in 0..200 {
+        let city = ["nyc", "nyc", "sf", "<la>"][i % 4];
+        let age = if i % 10 == 0 {
+            String::new()
+        } else {
+            (20 + i % 50).to_string()
+        };
+        csv.push_str(&format!("{},{},{}\n", i, city, age));
+    }
+    fs::write(&input, csv).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        mem_cap_bytes: 1 << 24,
+        ..Default::default()
+    };
+    let schema = parse_schema_spec("id:Int64,city:Utf8,age:Int64").unwrap();
+    let profile = profile_source(&input, &schema, &ProfileOptions::default(), &config).unwrap();
+    assert_eq!(profile.rows, 200);
+    let names: Vec<&str> = profile.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["id", "city", "age"]);
+
+    let id = &profile.columns[0];
+    assert_eq!((id.distinct, id.distinct_exact), (200, true));
+    assert_eq!(
+        (id.min.as_deref(), id.max.as_deref()),
+        (Some("0"), Some("199"))
+    );
+    assert!(id.top_values.is_empty());
+
+    let city = &profile.columns[1];
+    assert_eq!(city.data_type, "Utf8");
+    assert_eq!(city.distinct, 3);
+    assert_eq!(
+        (city.top_values[0].value.as_str(), city.top_values[0].count),
+        ("nyc", 100)
+    );
+    assert!(city.histogram.is_empty());
+
+    let age = &profile.columns[2];
+    assert_eq!((age.nulls, age.null_percent), (20, 10.0));
+    assert_eq!(age.histogram.len(), 10);
+    assert_eq!(age.histogram.iter().map(|b| b.count).sum::<u64>(), 180);
+
+    // The JSON report round-trips; the HTML page escapes values.
+    let json: emsqrt_exec::DataProfile = serde_json::from_str(&profile.to_json()).unwrap();
+    assert_eq!(json, profile);
+    let html = profile.to_html();
+    assert!(html.contains("&lt;la&gt;") && !html.contains("<la>"));
+    assert!(html.contains("<svg"));
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,258 @@
+//! Projection pruning: scans carry only the columns read above them
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{JoinType, LogicalPlan as L};
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_planner::{
+    column_lineage, estimate_work, lower_to_physical, parse_yaml_pipeline, rules,
+};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn scan(source: &str, columns: &[&str]) -> L {
+    L::Scan {
+        source: source.into(),
+        schema: Schema::new(
+            columns
+                .iter()
+                .map(|c| Field::new(*c, DataType::Int64, true))
+                .collect(),
+        ),
+    }
+}
+
+fn sink(input: L) -> L {
+    L::Sink {
+        input: Box::new(input),
+        destination: "out.csv".into(),
+        format: "csv".into(),
+    }
+}
+
+/// Columns of the projection directly over the scan of `source`, if any.
+fn scan_projection(plan: &L, source: &str) -> Option<Vec<String>> {
+    match plan {
+        L::Project { input, columns } if matches!(&**input, L::Scan { source: s, .. } if s == source) => {
+            Some(columns.clone())
+        }
+        L::Scan { .. } => None,
+        L::Join { left, right, .. } | L::ThetaJoin { left, right, .. } => {
+            scan_projection(left, source).or_else(|| scan_projection(right, source))
+        }
+        L::Union { inputs } => inputs.iter().find_map(|i| scan_projection(i, source)),
+        L::Filter { input, .. }
+        | L::FilterIn { input, .. }
+        | L::Map { input, .. }
+        | L::Project { input, .. }
+        | L::Aggregate { input, .. }
+        | L::Window { input, .. }
+        | L::Lateral { input, .. }
+        | L::TopK { input, .. }
+        | L::Limit { input, .. }
+        | L::Sink { input, .. } => scan_projection(input, source),
+    }
+}
+
+fn sink_columns(plan: &L) -> Vec<String> {
+    column_lineage(plan)[0]
+        .columns
+        .iter()
+        .map(|c| c.column.clone())
+        .collect()
+}
+
+#[test]
+fn test_scans_keep_only_columns_read_above() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "in.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+      - { name: "name", type: "Utf8" }
+      - { name: "score", type: "Float64" }
+      - { name: "note", type: "Utf8" }
+  - op: filter
+    expr: "score > 1.5"
+  - op: project
+    columns: ["name"]
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let plan = rules::optimize(parse_yaml_pipeline(yaml).unwrap().plan);
+    assert_eq!(
+        scan_projection(&plan, "in.csv"),
+        Some(vec!["name".to_string(), "score".to_string()])
+    );
+    // Lowering hands the same columns to the CSV reader.
+    let program = lower_to_physical(&plan);
+    let source = program
+        .bindings
+        .values()
+        .find(|b| b.key == "source")
+        .unwrap();
+    assert_eq!(
+        source.config.get("projection"),
+        Some(&serde_json::json!(["name", "score"]))
+    );
+
+    // A projection already over the scan is narrowed, not stacked.
+    let plan = rules::optimize(sink(L::Filter {
+        input: Box::new(L::Project {
+            input: Box::new(L::Project {
+                input: Box::new(scan("t.csv", &["a", "b", "c", "d"])),
+                columns: vec!["c".into(), "b".into(), "a".into()],
+            }),
+            columns: vec!["c".into(), "a".into()],
+        }),
+        expr: "a > 1".into(),
+    }));
+    let L::Sink { input, .. } = &plan else {
+        unreachable!()
+    };
+    let L::Filter { input, .. } = &**input else {
+        unreachable!()
+    };
+    assert!(
+        matches!(&**input, L::Project { input, columns } if matches!(**input, L::Scan { .. }) && columns == &["c", "a"]),
+        "{:?}",
+        input
+    );
+
+    // Sinks write every column, and count(*) still needs one to count rows.
+    let all = rules::optimize(sink(scan("t.csv", &["a", "b"])));
+    assert_eq!(scan_projection(&all, "t.csv"), None);
+    let counted = rules::optimize(sink(L::Aggregate {
+        input: Box::new(scan("t.csv", &["a", "b"])),
+        group_by: vec![],
+        aggs: vec![emsqrt_core::dag::Aggregation::Count],
+    }));
+    assert_eq!(scan_projection(&counted, "t.csv"), Some(vec!["a".into()]));
+}
+
+#[test]
+fn test_join_pruning_keeps_output_names() {
+    let join = |columns: &[&str]| {
+        sink(L::Project {
+            input: Box::new(L::Join {
+                left: Box::new(scan("l.csv", &["id", "v", "a", "b"])),
+                right: Box::new(scan("r.csv", &["id", "v", "c"])),
+                on: vec![("id".into(), "id".into())],
+                join_type: JoinType::Left,
+            }),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        })
+    };
+
+    let plan = join(&["id", "a", "c"]);
+    let pruned = rules::optimize(plan.clone());
+    assert_eq!(
+        scan_projection(&pruned, "l.csv"),
+        Some(vec!["id".into(), "a".into()])
+    );
+    assert_eq!(
+        scan_projection(&pruned, "r.csv"),
+        Some(vec!["id".into(), "c".into()])
+    );
+    assert_eq!(sink_columns(&pruned), sink_columns(&plan));
+
+    // Dropping the left `v` would turn `v_right` back into `v`.
+    let plan = join(&["id", "v_right"]);
+    let pruned = rules::optimize(plan.clone());
+    assert_eq!(scan_projection(&pruned, "l.csv"), None);
+    assert_eq!(sink_columns(&pruned), sink_columns(&plan));
+
+    // A join condition reads output names too.
+    let plan = sink(L::Project {
+        input: Box::new(L::ThetaJoin {
+            left: Box::new(scan("l.csv", &["id", "v", "a", "b"])),
+            right: Box::new(scan("r.csv", &["id", "v", "c"])),
+            condition: Some("b < id_right".into()),
+            join_type: JoinType::Inner,
+        }),
+        columns: vec!["id".into(), "a".into()],
+    });
+    let pruned = rules::optimize(plan.clone());
+    assert_eq!(
+        scan_projection(&pruned, "l.csv"),
+        Some(vec!["id".into(), "a".into(), "b".into()])
+    );
+    assert_eq!(scan_projection(&pruned, "r.csv"), Some(vec!["id".into()]));
+    assert_eq!(sink_columns(&pruned), sink_columns(&plan));
+}
+
+const INPUT: &str = "id,name,score,junk\n1,a,0.5,x\n2,b,2.5,7\n3,c,3.5,y\n";
+
+/// Run `scan(in.csv) → filter → map → project(label) → sink`, optimized or
+/// not; `junk_options` is spliced into the `junk` field definition. Without
+/// the rule nothing reaches the scan: lowering pushes a projection only
+/// through filters.
+fn run(dir: &str, optimize: bool, junk_options: &str) -> Result<RunManifest, ExecError> {
+    fs::write(format!("{}/in.csv", dir), INPUT).unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8" }}
+      - {{ name: "score", type: "Float64" }}
+      - {{ name: "junk", type: "Int64", nullable: true {junk_options} }}
+  - op: filter
+    expr: "score > 1.0"
+  - op: map
+    expr: "name AS label"
+  - op: project
+    columns: ["label"]
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: "csv"
+"#
+    );
+    let mut lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+    if optimize {
+        lp = rules::optimize(lp);
+    }
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te)
+}
+
+#[test]
+fn test_pruned_csv_scan_skips_unread_columns_but_checks_strict_ones() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let source = format!("{}/in.csv", dir);
+    let output = || fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+
+    let manifest = run(&dir, false, "").unwrap();
+    let expected = output();
+    assert_eq!(expected, "label\nb\nc\n");
+    assert_eq!(manifest.parse_errors[&source].get("junk"), Some(&2));
+
+    // The pruned reader never parses `junk`.
+    let manifest = run(&dir, true, "").unwrap();
+    assert_eq!(output(), expected);
+    assert!(manifest
+        .parse_errors
+        .get(&source)
+        .is_none_or(|counts| !counts.contains_key("junk")));
+
+    // A column whose bad values fail the run is still read.
+    let err = run(&dir, true, ", on_parse_error: fail").unwrap_err();
+    assert!(err.to_string().contains("'junk'"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,155 @@
+//! Alignment of inputs with different column sets (`align` on scans, unions)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::align::{align_columns, unify_schemas, AlignPolicy};
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::traits::Operator;
+use emsqrt_operators::union::Union;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(columns: &[(&str, Vec<Scalar>)]) -> RowBatch {
+    RowBatch {
+        columns: columns
+            .iter()
+            .map(|(name, values)| Column {
+                name: name.to_string(),
+                values: values.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn columns(batch: &RowBatch) -> Vec<(&str, &[Scalar])> {
+    batch
+        .columns
+        .iter()
+        .map(|c| (c.name.as_str(), c.values.as_slice()))
+        .collect()
+}
+
+#[test]
+fn test_unify_schemas_covers_every_column() {
+    let a = Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("name", DataType::Utf8, false),
+    ]);
+    let b = Schema::new(vec![
+        Field::new("id", DataType::Int64, false),
+        Field::new("score", DataType::Float64, false),
+    ]);
+    let unified = unify_schemas(&[a.clone(), b]).unwrap();
+    let fields: Vec<_> = unified
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.nullable))
+        .collect();
+    assert_eq!(fields, [("id", false), ("name", true), ("score", true)]);
+
+    let clash = Schema::new(vec![Field::new("id", DataType::Utf8, false)]);
+    let err = unify_schemas(&[a, clash]).unwrap_err();
+    assert!(err.contains("column 'id' is Int64"), "{}", err);
+}
+
+#[test]
+fn test_align_columns_per_policy() {
+    let target = ["id".to_string(), "name".to_string()];
+    let input = batch(&[
+        ("extra", vec![Scalar::Bool(true)]),
+        ("id", vec![Scalar::I64(1)]),
+    ]);
+
+    let aligned = align_columns(&input, &target, AlignPolicy::FillAndDrop).unwrap();
+    assert_eq!(
+        columns(&aligned),
+        [("id", &[Scalar::I64(1)][..]), ("name", &[Scalar::Null][..])]
+    );
+    let err = align_columns(&input, &target, AlignPolicy::FillMissing).unwrap_err();
+    assert!(err.contains("unexpected column 'extra'"), "{}", err);
+    let err = align_columns(&input, &target, AlignPolicy::Exact).unwrap_err();
+    assert!(err.contains("missing column 'name'"), "{}", err);
+}
+
+#[test]
+fn test_union_operator_aligns_ragged_inputs() {
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let inputs = [
+        batch(&[("id", vec![Scalar::I64(1)]), ("a", vec![Scalar::I64(10)])]),
+        batch(&[("b", vec![Scalar::I64(20)]), ("id", vec![Scalar::I64(2)])]),
+    ];
+
+    // No declared columns: the output has every column any input has.
+    let union = Union {
+        schema: Some(Schema::new(vec![]).with_align(AlignPolicy::FillMissing)),
+    };
+    let out = union.eval_block(&inputs, &budget).unwrap();
+    assert_eq!(
+        columns(&out),
+        [
+            ("id", &[Scalar::I64(1), Scalar::I64(2)][..]),
+            ("a", &[Scalar::I64(10), Scalar::Null][..]),
+            ("b", &[Scalar::Null, Scalar::I64(20)][..]),
+        ]
+    );
+
+    // Without alignment the inputs must match.
+    let strict = Union { schema: None };
+    assert!(strict.eval_block(&inputs, &budget).is_err());
+}
+
+fn run_template(dir: &str, align: &str) -> Result<String, String> {
+    fs::write(format!("{}/part1.csv", dir), "id,name\n1,a\n2,b\n").unwrap();
+    fs::write(format!("{}/part2.csv", dir), "id,extra\n3,x\n").unwrap();
+    let output = format!("{}/out.csv", dir);
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source_template: "{dir}/part{{1..2}}.csv"
+    align: {align}
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "name", type: "Utf8", nullable: true }}
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    );
+    let plan = parse_yaml_pipeline(&yaml).map_err(|e| e.to_string())?.plan;
+    let program = lower_to_physical(&plan);
+    let te = plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .map_err(|e| e.to_string())?;
+    Ok(fs::read_to_string(&output).unwrap())
+}
+
+#[test]
+fn test_multi_file_scan_fills_missing_columns() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let out = run_template(&dir, "fill_and_drop").unwrap();
+    assert_eq!(out, "id,name\n1,a\n2,b\n3,\n");
+
+    let err = run_template(&dir, "fill_missing").unwrap_err();
+    assert!(err.contains("unexpected column 'extra'"), "{}", err);
+    let err = run_template(&dir, "exact").unwrap_err();
+    assert!(err.contains("missing required column 'name'"), "{}", err);
+    assert!(run_template(&dir, "loose").is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,187 @@
+//! Read retries with offset resumption (`ResumableReader`, `read_retry`)
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use emsqrt_core::config::ReadRetryConfig;
+use emsqrt_io::buf::{ResumableFile, ResumableReader};
+use emsqrt_io::readers::csv::CsvReader;
+use emsqrt_planner::parse_yaml_pipeline;
+use test_data_gen::create_temp_spill_dir;
+
+/// In-memory source that returns at most 5 bytes per read, like a slow
+/// mount, fails every third read while `failures` lasts, and fails every
+/// read from byte `broken_at` on.
+struct Flaky {
+    data: Arc<Vec<u8>>,
+    pos: usize,
+    reads: Arc<AtomicUsize>,
+    failures: Arc<AtomicUsize>,
+    broken_at: usize,
+}
+
+impl Read for Flaky {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reads.fetch_add(1, Ordering::SeqCst);
+        if self.pos >= self.broken_at {
+            return Err(io::Error::other("host unreachable"));
+        }
+        if n % 3 == 2
+            && self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |f| f.checked_sub(1))
+                .is_ok()
+        {
+            return Err(io::Error::other("stale file handle"));
+        }
+        let len = buf.len().min(5).min(self.data.len() - self.pos);
+        buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn flaky(
+    data: &str,
+    failures: usize,
+    broken_at: usize,
+    max_retries: usize,
+) -> io::Result<ResumableReader<Flaky>> {
+    let data = Arc::new(data.as_bytes().to_vec());
+    let reads = Arc::new(AtomicUsize::new(0));
+    let failures = Arc::new(AtomicUsize::new(failures));
+    let retry = ReadRetryConfig {
+        max_retries,
+        initial_backoff_ms: 0,
+        max_backoff_ms: 0,
+    };
+    ResumableReader::new(
+        "nfs/events.csv",
+        retry,
+        Box::new(move |offset| {
+            Ok(Flaky {
+                data: data.clone(),
+                pos: offset as usize,
+                reads: reads.clone(),
+                failures: failures.clone(),
+                broken_at,
+            })
+        }),
+    )
+}
+
+fn csv(rows: usize) -> String {
+    let mut out = String::from("id,name\n");
+    for i in 0..rows {
+        out.push_str(&format!("{},user-{}\n", i, i));
+    }
+    out
+}
+
+#[test]
+fn test_failed_reads_resume_at_their_offset() {
+    let data = csv(200);
+    let mut reader = flaky(&data, 50, usize::MAX, 3).unwrap();
+    let mut read = String::new();
+    reader.read_to_string(&mut read).unwrap();
+    assert_eq!(read, data);
+    assert_eq!(reader.offset(), data.len() as u64);
+
+    // Through the CSV reader: every row once, in order.
+    let mut reader =
+        CsvReader::from_reader(flaky(&data, 50, usize::MAX, 3).unwrap(), true).unwrap();
+    let mut ids = Vec::new();
+    while let Some(batch) = reader.next_batch(64).unwrap() {
+        ids.extend(batch.columns[0].values.iter().map(|v| format!("{:?}", v)));
+    }
+    assert_eq!(ids.len(), 200);
+    assert_eq!(ids[199], "Str(\"199\")");
+}
+
+#[test]
+fn test_persistent_failures_name_the_file_and_offset() {
+    // Retries disabled: the first failure (the third read, at byte 10) is final.
+    let mut reader = flaky(&csv(10), 1, usize::MAX, 0).unwrap();
+    let mut buf = [0u8; 64];
+    let err = (0..3)
+        .try_for_each(|_| reader.read(&mut buf).map(drop))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "read of 'nfs/events.csv' failed at byte 10 after 0 retries: stale file handle"
+    );
+
+    // Failures outlasting the retries surface through the readers.
+    let reader = flaky(&csv(100), 0, 300, 2).unwrap();
+    let err = CsvReader::from_reader(reader, true)
+        .and_then(|mut r| r.next_batch(1000))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        err.contains("read of 'nfs/events.csv' failed at byte 300 after 2 retries"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_missing_files_fail_without_retrying() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let retry = ReadRetryConfig {
+        max_retries: 5,
+        initial_backoff_ms: 10_000,
+        max_backoff_ms: 10_000,
+    };
+    let started = Instant::now();
+    let err = ResumableFile::open(format!("{}/missing.csv", dir), &retry)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(started.elapsed().as_secs() < 5);
+
+    // A real file reads through unchanged.
+    let path = format!("{}/present.csv", dir);
+    fs::write(&path, csv(3)).unwrap();
+    let mut text = String::new();
+    ResumableFile::open(&path, &retry)
+        .unwrap()
+        .read_to_string(&mut text)
+        .unwrap();
+    assert_eq!(text, csv(3));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_pipeline_config_sets_read_retries() {
+    let yaml = r#"
+config:
+  read_retry:
+    max_retries: 8
+    initial_backoff_ms: 500
+steps:
+  - op: scan
+    source: "events.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let config = parse_yaml_pipeline(yaml).unwrap().config;
+    assert_eq!(
+        config.read_retry,
+        Some(ReadRetryConfig {
+            max_retries: 8,
+            initial_backoff_ms: 500,
+            max_backoff_ms: ReadRetryConfig::default().max_backoff_ms,
+        })
+    );
+}
@@ -0,0 +1,158 @@
+//! Block results waiting for their dependents spill under memory pressure
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::id::{BlockId, OpId};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::results::ResultStore;
+use emsqrt_exec::Engine;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_planner::logical::JoinType;
+use emsqrt_planner::{estimate_work, lower_to_physical, WorkHint};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, TeBlock};
+use test_data_gen::create_temp_spill_dir;
+
+fn block(id: u64, deps: &[u64]) -> TeBlock {
+    TeBlock {
+        id: BlockId::new(id),
+        op: OpId::new(id),
+        schema: Schema::new(vec![]),
+        deps: deps.iter().map(|&d| BlockId::new(d)).collect(),
+        range_rows: None,
+        partition: None,
+        exchange: None,
+    }
+}
+
+fn ids(values: std::ops::Range<i64>) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: values.map(Scalar::I64).collect(),
+        }],
+    }
+}
+
+#[test]
+fn test_results_past_half_the_budget_are_spilled_and_restored() {
+    let dir = create_temp_spill_dir();
+    let spill = Arc::new(Mutex::new(SpillManager::new(
+        Box::new(FsStorage::new()),
+        Codec::None,
+        dir.clone(),
+    )));
+    // Block 1 feeds blocks 3 and 4; block 2 feeds block 4.
+    let order = [
+        block(1, &[]),
+        block(2, &[]),
+        block(3, &[1]),
+        block(4, &[1, 2]),
+    ];
+    let mut store = ResultStore::new(spill.clone(), &order);
+    // 500 i64 values are 4000 bytes: one fits in half of 10000, two do not.
+    let budget = MemoryBudgetImpl::new(10_000);
+
+    assert!(!store.insert(1, ids(0..500), &budget).unwrap());
+    assert!(store.insert(2, ids(500..1000), &budget).unwrap());
+    assert_eq!(store.held_bytes(), 4000);
+    assert_eq!(store.spilled(), 1);
+    assert_eq!(spill.lock().unwrap().list_segments().len(), 1);
+    // Block 3 is not the last reader of block 1's result.
+    assert_eq!(store.freed_bytes(&order[2]), 0);
+    // A result no block reads is not held.
+    assert!(!store.insert(3, ids(0..100), &budget).unwrap());
+
+    assert_eq!(store.take(1, &budget).unwrap().num_rows(), 500);
+    // Block 4 releases both, but only block 1's result is in memory.
+    assert_eq!(store.freed_bytes(&order[3]), 4000);
+    assert_eq!(store.take(1, &budget).unwrap().num_rows(), 500);
+    assert_eq!(store.held_bytes(), 0);
+    let restored = store.take(2, &budget).unwrap();
+    assert_eq!(restored.columns[0].values[0], Scalar::I64(500));
+    assert!(spill.lock().unwrap().list_segments().is_empty());
+    assert!(store.take(1, &budget).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_join_inputs_spill_under_a_small_budget() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let left = format!("{}/left.csv", dir);
+    let right = format!("{}/right.csv", dir);
+    let mut l = String::from("id,a\n");
+    let mut r = String::from("id,b\n");
+    for i in 0..40 {
+        l.push_str(&format!("{},{}\n", i, i * 2));
+        r.push_str(&format!("{},{}\n", i * 2, i));
+    }
+    fs::write(&left, l).unwrap();
+    fs::write(&right, r).unwrap();
+    let schema = |col: &str| {
+        Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new(col, DataType::Int64, false),
+        ])
+    };
+    let output = format!("{}/out.csv", dir);
+    let plan = L::Sink {
+        input: Box::new(L::Join {
+            left: Box::new(L::Scan {
+                source: left.clone(),
+                schema: schema("a"),
+            }),
+            right: Box::new(L::Scan {
+                source: right.clone(),
+                schema: schema("b"),
+            }),
+            on: vec![("id".into(), "id".into())],
+            join_type: JoinType::Inner,
+        }),
+        destination: output.clone(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&plan);
+    let hint = WorkHint {
+        source_rows: vec![(left, 40), (right, 40)],
+        source_bytes: vec![],
+        ..Default::default()
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &estimate_work(&plan, Some(&hint)),
+        BlockSizeHint { rows_per_block: 10 },
+        &program.join_keys(),
+    )
+    .unwrap();
+
+    let mut outputs = Vec::new();
+    for mem_cap_bytes in [1 << 30, 1024] {
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            mem_cap_bytes,
+            ..Default::default()
+        };
+        let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+        let spilled: u64 = manifest
+            .block_costs
+            .iter()
+            .filter_map(|c| c.metrics.get("result_spilled"))
+            .sum();
+        outputs.push((spilled, fs::read_to_string(&output).unwrap()));
+    }
+    assert_eq!(outputs[0].0, 0);
+    assert!(outputs[1].0 > 0, "no result spilled");
+    assert_eq!(outputs[0].1, outputs[1].1);
+    assert_eq!(outputs[0].1.lines().count(), 21);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -1,10 +1,9 @@
-//! RowBatch helper functions tests (sort, hash, concat)
+//! RowBatch helper functions tests (sort, hash, concat, head, describe, Display)
 
 mod test_data_gen;
 
-use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::hash::{PartitionHashKind, PartitionHasher};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
-use test_data_gen::generate_sorted_batch;
 
 #[test]
 fn test_sort_by_single_column() {
@@ -186,6 +185,46 @@ fn test_hash_columns_consistency() {
     assert_eq!(hash1, hash2, "Hash should be deterministic");
 }
 
+#[test]
+fn test_hash_columns_with_pluggable_hashers() {
+    let batch = RowBatch {
+        columns: vec![Column {
+            name: "id".to_string(),
+            values: (0..1000).map(Scalar::I64).collect(),
+        }],
+    };
+    let keys = ["id".to_string()];
+
+    // Default hasher matches the plain hash_columns layout
+    assert_eq!(
+        batch.hash_columns(&keys, 8).unwrap(),
+        batch
+            .hash_columns_with(&keys, 8, &PartitionHasher::default())
+            .unwrap()
+    );
+
+    for kind in [
+        PartitionHashKind::Blake3,
+        PartitionHashKind::Xxhash64,
+        PartitionHashKind::Ahash,
+    ] {
+        let a = batch
+            .hash_columns_with(&keys, 8, &PartitionHasher::new(kind, 7))
+            .unwrap();
+        let b = batch
+            .hash_columns_with(&keys, 8, &PartitionHasher::new(kind, 7))
+            .unwrap();
+        let other_seed = batch
+            .hash_columns_with(&keys, 8, &PartitionHasher::new(kind, 8))
+            .unwrap();
+
+        // Same seed is reproducible; a different seed reshuffles rows
+        assert_eq!(a, b, "{:?} not deterministic", kind);
+        assert_ne!(a, other_seed, "{:?} ignores seed", kind);
+        assert!(a.iter().all(|&p| p < 8));
+    }
+}
+
 #[test]
 fn test_concat_schemas() {
     let left = RowBatch {
@@ -373,3 +412,84 @@ fn test_sort_preserves_row_order_across_columns() {
     assert_eq!(batch.columns[0].values[2], Scalar::I32(30));
     assert_eq!(batch.columns[1].values[2], Scalar::Str("third".to_string()));
 }
+
+fn people() -> RowBatch {
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "id".to_string(),
+                values: vec![Scalar::I64(7), Scalar::I64(12), Scalar::I64(3)],
+            },
+            Column {
+                name: "name".to_string(),
+                values: vec![
+                    Scalar::Str("ann".to_string()),
+                    Scalar::Null,
+                    Scalar::Str("bo\nb".to_string()),
+                ],
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_head_takes_leading_rows() {
+    let batch = people();
+    let head = batch.head(2);
+    assert_eq!(head.num_rows(), 2);
+    assert_eq!(head.columns[0].values, [Scalar::I64(7), Scalar::I64(12)]);
+    assert_eq!(head.columns[1].name, "name");
+    assert_eq!(batch.head(10).num_rows(), 3);
+    assert_eq!(batch.head(0).columns.len(), 2);
+}
+
+#[test]
+fn test_display_renders_aligned_table() {
+    let table = people().to_string();
+    let expected = "\
++----+------+
+| id | name |
++----+------+
+|  7 | ann  |
+| 12 | null |
+|  3 | bo b |
++----+------+
+";
+    assert_eq!(table, expected);
+
+    let long = RowBatch {
+        columns: vec![Column {
+            name: "s".to_string(),
+            values: vec![Scalar::Str("x".repeat(100)), Scalar::Bin(vec![0xab, 0x01])],
+        }],
+    };
+    let table = long.to_string();
+    assert!(
+        table.contains(&format!("| {}… |", "x".repeat(39))),
+        "{}",
+        table
+    );
+    assert!(table.contains("| 0xab01"), "{}", table);
+}
+
+#[test]
+fn test_describe_reports_types_nulls_and_ranges() {
+    let summary = people().describe();
+    assert_eq!(summary.rows, 3);
+    let id = &summary.columns[0];
+    assert_eq!(id.data_type, Some(emsqrt_core::schema::DataType::Int64));
+    assert_eq!(id.stats.min, Some(Scalar::I64(3)));
+    assert_eq!(id.stats.max, Some(Scalar::I64(12)));
+    assert_eq!(id.null_percent(), 0.0);
+    let name = &summary.columns[1];
+    assert_eq!(name.stats.null_count, 1);
+    assert!((name.null_percent() - 100.0 / 3.0).abs() < 1e-9);
+
+    let table = summary.to_string();
+    assert!(table.starts_with("3 rows\n"), "{}", table);
+    assert!(
+        table.contains("| name   | Utf8  |     1 |   33.3 | ann | bo b |"),
+        "{}",
+        table
+    );
+}
@@ -0,0 +1,66 @@
+//! Per-block and per-operator metrics in the run manifest
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use test_data_gen::{create_temp_spill_dir, run_aggregate_pipeline};
+
+#[test]
+fn test_manifest_records_rows_bytes_and_memory_per_operator() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let manifest = run_aggregate_pipeline(&dir, None, EngineConfig::default()).unwrap();
+    assert_eq!(manifest.operators, manifest.op_totals());
+    let ops: Vec<&str> = manifest.operators.iter().map(|t| t.op.as_str()).collect();
+    assert_eq!(ops, ["source", "aggregate", "sink"]);
+
+    let [source, aggregate, sink] = &manifest.operators[..] else {
+        unreachable!()
+    };
+    assert_eq!((source.rows_in, source.rows_out), (0, 4000));
+    assert_eq!((aggregate.rows_in, aggregate.rows_out), (4000, 500));
+    assert_eq!(sink.rows_in, 500);
+    // Each operator reads what the one below it produced.
+    assert_eq!(aggregate.bytes_in, source.bytes_out);
+    assert_eq!(sink.bytes_in, aggregate.bytes_out);
+    assert!(aggregate.bytes_in > aggregate.bytes_out);
+    assert!(aggregate.peak_memory_bytes > 0);
+    assert_eq!(aggregate.bytes_spilled + aggregate.bytes_spill_read, 0);
+
+    let metrics = manifest.metrics();
+    assert_eq!(metrics.operators, manifest.operators);
+    assert_eq!(metrics.blocks.len(), manifest.block_costs.len());
+    assert_eq!(
+        metrics.peak_memory_bytes,
+        manifest
+            .operators
+            .iter()
+            .map(|t| t.peak_memory_bytes)
+            .max()
+            .unwrap()
+    );
+    let json = serde_json::to_value(&metrics).unwrap();
+    assert!(json["operators"][1]["peak_memory_bytes"].as_u64().unwrap() > 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spilling_operator_reports_spill_reads_and_capped_peak() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let manifest = run_aggregate_pipeline(&dir, Some(4 << 10), EngineConfig::default()).unwrap();
+    let aggregate = &manifest.operators[1];
+    assert_eq!(aggregate.op, "aggregate");
+    assert_eq!(aggregate.rows_out, 500);
+    assert!(aggregate.bytes_spilled > 0);
+    // Spilled partitions are read back to finish the groups.
+    assert!(aggregate.bytes_spill_read > 0);
+    assert!(aggregate.peak_memory_bytes <= 4 << 10);
+
+    let _ = fs::remove_dir_all(&dir);
+}
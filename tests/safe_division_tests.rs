@@ -0,0 +1,179 @@
+//! Division by zero: safe_div(), nullif() and the arithmetic error policy
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::expr::{ArithErrorPolicy, EvalContext, Expr};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// `a` and `b` columns of `(a, b)` rows.
+fn rows(rows: &[(Scalar, Scalar)]) -> RowBatch {
+    let column = |name: &str, pick: fn(&(Scalar, Scalar)) -> Scalar| Column {
+        name: name.into(),
+        values: rows.iter().map(pick).collect(),
+    };
+    RowBatch {
+        columns: vec![column("a", |r| r.0.clone()), column("b", |r| r.1.clone())],
+    }
+}
+
+fn eval(expr: &str, batch: &RowBatch, policy: ArithErrorPolicy) -> Result<Vec<Scalar>, String> {
+    let expr = Expr::parse(expr).unwrap();
+    let ctx = EvalContext {
+        arith_errors: policy,
+        ..Default::default()
+    };
+    (0..batch.num_rows())
+        .map(|row| expr.evaluate_with(batch, row, &ctx))
+        .collect()
+}
+
+#[test]
+fn test_safe_div_and_nullif() {
+    use Scalar::*;
+    let batch = rows(&[
+        (I64(7), I64(2)),
+        (I64(7), I64(0)),
+        (F64(1.5), F64(0.0)),
+        (Null, I64(3)),
+    ]);
+    let policy = ArithErrorPolicy::Error;
+    assert_eq!(
+        eval("safe_div(a, b)", &batch, policy).unwrap(),
+        [I64(3), Null, Null, Null]
+    );
+    assert_eq!(
+        eval("nullif(b, 0)", &batch, policy).unwrap(),
+        [I64(2), Null, Null, I64(3)]
+    );
+    // Arithmetic on a null is null, so a zero divisor turned null skips the row.
+    assert_eq!(
+        eval("a / nullif(b, 0)", &batch, policy).unwrap(),
+        [I64(3), Null, Null, Null]
+    );
+    assert!(eval("a / b", &batch, policy)
+        .unwrap_err()
+        .contains("division by zero"));
+}
+
+#[test]
+fn test_policy_decides_what_division_by_zero_yields() {
+    use Scalar::*;
+    let batch = rows(&[(I64(9), I64(3)), (I64(9), I64(0)), (F32(1.0), I32(0))]);
+    assert_eq!(
+        eval("a / b", &batch, ArithErrorPolicy::Null).unwrap(),
+        [I64(3), Null, Null]
+    );
+    // The default takes the type the quotient would have had.
+    assert_eq!(
+        eval("a / b", &batch, ArithErrorPolicy::Default(-1.0)).unwrap(),
+        [I64(3), I64(-1), F32(-1.0)]
+    );
+    // Type errors are not arithmetic errors.
+    let text = rows(&[(Str("x".into()), I64(0))]);
+    assert!(eval("a / b", &text, ArithErrorPolicy::Null).is_err());
+}
+
+#[test]
+fn test_policy_parses_from_text_env_style_and_yaml() {
+    assert_eq!(
+        ArithErrorPolicy::parse("error"),
+        Some(ArithErrorPolicy::Error)
+    );
+    assert_eq!(
+        ArithErrorPolicy::parse("NULL"),
+        Some(ArithErrorPolicy::Null)
+    );
+    assert_eq!(
+        ArithErrorPolicy::parse("default(0.5)"),
+        Some(ArithErrorPolicy::Default(0.5))
+    );
+    assert_eq!(ArithErrorPolicy::parse("default()"), None);
+    assert_eq!(ArithErrorPolicy::parse("skip"), None);
+    assert_eq!(ArithErrorPolicy::Default(0.0).to_string(), "default(0)");
+
+    let yaml = |policy: &str| {
+        format!("config:\n  arithmetic_errors: {}\nsteps:\n  - op: scan\n    source: \"in.csv\"\n    schema:\n      - {{ name: \"id\", type: \"Int64\" }}\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n", policy)
+    };
+    let parsed =
+        |policy: &str| parse_yaml_pipeline(&yaml(policy)).map(|p| p.config.arithmetic_errors);
+    // An unquoted `null` is the null policy, not a missing one.
+    assert_eq!(parsed("null").unwrap(), Some(ArithErrorPolicy::Null));
+    assert_eq!(
+        parsed("\"default(-1)\"").unwrap(),
+        Some(ArithErrorPolicy::Default(-1.0))
+    );
+    assert!(parsed("sometimes").is_err());
+    let unset = yaml("error").replace("config:\n  arithmetic_errors: error\n", "");
+    assert_eq!(
+        parse_yaml_pipeline(&unset)
+            .unwrap()
+            .config
+            .arithmetic_errors,
+        None
+    );
+}
+
+#[test]
+fn test_one_zero_divisor_fails_the_run_only_under_the_error_policy() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "spend,clicks\n10,2\n5,0\n9,3\n8,1\n").unwrap();
+    let output = format!("{}/out.csv", dir);
+
+    let run = |expr: &str, policy: ArithErrorPolicy| {
+        let lp = L::Sink {
+            input: Box::new(L::Filter {
+                input: Box::new(L::Scan {
+                    source: input.clone(),
+                    schema: Schema::new(vec![
+                        Field::new("spend", DataType::Int64, false),
+                        Field::new("clicks", DataType::Int64, false),
+                    ]),
+                }),
+                expr: expr.into(),
+            }),
+            destination: output.clone(),
+            format: "csv".into(),
+        };
+        let program = lower_to_physical(&lp);
+        let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            arithmetic_errors: policy,
+            ..Default::default()
+        };
+        Engine::new(config)
+            .unwrap()
+            .run(&program, &te)
+            .map(|_| fs::read_to_string(&output).unwrap())
+    };
+
+    let err = run("spend / clicks > 3", ArithErrorPolicy::Error).unwrap_err();
+    assert!(err.to_string().contains("division by zero"), "{}", err);
+    let kept = "spend,clicks\n10,2\n8,1\n";
+    assert_eq!(
+        run("spend / clicks > 3", ArithErrorPolicy::Null).unwrap(),
+        kept
+    );
+    assert_eq!(
+        run("safe_div(spend, clicks) > 3", ArithErrorPolicy::Error).unwrap(),
+        kept
+    );
+    // A default of 100 keeps the row with no clicks.
+    assert_eq!(
+        run("spend / clicks > 3", ArithErrorPolicy::Default(100.0)).unwrap(),
+        "spend,clicks\n10,2\n5,0\n8,1\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
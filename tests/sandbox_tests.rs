@@ -0,0 +1,190 @@
+//! Sandbox restrictions for untrusted pipelines
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Write;
+
+use emsqrt_core::config::{EngineConfig, SandboxConfig};
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::id::SpillId;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_planner::{estimate_work, lower_to_physical, OperatorBinding};
+use emsqrt_te::plan_te;
+use serde_json::json;
+use test_data_gen::{create_temp_spill_dir, generate_random_batch};
+
+fn run_copy(dir: &str, sandbox: SandboxConfig) -> Result<(), ExecError> {
+    let input = format!("{}/in/data.csv", dir);
+    fs::create_dir_all(format!("{}/in", dir)).unwrap();
+    let mut f = fs::File::create(&input).unwrap();
+    writeln!(f, "id\n1\n2").unwrap();
+
+    let plan = L::Sink {
+        input: Box::new(L::Scan {
+            source: input,
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        destination: format!("{}/out/result.csv", dir),
+        format: "csv".to_string(),
+    };
+    fs::create_dir_all(format!("{}/out", dir)).unwrap();
+    let phys = lower_to_physical(&plan);
+    let te = plan_te(&phys.plan, &estimate_work(&plan, None), 1 << 20).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        sandbox: Some(sandbox),
+        ..Default::default()
+    };
+    Engine::new(config)?.run(&phys, &te).map(|_| ())
+}
+
+#[test]
+fn test_prefix_matching_is_segment_aware() {
+    let sandbox = SandboxConfig {
+        read_allow: vec!["/data".into()],
+        sink_allow: vec!["s3://bucket/out/".into()],
+        sink_deny: vec!["s3://bucket/out/private".into()],
+        ..Default::default()
+    };
+    assert!(sandbox.check_read("/data/x.csv").is_ok());
+    assert!(sandbox.check_read("file:///data/x.csv").is_ok());
+    assert!(sandbox.check_read("/database.csv").is_err());
+    assert!(sandbox.check_read("/data/../etc/passwd").is_err());
+
+    assert!(sandbox.check_write("s3://bucket/out/a.parquet").is_ok());
+    assert!(sandbox
+        .check_write("s3://bucket/out/private/a.parquet")
+        .is_err());
+    assert!(sandbox.check_write("s3://other/out/a.parquet").is_err());
+
+    // Empty allowlists deny everything (read-only / no-read by default)
+    assert!(SandboxConfig::default().check_write("/tmp/x").is_err());
+}
+
+#[test]
+fn test_engine_allows_sandboxed_pipeline() {
+    let dir = create_temp_spill_dir();
+    let sandbox = SandboxConfig {
+        read_allow: vec![format!("{}/in", dir)],
+        sink_allow: vec![format!("{}/out", dir)],
+        ..Default::default()
+    };
+    run_copy(&dir, sandbox).expect("allowed pipeline should run");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_engine_rejects_disallowed_source_and_sink() {
+    let dir = create_temp_spill_dir();
+
+    let no_read = SandboxConfig {
+        sink_allow: vec![format!("{}/out", dir)],
+        ..Default::default()
+    };
+    assert!(matches!(
+        run_copy(&dir, no_read),
+        Err(ExecError::Sandbox(_))
+    ));
+
+    let read_only = SandboxConfig {
+        read_allow: vec![format!("{}/in", dir)],
+        ..Default::default()
+    };
+    assert!(matches!(
+        run_copy(&dir, read_only),
+        Err(ExecError::Sandbox(_))
+    ));
+    // Nothing was written
+    assert!(!std::path::Path::new(&format!("{}/out/result.csv", dir)).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_engine_enforces_wall_time() {
+    let dir = create_temp_spill_dir();
+    let sandbox = SandboxConfig {
+        read_allow: vec![format!("{}/in", dir)],
+        sink_allow: vec![format!("{}/out", dir)],
+        max_wall_time_ms: Some(0),
+        ..Default::default()
+    };
+    let err = run_copy(&dir, sandbox).unwrap_err();
+    assert!(err.to_string().contains("wall time"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_eval_operator_is_sandboxed_like_a_run() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(format!("{}/in", dir)).unwrap();
+    fs::write(format!("{}/in/data.csv", dir), "id\n1\n").unwrap();
+    fs::write(format!("{}/secret.csv", dir), "id\n2\n").unwrap();
+    let engine = |max_wall_time_ms| {
+        Engine::new(EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            sandbox: Some(SandboxConfig {
+                read_allow: vec![format!("{}/in", dir)],
+                sink_allow: vec![format!("{}/out", dir)],
+                max_wall_time_ms,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+    };
+    let source = |path: &str| {
+        json!({
+            "source": format!("{}/{}", dir, path),
+            "schema": Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        })
+    };
+
+    let sandboxed = engine(None);
+    let out = sandboxed
+        .eval_operator("source", &source("in/data.csv"), &[])
+        .unwrap();
+    assert_eq!(out.num_rows(), 1);
+
+    // Outside the read roots: refused before the file is opened.
+    let err = sandboxed
+        .eval_operator("source", &source("secret.csv"), &[])
+        .unwrap_err();
+    assert!(matches!(err, ExecError::Sandbox(_)), "{}", err);
+    let sink = OperatorBinding {
+        key: "sink".into(),
+        config: json!({"destination": format!("{}/elsewhere.csv", dir), "format": "csv"}),
+    };
+    let err = sandboxed.eval_binding(&sink, &[out]).unwrap_err();
+    assert!(matches!(err, ExecError::Sandbox(_)), "{}", err);
+    assert!(!std::path::Path::new(&format!("{}/elsewhere.csv", dir)).exists());
+
+    let err = engine(Some(0))
+        .eval_operator("source", &source("in/data.csv"), &[])
+        .unwrap_err();
+    assert!(err.to_string().contains("wall time"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spill_manager_enforces_byte_limit() {
+    let dir = create_temp_spill_dir();
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone())
+        .with_spill_limit(Some(1024));
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+
+    let small = generate_random_batch(2, &schema);
+    mgr.write_batch(&small, SpillId::new(1), 0).unwrap();
+    assert!(mgr.bytes_written() > 0);
+
+    let big = generate_random_batch(1000, &schema);
+    let err = mgr.write_batch(&big, SpillId::new(1), 1).unwrap_err();
+    assert!(err.to_string().contains("spill limit"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,216 @@
+//! Schema constraints: declaration, propagation through lowering, and `--verify` checks
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan as L};
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_core::schema::{Constraints, DataType, Field, Schema};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn cols(names: &[&str]) -> Vec<String> {
+    names.iter().map(|s| s.to_string()).collect()
+}
+
+fn scan(source: &str, constraints: Constraints) -> L {
+    L::Scan {
+        source: source.to_string(),
+        schema: Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("v", DataType::Int64, false),
+        ])
+        .with_constraints(constraints),
+    }
+}
+
+fn sorted_pk() -> Constraints {
+    Constraints {
+        primary_key: cols(&["id"]),
+        sorted_by: cols(&["id"]),
+        ..Default::default()
+    }
+}
+
+fn run(lp: &L, dir: &str, verify: bool) -> Result<RunManifest, ExecError> {
+    let program = lower_to_physical(lp);
+    let te = plan_te(&program.plan, &estimate_work(lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        verify_constraints: verify,
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te)
+}
+
+#[test]
+fn test_constraint_helpers() {
+    let c = Constraints {
+        primary_key: cols(&["a", "b"]),
+        unique: vec![cols(&["c"])],
+        sorted_by: cols(&["a", "b", "c"]),
+    };
+    assert!(c.is_unique_on(&cols(&["a", "b"])));
+    assert!(c.is_unique_on(&cols(&["c", "d"])));
+    assert!(!c.is_unique_on(&cols(&["a"])));
+    assert!(c.is_sorted_by(&cols(&["a"])));
+    assert!(!c.is_sorted_by(&cols(&["b"])));
+    assert!(!c.is_sorted_by(&[]));
+
+    let projected = c.project(&cols(&["a", "c"]));
+    assert!(projected.primary_key.is_empty());
+    assert_eq!(projected.unique, vec![cols(&["c"])]);
+    assert_eq!(projected.sorted_by, cols(&["a"]));
+    assert!(c.project(&cols(&["d"])).is_empty());
+}
+
+#[test]
+fn test_lowering_exploits_constraints() {
+    let join = |left: Constraints, right: Constraints| {
+        let lp = L::Join {
+            left: Box::new(scan("l.csv", left)),
+            right: Box::new(scan("r.csv", right)),
+            on: vec![("id".into(), "id".into())],
+            join_type: JoinType::Inner,
+        };
+        let program = lower_to_physical(&lp);
+        program
+            .bindings
+            .values()
+            .find(|b| b.key.starts_with("join"))
+            .unwrap()
+            .key
+            .clone()
+    };
+    assert_eq!(join(sorted_pk(), sorted_pk()), "join_merge");
+    assert_eq!(join(sorted_pk(), Constraints::default()), "join_hash");
+
+    let aggregate = |input: L| {
+        let lp = L::Aggregate {
+            input: Box::new(input),
+            group_by: cols(&["id"]),
+            aggs: vec![Aggregation::Count],
+        };
+        let program = lower_to_physical(&lp);
+        let binding = program
+            .bindings
+            .values()
+            .find(|b| b.key == "aggregate")
+            .unwrap()
+            .clone();
+        binding.config.get("unique_groups").is_some()
+    };
+    assert!(aggregate(scan("t.csv", sorted_pk())));
+    assert!(aggregate(L::Filter {
+        input: Box::new(scan("t.csv", sorted_pk())),
+        expr: "v > 0".into(),
+    }));
+    assert!(!aggregate(scan("t.csv", Constraints::default())));
+    // Renames may invalidate the key.
+    assert!(!aggregate(L::Map {
+        input: Box::new(scan("t.csv", sorted_pk())),
+        expr: "id AS key".into(),
+    }));
+    assert!(aggregate(L::Map {
+        input: Box::new(scan("t.csv", sorted_pk())),
+        expr: "v * 2 AS w, v AS value".into(),
+    }));
+
+    // A map keeps the sort order of the columns it leaves alone.
+    let join_after_map = |expr: &str| {
+        let lp = L::Join {
+            left: Box::new(L::Map {
+                input: Box::new(scan("l.csv", sorted_pk())),
+                expr: expr.into(),
+            }),
+            right: Box::new(scan("r.csv", sorted_pk())),
+            on: vec![("id".into(), "id".into())],
+            join_type: JoinType::Inner,
+        };
+        let program = lower_to_physical(&lp);
+        program.bindings.values().any(|b| b.key == "join_merge")
+    };
+    assert!(join_after_map("id, v + 1 AS w"));
+    assert!(join_after_map("v AS value"));
+    assert!(!join_after_map("id + 1 AS id"));
+    assert!(!join_after_map("v AS id"));
+}
+
+#[test]
+fn test_yaml_scan_constraints() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "t.csv"
+    schema:
+      - { name: "id", type: "Int64", nullable: false }
+      - { name: "v", type: "Int64", nullable: false }
+    constraints:
+      primary_key: ["id"]
+      sorted_by: ["id"]
+  - op: sink
+    destination: "out.csv"
+    format: csv
+"#;
+    let lp = parse_yaml_pipeline(yaml).unwrap().plan;
+    let L::Sink { input, .. } = lp else {
+        panic!("expected sink");
+    };
+    let L::Scan { schema, .. } = *input else {
+        panic!("expected scan");
+    };
+    assert_eq!(schema.constraints, sorted_pk());
+}
+
+#[test]
+fn test_verify_mode() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, ids: &[i64]| {
+        let path = format!("{}/{}", dir, name);
+        let csv: String = std::iter::once("id,v\n".to_string())
+            .chain(ids.iter().map(|i| format!("{},{}\n", i, i * 10)))
+            .collect();
+        fs::write(&path, csv).unwrap();
+        path
+    };
+    let pipeline = |source: &str, constraints: Constraints| L::Sink {
+        input: Box::new(L::Aggregate {
+            input: Box::new(scan(source, constraints)),
+            group_by: cols(&["id"]),
+            aggs: vec![Aggregation::Count],
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".into(),
+    };
+
+    let good = write("good.csv", &(0..500).collect::<Vec<_>>());
+    let manifest = run(&pipeline(&good, sorted_pk()), &dir, true).unwrap();
+    assert!(manifest.warnings.is_empty(), "{:?}", manifest.warnings);
+    let out = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+    assert_eq!(out.lines().count(), 501);
+
+    let unsorted = write("unsorted.csv", &[1, 2, 4, 3]);
+    let sorted_only = Constraints {
+        sorted_by: cols(&["id"]),
+        ..Default::default()
+    };
+    assert!(matches!(
+        run(&pipeline(&unsorted, sorted_only.clone()), &dir, true),
+        Err(ExecError::Constraint(msg)) if msg.contains("not sorted")
+    ));
+    // Constraints are trusted unless verification is on.
+    assert!(run(&pipeline(&unsorted, sorted_only), &dir, false).is_ok());
+
+    let duplicated = write("dup.csv", &[1, 2, 2, 3]);
+    assert!(matches!(
+        run(&pipeline(&duplicated, sorted_pk()), &dir, true),
+        Err(ExecError::Constraint(msg)) if msg.contains("duplicate")
+    ));
+
+    let _ = fs::remove_dir_all(&dir);
+}
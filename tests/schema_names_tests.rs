@@ -0,0 +1,113 @@
+//! Duplicate field names: rejected by validated schemas, renamed by joins
+
+use emsqrt_core::dag::{JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{dedup_names, DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_mem::MemoryBudgetImpl;
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::join::merge::MergeJoin;
+use emsqrt_operators::Operator;
+use emsqrt_planner::{column_lineage, parse_schema_spec, parse_yaml_pipeline};
+
+fn int(name: &str) -> Field {
+    Field::new(name, DataType::Int64, false)
+}
+
+fn batch(names: &[&str]) -> RowBatch {
+    RowBatch {
+        columns: names
+            .iter()
+            .map(|n| Column {
+                name: n.to_string(),
+                values: vec![Scalar::I64(1)],
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_try_new_rejects_duplicates_but_keeps_case() {
+    let err = Schema::try_new(vec![int("id"), int("v"), int("id")]).unwrap_err();
+    assert!(
+        err.to_string().contains("duplicate field name 'id'"),
+        "{}",
+        err
+    );
+
+    let schema = Schema::try_new(vec![int("id"), int("ID")]).unwrap();
+    assert_eq!(schema.index_of("ID"), Some(1));
+
+    assert_eq!(
+        dedup_names(["a", "a", "a_x", "a", "B", "b"].map(String::from), "_x"),
+        ["a", "a_x2", "a_x", "a_x3", "B", "b"]
+    );
+}
+
+#[test]
+fn test_yaml_and_schema_specs_reject_duplicate_columns() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "t.csv"
+    schema:
+      - { name: "id", type: "Int64", nullable: false }
+      - { name: "id", type: "Utf8", nullable: true }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let err = parse_yaml_pipeline(yaml).unwrap_err().to_string();
+    assert!(err.contains("duplicate field name 'id'"), "{}", err);
+
+    assert!(parse_schema_spec("id:Int64,name,id").is_err());
+    assert!(parse_schema_spec("id:Int64,Id").is_ok());
+}
+
+#[test]
+fn test_join_renames_collisions_the_same_at_plan_and_run_time() {
+    // `v_right` is already a left column, so the right `v` cannot take it.
+    let left = Schema::new(vec![int("id"), int("v"), int("v_right")]);
+    let right = Schema::new(vec![int("id"), int("v")]);
+    let schema = JoinType::Inner.output_schema(&left, &right);
+    let names: Vec<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["id", "v", "v_right", "id_right", "v_right2"]);
+
+    let inputs = [batch(&["id", "v", "v_right"]), batch(&["id", "v"])];
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let on = vec![("id".to_string(), "id".to_string())];
+    let hash = HashJoin {
+        on: on.clone(),
+        ..Default::default()
+    };
+    let merge = MergeJoin {
+        on,
+        join_type: "inner".into(),
+        filters: Default::default(),
+    };
+    for op in [&hash as &dyn Operator, &merge] {
+        let out = op.eval_block(&inputs, &budget).unwrap();
+        let out: Vec<&str> = out.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(out, names, "{}", op.name());
+    }
+
+    let scan = |source: &str, schema: &Schema| L::Scan {
+        source: source.into(),
+        schema: schema.clone(),
+    };
+    let plan = L::Sink {
+        input: Box::new(L::Join {
+            left: Box::new(scan("l.csv", &left)),
+            right: Box::new(scan("r.csv", &right)),
+            on: vec![("id".into(), "id".into())],
+            join_type: JoinType::Inner,
+        }),
+        destination: "out.csv".into(),
+        format: "csv".into(),
+    };
+    let lineage: Vec<String> = column_lineage(&plan)[0]
+        .columns
+        .iter()
+        .map(|c| c.column.clone())
+        .collect();
+    assert_eq!(lineage, names);
+}
@@ -0,0 +1,161 @@
+//! Seeded expression functions: rand(), hash(col), sample_hash(col, fraction)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::expr::{EvalContext, Expr, Func};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn ids(range: std::ops::Range<i64>) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: range.map(Scalar::I64).collect(),
+        }],
+    }
+}
+
+fn eval(expr: &str, batch: &RowBatch, ctx: EvalContext) -> Vec<Scalar> {
+    let expr = Expr::parse(expr).unwrap();
+    (0..batch.num_rows())
+        .map(|row| expr.evaluate_with(batch, row, &ctx).unwrap())
+        .collect()
+}
+
+fn seed(seed: u64) -> EvalContext {
+    EvalContext {
+        seed,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_parse_function_calls() {
+    assert!(matches!(
+        Expr::parse("rand() < 0.5").unwrap(),
+        Expr::BinaryOp { left, .. } if matches!(*left, Expr::Call { func: Func::Rand, .. })
+    ));
+    assert_eq!(
+        Expr::parse("sample_hash(id, 0.25)").unwrap(),
+        Expr::Call {
+            func: Func::SampleHash,
+            args: vec![Expr::Column("id".into()), Expr::Literal(Scalar::F32(0.25))],
+        }
+    );
+    assert!(Expr::parse("nope(id)").is_err());
+    assert!(Expr::parse("hash()").is_err());
+}
+
+#[test]
+fn test_hash_depends_only_on_value_and_seed() {
+    let batch = ids(0..100);
+    assert_eq!(
+        eval("hash(id)", &batch, seed(1)),
+        eval("hash(id)", &batch, seed(1))
+    );
+    assert_ne!(
+        eval("hash(id)", &batch, seed(1)),
+        eval("hash(id)", &batch, seed(2))
+    );
+
+    // Same value, different row: same hash. Null stays null.
+    let dup = RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: vec![Scalar::I64(7), Scalar::Null, Scalar::I32(7)],
+        }],
+    };
+    let out = eval("hash(id)", &dup, seed(1));
+    assert_eq!(out[0], out[2]);
+    assert_eq!(out[1], Scalar::Null);
+}
+
+#[test]
+fn test_sample_hash_is_stable_and_nested() {
+    let batch = ids(0..10_000);
+    let picked = |fraction: &str| -> Vec<bool> {
+        eval(&format!("sample_hash(id, {})", fraction), &batch, seed(3))
+            .into_iter()
+            .map(|v| v == Scalar::Bool(true))
+            .collect()
+    };
+    let tenth = picked("0.1");
+    let fifth = picked("0.2");
+    let n = tenth.iter().filter(|b| **b).count();
+    assert!((800..1200).contains(&n), "sampled {} of 10000", n);
+    // A smaller sample is a subset of a larger one.
+    assert!(tenth.iter().zip(&fifth).all(|(a, b)| !a || *b));
+    assert!(picked("0").iter().all(|b| !b));
+    assert!(picked("1").iter().all(|b| *b));
+}
+
+#[test]
+fn test_rand_follows_stream_position_not_batches() {
+    let whole = eval("rand()", &ids(0..6), seed(9));
+    let mut split = eval("rand()", &ids(0..2), seed(9));
+    split.extend(eval(
+        "rand()",
+        &ids(2..6),
+        EvalContext {
+            seed: 9,
+            row_base: 2,
+            ..Default::default()
+        },
+    ));
+    assert_eq!(whole, split);
+    assert!(whole
+        .iter()
+        .all(|v| matches!(v, Scalar::F64(f) if (0.0..1.0).contains(f))));
+    assert_ne!(whole, eval("rand()", &ids(0..6), seed(10)));
+}
+
+#[test]
+fn test_runs_with_same_seed_reproduce() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let csv: String = std::iter::once("id\n".to_string())
+        .chain((0..200).map(|i| format!("{}\n", i)))
+        .collect();
+    fs::write(&input, csv).unwrap();
+
+    let run = |seed: u64, out: &str| {
+        let lp = L::Sink {
+            input: Box::new(L::Filter {
+                input: Box::new(L::Scan {
+                    source: input.clone(),
+                    schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+                }),
+                expr: "rand() < 0.5".into(),
+            }),
+            destination: format!("{}/{}", dir, out),
+            format: "csv".into(),
+        };
+        let program = lower_to_physical(&lp);
+        let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+        assert_eq!(manifest.seed, seed);
+        fs::read_to_string(format!("{}/{}", dir, out)).unwrap()
+    };
+
+    let first = run(42, "a.csv");
+    assert_eq!(first, run(42, "b.csv"));
+    assert_ne!(first, run(43, "c.csv"));
+    let kept = first.lines().count() - 1;
+    assert!((60..140).contains(&kept), "kept {} of 200", kept);
+
+    let _ = fs::remove_dir_all(&dir);
+}
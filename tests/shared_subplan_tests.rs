@@ -0,0 +1,170 @@
+//! Shared subplans: identical subtrees lower to one set of operators
+//! whose TE blocks feed every reader
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, rules};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint};
+use test_data_gen::create_temp_spill_dir;
+
+fn scan(source: &str) -> L {
+    L::Scan {
+        source: source.into(),
+        schema: Schema::new(vec![
+            Field::new("k", DataType::Int64, false),
+            Field::new("v", DataType::Int64, false),
+        ]),
+    }
+}
+
+fn sink(input: L, destination: &str) -> L {
+    L::Sink {
+        input: Box::new(input),
+        destination: destination.into(),
+        format: "csv".into(),
+    }
+}
+
+/// Each row of `scan` joined to its group's row count.
+fn with_counts(source: &str, destination: &str) -> L {
+    let counts = L::Aggregate {
+        input: Box::new(scan(source)),
+        group_by: vec!["k".into()],
+        aggs: vec![Aggregation::Count],
+    };
+    sink(
+        L::Join {
+            left: Box::new(scan(source)),
+            right: Box::new(counts),
+            on: vec![("k".into(), "k".into())],
+            join_type: JoinType::Inner,
+        },
+        destination,
+    )
+}
+
+fn sorted_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().collect();
+    lines[1..].sort_unstable();
+    lines
+}
+
+#[test]
+fn test_identical_subtrees_lower_to_one_operator() {
+    let plan = with_counts("in.csv", "out.csv");
+    for lp in [plan.clone(), rules::optimize(plan)] {
+        let program = lower_to_physical(&lp);
+        let sources: Vec<_> = program
+            .bindings
+            .iter()
+            .filter(|(_, b)| b.key == "source")
+            .map(|(op, _)| *op)
+            .collect();
+        assert_eq!(sources.len(), 1, "{:?}", lp);
+        assert_eq!(program.plan.graph().shared(), sources);
+        assert_eq!(program.plan.graph().consumers[&sources[0]].len(), 2);
+
+        // The source's blocks are planned once and read by both consumers.
+        let te = plan_te_with_block_size(
+            &program.plan,
+            &estimate_work(&lp, None),
+            BlockSizeHint {
+                rows_per_block: 1000,
+            },
+            &program.join_keys(),
+        )
+        .unwrap();
+        let source_blocks: Vec<_> = te
+            .order
+            .iter()
+            .filter(|b| b.op == sources[0])
+            .map(|b| b.id)
+            .collect();
+        let readers = te
+            .order
+            .iter()
+            .filter(|b| b.deps.iter().any(|d| source_blocks.contains(d)))
+            .map(|b| b.op)
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(readers.len(), 2);
+    }
+
+    // Scans of different files, or read with different filters, stay apart.
+    let apart = sink(
+        L::Union {
+            inputs: vec![
+                scan("a.csv"),
+                scan("b.csv"),
+                L::Filter {
+                    input: Box::new(scan("a.csv")),
+                    expr: "v > 1".into(),
+                },
+            ],
+        },
+        "out.csv",
+    );
+    let program = lower_to_physical(&apart);
+    assert_eq!(
+        program
+            .bindings
+            .values()
+            .filter(|b| b.key == "source")
+            .count(),
+        2
+    );
+    assert_eq!(program.plan.graph().shared().len(), 1);
+}
+
+#[test]
+fn test_shared_scan_runs_once_and_feeds_both_readers() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let rows: String = (0..3000)
+        .map(|i| format!("{},{}\n", (i * 7919) % 40, i))
+        .collect();
+    fs::write(format!("{}/in.csv", dir), format!("k,v\n{}", rows)).unwrap();
+    let destination = format!("{}/out.csv", dir);
+
+    let lp = rules::optimize(with_counts(&format!("{}/in.csv", dir), &destination));
+    let program = lower_to_physical(&lp);
+    // Small blocks make the self-join co-partition several blocks per side.
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &estimate_work(&lp, None),
+        BlockSizeHint {
+            rows_per_block: 500,
+        },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+    let source_runs = manifest
+        .block_costs
+        .iter()
+        .filter(|c| c.op == "source")
+        .count();
+    let source_blocks = te
+        .order
+        .iter()
+        .filter(|b| program.bindings[&b.op].key == "source")
+        .count();
+    assert_eq!(source_runs, source_blocks);
+
+    let out = fs::read_to_string(&destination).unwrap();
+    let lines = sorted_lines(&out);
+    assert_eq!(lines.len(), 3001);
+    // 3000 rows over 40 keys: 75 rows per key.
+    assert!(lines[1..].iter().all(|l| l.ends_with(",75")), "{}", out);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,171 @@
+//! Sink reorder buffer: blocks are written in TE order whatever order they finish in
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::budget::MemoryBudget;
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::reorder::ReorderBuffer;
+use emsqrt_exec::Engine;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::{Codec, SpillManager};
+use emsqrt_planner::{estimate_work, lower_to_physical, WorkHint};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(seq: i64) -> RowBatch {
+    RowBatch {
+        columns: vec![Column {
+            name: "seq".into(),
+            values: vec![Scalar::I64(seq); 4],
+        }],
+    }
+}
+
+fn buffer(dir: &str) -> (ReorderBuffer, Arc<Mutex<SpillManager>>) {
+    let spill = Arc::new(Mutex::new(SpillManager::new(
+        Box::new(FsStorage::new()),
+        Codec::None,
+        format!("{}/spill", dir),
+    )));
+    (ReorderBuffer::new(spill.clone()), spill)
+}
+
+/// Push `order` and return the sequence numbers in the order they were written.
+fn push_all(buffer: &mut ReorderBuffer, budget: &MemoryBudgetImpl, order: &[u64]) -> Vec<i64> {
+    let mut written = Vec::new();
+    for &seq in order {
+        buffer
+            .push(seq, batch(seq as i64), budget, |b| {
+                match b.columns[0].values[0] {
+                    Scalar::I64(v) => written.push(v),
+                    _ => unreachable!(),
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+    written
+}
+
+#[test]
+fn test_out_of_order_blocks_are_written_in_sequence() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let (mut buffer, _) = buffer(&dir);
+
+    let written = push_all(&mut buffer, &budget, &[2, 1, 4]);
+    assert!(written.is_empty());
+    assert_eq!(buffer.pending(), 3);
+    assert!(buffer.finish().is_err());
+
+    let written = push_all(&mut buffer, &budget, &[0, 3]);
+    assert_eq!(written, vec![0, 1, 2, 3, 4]);
+    assert_eq!(buffer.next_seq(), 5);
+    assert_eq!(budget.used_bytes(), 0);
+    assert_eq!(buffer.spilled(), 0);
+    buffer.finish().unwrap();
+
+    assert!(buffer.push(3, batch(3), &budget, |_| Ok(())).is_err());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_waiting_blocks_spill_when_over_budget() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let (mut buffer, spill) = buffer(&dir);
+
+    // Under pressure the waiting blocks go to disk...
+    let pressure = budget.try_acquire((1 << 20) - 40, "test").unwrap();
+    let written = push_all(&mut buffer, &budget, &[3, 1]);
+    assert!(written.is_empty());
+    assert_eq!(buffer.spilled(), 1);
+    let written = push_all(&mut buffer, &budget, &[2]);
+    assert!(written.is_empty());
+    assert_eq!(buffer.spilled(), 2);
+    assert_eq!(spill.lock().unwrap().list_segments().len(), 2);
+
+    // ...and are read back, in order, once their turn comes.
+    drop(pressure);
+    let written = push_all(&mut buffer, &budget, &[0]);
+    assert_eq!(written, vec![0, 1, 2, 3]);
+    assert!(spill.lock().unwrap().list_segments().is_empty());
+    buffer.finish().unwrap();
+
+    // Spilled batches left behind by a failed run are removed on drop.
+    let (mut buffer, spill) = self::buffer(&dir);
+    let pressure = budget.try_acquire((1 << 20) - 40, "test").unwrap();
+    push_all(&mut buffer, &budget, &[1, 2, 3]);
+    drop(pressure);
+    assert_eq!(spill.lock().unwrap().list_segments().len(), 2);
+    drop(buffer);
+    assert!(spill.lock().unwrap().list_segments().is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_multi_block_sinks_keep_source_order() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let csv: String = std::iter::once("id\n".to_string())
+        .chain((0..5000).map(|i| format!("{}\n", i)))
+        .collect();
+    fs::write(&input, &csv).unwrap();
+
+    for format in ["csv", "jsonl"] {
+        let output = format!("{}/out.{}", dir, format);
+        let lp = L::Sink {
+            input: Box::new(L::Scan {
+                source: input.clone(),
+                schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+            }),
+            destination: output.clone(),
+            format: format.into(),
+        };
+        let program = lower_to_physical(&lp);
+        // 5000 rows of ~8 bytes under a 32KB cap: five source and sink blocks.
+        let hint = WorkHint {
+            source_rows: vec![(input.clone(), 5000)],
+            source_bytes: vec![(input.clone(), 40_000)],
+            ..Default::default()
+        };
+        let te = plan_te(&program.plan, &estimate_work(&lp, Some(&hint)), 32_000).unwrap();
+        let sink_blocks = te
+            .order
+            .iter()
+            .filter(|b| program.bindings[&b.op].key == "sink")
+            .count();
+        assert_eq!(sink_blocks, 5);
+        let config = EngineConfig {
+            spill_dir: format!("{}/spill", dir),
+            source_batch: emsqrt_core::config::SourceBatchConfig {
+                initial_rows: 1000,
+                min_rows: 1000,
+                max_rows: 1000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        Engine::new(config).unwrap().run(&program, &te).unwrap();
+
+        let expected: String = match format {
+            "csv" => csv.clone(),
+            _ => (0..5000).map(|i| format!("{{\"id\":{}}}\n", i)).collect(),
+        };
+        assert_eq!(fs::read_to_string(&output).unwrap(), expected);
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
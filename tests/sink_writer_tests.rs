@@ -0,0 +1,360 @@
+//! Sink format writers: `open_writer` dispatch and append/overwrite semantics
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Read;
+
+use emsqrt_core::config::{CsvCompression, CsvQuoting, CsvSinkConfig, EngineConfig};
+use emsqrt_core::dag::LogicalPlan as L;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::writers::{infer_schema, open_writer, WriteMode, WriterOptions};
+use emsqrt_planner::{estimate_work, lower_to_physical, WorkHint};
+use emsqrt_te::{plan_te, plan_te_with_block_size, BlockSizeHint};
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(ids: &[i64]) -> RowBatch {
+    RowBatch {
+        columns: vec![
+            Column {
+                name: "id".into(),
+                values: ids.iter().map(|&v| Scalar::I64(v)).collect(),
+            },
+            Column {
+                name: "name".into(),
+                values: ids.iter().map(|v| Scalar::Str(format!("n{}", v))).collect(),
+            },
+        ],
+    }
+}
+
+/// Write `batches` through one writer and finish it.
+fn write(format: &str, path: &str, mode: WriteMode, batches: &[RowBatch]) {
+    write_with(format, path, mode, batches, &WriterOptions::default());
+}
+
+fn write_with(
+    format: &str,
+    path: &str,
+    mode: WriteMode,
+    batches: &[RowBatch],
+    opts: &WriterOptions,
+) {
+    let mut writer = open_writer(format, path, mode, &batches[0], opts).unwrap();
+    for b in batches {
+        writer.write_batch(b).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn test_csv_overwrite_and_append() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/out.csv", dir);
+    fs::write(&path, "stale\n").unwrap();
+
+    write(
+        "csv",
+        &path,
+        WriteMode::Overwrite,
+        &[batch(&[1]), batch(&[2])],
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id,name\n1,n1\n2,n2\n");
+
+    // Appending continues the file without a second header.
+    write("csv", &path, WriteMode::Append, &[batch(&[3])]);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "id,name\n1,n1\n2,n2\n3,n3\n"
+    );
+
+    // Appending to a missing file starts it, header included.
+    let fresh = format!("{}/fresh.csv", dir);
+    write("csv", &fresh, WriteMode::Append, &[batch(&[4])]);
+    assert_eq!(fs::read_to_string(&fresh).unwrap(), "id,name\n4,n4\n");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Every gzip member of `path`, decompressed.
+fn gunzip(path: &str) -> String {
+    let mut out = String::new();
+    flate2::read::MultiGzDecoder::new(fs::File::open(path).unwrap())
+        .read_to_string(&mut out)
+        .unwrap();
+    out
+}
+
+#[test]
+fn test_csv_delimiter_and_quoting() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/out.csv", dir);
+    let mut with_semicolon = batch(&[1, 2]);
+    with_semicolon.columns[1].values[1] = Scalar::Str("a;b".into());
+
+    let opts = |delimiter, quoting| WriterOptions {
+        csv: CsvSinkConfig {
+            delimiter,
+            quoting,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let batches = [with_semicolon];
+    let cases = [
+        (';', CsvQuoting::Necessary, "id;name\n1;n1\n2;\"a;b\"\n"),
+        (
+            '|',
+            CsvQuoting::Always,
+            "\"id\"|\"name\"\n\"1\"|\"n1\"\n\"2\"|\"a;b\"\n",
+        ),
+        (
+            ',',
+            CsvQuoting::NonNumeric,
+            "\"id\",\"name\"\n1,\"n1\"\n2,\"a;b\"\n",
+        ),
+        (';', CsvQuoting::Never, "id;name\n1;n1\n2;a;b\n"),
+    ];
+    for (delimiter, quoting, expected) in cases {
+        write_with(
+            "csv",
+            &path,
+            WriteMode::Overwrite,
+            &batches,
+            &opts(delimiter, quoting),
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            expected,
+            "{:?}",
+            quoting
+        );
+    }
+
+    let err = open_writer(
+        "csv",
+        &path,
+        WriteMode::Overwrite,
+        &batches[0],
+        &opts('é', CsvQuoting::Necessary),
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("not a single ASCII"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_csv_gzip_by_extension_or_config() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    // A `.gz` destination is gzipped unless compression says otherwise.
+    let gz = format!("{}/out.csv.gz", dir);
+    write(
+        "csv",
+        &gz,
+        WriteMode::Overwrite,
+        &[batch(&[1]), batch(&[2])],
+    );
+    assert_eq!(&fs::read(&gz).unwrap()[..2], &[0x1f, 0x8b]);
+    assert_eq!(gunzip(&gz), "id,name\n1,n1\n2,n2\n");
+
+    // Appending adds a gzip member and no second header.
+    write("csv", &gz, WriteMode::Append, &[batch(&[3])]);
+    assert_eq!(gunzip(&gz), "id,name\n1,n1\n2,n2\n3,n3\n");
+
+    let opts = |compression| WriterOptions {
+        csv: CsvSinkConfig {
+            compression: Some(compression),
+            compression_level: Some(9),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let plain = format!("{}/plain.csv.gz", dir);
+    write_with(
+        "csv",
+        &plain,
+        WriteMode::Overwrite,
+        &[batch(&[1])],
+        &opts(CsvCompression::None),
+    );
+    assert_eq!(fs::read_to_string(&plain).unwrap(), "id,name\n1,n1\n");
+    let zipped = format!("{}/zipped.csv", dir);
+    write_with(
+        "csv",
+        &zipped,
+        WriteMode::Overwrite,
+        &[batch(&[1])],
+        &opts(CsvCompression::Gzip),
+    );
+    assert_eq!(gunzip(&zipped), "id,name\n1,n1\n");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_engine_streams_blocks_into_one_configured_csv_file() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let rows: String = (0..2000).map(|i| format!("{},n{}\n", i, i)).collect();
+    fs::write(format!("{}/in.csv", dir), format!("id,name\n{}", rows)).unwrap();
+    let out = format!("{}/out.csv.gz", dir);
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: format!("{}/in.csv", dir),
+            schema: Schema::new(vec![
+                Field::new("id", DataType::Int64, false),
+                Field::new("name", DataType::Utf8, false),
+            ]),
+        }),
+        destination: out.clone(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let hint = WorkHint {
+        source_rows: vec![(format!("{}/in.csv", dir), 2000)],
+        ..Default::default()
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &estimate_work(&lp, Some(&hint)),
+        BlockSizeHint {
+            rows_per_block: 500,
+        },
+        &program.join_keys(),
+    )
+    .unwrap();
+    assert!(te.order.len() > 4, "{} blocks", te.order.len());
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        csv: CsvSinkConfig {
+            delimiter: '\t',
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+
+    let expected: String = (0..2000).map(|i| format!("{}\tn{}\n", i, i)).collect();
+    assert_eq!(gunzip(&out), format!("id\tname\n{}", expected));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_jsonl_overwrite_and_append() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/out.jsonl", dir);
+
+    write("jsonl", &path, WriteMode::Overwrite, &[batch(&[1])]);
+    write("jsonl", &path, WriteMode::Append, &[batch(&[2])]);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "{\"id\":1,\"name\":\"n1\"}\n{\"id\":2,\"name\":\"n2\"}\n"
+    );
+    write("jsonl", &path, WriteMode::Overwrite, &[batch(&[3])]);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "{\"id\":3,\"name\":\"n3\"}\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_unknown_format_is_rejected() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/out.xml", dir);
+
+    let err = open_writer(
+        "xml",
+        &path,
+        WriteMode::Overwrite,
+        &batch(&[1]),
+        &WriterOptions::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("unsupported sink format: xml"));
+    assert!(!std::path::Path::new(&path).exists());
+
+    // The engine surfaces the same error from the sink block.
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: format!("{}/in.csv", dir),
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        destination: path,
+        format: "xml".into(),
+    };
+    fs::write(format!("{}/in.csv", dir), "id\n1\n").unwrap();
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 20).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let err = Engine::new(config).unwrap().run(&program, &te).unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported sink format"),
+        "{}",
+        err
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_infer_schema_types_from_first_non_null() {
+    let mut b = batch(&[1, 2]);
+    b.columns[1].values[0] = Scalar::Null;
+    b.columns.push(Column {
+        name: "empty".into(),
+        values: vec![Scalar::Null, Scalar::Null],
+    });
+    let schema = infer_schema(&b).unwrap();
+    let types: Vec<_> = schema.fields.iter().map(|f| f.data_type.clone()).collect();
+    assert_eq!(types, [DataType::Int64, DataType::Utf8, DataType::Utf8]);
+    assert!(schema.fields.iter().all(|f| f.nullable));
+    assert!(infer_schema(&RowBatch { columns: vec![] }).is_err());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_arrow_ipc_file_has_footer_and_refuses_append() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let path = format!("{}/out.arrow", dir);
+
+    write(
+        "arrow",
+        &path,
+        WriteMode::Overwrite,
+        &[batch(&[1]), batch(&[2])],
+    );
+    let bytes = fs::read(&path).unwrap();
+    assert!(bytes.starts_with(b"ARROW1"));
+    assert!(bytes.ends_with(b"ARROW1"));
+
+    let err = open_writer(
+        "arrow",
+        &path,
+        WriteMode::Append,
+        &batch(&[3]),
+        &WriterOptions::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(err.to_string().contains("cannot append"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
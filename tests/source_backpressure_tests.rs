@@ -0,0 +1,158 @@
+//! Source read sizing driven by budget pressure
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::{EngineConfig, SourceBatchConfig};
+use emsqrt_exec::backpressure::ReadSizer;
+use emsqrt_exec::Engine;
+use emsqrt_planner::physical::OperatorBinding;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+const CAP: usize = 1000;
+
+fn sizer() -> ReadSizer {
+    ReadSizer::new(SourceBatchConfig {
+        initial_rows: 8,
+        min_rows: 2,
+        max_rows: 32,
+        shrink_above: 0.8,
+        grow_below: 0.5,
+    })
+}
+
+#[test]
+fn test_first_read_uses_initial_size() {
+    let mut s = sizer();
+    assert_eq!(s.next_rows(CAP, CAP), 8);
+}
+
+#[test]
+fn test_sizer_grows_with_headroom_and_shrinks_under_pressure() {
+    let mut s = sizer();
+    s.next_rows(0, CAP);
+    assert_eq!(s.next_rows(0, CAP), 16);
+    assert_eq!(s.next_rows(100, CAP), 32);
+    assert_eq!(s.next_rows(100, CAP), 32, "clamped to max_rows");
+
+    assert_eq!(s.next_rows(900, CAP), 16);
+    assert_eq!(s.next_rows(950, CAP), 8);
+    assert_eq!(s.next_rows(990, CAP), 4);
+    assert_eq!(s.next_rows(990, CAP), 2);
+    assert_eq!(s.next_rows(990, CAP), 2, "clamped to min_rows");
+}
+
+#[test]
+fn test_sizer_holds_size_inside_hysteresis_band() {
+    let mut s = sizer();
+    s.next_rows(0, CAP);
+    for used in [500, 650, 800, 700, 500] {
+        assert_eq!(s.next_rows(used, CAP), 8);
+    }
+}
+
+#[test]
+fn test_source_reads_configured_rows_per_block() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/rows.csv", dir);
+    let mut csv = String::from("id\n");
+    for i in 0..20 {
+        csv.push_str(&format!("{}\n", i));
+    }
+    fs::write(&input, csv).unwrap();
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        source_batch: SourceBatchConfig {
+            initial_rows: 5,
+            min_rows: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let engine = Engine::new(config).unwrap();
+    let binding = OperatorBinding {
+        key: "source".to_string(),
+        config: serde_json::json!({
+            "source": input,
+            "schema": {"fields": [{"name": "id", "data_type": "Int64", "nullable": false}]},
+        }),
+    };
+    let (batch, _) = engine.eval_binding(&binding, &[]).unwrap();
+    assert_eq!(batch.num_rows(), 5);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_shrinking_reads_still_return_every_row() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/rows.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    let rows: String = (0..25_000).map(|i| format!("{}\n", i)).collect();
+    fs::write(&input, format!("id\n{}", rows)).unwrap();
+
+    // Utilization is always over `shrink_above`, so every read past the
+    // first halves, down to `min_rows`.
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        source_batch: SourceBatchConfig {
+            initial_rows: 4000,
+            min_rows: 100,
+            max_rows: 4000,
+            shrink_above: -1.0,
+            grow_below: -2.0,
+        },
+        ..Default::default()
+    };
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    );
+    let parsed = parse_yaml_pipeline(&yaml).unwrap();
+    let program = lower_to_physical(&parsed.plan);
+    let te = plan_te(&program.plan, &estimate_work(&parsed.plan, None), 1 << 26).unwrap();
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        format!("id\n{}", rows)
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_source_batch_parses_from_pipeline_config() {
+    let yaml = r#"
+config:
+  source_batch:
+    initial_rows: 2000
+    shrink_above: 0.9
+steps:
+  - op: scan
+    source: "data.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let parsed = parse_yaml_pipeline(yaml).unwrap();
+    let source_batch = parsed.config.source_batch.unwrap();
+    assert_eq!(source_batch.initial_rows, 2000);
+    assert_eq!(source_batch.shrink_above, 0.9);
+    assert_eq!(source_batch.max_rows, SourceBatchConfig::default().max_rows);
+}
@@ -0,0 +1,209 @@
+//! Sources read exactly the row range TE planned for each block
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::Engine;
+use emsqrt_planner::{estimate_work, lower_to_physical, PhysicalProgram, WorkHint};
+use emsqrt_te::plan_te;
+use emsqrt_te::tree_eval::TePlan;
+use test_data_gen::create_temp_spill_dir;
+
+/// Plan `input → sink` as five 1000-row source blocks.
+fn plan(input: &str, output: &str) -> (PhysicalProgram, TePlan) {
+    let lp = L::Sink {
+        input: Box::new(L::Scan {
+            source: input.to_string(),
+            schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+        }),
+        destination: output.to_string(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    let hint = WorkHint {
+        source_rows: vec![(input.to_string(), 5000)],
+        source_bytes: vec![(input.to_string(), 40_000)],
+        ..Default::default()
+    };
+    let te = plan_te(&program.plan, &estimate_work(&lp, Some(&hint)), 32_000).unwrap();
+    (program, te)
+}
+
+fn source_rows(program: &PhysicalProgram, te: &TePlan, dir: &str) -> Vec<u64> {
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(program, te).unwrap();
+    manifest
+        .block_costs
+        .iter()
+        .filter(|c| program.bindings[&emsqrt_core::id::OpId::new(c.op_id)].key == "source")
+        .map(|c| c.rows_out)
+        .collect()
+}
+
+fn write_ids(path: &str, rows: usize, jsonl: bool) -> String {
+    let body: String = if jsonl {
+        (0..rows).map(|i| format!("{{\"id\":{}}}\n", i)).collect()
+    } else {
+        std::iter::once("id\n".to_string())
+            .chain((0..rows).map(|i| format!("{}\n", i)))
+            .collect()
+    };
+    fs::write(path, body).unwrap();
+    (0..rows).map(|i| format!("{}\n", i)).collect()
+}
+
+#[test]
+fn test_blocks_follow_planned_ranges() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    for (name, jsonl) in [("in.csv", false), ("in.jsonl", true)] {
+        let input = format!("{}/{}", dir, name);
+        let output = format!("{}/out.csv", dir);
+        // The source's default first read (10k rows) would swallow every block.
+        let ids = write_ids(&input, 5000, jsonl);
+        let (program, te) = plan(&input, &output);
+        assert_eq!(source_rows(&program, &te, &dir), vec![1000; 5]);
+        assert_eq!(fs::read_to_string(&output).unwrap(), format!("id\n{}", ids));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_last_block_takes_rows_past_the_estimate() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    let ids = write_ids(&input, 5600, false);
+
+    let (program, te) = plan(&input, &output);
+    assert_eq!(
+        source_rows(&program, &te, &dir),
+        vec![1000, 1000, 1000, 1000, 1600]
+    );
+    assert_eq!(fs::read_to_string(&output).unwrap(), format!("id\n{}", ids));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_last_block_reads_to_the_end_of_an_underestimated_input() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    let ids = write_ids(&input, 30_000, false);
+
+    // Without stats the plan expects one row, so one block reads it all,
+    // several sizer chunks past the estimate.
+    let scan = L::Scan {
+        source: input.clone(),
+        schema: Schema::new(vec![Field::new("id", DataType::Int64, false)]),
+    };
+    let sink = |input: L| L::Sink {
+        input: Box::new(input),
+        destination: output.clone(),
+        format: "csv".into(),
+    };
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let run = |lp: &L| {
+        let program = lower_to_physical(lp);
+        let te = plan_te(&program.plan, &estimate_work(lp, None), 1 << 26).unwrap();
+        Engine::new(config.clone())
+            .unwrap()
+            .run(&program, &te)
+            .unwrap()
+    };
+
+    run(&sink(scan.clone()));
+    assert_eq!(fs::read_to_string(&output).unwrap(), format!("id\n{}", ids));
+
+    let manifest = run(&sink(L::Aggregate {
+        input: Box::new(scan),
+        group_by: vec!["id".into()],
+        aggs: vec![Aggregation::Count],
+    }));
+    assert!(manifest.block_costs.iter().any(|c| c.rows_in == 30_000));
+    assert_eq!(fs::read_to_string(&output).unwrap().lines().count(), 30_001);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_ranges_hold_when_blocks_run_out_of_order() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    for (name, jsonl) in [("in.csv", false), ("in.jsonl", true)] {
+        let input = format!("{}/{}", dir, name);
+        let output = format!("{}/out.csv", dir);
+        let ids = write_ids(&input, 5000, jsonl);
+        let (program, mut te) = plan(&input, &output);
+
+        // Run every source block first, last range first; sinks keep their order.
+        let (mut sources, sinks): (Vec<_>, Vec<_>) = te
+            .order
+            .drain(..)
+            .partition(|b| program.bindings[&b.op].key == "source");
+        sources.reverse();
+        te.order = sources.into_iter().chain(sinks).collect();
+
+        assert_eq!(source_rows(&program, &te, &dir), vec![1000; 5]);
+        assert_eq!(fs::read_to_string(&output).unwrap(), format!("id\n{}", ids));
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn source_metric(program: &PhysicalProgram, te: &TePlan, dir: &str, name: &str) -> u64 {
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(program, te).unwrap();
+    manifest
+        .block_costs
+        .iter()
+        .filter_map(|c| c.metrics.get(name))
+        .sum()
+}
+
+#[test]
+fn test_csv_blocks_continue_from_the_open_reader() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    let output = format!("{}/out.csv", dir);
+    write_ids(&input, 5000, false);
+    let (program, mut te) = plan(&input, &output);
+
+    // In TE order each block picks up where the previous one stopped.
+    assert_eq!(source_metric(&program, &te, &dir, "rows_skipped"), 0);
+    assert_eq!(source_metric(&program, &te, &dir, "rows_discarded"), 0);
+
+    // Out of order, the reader restarts and skips to each block's range.
+    let (mut sources, sinks): (Vec<_>, Vec<_>) = te
+        .order
+        .drain(..)
+        .partition(|b| program.bindings[&b.op].key == "source");
+    sources.reverse();
+    te.order = sources.into_iter().chain(sinks).collect();
+    assert_eq!(
+        source_metric(&program, &te, &dir, "rows_skipped"),
+        4000 + 3000 + 2000 + 1000
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
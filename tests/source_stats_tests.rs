@@ -0,0 +1,206 @@
+//! Source column statistics collected during a run (`EngineConfig::collect_stats`)
+//! and fed back into planning (`attach_source_stats`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{LogicalPlan, PhysicalPlan};
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_core::stats::{ColumnStats, HyperLogLog, SchemaStats, SourceStats};
+use emsqrt_core::types::Scalar;
+use emsqrt_exec::{read_stats_file, Engine};
+use emsqrt_planner::{attach_source_stats, estimate_work, lower_to_physical, parse_yaml_pipeline};
+use test_data_gen::create_temp_spill_dir;
+
+fn pipeline(input: &str, output: &str) -> String {
+    format!(
+        r#"
+hints:
+  rows_per_block: 2
+  sources:
+    "{input}": {{ rows: 5 }}
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "score", type: "Float64", nullable: true }}
+  - op: filter
+    expr: "id > 2"
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    )
+}
+
+fn run(dir: &str, collect_stats: bool) -> RunManifest {
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id,score\n4,1.5\n1,\n5,-2.0\n3,\n2,9.25\n").unwrap();
+    let parsed = parse_yaml_pipeline(&pipeline(&input, &format!("{}/out.csv", dir))).unwrap();
+    let mut program = lower_to_physical(&parsed.plan);
+    parsed.hints.apply(&mut program);
+    let work = estimate_work(&parsed.plan, parsed.hints.work_hint().as_ref());
+    let te = parsed.hints.plan_te(&program, &work, 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        collect_stats,
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap()
+}
+
+#[test]
+fn test_stats_cover_every_source_block() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = run(&dir, true);
+
+    let stats = &manifest.source_stats[&format!("{}/in.csv", dir)];
+    // Three blocks of at most two rows, merged; the filter does not affect them.
+    assert_eq!(stats.row_count(), Some(5));
+    let id = stats.get("id").unwrap();
+    assert_eq!(
+        (id.min.clone(), id.max.clone()),
+        (Some(Scalar::I64(1)), Some(Scalar::I64(5)))
+    );
+    assert_eq!(id.null_count, 0);
+    let score = stats.get("score").unwrap();
+    assert_eq!(score.null_count, 2);
+    assert_eq!(score.min, Some(Scalar::F64(-2.0)));
+    assert_eq!(score.max, Some(Scalar::F64(9.25)));
+    // Distinct counts from the per-block sketches, merged.
+    assert_eq!(id.distinct_count, Some(5));
+    assert_eq!(score.distinct_count, Some(3));
+
+    // Stats survive the manifest's JSON form.
+    let back: RunManifest =
+        serde_json::from_str(&serde_json::to_string(&manifest).unwrap()).unwrap();
+    assert_eq!(back.source_stats, manifest.source_stats);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_stats_are_off_by_default() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = run(&dir, false);
+    assert!(manifest.source_stats.is_empty());
+    assert!(!serde_json::to_string(&manifest)
+        .unwrap()
+        .contains("source_stats"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_attached_stats_drive_estimates_and_reach_the_physical_plan() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let collected: SourceStats = run(&dir, true).source_stats;
+
+    // Without hints the source row count is unknown; stats supply it.
+    let yaml = pipeline(&format!("{}/in.csv", dir), "out.csv");
+    let mut plan = parse_yaml_pipeline(&yaml).unwrap().plan;
+    assert_eq!(estimate_work(&plan, None).total_rows, 1);
+    assert_eq!(attach_source_stats(&mut plan, &collected), 1);
+    // id in [1, 5]: "id > 2" keeps three quarters of 5 rows.
+    let rows = estimate_work(&plan, None).total_rows;
+    assert_eq!(rows, 3);
+
+    let program = lower_to_physical(&plan);
+    let mut source = &program.plan;
+    while let PhysicalPlan::Sink { input, .. } | PhysicalPlan::Unary { input, .. } = source {
+        source = input;
+    }
+    let PhysicalPlan::Source { schema, .. } = source else {
+        panic!("expected a source at the leaf");
+    };
+    assert_eq!(
+        schema.stats.as_ref().and_then(SchemaStats::row_count),
+        Some(5)
+    );
+
+    // Declared stats win over collected ones.
+    let mut declared = parse_yaml_pipeline(&yaml).unwrap().plan;
+    fn scan_schema(lp: &mut LogicalPlan) -> &mut emsqrt_core::schema::Schema {
+        match lp {
+            LogicalPlan::Scan { schema, .. } => schema,
+            LogicalPlan::Filter { input, .. } | LogicalPlan::Sink { input, .. } => {
+                scan_schema(input)
+            }
+            _ => unreachable!(),
+        }
+    }
+    scan_schema(&mut declared).stats = Some(SchemaStats::new());
+    assert_eq!(attach_source_stats(&mut declared, &collected), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_distinct_sketches_merge_and_stay_accurate() {
+    let mut whole = HyperLogLog::new();
+    let (mut even, mut odd) = (SchemaStats::new(), SchemaStats::new());
+    for chunk in (0..100_000i64).collect::<Vec<_>>().chunks(1000) {
+        let batch = |values: Vec<Scalar>| emsqrt_core::types::RowBatch {
+            columns: vec![emsqrt_core::types::Column {
+                name: "k".into(),
+                values,
+            }],
+        };
+        // Each value twice, so duplicates across blocks count once.
+        let values: Vec<Scalar> = chunk.iter().map(|&v| Scalar::I64(v / 2)).collect();
+        values.iter().for_each(|v| whole.insert(v));
+        if chunk[0] % 2000 == 0 {
+            &mut even
+        } else {
+            &mut odd
+        }
+        .update_batch(&batch(values));
+    }
+    let merged = even.merge(&odd);
+    let estimate = merged.get("k").and_then(|c| c.distinct_count).unwrap();
+    assert_eq!(estimate, whole.estimate());
+    assert!((47_500..52_500).contains(&estimate), "{}", estimate);
+
+    // Stats without a sketch (e.g. read back from JSON) cannot be combined.
+    let mut plain = ColumnStats::new();
+    plain.distinct_count = Some(7);
+    assert_eq!(plain.merge(&plain).distinct_count, None);
+}
+
+#[test]
+fn test_stats_file_keeps_other_sources_and_is_named_in_the_manifest() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let stats_file = format!("{}/stats.json", dir);
+    let other = format!("{}/other.csv", dir);
+    let mut seeded = SourceStats::new();
+    seeded.insert(other.clone(), SchemaStats::new());
+    fs::write(&stats_file, serde_json::to_string(&seeded).unwrap()).unwrap();
+
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id,score\n1,2.0\n2,\n").unwrap();
+    let plan = parse_yaml_pipeline(&pipeline(&input, &format!("{}/out.csv", dir)))
+        .unwrap()
+        .plan;
+    let program = lower_to_physical(&plan);
+    let te = emsqrt_te::plan_te(&program.plan, &estimate_work(&plan, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        stats_file: Some(stats_file.clone()),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert_eq!(manifest.stats_file.as_deref(), Some(stats_file.as_str()));
+
+    let saved = read_stats_file(std::path::Path::new(&stats_file)).unwrap();
+    assert_eq!(saved.keys().collect::<Vec<_>>(), [&input, &other]);
+    assert_eq!(saved[&input], manifest.source_stats[&input]);
+    assert_eq!(saved[&input].get("id").unwrap().distinct_count, Some(2));
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,172 @@
+//! Templated source lists (`source_template`) expanded at plan time
+
+mod test_data_gen;
+
+use std::fs;
+use std::io::Write;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::LogicalPlan;
+use emsqrt_exec::Engine;
+use emsqrt_planner::{
+    estimate_work, expand_source_template, lower_to_physical, parse_template_params,
+    parse_yaml_pipeline, parse_yaml_pipeline_with_params, rules, TemplateParams,
+};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn params(pairs: &[&str]) -> TemplateParams {
+    let pairs: Vec<String> = pairs.iter().map(|s| s.to_string()).collect();
+    parse_template_params(&pairs).unwrap()
+}
+
+#[test]
+fn test_expand_params_and_padded_ranges() {
+    let files = expand_source_template(
+        "data/{date}/hour={00..02}.csv",
+        &params(&["date=2024-01-01"]),
+    )
+    .unwrap();
+    assert_eq!(
+        files,
+        vec![
+            "data/2024-01-01/hour=00.csv",
+            "data/2024-01-01/hour=01.csv",
+            "data/2024-01-01/hour=02.csv",
+        ]
+    );
+}
+
+#[test]
+fn test_expand_multiple_ranges_is_cartesian() {
+    let files = expand_source_template("p{1..2}/f{8..10}", &TemplateParams::new()).unwrap();
+    assert_eq!(
+        files,
+        vec!["p1/f8", "p1/f9", "p1/f10", "p2/f8", "p2/f9", "p2/f10"]
+    );
+}
+
+#[test]
+fn test_expand_errors() {
+    let err = expand_source_template("data/{date}.csv", &TemplateParams::new()).unwrap_err();
+    assert!(err.contains("date"), "unexpected error: {}", err);
+    assert!(expand_source_template("data/{3..1}.csv", &TemplateParams::new()).is_err());
+    assert!(expand_source_template("data/{1..2.csv", &TemplateParams::new()).is_err());
+    assert!(parse_template_params(&["novalue".to_string()]).is_err());
+}
+
+#[test]
+fn test_yaml_template_becomes_union_of_scans() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source_template: "data/{date}/hour={0..23}.csv"
+    schema:
+      - { name: "id", type: "Int64" }
+  - op: sink
+    destination: "out.csv"
+    format: "csv"
+"#;
+    let parsed = parse_yaml_pipeline_with_params(yaml, &params(&["date=2024-01-01"])).unwrap();
+    let LogicalPlan::Sink { input, .. } = parsed.plan else {
+        panic!("expected sink");
+    };
+    let LogicalPlan::Union { inputs } = *input else {
+        panic!("expected union");
+    };
+    assert_eq!(inputs.len(), 24);
+
+    // Missing parameter surfaces the parameter name
+    let err = parse_yaml_pipeline(yaml).unwrap_err();
+    assert!(err.to_string().contains("date"));
+}
+
+#[test]
+fn test_sink_destination_takes_params_but_not_ranges() {
+    let yaml = |destination: &str| {
+        format!(
+            "steps:\n  - op: scan\n    source: \"in.csv\"\n    schema: []\n  - op: sink\n    destination: \"{}\"\n    format: \"csv\"\n",
+            destination
+        )
+    };
+    let parsed =
+        parse_yaml_pipeline_with_params(&yaml("out/{date}.csv"), &params(&["date=2024-01-01"]))
+            .unwrap();
+    let LogicalPlan::Sink { destination, .. } = parsed.plan else {
+        panic!("expected sink");
+    };
+    assert_eq!(destination, "out/2024-01-01.csv");
+
+    let err = parse_yaml_pipeline(&yaml("out/{date}.csv")).unwrap_err();
+    assert!(err.to_string().contains("sink destination"), "{}", err);
+    let err = parse_yaml_pipeline(&yaml("out/{1..2}.csv")).unwrap_err();
+    assert!(err.to_string().contains("cannot use a range"), "{}", err);
+}
+
+#[test]
+fn test_yaml_scan_requires_exactly_one_source() {
+    let yaml = r#"
+steps:
+  - op: scan
+    source: "a.csv"
+    source_template: "b{0..1}.csv"
+    schema: []
+"#;
+    assert!(parse_yaml_pipeline(yaml).is_err());
+}
+
+#[test]
+fn test_templated_scan_executes_per_file() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(format!("{}/data", dir)).unwrap();
+    for hour in 0..3 {
+        let mut f = fs::File::create(format!("{}/data/hour={:02}.csv", dir, hour)).unwrap();
+        writeln!(f, "id,hour").unwrap();
+        for i in 0..10 {
+            writeln!(f, "{},{}", hour * 100 + i, hour).unwrap();
+        }
+    }
+    let out = format!("{}/out.csv", dir);
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source_template: "{dir}/data/hour={{00..02}}.csv"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "hour", type: "Int64" }}
+  - op: sink
+    destination: "{out}"
+    format: "csv"
+"#
+    );
+
+    let parsed = parse_yaml_pipeline(&yaml).unwrap();
+    let optimized = rules::optimize(parsed.plan);
+    let phys = lower_to_physical(&optimized);
+    let work = estimate_work(&optimized, None);
+    let te = plan_te(&phys.plan, &work, 64 * 1024 * 1024).unwrap();
+
+    // One block per file feeds the union, each with a single dependency
+    let union_op = phys
+        .bindings
+        .iter()
+        .find(|(_, b)| b.key == "union")
+        .map(|(op, _)| *op)
+        .expect("union binding");
+    let union_blocks: Vec<_> = te.order.iter().filter(|b| b.op == union_op).collect();
+    assert_eq!(union_blocks.len(), 3);
+    assert!(union_blocks.iter().all(|b| b.deps.len() == 1));
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let mut engine = Engine::new(config).unwrap();
+    engine.run(&phys, &te).unwrap();
+
+    let output = fs::read_to_string(&out).unwrap();
+    assert_eq!(output.lines().count(), 31); // header + 3 files * 10 rows
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,127 @@
+//! Spill compression chosen by `EngineConfig::spill_codec`, and its totals in the manifest
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::{EngineConfig, SpillCodec};
+use emsqrt_core::id::SpillId;
+use emsqrt_core::manifest::RunManifest;
+use emsqrt_core::types::RowBatch;
+use emsqrt_exec::ExecError;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
+use emsqrt_planner::parse_yaml_pipeline;
+use test_data_gen::{create_temp_spill_dir, run_aggregate_pipeline};
+
+/// The shared aggregate pipeline, spilling under `codec`.
+fn run(dir: &str, codec: SpillCodec) -> Result<RunManifest, ExecError> {
+    let config = EngineConfig {
+        spill_codec: codec,
+        ..Default::default()
+    };
+    run_aggregate_pipeline(dir, Some(4 << 10), config)
+}
+
+#[test]
+fn test_parse_spill_codec() {
+    assert_eq!(SpillCodec::parse("none"), Some(SpillCodec::None));
+    assert_eq!(SpillCodec::parse("LZ4"), Some(SpillCodec::Lz4));
+    assert_eq!(
+        SpillCodec::parse("zstd"),
+        Some(SpillCodec::Zstd { level: 3 })
+    );
+    assert_eq!(
+        SpillCodec::parse("zstd:19"),
+        Some(SpillCodec::Zstd { level: 19 })
+    );
+    for bad in ["zstd:0", "zstd:23", "zstd:x", "lz4:1", "gzip", ""] {
+        assert_eq!(SpillCodec::parse(bad), None, "{}", bad);
+    }
+    assert_eq!(SpillCodec::Zstd { level: 7 }.to_string(), "zstd:7");
+
+    // Configs and pipelines name it as text.
+    let config = EngineConfig {
+        spill_codec: SpillCodec::Zstd { level: 9 },
+        ..Default::default()
+    };
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["spill_codec"], "zstd:9");
+    let back: EngineConfig = serde_json::from_value(json).unwrap();
+    assert_eq!(back.spill_codec, SpillCodec::Zstd { level: 9 });
+
+    let yaml = r#"
+config:
+  spill_codec: lz4
+steps:
+  - { op: scan, source: "in.csv", schema: [{ name: "id", type: "Int64" }] }
+  - { op: sink, destination: "out.csv", format: csv }
+"#;
+    let parsed = parse_yaml_pipeline(yaml).unwrap();
+    assert_eq!(parsed.config.spill_codec, Some(SpillCodec::Lz4));
+    let err = parse_yaml_pipeline(&yaml.replace("lz4", "zstd:99")).unwrap_err();
+    assert!(err.to_string().contains("zstd:level"), "{}", err);
+}
+
+#[test]
+fn test_manifest_records_spill_codec_and_bytes() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    let manifest = run(&dir, SpillCodec::None).unwrap();
+    let spill = manifest.spill.unwrap();
+    assert_eq!(spill.codec, SpillCodec::None);
+    let spilled: u64 = manifest.block_costs.iter().map(|b| b.bytes_spilled).sum();
+    assert!(spilled > 0);
+    assert_eq!(spill.bytes_compressed, spilled);
+    // Uncompressed segments differ from their payloads by the headers only.
+    assert!(spill.bytes_uncompressed > 0 && spill.bytes_uncompressed < spill.bytes_compressed);
+    let expected = fs::read_to_string(format!("{}/out.csv", dir)).unwrap();
+
+    for codec in [SpillCodec::Lz4, SpillCodec::Zstd { level: 1 }] {
+        let available = match codec {
+            SpillCodec::Lz4 => Codec::Lz4.is_available(),
+            _ => Codec::Zstd.is_available(),
+        };
+        match run(&dir, codec) {
+            Ok(manifest) => {
+                assert!(available);
+                let spill = manifest.spill.unwrap();
+                assert_eq!(spill.codec, codec);
+                assert!(spill.bytes_compressed < spill.bytes_uncompressed);
+                assert_eq!(
+                    fs::read_to_string(format!("{}/out.csv", dir)).unwrap(),
+                    expected
+                );
+            }
+            Err(e) => {
+                assert!(!available);
+                assert!(
+                    e.to_string().contains(&format!("spill codec '{}'", codec)),
+                    "{}",
+                    e
+                );
+            }
+        }
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_payload_a_codec_would_grow_is_stored_raw() {
+    if !Codec::Zstd.is_available() {
+        return;
+    }
+    let dir = create_temp_spill_dir();
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::Zstd, dir.clone());
+    let empty = RowBatch { columns: vec![] };
+    let meta = mgr.write_batch(&empty, SpillId::new(1), 0).unwrap();
+    assert_eq!(meta.codec, Codec::None);
+    assert_eq!(meta.compressed_len, meta.uncompressed_len);
+    let back = mgr
+        .read_batch(&meta, &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    assert!(back.columns.is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -6,7 +6,7 @@ use emsqrt_core::id::SpillId;
 use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::RowBatch;
 use emsqrt_io::storage::FsStorage;
-use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager, Storage};
+use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
 use test_data_gen::{create_temp_spill_dir, generate_random_batch};
 
 fn setup_spill_manager(codec: Codec) -> (SpillManager, String) {
@@ -81,7 +81,7 @@ fn test_spill_checksum_validation() {
     let meta = mgr.write_batch(&batch, spill_id, 0).expect("Write failed");
 
     // Corrupt the segment file on disk
-    let segment_path = format!("{}/test-spills/{}.seg", spill_dir, meta.name.0);
+    let segment_path = meta.path.clone();
     let mut corrupted_data = std::fs::read(&segment_path).expect("Failed to read segment");
 
     // Corrupt some bytes in the middle (past the header)
@@ -304,10 +304,31 @@ fn test_spill_empty_batch() {
         .write_batch(&empty_batch, spill_id, 0)
         .expect("Write failed");
 
-    assert!(meta.uncompressed_len >= 0);
-
     let read_batch = mgr.read_batch(&meta, &budget).expect("Read failed");
     assert_eq!(read_batch.num_rows(), 0);
 
     cleanup_spill_dir(&spill_dir);
 }
+
+#[test]
+fn test_managers_sharing_a_root_keep_their_segments_apart() {
+    let spill_dir = create_temp_spill_dir();
+    let budget = MemoryBudgetImpl::new(10 * 1024 * 1024);
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let manager = || SpillManager::new(Box::new(FsStorage::new()), Codec::None, spill_dir.clone());
+    let (mut a, mut b) = (manager(), manager());
+
+    // Same seed, so the same spill ids: the segments must still not collide.
+    let (id_a, id_b) = (a.next_spill_id(), b.next_spill_id());
+    assert_eq!(id_a, id_b);
+    let batch_a = generate_random_batch(10, &schema);
+    let batch_b = generate_random_batch(20, &schema);
+    let meta_a = a.write_batch(&batch_a, id_a, 0).unwrap();
+    let meta_b = b.write_batch(&batch_b, id_b, 0).unwrap();
+    assert_ne!(meta_a.path, meta_b.path);
+
+    b.delete_all().unwrap();
+    assert_eq!(a.read_batch(&meta_a, &budget).unwrap().num_rows(), 10);
+
+    cleanup_spill_dir(&spill_dir);
+}
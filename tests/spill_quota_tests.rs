@@ -0,0 +1,237 @@
+//! Spill disk quota, eager deletion of Grace join partitions, the
+//! end-of-run sweep and the spill high-watermark in the manifest
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::{EngineConfig, SpillCleanup};
+use emsqrt_core::id::SpillId;
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
+use emsqrt_operators::join::hash::HashJoin;
+use emsqrt_operators::traits::Operator;
+use test_data_gen::{create_temp_spill_dir, generate_random_batch, run_aggregate_pipeline};
+
+#[test]
+fn test_quota_counts_live_segments_only() {
+    let dir = create_temp_spill_dir();
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let batch = generate_random_batch(50, &schema);
+
+    let mut probe = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let size = probe.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    let size = size.compressed_len + emsqrt_mem::spill::HEADER_LEN as u64;
+    drop(probe);
+
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone())
+        .with_spill_quota(Some(2 * size));
+    let a = mgr.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    mgr.write_batch(&batch, SpillId::new(1), 1).unwrap();
+    assert_eq!(mgr.live_bytes(), 2 * size);
+
+    let err = mgr.write_batch(&batch, SpillId::new(1), 2).unwrap_err();
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+    assert_eq!(mgr.live_bytes(), 2 * size);
+
+    // Deleting a segment frees its share of the quota.
+    mgr.delete_segment(&a.name).unwrap();
+    mgr.write_batch(&batch, SpillId::new(1), 2).unwrap();
+    assert_eq!(mgr.live_bytes(), 2 * size);
+    assert_eq!(mgr.peak_bytes(), 2 * size);
+
+    mgr.delete_all().unwrap();
+    assert_eq!(mgr.live_bytes(), 0);
+    mgr.reset_peak();
+    assert_eq!(mgr.peak_bytes(), 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_limit_counts_deleted_segments_and_is_checked_before_quota() {
+    let dir = create_temp_spill_dir();
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let batch = generate_random_batch(50, &schema);
+
+    let mut probe = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let size = probe.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    let size = size.compressed_len + emsqrt_mem::spill::HEADER_LEN as u64;
+    drop(probe);
+
+    // Room for two segments at once, and three over the run.
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone())
+        .with_spill_limit(Some(3 * size))
+        .with_spill_quota(Some(2 * size));
+    mgr.begin_run();
+    let a = mgr.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    mgr.write_batch(&batch, SpillId::new(1), 1).unwrap();
+
+    // Within the limit but over the quota.
+    let err = mgr.write_batch(&batch, SpillId::new(1), 2).unwrap_err();
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+
+    // Deleting frees quota but not limit: the third write fits both, the
+    // fourth is under the quota and over the limit.
+    mgr.delete_segment(&a.name).unwrap();
+    let b = mgr.write_batch(&batch, SpillId::new(1), 2).unwrap();
+    mgr.delete_segment(&b.name).unwrap();
+    assert_eq!(mgr.live_bytes(), size);
+    let err = mgr.write_batch(&batch, SpillId::new(1), 3).unwrap_err();
+    assert!(err.to_string().contains("spill limit exceeded"), "{}", err);
+
+    // Over both, the limit is reported.
+    let big = generate_random_batch(200, &schema);
+    let err = mgr.write_batch(&big, SpillId::new(1), 3).unwrap_err();
+    assert!(err.to_string().contains("spill limit exceeded"), "{}", err);
+
+    // The limit starts over with the next run; the quota still holds.
+    mgr.begin_run();
+    mgr.write_batch(&batch, SpillId::new(1), 3).unwrap();
+    let err = mgr.write_batch(&batch, SpillId::new(1), 4).unwrap_err();
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_quota_at_the_limit_still_counts_segments_left_by_earlier_runs() {
+    let dir = create_temp_spill_dir();
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let batch = generate_random_batch(50, &schema);
+
+    let mut probe = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let size = probe.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    let size = size.compressed_len + emsqrt_mem::spill::HEADER_LEN as u64;
+    drop(probe);
+
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone())
+        .with_spill_limit(Some(2 * size))
+        .with_spill_quota(Some(2 * size));
+    mgr.begin_run();
+    mgr.write_batch(&batch, SpillId::new(1), 0).unwrap();
+
+    // The next run may write two segments, but the one left behind still
+    // holds half of the quota.
+    mgr.begin_run();
+    mgr.write_batch(&batch, SpillId::new(1), 1).unwrap();
+    let err = mgr.write_batch(&batch, SpillId::new(1), 2).unwrap_err();
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_sweep_removes_untracked_segments_only_when_asked() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let schema = Schema::new(vec![Field::new("id", DataType::Int64, false)]);
+    let mut mgr = SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.clone());
+    let kept = mgr
+        .write_batch(&generate_random_batch(5, &schema), SpillId::new(1), 0)
+        .unwrap();
+    let stray = format!("{}/spill9_run9.seg", dir);
+    let other = format!("{}/notes.txt", dir);
+    fs::write(&stray, b"left by a crashed run").unwrap();
+    fs::write(&other, b"not a segment").unwrap();
+
+    assert_eq!(mgr.sweep(false).unwrap(), 0);
+    assert!(fs::metadata(&stray).is_ok());
+
+    assert_eq!(mgr.sweep(true).unwrap(), 1);
+    assert!(fs::metadata(&stray).is_err());
+    assert!(fs::metadata(&other).is_ok());
+    assert!(fs::metadata(&kept.path).is_ok());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+fn join_inputs() -> (RowBatch, RowBatch) {
+    let column = |name: &str, values: Vec<Scalar>| Column {
+        name: name.to_string(),
+        values,
+    };
+    let left = RowBatch {
+        columns: vec![
+            column("id", (0..120_000).map(Scalar::I32).collect()),
+            column("l", (0..120_000).map(|i| Scalar::I64(i * 2)).collect()),
+        ],
+    };
+    let right = RowBatch {
+        columns: vec![
+            column("id", (60_000..180_000).map(Scalar::I32).collect()),
+            column("r", (60_000..180_000).map(|i| Scalar::I64(i * 3)).collect()),
+        ],
+    };
+    (left, right)
+}
+
+fn grace_join(dir: &str, quota: Option<u64>) -> (Arc<Mutex<SpillManager>>, HashJoin) {
+    let mgr = Arc::new(Mutex::new(
+        SpillManager::new(Box::new(FsStorage::new()), Codec::None, dir.to_string())
+            .with_spill_quota(quota),
+    ));
+    let join = HashJoin {
+        on: vec![("id".to_string(), "id".to_string())],
+        spill_mgr: Some(mgr.clone()),
+        num_partitions: Some(8),
+        ..Default::default()
+    };
+    (mgr, join)
+}
+
+#[test]
+fn test_grace_join_deletes_partitions_as_it_reads_them() {
+    let dir = create_temp_spill_dir();
+    let (left, right) = join_inputs();
+    let budget = MemoryBudgetImpl::new(EngineConfig::default().mem_cap_bytes);
+
+    let (mgr, join) = grace_join(&dir, None);
+    let out = join
+        .eval_block(&[left.clone(), right.clone()], &budget)
+        .unwrap();
+    assert_eq!(out.num_rows(), 60_000);
+    let mgr = mgr.lock().unwrap();
+    assert!(mgr.peak_bytes() > 0);
+    assert_eq!(mgr.live_bytes(), 0);
+    assert!(mgr.list_segments().is_empty());
+    let needed = mgr.peak_bytes();
+    drop(mgr);
+
+    // Too small a quota fails the join at once instead of filling the disk.
+    let (mgr, join) = grace_join(&dir, Some(needed / 2));
+    let err = join.eval_block(&[left, right], &budget).unwrap_err();
+    assert!(!err.is_recoverable());
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+    assert!(mgr.lock().unwrap().live_bytes() <= needed / 2);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_manifest_records_spill_peak_and_quota_fails_run() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(format!("{}/spill", dir)).unwrap();
+    let stray = format!("{}/spill/spill9_run9.seg", dir);
+    fs::write(&stray, b"left by a crashed run").unwrap();
+
+    let manifest = run_aggregate_pipeline(&dir, Some(4 << 10), EngineConfig::default()).unwrap();
+    let spill = manifest.spill.unwrap();
+    assert!(spill.peak_bytes > 0);
+    assert!(spill.peak_bytes <= spill.bytes_compressed);
+    // The default cleanup leaves files it did not write alone.
+    assert!(fs::metadata(&stray).is_ok());
+
+    let config = EngineConfig {
+        spill_cleanup: SpillCleanup::Sweep,
+        ..Default::default()
+    };
+    run_aggregate_pipeline(&dir, Some(4 << 10), config).unwrap();
+    assert!(fs::metadata(&stray).is_err());
+
+    let config = EngineConfig {
+        spill_quota_bytes: Some(spill.peak_bytes - 1),
+        ..Default::default()
+    };
+    let err = run_aggregate_pipeline(&dir, Some(4 << 10), config).unwrap_err();
+    assert!(err.to_string().contains("spill quota exceeded"), "{}", err);
+    let _ = fs::remove_dir_all(&dir);
+}
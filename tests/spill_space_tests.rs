@@ -0,0 +1,154 @@
+//! Spill capacity check before a run (`spill_space_check`)
+
+mod test_data_gen;
+
+use std::fs;
+use std::path::Path;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_exec::spill_space::available_space;
+use emsqrt_exec::{estimate_spill, Engine, ExecError};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+fn scan(source: &str) -> L {
+    L::Scan {
+        source: source.to_string(),
+        schema: Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]),
+    }
+}
+
+/// `left join right` on `id`, counted per `id`, written to `out`.
+fn plan(left: &str, right: &str, out: &str) -> L {
+    L::Sink {
+        input: Box::new(L::Aggregate {
+            input: Box::new(L::Join {
+                left: Box::new(scan(left)),
+                right: Box::new(scan(right)),
+                on: vec![("id".into(), "id".into())],
+                join_type: JoinType::Inner,
+            }),
+            group_by: vec!["id".into()],
+            aggs: vec![Aggregation::Count],
+        }),
+        destination: out.to_string(),
+        format: "csv".into(),
+    }
+}
+
+fn write_csv(path: &str, rows: usize) -> u64 {
+    let mut csv = String::from("id,name\n");
+    for i in 0..rows {
+        csv.push_str(&format!("{},user-{}\n", i, i));
+    }
+    fs::write(path, &csv).unwrap();
+    csv.len() as u64
+}
+
+#[test]
+fn test_estimate_counts_operators_whose_input_outgrows_memory() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let (left, right) = (format!("{}/left.csv", dir), format!("{}/right.csv", dir));
+    let left_bytes = write_csv(&left, 2000);
+    let right_bytes = write_csv(&right, 500);
+    let program = lower_to_physical(&plan(&left, &right, &format!("{}/out.csv", dir)));
+
+    let both = left_bytes + right_bytes;
+    let estimate = estimate_spill(&program, 1000);
+    let ops: Vec<(&str, u64)> = estimate
+        .ops
+        .iter()
+        .map(|o| (o.key.as_str(), o.bytes))
+        .collect();
+    assert_eq!(ops, [("join_hash", both), ("aggregate", both)]);
+    assert_eq!(estimate.total_bytes(), 2 * both);
+
+    // Inputs that fit in memory do not spill; missing sources count as empty.
+    assert!(estimate_spill(&program, both as usize).ops.is_empty());
+    let missing = lower_to_physical(&plan(
+        &format!("{}/nope.csv", dir),
+        &right,
+        &format!("{}/out.csv", dir),
+    ));
+    assert_eq!(
+        estimate_spill(&missing, 1000).total_bytes(),
+        2 * right_bytes
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_check_names_the_shortfall_and_the_ways_out() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let (left, right) = (format!("{}/left.csv", dir), format!("{}/right.csv", dir));
+    write_csv(&left, 4000);
+    write_csv(&right, 4000);
+    let program = lower_to_physical(&plan(&left, &right, &format!("{}/out.csv", dir)));
+    let estimate = estimate_spill(&program, 1000);
+
+    assert!(estimate.check("/mnt/spill", u64::MAX).is_ok());
+    let err = estimate.check("/mnt/spill", 100).unwrap_err();
+    assert!(
+        err.starts_with(
+            "spill dir '/mnt/spill' has 100 bytes free, but this plan may spill up to "
+        ) && err.contains("KiB (join_hash op ")
+            && err.contains(", aggregate op ")
+            && err.contains("--spill-dir")
+            && err.contains("spill_space_check: false"),
+        "{}",
+        err
+    );
+
+    // A spill dir yet to be created is measured on its parent's volume.
+    assert!(available_space(Path::new(&format!("{}/not/yet", dir))).unwrap() > 0);
+
+    let yaml = "config:\n  spill_space_check: false\nsteps:\n  - op: scan\n    source: \"in.csv\"\n    schema:\n      - { name: \"id\", type: \"Int64\" }\n  - op: sink\n    destination: \"out.csv\"\n    format: \"csv\"\n";
+    assert_eq!(
+        parse_yaml_pipeline(yaml).unwrap().config.spill_space_check,
+        Some(false)
+    );
+    assert!(EngineConfig::default().spill_space_check);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_run_fails_before_reading_when_spill_cannot_fit() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    // A sparse 8 TiB source: no real disk use, but more than the volume has.
+    let left = format!("{}/huge.csv", dir);
+    let size = 1u64 << 43;
+    let sparse = fs::File::create(&left).and_then(|f| f.set_len(size));
+    let available = available_space(Path::new(&dir)).unwrap();
+    if sparse.is_err() || available >= size {
+        let _ = fs::remove_dir_all(&dir);
+        return;
+    }
+    let right = format!("{}/right.csv", dir);
+    write_csv(&right, 10);
+
+    let lp = plan(&left, &right, &format!("{}/out.csv", dir));
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 24).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        mem_cap_bytes: 1 << 24,
+        ..Default::default()
+    };
+    let err = Engine::new(config).unwrap().run(&program, &te).unwrap_err();
+    assert!(matches!(err, ExecError::SpillSpace(_)), "{}", err);
+    assert!(err.to_string().contains("join_hash op"), "{}", err);
+    assert!(!Path::new(&format!("{}/out.csv", dir)).exists());
+
+    let _ = fs::remove_dir_all(&dir);
+}
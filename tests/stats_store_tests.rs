@@ -0,0 +1,118 @@
+//! Warm-start stats store (`EngineConfig::stats_dir`, `StatsStore`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::stats::{SchemaStats, SourceStats};
+use emsqrt_exec::{Engine, StatsStore};
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline, scan_sources};
+use test_data_gen::create_temp_spill_dir;
+
+fn pipeline(input: &str, output: &str) -> String {
+    format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "id", type: "Int64" }}
+  - op: filter
+    expr: "id > 2"
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    )
+}
+
+fn run(dir: &str, input: &str) {
+    let parsed = parse_yaml_pipeline(&pipeline(input, &format!("{}/out.csv", dir))).unwrap();
+    let program = lower_to_physical(&parsed.plan);
+    let work = estimate_work(&parsed.plan, None);
+    let te = parsed.hints.plan_te(&program, &work, 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        stats_dir: Some(format!("{}/stats", dir)),
+        ..Default::default()
+    };
+    let manifest = Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert!(manifest.warnings.is_empty(), "{:?}", manifest.warnings);
+}
+
+#[test]
+fn test_runs_populate_the_store_and_later_plans_use_it() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id\n4\n1\n5\n3\n2\n").unwrap();
+    let store = StatsStore::open(format!("{}/stats", dir));
+    assert!(store.get(&input).is_none());
+
+    run(&dir, &input);
+    let saved = store.get(&input).unwrap();
+    assert_eq!(saved.row_count(), Some(5));
+
+    // A fresh plan of the same pipeline starts from the saved stats.
+    let mut plan = parse_yaml_pipeline(&pipeline(&input, "out.csv"))
+        .unwrap()
+        .plan;
+    assert_eq!(scan_sources(&plan), [input.as_str()]);
+    assert_eq!(estimate_work(&plan, None).total_rows, 1);
+    assert_eq!(store.warm_start(&mut plan), 1);
+    // id in [1, 5]: "id > 2" keeps three quarters of 5 rows.
+    assert_eq!(estimate_work(&plan, None).total_rows, 3);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_changed_contents_are_not_matched() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id\n1\n2\n").unwrap();
+    let store = StatsStore::open(format!("{}/stats", dir));
+    run(&dir, &input);
+    let before = StatsStore::fingerprint(&input).unwrap();
+    assert_eq!(store.get(&input).unwrap().row_count(), Some(2));
+
+    fs::write(&input, "id\n1\n2\n3\n").unwrap();
+    assert_ne!(StatsStore::fingerprint(&input).unwrap(), before);
+    assert!(store.get(&input).is_none());
+    run(&dir, &input);
+    assert_eq!(store.get(&input).unwrap().row_count(), Some(3));
+
+    // Restoring the old contents finds the old entry again.
+    fs::write(&input, "id\n1\n2\n").unwrap();
+    assert_eq!(StatsStore::fingerprint(&input).unwrap(), before);
+    assert_eq!(store.get(&input).unwrap().row_count(), Some(2));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_only_local_files_are_recorded() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.csv", dir);
+    fs::write(&input, "id\n1\n").unwrap();
+    let store = StatsStore::open(format!("{}/stats", dir));
+
+    let stats: SourceStats = [
+        (format!("file://{}", input), SchemaStats::new()),
+        ("s3://bucket/in.csv".to_string(), SchemaStats::new()),
+        ("mem://orders".to_string(), SchemaStats::new()),
+        (format!("{}/missing.csv", dir), SchemaStats::new()),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(store.record(&stats).unwrap(), 1);
+    assert!(store.get(&format!("file://{}", input)).is_some());
+    // Entries are keyed by the URI as written in the pipeline.
+    assert!(store.get(&input).is_none());
+    assert!(StatsStore::fingerprint("s3://bucket/in.csv").is_none());
+
+    let _ = fs::remove_dir_all(&dir);
+}
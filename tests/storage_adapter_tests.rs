@@ -12,8 +12,10 @@ fn temp_spill_dir(name: &str) -> String {
 #[test]
 fn test_file_storage_builder_write_read() {
     let dir = temp_spill_dir("fs");
-    let mut cfg = EngineConfig::default();
-    cfg.spill_dir = dir.clone();
+    let cfg = EngineConfig {
+        spill_dir: dir.clone(),
+        ..Default::default()
+    };
 
     let storage_cfg = cfg.storage_config();
     let storage = build_storage_from_config(&storage_cfg).expect("fs storage");
@@ -27,8 +29,10 @@ fn test_file_storage_builder_write_read() {
 
 #[test]
 fn test_invalid_scheme_errors() {
-    let mut cfg = EngineConfig::default();
-    cfg.spill_uri = Some("ftp://example.com/spill".into());
+    let cfg = EngineConfig {
+        spill_uri: Some("ftp://example.com/spill".into()),
+        ..Default::default()
+    };
     let storage_cfg = cfg.storage_config();
     let err = build_storage_from_config(&storage_cfg)
         .err()
@@ -39,9 +43,11 @@ fn test_invalid_scheme_errors() {
 #[cfg(not(feature = "s3"))]
 #[test]
 fn test_s3_without_feature_fails() {
-    let mut cfg = EngineConfig::default();
-    cfg.spill_uri = Some("s3://dummy/test".into());
-    cfg.spill_aws_region = Some("us-east-1".into());
+    let cfg = EngineConfig {
+        spill_uri: Some("s3://dummy/test".into()),
+        spill_aws_region: Some("us-east-1".into()),
+        ..Default::default()
+    };
     let storage_cfg = cfg.storage_config();
     let err = build_storage_from_config(&storage_cfg)
         .err()
@@ -51,14 +57,41 @@ fn test_s3_without_feature_fails() {
         .contains("EM-√ was built without the `s3` feature"));
 }
 
-#[cfg(all(feature = "s3"))]
+#[cfg(feature = "s3")]
 #[test]
 fn test_s3_builder_initializes_with_dummy_credentials() {
-    let mut cfg = EngineConfig::default();
-    cfg.spill_uri = Some("s3://dummy-bucket/tests".into());
-    cfg.spill_aws_region = Some("us-east-1".into());
-    cfg.spill_aws_access_key_id = Some("ACCESSKEY123".into());
-    cfg.spill_aws_secret_access_key = Some("SECRETKEY456".into());
+    let cfg = EngineConfig {
+        spill_uri: Some("s3://dummy-bucket/tests".into()),
+        spill_aws_region: Some("us-east-1".into()),
+        spill_aws_access_key_id: Some("ACCESSKEY123".into()),
+        spill_aws_secret_access_key: Some("SECRETKEY456".into()),
+        ..Default::default()
+    };
     let storage_cfg = cfg.storage_config();
     build_storage_from_config(&storage_cfg).expect("s3 storage builds");
 }
+
+#[test]
+fn test_spill_dir_uri_selects_the_backend() {
+    let cfg = EngineConfig {
+        spill_dir: "s3://bucket/prefix/".into(),
+        ..Default::default()
+    };
+    let storage_cfg = cfg.storage_config();
+    assert_eq!(storage_cfg.scheme(), Some("s3"));
+    assert_eq!(storage_cfg.root, "s3://bucket/prefix");
+
+    // An explicit spill URI still wins over the directory.
+    let cfg = EngineConfig {
+        spill_dir: "s3://bucket/prefix".into(),
+        spill_uri: Some("mem://spill".into()),
+        ..Default::default()
+    };
+    assert_eq!(cfg.storage_config().scheme(), Some("mem"));
+
+    let cfg = EngineConfig {
+        spill_dir: "/tmp/emsqrt-spill".into(),
+        ..Default::default()
+    };
+    assert_eq!(cfg.storage_config().uri, None);
+}
@@ -0,0 +1,141 @@
+//! Storage conformance suite (`emsqrt_mem::testkit`) against the built-in backends
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_io::{FsStorage, MemStorage};
+use emsqrt_mem::error::Result as MemResult;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::testkit;
+use emsqrt_mem::{Codec, SpillManager, Storage};
+use test_data_gen::create_temp_spill_dir;
+
+#[test]
+fn test_fs_storage_conforms() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+
+    testkit::run(&FsStorage::new(), &dir);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_mem_storage_conforms() {
+    testkit::run(&MemStorage::new(), "/spill");
+}
+
+#[cfg(feature = "s3")]
+fn s3_storage(multipart_threshold: usize) -> emsqrt_io::storage::S3Storage {
+    use emsqrt_core::config::EngineConfig;
+    use object_store::memory::InMemory;
+
+    let cfg = EngineConfig {
+        spill_dir: "s3://bucket/spill".into(),
+        ..Default::default()
+    };
+    emsqrt_io::storage::S3Storage::with_store(
+        std::sync::Arc::new(InMemory::new()),
+        &cfg.storage_config(),
+    )
+    .unwrap()
+    .with_multipart_threshold(multipart_threshold)
+}
+
+#[cfg(feature = "s3")]
+#[test]
+fn test_s3_storage_conforms() {
+    testkit::run(
+        &s3_storage(emsqrt_io::storage::MULTIPART_THRESHOLD),
+        "s3://bucket/spill",
+    );
+    // Every write above a tiny threshold goes through a multipart upload.
+    testkit::run(&s3_storage(16), "s3://bucket/spill");
+}
+
+#[cfg(feature = "s3")]
+#[test]
+fn test_s3_multipart_segments_round_trip() {
+    let storage = s3_storage(1024);
+    let large: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+    storage
+        .write("s3://bucket/spill/large.seg", &large)
+        .unwrap();
+    storage
+        .write("s3://bucket/spill/small.seg", b"small")
+        .unwrap();
+    assert_eq!(storage.size("s3://bucket/spill/large.seg").unwrap(), 5000);
+    assert_eq!(
+        storage
+            .read_range("s3://bucket/spill/large.seg", 1000, 3000)
+            .unwrap(),
+        &large[1000..4000]
+    );
+    let mut listed = storage.list("s3://bucket/spill").unwrap();
+    listed.sort();
+    assert_eq!(
+        listed,
+        ["s3://bucket/spill/large.seg", "s3://bucket/spill/small.seg"]
+    );
+    // Paths outside the configured bucket and prefix are rejected.
+    assert!(storage.write("s3://other/x.seg", b"x").is_err());
+}
+
+/// Returns fewer bytes than asked for instead of failing.
+struct TruncatingStorage(MemStorage);
+
+impl Storage for TruncatingStorage {
+    fn write(&self, path: &str, bytes: &[u8]) -> MemResult<()> {
+        self.0.write(path, bytes)
+    }
+    fn read_range(&self, path: &str, offset: u64, len: usize) -> MemResult<Vec<u8>> {
+        let size = self.0.size(path)?;
+        let len = len.min(size.saturating_sub(offset) as usize);
+        self.0.read_range(path, offset.min(size), len)
+    }
+    fn delete(&self, path: &str) -> MemResult<()> {
+        self.0.delete(path)
+    }
+    fn list(&self, prefix: &str) -> MemResult<Vec<String>> {
+        self.0.list(prefix)
+    }
+    fn size(&self, path: &str) -> MemResult<u64> {
+        self.0.size(path)
+    }
+    fn etag(&self, path: &str) -> MemResult<Option<String>> {
+        self.0.etag(path)
+    }
+}
+
+#[test]
+fn test_suite_reports_short_reads() {
+    let failures = testkit::check(&TruncatingStorage(MemStorage::new()), "/spill");
+    let names: Vec<_> = failures.iter().map(|f| f.check).collect();
+    assert_eq!(names, ["short_read_errors"], "{:?}", failures);
+}
+
+#[test]
+fn test_spill_manager_on_mem_storage() {
+    let storage = MemStorage::new();
+    let mut spill = SpillManager::new(Box::new(storage.clone()), Codec::None, "/spill".into());
+    let budget = MemoryBudgetImpl::new(1 << 20);
+    let batch = RowBatch {
+        columns: vec![Column {
+            name: "id".into(),
+            values: (0..100).map(Scalar::I64).collect(),
+        }],
+    };
+
+    let id = spill.next_spill_id();
+    let run = spill.next_run_index();
+    let meta = spill.write_batch(&batch, id, run).unwrap();
+    assert_eq!(storage.len(), 1);
+    let back = spill.read_batch(&meta, &budget).unwrap();
+    assert_eq!(back.columns[0].values, batch.columns[0].values);
+    assert_eq!(budget.used_bytes(), 0);
+
+    spill.delete_segment(&meta.name).unwrap();
+    assert!(storage.is_empty());
+}
@@ -1,7 +1,16 @@
 //! Test data generation utilities for EM-√ test suite
 
+#![allow(dead_code)]
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{Aggregation, LogicalPlan};
+use emsqrt_core::manifest::RunManifest;
 use emsqrt_core::schema::{DataType, Field, Schema};
 use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::{Engine, ExecError};
+use emsqrt_planner::{estimate_work, lower_to_physical, PhysicalProgram};
+use emsqrt_te::plan_te;
+use emsqrt_te::tree_eval::TePlan;
 use std::collections::HashMap;
 
 /// Generate a random RowBatch matching the given schema
@@ -20,8 +29,9 @@ pub fn generate_random_batch(rows: usize, schema: &Schema) -> RowBatch {
                 DataType::Float64 => Scalar::F64((i as f64) * 0.5),
                 DataType::Utf8 => Scalar::Str(format!("value_{}", i % 100)),
                 DataType::Binary => Scalar::Bin(vec![i as u8; 10]),
-                DataType::Date64 => Scalar::I64((i as i64) * 86400000), // Days as ms
-                DataType::Decimal128 => Scalar::I64(i as i64),          // Simplified
+                DataType::Date64 => Scalar::Date64((i as i64) * 86400000), // Days as ms
+                DataType::Timestamp => Scalar::Timestamp((i as i64) * 1_000_000),
+                DataType::Decimal128 => Scalar::Decimal128(i as i128 * 25, 2),
             };
             values.push(value);
         }
@@ -133,6 +143,57 @@ pub fn create_temp_spill_dir() -> String {
     format!("/tmp/emsqrt-test-{}", nanos)
 }
 
+/// Plan `scan → aggregate(count per k) → sink` over a 4000-row CSV with 500
+/// groups, written to `{dir}/in.csv` (output in `{dir}/out.csv`).
+/// `aggregate_memory` caps the aggregate's budget; 4 KiB makes it spill.
+pub fn aggregate_pipeline(dir: &str, aggregate_memory: Option<u64>) -> (PhysicalProgram, TePlan) {
+    let input = format!("{}/in.csv", dir);
+    let rows: String = (0..4000)
+        .map(|i| format!("{},{}\n", (i * 7919) % 500, i))
+        .collect();
+    std::fs::write(&input, format!("k,v\n{}", rows)).unwrap();
+
+    let lp = LogicalPlan::Sink {
+        input: Box::new(LogicalPlan::Aggregate {
+            input: Box::new(LogicalPlan::Scan {
+                source: input,
+                schema: Schema::new(vec![
+                    Field::new("k", DataType::Int64, false),
+                    Field::new("v", DataType::Int64, false),
+                ]),
+            }),
+            group_by: vec!["k".into()],
+            aggs: vec![Aggregation::Count],
+        }),
+        destination: format!("{}/out.csv", dir),
+        format: "csv".into(),
+    };
+    let mut program = lower_to_physical(&lp);
+    if let Some(bytes) = aggregate_memory {
+        for binding in program.bindings.values_mut() {
+            if binding.key == "aggregate" {
+                binding.config["memory_bytes"] = bytes.into();
+            }
+        }
+    }
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    (program, te)
+}
+
+/// Run [`aggregate_pipeline`] with spills under `{dir}/spill`.
+pub fn run_aggregate_pipeline(
+    dir: &str,
+    aggregate_memory: Option<u64>,
+    config: EngineConfig,
+) -> Result<RunManifest, ExecError> {
+    let (program, te) = aggregate_pipeline(dir, aggregate_memory);
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..config
+    };
+    Engine::new(config)?.run(&program, &te)
+}
+
 /// Generate two batches suitable for join testing
 pub fn generate_join_batches(
     left_rows: usize,
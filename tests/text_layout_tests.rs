@@ -0,0 +1,212 @@
+//! Delimited and fixed-width text sources (`format: delimited | fixed_width`)
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::schema::{DataType, Field, FieldLayout, FixedWidthSpan, Schema, TextLayout};
+use emsqrt_exec::Engine;
+use emsqrt_io::readers::text::TextReader;
+use emsqrt_planner::{estimate_work, lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::plan_te;
+use test_data_gen::create_temp_spill_dir;
+
+/// Run `scan(input) → sink(jsonl)`; `scan` and `fields` are spliced into the
+/// scan step and its schema. Returns the output lines.
+fn run(dir: &str, input: &str, scan: &str, fields: &str) -> Vec<String> {
+    let output = format!("{}/out.jsonl", dir);
+    let yaml = format!(
+        r#"
+hints:
+  rows_per_block: 2
+steps:
+  - op: scan
+    source: "{input}"
+{scan}
+    schema:
+{fields}
+  - op: sink
+    destination: "{output}"
+    format: "jsonl"
+"#
+    );
+    let parsed = parse_yaml_pipeline(&yaml).unwrap();
+    let mut program = lower_to_physical(&parsed.plan);
+    parsed.hints.apply(&mut program);
+    let work = estimate_work(&parsed.plan, None);
+    let te = parsed.hints.plan_te(&program, &work, 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    fs::read_to_string(&output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_delimited_custom_record_and_field_delimiters() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/export.txt", dir);
+    fs::write(&input, "EXPORT 2024~name||id~a||1~b||2~~c||x~d||4").unwrap();
+
+    let scan = r#"    format: delimited
+    delimiter: "||"
+    record_delimiter: "~"
+    skip_records: 1"#;
+    let fields = r#"      - { name: "id", type: "Int64", nullable: true }
+      - { name: "name", type: "Utf8" }"#;
+    assert_eq!(
+        run(&dir, &input, scan, fields),
+        vec![
+            r#"{"id":1,"name":"a"}"#,
+            r#"{"id":2,"name":"b"}"#,
+            r#"{"id":null,"name":"c"}"#,
+            r#"{"id":4,"name":"d"}"#,
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_fixed_width_spans_and_null_options() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/legacy.dat", dir);
+    fs::write(
+        &input,
+        "HDR\r\n000001ALICE     0042\r\n000002BOB       \r\n000003          0007\r\n",
+    )
+    .unwrap();
+
+    let scan = r#"    format: fixed_width
+    skip_records: 1
+    trim_whitespace: true
+    empty_as_null: true"#;
+    // `name` follows `id`; `score` gives its offset explicitly.
+    let fields = r#"      - { name: "id", type: "Int64", length: 6 }
+      - { name: "name", type: "Utf8", nullable: true, length: 10 }
+      - { name: "score", type: "Int64", nullable: true, offset: 16, length: 4 }"#;
+    assert_eq!(
+        run(&dir, &input, scan, fields),
+        vec![
+            r#"{"id":1,"name":"ALICE","score":42}"#,
+            r#"{"id":2,"name":"BOB","score":null}"#,
+            r#"{"id":3,"name":null,"score":7}"#,
+        ]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_text_parse_errors_report_record_numbers() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/in.txt", dir);
+    fs::write(&input, "id;v\n1;10\n2;oops\n").unwrap();
+    let output = format!("{}/out.jsonl", dir);
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    format: delimited
+    delimiter: ";"
+    on_parse_error: fail
+    schema:
+      - {{ name: "id", type: "Int64" }}
+      - {{ name: "v", type: "Int64" }}
+  - op: sink
+    destination: "{output}"
+    format: "jsonl"
+"#
+    );
+    let lp = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let program = lower_to_physical(&lp);
+    let te = plan_te(&program.plan, &estimate_work(&lp, None), 1 << 26).unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    let err = Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .unwrap_err()
+        .to_string();
+    // Record 3 of the file: the header is record 1.
+    assert!(err.contains("line 3: column 'v' value 'oops'"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_reader_without_header_uses_schema_order() {
+    let schema = Schema::new(vec![
+        Field::new("a", DataType::Utf8, false),
+        Field::new("b", DataType::Utf8, false),
+    ])
+    .with_layout(Some(TextLayout {
+        record_delimiter: "\n".into(),
+        skip_records: 0,
+        fields: FieldLayout::Delimited {
+            delimiter: "\t".into(),
+            header: false,
+        },
+    }));
+    let mut reader = TextReader::from_reader("x\ty\n\nz\n".as_bytes(), &schema).unwrap();
+    let first = reader.next_record().unwrap().unwrap();
+    assert_eq!(
+        (first.line, first.values),
+        (1, vec!["x".into(), "y".into()])
+    );
+    // Blank records are skipped; missing trailing fields are empty.
+    let second = reader.next_record().unwrap().unwrap();
+    assert_eq!(
+        (second.line, second.values),
+        (3, vec!["z".into(), "".into()])
+    );
+    assert!(reader.next_record().unwrap().is_none());
+
+    // A fixed-width layout needs one span per field.
+    let bad = schema.with_layout(Some(TextLayout {
+        record_delimiter: "\n".into(),
+        skip_records: 0,
+        fields: FieldLayout::FixedWidth {
+            spans: vec![FixedWidthSpan {
+                offset: 0,
+                length: 1,
+            }],
+        },
+    }));
+    assert!(TextReader::from_reader("ab\n".as_bytes(), &bad).is_err());
+}
+
+#[test]
+fn test_layout_options_are_validated() {
+    let parse = |scan: &str, field: &str| {
+        let yaml = format!(
+            "steps:\n  - op: scan\n    source: in.txt\n{}\n    schema:\n      - {{ name: a, type: Utf8{} }}\n",
+            scan, field
+        );
+        parse_yaml_pipeline(&yaml)
+            .map(|_| ())
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(parse("    delimiter: \"|\"", "").contains("format: delimited"));
+    assert!(parse("    format: delimited", "").contains("needs a non-empty 'delimiter'"));
+    assert!(parse("    format: fixed_width", "").contains("needs a 'length'"));
+    assert!(parse(
+        "    format: fixed_width\n    delimiter: \",\"",
+        ", length: 1"
+    )
+    .contains("delimited scans only"));
+    assert!(parse("    format: xml", "").contains("unknown scan format 'xml'"));
+}
@@ -0,0 +1,295 @@
+//! Nested-loop joins on an arbitrary condition, and cross joins
+
+mod test_data_gen;
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::dag::{JoinType, LogicalPlan as L};
+use emsqrt_core::schema::{DataType, Field, Schema};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_mem::spill::{Codec, SpillManager};
+use emsqrt_operators::join::nested_loop::NestedLoopJoin;
+use emsqrt_operators::traits::Operator;
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use serde_json::json;
+use test_data_gen::create_temp_spill_dir;
+
+fn batch(columns: &[(&str, Vec<i64>)]) -> RowBatch {
+    RowBatch {
+        columns: columns
+            .iter()
+            .map(|(name, values)| Column {
+                name: name.to_string(),
+                values: values.iter().copied().map(Scalar::I64).collect(),
+            })
+            .collect(),
+    }
+}
+
+fn int_schema(names: &[&str]) -> Schema {
+    Schema::new(
+        names
+            .iter()
+            .map(|n| Field::new(*n, DataType::Int64, false))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_range_join_spans_blocks_in_probe_order() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let events: Vec<i64> = (0..200).map(|i| (i * 37) % 1000).collect();
+    let windows: Vec<(i64, i64, i64)> = (0..20).map(|w| (w, w * 45, w * 45 + 60)).collect();
+    let csv = |header: &str, rows: Vec<String>| format!("{}\n{}\n", header, rows.join("\n"));
+    fs::write(
+        format!("{}/events.csv", dir),
+        csv("ts", events.iter().map(|t| t.to_string()).collect()),
+    )
+    .unwrap();
+    fs::write(
+        format!("{}/windows.csv", dir),
+        csv(
+            "wid,start,end",
+            windows
+                .iter()
+                .map(|(w, s, e)| format!("{},{},{}", w, s, e))
+                .collect(),
+        ),
+    )
+    .unwrap();
+
+    let destination = format!("{}/out.csv", dir);
+    let lp = L::Sink {
+        input: Box::new(L::ThetaJoin {
+            left: Box::new(L::Scan {
+                source: format!("{}/events.csv", dir),
+                schema: int_schema(&["ts"]),
+            }),
+            right: Box::new(L::Scan {
+                source: format!("{}/windows.csv", dir),
+                schema: int_schema(&["wid", "start", "end"]),
+            }),
+            condition: Some("ts >= start AND ts < end".into()),
+            join_type: JoinType::Left,
+        }),
+        destination: destination.clone(),
+        format: "csv".into(),
+    };
+    let program = lower_to_physical(&lp);
+    // 16-row blocks over 200 rows: 13 per source; each join block reads
+    // one left block and every right block.
+    let work = WorkEstimate {
+        total_rows: 200,
+        total_bytes: 200,
+        max_fan_in: 2,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block: 16 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let join_blocks: Vec<_> = te
+        .order
+        .iter()
+        .filter(|b| program.bindings[&b.op].key == "join_nested_loop")
+        .collect();
+    assert_eq!(join_blocks.len(), 13);
+    assert!(join_blocks.iter().all(|b| b.deps.len() == 14));
+
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+
+    let mut expected = vec!["ts,wid,start,end".to_string()];
+    for ts in &events {
+        let before = expected.len();
+        for (w, s, e) in windows.iter().filter(|(_, s, e)| ts >= s && ts < e) {
+            expected.push(format!("{},{},{},{}", ts, w, s, e));
+        }
+        if expected.len() == before {
+            expected.push(format!("{},,,", ts));
+        }
+    }
+    let out = fs::read_to_string(&destination).unwrap();
+    assert_eq!(out.lines().collect::<Vec<_>>(), expected);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_condition_cross_and_outer_joins() {
+    let dir = create_temp_spill_dir();
+    let engine = Engine::new(EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    })
+    .unwrap();
+    let left = batch(&[("id", vec![1, 2, 3]), ("v", vec![10, 20, 30])]);
+    let right = batch(&[("id", vec![2, 3]), ("w", vec![15, 5])]);
+    let inputs = [left, right];
+
+    // Every pair, right columns renamed as in the output.
+    let cross = engine
+        .eval_operator("join_nested_loop", &json!({}), &inputs)
+        .unwrap();
+    let names: Vec<&str> = cross.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["id", "v", "id_right", "w"]);
+    assert_eq!(cross.num_rows(), 6);
+    assert_eq!(
+        cross.columns[2].values,
+        [2, 3, 2, 3, 2, 3].map(Scalar::I64).to_vec()
+    );
+
+    let left_join = engine
+        .eval_operator(
+            "join_nested_loop",
+            &json!({ "condition": "v > w && id >= id_right", "join_type": "left" }),
+            &inputs,
+        )
+        .unwrap();
+    let rows: Vec<Vec<Scalar>> = (0..left_join.num_rows())
+        .map(|r| {
+            left_join
+                .columns
+                .iter()
+                .map(|c| c.values[r].clone())
+                .collect()
+        })
+        .collect();
+    use Scalar::{Null, I64};
+    assert_eq!(
+        rows,
+        [
+            vec![I64(1), I64(10), Null, Null],
+            vec![I64(2), I64(20), I64(2), I64(15)],
+            vec![I64(3), I64(30), I64(2), I64(15)],
+            vec![I64(3), I64(30), I64(3), I64(5)],
+        ]
+    );
+
+    let err = engine
+        .eval_operator(
+            "join_nested_loop",
+            &json!({ "condition": "v > w", "join_type": "full" }),
+            &inputs,
+        )
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("supports inner and left joins"), "{}", err);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_right_input_over_budget_is_spilled() {
+    let dir = create_temp_spill_dir();
+    let spill_dir = format!("{}/spill", dir);
+    fs::create_dir_all(&spill_dir).unwrap();
+    let spill_mgr = Arc::new(Mutex::new(SpillManager::new(
+        Box::new(FsStorage::new()),
+        Codec::None,
+        spill_dir.clone(),
+    )));
+
+    let left = batch(&[("a", (0..300).collect())]);
+    let right = batch(&[("b", (0..400).map(|i| (i * 7) % 400).collect())]);
+    let inputs = [left, right];
+    let join = |spill_mgr: Option<Arc<Mutex<SpillManager>>>| NestedLoopJoin {
+        condition: Some("b >= a * 2 AND b < a * 2 + 3".into()),
+        spill_mgr,
+        pairs_per_chunk: 400,
+        ..Default::default()
+    };
+
+    let in_memory = join(None)
+        .eval_block(&inputs, &MemoryBudgetImpl::new(1 << 26))
+        .unwrap();
+    assert_eq!(in_memory.num_rows(), 199 * 3 + 2);
+
+    // The right input does not fit 8 KiB; its 20-row chunks do.
+    let spilled = join(Some(spill_mgr.clone()))
+        .eval_block(&inputs, &MemoryBudgetImpl::new(8 << 10))
+        .unwrap();
+    for (got, want) in spilled.columns.iter().zip(&in_memory.columns) {
+        assert_eq!(got.values, want.values);
+    }
+    let mgr = spill_mgr.lock().unwrap();
+    assert!(mgr.bytes_written() > 0);
+    assert!(mgr.bytes_read() > mgr.bytes_written());
+    // The chunks are deleted once the block is joined.
+    assert!(mgr.list_segments().is_empty());
+    drop(mgr);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_v2_condition_and_cross_join_stages() {
+    let pipeline = |join: &str| {
+        format!(
+            r#"
+version: 2
+stages:
+  - {{ name: a, op: scan, source: "a.csv", schema: [ {{ name: "x", type: "Int64" }} ] }}
+  - {{ name: b, op: scan, source: "b.csv", schema: [ {{ name: "y", type: "Int64" }} ] }}
+  - {{ name: j, inputs: [a, b], {join} }}
+  - {{ name: out, op: sink, destination: "out.csv", format: csv }}
+"#
+        )
+    };
+    let joined = |join: &str| {
+        let plan = parse_yaml_pipeline(&pipeline(join)).map(|p| p.plan);
+        match plan {
+            Ok(L::Sink { input, .. }) => Ok(*input),
+            Ok(other) => panic!("expected a sink: {:?}", other),
+            Err(e) => Err(e.to_string()),
+        }
+    };
+
+    let Ok(L::ThetaJoin {
+        condition,
+        join_type,
+        ..
+    }) = joined(r#"op: join, condition: "x < y", join_type: left"#)
+    else {
+        panic!("expected a theta join")
+    };
+    assert_eq!(condition.as_deref(), Some("x < y"));
+    assert_eq!(join_type, JoinType::Left);
+    assert!(matches!(
+        joined("op: cross_join"),
+        Ok(L::ThetaJoin {
+            condition: None,
+            join_type: JoinType::Inner,
+            ..
+        })
+    ));
+
+    let cases = [
+        (
+            r#"op: join, on: [x], condition: "x < y""#,
+            "'on' or a 'condition', not both",
+        ),
+        (
+            r#"op: join, condition: "x < y", join_type: full"#,
+            "inner or left",
+        ),
+        (r#"op: join, condition: "x <""#, "invalid join condition"),
+        ("op: join", "at least one key in 'on', or a 'condition'"),
+    ];
+    for (join, expected) in cases {
+        let err = joined(join).unwrap_err();
+        assert!(err.contains(expected), "{}: {}", join, err);
+    }
+}
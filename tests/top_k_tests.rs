@@ -0,0 +1,222 @@
+//! Approximate top-K frequent values (`top_k` step, Space-Saving sketch)
+
+mod test_data_gen;
+
+use std::collections::HashMap;
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_mem::guard::MemoryBudgetImpl;
+use emsqrt_operators::top_k::{SpaceSaving, TopK};
+use emsqrt_operators::Operator;
+use emsqrt_planner::{
+    column_lineage, estimate_work, lower_to_physical, parse_yaml_pipeline, WorkHint,
+};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint};
+use test_data_gen::create_temp_spill_dir;
+
+/// Zipf-like stream: value `v` in `0..distinct` occurs `total / (v + 1)`
+/// times, interleaved.
+fn skewed(distinct: i64, total: i64) -> Vec<i64> {
+    let mut left: Vec<i64> = (0..distinct).map(|v| total / (v + 1)).collect();
+    let mut out = Vec::new();
+    while left.iter().any(|&n| n > 0) {
+        for (v, n) in left.iter_mut().enumerate() {
+            if *n > 0 {
+                out.push(v as i64);
+                *n -= 1;
+            }
+        }
+    }
+    out
+}
+
+fn exact_counts(values: &[i64]) -> HashMap<i64, u64> {
+    let mut counts = HashMap::new();
+    for v in values {
+        *counts.entry(*v).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Each reported count bounds the true count from above, within its error.
+fn assert_bounds(top: &[(Scalar, u64, u64)], exact: &HashMap<i64, u64>) {
+    for (value, count, error) in top {
+        let Scalar::I64(v) = value else {
+            panic!("unexpected value {:?}", value);
+        };
+        let truth = exact[v];
+        assert!(count - error <= truth && truth <= *count, "{} {:?}", v, top);
+    }
+}
+
+#[test]
+fn test_sketch_keeps_heavy_hitters_within_error_bounds() {
+    let values = skewed(500, 2000);
+    let exact = exact_counts(&values);
+
+    // Room for every value: exact counts.
+    let mut roomy = SpaceSaving::new(1000);
+    values.iter().for_each(|v| roomy.insert(&Scalar::I64(*v)));
+    let top = roomy.top(3);
+    assert_eq!(
+        top,
+        [
+            (Scalar::I64(0), 2000, 0),
+            (Scalar::I64(1), 1000, 0),
+            (Scalar::I64(2), 666, 0)
+        ]
+    );
+
+    // 50 counters for 500 values: the heaviest still come out on top.
+    let mut tight = SpaceSaving::new(50);
+    values.iter().for_each(|v| tight.insert(&Scalar::I64(*v)));
+    assert_eq!(tight.len(), 50);
+    let top = tight.top(3);
+    let ranked: Vec<&Scalar> = top.iter().map(|(v, _, _)| v).collect();
+    assert_eq!(ranked, [&Scalar::I64(0), &Scalar::I64(1), &Scalar::I64(2)]);
+    assert_bounds(&top, &exact);
+}
+
+#[test]
+fn test_merged_block_sketches_find_the_same_heavy_hitters() {
+    let values = skewed(400, 1000);
+    let exact = exact_counts(&values);
+    let mut merged = SpaceSaving::new(40);
+    for chunk in values.chunks(700) {
+        let mut block = SpaceSaving::new(40);
+        chunk.iter().for_each(|v| block.insert(&Scalar::I64(*v)));
+        merged.merge(&block);
+    }
+    assert!(merged.len() <= 40);
+    let top = merged.top(4);
+    let ranked: Vec<&Scalar> = top.iter().map(|(v, _, _)| v).collect();
+    assert_eq!(
+        ranked,
+        [
+            &Scalar::I64(0),
+            &Scalar::I64(1),
+            &Scalar::I64(2),
+            &Scalar::I64(3)
+        ]
+    );
+    assert_bounds(&top, &exact);
+}
+
+#[test]
+fn test_operator_skips_nulls_and_emits_on_its_last_block() {
+    let op = TopK::new("k", 2, 0);
+    let batch = RowBatch {
+        columns: vec![Column {
+            name: "k".into(),
+            values: ["a", "b", "a", "", "c", "a", "b"]
+                .iter()
+                .map(|s| {
+                    if s.is_empty() {
+                        Scalar::Null
+                    } else {
+                        Scalar::Str(s.to_string())
+                    }
+                })
+                .collect(),
+        }],
+    };
+    // Outside a plan every call is the last block.
+    let out = op
+        .eval_block(
+            std::slice::from_ref(&batch),
+            &MemoryBudgetImpl::new(1 << 20),
+        )
+        .unwrap();
+    let names: Vec<&str> = out.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["k", "count", "count_error"]);
+    assert_eq!(
+        out.columns[0].values,
+        [Scalar::Str("a".into()), Scalar::Str("b".into())]
+    );
+    assert_eq!(out.columns[1].values, [Scalar::I64(3), Scalar::I64(2)]);
+
+    // A second call merges into the same sketch; `open` starts over.
+    let out = op
+        .eval_block(
+            std::slice::from_ref(&batch),
+            &MemoryBudgetImpl::new(1 << 20),
+        )
+        .unwrap();
+    assert_eq!(out.columns[1].values, [Scalar::I64(6), Scalar::I64(4)]);
+    op.open().unwrap();
+    let out = op
+        .eval_block(&[batch], &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    assert_eq!(out.columns[1].values, [Scalar::I64(3), Scalar::I64(2)]);
+    assert!(op
+        .eval_block(
+            &[RowBatch { columns: vec![] }],
+            &MemoryBudgetImpl::new(1 << 20)
+        )
+        .is_err());
+}
+
+#[test]
+fn test_top_k_pipeline_reports_skew_across_blocks() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let input = format!("{}/events.csv", dir);
+    let output = format!("{}/top.csv", dir);
+    let values = skewed(50, 120);
+    let csv: String = std::iter::once("user,amount\n".to_string())
+        .chain(values.iter().map(|v| format!("u{},1\n", v)))
+        .collect();
+    fs::write(&input, csv).unwrap();
+
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{input}"
+    schema:
+      - {{ name: "user", type: "Utf8" }}
+      - {{ name: "amount", type: "Int64" }}
+  - op: top_k
+    column: "user"
+    k: 3
+  - op: sink
+    destination: "{output}"
+    format: "csv"
+"#
+    );
+    let plan = parse_yaml_pipeline(&yaml).unwrap().plan;
+    let names: Vec<String> = column_lineage(&plan)[0]
+        .columns
+        .iter()
+        .map(|c| format!("{}{}", c.column, if c.derived { "*" } else { "" }))
+        .collect();
+    assert_eq!(names, ["user", "count*", "count_error*"]);
+
+    let program = lower_to_physical(&plan);
+    let hint = WorkHint {
+        source_rows: vec![(input.clone(), values.len() as u64)],
+        source_bytes: vec![],
+        ..Default::default()
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &estimate_work(&plan, Some(&hint)),
+        BlockSizeHint { rows_per_block: 2 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config).unwrap().run(&program, &te).unwrap();
+    assert_eq!(
+        fs::read_to_string(&output).unwrap(),
+        "user,count,count_error\nu0,120,0\nu1,60,0\nu2,40,0\n"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
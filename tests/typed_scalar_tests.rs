@@ -0,0 +1,270 @@
+//! Date64, Timestamp and Decimal128 values: parsing, expressions and spills
+
+mod test_data_gen;
+
+use std::fs;
+
+use emsqrt_core::config::EngineConfig;
+use emsqrt_core::decimal::{format_decimal, parse_decimal};
+use emsqrt_core::expr::Expr;
+use emsqrt_core::id::SpillId;
+use emsqrt_core::schema::{DataType, Field};
+use emsqrt_core::temporal::{format_timestamp, parse_date, parse_timestamp};
+use emsqrt_core::types::{Column, RowBatch, Scalar};
+use emsqrt_exec::Engine;
+use emsqrt_io::readers::jsonl::JsonlReader;
+use emsqrt_io::storage::FsStorage;
+use emsqrt_mem::{Codec, MemoryBudgetImpl, SpillManager};
+use emsqrt_planner::{lower_to_physical, parse_yaml_pipeline, rules};
+use emsqrt_te::{plan_te_with_block_size, BlockSizeHint, WorkEstimate};
+use test_data_gen::create_temp_spill_dir;
+
+fn eval(expr: &str, batch: &RowBatch) -> Result<Scalar, String> {
+    Expr::parse(expr)?.evaluate(batch, 0)
+}
+
+fn row(values: Vec<(&str, Scalar)>) -> RowBatch {
+    RowBatch {
+        columns: values
+            .into_iter()
+            .map(|(name, value)| Column {
+                name: name.to_string(),
+                values: vec![value],
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_parse_and_format() {
+    assert_eq!(parse_date("2024-02-29"), Some(19_782 * 86_400_000));
+    assert_eq!(parse_date("1969-12-31"), Some(-86_400_000));
+    assert_eq!(parse_date("2023-02-29"), None);
+
+    let ts = parse_timestamp("2024-03-01T12:30:00.25Z").unwrap();
+    assert_eq!(format_timestamp(ts), "2024-03-01T12:30:00.250");
+    assert_eq!(parse_timestamp("2024-03-01 14:30:00.25+02:00"), Some(ts));
+    assert_eq!(parse_timestamp("2024-03-01"), Some(ts - 45_000_250_000));
+    assert_eq!(
+        format_timestamp(parse_timestamp("1969-12-31T23:59:59.000001").unwrap()),
+        "1969-12-31T23:59:59.000001"
+    );
+    assert_eq!(parse_timestamp("2024-03-01T24:00"), None);
+
+    assert_eq!(parse_decimal("-12.50"), Some((-1250, 2)));
+    assert_eq!(parse_decimal(".5"), Some((5, 1)));
+    assert_eq!(parse_decimal("1e3"), None);
+    assert_eq!(format_decimal(-5, 3), "-0.005");
+    assert_eq!(format_decimal(1250, 2), "12.50");
+}
+
+#[test]
+fn test_expressions_over_typed_values() {
+    let day = parse_date("2024-02-28").unwrap();
+    let ts = parse_timestamp("2024-02-28T08:00:00").unwrap();
+    let batch = row(vec![
+        ("day", Scalar::Date64(day)),
+        ("start", Scalar::Date64(parse_date("2023-02-28").unwrap())),
+        ("ts", Scalar::Timestamp(ts)),
+        ("price", Scalar::Decimal128(1999, 2)),
+        ("qty", Scalar::I64(3)),
+    ]);
+
+    // Dates shift by days across the leap day, and compare with text,
+    // each other and timestamps.
+    assert_eq!(eval("(day + 2)", &batch).unwrap().to_string(), "2024-03-01");
+    assert_eq!(eval("day - start", &batch).unwrap(), Scalar::I64(365));
+    assert_eq!(
+        eval("day == date '2024-02-28'", &batch).unwrap(),
+        Scalar::Bool(true)
+    );
+    assert_eq!(eval("ts > day", &batch).unwrap(), Scalar::Bool(true));
+    assert_eq!(
+        eval("ts < '2024-02-28T08:00:00.001'", &batch).unwrap(),
+        Scalar::Bool(true)
+    );
+    assert_eq!(
+        eval("ts - day", &batch).unwrap(),
+        Scalar::I64(8 * 3_600_000_000)
+    );
+
+    // Decimals stay exact with integers and other decimals.
+    assert_eq!(eval("price * qty", &batch).unwrap().to_string(), "59.97");
+    assert_eq!(
+        eval("price * qty == 59.97", &batch).unwrap(),
+        Scalar::Bool(true)
+    );
+    let sum = Expr::parse("price + qty")
+        .unwrap()
+        .evaluate(
+            &row(vec![
+                ("price", Scalar::Decimal128(1, 1)),
+                ("qty", Scalar::Decimal128(2, 1)),
+            ]),
+            0,
+        )
+        .unwrap();
+    assert_eq!(sum.to_string(), "0.3");
+    assert_eq!(eval("price / 3", &batch).unwrap().to_string(), "6.663333");
+    assert_eq!(eval("price > 19", &batch).unwrap(), Scalar::Bool(true));
+    assert!(eval("price / 0", &batch)
+        .unwrap_err()
+        .contains("division by zero"));
+    let big = row(vec![("x", Scalar::Decimal128(i128::MAX / 2, 0))]);
+    assert!(eval("x * 3", &big).unwrap_err().contains("overflow"));
+}
+
+/// Run `scan(in.csv, schema) → filter → sink(out.csv)`.
+fn run_csv(dir: &str, csv: &str, predicate: &str, on_parse_error: &str) -> Result<String, String> {
+    fs::write(format!("{}/in.csv", dir), csv).unwrap();
+    let yaml = format!(
+        r#"
+steps:
+  - op: scan
+    source: "{dir}/in.csv"
+    on_parse_error: {on_parse_error}
+    schema:
+      - {{ name: "day", type: "date" }}
+      - {{ name: "ts", type: "Timestamp" }}
+      - {{ name: "amount", type: "decimal" }}
+  - {{ op: filter, expr: "{predicate}" }}
+  - op: sink
+    destination: "{dir}/out.csv"
+    format: csv
+"#
+    );
+    let plan = rules::optimize(parse_yaml_pipeline(&yaml).map_err(|e| e.to_string())?.plan);
+    let program = lower_to_physical(&plan);
+    let work = WorkEstimate {
+        total_rows: 4,
+        total_bytes: 4,
+        max_fan_in: 1,
+    };
+    let te = plan_te_with_block_size(
+        &program.plan,
+        &work,
+        BlockSizeHint { rows_per_block: 4 },
+        &program.join_keys(),
+    )
+    .unwrap();
+    let config = EngineConfig {
+        spill_dir: format!("{}/spill", dir),
+        ..Default::default()
+    };
+    Engine::new(config)
+        .unwrap()
+        .run(&program, &te)
+        .map_err(|e| e.to_string())?;
+    Ok(fs::read_to_string(format!("{}/out.csv", dir)).unwrap())
+}
+
+#[test]
+fn test_csv_and_jsonl_scans_read_typed_values() {
+    let dir = create_temp_spill_dir();
+    fs::create_dir_all(&dir).unwrap();
+    let csv = "day,ts,amount\n\
+               2024-3-1,2024-03-01T10:00:00Z,100.10\n\
+               2024-02-29,2024-03-01 09:00:00-02:00,-0.5\n\
+               2024-03-02,2024-03-02T00:00:00,99.999\n";
+    let out = run_csv(
+        &dir,
+        csv,
+        "amount > 99.99 AND ts >= date '2024-03-01'",
+        "fail",
+    )
+    .unwrap();
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        [
+            "day,ts,amount",
+            "2024-03-01,2024-03-01T10:00:00,100.10",
+            "2024-03-02,2024-03-02T00:00:00,99.999",
+        ]
+    );
+    let out = run_csv(&dir, csv, "ts == '2024-03-01T11:00:00'", "fail").unwrap();
+    assert_eq!(
+        out.lines().nth(1),
+        Some("2024-02-29,2024-03-01T11:00:00,-0.5")
+    );
+
+    let bad = "day,ts,amount\n2024-02-30,2024-03-01,1\n";
+    let err = run_csv(&dir, bad, "amount > 0", "fail").unwrap_err();
+    assert!(err.contains("is not a valid Date64"), "{}", err);
+    let out = run_csv(&dir, bad, "amount > 0", "null").unwrap();
+    assert_eq!(out.lines().nth(1), Some(",2024-03-01T00:00:00,1"));
+
+    // JSONL: ISO text, and decimals from text or numbers.
+    let jsonl = concat!(
+        r#"{"day": "2024-03-01", "ts": "2024-03-01T00:00:01.5Z", "amount": "1.10"}"#,
+        "\n",
+        r#"{"day": 20240301, "ts": "soon", "amount": 2.5}"#,
+        "\n"
+    );
+    let mut reader = JsonlReader::from_reader(jsonl.as_bytes())
+        .unwrap()
+        .with_types(&[
+            Field::new("day", DataType::Date64, true),
+            Field::new("ts", DataType::Timestamp, true),
+            Field::new("amount", DataType::Decimal128, true),
+        ]);
+    let batch = reader.next_batch(10).unwrap().unwrap();
+    let values = |name: &str| {
+        let column = batch.columns.iter().find(|c| c.name == name).unwrap();
+        column.values.clone()
+    };
+    let day = parse_date("2024-03-01").unwrap();
+    assert_eq!(values("day"), [Scalar::Date64(day), Scalar::I64(20240301)]);
+    assert_eq!(
+        values("ts"),
+        [
+            Scalar::Timestamp(day * 1000 + 1_500_000),
+            Scalar::Str("soon".into())
+        ]
+    );
+    assert_eq!(
+        values("amount"),
+        [Scalar::Decimal128(110, 2), Scalar::Decimal128(25, 1)]
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_typed_values_survive_a_spill() {
+    let dir = create_temp_spill_dir();
+    let mut mgr = SpillManager::new(
+        Box::new(FsStorage::new()),
+        Codec::None,
+        format!("{}/spills", dir),
+    );
+    // Past i64 and u64, which JSON numbers are often limited to.
+    let huge = -123_456_789_012_345_678_901_234_567_890_i128;
+    let batch = RowBatch {
+        columns: vec![
+            Column {
+                name: "amount".into(),
+                values: vec![
+                    Scalar::Decimal128(huge, 4),
+                    Scalar::Decimal128(i128::MAX, 38),
+                ],
+            },
+            Column {
+                name: "at".into(),
+                values: vec![Scalar::Date64(-86_400_000), Scalar::Timestamp(i64::MIN)],
+            },
+        ],
+    };
+    let meta = mgr.write_batch(&batch, SpillId::new(1), 0).unwrap();
+    let back = mgr
+        .read_batch(&meta, &MemoryBudgetImpl::new(1 << 20))
+        .unwrap();
+    for (back, column) in back.columns.iter().zip(&batch.columns) {
+        assert_eq!(back.values, column.values);
+    }
+    assert_eq!(
+        back.columns[0].values[0].to_string(),
+        "-12345678901234567890123456.7890"
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
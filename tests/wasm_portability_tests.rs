@@ -0,0 +1,54 @@
+//! emsqrt-core, emsqrt-te and emsqrt-planner stay free of APIs that compile
+//! on wasm32-unknown-unknown but panic there. That the crates build for the
+//! target is checked by `cargo check --target wasm32-unknown-unknown` in
+//! scripts/run_all_tests.sh; this scan covers what the build cannot catch.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// APIs that compile on wasm32-unknown-unknown but fail or panic when called.
+const FORBIDDEN: &[&str] = &[
+    "std::fs",
+    "std::net",
+    "std::process",
+    "std::thread",
+    "Instant::now",
+    "SystemTime::now",
+];
+
+const PORTABLE_CRATES: &[&str] = &["emsqrt-core", "emsqrt-te", "emsqrt-planner"];
+
+fn rust_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, out);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            out.push(path);
+        }
+    }
+}
+
+#[test]
+fn test_portable_crates_avoid_host_only_apis() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("crates");
+    let mut violations = Vec::new();
+    for krate in PORTABLE_CRATES {
+        let mut files = Vec::new();
+        rust_files(&root.join(krate).join("src"), &mut files);
+        for file in files {
+            let source = fs::read_to_string(&file).unwrap();
+            for (n, line) in source.lines().enumerate() {
+                if line.trim_start().starts_with("//") {
+                    continue;
+                }
+                for api in FORBIDDEN {
+                    if line.contains(api) {
+                        violations.push(format!("{}:{}: {}", file.display(), n + 1, api));
+                    }
+                }
+            }
+        }
+    }
+    assert!(violations.is_empty(), "{}", violations.join("\n"));
+}
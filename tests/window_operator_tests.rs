@@ -61,10 +61,14 @@ fn test_window_row_number_and_sum() {
                 alias: "sum_value".into(),
             },
         ],
+        ..Default::default()
     };
 
     let result = window
-        .eval_block(&[row_batch.clone()], &MemoryBudgetImpl::new(1024))
+        .eval_block(
+            std::slice::from_ref(&row_batch),
+            &MemoryBudgetImpl::new(1024),
+        )
         .expect("window execution");
 
     assert_eq!(result.columns.len(), 5);